@@ -107,6 +107,16 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = AwsConverseOutput;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            vision: true,
+            ..Default::default()
+        }
+    }
+
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
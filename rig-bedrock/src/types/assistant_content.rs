@@ -55,12 +55,14 @@ impl TryFrom<AwsConverseOutput> for completion::CompletionResponse<AwsConverseOu
                     },
                 })),
                 raw_response: value,
+                logprobs: None,
             });
         }
 
         Ok(completion::CompletionResponse {
             choice,
             raw_response: value,
+            logprobs: None,
         })
     }
 }
@@ -5,7 +5,7 @@ use aws_sdk_bedrockruntime::types::{
     InferenceConfiguration, SystemContentBlock, Tool, ToolConfiguration, ToolInputSchema,
     ToolSpecification,
 };
-use rig::completion::{CompletionError, Message};
+use rig::completion::{CompletionError, Message, SamplingParamNames, SamplingParams};
 use rig::message::{ContentFormat, DocumentMediaType, UserContent};
 use rig::OneOrMany;
 
@@ -33,6 +33,19 @@ impl AwsCompletionRequest {
                 inference_configuration.set_max_tokens(Some(*max_tokens as i32));
         }
 
+        if let Some(stop) = &self.0.sampling.stop {
+            inference_configuration =
+                inference_configuration.set_stop_sequences(Some(stop.clone()));
+        }
+
+        // The other sampling parameters depend on the model: they are ignored with a warning and
+        // can be set with the additional params instead
+        SamplingParams {
+            stop: None,
+            ..self.0.sampling.clone()
+        }
+        .to_json("Bedrock", &SamplingParamNames::NONE);
+
         Some(inference_configuration.build())
     }
 
@@ -0,0 +1,360 @@
+//! Interactive chat REPL to try an agent configuration without writing a host app.
+//!
+//! Usage:
+//! ```sh
+//! cargo run --example rig_cli -- [--provider openai|anthropic] [--config agent.json] [--session session.json]
+//! ```
+//!
+//! The config is an [AgentConfig] in JSON, e.g.:
+//! ```json
+//! {"model": "gpt-4o", "preamble": "You are a calculator.", "temperature": 0.2, "tools": ["add", "subtract"]}
+//! ```
+//!
+//! The responses are streamed, the tool calls of the agent (among the demo tools `add` and
+//! `subtract`) are displayed and run, and the session (i.e.: the chat history) can be saved and
+//! loaded with the `/save` and `/load` commands.
+
+use std::io::{self, BufRead, Write};
+
+use futures::StreamExt;
+use rig::{
+    agent::{Agent, AgentBuilder, AgentConfig},
+    completion::{Message, ToolDefinition},
+    message::{AssistantContent, UserContent},
+    providers::{anthropic, openai},
+    streaming::{StreamingChat, StreamingCompletionModel},
+    tool::Tool,
+    OneOrMany,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+const USAGE: &str =
+    "Usage: rig_cli [--provider openai|anthropic] [--config agent.json] [--session session.json]";
+
+const HELP: &str = "\
+Commands:
+  /save [path]  Save the session (to the --session file by default)
+  /load [path]  Load a session
+  /history      Print the session
+  /clear        Clear the session
+  /help         Print this help
+  /exit         Quit";
+
+/// Maximum number of rounds of tool calls per prompt
+const MAX_TOOL_ROUNDS: usize = 5;
+
+#[derive(Deserialize)]
+struct OperationArgs {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Math error")]
+struct MathError;
+
+struct Adder;
+
+impl Tool for Adder {
+    const NAME: &'static str = "add";
+
+    type Error = MathError;
+    type Args = OperationArgs;
+    type Output = i32;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Add x and y together".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "x": {"type": "number", "description": "The first number to add"},
+                    "y": {"type": "number", "description": "The second number to add"}
+                },
+                "required": ["x", "y"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(args.x + args.y)
+    }
+}
+
+struct Subtract;
+
+impl Tool for Subtract {
+    const NAME: &'static str = "subtract";
+
+    type Error = MathError;
+    type Args = OperationArgs;
+    type Output = i32;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Subtract y from x (i.e.: x - y)".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "x": {"type": "number", "description": "The number to subtract from"},
+                    "y": {"type": "number", "description": "The number to subtract"}
+                },
+                "required": ["x", "y"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(args.x - args.y)
+    }
+}
+
+struct Args {
+    provider: String,
+    config: AgentConfig,
+    session: Option<String>,
+}
+
+fn parse_args() -> Result<Args, anyhow::Error> {
+    let mut provider = "openai".to_string();
+    let mut config = None;
+    let mut session = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("Missing value of {arg}"))
+        };
+        match arg.as_str() {
+            "--provider" => provider = value()?,
+            "--config" => {
+                let json = std::fs::read_to_string(value()?)?;
+                config = Some(AgentConfig::from_json(&json)?);
+            }
+            "--session" => session = Some(value()?),
+            _ => anyhow::bail!("{USAGE}"),
+        }
+    }
+
+    let config = config.unwrap_or_else(|| AgentConfig {
+        model: match provider.as_str() {
+            "anthropic" => anthropic::CLAUDE_3_5_SONNET.to_string(),
+            _ => openai::GPT_4O.to_string(),
+        },
+        preamble: Some("You are a helpful assistant.".to_string()),
+        ..Default::default()
+    });
+
+    Ok(Args {
+        provider,
+        config,
+        session,
+    })
+}
+
+/// Apply the config (except its model, and its dynamic context which needs vector stores) to
+/// the agent builder.
+fn configure<M: StreamingCompletionModel>(
+    mut builder: AgentBuilder<M>,
+    config: &AgentConfig,
+) -> Result<Agent<M>, anyhow::Error> {
+    if let Some(preamble) = &config.preamble {
+        builder = builder.preamble(preamble);
+    }
+    for doc in &config.context {
+        builder = builder.context(doc);
+    }
+    for tool in &config.tools {
+        builder = match tool.as_str() {
+            Adder::NAME => builder.tool(Adder),
+            Subtract::NAME => builder.tool(Subtract),
+            _ => anyhow::bail!("Unknown tool `{tool}` (available tools: add, subtract)"),
+        };
+    }
+    if !config.dynamic_context.is_empty() {
+        println!("(The dynamic context of the config is ignored)");
+    }
+    if let Some(temperature) = config.temperature {
+        builder = builder.temperature(temperature);
+    }
+    if let Some(max_tokens) = config.max_tokens {
+        builder = builder.max_tokens(max_tokens);
+    }
+    if let Some(params) = &config.additional_params {
+        builder = builder.additional_params(params.clone());
+    }
+    Ok(builder.sampling(config.sampling.clone()).build())
+}
+
+/// Stream the answer of the agent to `prompt`, running its tool calls, and add the exchanged
+/// messages to the session.
+async fn answer<M: StreamingCompletionModel>(
+    agent: &Agent<M>,
+    prompt: Message,
+    session: &mut Vec<Message>,
+) -> Result<(), anyhow::Error> {
+    let mut history = session.clone();
+    let mut prompt = prompt;
+
+    for _ in 0..MAX_TOOL_ROUNDS {
+        let mut stream = agent.stream_chat(prompt.clone(), history.clone()).await?;
+        let mut tool_results = vec![];
+
+        while let Some(chunk) = stream.next().await {
+            match chunk? {
+                AssistantContent::Text(text) => {
+                    print!("{}", text.text);
+                    io::stdout().flush()?;
+                }
+                AssistantContent::ToolCall(tool_call) => {
+                    let name = &tool_call.function.name;
+                    let args = tool_call.function.arguments.to_string();
+                    println!("\n  ⚙ {name}({args})");
+                    let output = match agent.tools.call(name, args).await {
+                        Ok(output) => output,
+                        Err(err) => format!("Error: {err}"),
+                    };
+                    println!("  ↳ {output}");
+                    tool_results.push(UserContent::tool_result(
+                        tool_call.id,
+                        OneOrMany::one(output.into()),
+                    ));
+                }
+            }
+        }
+
+        history.push(prompt);
+        history.push(stream.choice.into());
+
+        match OneOrMany::many(tool_results) {
+            Ok(content) => prompt = Message::User { content },
+            Err(_) => {
+                println!("\n");
+                *session = history;
+                return Ok(());
+            }
+        }
+    }
+
+    println!("\n(Stopped after {MAX_TOOL_ROUNDS} rounds of tool calls)\n");
+    *session = history;
+    Ok(())
+}
+
+fn print_session(session: &[Message]) {
+    for message in session {
+        match message {
+            Message::User { content } => content.iter().for_each(|content| match content {
+                UserContent::Text(text) => println!("you> {}", text.text),
+                UserContent::ToolResult(result) => println!("  ↳ (tool result {})", result.id),
+                _ => println!("you> (attachment)"),
+            }),
+            Message::Assistant { content } => content.iter().for_each(|content| match content {
+                AssistantContent::Text(text) => println!("agent> {}", text.text),
+                AssistantContent::ToolCall(call) => {
+                    println!("  ⚙ {}({})", call.function.name, call.function.arguments)
+                }
+            }),
+        }
+    }
+}
+
+async fn repl<M: StreamingCompletionModel>(
+    agent: Agent<M>,
+    session_path: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let mut session: Vec<Message> = match &session_path {
+        Some(path) if std::path::Path::new(path).exists() => {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        }
+        _ => vec![],
+    };
+
+    println!("Welcome to the rig CLI! Type /help for the commands.");
+    if !session.is_empty() {
+        println!("(Loaded {} messages)", session.len());
+    }
+
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("you> ");
+        io::stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+
+        let (command, arg) = input.split_once(' ').unwrap_or((input, ""));
+        let path = (!arg.is_empty())
+            .then(|| arg.trim().to_string())
+            .or(session_path.clone());
+
+        match command {
+            "/exit" | "/quit" => break,
+            "/help" => println!("{HELP}"),
+            "/clear" => session.clear(),
+            "/history" => print_session(&session),
+            "/save" => match path {
+                Some(path) => {
+                    std::fs::write(&path, serde_json::to_string_pretty(&session)?)?;
+                    println!("(Saved {} messages to {path})", session.len());
+                }
+                None => println!("Usage: /save <path>"),
+            },
+            "/load" => match path {
+                Some(path) => match std::fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|json| Ok(serde_json::from_str(&json)?))
+                {
+                    Ok(loaded) => {
+                        session = loaded;
+                        println!("(Loaded {} messages from {path})", session.len());
+                    }
+                    Err(err) => println!("Error loading {path}: {err}"),
+                },
+                None => println!("Usage: /load <path>"),
+            },
+            _ if command.starts_with('/') => println!("Unknown command {command}\n{HELP}"),
+            _ => {
+                print!("agent> ");
+                if let Err(err) = answer(&agent, Message::user(input), &mut session).await {
+                    println!("\nError: {err}\n");
+                }
+            }
+        }
+    }
+
+    if let Some(path) = session_path {
+        std::fs::write(&path, serde_json::to_string_pretty(&session)?)?;
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let args = parse_args()?;
+
+    match args.provider.as_str() {
+        "openai" => {
+            let client = openai::Client::from_env();
+            let agent = configure(client.agent(&args.config.model), &args.config)?;
+            repl(agent, args.session).await
+        }
+        "anthropic" => {
+            let client = anthropic::Client::from_env();
+            let agent = configure(client.agent(&args.config.model), &args.config)?;
+            repl(agent, args.session).await
+        }
+        provider => anyhow::bail!("Unsupported provider `{provider}` (openai or anthropic)"),
+    }
+}
@@ -14,8 +14,10 @@ use syn::{
 mod basic;
 mod custom;
 mod embed;
+mod schema;
 
 pub(crate) const EMBED: &str = "embed";
+pub(crate) const RIG: &str = "rig";
 
 /// References:
 /// <https://doc.rust-lang.org/book/ch19-06-macros.html#how-to-write-a-custom-derive-macro>
@@ -29,6 +31,37 @@ pub fn derive_embedding_trait(item: TokenStream) -> TokenStream {
         .into()
 }
 
+/// A procedural macro that turns the `#[rig(desc = "...", example = "...")]` attributes of the
+/// fields of a struct or enum into descriptions and examples of its derived JSON schema (see
+/// `schemars::JsonSchema`), e.g.: for the extractors and the tools. The descriptions are also
+/// allowed on the struct or enum itself, and on the variants of an enum.
+///
+/// The examples which are valid JSON (e.g.: `"42"` or `"[1, 2]"`) are kept as such in the
+/// schema, the others are strings. The macro must be placed before the `JsonSchema` derive.
+///
+/// # Examples
+/// ```rust
+/// use rig_derive::rig_schema;
+///
+/// #[rig_schema]
+/// #[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+/// #[rig(desc = "A person mentioned in the text")]
+/// struct Person {
+///     #[rig(desc = "The full name of the person", example = "Jane Doe")]
+///     name: String,
+///     #[rig(desc = "The age of the person, in years", example = "42")]
+///     age: Option<u8>,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn rig_schema(_args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut item = parse_macro_input!(item as syn::Item);
+
+    schema::expand_rig_schema(&mut item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 struct MacroArgs {
     description: Option<String>,
     param_descriptions: HashMap<String, String>,
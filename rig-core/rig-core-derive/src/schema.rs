@@ -0,0 +1,124 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse_quote, Attribute, Fields, Ident, LitStr};
+
+use crate::RIG;
+
+const DESC: &str = "desc";
+const EXAMPLE: &str = "example";
+
+/// Arguments of a `#[rig(desc = "...", example = "...")]` attribute.
+#[derive(Default)]
+struct SchemaAnnotation {
+    desc: Option<LitStr>,
+    example: Option<LitStr>,
+}
+
+pub(crate) fn expand_rig_schema(item: &mut syn::Item) -> syn::Result<TokenStream> {
+    let mut examples = vec![];
+    match item {
+        syn::Item::Struct(item) => {
+            annotate(&mut item.attrs, None, &mut examples)?;
+            annotate_fields(&item.ident.to_string(), &mut item.fields, &mut examples)?;
+        }
+        syn::Item::Enum(item) => {
+            annotate(&mut item.attrs, None, &mut examples)?;
+            for variant in &mut item.variants {
+                annotate(&mut variant.attrs, None, &mut examples)?;
+                annotate_fields(
+                    &format!("{}_{}", item.ident, variant.ident),
+                    &mut variant.fields,
+                    &mut examples,
+                )?;
+            }
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                item,
+                "rig_schema macro should only be used on structs and enums",
+            ))
+        }
+    }
+
+    Ok(quote! {
+        #item
+
+        #(#examples)*
+    })
+}
+
+fn annotate_fields(
+    prefix: &str,
+    fields: &mut Fields,
+    examples: &mut Vec<TokenStream>,
+) -> syn::Result<()> {
+    for (index, field) in fields.iter_mut().enumerate() {
+        let field_name = field
+            .ident
+            .as_ref()
+            .map_or_else(|| index.to_string(), Ident::to_string);
+        let example_fn = format_ident!("__rig_example_{}_{}", prefix, field_name);
+        annotate(&mut field.attrs, Some(example_fn), examples)?;
+    }
+    Ok(())
+}
+
+/// Replace the `#[rig(...)]` attributes of `attrs` by the equivalent `#[schemars(...)]`
+/// attributes. The examples are returned by the function `example_fn` (pushed to `examples`),
+/// and are only allowed if it is set (i.e.: on the fields).
+fn annotate(
+    attrs: &mut Vec<Attribute>,
+    example_fn: Option<Ident>,
+    examples: &mut Vec<TokenStream>,
+) -> syn::Result<()> {
+    let mut annotation = SchemaAnnotation::default();
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path().is_ident(RIG) {
+            return true;
+        }
+        if let Err(err) = parse_annotation(attr, &mut annotation) {
+            result = Err(err);
+        }
+        false
+    });
+    result?;
+
+    if let Some(desc) = annotation.desc {
+        attrs.push(parse_quote!(#[schemars(description = #desc)]));
+    }
+    if let Some(example) = annotation.example {
+        let Some(example_fn) = example_fn else {
+            return Err(syn::Error::new_spanned(
+                example,
+                "Examples are only allowed on fields",
+            ));
+        };
+        let example_fn_name = LitStr::new(&example_fn.to_string(), Span::call_site());
+        attrs.push(parse_quote!(#[schemars(example = #example_fn_name)]));
+        // The examples which are valid JSON (e.g.: numbers or lists) are kept as such
+        examples.push(quote! {
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            fn #example_fn() -> serde_json::Value {
+                serde_json::from_str(#example)
+                    .unwrap_or_else(|_| serde_json::Value::String(#example.to_string()))
+            }
+        });
+    }
+    Ok(())
+}
+
+fn parse_annotation(attr: &Attribute, annotation: &mut SchemaAnnotation) -> syn::Result<()> {
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident(DESC) {
+            annotation.desc = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident(EXAMPLE) {
+            annotation.example = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `desc` or `example`"))
+        }
+    })
+}
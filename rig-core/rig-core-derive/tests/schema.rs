@@ -0,0 +1,49 @@
+use rig::extractor::schema::schema_for;
+use rig_derive::rig_schema;
+use serde_json::json;
+
+#[rig_schema]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[rig(desc = "A person mentioned in the text")]
+#[allow(dead_code)]
+struct Person {
+    #[rig(desc = "The full name of the person", example = "Jane Doe")]
+    name: String,
+    #[rig(desc = "The age of the person, in years", example = "42")]
+    age: Option<u8>,
+    contact: Contact,
+}
+
+#[rig_schema]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[allow(dead_code)]
+enum Contact {
+    #[rig(desc = "No known contact")]
+    Unknown,
+    Email {
+        #[rig(example = "jane@example.com")]
+        address: String,
+    },
+}
+
+#[test]
+fn test_rig_schema() {
+    let schema = schema_for::<Person>();
+    assert_eq!(schema["description"], "A person mentioned in the text");
+    assert_eq!(
+        schema["properties"]["name"],
+        json!({
+            "description": "The full name of the person",
+            "examples": ["Jane Doe"],
+            "type": "string",
+        })
+    );
+    assert_eq!(schema["properties"]["age"]["examples"], json!([42]));
+
+    let variants = schema["properties"]["contact"]["oneOf"].as_array().unwrap();
+    assert_eq!(variants[0]["description"], "No known contact");
+    assert_eq!(
+        variants[1]["properties"]["Email"]["properties"]["address"]["examples"],
+        json!(["jane@example.com"])
+    );
+}
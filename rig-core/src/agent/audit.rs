@@ -0,0 +1,401 @@
+use std::{
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    message::ToolCall,
+    tool::ToolSetError,
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[cfg(feature = "sql")]
+    #[error("DatabaseError: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("InvalidTable: {0}")]
+    InvalidTable(String),
+}
+
+/// Outcome of an audited tool call.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ToolCallStatus {
+    Success,
+    Error { message: String },
+}
+
+/// Record of a tool call of an agent, received by the [AuditSink]s of the agent.
+///
+/// The arguments of the call are only recorded as a hash, so that the audit log does not
+/// contain the (possibly sensitive) data exchanged with the tools.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToolAuditRecord {
+    /// Time of the end of the call, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Name of the agent (see [AgentBuilder::name](super::AgentBuilder::name))
+    pub agent: Option<String>,
+    /// Identity of the caller of the prompt (see
+    /// [PromptRequest::caller](super::PromptRequest::caller))
+    pub caller: Option<String>,
+    pub tool: String,
+    /// Id of the tool call given by the model
+    pub call_id: String,
+    /// SHA-256 of the arguments of the call (hexadecimal)
+    pub args_hash: String,
+    #[serde(flatten)]
+    pub status: ToolCallStatus,
+    pub duration_ms: u64,
+}
+
+impl ToolAuditRecord {
+    /// The hash of the arguments `args` of a tool call, as recorded in
+    /// [ToolAuditRecord::args_hash].
+    pub fn hash_args(args: &str) -> String {
+        format!("{:x}", Sha256::digest(args.as_bytes()))
+    }
+}
+
+/// Destination of the audit records of the tool calls of an agent (e.g.: a file or a database
+/// table), added with [AgentBuilder::audit_sink](super::AgentBuilder::audit_sink).
+///
+/// The failures of the sinks are logged and do not fail the prompts.
+pub trait AuditSink: WasmCompatSend + WasmCompatSync {
+    fn record<'a>(
+        &'a self,
+        record: &'a ToolAuditRecord,
+    ) -> WasmBoxedFuture<'a, Result<(), AuditError>>;
+}
+
+/// Sink appending the audit records to a file, as JSON lines.
+pub struct FileAuditSink {
+    file: Mutex<std::fs::File>,
+}
+
+impl FileAuditSink {
+    /// Open (or create) the file `path`, appending the records to it.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record<'a>(
+        &'a self,
+        record: &'a ToolAuditRecord,
+    ) -> WasmBoxedFuture<'a, Result<(), AuditError>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_vec(record)?;
+            line.push(b'\n');
+            let mut file = self.file.lock().expect("Audit file lock poisoned");
+            file.write_all(&line)?;
+            file.flush()?;
+            Ok(())
+        })
+    }
+}
+
+/// Sink keeping the audit records in memory (e.g.: for tests).
+#[derive(Clone, Default)]
+pub struct MemoryAuditSink {
+    records: Arc<Mutex<Vec<ToolAuditRecord>>>,
+}
+
+impl MemoryAuditSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<ToolAuditRecord> {
+        self.records
+            .lock()
+            .expect("Audit records lock poisoned")
+            .clone()
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record<'a>(
+        &'a self,
+        record: &'a ToolAuditRecord,
+    ) -> WasmBoxedFuture<'a, Result<(), AuditError>> {
+        Box::pin(async move {
+            self.records
+                .lock()
+                .expect("Audit records lock poisoned")
+                .push(record.clone());
+            Ok(())
+        })
+    }
+}
+
+/// Sink inserting the audit records in a table of a SQL database (feature `sql`, with the
+/// drivers of the features `sql-postgres`, `sql-mysql` and `sql-sqlite`).
+#[cfg(feature = "sql")]
+pub struct SqlAuditSink {
+    pool: sqlx::AnyPool,
+    table: String,
+    insert: String,
+}
+
+#[cfg(feature = "sql")]
+impl SqlAuditSink {
+    /// Create a sink inserting the records in the table `table` (see
+    /// [SqlAuditSink::create_table]).
+    pub fn new(pool: sqlx::AnyPool, table: &str) -> Result<Self, AuditError> {
+        if table.is_empty() || !table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(AuditError::InvalidTable(table.to_string()));
+        }
+        let backend =
+            crate::tools::sql::SqlBackend::from_url(pool.connect_options().database_url.as_str())
+                .map_err(|err| AuditError::InvalidTable(err.to_string()))?;
+        let placeholders = (1..=9)
+            .map(|i| match backend {
+                crate::tools::sql::SqlBackend::Postgres => format!("${i}"),
+                _ => "?".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Ok(Self {
+            insert: format!(
+                "INSERT INTO {table} (timestamp_ms, agent, caller, tool, call_id, args_hash, \
+                status, error, duration_ms) VALUES ({placeholders})"
+            ),
+            table: table.to_string(),
+            pool,
+        })
+    }
+
+    /// Create the table of the records, if it does not exist.
+    pub async fn create_table(&self) -> Result<(), AuditError> {
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                timestamp_ms BIGINT NOT NULL,
+                agent VARCHAR(255),
+                caller VARCHAR(255),
+                tool VARCHAR(255) NOT NULL,
+                call_id VARCHAR(255) NOT NULL,
+                args_hash CHAR(64) NOT NULL,
+                status VARCHAR(16) NOT NULL,
+                error TEXT,
+                duration_ms BIGINT NOT NULL
+            )",
+            self.table
+        ))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sql")]
+impl AuditSink for SqlAuditSink {
+    fn record<'a>(
+        &'a self,
+        record: &'a ToolAuditRecord,
+    ) -> WasmBoxedFuture<'a, Result<(), AuditError>> {
+        Box::pin(async move {
+            let (status, error) = match &record.status {
+                ToolCallStatus::Success => ("success", None),
+                ToolCallStatus::Error { message } => ("error", Some(message.clone())),
+            };
+            sqlx::query(&self.insert)
+                .bind(record.timestamp_ms as i64)
+                .bind(record.agent.clone())
+                .bind(record.caller.clone())
+                .bind(record.tool.clone())
+                .bind(record.call_id.clone())
+                .bind(record.args_hash.clone())
+                .bind(status)
+                .bind(error)
+                .bind(record.duration_ms as i64)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+/// Send the record of `tool_call` to `sinks`, logging their failures.
+pub(super) async fn audit_tool_call(
+    sinks: &[Arc<dyn AuditSink>],
+    agent: Option<&str>,
+    caller: Option<&str>,
+    tool_call: &ToolCall,
+    args: &str,
+    result: &Result<String, ToolSetError>,
+    duration: Duration,
+) {
+    if sinks.is_empty() {
+        return;
+    }
+
+    let record = ToolAuditRecord {
+        timestamp_ms: web_time::SystemTime::now()
+            .duration_since(web_time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        agent: agent.map(str::to_string),
+        caller: caller.map(str::to_string),
+        tool: tool_call.function.name.clone(),
+        call_id: tool_call.id.clone(),
+        args_hash: ToolAuditRecord::hash_args(args),
+        status: match result {
+            Ok(_) => ToolCallStatus::Success,
+            Err(err) => ToolCallStatus::Error {
+                message: err.to_string(),
+            },
+        },
+        duration_ms: duration.as_millis() as u64,
+    };
+    for sink in sinks {
+        if let Err(err) = sink.record(&record).await {
+            tracing::warn!(target: "rig",
+                "Failed to record the audit of the tool call {}: {}", record.call_id, err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse, Prompt,
+        },
+        message::{Message, UserContent},
+        testing::ScriptedTool,
+        OneOrMany,
+    };
+
+    /// Calls the `lookup` tool, then answers "done"
+    #[derive(Clone)]
+    struct LookupModel;
+
+    impl CompletionModel for LookupModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let choice = match request.chat_history.iter().last() {
+                Some(Message::User { content })
+                    if matches!(content.first(), UserContent::ToolResult(_)) =>
+                {
+                    AssistantContent::text("done")
+                }
+                _ => AssistantContent::tool_call(
+                    "call_1",
+                    "lookup",
+                    serde_json::json!({"order": 42}),
+                ),
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_tool_calls() {
+        let sink = MemoryAuditSink::new();
+        let path = std::env::temp_dir().join(format!("rig-audit-{}.jsonl", std::process::id()));
+        let agent = AgentBuilder::new(LookupModel)
+            .name("support")
+            .scripted_tool(ScriptedTool::new("lookup").returns("shipped"))
+            .audit_sink(sink.clone())
+            .audit_sink(FileAuditSink::new(&path).unwrap())
+            .build();
+
+        let response = agent
+            .prompt("Where is my order?")
+            .caller("user-7")
+            .multi_turn(1)
+            .await
+            .unwrap();
+        assert_eq!(response, "done");
+
+        let records = sink.records();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.agent.as_deref(), Some("support"));
+        assert_eq!(record.caller.as_deref(), Some("user-7"));
+        assert_eq!(record.tool, "lookup");
+        assert_eq!(record.call_id, "call_1");
+        assert_eq!(
+            record.args_hash,
+            ToolAuditRecord::hash_args(r#"{"order":42}"#)
+        );
+        assert_eq!(record.status, ToolCallStatus::Success);
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let line = serde_json::from_str::<serde_json::Value>(lines.trim()).unwrap();
+        assert_eq!(line["status"], "success");
+        assert_eq!(line["tool"], "lookup");
+    }
+
+    #[cfg(feature = "sql-sqlite")]
+    #[tokio::test]
+    async fn test_sql_audit_sink() {
+        use sqlx::Row;
+
+        sqlx::any::install_default_drivers();
+        let pool = sqlx::any::AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        assert!(SqlAuditSink::new(pool.clone(), "audit; DROP TABLE x").is_err());
+
+        let sink = SqlAuditSink::new(pool.clone(), "tool_audit").unwrap();
+        sink.create_table().await.unwrap();
+        let record = ToolAuditRecord {
+            timestamp_ms: 1,
+            agent: None,
+            caller: Some("user-7".to_string()),
+            tool: "lookup".to_string(),
+            call_id: "call_1".to_string(),
+            args_hash: ToolAuditRecord::hash_args("{}"),
+            status: ToolCallStatus::Error {
+                message: "not found".to_string(),
+            },
+            duration_ms: 3,
+        };
+        sink.record(&record).await.unwrap();
+
+        let row = sqlx::query("SELECT caller, status, error FROM tool_audit")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<String, _>(0), "user-7");
+        assert_eq!(row.get::<String, _>(1), "error");
+        assert_eq!(row.get::<String, _>(2), "not found");
+    }
+}
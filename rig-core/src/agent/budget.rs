@@ -0,0 +1,393 @@
+use std::sync::Arc;
+
+use crate::{
+    catalog::ModelInfo,
+    completion::{CompletionError, Document, Message, ToolDefinition},
+    tokens::{HeuristicTokenizer, Tokenizer},
+};
+
+/// Section of the context of a request that can be trimmed to fit in a [TokenBudget].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContextSection {
+    /// Documents fetched from the dynamic context (the last ones are dropped first)
+    DynamicContext,
+    /// Static context documents (the last ones are dropped first)
+    StaticContext,
+    /// Chat history (the oldest messages are dropped first)
+    History,
+    /// Tool definitions (the dynamic tools, then the last static tools are dropped first)
+    Tools,
+}
+
+/// Parts of the context of a request dropped to fit in a [TokenBudget] (see
+/// [AgentHooks::on_context_trimmed](super::AgentHooks::on_context_trimmed)).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrimReport {
+    /// Number of documents of the dynamic context dropped
+    pub dynamic_context: usize,
+    /// Number of static context documents dropped
+    pub static_context: usize,
+    /// Number of messages of the chat history dropped
+    pub history: usize,
+    /// Number of tool definitions dropped
+    pub tools: usize,
+    /// Estimated number of input tokens of the request before trimming
+    pub tokens_before: usize,
+    /// Estimated number of input tokens of the request after trimming
+    pub tokens_after: usize,
+}
+
+impl TrimReport {
+    /// Whether any part of the context was dropped.
+    pub fn is_trimmed(&self) -> bool {
+        self.tokens_after < self.tokens_before
+    }
+}
+
+/// Maximum number of input tokens of the requests of an agent.
+///
+/// When a request does not fit in the budget, its context is trimmed section by section in the
+/// trim order (by default: dynamic context, chat history, static context, then tools) until it
+/// fits. The preamble and the prompt are never trimmed, nor are the sections missing from the
+/// trim order: if the request still does not fit, the completion fails before reaching the
+/// provider.
+///
+/// # Example
+/// ```rust
+/// use rig::{agent::{ContextSection, TokenBudget}, tokens::HeuristicTokenizer};
+///
+/// let agent = openai.agent(openai::GPT_4O)
+///     .preamble("You are a helpful assistant.")
+///     .dynamic_context(10, index)
+///     .token_budget(
+///         TokenBudget::new(100_000)
+///             .trim_order([ContextSection::History, ContextSection::DynamicContext])
+///             .tokenizer(HeuristicTokenizer::new(3)),
+///     )
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct TokenBudget {
+    max_input_tokens: usize,
+    trim_order: Vec<ContextSection>,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+impl TokenBudget {
+    /// Create a budget of `max_input_tokens` tokens, counted with a [HeuristicTokenizer] by
+    /// default.
+    pub fn new(max_input_tokens: usize) -> Self {
+        Self {
+            max_input_tokens,
+            trim_order: vec![
+                ContextSection::DynamicContext,
+                ContextSection::History,
+                ContextSection::StaticContext,
+                ContextSection::Tools,
+            ],
+            tokenizer: Arc::new(HeuristicTokenizer::default()),
+        }
+    }
+
+    /// Create the budget of a model of the [catalog](crate::catalog), reserving `max_tokens`
+    /// tokens of its context window for the completion.
+    pub fn for_model(model: &ModelInfo, max_tokens: u64) -> Self {
+        Self::new(model.context_window.saturating_sub(max_tokens) as usize)
+    }
+
+    /// Set the order in which the sections of the context are trimmed. The sections missing from
+    /// the order are never trimmed.
+    pub fn trim_order(mut self, trim_order: impl IntoIterator<Item = ContextSection>) -> Self {
+        self.trim_order = trim_order.into_iter().collect();
+        self
+    }
+
+    /// Set the tokenizer counting the tokens of the requests (e.g.: the tokenizer of the model).
+    pub fn tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    pub fn max_input_tokens(&self) -> usize {
+        self.max_input_tokens
+    }
+
+    /// The budget, with a maximum of `max_input_tokens` tokens.
+    pub(crate) fn with_max_input_tokens(&self, max_input_tokens: usize) -> Self {
+        Self {
+            max_input_tokens,
+            ..self.clone()
+        }
+    }
+
+    fn count_json(&self, value: &impl serde::Serialize) -> usize {
+        serde_json::to_string(value)
+            .map(|json| self.tokenizer.count_tokens(&json))
+            .unwrap_or_default()
+    }
+
+    /// Trim `context` so that the request fits in the budget.
+    pub(crate) fn pack(
+        &self,
+        preamble: &str,
+        prompt: &Message,
+        context: &mut PackedContext,
+    ) -> Result<TrimReport, CompletionError> {
+        let mut history = context
+            .history
+            .iter()
+            .map(|message| self.count_json(message))
+            .collect::<Vec<_>>();
+        let documents = |documents: &[Document]| {
+            documents
+                .iter()
+                .map(|document| self.tokenizer.count_tokens(&document.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let mut static_context = documents(&context.static_context);
+        let mut dynamic_context = documents(&context.dynamic_context);
+        let mut tools = context
+            .tools
+            .iter()
+            .map(|tool| self.count_json(tool))
+            .collect::<Vec<_>>();
+
+        let mut tokens = self.tokenizer.count_tokens(preamble)
+            + self.count_json(prompt)
+            + [&history, &static_context, &dynamic_context, &tools]
+                .iter()
+                .flat_map(|counts| counts.iter())
+                .sum::<usize>();
+        let mut report = TrimReport {
+            tokens_before: tokens,
+            ..Default::default()
+        };
+
+        for section in &self.trim_order {
+            while tokens > self.max_input_tokens {
+                let removed = match section {
+                    ContextSection::DynamicContext => context
+                        .dynamic_context
+                        .pop()
+                        .and_then(|_| dynamic_context.pop()),
+                    ContextSection::StaticContext => context
+                        .static_context
+                        .pop()
+                        .and_then(|_| static_context.pop()),
+                    ContextSection::History if !context.history.is_empty() => {
+                        context.history.remove(0);
+                        Some(history.remove(0))
+                    }
+                    ContextSection::History => None,
+                    ContextSection::Tools => context.tools.pop().and_then(|_| tools.pop()),
+                };
+
+                match removed {
+                    Some(removed) => tokens -= removed,
+                    None => break,
+                }
+                *match section {
+                    ContextSection::DynamicContext => &mut report.dynamic_context,
+                    ContextSection::StaticContext => &mut report.static_context,
+                    ContextSection::History => &mut report.history,
+                    ContextSection::Tools => &mut report.tools,
+                } += 1;
+            }
+        }
+
+        if tokens > self.max_input_tokens {
+            return Err(CompletionError::RequestError(
+                format!(
+                    "Request of {tokens} tokens exceeds the token budget of {} tokens",
+                    self.max_input_tokens
+                )
+                .into(),
+            ));
+        }
+
+        report.tokens_after = tokens;
+        if report.is_trimmed() {
+            tracing::debug!(
+                "Trimmed the context of the request from {} to {tokens} tokens",
+                report.tokens_before
+            );
+        }
+
+        Ok(report)
+    }
+}
+
+/// Trimmable context of a request of an agent.
+pub(crate) struct PackedContext {
+    pub history: Vec<Message>,
+    pub static_context: Vec<Document>,
+    pub dynamic_context: Vec<Document>,
+    pub tools: Vec<ToolDefinition>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::{
+        agent::{AgentBuilder, AgentHooks},
+        completion::{
+            AssistantContent, CompletionModel, CompletionRequest, CompletionResponse, Prompt,
+            PromptError,
+        },
+        OneOrMany,
+    };
+
+    fn document(text: &str) -> Document {
+        Document {
+            id: "doc".to_string(),
+            text: text.to_string(),
+            additional_props: HashMap::new(),
+        }
+    }
+
+    fn context() -> PackedContext {
+        PackedContext {
+            history: vec![
+                Message::user("a".repeat(40)),
+                Message::assistant("b".repeat(40)),
+            ],
+            static_context: vec![document(&"c".repeat(40))],
+            dynamic_context: vec![document(&"d".repeat(40)), document(&"e".repeat(40))],
+            tools: vec![],
+        }
+    }
+
+    #[test]
+    fn test_pack_trim_order() {
+        // 1 token per byte
+        let tokenizer = HeuristicTokenizer::new(1);
+        let prompt = Message::user("prompt");
+        let context = context();
+
+        let fixed = "preamble".len() + TokenBudget::new(0).tokenizer(tokenizer).count_json(&prompt);
+        let message =
+            |message: &Message| TokenBudget::new(0).tokenizer(tokenizer).count_json(message);
+        let document = |document: &Document| document.to_string().len();
+
+        // Room for the last message and the static context
+        let budget = TokenBudget::new(
+            fixed + message(&context.history[1]) + document(&context.static_context[0]),
+        )
+        .tokenizer(tokenizer);
+        let mut packed = self::context();
+        let report = budget.pack("preamble", &prompt, &mut packed).unwrap();
+
+        // The dynamic context is trimmed first, then the oldest messages
+        assert_eq!((report.dynamic_context, report.history), (2, 1));
+        assert_eq!(report.tokens_after, budget.max_input_tokens());
+        assert!(packed.dynamic_context.is_empty());
+        assert_eq!(packed.history, vec![Message::assistant("b".repeat(40))]);
+        assert_eq!(packed.static_context.len(), 1);
+
+        // Room for the history and a dynamic context document
+        let budget = TokenBudget::new(
+            fixed
+                + context.history.iter().map(message).sum::<usize>()
+                + document(&context.dynamic_context[0]),
+        )
+        .tokenizer(tokenizer)
+        .trim_order([
+            ContextSection::StaticContext,
+            ContextSection::DynamicContext,
+        ]);
+        let mut packed = self::context();
+        budget.pack("preamble", &prompt, &mut packed).unwrap();
+
+        assert!(packed.static_context.is_empty());
+        assert_eq!(packed.dynamic_context.len(), 1);
+        assert_eq!(packed.history.len(), 2);
+    }
+
+    /// Rejects the requests with more than 2 messages as too long
+    #[derive(Clone)]
+    struct SmallModel;
+
+    impl CompletionModel for SmallModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            if request.chat_history.len() > 2 {
+                return Err(CompletionError::ProviderError(
+                    "This model's maximum context length is 8192 tokens".to_string(),
+                ));
+            }
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text("ok")),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct Reports(Mutex<Vec<TrimReport>>);
+
+    impl AgentHooks for Arc<Reports> {
+        fn on_context_trimmed(&self, report: &TrimReport) {
+            self.0.lock().unwrap().push(report.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_context_recovery() {
+        let reports = Arc::new(Reports::default());
+        let agent = AgentBuilder::new(SmallModel).hooks(reports.clone()).build();
+        let mut history = (0..8)
+            .map(|i| Message::user(format!("message {i}")))
+            .collect::<Vec<_>>();
+
+        let response = agent
+            .prompt("prompt")
+            .with_history(&mut history)
+            .await
+            .unwrap();
+        assert_eq!(response, "ok");
+        // The history is trimmed to 3 messages, then to a single message
+        let reports = reports.0.lock().unwrap().clone();
+        assert_eq!(
+            reports
+                .iter()
+                .map(|report| report.history)
+                .collect::<Vec<_>>(),
+            vec![5, 7]
+        );
+        assert!(reports[1].tokens_after < reports[1].tokens_before);
+
+        let agent = AgentBuilder::new(SmallModel)
+            .context_recovery_retries(0)
+            .build();
+        let len = history.len();
+        let result = agent.prompt("prompt").with_history(&mut history).await;
+        assert!(matches!(
+            result,
+            Err(PromptError::CompletionError(err)) if err.is_context_length_exceeded()
+        ));
+        // The failed prompt is not added to the history
+        assert_eq!(history.len(), len);
+    }
+
+    #[test]
+    fn test_pack_exceeded() {
+        let budget = TokenBudget::new(100)
+            .tokenizer(HeuristicTokenizer::new(1))
+            .trim_order([ContextSection::DynamicContext]);
+
+        let mut packed = context();
+        assert!(budget
+            .pack("preamble", &Message::user("prompt"), &mut packed)
+            .is_err());
+    }
+}
@@ -1,15 +1,43 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use crate::{
-    completion::{CompletionModel, Document},
-    tool::{Tool, ToolSet},
+    completion::{CompletionModel, Document, PostProcessor, SamplingParams, ToolChoice},
+    testing::{InjectedTool, ScriptedTool},
+    tool::{IdempotencyStore, MemoryIdempotencyStore, Tool, ToolDyn, ToolSet, ToolType},
     vector_store::VectorStoreIndexDyn,
 };
 
 #[cfg(feature = "mcp")]
 use crate::tool::McpTool;
 
-use super::Agent;
+use super::{
+    Agent, AgentHooks, AuditSink, CheckpointStore, DocumentFormatter, DynamicContextSettings,
+    DynamicContextSource, MemoryCheckpointStore, RagQueryStrategy, TokenBudget, ToolOutputLimits,
+};
+
+/// Default maximum number of dynamic indexes queried concurrently by the agents
+const DEFAULT_DYNAMIC_QUERY_CONCURRENCY: usize = 4;
+
+/// Configuration error of an agent, caught by [AgentBuilder::try_build] before the first prompt.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum AgentBuildError {
+    /// Two tools of the agent have the same name (only the last one is callable)
+    #[error("DuplicateTool: {0}")]
+    DuplicateTool(String),
+
+    /// A tool of the agent has no implementation in its toolset
+    #[error("UnregisteredTool: {0}")]
+    UnregisteredTool(String),
+
+    /// Dynamic context or tools which would never be inserted in the requests (e.g.: sampling
+    /// 0 documents, or without any tool implementation)
+    #[error("InvalidDynamicSource: {0}")]
+    InvalidDynamicSource(String),
+
+    /// A parameter of the agent is out of its range
+    #[error("InvalidParameter: {0}")]
+    InvalidParameter(String),
+}
 
 /// A builder for creating an agent
 ///
@@ -32,7 +60,12 @@ use super::Agent;
 ///     .additional_params(json!({"foo": "bar"}))
 ///     .build();
 /// ```
+///
+/// [AgentBuilder::try_build] fails on configuration errors (e.g.: tools with the same name,
+/// or dynamic context sampling no documents) instead of only logging them.
 pub struct AgentBuilder<M: CompletionModel> {
+    /// Name of the agent
+    name: Option<String>,
     /// Completion model (e.g.: OpenAI's gpt-3.5-turbo-1106, Cohere's command-r)
     model: M,
     /// System prompt
@@ -45,32 +78,94 @@ pub struct AgentBuilder<M: CompletionModel> {
     additional_params: Option<serde_json::Value>,
     /// Maximum number of tokens for the completion
     max_tokens: Option<u64>,
-    /// List of vector store, with the sample number
-    dynamic_context: Vec<(usize, Box<dyn VectorStoreIndexDyn>)>,
+    /// List of vector store, with the sample number and the options of the index
+    dynamic_context: Vec<(usize, Box<dyn VectorStoreIndexDyn>, DynamicContextSettings)>,
+    /// Maximum number of documents of the dynamic context (across all the indexes)
+    dynamic_context_top_k: Option<usize>,
+    /// Text querying the dynamic context and tools
+    rag_query: RagQueryStrategy,
+    /// Maximum number of dynamic indexes queried concurrently
+    dynamic_query_concurrency: usize,
     /// Dynamic tools
     dynamic_tools: Vec<(usize, Box<dyn VectorStoreIndexDyn>)>,
+    /// Minimum score of the dynamic context documents and tools
+    min_score: Option<f64>,
+    /// Maximum number of input tokens of the requests
+    token_budget: Option<TokenBudget>,
+    context_recovery_retries: usize,
     /// Temperature of the model
     temperature: Option<f64>,
+    /// Timeout of each completion request
+    timeout: Option<Duration>,
+    /// Additional sampling parameters of the model
+    sampling: SamplingParams,
     /// Actual tool implementations
     tools: ToolSet,
+    /// Maximum length of the tool outputs
+    tool_output_limits: Option<ToolOutputLimits>,
+    /// How the model should use the tools
+    tool_choice: ToolChoice,
+    /// Whether the model can call several tools at once
+    parallel_tool_calls: Option<bool>,
+    /// Post-processors of the text of the responses
+    post_processors: Vec<Arc<dyn PostProcessor>>,
+    /// Whether the tools without implementation fail the requests
+    strict_tools: bool,
+    /// Sinks receiving the audit records of the tool calls
+    audit_sinks: Vec<Arc<dyn AuditSink>>,
+    /// Outputs of the side-effecting tool calls
+    idempotency_store: Arc<dyn IdempotencyStore>,
+    checkpoint_store: Arc<dyn CheckpointStore>,
+    /// Handlers of the events of the agent
+    hooks: Vec<Arc<dyn AgentHooks>>,
+    /// Roles allowed to use the restricted tools, by tool name
+    tool_roles: HashMap<String, Vec<String>>,
+    /// Field of the documents of the dynamic context holding their tenant
+    tenant_field: Option<String>,
 }
 
 impl<M: CompletionModel> AgentBuilder<M> {
     pub fn new(model: M) -> Self {
         Self {
+            name: None,
             model,
             preamble: None,
             static_context: vec![],
             static_tools: vec![],
             temperature: None,
+            timeout: None,
+            sampling: SamplingParams::default(),
             max_tokens: None,
             additional_params: None,
             dynamic_context: vec![],
+            dynamic_context_top_k: None,
+            rag_query: RagQueryStrategy::default(),
+            dynamic_query_concurrency: DEFAULT_DYNAMIC_QUERY_CONCURRENCY,
             dynamic_tools: vec![],
+            min_score: None,
+            token_budget: None,
+            context_recovery_retries: 2,
             tools: ToolSet::default(),
+            tool_output_limits: None,
+            tool_choice: ToolChoice::default(),
+            post_processors: vec![],
+            parallel_tool_calls: None,
+            strict_tools: false,
+            audit_sinks: vec![],
+            idempotency_store: Arc::new(MemoryIdempotencyStore::default()),
+            checkpoint_store: Arc::new(MemoryCheckpointStore::default()),
+            hooks: vec![],
+            tool_roles: HashMap::new(),
+            tenant_field: None,
         }
     }
 
+    /// Set the name of the agent, identifying it in the audit records of its tool calls
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
     /// Set the system prompt
     pub fn preamble(mut self, preamble: &str) -> Self {
         self.preamble = Some(preamble.into());
@@ -105,6 +200,34 @@ impl<M: CompletionModel> AgentBuilder<M> {
         self
     }
 
+    /// Add a static tool to the agent, given as a [ToolDyn] (e.g.: a tool shared by several
+    /// agents)
+    pub fn dyn_tool(mut self, tool: impl ToolDyn + 'static) -> Self {
+        let toolname = tool.name();
+        self.tools.add_tool(tool);
+        self.static_tools.push(toolname);
+        self
+    }
+
+    /// Inject a [ScriptedTool] in the agent, returning canned results instead of calling the
+    /// tool of the same name (if any, whose definition is kept), for reproducible tests. The tool
+    /// replaced by the scripted tool must be added to the agent first.
+    pub fn scripted_tool(mut self, tool: ScriptedTool) -> Self {
+        let toolname = tool.name().to_string();
+        let replaced = self.tools.tools.remove(&toolname);
+        if replaced.is_none() {
+            self.static_tools.push(toolname.clone());
+        }
+        self.tools.tools.insert(
+            toolname,
+            ToolType::Simple(Arc::new(InjectedTool {
+                scripted: tool,
+                replaced,
+            })),
+        );
+        self
+    }
+
     // Add an MCP tool to the agent
     #[cfg(feature = "mcp")]
     pub fn mcp_tool<T: mcp_core::transport::Transport>(
@@ -121,12 +244,55 @@ impl<M: CompletionModel> AgentBuilder<M> {
     /// Add some dynamic context to the agent. On each prompt, `sample` documents from the
     /// dynamic context will be inserted in the request.
     pub fn dynamic_context(
-        mut self,
+        self,
+        sample: usize,
+        dynamic_context: impl VectorStoreIndexDyn + 'static,
+    ) -> Self {
+        self.dynamic_context_source(DynamicContextSource::new(sample, dynamic_context))
+    }
+
+    /// Same as [AgentBuilder::dynamic_context], but the documents are rendered with `formatter`
+    /// (e.g.: a closure or a [DocumentTemplate](super::DocumentTemplate)) instead of being
+    /// pretty-printed as JSON.
+    pub fn formatted_dynamic_context(
+        self,
+        sample: usize,
+        dynamic_context: impl VectorStoreIndexDyn + 'static,
+        formatter: impl DocumentFormatter + 'static,
+    ) -> Self {
+        self.dynamic_context_source(
+            DynamicContextSource::new(sample, dynamic_context).formatter(formatter),
+        )
+    }
+
+    /// Same as [AgentBuilder::dynamic_context], but the scores of the documents are multiplied by
+    /// `weight` when ranking the documents of all the dynamic context indexes, e.g.: to
+    /// downweight a noisy index (see [AgentBuilder::dynamic_context_top_k]). The documents of
+    /// the agents with weighted indexes are ranked by weighted score instead of by index.
+    pub fn weighted_dynamic_context(
+        self,
         sample: usize,
         dynamic_context: impl VectorStoreIndexDyn + 'static,
+        weight: f64,
     ) -> Self {
+        self.dynamic_context_source(
+            DynamicContextSource::new(sample, dynamic_context).weight(weight),
+        )
+    }
+
+    /// Add a dynamic context index with its options (formatter, weight and failure policy, see
+    /// [DynamicContextSource]).
+    pub fn dynamic_context_source(mut self, source: DynamicContextSource) -> Self {
         self.dynamic_context
-            .push((sample, Box::new(dynamic_context)));
+            .push((source.sample, source.index, source.settings));
+        self
+    }
+
+    /// Insert at most `top_k` documents of dynamic context in the requests, keeping the documents
+    /// with the best (weighted) scores across all the dynamic context indexes, instead of
+    /// concatenating the documents sampled from each index.
+    pub fn dynamic_context_top_k(mut self, top_k: usize) -> Self {
+        self.dynamic_context_top_k = Some(top_k);
         self
     }
 
@@ -143,12 +309,174 @@ impl<M: CompletionModel> AgentBuilder<M> {
         self
     }
 
+    /// Isolate the dynamic context by tenant: the documents of the dynamic context are only
+    /// returned to the prompts of their tenant (see
+    /// [PromptRequest::tenant](super::PromptRequest::tenant)), whose value is in the field
+    /// `field` of the documents (either a top-level field or a JSON pointer, see
+    /// [MetadataFilter](crate::vector_store::MetadataFilter)). The prompts without tenant get
+    /// no dynamic context.
+    pub fn tenant_field(mut self, field: &str) -> Self {
+        self.tenant_field = Some(field.to_string());
+        self
+    }
+
+    /// Set the maximum number of dynamic context indexes, and of dynamic tools indexes, queried
+    /// concurrently (default: 4). The dynamic context and tools are always queried concurrently.
+    pub fn dynamic_query_concurrency(mut self, concurrency: usize) -> Self {
+        self.dynamic_query_concurrency = concurrency;
+        self
+    }
+
+    /// Set the text querying the dynamic context and tools in multi-turn chats (default: the
+    /// latest user message, see [RagQueryStrategy]).
+    pub fn rag_query(mut self, strategy: RagQueryStrategy) -> Self {
+        self.rag_query = strategy;
+        self
+    }
+
+    /// Only insert the dynamic context documents and tools with a score of at least `min_score`.
+    /// The scores of the vector store indexes are normalized between 0 and 1 (see
+    /// [DistanceMetric](crate::vector_store::DistanceMetric)), so the threshold does not depend
+    /// on the backend of the indexes.
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    /// Set the maximum number of input tokens of the requests, trimming their context to fit (see
+    /// [TokenBudget]).
+    pub fn token_budget(mut self, token_budget: TokenBudget) -> Self {
+        self.token_budget = Some(token_budget);
+        self
+    }
+
+    /// Set the number of retries (default: 2, 0 to disable them) of the prompts rejected by the
+    /// provider as exceeding the context window of the model. Each retry trims the context of
+    /// the request to half of its tokens, in the trim order of the token budget of the
+    /// agent (see [TokenBudget::trim_order]), reporting the dropped parts to the hooks of the
+    /// agent (see [AgentHooks::on_context_trimmed](super::AgentHooks::on_context_trimmed)).
+    pub fn context_recovery_retries(mut self, retries: usize) -> Self {
+        self.context_recovery_retries = retries;
+        self
+    }
+
+    /// Fail the requests with [CompletionError::ToolNotFound](crate::completion::CompletionError::ToolNotFound)
+    /// when a static or dynamic tool has no implementation in the toolset of the agent, instead
+    /// of skipping the tool with a warning, so that broken deployments fail fast.
+    pub fn strict_tools(mut self, strict: bool) -> Self {
+        self.strict_tools = strict;
+        self
+    }
+
+    /// Limit the length of the tool outputs added to the conversation in multi-turn prompts,
+    /// truncating the longer ones (see [ToolOutputLimits])
+    pub fn tool_output_limits(mut self, limits: ToolOutputLimits) -> Self {
+        self.tool_output_limits = Some(limits);
+        self
+    }
+
+    /// Post-process the text of the responses of the model, e.g.: to strip the markdown code
+    /// fences of its answers (see [postprocess](crate::completion::postprocess))
+    pub fn post_processor(mut self, post_processor: impl PostProcessor + 'static) -> Self {
+        self.post_processors.push(Arc::new(post_processor));
+        self
+    }
+
+    /// Send the events of the agent (start and end of the prompts, tool calls and their results,
+    /// streamed text) to `hooks`, which can also deny the tool calls (see [AgentHooks])
+    pub fn hooks(mut self, hooks: impl AgentHooks + 'static) -> Self {
+        self.hooks.push(Arc::new(hooks));
+        self
+    }
+
+    /// Send a [ToolAuditRecord](super::ToolAuditRecord) of every tool call of the agent (tool,
+    /// caller, hash of the arguments, status and duration) to `sink`
+    pub fn audit_sink(mut self, sink: impl AuditSink + 'static) -> Self {
+        self.audit_sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Set the store of the outputs of the side-effecting tool calls (default: in memory), e.g.:
+    /// a store shared by the replicas of a service (see
+    /// [PromptRequest::conversation_id](super::PromptRequest::conversation_id))
+    pub fn idempotency_store(mut self, store: impl IdempotencyStore + 'static) -> Self {
+        self.idempotency_store = Arc::new(store);
+        self
+    }
+
+    /// Set the store of the checkpoints of the runs of the agent (default: in memory), e.g.: a
+    /// [FileCheckpointStore](super::FileCheckpointStore) so that the runs survive the restarts
+    /// of the process (see [PromptRequest::run_id](super::PromptRequest::run_id))
+    pub fn checkpoint_store(mut self, store: impl CheckpointStore + 'static) -> Self {
+        self.checkpoint_store = Arc::new(store);
+        self
+    }
+
+    /// Restrict the tool `toolname` to the callers having one of `roles` (see
+    /// [Principal](super::Principal)): the tool is neither advertised to nor callable by the other
+    /// callers, including the prompts without caller.
+    pub fn tool_roles<'a>(
+        mut self,
+        toolname: &str,
+        roles: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.tool_roles
+            .entry(toolname.to_string())
+            .or_default()
+            .extend(roles.into_iter().map(str::to_string));
+        self
+    }
+
+    /// Set how the model should use the tools of the agent, e.g.: `ToolChoice::Tool(name)` to
+    /// force the call of a tool (for router agents)
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = tool_choice;
+        self
+    }
+
+    /// Set whether the model can call several tools at once
+    pub fn parallel_tool_calls(mut self, parallel_tool_calls: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel_tool_calls);
+        self
+    }
+
     /// Set the temperature of the model
     pub fn temperature(mut self, temperature: f64) -> Self {
         self.temperature = Some(temperature);
         self
     }
 
+    /// Set the default timeout of each completion request of the agent, after which the request
+    /// (and the prompt being answered) fails with a
+    /// [CompletionError::Timeout](crate::completion::CompletionError::Timeout). See
+    /// [PromptRequest::timeout](super::PromptRequest::timeout) for the timeout of a whole
+    /// multi-turn prompt.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the additional sampling parameters of the model
+    pub fn sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Set the seed of the sampling of the model
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.sampling.seed = Some(seed);
+        self
+    }
+
+    /// Make the completions of the agent (best effort) reproducible, for snapshot tests: sample
+    /// them with the given seed, and with a temperature of 0 unless another one is set.
+    /// See also [AgentBuilder::scripted_tool] to make the results of its tools reproducible.
+    pub fn deterministic(mut self, seed: u64) -> Self {
+        self.temperature.get_or_insert(0.0);
+        self.sampling.seed = Some(seed);
+        self
+    }
+
     /// Set the maximum number of tokens for the completion
     pub fn max_tokens(mut self, max_tokens: u64) -> Self {
         self.max_tokens = Some(max_tokens);
@@ -161,19 +489,325 @@ impl<M: CompletionModel> AgentBuilder<M> {
         self
     }
 
-    /// Build the agent
+    /// Check the configuration of the agent: see [AgentBuildError] for the errors caught.
+    pub fn validate(&self) -> Result<(), AgentBuildError> {
+        let mut names = std::collections::HashSet::new();
+        for toolname in &self.static_tools {
+            if !names.insert(toolname) {
+                return Err(AgentBuildError::DuplicateTool(toolname.clone()));
+            }
+            if !self.tools.contains(toolname) {
+                return Err(AgentBuildError::UnregisteredTool(toolname.clone()));
+            }
+        }
+
+        if let ToolChoice::Tool(toolname) = &self.tool_choice {
+            if !self.tools.contains(toolname) {
+                return Err(AgentBuildError::UnregisteredTool(toolname.clone()));
+            }
+        }
+
+        if self
+            .dynamic_context
+            .iter()
+            .any(|(sample, _, _)| *sample == 0)
+        {
+            return Err(AgentBuildError::InvalidDynamicSource(
+                "dynamic context sampling 0 documents".into(),
+            ));
+        }
+        if self.dynamic_context_top_k == Some(0) {
+            return Err(AgentBuildError::InvalidDynamicSource(
+                "dynamic context keeping 0 documents".into(),
+            ));
+        }
+        if let Some(weight) = self
+            .dynamic_context
+            .iter()
+            .filter_map(|(_, _, settings)| settings.weight)
+            .find(|weight| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err(AgentBuildError::InvalidParameter(format!(
+                "dynamic context weights must be positive, got {weight}"
+            )));
+        }
+        if self.dynamic_tools.iter().any(|(sample, _)| *sample == 0) {
+            return Err(AgentBuildError::InvalidDynamicSource(
+                "dynamic tools sampling 0 tools".into(),
+            ));
+        }
+        if !self.dynamic_tools.is_empty() && self.tools.tools.len() <= names.len() {
+            return Err(AgentBuildError::InvalidDynamicSource(
+                "dynamic tools without any tool implementation in their toolset".into(),
+            ));
+        }
+
+        if let Some(min_score) = self.min_score.filter(|score| !(0.0..=1.0).contains(score)) {
+            return Err(AgentBuildError::InvalidParameter(format!(
+                "min_score must be between 0 and 1, got {min_score}"
+            )));
+        }
+        if let Some(temperature) = self.temperature.filter(|t| *t < 0.0 || t.is_nan()) {
+            return Err(AgentBuildError::InvalidParameter(format!(
+                "temperature must be positive, got {temperature}"
+            )));
+        }
+        if self.max_tokens == Some(0) {
+            return Err(AgentBuildError::InvalidParameter(
+                "max_tokens must be positive".into(),
+            ));
+        }
+        if self.timeout == Some(Duration::ZERO) {
+            return Err(AgentBuildError::InvalidParameter(
+                "timeout must be positive".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Build the agent, failing if its configuration is invalid (see [AgentBuilder::validate]).
+    pub fn try_build(self) -> Result<Agent<M>, AgentBuildError> {
+        self.validate()?;
+        Ok(self.build_unchecked())
+    }
+
+    /// Build the agent. Configuration errors (see [AgentBuilder::validate]) are logged; use
+    /// [AgentBuilder::try_build] to handle them.
     pub fn build(self) -> Agent<M> {
+        if let Err(err) = self.validate() {
+            tracing::warn!(target: "rig", "Invalid agent configuration: {}", err);
+        }
+        self.build_unchecked()
+    }
+
+    fn build_unchecked(self) -> Agent<M> {
         Agent {
+            name: self.name,
             model: self.model,
             preamble: self.preamble.unwrap_or_default(),
             static_context: self.static_context,
             static_tools: self.static_tools,
             temperature: self.temperature,
             max_tokens: self.max_tokens,
+            timeout: self.timeout,
+            sampling: self.sampling,
             additional_params: self.additional_params,
             dynamic_context: self.dynamic_context,
+            dynamic_context_top_k: self.dynamic_context_top_k,
+            rag_query: self.rag_query,
+            dynamic_query_concurrency: self.dynamic_query_concurrency,
             dynamic_tools: self.dynamic_tools,
+            min_score: self.min_score,
+            token_budget: self.token_budget,
+            context_recovery_retries: self.context_recovery_retries,
             tools: self.tools,
+            tool_output_limits: self.tool_output_limits,
+            tool_choice: self.tool_choice,
+            post_processors: self.post_processors,
+            parallel_tool_calls: self.parallel_tool_calls,
+            strict_tools: self.strict_tools,
+            audit_sinks: self.audit_sinks,
+            idempotency_store: self.idempotency_store,
+            checkpoint_store: self.checkpoint_store,
+            hooks: self.hooks,
+            tool_roles: self.tool_roles,
+            tenant_field: self.tenant_field,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{DocumentTemplate, DynamicContextSource},
+        completion::{
+            AssistantContent, Completion, CompletionError, CompletionRequest, CompletionResponse,
+            ToolDefinition,
+        },
+        pipeline::agent_ops::tests::MockIndex,
+        tool::ToolError,
+        vector_store::tests::TenantIndex,
+        OneOrMany,
+    };
+
+    #[derive(Clone)]
+    struct MockModel;
+
+    impl CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text("Hi")),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    struct Noop;
+
+    impl Tool for Noop {
+        const NAME: &'static str = "noop";
+
+        type Error = ToolError;
+        type Args = serde_json::Value;
+        type Output = ();
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Do nothing".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            }
         }
+
+        async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_try_build() {
+        assert!(AgentBuilder::new(MockModel)
+            .tool(Noop)
+            .scripted_tool(ScriptedTool::new("noop"))
+            .temperature(0.5)
+            .try_build()
+            .is_ok());
+
+        assert_eq!(
+            AgentBuilder::new(MockModel)
+                .tool(Noop)
+                .tool(Noop)
+                .try_build()
+                .err(),
+            Some(AgentBuildError::DuplicateTool("noop".into()))
+        );
+        assert!(matches!(
+            AgentBuilder::new(MockModel)
+                .dynamic_tools(2, MockIndex, ToolSet::default())
+                .try_build(),
+            Err(AgentBuildError::InvalidDynamicSource(_))
+        ));
+        assert!(matches!(
+            AgentBuilder::new(MockModel).min_score(2.0).try_build(),
+            Err(AgentBuildError::InvalidParameter(_))
+        ));
+        assert_eq!(
+            AgentBuilder::new(MockModel)
+                .tool(Noop)
+                .tool_choice(ToolChoice::Tool("route".into()))
+                .try_build()
+                .err(),
+            Some(AgentBuildError::UnregisteredTool("route".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_strict_tools() {
+        use crate::completion::{Prompt, PromptError};
+
+        let mut agent = AgentBuilder::new(MockModel).tool(Noop).build();
+        agent.static_tools.push("missing".into());
+        assert_eq!(agent.prompt("Hello").await.unwrap(), "Hi");
+
+        let mut agent = AgentBuilder::new(MockModel)
+            .tool(Noop)
+            .strict_tools(true)
+            .build();
+        assert_eq!(agent.prompt("Hello").await.unwrap(), "Hi");
+        agent.static_tools.push("missing".into());
+        assert!(matches!(
+            agent.prompt("Hello").await,
+            Err(PromptError::CompletionError(CompletionError::ToolNotFound(name))) if name == "missing"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tenant_isolation() {
+        let agent = AgentBuilder::new(MockModel)
+            .dynamic_context(3, TenantIndex)
+            .tenant_field("tenant")
+            .build();
+        let documents = |request: CompletionRequest| {
+            request
+                .documents
+                .into_iter()
+                .map(|doc| doc.id)
+                .collect::<Vec<_>>()
+        };
+
+        let request = agent
+            .completion_for("Hi", vec![], None, Some("acme"))
+            .await
+            .unwrap()
+            .build();
+        assert_eq!(documents(request), ["0", "3", "6"]);
+
+        let request = agent.completion("Hi", vec![]).await.unwrap().build();
+        assert!(documents(request).is_empty());
+
+        // Without tenant field, the tenant is in the `tenant` field of the documents
+        let agent = AgentBuilder::new(MockModel)
+            .dynamic_context(2, TenantIndex)
+            .build();
+        let request = agent
+            .completion_for("Hi", vec![], None, Some("globex"))
+            .await
+            .unwrap()
+            .build();
+        assert_eq!(documents(request), ["1", "2"]);
+        let request = agent.completion("Hi", vec![]).await.unwrap().build();
+        assert_eq!(documents(request), ["0", "1"]);
+    }
+
+    #[tokio::test]
+    async fn test_formatted_dynamic_context() {
+        let agent = AgentBuilder::new(MockModel)
+            .formatted_dynamic_context(2, TenantIndex, DocumentTemplate::new("#{rank} ({tenant})"))
+            .dynamic_context(1, TenantIndex)
+            .build();
+        let request = agent.completion("Hi", vec![]).await.unwrap().build();
+
+        let texts = request
+            .documents
+            .iter()
+            .map(|doc| doc.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts[..2], ["#0 (acme)", "#1 (globex)"]);
+        assert!(texts[2].contains("\"tenant\": \"acme\""));
+    }
+
+    #[tokio::test]
+    async fn test_weighted_dynamic_context() {
+        let agent = AgentBuilder::new(MockModel)
+            .formatted_dynamic_context(3, TenantIndex, DocumentTemplate::new("a{rank}"))
+            .dynamic_context_source(
+                DynamicContextSource::new(3, TenantIndex)
+                    .formatter(DocumentTemplate::new("b{rank}"))
+                    .weight(0.6),
+            )
+            .dynamic_context_top_k(4)
+            .build();
+        let request = agent.completion("Hi", vec![]).await.unwrap().build();
+        let texts = request
+            .documents
+            .iter()
+            .map(|doc| doc.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["a0", "b0", "a1", "a2"]);
+
+        assert!(matches!(
+            AgentBuilder::new(MockModel)
+                .weighted_dynamic_context(1, TenantIndex, -1.0)
+                .try_build(),
+            Err(AgentBuildError::InvalidParameter(_))
+        ));
     }
 }
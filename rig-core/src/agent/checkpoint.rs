@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::{Document, Message},
+    message::ToolCall,
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CheckpointError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// The run id cannot be used by the store (e.g.: as a file name)
+    #[error("InvalidRunId: {0}")]
+    InvalidRunId(String),
+
+    /// Error of a custom store
+    #[error("StoreError: {0}")]
+    StoreError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Next step of a checkpointed run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum RunStep {
+    /// Send the prompt (or the results of the tool calls) to the model
+    Prompt { prompt: Message },
+    /// Run the tool calls of the latest assistant message of the chat history
+    ToolCalls { tool_calls: Vec<ToolCall> },
+}
+
+/// State of an in-progress multi-turn prompt of an agent (see
+/// [PromptRequest::run_id](super::PromptRequest::run_id)), saved to the [CheckpointStore] of
+/// the agent after each completion request and after each batch of tool calls.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub run_id: String,
+    /// Number of the completion requests sent so far
+    pub depth: usize,
+    #[serde(with = "crate::completion::history")]
+    pub chat_history: Vec<Message>,
+    pub step: RunStep,
+    /// Documents of the dynamic and static context of the latest completion request
+    pub documents: Vec<Document>,
+    /// Time of the checkpoint, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+}
+
+impl Checkpoint {
+    pub(super) fn new(
+        run_id: &str,
+        depth: usize,
+        chat_history: &[Message],
+        step: RunStep,
+        documents: &[Document],
+    ) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            depth,
+            chat_history: chat_history.to_vec(),
+            step,
+            documents: documents.to_vec(),
+            timestamp_ms: web_time::SystemTime::now()
+                .duration_since(web_time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        }
+    }
+}
+
+/// Storage of the [Checkpoint]s of the runs of an agent, by run id, set with
+/// [AgentBuilder::checkpoint_store](super::AgentBuilder::checkpoint_store).
+pub trait CheckpointStore: WasmCompatSend + WasmCompatSync {
+    /// Save `checkpoint`, replacing the previous checkpoint of its run.
+    fn save<'a>(
+        &'a self,
+        checkpoint: &'a Checkpoint,
+    ) -> WasmBoxedFuture<'a, Result<(), CheckpointError>>;
+
+    /// The latest checkpoint of the run `run_id`, if any.
+    fn load<'a>(
+        &'a self,
+        run_id: &'a str,
+    ) -> WasmBoxedFuture<'a, Result<Option<Checkpoint>, CheckpointError>>;
+
+    /// Remove the checkpoint of the run `run_id` (e.g.: once the run completed).
+    fn remove<'a>(&'a self, run_id: &'a str) -> WasmBoxedFuture<'a, Result<(), CheckpointError>>;
+}
+
+/// [CheckpointStore] keeping the checkpoints in memory, e.g.: to resume the runs failing on a
+/// transient error. The checkpoints do not survive the restarts of the process, see
+/// [FileCheckpointStore].
+#[derive(Clone, Default)]
+pub struct MemoryCheckpointStore {
+    checkpoints: Arc<Mutex<HashMap<String, Checkpoint>>>,
+}
+
+impl MemoryCheckpointStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CheckpointStore for MemoryCheckpointStore {
+    fn save<'a>(
+        &'a self,
+        checkpoint: &'a Checkpoint,
+    ) -> WasmBoxedFuture<'a, Result<(), CheckpointError>> {
+        self.checkpoints
+            .lock()
+            .expect("Checkpoint store lock poisoned")
+            .insert(checkpoint.run_id.clone(), checkpoint.clone());
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn load<'a>(
+        &'a self,
+        run_id: &'a str,
+    ) -> WasmBoxedFuture<'a, Result<Option<Checkpoint>, CheckpointError>> {
+        let checkpoint = self
+            .checkpoints
+            .lock()
+            .expect("Checkpoint store lock poisoned")
+            .get(run_id)
+            .cloned();
+        Box::pin(async move { Ok(checkpoint) })
+    }
+
+    fn remove<'a>(&'a self, run_id: &'a str) -> WasmBoxedFuture<'a, Result<(), CheckpointError>> {
+        self.checkpoints
+            .lock()
+            .expect("Checkpoint store lock poisoned")
+            .remove(run_id);
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+/// [CheckpointStore] saving the checkpoints as JSON files of a directory, named after their run
+/// id (which must only contain ASCII letters, digits, `-` and `_`). The files are replaced
+/// atomically, so that a crash while saving keeps the previous checkpoint.
+pub struct FileCheckpointStore {
+    dir: PathBuf,
+}
+
+impl FileCheckpointStore {
+    /// Store saving the checkpoints in the directory `dir`, created if needed.
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn path(&self, run_id: &str) -> Result<PathBuf, CheckpointError> {
+        if run_id.is_empty()
+            || !run_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(CheckpointError::InvalidRunId(run_id.to_string()));
+        }
+        Ok(self.dir.join(format!("{run_id}.json")))
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn save<'a>(
+        &'a self,
+        checkpoint: &'a Checkpoint,
+    ) -> WasmBoxedFuture<'a, Result<(), CheckpointError>> {
+        Box::pin(async move {
+            let path = self.path(&checkpoint.run_id)?;
+            let partial = path.with_extension("json.partial");
+            std::fs::write(&partial, serde_json::to_vec(checkpoint)?)?;
+            std::fs::rename(partial, path)?;
+            Ok(())
+        })
+    }
+
+    fn load<'a>(
+        &'a self,
+        run_id: &'a str,
+    ) -> WasmBoxedFuture<'a, Result<Option<Checkpoint>, CheckpointError>> {
+        Box::pin(async move {
+            match std::fs::read(self.path(run_id)?) {
+                Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+
+    fn remove<'a>(&'a self, run_id: &'a str) -> WasmBoxedFuture<'a, Result<(), CheckpointError>> {
+        Box::pin(async move {
+            match std::fs::remove_file(self.path(run_id)?) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse, Prompt,
+        },
+        message::UserContent,
+        testing::ScriptedTool,
+        OneOrMany,
+    };
+
+    /// Calls the `charge` tool, then answers "done", counting its requests
+    #[derive(Clone, Default)]
+    struct ChargeModel {
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl CompletionModel for ChargeModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let choice = match request.chat_history.iter().last() {
+                Some(Message::User { content })
+                    if matches!(content.first(), UserContent::ToolResult(_)) =>
+                {
+                    AssistantContent::text("done")
+                }
+                _ => AssistantContent::tool_call(
+                    "call_1",
+                    "charge",
+                    serde_json::json!({"amount": 10}),
+                ),
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_run() {
+        let dir = std::env::temp_dir().join(format!("rig-checkpoints-{}", std::process::id()));
+        let store = FileCheckpointStore::new(&dir).unwrap();
+        let model = ChargeModel::default();
+        let charge = ScriptedTool::new("charge")
+            .fails("Payment service unavailable")
+            .returns("charged");
+        let agent = AgentBuilder::new(model.clone())
+            .scripted_tool(charge.clone())
+            .checkpoint_store(FileCheckpointStore::new(&dir).unwrap())
+            .build();
+
+        // The tool call fails, the run is checkpointed before it
+        assert!(agent
+            .prompt("Charge 10")
+            .run_id("job-1")
+            .multi_turn(2)
+            .await
+            .is_err());
+        let checkpoint = store.load("job-1").await.unwrap().unwrap();
+        assert_eq!(checkpoint.depth, 1);
+        assert_eq!(checkpoint.chat_history.len(), 2);
+        assert!(matches!(
+            &checkpoint.step,
+            RunStep::ToolCalls { tool_calls } if tool_calls[0].function.name == "charge"
+        ));
+
+        // The resumed run only runs the pending tool call and the final request
+        let mut chat_history = vec![];
+        let response = agent
+            .prompt("Ignored")
+            .with_history(&mut chat_history)
+            .run_id("job-1")
+            .multi_turn(2)
+            .await
+            .unwrap();
+        assert_eq!(response, "done");
+        assert_eq!(model.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(charge.calls().len(), 2);
+        assert_eq!(chat_history.len(), 4);
+        assert_eq!(chat_history[0], Message::user("Charge 10"));
+        assert!(store.load("job-1").await.unwrap().is_none());
+
+        assert!(matches!(
+            store.load("../job-1").await,
+            Err(CheckpointError::InvalidRunId(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,21 +1,37 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use futures::{stream, StreamExt, TryStreamExt};
 
 use crate::{
+    cancellation::CancellationToken,
     completion::{
-        Chat, Completion, CompletionError, CompletionModel, CompletionRequestBuilder, Document,
-        Message, Prompt, PromptError,
+        Chat, Completion, CompletionError, CompletionModel, CompletionRequestBuilder,
+        CompletionResponse, Document, Message, PostProcessor, Prompt, PromptError, SamplingParams,
+        ToolChoice, ToolDefinition,
     },
     streaming::{
         StreamingChat, StreamingCompletion, StreamingCompletionModel, StreamingCompletionResponse,
         StreamingPrompt,
     },
-    tool::ToolSet,
-    vector_store::VectorStoreError,
+    tool::{IdempotencyStore, ToolSet},
+    vector_store::{MetadataFilter, VectorStoreError},
 };
 
-use super::prompt_request::PromptRequest;
+use super::{
+    audit::AuditSink,
+    budget::{PackedContext, TokenBudget, TrimReport},
+    checkpoint::CheckpointStore,
+    dynamic_context::{query_index, DynamicContextSettings},
+    hooks::AgentHooks,
+    principal::Principal,
+    prompt_request::PromptRequest,
+    rag_query::RagQueryStrategy,
+    tool_output::ToolOutputLimits,
+};
+
+/// Field of the documents holding their tenant, when the tenant of the prompts is set without
+/// [AgentBuilder::tenant_field](super::AgentBuilder::tenant_field)
+pub const DEFAULT_TENANT_FIELD: &str = "tenant";
 
 /// Struct representing an LLM agent. An agent is an LLM model combined with a preamble
 /// (i.e.: system prompt) and a static set of context documents and tools.
@@ -38,6 +54,8 @@ use super::prompt_request::PromptRequest;
 ///     .expect("Failed to prompt the agent");
 /// ```
 pub struct Agent<M: CompletionModel> {
+    /// Name of the agent (e.g.: in the audit records of its tool calls)
+    pub name: Option<String>,
     /// Completion model (e.g.: OpenAI's gpt-3.5-turbo-1106, Cohere's command-r)
     pub model: M,
     /// System prompt
@@ -50,139 +68,393 @@ pub struct Agent<M: CompletionModel> {
     pub temperature: Option<f64>,
     /// Maximum number of tokens for the completion
     pub max_tokens: Option<u64>,
+    /// Timeout of each completion request of the agent
+    pub timeout: Option<Duration>,
+    /// Additional sampling parameters of the model
+    pub sampling: SamplingParams,
     /// Additional parameters to be passed to the model
     pub additional_params: Option<serde_json::Value>,
-    /// List of vector store, with the sample number
-    pub dynamic_context: Vec<(usize, Box<dyn crate::vector_store::VectorStoreIndexDyn>)>,
+    /// List of vector store, with the sample number and the options of the index (formatter,
+    /// weight and failure policy)
+    pub dynamic_context: Vec<(
+        usize,
+        Box<dyn crate::vector_store::VectorStoreIndexDyn>,
+        DynamicContextSettings,
+    )>,
+    /// Text querying the dynamic context and tools
+    pub rag_query: RagQueryStrategy,
+    /// Maximum number of dynamic context indexes (and of dynamic tools indexes) queried
+    /// concurrently
+    pub dynamic_query_concurrency: usize,
+    /// Maximum number of documents of the dynamic context, keeping the documents with the best
+    /// weighted scores across all the indexes
+    pub dynamic_context_top_k: Option<usize>,
     /// Dynamic tools
     pub dynamic_tools: Vec<(usize, Box<dyn crate::vector_store::VectorStoreIndexDyn>)>,
+    /// Minimum score (between 0 and 1) of the dynamic context documents and tools
+    pub min_score: Option<f64>,
+    /// Maximum number of input tokens of the requests
+    pub token_budget: Option<TokenBudget>,
+    /// Number of retries of the requests rejected by the provider as exceeding the context
+    /// window of the model, with a smaller context
+    pub context_recovery_retries: usize,
     /// Actual tool implementations
     pub tools: ToolSet,
+    /// Maximum length of the tool outputs added to the conversation
+    pub tool_output_limits: Option<ToolOutputLimits>,
+    /// How the model should use the tools of the agent
+    pub tool_choice: ToolChoice,
+    /// Whether the model can call several tools at once (`None` for the default of the provider)
+    pub parallel_tool_calls: Option<bool>,
+    /// Post-processors of the text of the responses of the model
+    pub post_processors: Vec<Arc<dyn PostProcessor>>,
+    /// Whether the tools without implementation in the toolset fail the requests (instead of
+    /// being skipped with a warning)
+    pub strict_tools: bool,
+    /// Sinks receiving the audit records of the tool calls
+    pub audit_sinks: Vec<Arc<dyn AuditSink>>,
+    /// Outputs of the side-effecting tool calls, by idempotency key
+    pub idempotency_store: Arc<dyn IdempotencyStore>,
+    /// Checkpoints of the runs of the agent, by run id
+    pub checkpoint_store: Arc<dyn CheckpointStore>,
+    /// Handlers of the events of the agent (prompts, tool calls, streamed text)
+    pub hooks: Vec<Arc<dyn AgentHooks>>,
+    /// Roles allowed to use the restricted tools, by tool name
+    pub tool_roles: HashMap<String, Vec<String>>,
+    /// Field of the documents of the dynamic context holding their tenant, if the dynamic context
+    /// is isolated by tenant
+    pub tenant_field: Option<String>,
 }
 
-impl<M: CompletionModel> Completion<M> for Agent<M> {
-    async fn completion(
+impl<M: CompletionModel> Agent<M> {
+    fn passes_min_score(&self, score: f64) -> bool {
+        !matches!(self.min_score, Some(min_score) if score < min_score)
+    }
+
+    /// Query the dynamic context indexes for `text`, returning their documents with their weighted
+    /// scores.
+    async fn query_dynamic_context(
+        &self,
+        text: &str,
+        tenant_filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<(f64, Document)>, CompletionError> {
+        let queries = self
+            .dynamic_context
+            .iter()
+            .enumerate()
+            .map(|(position, (num_sample, index, settings))| async move {
+                let formatter = settings.formatter.as_ref();
+                let weight = settings.weight.unwrap_or(1.0);
+                let results = query_index(
+                    &**index,
+                    &settings.on_failure,
+                    position,
+                    text,
+                    *num_sample,
+                    tenant_filter,
+                )
+                .await?;
+                Ok::<_, VectorStoreError>(
+                    results
+                        .into_iter()
+                        .filter(|(score, _, _)| self.passes_min_score(*score))
+                        .map(|(score, id, doc)| {
+                            let text = match formatter {
+                                Some(formatter) => formatter.format(&id, &doc),
+                                // Pretty print the document if possible for better readability
+                                None => serde_json::to_string_pretty(&doc)
+                                    .unwrap_or_else(|_| doc.to_string()),
+                            };
+
+                            let document = Document {
+                                id,
+                                text,
+                                additional_props: HashMap::new(),
+                            };
+                            (score * weight, document)
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let documents = stream::iter(queries)
+            .buffered(self.dynamic_query_concurrency.max(1))
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|e| CompletionError::RequestError(Box::new(e)))?;
+        Ok(documents.concat())
+    }
+
+    /// Query the dynamic tools indexes for `text`, returning the definitions of their tools allowed
+    /// to `principal`.
+    async fn query_dynamic_tools(
+        &self,
+        text: &str,
+        principal: Option<&Principal>,
+    ) -> Result<Vec<ToolDefinition>, CompletionError> {
+        let queries = self
+            .dynamic_tools
+            .iter()
+            .map(|(num_sample, index)| async move {
+                Ok::<_, VectorStoreError>(
+                    index
+                        .top_n_ids(text, *num_sample)
+                        .await?
+                        .into_iter()
+                        .filter(|(score, _)| self.passes_min_score(*score))
+                        .map(|(_, id)| id)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let toolnames = stream::iter(queries)
+            .buffered(self.dynamic_query_concurrency.max(1))
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|e| CompletionError::RequestError(Box::new(e)))?
+            .concat();
+        self.tool_definitions(toolnames.iter(), text, principal)
+            .await
+    }
+
+    /// Merge the documents of the dynamic context indexes with their weighted scores: the documents
+    /// are ranked by weighted score if some indexes are weighted or the number of documents is
+    /// limited (see [AgentBuilder::dynamic_context_top_k](super::AgentBuilder::dynamic_context_top_k)),
+    /// and concatenated in the order of their indexes otherwise.
+    fn merge_dynamic_context(&self, mut documents: Vec<(f64, Document)>) -> Vec<Document> {
+        let weighted = self
+            .dynamic_context
+            .iter()
+            .any(|(_, _, settings)| settings.weight.is_some());
+        if weighted || self.dynamic_context_top_k.is_some() {
+            documents.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        }
+        if let Some(top_k) = self.dynamic_context_top_k {
+            documents.truncate(top_k);
+        }
+        documents
+            .into_iter()
+            .map(|(_, document)| document)
+            .collect()
+    }
+
+    /// Whether `principal` may use the tool `toolname` (see
+    /// [AgentBuilder::tool_roles](super::AgentBuilder::tool_roles))
+    pub fn tool_allowed(&self, toolname: &str, principal: Option<&Principal>) -> bool {
+        match self.tool_roles.get(toolname) {
+            Some(roles) => {
+                principal.is_some_and(|principal| roles.iter().any(|role| principal.has_role(role)))
+            }
+            None => true,
+        }
+    }
+
+    /// The definitions of the tools named `toolnames` allowed to `principal`. The tools without
+    /// implementation in the toolset are skipped with a warning, or fail with
+    /// [CompletionError::ToolNotFound] if the agent has strict tools.
+    async fn tool_definitions(
+        &self,
+        toolnames: impl Iterator<Item = &String>,
+        prompt: &str,
+        principal: Option<&Principal>,
+    ) -> Result<Vec<ToolDefinition>, CompletionError> {
+        let mut resolved = vec![];
+        for toolname in toolnames {
+            if !self.tool_allowed(toolname, principal) {
+                continue;
+            }
+            if self.tools.contains(toolname) {
+                resolved.push(self.tools.definition(toolname, prompt));
+            } else if self.strict_tools {
+                return Err(CompletionError::ToolNotFound(toolname.clone()));
+            } else {
+                tracing::warn!("Tool implementation not found in toolset: {}", toolname);
+            }
+        }
+        // The definitions are resolved concurrently, in the order of the tools
+        Ok(futures::future::join_all(resolved)
+            .await
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Generate a completion request for `principal`, with only the tools allowed to it (see
+    /// [AgentBuilder::tool_roles](super::AgentBuilder::tool_roles)), and the dynamic context of
+    /// `tenant` (see [AgentBuilder::tenant_field](super::AgentBuilder::tenant_field)).
+    /// [Completion::completion] generates the requests without principal nor tenant, without
+    /// the restricted tools.
+    pub async fn completion_for(
         &self,
         prompt: impl Into<Message> + Send,
         chat_history: Vec<Message>,
+        principal: Option<&Principal>,
+        tenant: Option<&str>,
     ) -> Result<CompletionRequestBuilder<M>, CompletionError> {
-        let prompt = prompt.into();
+        let (request, _) = self
+            .completion_with_budget(
+                prompt.into(),
+                chat_history,
+                principal,
+                tenant,
+                self.token_budget.as_ref(),
+            )
+            .await?;
+        Ok(request)
+    }
+
+    /// Send the completion request of `prompt` (see [Agent::completion_for]), returning the
+    /// response and the documents attached to the request.
+    ///
+    /// When the provider rejects the request as exceeding the context window of the model, the
+    /// request is retried (up to [Agent::context_recovery_retries] times) with a context trimmed
+    /// to half of its tokens, in the trim order of the token budget of the agent.
+    pub(crate) async fn send_completion(
+        &self,
+        prompt: Message,
+        chat_history: &[Message],
+        principal: Option<&Principal>,
+        tenant: Option<&str>,
+        cancellation: Option<CancellationToken>,
+    ) -> Result<(CompletionResponse<M::Response>, Vec<Document>), CompletionError> {
+        // The requests are counted, so that they can be trimmed on retry
+        let mut budget = self
+            .token_budget
+            .clone()
+            .or_else(|| (self.context_recovery_retries > 0).then(|| TokenBudget::new(usize::MAX)));
+        let mut retries = 0;
+        loop {
+            let (request, report) = self
+                .completion_with_budget(
+                    prompt.clone(),
+                    chat_history.to_vec(),
+                    principal,
+                    tenant,
+                    budget.as_ref(),
+                )
+                .await?;
+            let request = request.cancellation_opt(cancellation.clone());
+            let documents = request.attached_documents().to_vec();
+
+            match (request.send().await, budget, report) {
+                (Err(err), Some(previous), Some(report))
+                    if err.is_context_length_exceeded()
+                        && retries < self.context_recovery_retries =>
+                {
+                    retries += 1;
+                    tracing::warn!(target: "rig",
+                        "The request of about {} tokens exceeds the context window of the model, \
+                        retrying with a smaller context ({retries}/{}): {err}",
+                        report.tokens_after, self.context_recovery_retries
+                    );
+                    budget = Some(previous.with_max_input_tokens(report.tokens_after / 2));
+                }
+                (response, _, _) => return response.map(|response| (response, documents)),
+            }
+        }
+    }
 
-        // Find the latest message in the chat history that contains RAG text
-        let rag_text = prompt.rag_text();
-        let rag_text = rag_text.or_else(|| {
-            chat_history
-                .iter()
-                .rev()
-                .find_map(|message| message.rag_text())
+    /// Generate a completion request (see [Agent::completion_for]), trimmed to fit in `budget`.
+    async fn completion_with_budget(
+        &self,
+        prompt: Message,
+        chat_history: Vec<Message>,
+        principal: Option<&Principal>,
+        tenant: Option<&str>,
+        budget: Option<&TokenBudget>,
+    ) -> Result<(CompletionRequestBuilder<M>, Option<TrimReport>), CompletionError> {
+        let tenant_filter = tenant.map(|tenant| {
+            MetadataFilter::eq(
+                self.tenant_field.as_deref().unwrap_or(DEFAULT_TENANT_FIELD),
+                tenant,
+            )
         });
+        let tenant_filter = tenant_filter.as_ref();
+        // The documents of an isolated dynamic context are never returned without tenant
+        let skip_dynamic_context = self.tenant_field.is_some() && tenant.is_none();
 
-        let completion_request = self
-            .model
-            .completion_request(prompt)
-            .preamble(self.preamble.clone())
-            .messages(chat_history)
-            .temperature_opt(self.temperature)
-            .max_tokens_opt(self.max_tokens)
-            .additional_params_opt(self.additional_params.clone())
-            .documents(self.static_context.clone());
+        let rag_text = self.rag_query(&prompt, &chat_history).await?;
 
         // If the agent has RAG text, we need to fetch the dynamic context and tools
-        let agent = match &rag_text {
+        let (dynamic_context, tools) = match &rag_text {
             Some(text) => {
-                let dynamic_context = stream::iter(self.dynamic_context.iter())
-                    .then(|(num_sample, index)| async {
-                        Ok::<_, VectorStoreError>(
-                            index
-                                .top_n(text, *num_sample)
-                                .await?
-                                .into_iter()
-                                .map(|(_, id, doc)| {
-                                    // Pretty print the document if possible for better readability
-                                    let text = serde_json::to_string_pretty(&doc)
-                                        .unwrap_or_else(|_| doc.to_string());
-
-                                    Document {
-                                        id,
-                                        text,
-                                        additional_props: HashMap::new(),
-                                    }
-                                })
-                                .collect::<Vec<_>>(),
-                        )
-                    })
-                    .try_fold(vec![], |mut acc, docs| async {
-                        acc.extend(docs);
-                        Ok(acc)
-                    })
-                    .await
-                    .map_err(|e| CompletionError::RequestError(Box::new(e)))?;
-
-                let dynamic_tools = stream::iter(self.dynamic_tools.iter())
-                    .then(|(num_sample, index)| async {
-                        Ok::<_, VectorStoreError>(
-                            index
-                                .top_n_ids(text, *num_sample)
-                                .await?
-                                .into_iter()
-                                .map(|(_, id)| id)
-                                .collect::<Vec<_>>(),
-                        )
-                    })
-                    .try_fold(vec![], |mut acc, docs| async {
-                        for doc in docs {
-                            if let Some(tool) = self.tools.get(&doc) {
-                                acc.push(tool.definition(text.into()).await)
-                            } else {
-                                tracing::warn!("Tool implementation not found in toolset: {}", doc);
-                            }
-                        }
-                        Ok(acc)
-                    })
-                    .await
-                    .map_err(|e| CompletionError::RequestError(Box::new(e)))?;
-
-                let static_tools = stream::iter(self.static_tools.iter())
-                    .filter_map(|toolname| async move {
-                        if let Some(tool) = self.tools.get(toolname) {
-                            Some(tool.definition(text.into()).await)
-                        } else {
-                            tracing::warn!(
-                                "Tool implementation not found in toolset: {}",
-                                toolname
-                            );
-                            None
+                // The dynamic context and tools, and the static tools, are resolved concurrently
+                let (dynamic_context, dynamic_tools, static_tools) = futures::try_join!(
+                    async {
+                        if skip_dynamic_context {
+                            return Ok(vec![]);
                         }
-                    })
-                    .collect::<Vec<_>>()
-                    .await;
+                        self.query_dynamic_context(text, tenant_filter).await
+                    },
+                    self.query_dynamic_tools(text, principal),
+                    self.tool_definitions(self.static_tools.iter(), text, principal),
+                )?;
+                let dynamic_context = self.merge_dynamic_context(dynamic_context);
 
-                completion_request
-                    .documents(dynamic_context)
-                    .tools([static_tools.clone(), dynamic_tools].concat())
+                (dynamic_context, [static_tools, dynamic_tools].concat())
             }
-            None => {
-                let static_tools = stream::iter(self.static_tools.iter())
-                    .filter_map(|toolname| async move {
-                        if let Some(tool) = self.tools.get(toolname) {
-                            // TODO: tool definitions should likely take an `Option<String>`
-                            Some(tool.definition("".into()).await)
-                        } else {
-                            tracing::warn!(
-                                "Tool implementation not found in toolset: {}",
-                                toolname
-                            );
-                            None
-                        }
-                    })
-                    .collect::<Vec<_>>()
-                    .await;
+            // TODO: tool definitions should likely take an `Option<String>`
+            None => (
+                vec![],
+                self.tool_definitions(self.static_tools.iter(), "", principal)
+                    .await?,
+            ),
+        };
 
-                completion_request.tools(static_tools)
+        let mut context = PackedContext {
+            history: chat_history,
+            static_context: self.static_context.clone(),
+            dynamic_context,
+            tools,
+        };
+        let report = match budget {
+            Some(budget) => {
+                let report = budget.pack(&self.preamble, &prompt, &mut context)?;
+                if report.is_trimmed() {
+                    tracing::info!(target: "rig",
+                        "Dropped {} dynamic context documents, {} static context documents, {} \
+                        messages and {} tools to fit the request in {} tokens",
+                        report.dynamic_context, report.static_context, report.history,
+                        report.tools, budget.max_input_tokens()
+                    );
+                    self.hooks
+                        .iter()
+                        .for_each(|hook| hook.on_context_trimmed(&report));
+                }
+                Some(report)
             }
+            None => None,
         };
 
-        Ok(agent)
+        let request = self
+            .model
+            .completion_request(prompt)
+            .preamble(self.preamble.clone())
+            .messages(context.history)
+            .temperature_opt(self.temperature)
+            .max_tokens_opt(self.max_tokens)
+            .timeout_opt(self.timeout)
+            .sampling(self.sampling.clone())
+            .additional_params_opt(self.additional_params.clone())
+            .documents(context.static_context)
+            .documents(context.dynamic_context)
+            .tools(context.tools)
+            .tool_choice(self.tool_choice.clone())
+            .parallel_tool_calls_opt(self.parallel_tool_calls)
+            .post_processors(self.post_processors.clone());
+        Ok((request, report))
+    }
+}
+
+impl<M: CompletionModel> Completion<M> for Agent<M> {
+    async fn completion(
+        &self,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<CompletionRequestBuilder<M>, CompletionError> {
+        self.completion_for(prompt, chat_history, None, None).await
     }
 }
 
@@ -248,9 +520,15 @@ impl<M: StreamingCompletionModel> StreamingChat<M::StreamingResponse> for Agent<
         prompt: impl Into<Message> + Send,
         chat_history: Vec<Message>,
     ) -> Result<StreamingCompletionResponse<M::StreamingResponse>, CompletionError> {
-        self.stream_completion(prompt, chat_history)
+        let mut response = self
+            .stream_completion(prompt, chat_history)
             .await?
             .stream()
-            .await
+            .await?;
+        for hook in &self.hooks {
+            let hook = hook.clone();
+            response = response.on_text(move |text| hook.on_token(text));
+        }
+        Ok(response)
     }
 }
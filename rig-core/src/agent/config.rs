@@ -0,0 +1,403 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::wasm_compat::WasmBoxedFuture;
+use crate::{
+    completion::{BoxCompletionModel, CompletionModel, SamplingParams, ToolChoice, ToolDefinition},
+    tool::{Tool, ToolDyn, ToolError},
+    vector_store::{BoxVectorIndex, VectorStoreIndex},
+};
+
+use super::{
+    Agent, AgentBuildError, AgentBuilder, DocumentTemplate, DynAgent, DynamicContextSource,
+    IndexFailurePolicy,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AgentConfigError {
+    /// The config is not valid JSON (or does not match [AgentConfig])
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// The config refers to a model which is not registered
+    #[error("UnknownModel: {0}")]
+    UnknownModel(String),
+
+    /// The config refers to a vector store index which is not registered
+    #[error("UnknownIndex: {0}")]
+    UnknownIndex(String),
+
+    /// The config refers to a tool which is not registered
+    #[error("UnknownTool: {0}")]
+    UnknownTool(String),
+
+    /// The agent described by the config is not valid
+    #[error("InvalidAgent: {0}")]
+    InvalidAgent(#[from] AgentBuildError),
+}
+
+/// Serializable configuration of an agent, whose model, tools and dynamic context sources are
+/// referred to by their names in an [AgentRegistry].
+///
+/// The config can be deserialized from any serde format (e.g.: JSON with
+/// [AgentConfig::from_json], or YAML with `serde_yaml`).
+///
+/// # Example
+/// ```yaml
+/// model: gpt-4o
+/// preamble: You are a dictionary assistant.
+/// temperature: 0.2
+/// tools: [lookup]
+/// dynamic_context:
+///   - index: definitions
+///     samples: 2
+///     template: "{word}: {definition}"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentConfig {
+    /// Name of the model in the registry
+    pub model: String,
+    /// System prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preamble: Option<String>,
+    /// Static context documents
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context: Vec<String>,
+    /// Names of the static tools in the registry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Dynamic context sources
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dynamic_context: Vec<DynamicContextConfig>,
+    /// Maximum number of documents of dynamic context (see
+    /// [AgentBuilder::dynamic_context_top_k])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dynamic_context_top_k: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    /// Timeout of each completion request, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<f64>,
+    #[serde(default)]
+    pub sampling: SamplingParams,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub additional_params: Option<serde_json::Value>,
+    /// Fail the requests when a tool has no implementation (see
+    /// [AgentBuilder::strict_tools])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict_tools: bool,
+    /// How the model should use the tools, e.g.: `{"type": "tool", "name": "route"}`
+    #[serde(default, skip_serializing_if = "is_auto")]
+    pub tool_choice: ToolChoice,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+}
+
+/// A dynamic context source of an [AgentConfig].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DynamicContextConfig {
+    /// Name of the vector store index in the registry
+    pub index: String,
+    /// Number of documents of the index inserted in each request
+    pub samples: usize,
+    /// Template rendering the documents (see [DocumentTemplate]), instead of pretty-printing them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Weight of the scores of the documents (see [AgentBuilder::weighted_dynamic_context])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+    /// Behaviour of the agent when the index fails (see [IndexFailurePolicy])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<IndexFailureConfig>,
+}
+
+/// Serializable [IndexFailurePolicy] of a [DynamicContextConfig], e.g.: `on_failure: skip` or
+/// `on_failure: {fallback: docs_snapshot}` in YAML.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexFailureConfig {
+    Fail,
+    Skip,
+    /// Name of the fallback index in the registry
+    Fallback(String),
+}
+
+impl AgentConfig {
+    pub fn from_json(json: &str) -> Result<Self, AgentConfigError> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Registry of the named models, vector store indexes and tools that [AgentConfig]s refer to.
+///
+/// # Example
+/// ```
+/// use rig::{agent::{AgentConfig, AgentRegistry}, completion::Prompt, providers::openai};
+///
+/// let openai = openai::Client::from_env();
+///
+/// let registry = AgentRegistry::new()
+///     .model("gpt-4o", openai.completion_model(openai::GPT_4O))
+///     .model("gpt-4o-mini", openai.completion_model(openai::GPT_4O_MINI))
+///     .index("definitions", index)
+///     .tool(Lookup);
+///
+/// // E.g.: one config per variant of an A/B experiment
+/// let config = AgentConfig::from_json(&std::fs::read_to_string("agent.json")?)?;
+/// let agent = registry.build(&config)?;
+///
+/// let response = agent.prompt("What does \"glarb-glarb\" mean?").await?;
+/// ```
+#[derive(Clone, Default)]
+pub struct AgentRegistry {
+    models: HashMap<String, BoxCompletionModel>,
+    indexes: HashMap<String, BoxVectorIndex>,
+    tools: HashMap<String, Arc<dyn ToolDyn>>,
+}
+
+impl AgentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a model under `name`.
+    pub fn model<M>(mut self, name: &str, model: M) -> Self
+    where
+        M: CompletionModel + 'static,
+        M::Response: 'static,
+    {
+        self.models
+            .insert(name.to_string(), BoxCompletionModel::new(model));
+        self
+    }
+
+    /// Register a vector store index under `name`.
+    pub fn index(mut self, name: &str, index: impl VectorStoreIndex + 'static) -> Self {
+        self.indexes
+            .insert(name.to_string(), BoxVectorIndex::new(index));
+        self
+    }
+
+    /// Register a tool under its name (shared by all the agents using it).
+    pub fn tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.insert(tool.name(), Arc::new(tool));
+        self
+    }
+
+    /// Build the agent described by `config`.
+    pub fn build(
+        &self,
+        config: &AgentConfig,
+    ) -> Result<Agent<BoxCompletionModel>, AgentConfigError> {
+        let model = self
+            .models
+            .get(&config.model)
+            .ok_or_else(|| AgentConfigError::UnknownModel(config.model.clone()))?;
+
+        let mut builder = AgentBuilder::new(model.clone())
+            .sampling(config.sampling.clone())
+            .strict_tools(config.strict_tools)
+            .tool_choice(config.tool_choice.clone());
+
+        if let Some(preamble) = &config.preamble {
+            builder = builder.preamble(preamble);
+        }
+        for doc in &config.context {
+            builder = builder.context(doc);
+        }
+        for name in &config.tools {
+            let tool = self
+                .tools
+                .get(name)
+                .ok_or_else(|| AgentConfigError::UnknownTool(name.clone()))?;
+            builder = builder.dyn_tool(SharedTool(tool.clone()));
+        }
+        for source in &config.dynamic_context {
+            let index = self
+                .indexes
+                .get(&source.index)
+                .ok_or_else(|| AgentConfigError::UnknownIndex(source.index.clone()))?;
+            let mut dynamic_context = DynamicContextSource::new(source.samples, index.clone());
+            if let Some(template) = &source.template {
+                dynamic_context = dynamic_context.formatter(DocumentTemplate::new(template));
+            }
+            if let Some(weight) = source.weight {
+                dynamic_context = dynamic_context.weight(weight);
+            }
+            match &source.on_failure {
+                Some(IndexFailureConfig::Fail) | None => {}
+                Some(IndexFailureConfig::Skip) => {
+                    dynamic_context = dynamic_context.on_failure(IndexFailurePolicy::Skip);
+                }
+                Some(IndexFailureConfig::Fallback(name)) => {
+                    let fallback = self
+                        .indexes
+                        .get(name)
+                        .ok_or_else(|| AgentConfigError::UnknownIndex(name.clone()))?;
+                    dynamic_context =
+                        dynamic_context.on_failure(IndexFailurePolicy::fallback(fallback.clone()));
+                }
+            }
+            builder = builder.dynamic_context_source(dynamic_context);
+        }
+        if let Some(top_k) = config.dynamic_context_top_k {
+            builder = builder.dynamic_context_top_k(top_k);
+        }
+        if let Some(temperature) = config.temperature {
+            builder = builder.temperature(temperature);
+        }
+        if let Some(max_tokens) = config.max_tokens {
+            builder = builder.max_tokens(max_tokens);
+        }
+        if let Some(parallel_tool_calls) = config.parallel_tool_calls {
+            builder = builder.parallel_tool_calls(parallel_tool_calls);
+        }
+        if let Some(timeout) = config.timeout_secs {
+            builder = builder.timeout(Duration::from_secs_f64(timeout));
+        }
+        if let Some(params) = &config.additional_params {
+            builder = builder.additional_params(params.clone());
+        }
+
+        Ok(builder.try_build()?)
+    }
+
+    /// Build the agents described by `configs`, by name.
+    pub fn build_all(
+        &self,
+        configs: &HashMap<String, AgentConfig>,
+    ) -> Result<HashMap<String, DynAgent>, AgentConfigError> {
+        configs
+            .iter()
+            .map(|(name, config)| Ok((name.clone(), self.build(config)?.into())))
+            .collect()
+    }
+}
+
+/// A tool of a registry, shared by the agents using it.
+struct SharedTool(Arc<dyn ToolDyn>);
+
+impl ToolDyn for SharedTool {
+    fn name(&self) -> String {
+        self.0.name()
+    }
+
+    fn definition(&self, prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        self.0.definition(prompt)
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        self.0.call(args)
+    }
+}
+
+fn is_auto(tool_choice: &ToolChoice) -> bool {
+    *tool_choice == ToolChoice::Auto
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::{
+            AssistantContent, CompletionError, CompletionRequest, CompletionResponse, Prompt,
+        },
+        OneOrMany,
+    };
+
+    /// Answers with its name and the preamble, temperature and tools of the request
+    #[derive(Clone)]
+    struct NamedModel(&'static str);
+
+    impl CompletionModel for NamedModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let tools = request
+                .tools
+                .iter()
+                .map(|tool| tool.name.clone())
+                .collect::<Vec<_>>();
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!(
+                    "{} {:?} {:?} {:?}",
+                    self.0,
+                    request.preamble.unwrap_or_default(),
+                    request.temperature,
+                    tools
+                ))),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    struct Lookup;
+
+    impl Tool for Lookup {
+        const NAME: &'static str = "lookup";
+
+        type Error = ToolError;
+        type Args = serde_json::Value;
+        type Output = String;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Look up a word".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            }
+        }
+
+        async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(String::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_from_config() {
+        let registry = AgentRegistry::new()
+            .model("a", NamedModel("a"))
+            .model("b", NamedModel("b"))
+            .tool(Lookup);
+
+        let configs: HashMap<String, AgentConfig> = serde_json::from_str(
+            r#"{
+                "control": {"model": "a", "preamble": "Be brief.", "tools": ["lookup"]},
+                "variant": {"model": "b", "temperature": 0.5, "sampling": {"seed": 42}}
+            }"#,
+        )
+        .unwrap();
+        let agents = registry.build_all(&configs).unwrap();
+
+        assert_eq!(
+            agents["control"].prompt("Hi").await.unwrap(),
+            r#"a "Be brief." None ["lookup"]"#
+        );
+        assert_eq!(
+            agents["variant"].prompt("Hi").await.unwrap(),
+            r#"b "" Some(0.5) []"#
+        );
+
+        let config = AgentConfig {
+            model: "c".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            registry.build(&config),
+            Err(AgentConfigError::UnknownModel(model)) if model == "c"
+        ));
+        assert!(matches!(
+            AgentConfig::from_json(r#"{"model": "a", "tool": []}"#),
+            Err(AgentConfigError::JsonError(_))
+        ));
+    }
+}
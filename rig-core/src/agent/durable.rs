@@ -0,0 +1,663 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use crate::{
+    completion::{AssistantContent, CompletionError, CompletionModel, Message},
+    message::{ToolCall, UserContent},
+    tool::{IdempotencyKey, ToolSetError},
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+    OneOrMany,
+};
+
+use super::{
+    audit::audit_tool_call,
+    checkpoint::{Checkpoint, CheckpointError, RunStep},
+    hooks::{denial_output, deny_tool_call},
+    Agent,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DurableError {
+    #[error("CompletionError: {0}")]
+    CompletionError(#[from] CompletionError),
+
+    #[error("ToolCallError: {0}")]
+    ToolError(#[from] ToolSetError),
+
+    #[error("CheckpointError: {0}")]
+    CheckpointError(#[from] CheckpointError),
+
+    /// The checkpoint store has no checkpoint for the run (e.g.: the run completed already)
+    #[error("UnknownRun: {0}")]
+    UnknownRun(String),
+
+    /// The tool calls expected by the completion activity of the run did not run yet
+    #[error("PendingToolCalls: {0}")]
+    PendingToolCalls(String),
+
+    /// The run reached its maximum depth without a final response
+    #[error("MaxDepthError: (reached limit: {max_depth})")]
+    MaxDepthError { max_depth: usize },
+
+    /// Error of the job queue
+    #[error("QueueError: {0}")]
+    QueueError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// A step of a durable run of an agent. The durable runs are split into activities, each running
+/// one completion request or tool call from the checkpoint of the run (see
+/// [AgentBuilder::checkpoint_store](super::AgentBuilder::checkpoint_store)), so that a workflow
+/// engine or a job queue can schedule, retry and distribute them, e.g.: to run multi-hour
+/// workflows surviving the restarts of the workers.
+///
+/// The activities are serializable and retryable:
+/// - The tool calls are claimed in the idempotency store of the agent (see
+///   [AgentBuilder::idempotency_store](super::AgentBuilder::idempotency_store)) before running,
+///   and their outputs are recorded there, so that a retried activity returns the recorded
+///   output instead of calling the tool again, and fails with
+///   [ToolSetError::CallInProgress] while the call is still running on another worker.
+/// - The side-effecting tools run at least once per run, turn and call id, deduplicated by the
+///   store: a worker crashing during a call leaves its claim pending, and the call runs again
+///   once the store expired the claim. Such tools should forward the [IdempotencyKey] of the
+///   call to the APIs they call, by implementing
+///   [Tool::call_idempotent](crate::tool::Tool::call_idempotent).
+/// - A [completion activity](AgentActivity::Completion) expects the checkpoint of the run to be
+///   at its depth: retried after its checkpoint was saved, it returns the activities following
+///   the checkpoint again, and the activities of outdated depths do nothing. It fails with
+///   [DurableError::PendingToolCalls] until the tool calls of the run ran.
+///
+/// [DurableWorker] runs the activities of a [JobQueue] with retries. A workflow engine (e.g.:
+/// Temporal) can instead register [Agent::run_activity] as an activity, and schedule the
+/// activities returned by each activity until the run completes.
+///
+/// # Example
+/// ```rust
+/// use rig::agent::{DurableWorker, FileCheckpointStore, MemoryJobQueue};
+///
+/// let agent = openai.agent(openai::GPT_4O)
+///     .tool(Charge)
+///     .checkpoint_store(FileCheckpointStore::new("checkpoints")?)
+///     .idempotency_store(shared_store)
+///     .build();
+///
+/// let queue = MemoryJobQueue::new();
+/// let worker = DurableWorker::new(&agent, &queue).max_attempts(5);
+/// worker.start("order-42", "Charge the order 42", vec![], 4).await?;
+/// for outcome in worker.run_until_idle().await? {
+///     println!("{outcome:?}");
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "activity", rename_all = "snake_case")]
+pub enum AgentActivity {
+    /// Send the next completion request of the run, with the results of its tool calls, if any,
+    /// after `depth` completion requests
+    Completion {
+        run_id: String,
+        max_depth: usize,
+        depth: usize,
+    },
+    /// Run a tool call of the model, at the turn `turn` of the chat history of the run
+    ToolCall {
+        run_id: String,
+        turn: usize,
+        tool_call: ToolCall,
+    },
+}
+
+impl AgentActivity {
+    pub fn run_id(&self) -> &str {
+        match self {
+            Self::Completion { run_id, .. } | Self::ToolCall { run_id, .. } => run_id,
+        }
+    }
+}
+
+/// Result of an [AgentActivity].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActivityOutcome {
+    /// The activities to run next: the tool calls (which can run concurrently), followed by the
+    /// completion activity expecting their results
+    Next(Vec<AgentActivity>),
+    /// The tool call ran (or had run already), with its output
+    ToolOutput(String),
+    /// The run completed with the final response of the model
+    Completed(String),
+}
+
+fn tool_call_key(run_id: &str, turn: usize, tool_call: &ToolCall) -> IdempotencyKey {
+    let call_id = if tool_call.id.is_empty() {
+        format!(
+            "{}:{}",
+            tool_call.function.name, tool_call.function.arguments
+        )
+    } else {
+        tool_call.id.clone()
+    };
+    IdempotencyKey::derive(run_id, turn, &call_id)
+}
+
+impl<M: CompletionModel> Agent<M> {
+    /// Start the durable run `run_id` of `prompt`, saving its first checkpoint, and return its
+    /// first activity. The run is limited to `max_depth` rounds of tool calls (see
+    /// [PromptRequest::multi_turn](super::PromptRequest::multi_turn)).
+    pub async fn start_run(
+        &self,
+        run_id: &str,
+        prompt: impl Into<Message>,
+        chat_history: Vec<Message>,
+        max_depth: usize,
+    ) -> Result<AgentActivity, DurableError> {
+        let step = RunStep::Prompt {
+            prompt: prompt.into(),
+        };
+        self.checkpoint_store
+            .save(&Checkpoint::new(run_id, 0, &chat_history, step, &[]))
+            .await?;
+        Ok(AgentActivity::Completion {
+            run_id: run_id.to_string(),
+            max_depth,
+            depth: 0,
+        })
+    }
+
+    /// Run `activity`, one step of a durable run (see [AgentActivity]). The activities are
+    /// retryable: running an activity again after a failure (or after a success whose outcome
+    /// was lost) does not repeat the steps that completed.
+    ///
+    /// The restricted tools (see [AgentBuilder::tool_roles](super::AgentBuilder::tool_roles))
+    /// are not available to the durable runs, which have no caller.
+    pub async fn run_activity(
+        &self,
+        activity: &AgentActivity,
+    ) -> Result<ActivityOutcome, DurableError> {
+        match activity {
+            AgentActivity::Completion {
+                run_id,
+                max_depth,
+                depth,
+            } => self.run_completion(run_id, *max_depth, *depth).await,
+            AgentActivity::ToolCall {
+                run_id,
+                turn,
+                tool_call,
+            } => self.run_tool_call(run_id, *turn, tool_call).await,
+        }
+    }
+
+    async fn run_tool_call(
+        &self,
+        run_id: &str,
+        turn: usize,
+        tool_call: &ToolCall,
+    ) -> Result<ActivityOutcome, DurableError> {
+        let key = tool_call_key(run_id, turn, tool_call);
+        if let Some(output) = self.idempotency_store.get(&key).await? {
+            return Ok(ActivityOutcome::ToolOutput(output));
+        }
+        // The run failed (or completed) meanwhile
+        if self.checkpoint_store.load(run_id).await?.is_none() {
+            return Err(DurableError::UnknownRun(run_id.to_string()));
+        }
+
+        if let Some(reason) = deny_tool_call(&self.hooks, tool_call).await {
+            let output = denial_output(&reason);
+            self.idempotency_store.put(&key, &output).await?;
+            return Ok(ActivityOutcome::ToolOutput(output));
+        }
+
+        let toolname = &tool_call.function.name;
+        let args = tool_call.function.arguments.to_string();
+        let started = Instant::now();
+        let result = if self.tool_allowed(toolname, None) {
+            self.tools
+                .call_idempotent(toolname, args.clone(), &key, &*self.idempotency_store)
+                .await
+        } else {
+            Err(ToolSetError::ToolNotFoundError(toolname.clone()))
+        };
+        audit_tool_call(
+            &self.audit_sinks,
+            self.name.as_deref(),
+            None,
+            tool_call,
+            &args,
+            &result,
+            started.elapsed(),
+        )
+        .await;
+        for hook in &self.hooks {
+            hook.on_tool_result(tool_call, &result);
+        }
+        let output = result?;
+        // The outputs of all the tools are recorded, for the completion activity
+        self.idempotency_store.put(&key, &output).await?;
+        Ok(ActivityOutcome::ToolOutput(output))
+    }
+
+    async fn run_completion(
+        &self,
+        run_id: &str,
+        max_depth: usize,
+        expected_depth: usize,
+    ) -> Result<ActivityOutcome, DurableError> {
+        let Checkpoint {
+            depth,
+            mut chat_history,
+            step,
+            ..
+        } = self
+            .checkpoint_store
+            .load(run_id)
+            .await?
+            .ok_or_else(|| DurableError::UnknownRun(run_id.to_string()))?;
+        let turn = chat_history.len().saturating_sub(1);
+
+        let prompt = match step {
+            // The activity completed, but its outcome was lost
+            RunStep::ToolCalls { tool_calls } if depth == expected_depth + 1 => {
+                return Ok(ActivityOutcome::Next(next_activities(
+                    run_id, max_depth, depth, turn, tool_calls,
+                )));
+            }
+            _ if depth != expected_depth => return Ok(ActivityOutcome::Next(vec![])),
+            RunStep::Prompt { prompt } => prompt,
+            RunStep::ToolCalls { tool_calls } => {
+                let mut results = vec![];
+                for tool_call in tool_calls {
+                    let key = tool_call_key(run_id, turn, &tool_call);
+                    let Some(output) = self.idempotency_store.get(&key).await? else {
+                        return Err(DurableError::PendingToolCalls(run_id.to_string()));
+                    };
+                    let output = match &self.tool_output_limits {
+                        Some(limits) => limits.truncate(&tool_call.function.name, output),
+                        None => output,
+                    };
+                    results.push(UserContent::tool_result(
+                        tool_call.id.clone(),
+                        OneOrMany::one(output.into()),
+                    ));
+                }
+                Message::User {
+                    content: OneOrMany::many(results).expect("There is atleast one tool call"),
+                }
+            }
+        };
+
+        // Same number of requests as the multi-turn prompts (see `PromptRequest::send`)
+        if depth > max_depth + 1 {
+            self.checkpoint_store.remove(run_id).await?;
+            return Err(DurableError::MaxDepthError { max_depth });
+        }
+
+        let (response, documents) = self
+            .send_completion(prompt.clone(), &chat_history, None, None, None)
+            .await?;
+
+        chat_history.push(prompt);
+        chat_history.push(Message::Assistant {
+            content: response.choice.clone(),
+        });
+
+        let tool_calls = response
+            .choice
+            .iter()
+            .filter_map(|choice| match choice {
+                AssistantContent::ToolCall(tool_call) => Some(tool_call.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if tool_calls.is_empty() {
+            self.checkpoint_store.remove(run_id).await?;
+            let text = response
+                .choice
+                .iter()
+                .filter_map(|content| match content {
+                    AssistantContent::Text(text) => Some(text.text.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Ok(ActivityOutcome::Completed(text));
+        }
+
+        let turn = chat_history.len() - 1;
+        self.checkpoint_store
+            .save(&Checkpoint::new(
+                run_id,
+                depth + 1,
+                &chat_history,
+                RunStep::ToolCalls {
+                    tool_calls: tool_calls.clone(),
+                },
+                &documents,
+            ))
+            .await?;
+        Ok(ActivityOutcome::Next(next_activities(
+            run_id,
+            max_depth,
+            depth + 1,
+            turn,
+            tool_calls,
+        )))
+    }
+}
+
+/// The activities following the tool calls `tool_calls` of the turn `turn` of a run, after
+/// `depth` completion requests
+fn next_activities(
+    run_id: &str,
+    max_depth: usize,
+    depth: usize,
+    turn: usize,
+    tool_calls: Vec<ToolCall>,
+) -> Vec<AgentActivity> {
+    let mut next = tool_calls
+        .into_iter()
+        .map(|tool_call| AgentActivity::ToolCall {
+            run_id: run_id.to_string(),
+            turn,
+            tool_call,
+        })
+        .collect::<Vec<_>>();
+    next.push(AgentActivity::Completion {
+        run_id: run_id.to_string(),
+        max_depth,
+        depth,
+    });
+    next
+}
+
+/// An [AgentActivity] scheduled in a [JobQueue], with its number of failed attempts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentJob {
+    pub activity: AgentActivity,
+    pub attempt: u32,
+}
+
+/// Queue of the jobs of the durable runs (e.g.: a queue of a message broker, or a table of a
+/// database shared by the workers).
+pub trait JobQueue: WasmCompatSend + WasmCompatSync {
+    fn push(&self, job: AgentJob) -> WasmBoxedFuture<'_, Result<(), DurableError>>;
+
+    /// The next job, if any.
+    fn pop(&self) -> WasmBoxedFuture<'_, Result<Option<AgentJob>, DurableError>>;
+}
+
+/// [JobQueue] keeping the jobs in memory, in FIFO order.
+#[derive(Clone, Default)]
+pub struct MemoryJobQueue {
+    jobs: Arc<Mutex<VecDeque<AgentJob>>>,
+}
+
+impl MemoryJobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.lock().expect("Job queue lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl JobQueue for MemoryJobQueue {
+    fn push(&self, job: AgentJob) -> WasmBoxedFuture<'_, Result<(), DurableError>> {
+        self.jobs
+            .lock()
+            .expect("Job queue lock poisoned")
+            .push_back(job);
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn pop(&self) -> WasmBoxedFuture<'_, Result<Option<AgentJob>, DurableError>> {
+        let job = self
+            .jobs
+            .lock()
+            .expect("Job queue lock poisoned")
+            .pop_front();
+        Box::pin(async move { Ok(job) })
+    }
+}
+
+/// Terminal outcome of a durable run, returned by [DurableWorker::run_until_idle].
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunOutcome {
+    Completed {
+        run_id: String,
+        response: String,
+    },
+    /// An activity of the run failed on all its attempts
+    Failed {
+        run_id: String,
+        error: String,
+    },
+}
+
+/// Default number of attempts of the activities of a [DurableWorker]
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Worker running the jobs of a [JobQueue] with an agent: the activities following each job are
+/// pushed to the queue, and the failed jobs are pushed again until their maximum number of
+/// attempts.
+pub struct DurableWorker<'a, M: CompletionModel> {
+    agent: &'a Agent<M>,
+    queue: &'a dyn JobQueue,
+    max_attempts: u32,
+}
+
+impl<'a, M: CompletionModel> DurableWorker<'a, M> {
+    pub fn new(agent: &'a Agent<M>, queue: &'a dyn JobQueue) -> Self {
+        Self {
+            agent,
+            queue,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    /// Set the maximum number of attempts of each activity (3 by default)
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Start the durable run `run_id` (see [Agent::start_run]), pushing its first job.
+    pub async fn start(
+        &self,
+        run_id: &str,
+        prompt: impl Into<Message>,
+        chat_history: Vec<Message>,
+        max_depth: usize,
+    ) -> Result<(), DurableError> {
+        let activity = self
+            .agent
+            .start_run(run_id, prompt, chat_history, max_depth)
+            .await?;
+        self.queue
+            .push(AgentJob {
+                activity,
+                attempt: 0,
+            })
+            .await
+    }
+
+    /// Run the job `job` (e.g.: received from the queue by another consumer), returning the
+    /// terminal outcome of its run, if any.
+    pub async fn run_job(&self, job: AgentJob) -> Result<Option<RunOutcome>, DurableError> {
+        let run_id = job.activity.run_id().to_string();
+        let outcome = match self.agent.run_activity(&job.activity).await {
+            Ok(ActivityOutcome::Next(activities)) => {
+                for activity in activities {
+                    self.queue
+                        .push(AgentJob {
+                            activity,
+                            attempt: 0,
+                        })
+                        .await?;
+                }
+                None
+            }
+            Ok(ActivityOutcome::ToolOutput(_)) => None,
+            Ok(ActivityOutcome::Completed(response)) => {
+                Some(RunOutcome::Completed { run_id, response })
+            }
+            // The run failed or completed already
+            Err(DurableError::UnknownRun(_)) => None,
+            // Waiting for the tool calls, without counting as an attempt
+            Err(DurableError::PendingToolCalls(_)) => {
+                self.queue.push(job).await?;
+                None
+            }
+            Err(err @ DurableError::MaxDepthError { .. }) => Some(RunOutcome::Failed {
+                run_id,
+                error: err.to_string(),
+            }),
+            Err(err) if job.attempt + 1 < self.max_attempts => {
+                tracing::warn!(target: "rig",
+                    "Retrying the activity of the run {run_id} (attempt {}/{}): {err}",
+                    job.attempt + 2,
+                    self.max_attempts
+                );
+                self.queue
+                    .push(AgentJob {
+                        attempt: job.attempt + 1,
+                        ..job
+                    })
+                    .await?;
+                None
+            }
+            Err(err) => {
+                // The other activities of the run are dropped
+                self.agent.checkpoint_store.remove(&run_id).await?;
+                Some(RunOutcome::Failed {
+                    run_id,
+                    error: err.to_string(),
+                })
+            }
+        };
+        Ok(outcome)
+    }
+
+    /// Run the jobs of the queue until it is empty, returning the terminal outcomes of the runs.
+    pub async fn run_until_idle(&self) -> Result<Vec<RunOutcome>, DurableError> {
+        let mut outcomes = vec![];
+        while let Some(job) = self.queue.pop().await? {
+            outcomes.extend(self.run_job(job).await?);
+        }
+        Ok(outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{CompletionRequest, CompletionResponse},
+        testing::ScriptedTool,
+    };
+
+    fn charge_call() -> ToolCall {
+        match AssistantContent::tool_call("call_1", "charge", serde_json::json!({"amount": 10})) {
+            AssistantContent::ToolCall(tool_call) => tool_call,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Calls the `charge` tool, then answers "done", counting its requests
+    #[derive(Clone, Default)]
+    struct ChargeModel {
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl CompletionModel for ChargeModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let choice = match request.chat_history.iter().last() {
+                Some(Message::User { content })
+                    if matches!(content.first(), UserContent::ToolResult(_)) =>
+                {
+                    AssistantContent::text("done")
+                }
+                _ => AssistantContent::ToolCall(charge_call()),
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_durable_worker() {
+        let model = ChargeModel::default();
+        let charge = ScriptedTool::new("charge")
+            .fails("Payment service unavailable")
+            .returns("charged");
+        let agent = AgentBuilder::new(model.clone())
+            .scripted_tool(charge.clone())
+            .build();
+        let queue = MemoryJobQueue::new();
+        let worker = DurableWorker::new(&agent, &queue);
+
+        worker
+            .start("order-1", "Charge 10", vec![], 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            worker.run_until_idle().await.unwrap(),
+            [RunOutcome::Completed {
+                run_id: "order-1".to_string(),
+                response: "done".to_string()
+            }]
+        );
+        assert!(queue.is_empty());
+        assert_eq!(model.requests.load(Ordering::SeqCst), 2);
+        // The failed attempt, then the successful one
+        assert_eq!(charge.calls().len(), 2);
+
+        // A retried tool call activity returns the recorded output
+        let activity = AgentActivity::ToolCall {
+            run_id: "order-1".to_string(),
+            turn: 1,
+            tool_call: charge_call(),
+        };
+        assert_eq!(
+            agent.run_activity(&activity).await.unwrap(),
+            ActivityOutcome::ToolOutput("\"charged\"".to_string())
+        );
+        assert_eq!(charge.calls().len(), 2);
+
+        // The tool has no result left, failing all the attempts
+        worker
+            .start("order-2", "Charge 10", vec![], 2)
+            .await
+            .unwrap();
+        let outcomes = DurableWorker::new(&agent, &queue)
+            .max_attempts(2)
+            .run_until_idle()
+            .await
+            .unwrap();
+        assert!(matches!(
+            &outcomes[..],
+            [RunOutcome::Failed { run_id, .. }] if run_id == "order-2"
+        ));
+        assert_eq!(charge.calls().len(), 4);
+    }
+}
@@ -0,0 +1,282 @@
+use std::{future::IntoFuture, sync::Arc, time::Duration};
+
+use crate::wasm_compat::WasmBoxedFuture;
+use crate::{
+    cancellation::CancellationToken,
+    completion::{Chat, CompletionModel, Message, Prompt, PromptError},
+};
+
+use super::{Agent, Principal, PromptRequest};
+
+/// Object-safe version of the prompt interface of [Agent], implemented by the agents of all the
+/// completion models.
+pub trait AgentDyn: Send + Sync {
+    /// Send the prompt request (see [PromptRequest]).
+    fn send<'a>(
+        &'a self,
+        request: DynPromptRequest<'a>,
+    ) -> WasmBoxedFuture<'a, Result<String, PromptError>>;
+}
+
+impl<M: CompletionModel> AgentDyn for Agent<M> {
+    fn send<'a>(
+        &'a self,
+        request: DynPromptRequest<'a>,
+    ) -> WasmBoxedFuture<'a, Result<String, PromptError>> {
+        let mut prompt_request =
+            PromptRequest::new(self, request.prompt).multi_turn(request.max_depth);
+        if let Some(chat_history) = request.chat_history {
+            prompt_request = prompt_request.with_history(chat_history);
+        }
+        if let Some(timeout) = request.timeout {
+            prompt_request = prompt_request.timeout(timeout);
+        }
+        if let Some(cancellation) = request.cancellation {
+            prompt_request = prompt_request.cancellation(cancellation);
+        }
+        if let Some(caller) = request.caller {
+            prompt_request = prompt_request.principal(caller);
+        }
+        if let Some(conversation_id) = &request.conversation_id {
+            prompt_request = prompt_request.conversation_id(conversation_id);
+        }
+        if let Some(tenant) = &request.tenant {
+            prompt_request = prompt_request.tenant(tenant);
+        }
+        if let Some(run_id) = &request.run_id {
+            prompt_request = prompt_request.run_id(run_id);
+        }
+        prompt_request.into_future()
+    }
+}
+
+/// A type-erased [Agent], cheap to clone, e.g.: to keep agents backed by different providers in
+/// a `HashMap<String, DynAgent>` and choose them at runtime.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+///
+/// use rig::{agent::DynAgent, completion::Prompt, providers::{anthropic, openai}};
+///
+/// let mut agents = HashMap::new();
+/// agents.insert(
+///     "openai",
+///     DynAgent::new(openai::Client::from_env().agent(openai::GPT_4O).build()),
+/// );
+/// agents.insert(
+///     "anthropic",
+///     DynAgent::new(
+///         anthropic::Client::from_env()
+///             .agent(anthropic::CLAUDE_3_5_SONNET)
+///             .build(),
+///     ),
+/// );
+///
+/// let response = agents["anthropic"].prompt("Hello!").multi_turn(2).await?;
+/// ```
+#[derive(Clone)]
+pub struct DynAgent(Arc<dyn AgentDyn>);
+
+impl DynAgent {
+    pub fn new(agent: impl AgentDyn + 'static) -> Self {
+        Self(Arc::new(agent))
+    }
+}
+
+impl<M: CompletionModel + 'static> From<Agent<M>> for DynAgent {
+    fn from(agent: Agent<M>) -> Self {
+        Self::new(agent)
+    }
+}
+
+/// Prompt request of a [DynAgent], with the same options as [PromptRequest].
+pub struct DynPromptRequest<'a> {
+    prompt: Message,
+    chat_history: Option<&'a mut Vec<Message>>,
+    max_depth: usize,
+    timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+    caller: Option<Principal>,
+    conversation_id: Option<String>,
+    tenant: Option<String>,
+    run_id: Option<String>,
+    agent: &'a dyn AgentDyn,
+}
+
+impl<'a> DynPromptRequest<'a> {
+    /// Create a prompt request of `agent` (e.g.: an [Agent] behind a `&dyn AgentDyn`)
+    pub fn new(agent: &'a dyn AgentDyn, prompt: impl Into<Message>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            chat_history: None,
+            max_depth: 0,
+            timeout: None,
+            cancellation: None,
+            caller: None,
+            conversation_id: None,
+            tenant: None,
+            run_id: None,
+            agent,
+        }
+    }
+
+    /// Set the maximum depth for multi-turn conversations (see [PromptRequest::multi_turn])
+    pub fn multi_turn(self, depth: usize) -> Self {
+        Self {
+            max_depth: depth,
+            ..self
+        }
+    }
+
+    /// Add chat history to the prompt request (see [PromptRequest::with_history])
+    pub fn with_history(self, history: &'a mut Vec<Message>) -> Self {
+        Self {
+            chat_history: Some(history),
+            ..self
+        }
+    }
+
+    /// Set the maximum duration of the whole prompt (see [PromptRequest::timeout])
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Set the token cancelling the prompt (see [PromptRequest::cancellation])
+    pub fn cancellation(self, cancellation: CancellationToken) -> Self {
+        Self {
+            cancellation: Some(cancellation),
+            ..self
+        }
+    }
+
+    /// Set the identity of the caller of the prompt (see [PromptRequest::caller])
+    pub fn caller(self, caller: &str) -> Self {
+        self.principal(Principal::new(caller))
+    }
+
+    /// Set the caller of the prompt with its roles (see [PromptRequest::principal])
+    pub fn principal(self, principal: Principal) -> Self {
+        Self {
+            caller: Some(principal),
+            ..self
+        }
+    }
+
+    /// Set the id of the conversation of the prompt (see [PromptRequest::conversation_id])
+    pub fn conversation_id(self, conversation_id: &str) -> Self {
+        Self {
+            conversation_id: Some(conversation_id.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the tenant of the prompt (see [PromptRequest::tenant])
+    pub fn tenant(self, tenant: &str) -> Self {
+        Self {
+            tenant: Some(tenant.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the id of the run of the prompt (see [PromptRequest::run_id])
+    pub fn run_id(self, run_id: &str) -> Self {
+        Self {
+            run_id: Some(run_id.to_string()),
+            ..self
+        }
+    }
+}
+
+impl<'a> IntoFuture for DynPromptRequest<'a> {
+    type Output = Result<String, PromptError>;
+    type IntoFuture = WasmBoxedFuture<'a, Self::Output>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.agent.send(self)
+    }
+}
+
+#[allow(refining_impl_trait)]
+impl Prompt for DynAgent {
+    fn prompt(&self, prompt: impl Into<Message> + Send) -> DynPromptRequest<'_> {
+        DynPromptRequest::new(&*self.0, prompt)
+    }
+}
+
+#[allow(refining_impl_trait)]
+impl Chat for DynAgent {
+    async fn chat(
+        &self,
+        prompt: impl Into<Message> + Send,
+        mut chat_history: Vec<Message>,
+    ) -> Result<String, PromptError> {
+        self.prompt(prompt).with_history(&mut chat_history).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{AssistantContent, CompletionError, CompletionRequest, CompletionResponse},
+        OneOrMany,
+    };
+
+    /// Answers with its name and the number of messages of the request
+    #[derive(Clone)]
+    struct NamedModel(&'static str);
+
+    impl CompletionModel for NamedModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!(
+                    "{}: {}",
+                    self.0,
+                    request.chat_history.len()
+                ))),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dyn_agents() {
+        let agents = HashMap::from([
+            (
+                "a",
+                DynAgent::from(AgentBuilder::new(NamedModel("a")).build()),
+            ),
+            (
+                "b",
+                DynAgent::new(AgentBuilder::new(NamedModel("b")).build()),
+            ),
+        ]);
+
+        assert_eq!(agents["a"].prompt("Hello").await.unwrap(), "a: 1");
+
+        let mut history = vec![Message::user("Hi"), Message::assistant("Hello")];
+        let response = agents["b"]
+            .prompt("How are you?")
+            .with_history(&mut history)
+            .await
+            .unwrap();
+        assert_eq!(response, "b: 3");
+        assert_eq!(history.len(), 4);
+
+        let response = agents["b"].chat("Bye", vec![]).await.unwrap();
+        assert_eq!(response, "b: 1");
+    }
+}
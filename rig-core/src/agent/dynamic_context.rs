@@ -0,0 +1,286 @@
+use std::sync::Arc;
+
+use crate::vector_store::{MetadataFilter, TopNResults, VectorStoreIndexDyn};
+
+use super::DocumentFormatter;
+
+/// Behaviour of an agent when the query of one of its dynamic context indexes fails (e.g.: the
+/// vector store is unavailable).
+#[derive(Default)]
+pub enum IndexFailurePolicy {
+    /// Fail the request of the agent
+    #[default]
+    Fail,
+    /// Skip the documents of the index, with a warning
+    Skip,
+    /// Query another index instead, with a warning (e.g.: a replica, or a local snapshot of the
+    /// store). The request fails if the fallback index fails too.
+    Fallback(Box<dyn VectorStoreIndexDyn>),
+}
+
+impl IndexFailurePolicy {
+    pub fn fallback(index: impl VectorStoreIndexDyn + 'static) -> Self {
+        Self::Fallback(Box::new(index))
+    }
+}
+
+/// Options of a dynamic context index of an agent, stored with the index in
+/// [Agent::dynamic_context](super::Agent::dynamic_context) (see [DynamicContextSource]).
+#[derive(Default)]
+pub struct DynamicContextSettings {
+    pub(super) formatter: Option<Arc<dyn DocumentFormatter>>,
+    pub(super) weight: Option<f64>,
+    pub(super) on_failure: IndexFailurePolicy,
+}
+
+/// A dynamic context index of an agent with its options, added with
+/// [AgentBuilder::dynamic_context_source](super::AgentBuilder::dynamic_context_source).
+///
+/// # Example
+/// ```rust
+/// use rig::agent::{DocumentTemplate, DynamicContextSource, IndexFailurePolicy};
+///
+/// let agent = openai.agent(openai::GPT_4O)
+///     .dynamic_context_source(
+///         DynamicContextSource::new(5, docs_index)
+///             .formatter(DocumentTemplate::new("{title} — {snippet}"))
+///             .on_failure(IndexFailurePolicy::fallback(docs_snapshot)),
+///     )
+///     .dynamic_context_source(
+///         DynamicContextSource::new(5, forum_index)
+///             .weight(0.5)
+///             .on_failure(IndexFailurePolicy::Skip),
+///     )
+///     .dynamic_context_top_k(6)
+///     .build();
+/// ```
+pub struct DynamicContextSource {
+    pub(super) sample: usize,
+    pub(super) index: Box<dyn VectorStoreIndexDyn>,
+    pub(super) settings: DynamicContextSettings,
+}
+
+impl DynamicContextSource {
+    /// Source inserting `sample` documents of `index` in each request
+    pub fn new(sample: usize, index: impl VectorStoreIndexDyn + 'static) -> Self {
+        Self {
+            sample,
+            index: Box::new(index),
+            settings: DynamicContextSettings::default(),
+        }
+    }
+
+    /// Render the documents with `formatter` (e.g.: a closure or a
+    /// [DocumentTemplate](super::DocumentTemplate)) instead of pretty-printing them as JSON
+    pub fn formatter(mut self, formatter: impl DocumentFormatter + 'static) -> Self {
+        self.settings.formatter = Some(Arc::new(formatter));
+        self
+    }
+
+    /// Multiply the scores of the documents by `weight` when ranking the documents of all the
+    /// dynamic context sources (see
+    /// [AgentBuilder::weighted_dynamic_context](super::AgentBuilder::weighted_dynamic_context))
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.settings.weight = Some(weight);
+        self
+    }
+
+    /// Set the behaviour of the agent when the index fails (default: fail the request)
+    pub fn on_failure(mut self, policy: IndexFailurePolicy) -> Self {
+        self.settings.on_failure = policy;
+        self
+    }
+}
+
+async fn top_n(
+    index: &dyn VectorStoreIndexDyn,
+    query: &str,
+    n: usize,
+    filter: Option<&MetadataFilter>,
+) -> TopNResults {
+    match filter {
+        Some(filter) => index.top_n_filtered(query, n, filter).await,
+        None => index.top_n(query, n).await,
+    }
+}
+
+/// Query the dynamic context index at `position` of the agent, applying the failure policy of
+/// the index.
+pub(super) async fn query_index(
+    index: &dyn VectorStoreIndexDyn,
+    policy: &IndexFailurePolicy,
+    position: usize,
+    query: &str,
+    n: usize,
+    filter: Option<&MetadataFilter>,
+) -> TopNResults {
+    let err = match top_n(index, query, n, filter).await {
+        Ok(results) => return Ok(results),
+        Err(err) => err,
+    };
+    match policy {
+        IndexFailurePolicy::Fail => Err(err),
+        IndexFailurePolicy::Skip => {
+            tracing::warn!(target: "rig",
+                "Skipping the dynamic context index {position}, which failed: {err}"
+            );
+            Ok(vec![])
+        }
+        IndexFailurePolicy::Fallback(fallback) => {
+            tracing::warn!(target: "rig",
+                "Querying the fallback of the dynamic context index {position}, which failed: {err}"
+            );
+            top_n(&**fallback, query, n, filter).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, Completion, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse,
+        },
+        vector_store::{tests::TenantIndex, VectorStoreError, VectorStoreIndex},
+        OneOrMany,
+    };
+
+    #[derive(Clone)]
+    struct MockModel;
+
+    impl CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text("Hi")),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    struct UnavailableIndex;
+
+    impl VectorStoreIndex for UnavailableIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            Err(VectorStoreError::DatastoreError(
+                "connection refused".into(),
+            ))
+        }
+
+        async fn top_n_ids(
+            &self,
+            _query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Err(VectorStoreError::DatastoreError(
+                "connection refused".into(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_failure_policies() {
+        let document_ids = |policy| async move {
+            let agent = AgentBuilder::new(MockModel)
+                .dynamic_context(1, TenantIndex)
+                .dynamic_context_source(
+                    DynamicContextSource::new(2, UnavailableIndex).on_failure(policy),
+                )
+                .build();
+            let request = agent.completion("Hi", vec![]).await?.build();
+            Ok::<_, CompletionError>(
+                request
+                    .documents
+                    .into_iter()
+                    .map(|doc| doc.id)
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        assert!(matches!(
+            document_ids(IndexFailurePolicy::Fail).await,
+            Err(CompletionError::RequestError(_))
+        ));
+        assert_eq!(document_ids(IndexFailurePolicy::Skip).await.unwrap(), ["0"]);
+        assert_eq!(
+            document_ids(IndexFailurePolicy::fallback(TenantIndex))
+                .await
+                .unwrap(),
+            ["0", "0", "1"]
+        );
+        assert!(document_ids(IndexFailurePolicy::fallback(UnavailableIndex))
+            .await
+            .is_err());
+    }
+
+    /// Index recording the maximum number of its concurrent queries
+    #[derive(Clone, Default)]
+    struct SlowIndex {
+        running: Arc<std::sync::atomic::AtomicUsize>,
+        max_running: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl VectorStoreIndex for SlowIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            use std::sync::atomic::Ordering;
+
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            VectorStoreIndex::top_n(&TenantIndex, query, n).await
+        }
+
+        async fn top_n_ids(
+            &self,
+            query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            VectorStoreIndex::top_n_ids(&TenantIndex, query, n).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_queries() {
+        for (concurrency, expected) in [(4, 3), (1, 1)] {
+            let index = SlowIndex::default();
+            let agent = AgentBuilder::new(MockModel)
+                .dynamic_context(1, index.clone())
+                .dynamic_context(2, index.clone())
+                .dynamic_context(3, index.clone())
+                .dynamic_query_concurrency(concurrency)
+                .build();
+            let request = agent.completion("Hi", vec![]).await.unwrap().build();
+
+            // The documents are in the order of their indexes
+            let ids = request
+                .documents
+                .into_iter()
+                .map(|doc| doc.id)
+                .collect::<Vec<_>>();
+            assert_eq!(ids, ["0", "0", "1", "0", "1", "2"]);
+            assert_eq!(
+                index.max_running.load(std::sync::atomic::Ordering::SeqCst),
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::future::IntoFuture;
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+
+use crate::completion::{Message, Prompt, PromptError};
+
+use super::DynAgent;
+
+/// Preamble of the prompts of the critique round of an [Ensemble].
+pub const CRITIQUE_PROMPT: &str = "Other models answered the same question. Critique their \
+    answers and yours, then give your revised answer. Only output the revised answer.";
+
+/// Preamble of the prompt of the synthesizer of an [Ensemble].
+pub const SYNTHESIS_PROMPT: &str = "Several models answered the following question. Synthesize \
+    their answers into the best final answer, resolving their disagreements. Only output the \
+    final answer.";
+
+/// Answer of a member of an [Ensemble].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MemberAnswer {
+    pub member: String,
+    /// The answer of the member to the prompt
+    pub answer: String,
+    /// The answer revised after the critique round, if any
+    pub revision: Option<String>,
+}
+
+impl MemberAnswer {
+    /// The latest answer of the member (i.e.: its revision, if any).
+    pub fn latest(&self) -> &str {
+        self.revision.as_deref().unwrap_or(&self.answer)
+    }
+}
+
+/// Response of an [Ensemble], with the answers of its members for audit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnsembleResponse {
+    /// The final answer of the synthesizer
+    pub response: String,
+    /// The answers of the members which answered, in the order of the members
+    pub answers: Vec<MemberAnswer>,
+    /// The members which failed, with their error
+    pub failures: Vec<(String, String)>,
+}
+
+/// Agents (e.g.: backed by different models) answering the same prompt concurrently, whose
+/// answers are synthesized into a final answer by a synthesizer agent. With
+/// [Ensemble::critique], the members first critique the answers of the others and revise
+/// their own answer.
+///
+/// The failing members are reported in the response, and left out of the later rounds: the
+/// ensemble only fails if all the members fail (or if the synthesizer fails).
+///
+/// # Example
+/// ```rust
+/// use rig::agent::Ensemble;
+///
+/// let ensemble = Ensemble::new(openai.agent(openai::GPT_4O).build())
+///     .member("gpt-4o", openai.agent(openai::GPT_4O).build())
+///     .member("claude", anthropic.agent(anthropic::CLAUDE_3_5_SONNET).build())
+///     .member("gemini", gemini.agent(gemini::GEMINI_1_5_PRO).build())
+///     .critique(true);
+///
+/// let response = ensemble.prompt("Is P equal to NP?").await?;
+/// for answer in &response.answers {
+///     println!("{}: {}", answer.member, answer.latest());
+/// }
+/// println!("Final answer: {}", response.response);
+/// ```
+#[derive(Clone)]
+pub struct Ensemble {
+    synthesizer: DynAgent,
+    members: Vec<(String, DynAgent)>,
+    critique: bool,
+}
+
+impl Ensemble {
+    /// Ensemble whose answers are synthesized by `synthesizer`.
+    pub fn new(synthesizer: impl Into<DynAgent>) -> Self {
+        Self {
+            synthesizer: synthesizer.into(),
+            members: vec![],
+            critique: false,
+        }
+    }
+
+    /// Add the member `name`, answering the prompts.
+    pub fn member(mut self, name: &str, agent: impl Into<DynAgent>) -> Self {
+        self.members.push((name.to_string(), agent.into()));
+        self
+    }
+
+    /// Run a critique round before the synthesis (default: no), in which every member reads
+    /// the answers of the others and revises its own answer.
+    pub fn critique(mut self, critique: bool) -> Self {
+        self.critique = critique;
+        self
+    }
+
+    /// Send `prompt` to the members, then synthesize their answers.
+    pub async fn prompt(
+        &self,
+        prompt: impl Into<Message> + Send,
+    ) -> Result<EnsembleResponse, PromptError> {
+        let prompt = prompt.into();
+        let question = prompt.rag_text().unwrap_or_default();
+
+        let results = join_all(
+            self.members
+                .iter()
+                .map(|(_, agent)| agent.prompt(prompt.clone()).into_future()),
+        )
+        .await;
+        let mut answers = vec![];
+        let mut failures = vec![];
+        for ((member, _), result) in self.members.iter().zip(results) {
+            match result {
+                Ok(answer) => answers.push(MemberAnswer {
+                    member: member.clone(),
+                    answer,
+                    revision: None,
+                }),
+                Err(err) => {
+                    tracing::warn!(target: "rig", "Ensemble member {member} failed: {err}");
+                    failures.push((member.clone(), err.to_string()));
+                }
+            }
+        }
+        if answers.is_empty() {
+            return Err(PromptError::EnsembleError(
+                "All the members of the ensemble failed".to_string(),
+            ));
+        }
+
+        if self.critique && answers.len() > 1 {
+            let revisions = join_all(answers.iter().map(|answer| {
+                let (_, agent) = self
+                    .members
+                    .iter()
+                    .find(|(member, _)| *member == answer.member)
+                    .expect("The answers are given by the members");
+                let others =
+                    format_answers(answers.iter().filter(|other| other.member != answer.member));
+                agent
+                    .prompt(format!(
+                        "{CRITIQUE_PROMPT}\n\nQuestion:\n{question}\n\nYour answer:\n{}\n\n\
+                        Their answers:\n{others}",
+                        answer.answer
+                    ))
+                    .into_future()
+            }))
+            .await;
+            for (answer, revision) in answers.iter_mut().zip(revisions) {
+                match revision {
+                    Ok(revision) => answer.revision = Some(revision),
+                    // The member keeps its first answer
+                    Err(err) => tracing::warn!(target: "rig",
+                        "Ensemble member {} failed to revise its answer: {err}", answer.member
+                    ),
+                }
+            }
+        }
+
+        let response = self
+            .synthesizer
+            .prompt(format!(
+                "{SYNTHESIS_PROMPT}\n\nQuestion:\n{question}\n\nAnswers:\n{}",
+                format_answers(answers.iter())
+            ))
+            .await?;
+        Ok(EnsembleResponse {
+            response,
+            answers,
+            failures,
+        })
+    }
+}
+
+fn format_answers<'a>(answers: impl Iterator<Item = &'a MemberAnswer>) -> String {
+    answers
+        .map(|answer| {
+            format!(
+                "<answer model=\"{}\">\n{}\n</answer>",
+                answer.member,
+                answer.latest()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse,
+        },
+        OneOrMany,
+    };
+
+    /// Answers `answer`, or `revision` in the critique round, or fails if `answer` is empty. The
+    /// synthesizer echoes its prompt.
+    #[derive(Clone)]
+    struct MemberModel {
+        answer: &'static str,
+        revision: &'static str,
+    }
+
+    impl CompletionModel for MemberModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let prompt = request
+                .chat_history
+                .iter()
+                .last()
+                .and_then(Message::rag_text)
+                .unwrap_or_default();
+            let text = if prompt.starts_with(SYNTHESIS_PROMPT) {
+                prompt
+            } else if prompt.starts_with(CRITIQUE_PROMPT) {
+                self.revision.to_string()
+            } else if self.answer.is_empty() {
+                return Err(CompletionError::ProviderError("overloaded".to_string()));
+            } else {
+                self.answer.to_string()
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(text)),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    fn agent(answer: &'static str, revision: &'static str) -> DynAgent {
+        AgentBuilder::new(MemberModel { answer, revision })
+            .build()
+            .into()
+    }
+
+    #[tokio::test]
+    async fn test_ensemble() {
+        let ensemble = Ensemble::new(agent("", ""))
+            .member("a", agent("4", "4"))
+            .member("b", agent("5", "4, I miscounted"))
+            .member("c", agent("", ""))
+            .critique(true);
+
+        let response = ensemble.prompt("2 + 2?").await.unwrap();
+        assert_eq!(response.answers.len(), 2);
+        assert_eq!(response.answers[1].answer, "5");
+        assert_eq!(response.answers[1].latest(), "4, I miscounted");
+        assert_eq!(response.failures[0].0, "c");
+        assert!(response.response.contains("Question:\n2 + 2?"));
+        assert!(response
+            .response
+            .contains("<answer model=\"b\">\n4, I miscounted\n</answer>"));
+
+        let ensemble = Ensemble::new(agent("", "")).member("c", agent("", ""));
+        assert!(matches!(
+            ensemble.prompt("2 + 2?").await,
+            Err(PromptError::EnsembleError(_))
+        ));
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::completion::{Message, Prompt, PromptError};
+
+use super::{AgentConfig, AgentConfigError, AgentRegistry, DynAgent};
+
+/// Response of an [Experiment], tagged with the version of the agent which produced it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VersionedResponse {
+    pub experiment: String,
+    pub version: String,
+    pub response: String,
+}
+
+/// Serializable configuration of an [Experiment] (see [AgentRegistry::build_experiment]).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentConfig {
+    pub name: String,
+    pub versions: Vec<VersionConfig>,
+}
+
+/// A version of the agent of an [ExperimentConfig].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VersionConfig {
+    pub version: String,
+    /// Share of the traffic of the version, relative to the weights of the other versions
+    pub weight: u32,
+    pub agent: AgentConfig,
+}
+
+/// Several versions of an agent (e.g.: with different preambles or parameters) between which
+/// the traffic is split according to their weights. The responses are tagged with the version
+/// which produced them, for offline analysis.
+///
+/// The requests with an assignment key (e.g.: a user or session ID) are always assigned to the
+/// same version for the same key, the other requests are assigned at random.
+///
+/// # Example
+/// ```
+/// use rig::{agent::Experiment, providers::openai};
+///
+/// let openai = openai::Client::from_env();
+///
+/// // 90% of the traffic on the current preamble, 10% on the candidate
+/// let experiment = Experiment::new("support-preamble")
+///     .version("v1", 90, openai.agent(openai::GPT_4O).preamble("You are a support agent.").build())
+///     .version("v2", 10, openai.agent(openai::GPT_4O).preamble("You are a friendly support agent.").build());
+///
+/// let response = experiment.prompt_for("user-42", "How do I reset my password?").await?;
+/// println!("{} answered: {}", response.version, response.response);
+/// ```
+pub struct Experiment {
+    name: String,
+    versions: Vec<(String, u32, DynAgent)>,
+    random: RandomState,
+    counter: AtomicU64,
+}
+
+impl Experiment {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            versions: vec![],
+            random: RandomState::new(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Add a version of the agent, receiving a share `weight` of the traffic (relative to the
+    /// weights of the other versions).
+    pub fn version(mut self, version: &str, weight: u32, agent: impl Into<DynAgent>) -> Self {
+        self.versions
+            .push((version.to_string(), weight, agent.into()));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The version assigned to the requests with the assignment key `key`, if any.
+    pub fn version_for(&self, key: &str) -> Option<&str> {
+        let digest = Sha256::digest(format!("{}:{}", self.name, key).as_bytes());
+        let hash = u64::from_be_bytes(digest[..8].try_into().expect("The digest has 32 bytes"));
+        self.pick(hash).map(|(version, _)| version)
+    }
+
+    /// Send `prompt` to the version assigned to `key`.
+    pub async fn prompt_for(
+        &self,
+        key: &str,
+        prompt: impl Into<Message> + Send,
+    ) -> Result<VersionedResponse, PromptError> {
+        let version = self.version_for(key).unwrap_or_default().to_string();
+        self.send(&version, prompt.into()).await
+    }
+
+    /// Send `prompt` to a version picked at random.
+    pub async fn prompt(
+        &self,
+        prompt: impl Into<Message> + Send,
+    ) -> Result<VersionedResponse, PromptError> {
+        let hash = self
+            .random
+            .hash_one(self.counter.fetch_add(1, Ordering::Relaxed));
+        let version = self
+            .pick(hash)
+            .map(|(version, _)| version.to_string())
+            .unwrap_or_default();
+        self.send(&version, prompt.into()).await
+    }
+
+    /// Pick the version of the bucket `hash` (the versions own consecutive ranges of buckets
+    /// proportional to their weights).
+    fn pick(&self, hash: u64) -> Option<(&str, &DynAgent)> {
+        let total = self
+            .versions
+            .iter()
+            .map(|(_, weight, _)| *weight as u64)
+            .sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut bucket = hash % total;
+        self.versions.iter().find_map(|(version, weight, agent)| {
+            if bucket < *weight as u64 {
+                Some((version.as_str(), agent))
+            } else {
+                bucket -= *weight as u64;
+                None
+            }
+        })
+    }
+
+    async fn send(&self, version: &str, prompt: Message) -> Result<VersionedResponse, PromptError> {
+        let agent = self
+            .versions
+            .iter()
+            .find(|(name, _, _)| name == version)
+            .map(|(_, _, agent)| agent)
+            .ok_or_else(|| {
+                PromptError::ExperimentError(format!(
+                    "Experiment {} has no version with traffic",
+                    self.name
+                ))
+            })?;
+
+        tracing::info!(target: "rig",
+            "Experiment {}: assigned version {}", self.name, version
+        );
+        let response = agent.prompt(prompt).await?;
+
+        Ok(VersionedResponse {
+            experiment: self.name.clone(),
+            version: version.to_string(),
+            response,
+        })
+    }
+}
+
+impl AgentRegistry {
+    /// Build the experiment described by `config`.
+    pub fn build_experiment(
+        &self,
+        config: &ExperimentConfig,
+    ) -> Result<Experiment, AgentConfigError> {
+        config
+            .versions
+            .iter()
+            .try_fold(Experiment::new(&config.name), |experiment, version| {
+                Ok(experiment.version(
+                    &version.version,
+                    version.weight,
+                    self.build(&version.agent)?,
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse,
+        },
+        OneOrMany,
+    };
+
+    /// Answers with the preamble of the request
+    #[derive(Clone)]
+    struct PreambleModel;
+
+    impl CompletionModel for PreambleModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(
+                    request.preamble.unwrap_or_default(),
+                )),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_experiment_split() {
+        let experiment = Experiment::new("preamble")
+            .version(
+                "a",
+                75,
+                AgentBuilder::new(PreambleModel).preamble("A").build(),
+            )
+            .version(
+                "b",
+                25,
+                AgentBuilder::new(PreambleModel).preamble("B").build(),
+            )
+            .version("off", 0, AgentBuilder::new(PreambleModel).build());
+
+        // Sticky assignment
+        let version = experiment.version_for("user-1").unwrap().to_string();
+        for _ in 0..3 {
+            let response = experiment.prompt_for("user-1", "Hi").await.unwrap();
+            assert_eq!(response.version, version);
+            assert_eq!(response.response, version.to_uppercase());
+            assert_eq!(response.experiment, "preamble");
+        }
+
+        let assigned = (0..1000)
+            .map(|user| experiment.version_for(&format!("user-{user}")).unwrap())
+            .collect::<Vec<_>>();
+        let a = assigned.iter().filter(|version| **version == "a").count();
+        assert!((650..850).contains(&a), "{a}");
+        assert!(!assigned.contains(&"off"));
+
+        let response = experiment.prompt("Hi").await.unwrap();
+        assert!(response.version == "a" || response.version == "b");
+
+        let empty = Experiment::new("empty");
+        assert_eq!(empty.version_for("user-1"), None);
+        assert!(matches!(
+            empty.prompt("Hi").await,
+            Err(PromptError::ExperimentError(_))
+        ));
+    }
+}
@@ -0,0 +1,116 @@
+use serde_json::Value;
+
+/// Rendering of the documents of a dynamic context index in the requests of an agent (see
+/// [AgentBuilder::formatted_dynamic_context](super::AgentBuilder::formatted_dynamic_context)),
+/// instead of their pretty-printed JSON, e.g.: to render them compactly and save tokens.
+///
+/// Closures taking the id and the document implement the trait, and [DocumentTemplate] renders
+/// the documents from a template.
+pub trait DocumentFormatter: Send + Sync {
+    /// Render the document `document` of id `id`.
+    fn format(&self, id: &str, document: &Value) -> String;
+}
+
+impl<F> DocumentFormatter for F
+where
+    F: Fn(&str, &Value) -> String + Send + Sync,
+{
+    fn format(&self, id: &str, document: &Value) -> String {
+        self(id, document)
+    }
+}
+
+/// [DocumentFormatter] rendering the documents from a template, whose placeholders `{field}`
+/// are replaced by the fields of the documents. A placeholder is either the name of a top-level
+/// field, or a JSON pointer to a nested field (e.g.: `{/metadata/url}`), and `{id}` is replaced
+/// by the id of the document, unless the document has an `id` field.
+///
+/// The string fields are inserted as is, the other fields as JSON, and the missing fields are
+/// replaced by an empty string. `{{` and `}}` are rendered as `{` and `}`.
+///
+/// # Example
+/// ```rust
+/// use rig::agent::DocumentTemplate;
+///
+/// let agent = openai.agent(openai::GPT_4O)
+///     .formatted_dynamic_context(5, index, DocumentTemplate::new("{title} — {snippet}"))
+///     .build();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentTemplate {
+    template: String,
+}
+
+impl DocumentTemplate {
+    pub fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+        }
+    }
+}
+
+impl DocumentFormatter for DocumentTemplate {
+    fn format(&self, id: &str, document: &Value) -> String {
+        let mut output = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find(['{', '}']) {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                output.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            let end = match rest.find('}') {
+                Some(end) if rest.starts_with('{') => end,
+                // Unbalanced brace, rendered as is
+                _ => {
+                    output.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+            let field = &rest[1..end];
+            let value = if field.starts_with('/') {
+                document.pointer(field)
+            } else {
+                document.get(field)
+            };
+            match value {
+                Some(Value::String(value)) => output.push_str(value),
+                Some(value) => output.push_str(&value.to_string()),
+                None if field == "id" => output.push_str(id),
+                None => {}
+            }
+            rest = &rest[end + 1..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_template() {
+        let document = serde_json::json!({
+            "title": "Rust",
+            "snippet": "A language empowering everyone",
+            "metadata": {"year": 2015},
+        });
+
+        let template = DocumentTemplate::new("[{id}] {title} — {snippet} ({/metadata/year})");
+        assert_eq!(
+            template.format("doc1", &document),
+            "[doc1] Rust — A language empowering everyone (2015)"
+        );
+
+        let template = DocumentTemplate::new("{{{title}}} {missing}} {");
+        assert_eq!(template.format("doc1", &document), "{Rust} } {");
+
+        let formatter = |id: &str, document: &Value| format!("{id}: {}", document["title"]);
+        assert_eq!(formatter.format("doc1", &document), r#"doc1: "Rust""#);
+    }
+}
@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    completion::{AssistantContent, Message, Prompt, PromptError, ToolDefinition},
+    message::UserContent,
+    tool::Tool,
+};
+
+use super::DynAgent;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HandoffError {
+    #[error("PromptError: {0}")]
+    PromptError(#[from] PromptError),
+
+    /// The handoff targets an agent unknown to the orchestrator (or to the handoff tool)
+    #[error("UnknownAgent: {0}")]
+    UnknownAgent(String),
+
+    /// The agents handed the conversation off more times than allowed
+    #[error("MaxHandoffsError: the agents handed the conversation off more than {0} times")]
+    MaxHandoffsError(usize),
+}
+
+/// Transfer of a conversation from an agent to another one, emitted by the agents with the
+/// [HandoffTool] and carried out by a [HandoffOrchestrator].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Handoff {
+    /// Name of the agent taking over the conversation
+    pub target: String,
+    /// Why the conversation is handed off
+    pub reason: String,
+    /// What the target agent needs to know (e.g.: a summary of the conversation so far)
+    pub context: String,
+}
+
+impl Handoff {
+    /// Prompt of the target agent, from the agent `source`.
+    pub fn prompt(&self, source: &str) -> String {
+        format!(
+            "The agent {source} handed the conversation off to you.\nReason: {}\nContext: {}",
+            self.reason, self.context
+        )
+    }
+}
+
+/// Part of the conversation carried over to the target of a handoff, along with the
+/// [Handoff] itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryCarryOver {
+    /// The whole conversation
+    #[default]
+    All,
+    /// The latest messages of the conversation
+    Last(usize),
+    /// Nothing: the target agent only receives the context of the handoff
+    None,
+}
+
+impl HistoryCarryOver {
+    fn select(&self, chat_history: &[Message]) -> Vec<Message> {
+        let mut start = match self {
+            Self::All => 0,
+            Self::Last(count) => chat_history.len().saturating_sub(*count),
+            Self::None => chat_history.len(),
+        };
+        // The carried over history does not start with the results of truncated tool calls
+        while chat_history.get(start).is_some_and(is_tool_result) {
+            start += 1;
+        }
+        chat_history[start..].to_vec()
+    }
+}
+
+fn is_tool_result(message: &Message) -> bool {
+    matches!(
+        message,
+        Message::User { content } if matches!(content.first(), UserContent::ToolResult(_))
+    )
+}
+
+/// Tool handing the conversation off to another agent, named `handoff`, with the [Handoff] as
+/// its arguments. The tool only acknowledges the handoff: the [HandoffOrchestrator] running the
+/// agent transfers the conversation once the agent has answered.
+#[derive(Clone, Debug, Default)]
+pub struct HandoffTool {
+    /// Names and descriptions of the agents the conversation can be handed off to
+    targets: Vec<(String, String)>,
+}
+
+impl HandoffTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow handing the conversation off to the agent `name`, with the description of its
+    /// competences given to the model.
+    pub fn target(mut self, name: &str, description: &str) -> Self {
+        self.targets
+            .push((name.to_string(), description.to_string()));
+        self
+    }
+}
+
+impl Tool for HandoffTool {
+    const NAME: &'static str = "handoff";
+
+    type Error = HandoffError;
+    type Args = Handoff;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let targets = self
+            .targets
+            .iter()
+            .map(|(name, description)| format!("- {name}: {description}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Hand the conversation off to another agent, better suited to it. The agents \
+                are:\n{targets}"
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "target": {
+                        "type": "string",
+                        "enum": self.targets.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                        "description": "The agent taking over the conversation"
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "Why the conversation is handed off"
+                    },
+                    "context": {
+                        "type": "string",
+                        "description": "What the agent needs to know to take over, e.g.: a summary of the conversation"
+                    }
+                },
+                "required": ["target", "reason", "context"]
+            }),
+        }
+    }
+
+    async fn call(&self, handoff: Self::Args) -> Result<Self::Output, Self::Error> {
+        if !self.targets.iter().any(|(name, _)| *name == handoff.target) {
+            return Err(HandoffError::UnknownAgent(handoff.target));
+        }
+        Ok(format!(
+            "The conversation is handed off to {}. Tell the user in one sentence.",
+            handoff.target
+        ))
+    }
+
+    fn static_definition(&self) -> bool {
+        true
+    }
+}
+
+/// Response of a [HandoffOrchestrator].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HandoffResponse {
+    /// Name of the agent which gave the response
+    pub agent: String,
+    pub response: String,
+    /// The handoffs of the conversation, in order
+    pub handoffs: Vec<Handoff>,
+}
+
+/// Agents handing a conversation off to each other with the [HandoffTool]. When an agent calls
+/// the tool, the orchestrator prompts the target agent with the [Handoff] (see
+/// [Handoff::prompt]) once the agent has answered, carrying over the selected part of the
+/// conversation (see [HistoryCarryOver]).
+///
+/// The orchestrator keeps no state between the runs: the handoffs are read from the tool calls
+/// of the conversation.
+///
+/// # Example
+/// ```rust
+/// use rig::agent::{HandoffOrchestrator, HandoffTool, HistoryCarryOver};
+///
+/// let triage = openai.agent(openai::GPT_4O)
+///     .preamble("You are the first contact of the customers.")
+///     .tool(HandoffTool::new().target("billing", "Invoices, payments and refunds"))
+///     .build();
+/// let billing = openai.agent(openai::GPT_4O)
+///     .preamble("You are the billing team.")
+///     .tool(refund)
+///     .build();
+///
+/// let orchestrator = HandoffOrchestrator::new()
+///     .agent("triage", triage)
+///     .agent("billing", billing)
+///     .carry_over(HistoryCarryOver::Last(6));
+///
+/// let mut chat_history = vec![];
+/// let response = orchestrator
+///     .run("triage", "I was charged twice this month", &mut chat_history)
+///     .await?;
+/// println!("{}: {}", response.agent, response.response);
+/// ```
+#[derive(Clone)]
+pub struct HandoffOrchestrator {
+    agents: HashMap<String, DynAgent>,
+    carry_over: HistoryCarryOver,
+    max_handoffs: usize,
+    max_depth: usize,
+}
+
+impl Default for HandoffOrchestrator {
+    fn default() -> Self {
+        Self {
+            agents: HashMap::new(),
+            carry_over: HistoryCarryOver::default(),
+            max_handoffs: 5,
+            max_depth: 3,
+        }
+    }
+}
+
+impl HandoffOrchestrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the agent `name`, to which the conversations can be handed off.
+    pub fn agent(mut self, name: &str, agent: impl Into<DynAgent>) -> Self {
+        self.agents.insert(name.to_string(), agent.into());
+        self
+    }
+
+    /// Set the part of the conversation carried over to the target agents (default: all).
+    pub fn carry_over(mut self, carry_over: HistoryCarryOver) -> Self {
+        self.carry_over = carry_over;
+        self
+    }
+
+    /// Set the maximum number of handoffs of a run (default: 5), e.g.: to stop agents handing
+    /// the conversation off back and forth.
+    pub fn max_handoffs(mut self, max_handoffs: usize) -> Self {
+        self.max_handoffs = max_handoffs;
+        self
+    }
+
+    /// Set the maximum depth of the multi-turn prompts of the agents (default: 3, see
+    /// [PromptRequest::multi_turn](super::PromptRequest::multi_turn)).
+    pub fn multi_turn(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Prompt the agent `agent`, then the targets of its handoffs until an agent answers
+    /// without handing off. The messages of all the agents are appended to `chat_history`.
+    pub async fn run(
+        &self,
+        agent: &str,
+        prompt: impl Into<Message>,
+        chat_history: &mut Vec<Message>,
+    ) -> Result<HandoffResponse, HandoffError> {
+        let mut current = agent.to_string();
+        let mut prompt = prompt.into();
+        let mut handoffs = vec![];
+        loop {
+            let agent = self
+                .agents
+                .get(&current)
+                .ok_or_else(|| HandoffError::UnknownAgent(current.clone()))?;
+            let mut history = if handoffs.is_empty() {
+                chat_history.clone()
+            } else {
+                self.carry_over.select(chat_history)
+            };
+            let start = history.len();
+            let response = agent
+                .prompt(prompt)
+                .with_history(&mut history)
+                .multi_turn(self.max_depth)
+                .await?;
+            let messages = history.split_off(start);
+
+            let Some(handoff) = self.handoff(&messages) else {
+                chat_history.extend(messages);
+                return Ok(HandoffResponse {
+                    agent: current,
+                    response,
+                    handoffs,
+                });
+            };
+            if handoffs.len() == self.max_handoffs {
+                return Err(HandoffError::MaxHandoffsError(self.max_handoffs));
+            }
+            tracing::info!(target: "rig",
+                "The agent {current} handed the conversation off to {}: {}",
+                handoff.target, handoff.reason
+            );
+            chat_history.extend(messages);
+            prompt = Message::user(handoff.prompt(&current));
+            current = handoff.target.clone();
+            handoffs.push(handoff);
+        }
+    }
+
+    /// The latest handoff of `messages` to an agent of the orchestrator, if any.
+    fn handoff(&self, messages: &[Message]) -> Option<Handoff> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::Assistant { content } => Some(content.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|content| match content {
+                AssistantContent::ToolCall(tool_call)
+                    if tool_call.function.name == HandoffTool::NAME =>
+                {
+                    serde_json::from_value::<Handoff>(tool_call.function.arguments.clone()).ok()
+                }
+                _ => None,
+            })
+            .filter(|handoff| self.agents.contains_key(&handoff.target))
+            .last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse},
+        OneOrMany,
+    };
+
+    /// Hands the conversation off to the billing agent, then tells the user
+    #[derive(Clone)]
+    struct TriageModel;
+
+    impl CompletionModel for TriageModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let choice = match request.chat_history.iter().last() {
+                Some(message) if is_tool_result(message) => {
+                    AssistantContent::text("Transferring you to billing")
+                }
+                _ => AssistantContent::tool_call(
+                    "call_1",
+                    "handoff",
+                    json!({
+                        "target": "billing",
+                        "reason": "Double charge",
+                        "context": "Charged twice in March"
+                    }),
+                ),
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    /// Answers with the number of messages of the conversation and its prompt
+    #[derive(Clone)]
+    struct BillingModel;
+
+    impl CompletionModel for BillingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let prompt = request
+                .chat_history
+                .iter()
+                .last()
+                .and_then(Message::rag_text)
+                .unwrap_or_default();
+            let text = format!("{} messages, {prompt}", request.chat_history.len());
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(text)),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handoff() {
+        let triage = AgentBuilder::new(TriageModel)
+            .tool(HandoffTool::new().target("billing", "Invoices and payments"))
+            .build();
+        let billing = AgentBuilder::new(BillingModel).build();
+        let orchestrator = HandoffOrchestrator::new()
+            .agent("triage", triage)
+            .agent("billing", billing)
+            .carry_over(HistoryCarryOver::Last(2));
+
+        let mut chat_history = vec![];
+        let response = orchestrator
+            .run("triage", "I was charged twice", &mut chat_history)
+            .await
+            .unwrap();
+        assert_eq!(response.agent, "billing");
+        assert_eq!(response.handoffs[0].target, "billing");
+        // The tool result is not carried over without its tool call
+        let prompt = response.handoffs[0].prompt("triage");
+        assert_eq!(response.response, format!("2 messages, {prompt}"));
+        assert_eq!(chat_history.len(), 6);
+        assert_eq!(
+            chat_history[5],
+            Message::assistant(format!("2 messages, {prompt}"))
+        );
+
+        assert!(matches!(
+            orchestrator
+                .clone()
+                .max_handoffs(0)
+                .run("triage", "I was charged twice", &mut vec![])
+                .await,
+            Err(HandoffError::MaxHandoffsError(0))
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use crate::{
+    completion::{Message, PromptError},
+    message::ToolCall,
+    tool::ToolSetError,
+    tools::approval::Approval,
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+};
+
+use super::budget::TrimReport;
+
+/// Handler of the events of an agent, added with
+/// [AgentBuilder::hooks](super::AgentBuilder::hooks), e.g.: to update a UI, approve the tool
+/// calls or log the prompts from a single place instead of wrapping every call site. All the
+/// methods do nothing by default.
+///
+/// The prompts (see [PromptRequest](super::PromptRequest)) emit all the events, the streaming
+/// prompts and chats (see [StreamingPrompt](crate::streaming::StreamingPrompt)) emit
+/// [AgentHooks::on_token].
+///
+/// # Example
+/// ```rust
+/// use rig::{
+///     agent::AgentHooks, message::ToolCall, tools::approval::Approval,
+///     wasm_compat::WasmBoxedFuture,
+/// };
+///
+/// struct Ui;
+///
+/// impl AgentHooks for Ui {
+///     fn on_token(&self, text: &str) {
+///         print!("{text}");
+///     }
+///
+///     fn on_tool_call<'a>(&'a self, tool_call: &'a ToolCall) -> WasmBoxedFuture<'a, Approval> {
+///         Box::pin(async move {
+///             if tool_call.function.name == "delete_account" {
+///                 Approval::deny("Accounts are deleted by the support team")
+///             } else {
+///                 Approval::Approve
+///             }
+///         })
+///     }
+/// }
+///
+/// let agent = openai.agent(openai::GPT_4O).hooks(Ui).build();
+/// ```
+pub trait AgentHooks: WasmCompatSend + WasmCompatSync {
+    /// A prompt starts, with its chat history
+    fn on_start(&self, _prompt: &Message, _chat_history: &[Message]) {}
+
+    /// The model called a tool. A denied call is not run: the model receives the denial (and
+    /// its reason) as the output of the tool, and can carry on.
+    fn on_tool_call<'a>(&'a self, _tool_call: &'a ToolCall) -> WasmBoxedFuture<'a, Approval> {
+        Box::pin(async { Approval::Approve })
+    }
+
+    /// A tool call returned
+    fn on_tool_result(&self, _tool_call: &ToolCall, _result: &Result<String, ToolSetError>) {}
+
+    /// The context of a request was trimmed to fit in the token budget of the agent, or in the
+    /// context window of the model after the provider rejected the request as too long
+    fn on_context_trimmed(&self, _report: &TrimReport) {}
+
+    /// A text fragment was streamed by the model
+    fn on_token(&self, _text: &str) {}
+
+    /// A prompt ended, with its response or error
+    fn on_end(&self, _result: &Result<String, PromptError>) {}
+}
+
+/// The reason of the first denial of `tool_call` by `hooks`, if any.
+pub(super) async fn deny_tool_call(
+    hooks: &[Arc<dyn AgentHooks>],
+    tool_call: &ToolCall,
+) -> Option<String> {
+    for hook in hooks {
+        if let Approval::Deny { reason } = hook.on_tool_call(tool_call).await {
+            return Some(reason);
+        }
+    }
+    None
+}
+
+/// Output of a tool call denied by the hooks of the agent, for the model
+pub(super) fn denial_output(reason: &str) -> String {
+    format!("The tool call was denied: {reason}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse, Prompt,
+        },
+        message::{ToolResultContent, UserContent},
+        testing::ScriptedTool,
+        OneOrMany,
+    };
+
+    /// Calls the `delete_account` tool, then answers with the output of the tool
+    #[derive(Clone)]
+    struct DeleteModel;
+
+    impl CompletionModel for DeleteModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let choice = match request.chat_history.iter().last() {
+                Some(Message::User { content }) => match content.first() {
+                    UserContent::ToolResult(result) => match result.content.first() {
+                        ToolResultContent::Text(text) => AssistantContent::text(text.text),
+                        _ => AssistantContent::text("unexpected tool result"),
+                    },
+                    _ => AssistantContent::tool_call(
+                        "call_1",
+                        "delete_account",
+                        serde_json::json!({"id": 42}),
+                    ),
+                },
+                _ => AssistantContent::text("unexpected message"),
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    /// Records the events, denying the tool calls
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl AgentHooks for Arc<Recorder> {
+        fn on_start(&self, prompt: &Message, _chat_history: &[Message]) {
+            let event = format!("start: {}", prompt.rag_text().unwrap_or_default());
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn on_tool_call<'a>(&'a self, tool_call: &'a ToolCall) -> WasmBoxedFuture<'a, Approval> {
+            let event = format!("tool call: {}", tool_call.function.name);
+            self.events.lock().unwrap().push(event);
+            Box::pin(async { Approval::deny("support only") })
+        }
+
+        fn on_tool_result(&self, _tool_call: &ToolCall, _result: &Result<String, ToolSetError>) {
+            self.events.lock().unwrap().push("tool result".to_string());
+        }
+
+        fn on_end(&self, result: &Result<String, PromptError>) {
+            let event = format!("end: {}", result.as_deref().unwrap_or("error"));
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks() {
+        let recorder = Arc::new(Recorder::default());
+        let delete_account = ScriptedTool::new("delete_account").returns("deleted");
+        let agent = AgentBuilder::new(DeleteModel)
+            .scripted_tool(delete_account.clone())
+            .hooks(recorder.clone())
+            .build();
+
+        let response = agent
+            .prompt("Delete the account 42")
+            .multi_turn(2)
+            .await
+            .unwrap();
+        assert_eq!(response, "The tool call was denied: support only");
+        assert!(delete_account.calls().is_empty());
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![
+                "start: Delete the account 42",
+                "tool call: delete_account",
+                "end: The tool call was denied: support only",
+            ]
+        );
+    }
+}
@@ -107,10 +107,58 @@
 //!     .expect("Failed to prompt the agent");
 //! ```
 
+mod audit;
+mod budget;
 mod builder;
+mod checkpoint;
 mod completion;
+mod config;
+mod durable;
+mod dyn_agent;
+mod dynamic_context;
+mod ensemble;
+mod experiment;
+mod formatter;
+mod handoff;
+mod hooks;
+mod principal;
 mod prompt_request;
+mod rag_query;
+mod router;
+mod tool_output;
 
-pub use builder::AgentBuilder;
-pub use completion::Agent;
+#[cfg(feature = "sql")]
+pub use audit::SqlAuditSink;
+pub use audit::{
+    AuditError, AuditSink, FileAuditSink, MemoryAuditSink, ToolAuditRecord, ToolCallStatus,
+};
+pub use budget::{ContextSection, TokenBudget, TrimReport};
+pub use builder::{AgentBuildError, AgentBuilder};
+pub use checkpoint::{
+    Checkpoint, CheckpointError, CheckpointStore, FileCheckpointStore, MemoryCheckpointStore,
+    RunStep,
+};
+pub use completion::{Agent, DEFAULT_TENANT_FIELD};
+pub use config::{
+    AgentConfig, AgentConfigError, AgentRegistry, DynamicContextConfig, IndexFailureConfig,
+};
+pub use durable::{
+    ActivityOutcome, AgentActivity, AgentJob, DurableError, DurableWorker, JobQueue,
+    MemoryJobQueue, RunOutcome,
+};
+pub use dyn_agent::{AgentDyn, DynAgent, DynPromptRequest};
+pub use dynamic_context::{DynamicContextSettings, DynamicContextSource, IndexFailurePolicy};
+pub use ensemble::{Ensemble, EnsembleResponse, MemberAnswer, CRITIQUE_PROMPT, SYNTHESIS_PROMPT};
+pub use experiment::{Experiment, ExperimentConfig, VersionConfig, VersionedResponse};
+pub use formatter::{DocumentFormatter, DocumentTemplate};
+pub use handoff::{
+    Handoff, HandoffError, HandoffOrchestrator, HandoffResponse, HandoffTool, HistoryCarryOver,
+};
+pub use hooks::AgentHooks;
+pub use principal::Principal;
 pub use prompt_request::PromptRequest;
+pub use rag_query::{RagQueryStrategy, STANDALONE_QUESTION_PREAMBLE};
+pub use router::{
+    RouteChoice, RouteTarget, RoutedResponse, RouterAgent, RouterAgentBuilder, RouterError,
+};
+pub use tool_output::{ToolOutputLimits, TruncationStrategy};
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+/// Identity of the caller of a prompt: a user id and its roles.
+///
+/// The tools restricted to some roles (see
+/// [AgentBuilder::tool_roles](super::AgentBuilder::tool_roles)) are only advertised to, and
+/// callable by, the callers having one of these roles. This lets a single agent serve callers
+/// with different permissions.
+///
+/// # Example
+/// ```rust
+/// use rig::agent::Principal;
+///
+/// let admin = Principal::new("user-42").role("admin");
+///
+/// let response = agent
+///     .prompt("Delete the account of user-7")
+///     .principal(admin)
+///     .await?;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Principal {
+    /// Id of the caller (e.g.: a user id)
+    pub id: String,
+    /// Roles of the caller
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl Principal {
+    /// A caller without roles
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            roles: vec![],
+        }
+    }
+
+    /// Add a role to the caller
+    pub fn role(mut self, role: &str) -> Self {
+        self.roles.push(role.to_string());
+        self
+    }
+
+    /// Add roles to the caller
+    pub fn roles<'a>(mut self, roles: impl IntoIterator<Item = &'a str>) -> Self {
+        self.roles.extend(roles.into_iter().map(str::to_string));
+        self
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+impl From<&str> for Principal {
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse, Prompt, PromptError,
+        },
+        message::{Message, UserContent},
+        testing::ScriptedTool,
+        OneOrMany,
+    };
+
+    /// Calls the `delete_account` tool (advertised or not), then answers "done"
+    #[derive(Clone)]
+    struct DeleteModel;
+
+    impl CompletionModel for DeleteModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let choice = match request.chat_history.iter().last() {
+                Some(Message::User { content })
+                    if matches!(content.first(), UserContent::ToolResult(_)) =>
+                {
+                    AssistantContent::text("done")
+                }
+                _ => AssistantContent::tool_call(
+                    "call_1",
+                    "delete_account",
+                    serde_json::json!({"user": "user-7"}),
+                ),
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_roles() {
+        let agent = AgentBuilder::new(DeleteModel)
+            .scripted_tool(ScriptedTool::new("lookup").returns("found"))
+            .scripted_tool(ScriptedTool::new("delete_account").returns("deleted"))
+            .tool_roles("delete_account", ["admin", "support"])
+            .build();
+        let admin = Principal::new("user-1").roles(["ops", "admin"]);
+        let user = Principal::new("user-2");
+
+        let toolnames = |request: CompletionRequest| {
+            let mut toolnames = request
+                .tools
+                .into_iter()
+                .map(|tool| tool.name)
+                .collect::<Vec<_>>();
+            toolnames.sort();
+            toolnames
+        };
+        let request = agent
+            .completion_for("Hi", vec![], Some(&admin), None)
+            .await
+            .unwrap()
+            .build();
+        assert_eq!(toolnames(request), ["delete_account", "lookup"]);
+        for principal in [Some(&user), None] {
+            let request = agent
+                .completion_for("Hi", vec![], principal, None)
+                .await
+                .unwrap()
+                .build();
+            assert_eq!(toolnames(request), ["lookup"]);
+        }
+
+        let response = agent
+            .prompt("Delete user-7")
+            .principal(admin)
+            .multi_turn(1)
+            .await
+            .unwrap();
+        assert_eq!(response, "done");
+
+        let err = agent
+            .prompt("Delete user-7")
+            .principal(user)
+            .multi_turn(1)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PromptError::CompletionError(CompletionError::RequestError(_))
+        ));
+        assert!(err
+            .to_string()
+            .contains("ToolNotFoundError: delete_account"));
+    }
+}
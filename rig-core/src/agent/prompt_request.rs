@@ -1,15 +1,25 @@
-use std::future::IntoFuture;
+use std::{future::IntoFuture, time::Duration};
 
-use futures::{future::BoxFuture, stream, FutureExt, StreamExt};
+use futures::{stream, FutureExt, StreamExt};
+use web_time::Instant;
 
+use crate::wasm_compat::WasmBoxedFuture;
 use crate::{
-    completion::{Completion, CompletionError, CompletionModel, Message, PromptError},
+    cancellation::{with_cancellation, CancellationToken},
+    completion::{
+        request::with_timeout, CompletionError, CompletionModel, Document, Message, PromptError,
+    },
     message::{AssistantContent, UserContent},
-    tool::ToolSetError,
+    tool::{IdempotencyKey, ToolSetError},
     OneOrMany,
 };
 
-use super::Agent;
+use super::{
+    audit::audit_tool_call,
+    checkpoint::{Checkpoint, CheckpointError, CheckpointStore, RunStep},
+    hooks::{denial_output, deny_tool_call},
+    Agent, Principal,
+};
 
 /// A builder for creating prompt requests with customizable options.
 /// Uses generics to track which options have been set during the build process.
@@ -21,6 +31,18 @@ pub struct PromptRequest<'a, M: CompletionModel> {
     chat_history: Option<&'a mut Vec<Message>>,
     /// Maximum depth for multi-turn conversations (0 means no multi-turn)
     max_depth: usize,
+    /// Maximum duration of the whole prompt (including the tool calls of multi-turn conversations)
+    timeout: Option<Duration>,
+    /// Token cancelling the prompt (including its running completion requests and tool calls)
+    cancellation: Option<CancellationToken>,
+    /// Identity of the caller of the prompt (e.g.: in the audit records of the tool calls)
+    caller: Option<Principal>,
+    /// Id of the conversation, from which the idempotency keys of the tool calls are derived
+    conversation_id: Option<String>,
+    /// Tenant of the prompt, filtering the dynamic context
+    tenant: Option<String>,
+    /// Id of the run of the prompt, whose checkpoints are saved to the store of the agent
+    run_id: Option<String>,
     /// The agent to use for execution
     agent: &'a Agent<M>,
 }
@@ -32,6 +54,12 @@ impl<'a, M: CompletionModel> PromptRequest<'a, M> {
             prompt: prompt.into(),
             chat_history: None,
             max_depth: 0,
+            timeout: None,
+            cancellation: None,
+            caller: None,
+            conversation_id: None,
+            tenant: None,
+            run_id: None,
             agent,
         }
     }
@@ -41,128 +69,373 @@ impl<'a, M: CompletionModel> PromptRequest<'a, M> {
     /// Set the maximum depth for multi-turn conversations
     pub fn multi_turn(self, depth: usize) -> PromptRequest<'a, M> {
         PromptRequest {
-            prompt: self.prompt,
-            chat_history: self.chat_history,
             max_depth: depth,
-            agent: self.agent,
+            ..self
         }
     }
 
     /// Add chat history to the prompt request
     pub fn with_history(self, history: &'a mut Vec<Message>) -> PromptRequest<'a, M> {
         PromptRequest {
-            prompt: self.prompt,
             chat_history: Some(history),
-            max_depth: self.max_depth,
-            agent: self.agent,
+            ..self
+        }
+    }
+
+    /// Set the maximum duration of the whole prompt, including all the completion requests and
+    /// tool calls of multi-turn conversations, after which it fails with a
+    /// [CompletionError::Timeout]. Each completion request is also bounded by the timeout of the
+    /// agent, if any.
+    pub fn timeout(self, timeout: Duration) -> PromptRequest<'a, M> {
+        PromptRequest {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Set the token cancelling the prompt: the running completion request or tool calls are
+    /// aborted, and the prompt fails with a [PromptError::Cancelled] containing the chat history
+    /// up to the cancellation.
+    pub fn cancellation(self, cancellation: CancellationToken) -> PromptRequest<'a, M> {
+        PromptRequest {
+            cancellation: Some(cancellation),
+            ..self
+        }
+    }
+
+    /// Set the identity of the caller of the prompt (e.g.: a user id), recorded in the audit
+    /// records of the tool calls (see [AgentBuilder::audit_sink](super::AgentBuilder::audit_sink)).
+    /// The caller has no roles, see [PromptRequest::principal].
+    pub fn caller(self, caller: &str) -> PromptRequest<'a, M> {
+        self.principal(Principal::new(caller))
+    }
+
+    /// Set the caller of the prompt with its roles: the tools restricted to other roles (see
+    /// [AgentBuilder::tool_roles](super::AgentBuilder::tool_roles)) are neither advertised to
+    /// the model nor called.
+    pub fn principal(self, principal: Principal) -> PromptRequest<'a, M> {
+        PromptRequest {
+            caller: Some(principal),
+            ..self
+        }
+    }
+
+    /// Set the id of the conversation of the prompt. The calls of the side-effecting tools (see
+    /// [Tool::side_effecting](crate::tool::Tool::side_effecting)) then get an
+    /// [IdempotencyKey] derived from the conversation, the turn and the id of the call, so that
+    /// retrying the prompt with the same chat history does not repeat them.
+    pub fn conversation_id(self, conversation_id: &str) -> PromptRequest<'a, M> {
+        PromptRequest {
+            conversation_id: Some(conversation_id.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the tenant of the prompt: the queries of the dynamic context only return the documents
+    /// of the tenant, whose tenant field (see
+    /// [AgentBuilder::tenant_field](super::AgentBuilder::tenant_field), `tenant` by default)
+    /// equals `tenant`.
+    pub fn tenant(self, tenant: &str) -> PromptRequest<'a, M> {
+        PromptRequest {
+            tenant: Some(tenant.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the id of the run of the prompt (e.g.: the id of a background job): the state of the
+    /// run is saved to the checkpoint store of the agent (see
+    /// [AgentBuilder::checkpoint_store](super::AgentBuilder::checkpoint_store)) after each
+    /// completion request and each batch of tool calls, and removed once the run completes.
+    ///
+    /// If the store has a checkpoint of the run (e.g.: the process restarted, or the run failed
+    /// on a transient error), the prompt resumes it instead of starting over: the prompt is
+    /// ignored, and the chat history is replaced by the chat history of the checkpoint. The
+    /// pending tool calls of the checkpoint are run again, the idempotency keys of the
+    /// side-effecting tools being derived from the run id unless a conversation id is set (see
+    /// [PromptRequest::conversation_id]).
+    pub fn run_id(self, run_id: &str) -> PromptRequest<'a, M> {
+        PromptRequest {
+            run_id: Some(run_id.to_string()),
+            ..self
         }
     }
 }
 
-/// Due to: [RFC 2515](https://github.com/rust-lang/rust/issues/63063), we have to use a `BoxFuture`
+/// Due to: [RFC 2515](https://github.com/rust-lang/rust/issues/63063), we have to use a boxed future
 ///  for the `IntoFuture` implementation. In the future, we should be able to use `impl Future<...>`
 ///  directly via the associated type.
 impl<'a, M: CompletionModel> IntoFuture for PromptRequest<'a, M> {
     type Output = Result<String, PromptError>;
-    type IntoFuture = BoxFuture<'a, Self::Output>; // This future should not outlive the agent
+    type IntoFuture = WasmBoxedFuture<'a, Self::Output>; // This future should not outlive the agent
 
     fn into_future(self) -> Self::IntoFuture {
-        self.send().boxed()
+        let agent = self.agent;
+        let timeout = self.timeout;
+        Box::pin(async move {
+            let chat_history = self.chat_history.as_deref().map_or(&[][..], Vec::as_slice);
+            for hook in &agent.hooks {
+                hook.on_start(&self.prompt, chat_history);
+            }
+            let result = with_timeout(timeout, self.send()).await;
+            for hook in &agent.hooks {
+                hook.on_end(&result);
+            }
+            result
+        })
+    }
+}
+
+/// Save the checkpoint of the run `run_id`, if any.
+async fn save_checkpoint(
+    store: &dyn CheckpointStore,
+    run_id: Option<&str>,
+    depth: usize,
+    chat_history: &[Message],
+    step: RunStep,
+    documents: &[Document],
+) -> Result<(), CheckpointError> {
+    match run_id {
+        Some(run_id) => {
+            store
+                .save(&Checkpoint::new(
+                    run_id,
+                    depth,
+                    chat_history,
+                    step,
+                    documents,
+                ))
+                .await
+        }
+        None => Ok(()),
     }
 }
 
 impl<M: CompletionModel> PromptRequest<'_, M> {
     async fn send(self) -> Result<String, PromptError> {
         let agent = self.agent;
-        let mut prompt = self.prompt;
         let chat_history = if let Some(history) = self.chat_history {
             history
         } else {
             &mut Vec::new()
         };
+        let store = &*agent.checkpoint_store;
+        let run_id = self.run_id.as_deref();
+        // The idempotency keys of a run are derived from its id by default, so that resuming
+        // the run does not repeat its side-effecting tool calls
+        let conversation_id = self.conversation_id.as_deref().or(run_id);
 
         let mut current_max_depth = 0;
-        // We need to do atleast 2 loops for 1 roundtrip (user expects normal message)
-        while current_max_depth <= self.max_depth + 1 {
-            current_max_depth += 1;
-
-            if self.max_depth > 1 {
-                tracing::info!(
-                    "Current conversation depth: {}/{}",
-                    current_max_depth,
-                    self.max_depth
+        let mut step = RunStep::Prompt {
+            prompt: self.prompt,
+        };
+        let mut documents = vec![];
+        if let Some(run_id) = run_id {
+            if let Some(checkpoint) = store.load(run_id).await? {
+                tracing::info!(target: "rig",
+                    "Resuming the run {run_id} after {} completion requests", checkpoint.depth
                 );
+                *chat_history = checkpoint.chat_history;
+                current_max_depth = checkpoint.depth;
+                step = checkpoint.step;
+                documents = checkpoint.documents;
             }
+        }
 
-            let resp = agent
-                .completion(prompt.clone(), chat_history.to_vec())
-                .await?
-                .send()
-                .await?;
-
-            chat_history.push(prompt);
-
-            let (tool_calls, texts): (Vec<_>, Vec<_>) = resp
-                .choice
-                .iter()
-                .partition(|choice| matches!(choice, AssistantContent::ToolCall(_)));
-
-            chat_history.push(Message::Assistant {
-                content: resp.choice.clone(),
-            });
-
-            if tool_calls.is_empty() {
-                let merged_texts = texts
-                    .into_iter()
-                    .filter_map(|content| {
-                        if let AssistantContent::Text(text) = content {
-                            Some(text.text.clone())
-                        } else {
-                            None
+        // We need to do atleast 2 loops for 1 roundtrip (user expects normal message)
+        let prompt = loop {
+            let tool_calls = match step {
+                RunStep::ToolCalls { tool_calls } => tool_calls,
+                RunStep::Prompt { prompt } if current_max_depth > self.max_depth + 1 => {
+                    break prompt
+                }
+                RunStep::Prompt { prompt } => {
+                    current_max_depth += 1;
+
+                    if self.max_depth > 1 {
+                        tracing::info!(
+                            "Current conversation depth: {}/{}",
+                            current_max_depth,
+                            self.max_depth
+                        );
+                    }
+
+                    let resp = agent
+                        .send_completion(
+                            prompt.clone(),
+                            chat_history,
+                            self.caller.as_ref(),
+                            self.tenant.as_deref(),
+                            self.cancellation.clone(),
+                        )
+                        .await
+                        .map(|(resp, attached)| {
+                            documents = attached;
+                            resp
+                        });
+
+                    // The prompt is only added to the history once answered, so that a failed
+                    // prompt can be retried with the same history
+                    let resp = match resp {
+                        Err(CompletionError::Cancelled) => {
+                            let mut chat_history = chat_history.clone();
+                            chat_history.push(prompt);
+                            return Err(PromptError::Cancelled { chat_history });
                         }
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n");
+                        resp => resp?,
+                    };
+                    chat_history.push(prompt);
 
-                if self.max_depth > 1 {
-                    tracing::info!("Depth reached: {}/{}", current_max_depth, self.max_depth);
-                }
+                    let tool_calls = resp
+                        .choice
+                        .iter()
+                        .filter_map(|choice| match choice {
+                            AssistantContent::ToolCall(tool_call) => Some(tool_call.clone()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
 
-                // If there are no tool calls, depth is not relevant, we can just return the merged text.
-                return Ok(merged_texts);
-            }
+                    chat_history.push(Message::Assistant {
+                        content: resp.choice.clone(),
+                    });
 
-            let tool_content = stream::iter(tool_calls)
-                .then(|choice| async move {
-                    if let AssistantContent::ToolCall(tool_call) = choice {
-                        let output = agent
-                            .tools
-                            .call(
-                                &tool_call.function.name,
-                                tool_call.function.arguments.to_string(),
-                            )
-                            .await?;
-                        Ok(UserContent::tool_result(
+                    if tool_calls.is_empty() {
+                        let merged_texts = resp
+                            .choice
+                            .iter()
+                            .filter_map(|content| {
+                                if let AssistantContent::Text(text) = content {
+                                    Some(text.text.clone())
+                                } else {
+                                    None
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+
+                        if self.max_depth > 1 {
+                            tracing::info!(
+                                "Depth reached: {}/{}",
+                                current_max_depth,
+                                self.max_depth
+                            );
+                        }
+
+                        if let Some(run_id) = run_id {
+                            store.remove(run_id).await?;
+                        }
+                        // If there are no tool calls, depth is not relevant, we can just return the merged text.
+                        return Ok(merged_texts);
+                    }
+
+                    save_checkpoint(
+                        store,
+                        run_id,
+                        current_max_depth,
+                        chat_history,
+                        RunStep::ToolCalls {
+                            tool_calls: tool_calls.clone(),
+                        },
+                        &documents,
+                    )
+                    .await?;
+                    tool_calls
+                }
+            };
+
+            let caller = self.caller.as_ref();
+            // Index of the assistant message of the tool calls
+            let turn = chat_history.len() - 1;
+            let tool_results = stream::iter(&tool_calls)
+                .then(|tool_call| async move {
+                    if let Some(reason) = deny_tool_call(&agent.hooks, tool_call).await {
+                        return Ok(UserContent::tool_result(
                             tool_call.id.clone(),
-                            OneOrMany::one(output.into()),
-                        ))
-                    } else {
-                        unreachable!(
-                            "This should never happen as we already filtered for `ToolCall`"
-                        )
+                            OneOrMany::one(denial_output(&reason).into()),
+                        ));
+                    }
+                    let toolname = &tool_call.function.name;
+                    let args = tool_call.function.arguments.to_string();
+                    let started = Instant::now();
+                    let result = match conversation_id {
+                        // The tools not allowed to the caller are not advertised, the model
+                        // is not supposed to know them
+                        _ if !agent.tool_allowed(toolname, caller) => {
+                            Err(ToolSetError::ToolNotFoundError(toolname.clone()))
+                        }
+                        Some(conversation_id) => {
+                            let call_id = if tool_call.id.is_empty() {
+                                format!("{toolname}:{args}")
+                            } else {
+                                tool_call.id.clone()
+                            };
+                            let key = IdempotencyKey::derive(conversation_id, turn, &call_id);
+                            agent
+                                .tools
+                                .call_idempotent(
+                                    toolname,
+                                    args.clone(),
+                                    &key,
+                                    &*agent.idempotency_store,
+                                )
+                                .await
+                        }
+                        None => agent.tools.call(toolname, args.clone()).await,
+                    };
+                    audit_tool_call(
+                        &agent.audit_sinks,
+                        agent.name.as_deref(),
+                        caller.map(|caller| caller.id.as_str()),
+                        tool_call,
+                        &args,
+                        &result,
+                        started.elapsed(),
+                    )
+                    .await;
+                    let result = result.map(|output| match &agent.tool_output_limits {
+                        Some(limits) => limits.truncate(&tool_call.function.name, output),
+                        None => output,
+                    });
+                    for hook in &agent.hooks {
+                        hook.on_tool_result(tool_call, &result);
                     }
+                    let output = result?;
+                    Ok(UserContent::tool_result(
+                        tool_call.id.clone(),
+                        OneOrMany::one(output.into()),
+                    ))
                 })
                 .collect::<Vec<Result<UserContent, ToolSetError>>>()
+                .map(Ok::<_, CompletionError>);
+
+            let tool_content = with_cancellation(self.cancellation.as_ref(), tool_results)
                 .await
+                .map_err(|_| PromptError::Cancelled {
+                    chat_history: chat_history.clone(),
+                })?
                 .into_iter()
                 .collect::<Result<Vec<_>, _>>()
                 .map_err(|e| CompletionError::RequestError(Box::new(e)))?;
 
-            prompt = Message::User {
+            let prompt = Message::User {
                 content: OneOrMany::many(tool_content).expect("There is atleast one tool call"),
             };
-        }
+            save_checkpoint(
+                store,
+                run_id,
+                current_max_depth,
+                chat_history,
+                RunStep::Prompt {
+                    prompt: prompt.clone(),
+                },
+                &documents,
+            )
+            .await?;
+            step = RunStep::Prompt { prompt };
+        };
 
+        if let Some(run_id) = run_id {
+            store.remove(run_id).await?;
+        }
         // If we reach here, we never resolved the final tool call. We need to do ... something.
         Err(PromptError::MaxDepthError {
             max_depth: self.max_depth,
@@ -0,0 +1,210 @@
+use crate::{
+    completion::{CompletionError, CompletionModel, Message},
+    message::{AssistantContent, Text, UserContent},
+};
+
+use super::Agent;
+
+/// Preamble of the requests rewriting the latest message of a conversation into a standalone
+/// question (see [RagQueryStrategy::StandaloneQuestion])
+pub const STANDALONE_QUESTION_PREAMBLE: &str = "\
+Rewrite the latest message of the user in the conversation below into a standalone question, \
+understandable without the conversation (e.g.: replacing pronouns by what they refer to). \
+Answer with the standalone question only.";
+
+/// Text used to query the dynamic context and tools of an agent in multi-turn chats (see
+/// [AgentBuilder::rag_query](super::AgentBuilder::rag_query)).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RagQueryStrategy {
+    /// The text of the latest user message
+    #[default]
+    LastMessage,
+    /// The texts of the latest `n` messages of the chat history (user and assistant) followed by
+    /// the prompt, e.g.: so that a follow-up question still retrieves the documents of the
+    /// conversation
+    LastTurns(usize),
+    /// A standalone question rewritten by the model of the agent from the latest user message
+    /// and the latest `n` messages of the chat history. This costs an additional completion
+    /// request per request of the agent, falling back to the latest user message when the
+    /// chat history is empty.
+    StandaloneQuestion(usize),
+}
+
+/// The texts of a message (e.g.: not its tool calls and results)
+fn message_text(message: &Message) -> Option<String> {
+    let texts = match message {
+        Message::User { content } => content
+            .iter()
+            .filter_map(|content| match content {
+                UserContent::Text(Text { text }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        Message::Assistant { content } => content
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(Text { text }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+    };
+    (!texts.is_empty()).then(|| texts.join("\n"))
+}
+
+impl<M: CompletionModel> Agent<M> {
+    /// The text querying the dynamic context and tools for `prompt`, according to the RAG query
+    /// strategy of the agent.
+    pub(super) async fn rag_query(
+        &self,
+        prompt: &Message,
+        chat_history: &[Message],
+    ) -> Result<Option<String>, CompletionError> {
+        // Find the latest message in the chat history that contains RAG text
+        let last_message = || {
+            prompt.rag_text().or_else(|| {
+                chat_history
+                    .iter()
+                    .rev()
+                    .find_map(|message| message.rag_text())
+            })
+        };
+        let latest = |n: usize| chat_history[chat_history.len().saturating_sub(n)..].iter();
+
+        match self.rag_query {
+            RagQueryStrategy::LastMessage => Ok(last_message()),
+            RagQueryStrategy::LastTurns(n) => {
+                let texts = latest(n)
+                    .chain(std::iter::once(prompt))
+                    .filter_map(message_text)
+                    .collect::<Vec<_>>();
+                Ok((!texts.is_empty()).then(|| texts.join("\n")))
+            }
+            RagQueryStrategy::StandaloneQuestion(n) => {
+                let Some(question) = last_message() else {
+                    return Ok(None);
+                };
+                // Not worth a request without dynamic sources to query
+                if self.dynamic_context.is_empty() && self.dynamic_tools.is_empty() {
+                    return Ok(Some(question));
+                }
+                let transcript = latest(n)
+                    .filter_map(|message| {
+                        let role = match message {
+                            Message::User { .. } => "User",
+                            Message::Assistant { .. } => "Assistant",
+                        };
+                        message_text(message).map(|text| format!("{role}: {text}"))
+                    })
+                    .collect::<Vec<_>>();
+                if transcript.is_empty() {
+                    return Ok(Some(question));
+                }
+
+                let response = self
+                    .model
+                    .completion_request(Message::user(format!(
+                        "Conversation:\n{}\n\nLatest message: {question}",
+                        transcript.join("\n")
+                    )))
+                    .preamble(STANDALONE_QUESTION_PREAMBLE.to_string())
+                    .temperature(0.0)
+                    .timeout_opt(self.timeout)
+                    .send()
+                    .await?;
+                let standalone = response
+                    .choice
+                    .iter()
+                    .filter_map(|content| match content {
+                        AssistantContent::Text(Text { text }) => Some(text.trim()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Ok(Some(if standalone.is_empty() {
+                    question
+                } else {
+                    standalone
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{CompletionRequest, CompletionResponse},
+        vector_store::tests::TenantIndex,
+        OneOrMany,
+    };
+
+    /// Rewrites the questions into "standalone: <number of lines of the prompt>"
+    #[derive(Clone)]
+    struct RewritingModel;
+
+    impl CompletionModel for RewritingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            assert_eq!(
+                request.preamble.as_deref(),
+                Some(STANDALONE_QUESTION_PREAMBLE)
+            );
+            let prompt = request.chat_history.first().rag_text().unwrap();
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!(
+                    "standalone: {}",
+                    prompt.lines().count()
+                ))),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rag_query() {
+        let history = vec![
+            Message::user("Who wrote Dune?"),
+            Message::assistant("Frank Herbert."),
+        ];
+        let prompt = Message::user("When was it published?");
+        let (history, prompt) = (&history, &prompt);
+
+        let query = |strategy| async move {
+            AgentBuilder::new(RewritingModel)
+                .dynamic_context(1, TenantIndex)
+                .rag_query(strategy)
+                .build()
+                .rag_query(prompt, history)
+                .await
+                .unwrap()
+        };
+        assert_eq!(
+            query(RagQueryStrategy::LastMessage).await.as_deref(),
+            Some("When was it published?")
+        );
+        assert_eq!(
+            query(RagQueryStrategy::LastTurns(1)).await.as_deref(),
+            Some("Frank Herbert.\nWhen was it published?")
+        );
+        // Conversation header, 2 messages, blank line and latest message
+        assert_eq!(
+            query(RagQueryStrategy::StandaloneQuestion(5))
+                .await
+                .as_deref(),
+            Some("standalone: 5")
+        );
+        assert_eq!(
+            query(RagQueryStrategy::StandaloneQuestion(0))
+                .await
+                .as_deref(),
+            Some("When was it published?")
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::{CompletionModel, Message, Prompt, PromptError},
+    extractor::{ExtractionError, Extractor, ExtractorBuilder},
+    pipeline::Op,
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+};
+
+use super::DynAgent;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RouterError {
+    /// The prompt could not be classified, and the router has no fallback
+    #[error("ExtractionError: {0}")]
+    ExtractionError(#[from] ExtractionError),
+
+    /// The route of the prompt has no target, and the router has no fallback
+    #[error("NoRoute: {0}")]
+    NoRoute(String),
+
+    /// The agent of the route failed
+    #[error("PromptError: {0}")]
+    PromptError(#[from] PromptError),
+
+    /// The pipeline of the route failed
+    #[error("RouteError: {0}")]
+    RouteError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Arguments of the classification of a [RouterAgent], submitted by the model.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct RouteChoice<R> {
+    /// The route best suited to answer the prompt
+    pub route: R,
+}
+
+/// Target of a route of a [RouterAgent], answering the prompts: an agent (see
+/// [RouterAgentBuilder::route]) or a pipeline (see [RouterAgentBuilder::route_op]).
+pub trait RouteTarget: WasmCompatSend + WasmCompatSync {
+    fn dispatch<'a>(&'a self, prompt: Message) -> WasmBoxedFuture<'a, Result<String, RouterError>>;
+}
+
+impl RouteTarget for DynAgent {
+    fn dispatch<'a>(&'a self, prompt: Message) -> WasmBoxedFuture<'a, Result<String, RouterError>> {
+        Box::pin(async move { Ok(self.prompt(prompt).await?) })
+    }
+}
+
+/// [RouteTarget] of a pipeline, see [RouterAgentBuilder::route_op].
+struct OpTarget<O>(O);
+
+impl<O, E> RouteTarget for OpTarget<O>
+where
+    O: Op<Input = String, Output = Result<String, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn dispatch<'a>(&'a self, prompt: Message) -> WasmBoxedFuture<'a, Result<String, RouterError>> {
+        Box::pin(async move {
+            self.0
+                .call(prompt.rag_text().unwrap_or_default())
+                .await
+                .map_err(|err| RouterError::RouteError(Box::new(err)))
+        })
+    }
+}
+
+/// Response of a [RouterAgent], with the route taken.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutedResponse<R> {
+    /// The route of the prompt, `None` if the classification failed (in which case the prompt
+    /// was answered by the fallback)
+    pub route: Option<R>,
+    pub response: String,
+}
+
+/// Agent classifying the prompts into routes (the variants of the enum `R`, described to the
+/// model by their doc comments) with an [Extractor], and dispatching them to the agent or
+/// pipeline of their route.
+///
+/// The prompts whose route has no target, or which cannot be classified, are answered by the
+/// fallback target of the router, if any (see [RouterAgentBuilder::fallback]).
+///
+/// # Example
+/// ```rust
+/// use rig::agent::RouterAgentBuilder;
+///
+/// #[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+/// #[serde(rename_all = "snake_case")]
+/// enum Route {
+///     /// Invoices, payments and refunds
+///     Billing,
+///     /// Technical issues with the product
+///     Support,
+/// }
+///
+/// let router = RouterAgentBuilder::<Route, _>::new(openai.completion_model(openai::GPT_4O_MINI))
+///     .route(Route::Billing, billing_agent)
+///     .route_op(Route::Support, support_pipeline)
+///     .fallback(general_agent)
+///     .build();
+///
+/// let response = router.prompt("I was charged twice this month").await?;
+/// println!("{:?}: {}", response.route, response.response);
+/// ```
+pub struct RouterAgent<M, R>
+where
+    M: CompletionModel,
+    R: JsonSchema + for<'a> Deserialize<'a> + Send + Sync,
+{
+    classifier: Extractor<M, RouteChoice<R>>,
+    routes: Vec<(R, Box<dyn RouteTarget>)>,
+    fallback: Option<Box<dyn RouteTarget>>,
+}
+
+impl<M, R> RouterAgent<M, R>
+where
+    M: CompletionModel,
+    R: JsonSchema + for<'a> Deserialize<'a> + PartialEq + std::fmt::Debug + Send + Sync,
+{
+    /// The route of `prompt`.
+    pub async fn classify(&self, prompt: impl Into<Message> + Send) -> Result<R, ExtractionError> {
+        Ok(self.classifier.extract(prompt).await?.route)
+    }
+
+    /// Classify `prompt`, then send it to the target of its route.
+    pub async fn prompt(
+        &self,
+        prompt: impl Into<Message> + Send,
+    ) -> Result<RoutedResponse<R>, RouterError> {
+        let prompt = prompt.into();
+        let route = match self.classify(prompt.clone()).await {
+            Ok(route) => Some(route),
+            Err(err) if self.fallback.is_some() => {
+                tracing::warn!(target: "rig",
+                    "Failed to classify the prompt, routing it to the fallback: {err}"
+                );
+                None
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let target = route
+            .as_ref()
+            .and_then(|route| {
+                self.routes
+                    .iter()
+                    .find(|(candidate, _)| candidate == route)
+                    .map(|(_, target)| target)
+            })
+            .or(self.fallback.as_ref())
+            .ok_or_else(|| RouterError::NoRoute(format!("{route:?}")))?;
+        tracing::info!(target: "rig", "Routing the prompt to the route {route:?}");
+
+        let response = target.dispatch(prompt).await?;
+        Ok(RoutedResponse { route, response })
+    }
+}
+
+/// Builder of a [RouterAgent].
+pub struct RouterAgentBuilder<R, M>
+where
+    M: CompletionModel,
+    R: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync + 'static,
+{
+    classifier: ExtractorBuilder<RouteChoice<R>, M>,
+    routes: Vec<(R, Box<dyn RouteTarget>)>,
+    fallback: Option<Box<dyn RouteTarget>>,
+}
+
+impl<R, M> RouterAgentBuilder<R, M>
+where
+    M: CompletionModel,
+    R: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync + 'static,
+{
+    /// Router classifying the prompts with `model`.
+    pub fn new(model: M) -> Self {
+        Self {
+            classifier: ExtractorBuilder::new(model).preamble(
+                "Classify the text into the route best suited to answer it. Do not answer it.",
+            ),
+            routes: vec![],
+            fallback: None,
+        }
+    }
+
+    /// Add instructions to the classifier (e.g.: to describe the routes further).
+    pub fn preamble(mut self, preamble: &str) -> Self {
+        self.classifier = self.classifier.preamble(preamble);
+        self
+    }
+
+    /// Send the prompts of the route `route` to `agent`.
+    pub fn route(mut self, route: R, agent: impl Into<DynAgent>) -> Self {
+        self.routes.push((route, Box::new(agent.into())));
+        self
+    }
+
+    /// Send the prompts of the route `route` (as text) to the pipeline `op`.
+    pub fn route_op<O, E>(mut self, route: R, op: O) -> Self
+    where
+        O: Op<Input = String, Output = Result<String, E>> + 'static,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        self.routes.push((route, Box::new(OpTarget(op))));
+        self
+    }
+
+    /// Send the prompts that cannot be classified, or whose route has no target, to `agent`.
+    pub fn fallback(mut self, agent: impl Into<DynAgent>) -> Self {
+        self.fallback = Some(Box::new(agent.into()));
+        self
+    }
+
+    pub fn build(self) -> RouterAgent<M, R> {
+        RouterAgent {
+            classifier: self.classifier.build(),
+            routes: self.routes,
+            fallback: self.fallback,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{AssistantContent, CompletionError, CompletionRequest, CompletionResponse},
+        pipeline, OneOrMany,
+    };
+
+    #[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum Route {
+        /// Invoices and payments
+        Billing,
+        /// Technical issues
+        Support,
+        /// Anything else
+        Other,
+    }
+
+    /// Submits the route named in the prompt (none if no route is named), or answers `answer` if
+    /// it has no tools
+    #[derive(Clone)]
+    struct RouteModel {
+        answer: &'static str,
+    }
+
+    impl CompletionModel for RouteModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let prompt = request
+                .chat_history
+                .iter()
+                .last()
+                .and_then(Message::rag_text)
+                .unwrap_or_default();
+            let choice = if request.tools.is_empty() {
+                AssistantContent::text(self.answer)
+            } else {
+                match ["billing", "support", "other"]
+                    .into_iter()
+                    .find(|route| prompt.contains(route))
+                {
+                    Some(route) => AssistantContent::tool_call(
+                        "call_1",
+                        "submit",
+                        serde_json::json!({ "route": route }),
+                    ),
+                    None => AssistantContent::text("I cannot classify it"),
+                }
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    fn agent(answer: &'static str) -> DynAgent {
+        AgentBuilder::new(RouteModel { answer }).build().into()
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let support = pipeline::new()
+            .map(|prompt: String| Ok::<_, std::fmt::Error>(format!("support: {prompt}")));
+        let router = RouterAgentBuilder::<Route, _>::new(RouteModel { answer: "" })
+            .route(Route::Billing, agent("refunded"))
+            .route_op(Route::Support, support)
+            .build();
+
+        let response = router.prompt("billing question").await.unwrap();
+        assert_eq!(response.route, Some(Route::Billing));
+        assert_eq!(response.response, "refunded");
+        let response = router.prompt("support question").await.unwrap();
+        assert_eq!(response.response, "support: support question");
+        assert!(matches!(
+            router.prompt("other question").await,
+            Err(RouterError::NoRoute(_))
+        ));
+        assert!(matches!(
+            router.prompt("unclear question").await,
+            Err(RouterError::ExtractionError(ExtractionError::NoData))
+        ));
+
+        let router = RouterAgentBuilder::<Route, _>::new(RouteModel { answer: "" })
+            .fallback(agent("hello"))
+            .build();
+        let response = router.prompt("unclear question").await.unwrap();
+        assert_eq!(response.route, None);
+        assert_eq!(response.response, "hello");
+    }
+}
@@ -0,0 +1,274 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde_json::Value;
+
+use crate::tokens::{HeuristicTokenizer, Tokenizer};
+
+/// Part of the output of a tool kept when it is truncated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TruncationStrategy {
+    /// Keep the beginning of the output
+    Head,
+    /// Keep the beginning and the end of the output (e.g.: the summary of a log), dropping its
+    /// middle
+    #[default]
+    HeadTail,
+}
+
+/// Maximum length (in tokens) of the outputs of the tools of an agent, so that a verbose tool
+/// cannot blow the context window of the model.
+///
+/// The outputs exceeding their limit are truncated before being added to the conversation,
+/// with a marker telling the model how many tokens were dropped. JSON outputs are shrunk
+/// structurally (keeping the first items of the arrays and the beginning of the long strings)
+/// so that the model still receives valid JSON, unless it does not fit anyway.
+///
+/// # Example
+/// ```rust
+/// use rig::{agent::ToolOutputLimits, tokens::HeuristicTokenizer};
+///
+/// let agent = openai.agent(openai::GPT_4O)
+///     .tool(WebSearch)
+///     .tool(ReadFile)
+///     .tool_output_limits(
+///         ToolOutputLimits::new(2_000)
+///             .tool("read_file", 8_000)
+///             .tokenizer(HeuristicTokenizer::new(3)),
+///     )
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct ToolOutputLimits {
+    max_tokens: Option<usize>,
+    per_tool: HashMap<String, usize>,
+    tokenizer: Arc<dyn Tokenizer>,
+    strategy: TruncationStrategy,
+    json_aware: bool,
+}
+
+impl Default for ToolOutputLimits {
+    /// No limit, except for the tools with a limit set by [ToolOutputLimits::tool].
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            per_tool: HashMap::new(),
+            tokenizer: Arc::new(HeuristicTokenizer::default()),
+            strategy: TruncationStrategy::default(),
+            json_aware: true,
+        }
+    }
+}
+
+impl ToolOutputLimits {
+    /// Limit the outputs of all the tools to `max_tokens` tokens, counted with a
+    /// [HeuristicTokenizer] by default.
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens: Some(max_tokens),
+            ..Default::default()
+        }
+    }
+
+    /// Set the limit of the outputs of the tool `toolname`, overriding the global limit.
+    pub fn tool(mut self, toolname: &str, max_tokens: usize) -> Self {
+        self.per_tool.insert(toolname.to_string(), max_tokens);
+        self
+    }
+
+    /// Set the tokenizer counting the tokens of the outputs (e.g.: the tokenizer of the model).
+    pub fn tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    /// Set the part of the outputs kept when they are truncated (default: head and tail).
+    pub fn strategy(mut self, strategy: TruncationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set whether the JSON outputs are shrunk structurally (default: true).
+    pub fn json_aware(mut self, json_aware: bool) -> Self {
+        self.json_aware = json_aware;
+        self
+    }
+
+    /// The limit of the outputs of the tool `toolname`, if any.
+    pub fn limit(&self, toolname: &str) -> Option<usize> {
+        self.per_tool.get(toolname).copied().or(self.max_tokens)
+    }
+
+    /// Truncate the `output` of the tool `toolname` to its limit.
+    pub fn truncate(&self, toolname: &str, output: String) -> String {
+        let Some(max_tokens) = self.limit(toolname) else {
+            return output;
+        };
+        let tokens = self.tokenizer.count_tokens(&output);
+        if tokens <= max_tokens {
+            return output;
+        }
+
+        tracing::debug!(target: "rig",
+            "Truncating the output of the tool {} from {} to {} tokens", toolname, tokens, max_tokens
+        );
+        if self.json_aware {
+            if let Some(json) = self.shrink_json(&output, max_tokens) {
+                return json;
+            }
+        }
+        self.truncate_text(&output, tokens, max_tokens)
+    }
+
+    /// Shrink the JSON `output` by halving the number of items of its arrays and the length of
+    /// its strings until it fits, `None` if the output is not a JSON array or object or does not
+    /// fit anyway.
+    fn shrink_json(&self, output: &str, max_tokens: usize) -> Option<String> {
+        let value = match serde_json::from_str::<Value>(output) {
+            Ok(value @ (Value::Array(_) | Value::Object(_))) => value,
+            _ => return None,
+        };
+
+        let (mut max_items, mut max_chars) = json_extent(&value);
+        loop {
+            max_items = (max_items / 2).max(1);
+            max_chars = (max_chars / 2).max(MIN_STRING_CHARS);
+
+            let json = shrink(&value, max_items, max_chars).to_string();
+            if self.tokenizer.count_tokens(&json) <= max_tokens {
+                return Some(json);
+            }
+            if max_items == 1 && max_chars == MIN_STRING_CHARS {
+                return None;
+            }
+        }
+    }
+
+    fn truncate_text(&self, output: &str, tokens: usize, max_tokens: usize) -> String {
+        let marker = |dropped: usize| format!("\n[... {dropped} tokens truncated ...]\n");
+        let budget = max_tokens.saturating_sub(self.tokenizer.count_tokens(&marker(tokens)));
+
+        match self.strategy {
+            TruncationStrategy::Head => {
+                let head = self.tokenizer.truncate(output, budget);
+                let dropped = tokens.saturating_sub(self.tokenizer.count_tokens(head));
+                format!("{head}{}", marker(dropped).trim_end())
+            }
+            TruncationStrategy::HeadTail => {
+                let head = self.tokenizer.truncate(output, budget * 2 / 3);
+                let head_tokens = self.tokenizer.count_tokens(head);
+                let tail = truncate_start(
+                    &*self.tokenizer,
+                    &output[head.len()..],
+                    budget.saturating_sub(head_tokens),
+                );
+                let dropped =
+                    tokens.saturating_sub(head_tokens + self.tokenizer.count_tokens(tail));
+                format!("{head}{}{tail}", marker(dropped))
+            }
+        }
+    }
+}
+
+/// Minimum number of characters kept of the strings of the JSON outputs.
+const MIN_STRING_CHARS: usize = 16;
+
+/// The number of items of the largest array and of characters of the longest string of `value`.
+fn json_extent(value: &Value) -> (usize, usize) {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(json_extent)
+            .fold((items.len(), 0), |(i, c), (ii, cc)| (i.max(ii), c.max(cc))),
+        Value::Object(map) => map
+            .values()
+            .map(json_extent)
+            .fold((0, 0), |(i, c), (ii, cc)| (i.max(ii), c.max(cc))),
+        Value::String(string) => (0, string.chars().count()),
+        _ => (0, 0),
+    }
+}
+
+fn shrink(value: &Value, max_items: usize, max_chars: usize) -> Value {
+    match value {
+        Value::Array(items) => {
+            let mut shrunk = items
+                .iter()
+                .take(max_items)
+                .map(|item| shrink(item, max_items, max_chars))
+                .collect::<Vec<_>>();
+            if items.len() > max_items {
+                shrunk.push(format!("[... {} more items]", items.len() - max_items).into());
+            }
+            Value::Array(shrunk)
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), shrink(value, max_items, max_chars)))
+                .collect(),
+        ),
+        Value::String(string) if string.chars().count() > max_chars => {
+            let prefix = string.chars().take(max_chars).collect::<String>();
+            format!("{prefix}[... truncated]").into()
+        }
+        value => value.clone(),
+    }
+}
+
+/// Truncate `text` to its longest suffix (on a character boundary) of at most `max_tokens`
+/// tokens.
+fn truncate_start<'a>(tokenizer: &dyn Tokenizer, text: &'a str, max_tokens: usize) -> &'a str {
+    if tokenizer.count_tokens(text) <= max_tokens {
+        return text;
+    }
+
+    let starts = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect::<Vec<_>>();
+    let first_fitting =
+        starts.partition_point(|&start| tokenizer.count_tokens(&text[start..]) > max_tokens);
+
+    &text[starts[first_fitting]..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_tool_output() {
+        // 1 token per byte
+        let limits = ToolOutputLimits::new(50)
+            .tool("verbose", 40)
+            .tool("unlimited", usize::MAX)
+            .tokenizer(HeuristicTokenizer::new(1));
+
+        assert_eq!(limits.truncate("short", "ok".to_string()), "ok");
+
+        let log = format!("start {} end", "x".repeat(100));
+        assert_eq!(limits.truncate("unlimited", log.clone()), log);
+
+        let truncated = limits.truncate("verbose", log.clone());
+        assert!(truncated.len() <= 40);
+        assert!(truncated.starts_with("start"));
+        assert!(truncated.ends_with("end"));
+        assert!(truncated.contains("tokens truncated"));
+
+        let truncated = limits
+            .clone()
+            .strategy(TruncationStrategy::Head)
+            .truncate("verbose", log);
+        assert!(truncated.starts_with("start") && truncated.ends_with("truncated ...]"));
+
+        // JSON outputs stay valid JSON
+        let results = serde_json::json!({
+            "results": (0..20).map(|i| format!("result {i}")).collect::<Vec<_>>()
+        })
+        .to_string();
+        let truncated = limits.truncate("search", results);
+        assert!(truncated.len() <= 50);
+        let truncated = serde_json::from_str::<Value>(&truncated).unwrap();
+        assert_eq!(truncated["results"][0], "result 0");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::wasm_compat::WasmCompatSend;
 use serde_json::Value;
 use thiserror::Error;
 
@@ -38,7 +39,7 @@ pub trait AudioGeneration<M: AudioGenerationModel> {
         voice: &str,
     ) -> impl std::future::Future<
         Output = Result<AudioGenerationRequestBuilder<M>, AudioGenerationError>,
-    > + Send;
+    > + WasmCompatSend;
 }
 
 pub struct AudioGenerationResponse<T> {
@@ -54,7 +55,7 @@ pub trait AudioGenerationModel: Clone + Send + Sync {
         request: AudioGenerationRequest,
     ) -> impl std::future::Future<
         Output = Result<AudioGenerationResponse<Self::Response>, AudioGenerationError>,
-    > + Send;
+    > + WasmCompatSend;
 
     fn audio_generation_request(&self) -> AudioGenerationRequestBuilder<Self> {
         AudioGenerationRequestBuilder::new(self.clone())
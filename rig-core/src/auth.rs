@@ -0,0 +1,505 @@
+//! Pluggable authentication of the provider clients, for credentials which are not a static API
+//! key (e.g.: Azure AD / Entra ID tokens, Google Cloud tokens of a service account).
+//!
+//! An [AuthProvider] returns the headers authenticating each request, refreshing its credentials
+//! when they expire. The following providers are included:
+//! - [StaticKey]: a fixed API key or bearer token
+//! - [EnvKey]: an API key or bearer token read from an environment variable on each request (so
+//!   it can be rotated without restarting)
+//! - [ClientCredentials]: an OAuth2 access token obtained with the client credentials grant,
+//!   refreshed before it expires (e.g.: [ClientCredentials::azure_ad])
+//! - [CloudMetadata]: an access token of the identity of the machine, obtained from the metadata
+//!   server of the cloud it runs on (e.g.: [CloudMetadata::gcp], [CloudMetadata::azure])
+//!
+//! The clients supporting auth providers have a `with_auth` method:
+//! [openai::Client::with_auth](crate::providers::openai::Client::with_auth),
+//! [anthropic::Client::with_auth](crate::providers::anthropic::Client::with_auth),
+//! [azure::Client::with_auth](crate::providers::azure::Client::with_auth) and
+//! [gemini::Client::with_auth](crate::providers::gemini::Client::with_auth). The other clients
+//! are authenticated by their static API key. Note that AWS Bedrock (see the `rig-bedrock`
+//! crate) uses the credential providers of the AWS SDK instead.
+//!
+//! # Example
+//! ```rust
+//! use rig::{auth::ClientCredentials, providers::azure};
+//!
+//! let credentials = ClientCredentials::azure_ad("TENANT_ID", "CLIENT_ID", "CLIENT_SECRET");
+//!
+//! let client = azure::Client::from_auth_provider(credentials, "2024-10-21", "YOUR_ENDPOINT");
+//! let gpt4o = client.completion_model(azure::GPT_4O);
+//! ```
+
+use std::{sync::Arc, time::Duration};
+
+use futures::{lock::Mutex, Future};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Deserializer};
+use web_time::Instant;
+
+use crate::wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync};
+use crate::{
+    completion::CompletionError, embeddings::EmbeddingError, transcription::TranscriptionError,
+};
+
+/// Tokens are refreshed when they expire in less than this margin.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Lifetime assumed for the tokens returned without an `expires_in`.
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(300);
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    /// Http error while fetching a token
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// The environment variable holding the credential is not set
+    #[error("MissingEnvVar: {0} not set")]
+    MissingEnvVar(String),
+
+    /// The credential is not a valid header value
+    #[error("InvalidCredential: {0}")]
+    InvalidCredential(String),
+
+    /// Error returned by the token endpoint
+    #[error("TokenError: {0}")]
+    TokenError(String),
+}
+
+impl From<AuthError> for CompletionError {
+    fn from(err: AuthError) -> Self {
+        CompletionError::RequestError(Box::new(err))
+    }
+}
+
+impl From<AuthError> for EmbeddingError {
+    fn from(err: AuthError) -> Self {
+        EmbeddingError::ProviderError(err.to_string())
+    }
+}
+
+impl From<AuthError> for TranscriptionError {
+    fn from(err: AuthError) -> Self {
+        TranscriptionError::RequestError(Box::new(err))
+    }
+}
+
+impl From<AuthError> for crate::completion::batch::BatchError {
+    fn from(err: AuthError) -> Self {
+        crate::completion::batch::BatchError::CompletionError(err.into())
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<AuthError> for crate::image_generation::ImageGenerationError {
+    fn from(err: AuthError) -> Self {
+        crate::image_generation::ImageGenerationError::RequestError(Box::new(err))
+    }
+}
+
+#[cfg(feature = "audio")]
+impl From<AuthError> for crate::audio_generation::AudioGenerationError {
+    fn from(err: AuthError) -> Self {
+        crate::audio_generation::AudioGenerationError::RequestError(Box::new(err))
+    }
+}
+
+#[cfg(feature = "realtime")]
+impl From<AuthError> for crate::realtime::RealtimeError {
+    fn from(err: AuthError) -> Self {
+        crate::realtime::RealtimeError::ConnectionError(Box::new(err))
+    }
+}
+
+/// Source of the headers authenticating the requests of a provider client.
+pub trait AuthProvider: WasmCompatSend + WasmCompatSync {
+    /// The headers to add to the next request (e.g.: `Authorization: Bearer <token>`), refreshing
+    /// the credentials first if they expired.
+    fn headers(&self) -> WasmBoxedFuture<'_, Result<HeaderMap, AuthError>>;
+}
+
+impl<T: AuthProvider + ?Sized> AuthProvider for Arc<T> {
+    fn headers(&self) -> WasmBoxedFuture<'_, Result<HeaderMap, AuthError>> {
+        (**self).headers()
+    }
+}
+
+fn header(name: &str, value: &str) -> Result<HeaderMap, AuthError> {
+    let name = HeaderName::try_from(name)
+        .map_err(|_| AuthError::InvalidCredential(format!("invalid header name `{name}`")))?;
+    let mut value = HeaderValue::try_from(value)
+        .map_err(|_| AuthError::InvalidCredential(format!("invalid value for header `{name}`")))?;
+    value.set_sensitive(true);
+
+    Ok(HeaderMap::from_iter([(name, value)]))
+}
+
+fn bearer(token: &str) -> Result<HeaderMap, AuthError> {
+    header(
+        reqwest::header::AUTHORIZATION.as_str(),
+        &format!("Bearer {token}"),
+    )
+}
+
+/// A fixed API key or bearer token.
+#[derive(Clone)]
+pub struct StaticKey {
+    name: String,
+    value: String,
+}
+
+impl StaticKey {
+    /// Send `token` in the `Authorization: Bearer <token>` header.
+    pub fn bearer(token: &str) -> Self {
+        Self {
+            name: reqwest::header::AUTHORIZATION.to_string(),
+            value: format!("Bearer {token}"),
+        }
+    }
+
+    /// Send `key` in the header `name` (e.g.: `api-key` for Azure OpenAI).
+    pub fn header(name: &str, key: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: key.to_string(),
+        }
+    }
+}
+
+impl AuthProvider for StaticKey {
+    fn headers(&self) -> WasmBoxedFuture<'_, Result<HeaderMap, AuthError>> {
+        Box::pin(async move { header(&self.name, &self.value) })
+    }
+}
+
+/// An API key or bearer token read from an environment variable on each request.
+#[derive(Clone)]
+pub struct EnvKey {
+    var: String,
+    name: Option<String>,
+}
+
+impl EnvKey {
+    /// Send the content of the environment variable `var` as a bearer token.
+    pub fn bearer(var: &str) -> Self {
+        Self {
+            var: var.to_string(),
+            name: None,
+        }
+    }
+
+    /// Send the content of the environment variable `var` in the header `name`.
+    pub fn header(name: &str, var: &str) -> Self {
+        Self {
+            var: var.to_string(),
+            name: Some(name.to_string()),
+        }
+    }
+}
+
+impl AuthProvider for EnvKey {
+    fn headers(&self) -> WasmBoxedFuture<'_, Result<HeaderMap, AuthError>> {
+        Box::pin(async move {
+            let value =
+                std::env::var(&self.var).map_err(|_| AuthError::MissingEnvVar(self.var.clone()))?;
+            match &self.name {
+                Some(name) => header(name, &value),
+                None => bearer(&value),
+            }
+        })
+    }
+}
+
+/// Response of the OAuth2 token endpoints (and of the metadata servers).
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// Lifetime of the token in seconds (a string for the Azure metadata server)
+    #[serde(default, deserialize_with = "number_or_string")]
+    expires_in: Option<u64>,
+}
+
+fn number_or_string<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    match Option::<NumberOrString>::deserialize(deserializer)? {
+        Some(NumberOrString::Number(n)) => Ok(Some(n)),
+        Some(NumberOrString::String(s)) => s.parse().map(Some).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+impl TokenResponse {
+    async fn from_response(response: reqwest::Response) -> Result<Self, AuthError> {
+        if !response.status().is_success() {
+            return Err(AuthError::TokenError(response.text().await?));
+        }
+
+        let text = response.text().await?;
+        serde_json::from_str(&text).map_err(|err| AuthError::TokenError(err.to_string()))
+    }
+}
+
+/// An access token shared by the clones of a provider, refreshed when it is about to expire.
+#[derive(Clone, Default)]
+struct TokenCache {
+    token: Arc<Mutex<Option<(String, Instant)>>>,
+}
+
+impl TokenCache {
+    /// The cached token, or a new one fetched with `refresh` if it is (about to be) expired. The
+    /// concurrent requests wait for the same refresh.
+    async fn get<F, Fut>(&self, refresh: F) -> Result<String, AuthError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<TokenResponse, AuthError>>,
+    {
+        let mut token = self.token.lock().await;
+
+        if let Some((access_token, expires_at)) = &*token {
+            if Instant::now() + REFRESH_MARGIN < *expires_at {
+                return Ok(access_token.clone());
+            }
+        }
+
+        let response = refresh().await?;
+        let lifetime = response
+            .expires_in
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+        *token = Some((response.access_token.clone(), Instant::now() + lifetime));
+        tracing::debug!(target: "rig", "Refreshed access token (expires in {:?})", lifetime);
+
+        Ok(response.access_token)
+    }
+}
+
+/// An OAuth2 access token obtained with the client credentials grant, and refreshed before it
+/// expires.
+#[derive(Clone)]
+pub struct ClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    http_client: reqwest::Client,
+    cache: TokenCache,
+}
+
+impl ClientCredentials {
+    pub fn new(token_url: &str, client_id: &str, client_secret: &str) -> Self {
+        Self {
+            token_url: token_url.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scope: None,
+            http_client: reqwest::Client::new(),
+            cache: TokenCache::default(),
+        }
+    }
+
+    /// Credentials of an Azure AD (Entra ID) application, for the Azure OpenAI API.
+    pub fn azure_ad(tenant_id: &str, client_id: &str, client_secret: &str) -> Self {
+        Self::new(
+            &format!("https://login.microsoftonline.com/{tenant_id}/oauth2/v2.0/token"),
+            client_id,
+            client_secret,
+        )
+        .scope("https://cognitiveservices.azure.com/.default")
+    }
+
+    /// Set the scope of the requested tokens.
+    pub fn scope(mut self, scope: &str) -> Self {
+        self.scope = Some(scope.to_string());
+        self
+    }
+
+    /// Use a custom HTTP client for the requests to the token endpoint.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    async fn fetch_token(&self) -> Result<TokenResponse, AuthError> {
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", &self.client_id),
+            ("client_secret", &self.client_secret),
+        ];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+
+        let response = self
+            .http_client
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await?;
+        TokenResponse::from_response(response).await
+    }
+}
+
+impl AuthProvider for ClientCredentials {
+    fn headers(&self) -> WasmBoxedFuture<'_, Result<HeaderMap, AuthError>> {
+        Box::pin(async move {
+            let token = self.cache.get(|| self.fetch_token()).await?;
+            bearer(&token)
+        })
+    }
+}
+
+/// An access token of the identity of the machine (e.g.: the service account of a GCE instance
+/// or the managed identity of an Azure VM), obtained from the metadata server of the cloud and
+/// refreshed before it expires.
+#[derive(Clone)]
+pub struct CloudMetadata {
+    url: String,
+    metadata_header: (&'static str, &'static str),
+    http_client: reqwest::Client,
+    cache: TokenCache,
+}
+
+impl CloudMetadata {
+    /// Token of the default service account of a Google Cloud machine (e.g.: for Vertex AI).
+    pub fn gcp() -> Self {
+        Self::new(
+            "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token",
+            ("Metadata-Flavor", "Google"),
+        )
+    }
+
+    /// Token of the managed identity of an Azure machine, for the given resource (e.g.:
+    /// `https://cognitiveservices.azure.com` for the Azure OpenAI API).
+    pub fn azure(resource: &str) -> Self {
+        Self::new(
+            &format!(
+                "http://169.254.169.254/metadata/identity/oauth2/token?api-version=2018-02-01&resource={resource}"
+            ),
+            ("Metadata", "true"),
+        )
+    }
+
+    fn new(url: &str, metadata_header: (&'static str, &'static str)) -> Self {
+        Self {
+            url: url.to_string(),
+            metadata_header,
+            http_client: reqwest::Client::new(),
+            cache: TokenCache::default(),
+        }
+    }
+
+    /// Use a custom HTTP client for the requests to the metadata server.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    async fn fetch_token(&self) -> Result<TokenResponse, AuthError> {
+        let (name, value) = self.metadata_header;
+        let response = self
+            .http_client
+            .get(&self.url)
+            .header(name, value)
+            .send()
+            .await?;
+        TokenResponse::from_response(response).await
+    }
+}
+
+impl AuthProvider for CloudMetadata {
+    fn headers(&self) -> WasmBoxedFuture<'_, Result<HeaderMap, AuthError>> {
+        Box::pin(async move {
+            let token = self.cache.get(|| self.fetch_token()).await?;
+            bearer(&token)
+        })
+    }
+}
+
+/// Add the headers of `auth` (if any) to `request`.
+pub(crate) async fn authorize(
+    request: reqwest::RequestBuilder,
+    auth: Option<&dyn AuthProvider>,
+) -> Result<reqwest::RequestBuilder, AuthError> {
+    match auth {
+        Some(auth) => Ok(request.headers(auth.headers().await?)),
+        None => Ok(request),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_static_and_env_keys() {
+        let headers = StaticKey::bearer("sk-test").headers().await.unwrap();
+        assert_eq!(headers["authorization"], "Bearer sk-test");
+
+        let headers = StaticKey::header("api-key", "key").headers().await.unwrap();
+        assert_eq!(headers["api-key"], "key");
+
+        std::env::set_var("RIG_TEST_AUTH_KEY", "rotated");
+        let headers = EnvKey::header("x-api-key", "RIG_TEST_AUTH_KEY")
+            .headers()
+            .await
+            .unwrap();
+        assert_eq!(headers["x-api-key"], "rotated");
+
+        assert!(matches!(
+            EnvKey::bearer("RIG_TEST_AUTH_MISSING").headers().await,
+            Err(AuthError::MissingEnvVar(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_token_cache() {
+        let cache = TokenCache::default();
+        let refreshes = AtomicUsize::new(0);
+        let refresh = |expires_in| {
+            let n = refreshes.fetch_add(1, Ordering::SeqCst);
+            async move {
+                Ok(TokenResponse {
+                    access_token: format!("token-{n}"),
+                    expires_in: Some(expires_in),
+                })
+            }
+        };
+
+        assert_eq!(cache.get(|| refresh(3600)).await.unwrap(), "token-0");
+        assert_eq!(cache.get(|| refresh(3600)).await.unwrap(), "token-0");
+
+        // Tokens expiring within the refresh margin are refreshed
+        let cache = TokenCache::default();
+        assert_eq!(cache.get(|| refresh(30)).await.unwrap(), "token-1");
+        assert_eq!(cache.get(|| refresh(30)).await.unwrap(), "token-2");
+        assert_eq!(refreshes.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_deserialize_token_response() {
+        let response: TokenResponse =
+            serde_json::from_str(r#"{"access_token": "a", "expires_in": 3599}"#).unwrap();
+        assert_eq!(response.expires_in, Some(3599));
+
+        // Azure metadata server
+        let response: TokenResponse = serde_json::from_str(
+            r#"{"access_token": "b", "expires_in": "3599", "token_type": "Bearer"}"#,
+        )
+        .unwrap();
+        assert_eq!(response.expires_in, Some(3599));
+
+        let response: TokenResponse = serde_json::from_str(r#"{"access_token": "c"}"#).unwrap();
+        assert_eq!(response.expires_in, None);
+    }
+}
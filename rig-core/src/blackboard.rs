@@ -0,0 +1,422 @@
+//! Shared state of cooperating agents: a [Blackboard] is a concurrent key-value store that the
+//! agents, tools and orchestration code of a workflow read and write, e.g.: so that a research
+//! agent hands its findings to a writer agent without stuffing them in the prompts.
+//!
+//! The values are stored as JSON, and accessed either with typed keys ([BlackboardKey]) from
+//! the code, or with the [ReadBlackboard] and [WriteBlackboard] tools by the agents. Every
+//! change is sent to the subscribers of the blackboard (see [Blackboard::subscribe]), and
+//! [Blackboard::wait_for] waits until a value is written (e.g.: by another agent).
+//!
+//! # Example
+//! ```rust
+//! use rig::blackboard::{Blackboard, BlackboardKey};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Findings {
+//!     sources: Vec<String>,
+//!     summary: String,
+//! }
+//!
+//! const FINDINGS: BlackboardKey<Findings> = BlackboardKey::new("findings");
+//!
+//! let blackboard = Blackboard::new();
+//! let researcher = openai.agent(openai::GPT_4O)
+//!     .preamble("Research the topic, then write your findings to the `findings` key.")
+//!     .tool(web_search)
+//!     .tool(blackboard.write_tool())
+//!     .build();
+//!
+//! researcher.prompt("Research the history of the Rust language").await?;
+//! let findings = blackboard.get(&FINDINGS)?.expect("The researcher wrote its findings");
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use futures::{channel::mpsc, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{completion::ToolDefinition, tool::Tool};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlackboardError {
+    /// The value cannot be (de)serialized as the type of its key
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Name of a value of a [Blackboard], with its type.
+pub struct BlackboardKey<T> {
+    name: &'static str,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> BlackboardKey<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> Clone for BlackboardKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BlackboardKey<T> {}
+
+/// A change of a value of a [Blackboard].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlackboardChange {
+    pub key: String,
+    /// The new value, `None` if the value was removed
+    pub value: Option<Value>,
+    /// Version of the blackboard after the change, incremented by every change
+    pub version: u64,
+}
+
+#[derive(Default)]
+struct BlackboardState {
+    values: BTreeMap<String, Value>,
+    version: u64,
+    subscribers: Vec<mpsc::UnboundedSender<BlackboardChange>>,
+}
+
+impl BlackboardState {
+    fn change(&mut self, key: &str, value: Option<Value>) -> u64 {
+        self.version += 1;
+        match &value {
+            Some(value) => self.values.insert(key.to_string(), value.clone()),
+            None => self.values.remove(key),
+        };
+        let change = BlackboardChange {
+            key: key.to_string(),
+            value,
+            version: self.version,
+        };
+        // The dropped subscriptions are forgotten
+        self.subscribers
+            .retain(|subscriber| subscriber.unbounded_send(change.clone()).is_ok());
+        self.version
+    }
+}
+
+/// Concurrent key-value store shared by the agents and tools of a workflow (see the
+/// [module documentation](self)). The clones of a blackboard share its values.
+#[derive(Clone, Default)]
+pub struct Blackboard {
+    state: Arc<Mutex<BlackboardState>>,
+}
+
+impl Blackboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BlackboardState> {
+        self.state.lock().expect("Blackboard lock poisoned")
+    }
+
+    /// The value of `key`, if any.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        key: &BlackboardKey<T>,
+    ) -> Result<Option<T>, BlackboardError> {
+        self.get_json(key.name)
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Set the value of `key`, returning the new version of the blackboard.
+    pub fn set<T: Serialize>(
+        &self,
+        key: &BlackboardKey<T>,
+        value: &T,
+    ) -> Result<u64, BlackboardError> {
+        Ok(self.set_json(key.name, serde_json::to_value(value)?))
+    }
+
+    /// Update the value of `key` with `update` (called with the current value, if any),
+    /// atomically with respect to the other writers, returning the new version.
+    pub fn update<T: Serialize + DeserializeOwned>(
+        &self,
+        key: &BlackboardKey<T>,
+        update: impl FnOnce(Option<T>) -> T,
+    ) -> Result<u64, BlackboardError> {
+        let mut state = self.state();
+        let current = state
+            .values
+            .get(key.name)
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()?;
+        let value = serde_json::to_value(update(current))?;
+        Ok(state.change(key.name, Some(value)))
+    }
+
+    /// Remove the value of `key`, returning whether it was set.
+    pub fn remove<T>(&self, key: &BlackboardKey<T>) -> bool {
+        self.remove_json(key.name)
+    }
+
+    /// The JSON value of the key `name`, if any.
+    pub fn get_json(&self, name: &str) -> Option<Value> {
+        self.state().values.get(name).cloned()
+    }
+
+    /// Set the JSON value of the key `name`, returning the new version of the blackboard.
+    pub fn set_json(&self, name: &str, value: Value) -> u64 {
+        self.state().change(name, Some(value))
+    }
+
+    /// Remove the value of the key `name`, returning whether it was set.
+    pub fn remove_json(&self, name: &str) -> bool {
+        let mut state = self.state();
+        if !state.values.contains_key(name) {
+            return false;
+        }
+        state.change(name, None);
+        true
+    }
+
+    /// The keys of the values of the blackboard, in alphabetical order.
+    pub fn keys(&self) -> Vec<String> {
+        self.state().values.keys().cloned().collect()
+    }
+
+    /// Version of the blackboard, incremented by every change.
+    pub fn version(&self) -> u64 {
+        self.state().version
+    }
+
+    /// Stream of the changes of the blackboard, from now on.
+    pub fn subscribe(&self) -> impl Stream<Item = BlackboardChange> + Unpin {
+        let (sender, receiver) = mpsc::unbounded();
+        self.state().subscribers.push(sender);
+        receiver
+    }
+
+    /// Wait until `key` has a value (e.g.: written by another agent), and return it.
+    pub async fn wait_for<T: DeserializeOwned>(
+        &self,
+        key: &BlackboardKey<T>,
+    ) -> Result<T, BlackboardError> {
+        // Subscribe before reading the value, so that no change is missed in between
+        let mut changes = self.subscribe();
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
+        }
+        while let Some(change) = changes.next().await {
+            if let (true, Some(value)) = (change.key == key.name, change.value) {
+                return Ok(serde_json::from_value(value)?);
+            }
+        }
+        unreachable!("The blackboard outlives its subscriptions")
+    }
+
+    /// The tool reading the blackboard (see [ReadBlackboard]).
+    pub fn read_tool(&self) -> ReadBlackboard {
+        ReadBlackboard(self.clone())
+    }
+
+    /// The tool writing to the blackboard (see [WriteBlackboard]).
+    pub fn write_tool(&self) -> WriteBlackboard {
+        WriteBlackboard(self.clone())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReadBlackboardArgs {
+    /// Key of the value to read, all the keys being listed if `None`
+    pub key: Option<String>,
+}
+
+/// Tool reading a value of a [Blackboard] (or listing its keys), named `read_blackboard`.
+#[derive(Clone)]
+pub struct ReadBlackboard(Blackboard);
+
+impl Tool for ReadBlackboard {
+    const NAME: &'static str = "read_blackboard";
+
+    type Error = BlackboardError;
+    type Args = ReadBlackboardArgs;
+    type Output = Value;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Read a value of the blackboard shared with the other agents, or list \
+                its keys when no key is given."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "key": {
+                        "type": "string",
+                        "description": "The key of the value to read"
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Ok(match args.key {
+            Some(key) => json!({"key": key, "value": self.0.get_json(&key)}),
+            None => json!({"keys": self.0.keys()}),
+        })
+    }
+
+    fn static_definition(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WriteBlackboardArgs {
+    /// Key of the value to write
+    pub key: String,
+    /// The value, which can be any JSON value
+    pub value: Value,
+}
+
+/// Tool writing a value to a [Blackboard], named `write_blackboard`.
+#[derive(Clone)]
+pub struct WriteBlackboard(Blackboard);
+
+impl Tool for WriteBlackboard {
+    const NAME: &'static str = "write_blackboard";
+
+    type Error = BlackboardError;
+    type Args = WriteBlackboardArgs;
+    type Output = Value;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Write a value to the blackboard shared with the other agents, \
+                replacing the previous value of its key."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "key": {
+                        "type": "string",
+                        "description": "The key of the value"
+                    },
+                    "value": {
+                        "description": "The value to write (any JSON value)"
+                    }
+                },
+                "required": ["key", "value"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let version = self.0.set_json(&args.key, args.value);
+        Ok(json!({"key": args.key, "version": version}))
+    }
+
+    fn static_definition(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Findings {
+        summary: String,
+    }
+
+    const FINDINGS: BlackboardKey<Findings> = BlackboardKey::new("findings");
+    const COUNT: BlackboardKey<u32> = BlackboardKey::new("count");
+
+    #[tokio::test]
+    async fn test_blackboard() {
+        let blackboard = Blackboard::new();
+        let mut changes = blackboard.subscribe();
+
+        // A value written by an agent, read with its typed key by another one
+        let waiting = blackboard.clone();
+        let waiter = tokio::spawn(async move { waiting.wait_for(&FINDINGS).await });
+        blackboard
+            .write_tool()
+            .call(WriteBlackboardArgs {
+                key: "findings".to_string(),
+                value: json!({"summary": "Rust 1.0 was released in 2015"}),
+            })
+            .await
+            .unwrap();
+        let findings = Findings {
+            summary: "Rust 1.0 was released in 2015".to_string(),
+        };
+        assert_eq!(waiter.await.unwrap().unwrap(), findings);
+        assert_eq!(blackboard.get(&FINDINGS).unwrap(), Some(findings));
+
+        assert_eq!(
+            blackboard
+                .update(&COUNT, |count| count.unwrap_or(0) + 1)
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            blackboard
+                .update(&COUNT, |count| count.unwrap_or(0) + 1)
+                .unwrap(),
+            3
+        );
+        assert_eq!(blackboard.get(&COUNT).unwrap(), Some(2));
+        assert!(blackboard.get_json("missing").is_none());
+        assert!(blackboard.remove(&FINDINGS));
+        assert!(!blackboard.remove(&FINDINGS));
+
+        let output = blackboard
+            .read_tool()
+            .call(ReadBlackboardArgs { key: None })
+            .await
+            .unwrap();
+        assert_eq!(output, json!({"keys": ["count"]}));
+
+        let versions = changes
+            .by_ref()
+            .take(4)
+            .map(|change| (change.key, change.version))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            versions,
+            vec![
+                ("findings".to_string(), 1),
+                ("count".to_string(), 2),
+                ("count".to_string(), 3),
+                ("findings".to_string(), 4),
+            ]
+        );
+        assert!(matches!(
+            blackboard.set(
+                &BlackboardKey::<Findings>::new("count"),
+                &Findings {
+                    summary: String::new()
+                }
+            ),
+            Ok(5)
+        ));
+        assert!(blackboard.get(&COUNT).is_err());
+    }
+}
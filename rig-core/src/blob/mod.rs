@@ -0,0 +1,414 @@
+//! This module provides stores of the large binary contents of the messages (images, audio,
+//! documents), so that the chat histories only hold their handles.
+//!
+//! The contents are stored in a [BlobStore]:
+//! - [MemoryBlobStore]: process-local store, e.g.: for tests
+//! - [FileBlobStore]: one file per blob in a directory
+//! - [S3BlobStore]: objects of an S3 (or S3 compatible) bucket, which can also be downloaded by
+//!   the providers from presigned URLs
+//!
+//! The inline (base64) contents of messages are replaced by the handles of their blobs with
+//! [offload] (the contents are stored with the [Blob](crate::message::ContentFormat::Blob)
+//! format), and the handles are resolved back to the contents with [resolve]. The [BlobModel]
+//! wrapper of completion models resolves the handles of its requests lazily, before they are
+//! sent to the provider: the contents are inlined, or sent as URLs of the store if the
+//! [Resolution] prefers them (e.g.: for the providers downloading the images from URLs).
+//!
+//! # Example
+//! ```rust
+//! use rig::{blob::{self, BlobModel, FileBlobStore}, completion::Prompt, providers::openai};
+//!
+//! let store = FileBlobStore::new("blobs")?;
+//! let model = BlobModel::new(
+//!     openai::Client::from_env().completion_model(openai::GPT_4O),
+//!     store.clone(),
+//! );
+//! let agent = rig::agent::AgentBuilder::new(model).build();
+//!
+//! // Store the images of the history in the blob store before persisting it
+//! blob::offload(&mut history, &store, 1024).await?;
+//! let answer = agent.chat("What is in the last image?", history.clone()).await?;
+//! ```
+
+use std::sync::Arc;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    completion::{
+        Capabilities, CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
+    },
+    message::{ContentFormat, Message, MimeType, ToolResultContent, UserContent},
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+};
+
+pub mod s3;
+pub mod store;
+
+pub use s3::S3BlobStore;
+pub use store::{FileBlobStore, MemoryBlobStore};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlobError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// No blob has the handle
+    #[error("NotFound: {0}")]
+    NotFound(String),
+
+    /// The handle cannot be used by the store (e.g.: as a file name)
+    #[error("InvalidHandle: {0}")]
+    InvalidHandle(String),
+
+    /// The inline content is not valid base64
+    #[error("InvalidData: {0}")]
+    InvalidData(String),
+
+    /// Error of the store (e.g.: an error response of S3)
+    #[error("StoreError: {0}")]
+    StoreError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Storage of binary contents (blobs), by handle.
+pub trait BlobStore: WasmCompatSend + WasmCompatSync {
+    /// Store `data` (of the MIME type `mime_type`, if known), returning its handle.
+    fn put<'a>(
+        &'a self,
+        data: &'a [u8],
+        mime_type: Option<&'a str>,
+    ) -> WasmBoxedFuture<'a, Result<String, BlobError>>;
+
+    /// The content of the blob `handle`.
+    fn get<'a>(&'a self, handle: &'a str) -> WasmBoxedFuture<'a, Result<Vec<u8>, BlobError>>;
+
+    /// A URL from which the providers can download the blob `handle`, if the store serves its
+    /// blobs (e.g.: a presigned URL of S3).
+    fn url<'a>(
+        &'a self,
+        _handle: &'a str,
+    ) -> WasmBoxedFuture<'a, Result<Option<String>, BlobError>> {
+        Box::pin(async { Ok(None) })
+    }
+}
+
+/// The content addressed handle of `data` (its SHA-256 hash, in hexadecimal), so that the
+/// contents stored several times (e.g.: in several histories) are stored once.
+pub fn content_handle(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// How the handles of the blobs of the requests are resolved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Resolution {
+    /// Inline the contents of the blobs in base64, which all the providers accept
+    #[default]
+    Inline,
+    /// Send the images as the URLs of the store if it serves them (e.g.: for OpenAI), or else
+    /// inline them
+    PreferUrl,
+}
+
+/// Binary content of a message.
+struct BinaryContent<'a> {
+    data: &'a mut String,
+    format: &'a mut Option<ContentFormat>,
+    mime_type: Option<&'static str>,
+    image: bool,
+    /// Whether the contents without format are base64
+    base64_by_default: bool,
+}
+
+/// The binary contents of `messages` (and of their tool results).
+fn binary_contents<'a>(
+    messages: impl IntoIterator<Item = &'a mut Message>,
+) -> Vec<BinaryContent<'a>> {
+    let mut contents = vec![];
+    for message in messages {
+        let Message::User { content } = message else {
+            continue;
+        };
+        for content in content.iter_mut() {
+            match content {
+                UserContent::Image(image) => contents.push(BinaryContent {
+                    mime_type: image.media_type.as_ref().map(MimeType::to_mime_type),
+                    data: &mut image.data,
+                    format: &mut image.format,
+                    image: true,
+                    base64_by_default: true,
+                }),
+                UserContent::Audio(audio) => contents.push(BinaryContent {
+                    mime_type: audio.media_type.as_ref().map(MimeType::to_mime_type),
+                    data: &mut audio.data,
+                    format: &mut audio.format,
+                    image: false,
+                    base64_by_default: true,
+                }),
+                // The documents without format are often text
+                UserContent::Document(document) => contents.push(BinaryContent {
+                    mime_type: document.media_type.as_ref().map(MimeType::to_mime_type),
+                    data: &mut document.data,
+                    format: &mut document.format,
+                    image: false,
+                    base64_by_default: false,
+                }),
+                UserContent::ToolResult(tool_result) => {
+                    for content in tool_result.content.iter_mut() {
+                        if let ToolResultContent::Image(image) = content {
+                            contents.push(BinaryContent {
+                                mime_type: image.media_type.as_ref().map(MimeType::to_mime_type),
+                                data: &mut image.data,
+                                format: &mut image.format,
+                                image: true,
+                                base64_by_default: true,
+                            });
+                        }
+                    }
+                }
+                UserContent::Text(_) => {}
+            }
+        }
+    }
+    contents
+}
+
+/// Replace the inline (base64) contents of `messages` of at least `min_size` bytes (encoded) by
+/// the handles of their blobs, stored in `store`.
+pub async fn offload<'a>(
+    messages: impl IntoIterator<Item = &'a mut Message>,
+    store: &dyn BlobStore,
+    min_size: usize,
+) -> Result<(), BlobError> {
+    for content in binary_contents(messages) {
+        let base64 = match content.format {
+            Some(ContentFormat::Base64) => true,
+            None => content.base64_by_default,
+            _ => false,
+        };
+        if !base64 || content.data.len() < min_size {
+            continue;
+        }
+
+        let data = BASE64_STANDARD
+            .decode(content.data.as_bytes())
+            .map_err(|e| BlobError::InvalidData(e.to_string()))?;
+        *content.data = store.put(&data, content.mime_type).await?;
+        *content.format = Some(ContentFormat::Blob);
+    }
+    Ok(())
+}
+
+/// Replace the handles of the blobs of `messages` by their contents in `store` (or by their
+/// URLs, see [Resolution]).
+pub async fn resolve<'a>(
+    messages: impl IntoIterator<Item = &'a mut Message>,
+    store: &dyn BlobStore,
+    resolution: Resolution,
+) -> Result<(), BlobError> {
+    for content in binary_contents(messages) {
+        if *content.format != Some(ContentFormat::Blob) {
+            continue;
+        }
+
+        if resolution == Resolution::PreferUrl && content.image {
+            if let Some(url) = store.url(content.data).await? {
+                *content.data = url;
+                *content.format = Some(ContentFormat::String);
+                continue;
+            }
+        }
+        *content.data = BASE64_STANDARD.encode(store.get(content.data).await?);
+        *content.format = Some(ContentFormat::Base64);
+    }
+    Ok(())
+}
+
+/// Completion model wrapper resolving the handles of the blobs of the requests (see [resolve])
+/// before sending them to the model.
+#[derive(Clone)]
+pub struct BlobModel<M> {
+    model: M,
+    store: Arc<dyn BlobStore>,
+    resolution: Resolution,
+}
+
+impl<M: CompletionModel> BlobModel<M> {
+    pub fn new(model: M, store: impl BlobStore + 'static) -> Self {
+        Self {
+            model,
+            store: Arc::new(store),
+            resolution: Resolution::default(),
+        }
+    }
+
+    /// Set how the handles are resolved (default: [Resolution::Inline]).
+    pub fn resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Get the blob store
+    pub fn store(&self) -> &dyn BlobStore {
+        &*self.store
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for BlobModel<M> {
+    type Response = M::Response;
+
+    fn capabilities(&self) -> Capabilities {
+        self.model.capabilities()
+    }
+
+    async fn completion(
+        &self,
+        mut request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        resolve(
+            request.chat_history.iter_mut(),
+            &*self.store,
+            self.resolution,
+        )
+        .await
+        .map_err(|e| CompletionError::RequestError(e.into()))?;
+        self.model.completion(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        completion::{AssistantContent, CompletionRequestBuilder},
+        message::ImageMediaType,
+        OneOrMany,
+    };
+
+    /// Format and data of an image
+    type Image = (Option<ContentFormat>, String);
+
+    /// Records the formats and data of the images of the prompt
+    #[derive(Clone, Default)]
+    struct ImageModel(Arc<Mutex<Vec<Image>>>);
+
+    impl CompletionModel for ImageModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            if let Message::User { content } = request.chat_history.first() {
+                for content in content.iter() {
+                    if let UserContent::Image(image) = content {
+                        self.0
+                            .lock()
+                            .unwrap()
+                            .push((image.format.clone(), image.data.clone()));
+                    }
+                }
+            }
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text("ok")),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    /// Serves the blobs of a memory store from URLs
+    struct UrlStore(MemoryBlobStore);
+
+    impl BlobStore for UrlStore {
+        fn put<'a>(
+            &'a self,
+            data: &'a [u8],
+            mime_type: Option<&'a str>,
+        ) -> WasmBoxedFuture<'a, Result<String, BlobError>> {
+            self.0.put(data, mime_type)
+        }
+
+        fn get<'a>(&'a self, handle: &'a str) -> WasmBoxedFuture<'a, Result<Vec<u8>, BlobError>> {
+            self.0.get(handle)
+        }
+
+        fn url<'a>(
+            &'a self,
+            handle: &'a str,
+        ) -> WasmBoxedFuture<'a, Result<Option<String>, BlobError>> {
+            Box::pin(async move { Ok(Some(format!("https://blobs.test/{handle}"))) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blob_model() {
+        let image = BASE64_STANDARD.encode(vec![7u8; 100]);
+        let mut history = vec![Message::User {
+            content: OneOrMany::many(vec![
+                UserContent::text("What is it?"),
+                UserContent::image(image.clone(), None, Some(ImageMediaType::PNG), None),
+                UserContent::image("small", None, Some(ImageMediaType::PNG), None),
+                UserContent::document("A text", Some(ContentFormat::String), None),
+            ])
+            .unwrap(),
+        }];
+        let store = MemoryBlobStore::new();
+        offload(&mut history, &store, 64).await.unwrap();
+
+        let Message::User { content } = &history[0] else {
+            unreachable!()
+        };
+        let handle = content_handle(&[7u8; 100]);
+        assert!(matches!(
+            content.iter().nth(1),
+            Some(UserContent::Image(image))
+                if image.data == handle && image.format == Some(ContentFormat::Blob)
+        ));
+        assert!(matches!(
+            content.iter().nth(2),
+            Some(UserContent::Image(image)) if image.data == "small" && image.format.is_none()
+        ));
+        assert!(matches!(
+            content.iter().nth(3),
+            Some(UserContent::Document(document)) if document.data == "A text"
+        ));
+
+        let model = ImageModel::default();
+        let prompt = history[0].clone();
+        CompletionRequestBuilder::new(BlobModel::new(model.clone(), store.clone()), prompt.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            model.0.lock().unwrap()[0],
+            (Some(ContentFormat::Base64), image)
+        );
+
+        let url_model = BlobModel::new(model.clone(), UrlStore(store.clone()))
+            .resolution(Resolution::PreferUrl);
+        CompletionRequestBuilder::new(url_model, prompt.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            model.0.lock().unwrap()[2],
+            (
+                Some(ContentFormat::String),
+                format!("https://blobs.test/{handle}")
+            )
+        );
+
+        assert!(matches!(
+            CompletionRequestBuilder::new(BlobModel::new(model, MemoryBlobStore::new()), prompt)
+                .send()
+                .await,
+            Err(CompletionError::RequestError(_))
+        ));
+    }
+}
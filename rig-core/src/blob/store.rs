@@ -0,0 +1,134 @@
+//! Local blob stores.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use super::{content_handle, BlobError, BlobStore};
+use crate::wasm_compat::WasmBoxedFuture;
+
+/// [BlobStore] keeping the blobs in memory.
+#[derive(Clone, Default)]
+pub struct MemoryBlobStore {
+    blobs: Arc<RwLock<HashMap<String, Vec<u8>>>>,
+}
+
+impl MemoryBlobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blobs.read().expect("Blob store lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl BlobStore for MemoryBlobStore {
+    fn put<'a>(
+        &'a self,
+        data: &'a [u8],
+        _mime_type: Option<&'a str>,
+    ) -> WasmBoxedFuture<'a, Result<String, BlobError>> {
+        let handle = content_handle(data);
+        self.blobs
+            .write()
+            .expect("Blob store lock poisoned")
+            .insert(handle.clone(), data.to_vec());
+        Box::pin(async move { Ok(handle) })
+    }
+
+    fn get<'a>(&'a self, handle: &'a str) -> WasmBoxedFuture<'a, Result<Vec<u8>, BlobError>> {
+        let blob = self
+            .blobs
+            .read()
+            .expect("Blob store lock poisoned")
+            .get(handle)
+            .cloned()
+            .ok_or_else(|| BlobError::NotFound(handle.to_string()));
+        Box::pin(async move { blob })
+    }
+}
+
+/// [BlobStore] saving each blob in a file of a directory, named after its handle.
+#[derive(Clone, Debug)]
+pub struct FileBlobStore {
+    dir: PathBuf,
+}
+
+impl FileBlobStore {
+    /// Store saving the blobs in the directory `dir`, created if needed.
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, BlobError> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn path(&self, handle: &str) -> Result<PathBuf, BlobError> {
+        if handle.is_empty() || !handle.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(BlobError::InvalidHandle(handle.to_string()));
+        }
+        Ok(self.dir.join(handle))
+    }
+}
+
+impl BlobStore for FileBlobStore {
+    fn put<'a>(
+        &'a self,
+        data: &'a [u8],
+        _mime_type: Option<&'a str>,
+    ) -> WasmBoxedFuture<'a, Result<String, BlobError>> {
+        Box::pin(async move {
+            let handle = content_handle(data);
+            let path = self.path(&handle)?;
+            // The blobs are content addressed, so an existing file holds the same data
+            if !path.exists() {
+                std::fs::write(path, data)?;
+            }
+            Ok(handle)
+        })
+    }
+
+    fn get<'a>(&'a self, handle: &'a str) -> WasmBoxedFuture<'a, Result<Vec<u8>, BlobError>> {
+        Box::pin(async move {
+            match std::fs::read(self.path(handle)?) {
+                Ok(data) => Ok(data),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    Err(BlobError::NotFound(handle.to_string()))
+                }
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_blob_store() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let store = FileBlobStore::new(dir.path().join("blobs")).unwrap();
+
+        let handle = store.put(b"hello", Some("text/plain")).await.unwrap();
+        assert_eq!(handle, content_handle(b"hello"));
+        assert_eq!(store.put(b"hello", None).await.unwrap(), handle);
+        assert_eq!(store.get(&handle).await.unwrap(), b"hello");
+
+        assert!(matches!(
+            store.get(&content_handle(b"other")).await,
+            Err(BlobError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.get("../secret").await,
+            Err(BlobError::InvalidHandle(_))
+        ));
+    }
+}
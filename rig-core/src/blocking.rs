@@ -0,0 +1,205 @@
+//! Blocking (synchronous) facade of the agents, embedding models and vector store indexes,
+//! for CLI tools, scripts and other contexts which do not want to be async.
+//!
+//! The blocking methods drive the futures on a global, single-threaded tokio runtime (which
+//! also provides the reactor required by the HTTP client of the providers). They must not be
+//! called from within an async runtime: use the async methods there.
+//!
+//! Requires the `blocking` feature.
+//!
+//! # Example
+//! ```rust
+//! use rig::{blocking::PromptBlocking, providers::openai};
+//!
+//! let agent = openai::Client::from_env().agent(openai::GPT_4O).build();
+//!
+//! let response = agent.prompt_blocking("Hello!")?;
+//! ```
+
+use std::{future::Future, future::IntoFuture, sync::LazyLock};
+
+use serde::Deserialize;
+
+use crate::{
+    completion::{Chat, Message, Prompt, PromptError},
+    embeddings::{Embedding, EmbeddingError, EmbeddingModel},
+    vector_store::{VectorStoreError, VectorStoreIndex},
+};
+
+static RUNTIME: LazyLock<tokio::runtime::Runtime> = LazyLock::new(|| {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build the tokio runtime of the blocking facade")
+});
+
+/// Run `future` to completion on the global runtime of the blocking facade, blocking the
+/// current thread.
+///
+/// # Panics
+/// If called from within a tokio runtime.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        panic!("The blocking methods of rig cannot be called from within an async runtime");
+    }
+    RUNTIME.block_on(future)
+}
+
+/// Blocking version of [Prompt].
+// Same errors as the async methods
+#[allow(clippy::result_large_err)]
+pub trait PromptBlocking {
+    /// Send a prompt and block until the response.
+    /// See [Prompt::prompt].
+    fn prompt_blocking(&self, prompt: impl Into<Message> + Send) -> Result<String, PromptError>;
+}
+
+impl<T: Prompt> PromptBlocking for T {
+    fn prompt_blocking(&self, prompt: impl Into<Message> + Send) -> Result<String, PromptError> {
+        block_on(self.prompt(prompt).into_future())
+    }
+}
+
+/// Blocking version of [Chat].
+#[allow(clippy::result_large_err)]
+pub trait ChatBlocking {
+    /// Send a prompt with the chat history and block until the response.
+    /// See [Chat::chat].
+    fn chat_blocking(
+        &self,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<String, PromptError>;
+}
+
+impl<T: Chat> ChatBlocking for T {
+    fn chat_blocking(
+        &self,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<String, PromptError> {
+        block_on(self.chat(prompt, chat_history).into_future())
+    }
+}
+
+/// Blocking version of [EmbeddingModel].
+pub trait EmbeddingModelBlocking {
+    /// Embed a single text document, blocking until the embedding.
+    /// See [EmbeddingModel::embed_text].
+    fn embed_text_blocking(&self, text: &str) -> Result<Embedding, EmbeddingError>;
+
+    /// Embed multiple text documents in a single request, blocking until the embeddings.
+    /// See [EmbeddingModel::embed_texts].
+    fn embed_texts_blocking(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError>;
+}
+
+impl<T: EmbeddingModel> EmbeddingModelBlocking for T {
+    fn embed_text_blocking(&self, text: &str) -> Result<Embedding, EmbeddingError> {
+        block_on(self.embed_text(text))
+    }
+
+    fn embed_texts_blocking(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        block_on(self.embed_texts(texts))
+    }
+}
+
+/// Blocking version of [VectorStoreIndex].
+pub trait VectorStoreIndexBlocking {
+    /// Get the top n documents based on the distance to the given query, blocking until the
+    /// results. See [VectorStoreIndex::top_n].
+    fn top_n_blocking<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError>;
+
+    /// Same as `top_n_blocking` but returns the document ids only.
+    fn top_n_ids_blocking(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError>;
+}
+
+impl<I: VectorStoreIndex> VectorStoreIndexBlocking for I {
+    fn top_n_blocking<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        block_on(self.top_n(query, n))
+    }
+
+    fn top_n_ids_blocking(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        block_on(self.top_n_ids(query, n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse,
+        },
+        OneOrMany,
+    };
+
+    #[derive(Clone)]
+    struct EchoModel;
+
+    impl CompletionModel for EchoModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            // Requires a tokio context, as the HTTP clients of the providers
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!(
+                    "{} messages",
+                    request.chat_history.len()
+                ))),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn test_blocking() {
+        let agent = AgentBuilder::new(EchoModel).build();
+        assert_eq!(agent.prompt_blocking("Hi").unwrap(), "1 messages");
+        assert_eq!(
+            agent
+                .chat_blocking(
+                    "Hi",
+                    vec![Message::user("Hello"), Message::assistant("Hey")]
+                )
+                .unwrap(),
+            "3 messages"
+        );
+
+        let in_runtime = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async { agent.prompt_blocking("Hi") })
+        }));
+        assert!(in_runtime.is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! Key-value cache backends shared by the cache layers.
+//!
+//! The following backends are provided:
+//! - [InMemoryCache]: process-local cache
+//! - [DiskCache]: one JSON file per entry in a directory, which persists across runs
+//!   (e.g.: committed to the repository or cached in CI)
+//! - [RedisCache]: shared cache stored in Redis (requires the `redis` feature)
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{now, CacheError};
+use crate::{
+    runtime::spawn_blocking,
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+};
+
+/// Trait for key-value cache backends storing JSON values.
+pub trait CacheBackend: WasmCompatSend + WasmCompatSync {
+    /// Get the non-expired value stored under `key`, if any.
+    fn get<'a>(&'a self, key: &'a str) -> WasmBoxedFuture<'a, Result<Option<Value>, CacheError>>;
+
+    /// Store `value` under `key` for `ttl` (or without expiration), replacing the previous value.
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> WasmBoxedFuture<'a, Result<(), CacheError>>;
+}
+
+/// A value stored with its expiration time (in seconds since the UNIX epoch).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Entry {
+    value: Value,
+    expires_at: Option<u64>,
+}
+
+impl Entry {
+    fn new(value: Value, ttl: Option<Duration>) -> Self {
+        Self {
+            value,
+            expires_at: ttl.map(|ttl| now() + ttl.as_secs()),
+        }
+    }
+
+    fn into_value(self) -> Option<Value> {
+        match self.expires_at {
+            Some(expires_at) if expires_at <= now() => None,
+            _ => Some(self.value),
+        }
+    }
+}
+
+/// In-memory cache backend.
+#[derive(Clone, Default)]
+pub struct InMemoryCache {
+    entries: Arc<RwLock<HashMap<String, Entry>>>,
+}
+
+impl InMemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().expect("Lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheBackend for InMemoryCache {
+    fn get<'a>(&'a self, key: &'a str) -> WasmBoxedFuture<'a, Result<Option<Value>, CacheError>> {
+        Box::pin(async move {
+            Ok(self
+                .entries
+                .read()
+                .expect("Lock poisoned")
+                .get(key)
+                .cloned()
+                .and_then(Entry::into_value))
+        })
+    }
+
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> WasmBoxedFuture<'a, Result<(), CacheError>> {
+        Box::pin(async move {
+            self.entries
+                .write()
+                .expect("Lock poisoned")
+                .insert(key.to_string(), Entry::new(value, ttl));
+            Ok(())
+        })
+    }
+}
+
+/// Disk cache backend. Each entry is stored as a JSON file named after the SHA-256 hash of its
+/// key. The files are read and written off the executor, without requiring a specific async
+/// runtime (see [runtime](crate::runtime)).
+#[derive(Clone, Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Create a disk cache in `dir`, creating the directory if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, CacheError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        // Keys may contain characters that are not valid in file names (e.g.: `/` in model names),
+        // so the file names are hashes of the keys rather than (lossy) sanitized keys
+        self.dir
+            .join(format!("{:x}.json", Sha256::digest(key.as_bytes())))
+    }
+}
+
+impl CacheBackend for DiskCache {
+    fn get<'a>(&'a self, key: &'a str) -> WasmBoxedFuture<'a, Result<Option<Value>, CacheError>> {
+        let path = self.path(key);
+        Box::pin(async move {
+            match spawn_blocking(move || std::fs::read_to_string(path)).await {
+                Ok(content) => Ok(serde_json::from_str::<Entry>(&content)?.into_value()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> WasmBoxedFuture<'a, Result<(), CacheError>> {
+        let path = self.path(key);
+        Box::pin(async move {
+            let content = serde_json::to_string_pretty(&Entry::new(value, ttl))?;
+            spawn_blocking(move || std::fs::write(path, content)).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Redis cache backend. Entries with a TTL are stored with a Redis expiration.
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisCache {
+    connection: redis::aio::MultiplexedConnection,
+    prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisCache {
+    /// Connect to the Redis server at `url` (e.g.: `redis://127.0.0.1/`).
+    pub async fn connect(url: &str) -> Result<Self, CacheError> {
+        let client = redis::Client::open(url).map_err(|e| CacheError::BackendError(e.into()))?;
+        let connection = client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| CacheError::BackendError(e.into()))?;
+
+        Ok(Self {
+            connection,
+            prefix: "rig:cache:".to_string(),
+        })
+    }
+
+    /// Set the prefix of the Redis keys (default: `rig:cache:`).
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+}
+
+#[cfg(feature = "redis")]
+impl CacheBackend for RedisCache {
+    fn get<'a>(&'a self, key: &'a str) -> WasmBoxedFuture<'a, Result<Option<Value>, CacheError>> {
+        Box::pin(async move {
+            let value: Option<String> = redis::AsyncCommands::get(
+                &mut self.connection.clone(),
+                format!("{}{key}", self.prefix),
+            )
+            .await
+            .map_err(|e| CacheError::BackendError(e.into()))?;
+
+            Ok(value
+                .map(|value| serde_json::from_str(&value))
+                .transpose()?)
+        })
+    }
+
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> WasmBoxedFuture<'a, Result<(), CacheError>> {
+        Box::pin(async move {
+            let key = format!("{}{key}", self.prefix);
+            let value = value.to_string();
+            let mut connection = self.connection.clone();
+
+            match ttl {
+                Some(ttl) => {
+                    redis::AsyncCommands::set_ex::<_, _, ()>(
+                        &mut connection,
+                        key,
+                        value,
+                        ttl.as_secs().max(1),
+                    )
+                    .await
+                }
+                None => redis::AsyncCommands::set::<_, _, ()>(&mut connection, key, value).await,
+            }
+            .map_err(|e| CacheError::BackendError(e.into()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_disk_cache() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let cache = DiskCache::new(dir.path()).unwrap();
+
+        cache.set("model/a", json!([1, 2]), None).await.unwrap();
+        cache
+            .set("b", json!("b"), Some(Duration::from_secs(0)))
+            .await
+            .unwrap();
+
+        let cache = DiskCache::new(dir.path()).unwrap();
+        assert_eq!(cache.get("model/a").await.unwrap(), Some(json!([1, 2])));
+        assert_eq!(cache.get("b").await.unwrap(), None);
+        assert_eq!(cache.get("c").await.unwrap(), None);
+
+        // Keys which would be the same once sanitized are stored in different files
+        cache.set("model_a", json!("other"), None).await.unwrap();
+        assert_eq!(cache.get("model/a").await.unwrap(), Some(json!([1, 2])));
+        assert_eq!(cache.get("model_a").await.unwrap(), Some(json!("other")));
+    }
+
+    // No tokio runtime in this test
+    #[test]
+    fn test_disk_cache_without_tokio() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let cache = DiskCache::new(dir.path()).unwrap();
+        futures::executor::block_on(async {
+            cache.set("a", json!(1), None).await.unwrap();
+            assert_eq!(cache.get("a").await.unwrap(), Some(json!(1)));
+        });
+    }
+}
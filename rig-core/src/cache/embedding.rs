@@ -0,0 +1,243 @@
+//! Embedding cache.
+//!
+//! The [EmbeddingCache] stores embeddings in a [CacheBackend] keyed by model and content hash,
+//! so that re-indexing unchanged documents or embedding repeated queries (e.g.: when an agent
+//! computes its dynamic context) does not call the embedding model provider.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     cache::{DiskCache, EmbeddingCache},
+//!     embeddings::EmbeddingsBuilder,
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let model = EmbeddingCache::new(
+//!     openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL),
+//!     openai::TEXT_EMBEDDING_3_SMALL,
+//!     DiskCache::new(".rig-cache/embeddings")?,
+//! );
+//!
+//! // Only new or modified documents are embedded by the provider
+//! let embeddings = EmbeddingsBuilder::new(model.clone())
+//!     .documents(documents)?
+//!     .build()
+//!     .await?;
+//! ```
+
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use super::{backend::InMemoryCache, CacheBackend};
+use crate::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+
+/// Embedding model wrapper that caches embeddings by (model, content hash).
+///
+/// Errors of the cache backend are logged and the texts are embedded by the underlying model.
+pub struct EmbeddingCache<M, B = InMemoryCache> {
+    model: M,
+    model_name: String,
+    backend: Arc<B>,
+    ttl: Option<std::time::Duration>,
+}
+
+impl<M: Clone, B> Clone for EmbeddingCache<M, B> {
+    fn clone(&self) -> Self {
+        Self {
+            model: self.model.clone(),
+            model_name: self.model_name.clone(),
+            backend: self.backend.clone(),
+            ttl: self.ttl,
+        }
+    }
+}
+
+impl<M: EmbeddingModel> EmbeddingCache<M, InMemoryCache> {
+    /// Create an embedding cache backed by an [InMemoryCache].
+    pub fn in_memory(model: M, model_name: &str) -> Self {
+        Self::new(model, model_name, InMemoryCache::new())
+    }
+}
+
+impl<M: EmbeddingModel, B: CacheBackend> EmbeddingCache<M, B> {
+    /// Create an embedding cache. `model_name` identifies the model in the cache keys, so
+    /// that embeddings of different models sharing a backend do not collide.
+    pub fn new(model: M, model_name: &str, backend: B) -> Self {
+        Self {
+            model,
+            model_name: model_name.to_string(),
+            backend: Arc::new(backend),
+            ttl: None,
+        }
+    }
+
+    /// Set the time to live of cached embeddings (default: no expiration).
+    pub fn ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Get the cache backend
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    fn key(&self, text: &str) -> String {
+        format!(
+            "{}-{}-{:x}",
+            self.model_name,
+            self.model.ndims(),
+            Sha256::digest(text.as_bytes())
+        )
+    }
+
+    async fn get(&self, key: &str) -> Option<Vec<f64>> {
+        match self.backend.get(key).await {
+            Ok(value) => value.and_then(|value| serde_json::from_value(value).ok()),
+            Err(err) => {
+                tracing::warn!(target: "rig", "Embedding cache lookup failed: {}", err);
+                None
+            }
+        }
+    }
+}
+
+impl<M, B> EmbeddingModel for EmbeddingCache<M, B>
+where
+    M: EmbeddingModel,
+    B: CacheBackend + 'static,
+{
+    const MAX_DOCUMENTS: usize = M::MAX_DOCUMENTS;
+    const MAX_TOKENS_PER_REQUEST: Option<usize> = M::MAX_TOKENS_PER_REQUEST;
+
+    fn ndims(&self) -> usize {
+        self.model.ndims()
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+        let mut embeddings = vec![];
+        let mut misses = vec![];
+
+        for (i, text) in texts.into_iter().enumerate() {
+            let key = self.key(&text);
+            match self.get(&key).await {
+                Some(vec) => embeddings.push(Some(Embedding {
+                    document: text,
+                    vec,
+                })),
+                None => {
+                    embeddings.push(None);
+                    misses.push((i, key, text));
+                }
+            }
+        }
+
+        tracing::debug!(target: "rig",
+            "Embedding cache: {} hits, {} misses",
+            embeddings.len() - misses.len(),
+            misses.len()
+        );
+
+        if !misses.is_empty() {
+            let computed = self
+                .model
+                .embed_texts(
+                    misses
+                        .iter()
+                        .map(|(_, _, text)| text.clone())
+                        .collect::<Vec<_>>(),
+                )
+                .await?;
+
+            for ((i, key, _), embedding) in misses.into_iter().zip(computed) {
+                if let Err(err) = self
+                    .backend
+                    .set(&key, serde_json::to_value(&embedding.vec)?, self.ttl)
+                    .await
+                {
+                    tracing::warn!(target: "rig", "Embedding cache insert failed: {}", err);
+                }
+                embeddings[i] = Some(embedding);
+            }
+        }
+
+        embeddings
+            .into_iter()
+            .map(|embedding| {
+                embedding.ok_or_else(|| {
+                    EmbeddingError::ResponseError(
+                        "Response data length does not match input length".into(),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct CountingModel {
+        texts: Arc<AtomicUsize>,
+    }
+
+    impl EmbeddingModel for CountingModel {
+        const MAX_DOCUMENTS: usize = 10;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|text| {
+                    self.texts.fetch_add(1, Ordering::SeqCst);
+                    Embedding {
+                        vec: vec![text.len() as f64],
+                        document: text,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embedding_cache() {
+        let model = CountingModel::default();
+        let cache = EmbeddingCache::in_memory(model.clone(), "counting");
+
+        cache
+            .embed_texts(vec!["a".to_string(), "bb".to_string()])
+            .await
+            .unwrap();
+        let embeddings = cache
+            .embed_texts(vec!["ccc".to_string(), "a".to_string(), "bb".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(model.texts.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            embeddings
+                .iter()
+                .map(|embedding| embedding.vec[0])
+                .collect::<Vec<_>>(),
+            vec![3.0, 1.0, 2.0]
+        );
+        assert_eq!(embeddings[1].document, "a");
+    }
+}
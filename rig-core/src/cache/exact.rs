@@ -0,0 +1,230 @@
+//! Exact-match completion cache.
+//!
+//! The [ExactCache] returns the cached response of a previous request identical to the
+//! current one (see [request_hash](super::request_hash)). Responses are stored in a
+//! [CacheBackend] (see the [backend](super::backend) module).
+//!
+//! # Example
+//! ```rust
+//! use rig::{cache::{DiskCache, ExactCache}, completion::Prompt, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let model = ExactCache::new(
+//!     openai.completion_model(openai::GPT_4O),
+//!     DiskCache::new(".rig-cache")?,
+//! )
+//! .namespace(openai::GPT_4O);
+//!
+//! let agent = rig::agent::AgentBuilder::new(model).build();
+//!
+//! // Only the first run of the program calls the OpenAI API
+//! agent.prompt("What is the capital of France?").await?;
+//! ```
+
+use std::sync::Arc;
+
+use super::{backend::InMemoryCache, request_hash, CacheBackend};
+use crate::{
+    completion::{
+        AssistantContent, Capabilities, CompletionError, CompletionModel, CompletionRequest,
+        CompletionResponse,
+    },
+    OneOrMany,
+};
+
+/// Completion model wrapper that caches responses by hash of the full completion request.
+///
+/// Errors of the cache backend are logged and the request is sent to the underlying
+/// completion model.
+pub struct ExactCache<M, B = InMemoryCache> {
+    model: M,
+    backend: Arc<B>,
+    namespace: Option<String>,
+    ttl: Option<std::time::Duration>,
+}
+
+impl<M: Clone, B> Clone for ExactCache<M, B> {
+    fn clone(&self) -> Self {
+        Self {
+            model: self.model.clone(),
+            backend: self.backend.clone(),
+            namespace: self.namespace.clone(),
+            ttl: self.ttl,
+        }
+    }
+}
+
+impl<M: CompletionModel> ExactCache<M, InMemoryCache> {
+    /// Create an exact-match cache backed by an [InMemoryCache].
+    pub fn in_memory(model: M) -> Self {
+        Self::new(model, InMemoryCache::new())
+    }
+}
+
+impl<M: CompletionModel, B: CacheBackend> ExactCache<M, B> {
+    pub fn new(model: M, backend: B) -> Self {
+        Self {
+            model,
+            backend: Arc::new(backend),
+            namespace: None,
+            ttl: None,
+        }
+    }
+
+    /// Set the namespace of the cache keys. Since the request does not identify the model,
+    /// models sharing a backend should use different namespaces (e.g.: the model name).
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Set the time to live of cached responses (default: no expiration).
+    pub fn ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Get the cache backend
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    fn key(&self, request: &CompletionRequest) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{namespace}-{}", request_hash(request)),
+            None => request_hash(request),
+        }
+    }
+}
+
+impl<M, B> CompletionModel for ExactCache<M, B>
+where
+    M: CompletionModel,
+    B: CacheBackend + 'static,
+{
+    /// `None` if the response was returned from the cache
+    type Response = Option<M::Response>;
+
+    fn capabilities(&self) -> Capabilities {
+        self.model.capabilities()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let key = self.key(&request);
+
+        match self.backend.get(&key).await {
+            Ok(Some(cached)) => match serde_json::from_value::<OneOrMany<AssistantContent>>(cached)
+            {
+                Ok(choice) => {
+                    tracing::debug!(target: "rig", "Completion cache hit: {}", key);
+                    return Ok(CompletionResponse {
+                        choice,
+                        raw_response: None,
+                        logprobs: None,
+                    });
+                }
+                Err(err) => {
+                    tracing::warn!(target: "rig", "Invalid completion cache entry {}: {}", key, err)
+                }
+            },
+            Ok(None) => (),
+            Err(err) => tracing::warn!(target: "rig", "Completion cache lookup failed: {}", err),
+        }
+
+        let response = self.model.completion(request).await?;
+
+        let choice = serde_json::to_value(&response.choice)?;
+        if let Err(err) = self.backend.set(&key, choice, self.ttl).await {
+            tracing::warn!(target: "rig", "Completion cache insert failed: {}", err);
+        }
+
+        Ok(CompletionResponse {
+            choice: response.choice,
+            raw_response: Some(response.raw_response),
+            logprobs: response.logprobs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{cache::DiskCache, completion::Message};
+
+    #[derive(Clone, Default)]
+    struct MockModel {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!("response {call}"))),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    fn request(prompt: &str) -> CompletionRequest {
+        CompletionRequest {
+            preamble: None,
+            chat_history: OneOrMany::one(Message::user(prompt)),
+            documents: vec![],
+            tools: vec![],
+            tool_choice: Default::default(),
+            parallel_tool_calls: None,
+            temperature: None,
+            max_tokens: None,
+            timeout: None,
+            cancellation: None,
+            http_extras: Default::default(),
+            output_constraint: None,
+            sampling: Default::default(),
+            additional_params: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_cache() {
+        let model = MockModel::default();
+        let cache = ExactCache::in_memory(model.clone());
+
+        let first = cache.completion(request("Hello")).await.unwrap();
+        let second = cache.completion(request("Hello")).await.unwrap();
+        let third = cache.completion(request("Hello!")).await.unwrap();
+
+        assert!(second.raw_response.is_none());
+        assert_eq!(first.choice, second.choice);
+        assert_ne!(first.choice, third.choice);
+        assert_eq!(model.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache() {
+        let dir = assert_fs::TempDir::new().unwrap();
+
+        let model = MockModel::default();
+        let cache = ExactCache::new(model.clone(), DiskCache::new(dir.path()).unwrap());
+        let first = cache.completion(request("Hello")).await.unwrap();
+
+        // A new cache in the same directory reuses the stored entries
+        let cache = ExactCache::new(model.clone(), DiskCache::new(dir.path()).unwrap());
+        let second = cache.completion(request("Hello")).await.unwrap();
+
+        assert_eq!(first.choice, second.choice);
+        assert_eq!(model.calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,191 @@
+//! This module provides caching layers for completion and embedding models.
+//!
+//! The most important types are:
+//! - [SemanticCache]: caches responses by embedding similarity of the prompt, so that
+//!   near-duplicate queries can be answered without calling the completion model.
+//! - [ExactCache]: caches responses by hash of the full completion request, with in-memory,
+//!   disk and Redis (requires the `redis` feature) backends. This is mostly useful to make
+//!   development loops and CI runs fast and free.
+//! - [EmbeddingCache]: caches embeddings by model and content hash, using the same backends.
+//!
+//! Cache layers wrap a [CompletionModel](crate::completion::CompletionModel) (or an
+//! [EmbeddingModel](crate::embeddings::EmbeddingModel)) and implement the trait themselves, so
+//! they can be used anywhere a model is expected (e.g.: agents, vector store indexes).
+//! Since a cached completion response is not returned by the underlying provider, the raw
+//! response of a completion cache layer is an `Option` that is `None` on cache hits.
+//!
+//! The steps of a [pipeline](crate::pipeline) can be cached with the same backends, see
+//! [pipeline::cached](crate::pipeline::cached).
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    completion::{AssistantContent, CompletionRequest, Message, ToolChoice},
+    OneOrMany,
+};
+
+pub mod backend;
+pub mod embedding;
+pub mod exact;
+pub mod semantic;
+
+#[cfg(feature = "redis")]
+pub use backend::RedisCache;
+pub use backend::{CacheBackend, DiskCache, InMemoryCache};
+pub use embedding::EmbeddingCache;
+pub use exact::ExactCache;
+pub use semantic::{InMemorySemanticStore, SemanticCache, SemanticCacheEntry, SemanticCacheStore};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Io error (e.g.: disk cache)
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Error returned by the cache backend
+    #[error("BackendError: {0}")]
+    BackendError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+/// A completion choice stored in a cache.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CachedChoice {
+    pub choice: OneOrMany<AssistantContent>,
+    /// Expiration time of the entry, in seconds since the UNIX epoch
+    pub expires_at: Option<u64>,
+}
+
+impl CachedChoice {
+    pub fn new(choice: OneOrMany<AssistantContent>, ttl: Option<std::time::Duration>) -> Self {
+        Self {
+            choice,
+            expires_at: ttl.map(|ttl| now() + ttl.as_secs()),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .map(|expires_at| expires_at <= now())
+            .unwrap_or(false)
+    }
+}
+
+/// Current time in seconds since the UNIX epoch
+pub(crate) fn now() -> u64 {
+    web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn hash_request(request: &CompletionRequest, chat_history: &[&Message]) -> String {
+    let mut value = serde_json::json!({
+        "preamble": request.preamble,
+        "chat_history": chat_history,
+        "documents": request.documents,
+        "tools": request.tools,
+        "temperature": request.temperature,
+        "max_tokens": request.max_tokens,
+        "additional_params": request.additional_params,
+    });
+    // Only hashed when set, so that the hashes of the requests without them do not change
+    if !request.sampling.is_empty() {
+        value["sampling"] = serde_json::json!(request.sampling);
+    }
+    if let Some(output_constraint) = &request.output_constraint {
+        value["output_constraint"] = serde_json::json!(output_constraint);
+    }
+    if request.tool_choice != ToolChoice::Auto {
+        value["tool_choice"] = serde_json::json!(request.tool_choice);
+    }
+    if let Some(parallel_tool_calls) = request.parallel_tool_calls {
+        value["parallel_tool_calls"] = serde_json::json!(parallel_tool_calls);
+    }
+
+    format!("{:x}", Sha256::digest(value.to_string().as_bytes()))
+}
+
+/// Returns the last message of the request (i.e.: the prompt).
+pub(crate) fn prompt_message(request: &CompletionRequest) -> Message {
+    request
+        .chat_history
+        .rest()
+        .pop()
+        .unwrap_or_else(|| request.chat_history.first())
+}
+
+/// Hash of the full request. Two requests with the same hash are identical.
+pub fn request_hash(request: &CompletionRequest) -> String {
+    hash_request(request, &request.chat_history.iter().collect::<Vec<_>>())
+}
+
+/// Hash of everything in the request except the prompt (i.e.: the last message of the chat
+/// history). Two requests with the same configuration hash only differ by their prompt.
+pub fn config_hash(request: &CompletionRequest) -> String {
+    let history = request.chat_history.iter().collect::<Vec<_>>();
+    hash_request(request, &history[..history.len() - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::Document;
+
+    fn request(prompt: &str, temperature: f64) -> CompletionRequest {
+        CompletionRequest {
+            preamble: Some("You are a helpful assistant".to_string()),
+            chat_history: OneOrMany::many(vec![Message::user("Hi"), Message::user(prompt)])
+                .unwrap(),
+            documents: vec![Document {
+                id: "doc".to_string(),
+                text: "text".to_string(),
+                additional_props: Default::default(),
+            }],
+            tools: vec![],
+            tool_choice: Default::default(),
+            parallel_tool_calls: None,
+            temperature: Some(temperature),
+            max_tokens: None,
+            timeout: None,
+            cancellation: None,
+            http_extras: Default::default(),
+            output_constraint: None,
+            sampling: Default::default(),
+            additional_params: None,
+        }
+    }
+
+    #[test]
+    fn test_config_hash() {
+        assert_eq!(
+            config_hash(&request("a", 0.0)),
+            config_hash(&request("b", 0.0))
+        );
+        assert_ne!(
+            config_hash(&request("a", 0.0)),
+            config_hash(&request("a", 0.5))
+        );
+        assert_ne!(
+            request_hash(&request("a", 0.0)),
+            request_hash(&request("b", 0.0))
+        );
+        assert_eq!(prompt_message(&request("a", 0.0)), Message::user("a"));
+
+        // The requests differing by how the tools are used have different hashes
+        let forced = CompletionRequest {
+            tool_choice: ToolChoice::Tool("search".to_string()),
+            ..request("a", 0.0)
+        };
+        assert_ne!(request_hash(&request("a", 0.0)), request_hash(&forced));
+        let sequential = CompletionRequest {
+            parallel_tool_calls: Some(false),
+            ..request("a", 0.0)
+        };
+        assert_ne!(request_hash(&request("a", 0.0)), request_hash(&sequential));
+    }
+}
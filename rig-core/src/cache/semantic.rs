@@ -0,0 +1,380 @@
+//! Semantic response cache.
+//!
+//! The [SemanticCache] embeds the prompt of every completion request and looks up a previous
+//! response whose prompt embedding is similar enough (i.e.: cosine similarity above a threshold).
+//! Only responses to requests with the same configuration (preamble, chat history, documents,
+//! tools, etc.; see [config_hash](super::config_hash)) are considered.
+//!
+//! # Example
+//! ```rust
+//! use rig::{cache::SemanticCache, completion::Prompt, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let model = SemanticCache::in_memory(
+//!     openai.completion_model(openai::GPT_4O),
+//!     openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL),
+//! )
+//! .threshold(0.95)
+//! .ttl(std::time::Duration::from_secs(3600));
+//!
+//! let agent = rig::agent::AgentBuilder::new(model).build();
+//!
+//! // The second prompt is answered from the cache
+//! agent.prompt("What is the capital of France?").await?;
+//! agent.prompt("what's the capital of france").await?;
+//! ```
+
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::{config_hash, prompt_message, CacheError, CachedChoice};
+use crate::wasm_compat::WasmBoxedFuture;
+use crate::{
+    completion::{
+        Capabilities, CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
+    },
+    embeddings::{distance::VectorDistance, Embedding, EmbeddingModel},
+};
+
+/// An entry of a semantic cache.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SemanticCacheEntry {
+    /// Configuration hash of the request (see [config_hash](super::config_hash))
+    pub namespace: String,
+    /// Embedding of the prompt
+    pub embedding: Embedding,
+    /// Cached response
+    pub response: CachedChoice,
+}
+
+/// Trait for semantic cache storage backends.
+pub trait SemanticCacheStore: Send + Sync {
+    /// Get the non-expired entry of `namespace` whose embedding is the most similar to
+    /// `embedding`, if its cosine similarity is at least `threshold`.
+    fn lookup<'a>(
+        &'a self,
+        namespace: &'a str,
+        embedding: &'a Embedding,
+        threshold: f64,
+    ) -> WasmBoxedFuture<'a, Result<Option<SemanticCacheEntry>, CacheError>>;
+
+    /// Insert an entry in the cache.
+    fn insert(&self, entry: SemanticCacheEntry) -> WasmBoxedFuture<'_, Result<(), CacheError>>;
+}
+
+/// In-memory semantic cache store. Entries are scanned linearly on lookup and
+/// expired entries are evicted on insert.
+#[derive(Clone, Default)]
+pub struct InMemorySemanticStore {
+    entries: Arc<RwLock<Vec<SemanticCacheEntry>>>,
+}
+
+impl InMemorySemanticStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().expect("Lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SemanticCacheStore for InMemorySemanticStore {
+    fn lookup<'a>(
+        &'a self,
+        namespace: &'a str,
+        embedding: &'a Embedding,
+        threshold: f64,
+    ) -> WasmBoxedFuture<'a, Result<Option<SemanticCacheEntry>, CacheError>> {
+        Box::pin(async move {
+            let entries = self.entries.read().expect("Lock poisoned");
+
+            Ok(entries
+                .iter()
+                .filter(|entry| entry.namespace == namespace && !entry.response.is_expired())
+                .map(|entry| (entry.embedding.cosine_similarity(embedding, false), entry))
+                .filter(|(similarity, _)| *similarity >= threshold)
+                .max_by(|(a, _), (b, _)| a.total_cmp(b))
+                .map(|(_, entry)| entry.clone()))
+        })
+    }
+
+    fn insert(&self, entry: SemanticCacheEntry) -> WasmBoxedFuture<'_, Result<(), CacheError>> {
+        Box::pin(async move {
+            let mut entries = self.entries.write().expect("Lock poisoned");
+            entries.retain(|entry| !entry.response.is_expired());
+            entries.push(entry);
+            Ok(())
+        })
+    }
+}
+
+/// Completion model wrapper that caches responses by embedding similarity of the prompt.
+///
+/// Requests whose prompt has no text content are passed through without caching. Errors
+/// of the embedding model or of the cache store are logged and the request is sent to
+/// the underlying completion model.
+pub struct SemanticCache<M, E, S = InMemorySemanticStore> {
+    model: M,
+    embedding_model: E,
+    store: Arc<S>,
+    threshold: f64,
+    ttl: Option<std::time::Duration>,
+}
+
+impl<M: Clone, E: Clone, S> Clone for SemanticCache<M, E, S> {
+    fn clone(&self) -> Self {
+        Self {
+            model: self.model.clone(),
+            embedding_model: self.embedding_model.clone(),
+            store: self.store.clone(),
+            threshold: self.threshold,
+            ttl: self.ttl,
+        }
+    }
+}
+
+impl<M, E> SemanticCache<M, E, InMemorySemanticStore>
+where
+    M: CompletionModel,
+    E: EmbeddingModel,
+{
+    /// Create a semantic cache backed by an [InMemorySemanticStore].
+    pub fn in_memory(model: M, embedding_model: E) -> Self {
+        Self::new(model, embedding_model, InMemorySemanticStore::new())
+    }
+}
+
+impl<M, E, S> SemanticCache<M, E, S>
+where
+    M: CompletionModel,
+    E: EmbeddingModel,
+    S: SemanticCacheStore,
+{
+    pub fn new(model: M, embedding_model: E, store: S) -> Self {
+        Self {
+            model,
+            embedding_model,
+            store: Arc::new(store),
+            threshold: 0.95,
+            ttl: None,
+        }
+    }
+
+    /// Set the minimum cosine similarity between two prompts for a cached response to
+    /// be returned (default: 0.95).
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set the time to live of cached responses (default: no expiration).
+    pub fn ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Get the cache store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<M, E, S> CompletionModel for SemanticCache<M, E, S>
+where
+    M: CompletionModel,
+    E: EmbeddingModel,
+    S: SemanticCacheStore + 'static,
+{
+    /// `None` if the response was returned from the cache
+    type Response = Option<M::Response>;
+
+    fn capabilities(&self) -> Capabilities {
+        self.model.capabilities()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let Some(prompt) = prompt_message(&request).rag_text() else {
+            return passthrough(&self.model, request).await;
+        };
+
+        let embedding = match self.embedding_model.embed_text(&prompt).await {
+            Ok(embedding) => embedding,
+            Err(err) => {
+                tracing::warn!(target: "rig", "Semantic cache: failed to embed prompt: {}", err);
+                return passthrough(&self.model, request).await;
+            }
+        };
+
+        let namespace = config_hash(&request);
+
+        match self
+            .store
+            .lookup(&namespace, &embedding, self.threshold)
+            .await
+        {
+            Ok(Some(entry)) => {
+                tracing::debug!(target: "rig", "Semantic cache hit for prompt: {}", prompt);
+                return Ok(CompletionResponse {
+                    choice: entry.response.choice,
+                    raw_response: None,
+                    logprobs: None,
+                });
+            }
+            Ok(None) => (),
+            Err(err) => tracing::warn!(target: "rig", "Semantic cache lookup failed: {}", err),
+        }
+
+        let response = self.model.completion(request).await?;
+
+        if let Err(err) = self
+            .store
+            .insert(SemanticCacheEntry {
+                namespace,
+                embedding,
+                response: CachedChoice::new(response.choice.clone(), self.ttl),
+            })
+            .await
+        {
+            tracing::warn!(target: "rig", "Semantic cache insert failed: {}", err);
+        }
+
+        Ok(CompletionResponse {
+            choice: response.choice,
+            raw_response: Some(response.raw_response),
+            logprobs: response.logprobs,
+        })
+    }
+}
+
+async fn passthrough<M: CompletionModel>(
+    model: &M,
+    request: CompletionRequest,
+) -> Result<CompletionResponse<Option<M::Response>>, CompletionError> {
+    let response = model.completion(request).await?;
+    Ok(CompletionResponse {
+        choice: response.choice,
+        raw_response: Some(response.raw_response),
+        logprobs: response.logprobs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        completion::{AssistantContent, Message},
+        embeddings::EmbeddingError,
+        OneOrMany,
+    };
+
+    #[derive(Clone, Default)]
+    struct MockModel {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!("response {call}"))),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    /// Embeds texts by their first letter (case insensitive)
+    #[derive(Clone)]
+    struct MockEmbeddingModel;
+
+    impl EmbeddingModel for MockEmbeddingModel {
+        const MAX_DOCUMENTS: usize = 10;
+
+        fn ndims(&self) -> usize {
+            26
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|text| {
+                    let first = text.to_lowercase().chars().next().unwrap_or('a');
+                    Embedding {
+                        vec: ('a'..='z')
+                            .map(|c| if c == first { 1.0 } else { 0.0 })
+                            .collect(),
+                        document: text,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    fn request(prompt: &str) -> CompletionRequest {
+        CompletionRequest {
+            preamble: None,
+            chat_history: OneOrMany::one(Message::user(prompt)),
+            documents: vec![],
+            tools: vec![],
+            tool_choice: Default::default(),
+            parallel_tool_calls: None,
+            temperature: None,
+            max_tokens: None,
+            timeout: None,
+            cancellation: None,
+            http_extras: Default::default(),
+            output_constraint: None,
+            sampling: Default::default(),
+            additional_params: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantic_cache() {
+        let model = MockModel::default();
+        let cache = SemanticCache::in_memory(model.clone(), MockEmbeddingModel);
+
+        let first = cache.completion(request("Hello")).await.unwrap();
+        let second = cache.completion(request("hello there")).await.unwrap();
+        let third = cache.completion(request("Ok")).await.unwrap();
+
+        assert!(first.raw_response.is_some());
+        assert!(second.raw_response.is_none());
+        assert_eq!(first.choice, second.choice);
+        assert_eq!(third.choice.first(), AssistantContent::text("response 1"));
+        assert_eq!(model.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.store().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_semantic_cache_ttl() {
+        let model = MockModel::default();
+        let cache = SemanticCache::in_memory(model.clone(), MockEmbeddingModel)
+            .ttl(std::time::Duration::from_secs(0));
+
+        cache.completion(request("Hello")).await.unwrap();
+        cache.completion(request("Hello")).await.unwrap();
+
+        assert_eq!(model.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,188 @@
+//! Cancellation of in-flight completion requests, streams, tool calls and agent prompts (e.g.:
+//! when a user clicks "stop").
+//!
+//! A [CancellationToken] is given to the requests to cancel (see
+//! [CompletionRequestBuilder::cancellation](crate::completion::CompletionRequestBuilder::cancellation)
+//! and [PromptRequest::cancellation](crate::agent::PromptRequest::cancellation)), and cancelled
+//! from anywhere else with [CancellationToken::cancel]. The cancelled requests are dropped (which
+//! aborts their HTTP requests and running tools) and fail with a
+//! [CompletionError::Cancelled](crate::completion::CompletionError::Cancelled), except for the
+//! streams which end early with the partial output generated so far.
+//!
+//! # Example
+//! ```rust
+//! use rig::{cancellation::CancellationToken, completion::Prompt, providers::openai};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let agent = openai::Client::from_env().agent(openai::GPT_4O).build();
+//!
+//! let token = CancellationToken::new();
+//! let stop_button = token.clone();
+//! // ... call `stop_button.cancel()` when the user clicks "stop"
+//!
+//! let response = agent
+//!     .prompt("Write a long story")
+//!     .cancellation(token)
+//!     .await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use crate::completion::CompletionError;
+
+/// A token to cancel the requests it is given to. Clones share the same cancellation state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the requests given this token (or one of its clones).
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        for waker in self.inner.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// A future completing when the token is cancelled.
+    pub fn cancelled(&self) -> Cancelled<'_> {
+        Cancelled { token: self }
+    }
+
+    /// Returns true if the token is cancelled, otherwise wakes up the task of `cx` when it is.
+    pub(crate) fn poll_cancelled(&self, cx: &mut Context<'_>) -> bool {
+        if self.is_cancelled() {
+            return true;
+        }
+
+        let mut wakers = self.inner.wakers.lock().unwrap();
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        drop(wakers);
+
+        // The token may have been cancelled before the waker was registered
+        self.is_cancelled()
+    }
+}
+
+/// Future returned by [CancellationToken::cancelled].
+pub struct Cancelled<'a> {
+    token: &'a CancellationToken,
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.poll_cancelled(cx) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Abort `future` with a [CompletionError::Cancelled] if `token` is cancelled before it completes.
+pub(crate) async fn with_cancellation<T, E: From<CompletionError>>(
+    token: Option<&CancellationToken>,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let Some(token) = token else {
+        return future.await;
+    };
+    if token.is_cancelled() {
+        return Err(CompletionError::Cancelled.into());
+    }
+
+    futures::pin_mut!(future);
+    match futures::future::select(future, token.cancelled()).await {
+        futures::future::Either::Left((result, _)) => result,
+        futures::future::Either::Right(_) => Err(CompletionError::Cancelled.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_with_cancellation() {
+        let token = CancellationToken::new();
+
+        let slow = async {
+            futures_timer::Delay::new(Duration::from_secs(5)).await;
+            Ok::<_, CompletionError>(())
+        };
+        let cancel = {
+            let token = token.clone();
+            async move {
+                futures_timer::Delay::new(Duration::from_millis(10)).await;
+                token.cancel();
+            }
+        };
+
+        let (result, _) = futures::join!(with_cancellation(Some(&token), slow), cancel);
+        assert!(matches!(result, Err(CompletionError::Cancelled)));
+        assert!(token.is_cancelled());
+
+        // Already cancelled
+        let result = with_cancellation(Some(&token), async { Ok::<_, CompletionError>(()) });
+        assert!(matches!(result.await, Err(CompletionError::Cancelled)));
+
+        let result = with_cancellation(None, async { Ok::<_, CompletionError>(1) });
+        assert_eq!(result.await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_stream() {
+        use futures::StreamExt;
+
+        use crate::{
+            message::AssistantContent,
+            streaming::{RawStreamingChoice, StreamingCompletionResponse},
+        };
+
+        let token = CancellationToken::new();
+        let inner = futures::stream::iter(["Hello", " world"])
+            .map(|text| Ok(RawStreamingChoice::<()>::Message(text.to_string())))
+            .chain(futures::stream::pending());
+        let mut stream =
+            StreamingCompletionResponse::new(Box::pin(inner)).cancellation(token.clone());
+
+        assert!(stream.next().await.is_some());
+        assert!(stream.next().await.is_some());
+        token.cancel();
+        assert!(stream.next().await.is_none());
+
+        assert!(stream.is_cancelled());
+        assert_eq!(stream.choice.first(), AssistantContent::text("Hello world"));
+    }
+}
@@ -0,0 +1,337 @@
+//! This module provides a catalog of the models of the bundled providers: their context window,
+//! maximum output, capabilities and pricing, so that code routing requests, budgeting tokens or
+//! validating requests does not hardcode them.
+//!
+//! The bundled catalog (see [ModelCatalog::bundled]) covers the main completion models of the
+//! providers; prices are the list prices in USD at the time of writing. Register custom models
+//! (e.g.: fine-tuned or self-hosted models) or updated prices with [ModelCatalog::register].
+//!
+//! # Example
+//! ```rust
+//! use rig::{catalog::{ModelCatalog, ModelInfo}, providers::openai};
+//!
+//! let mut catalog = ModelCatalog::bundled();
+//! catalog.register(
+//!     ModelInfo::new("ollama", "llama3.2", 128_000, 2_048).supports_tools(true),
+//! );
+//!
+//! // Dated versions are matched to their model
+//! let gpt4o = catalog.get("openai", "gpt-4o-2024-08-06").unwrap();
+//! assert!(gpt4o.supports_vision);
+//!
+//! let cost = gpt4o.cost(10_000, 1_000);
+//! ```
+
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+
+/// Price of a model, in USD per million tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pricing {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl Pricing {
+    pub fn new(input: f64, output: f64) -> Self {
+        Self { input, output }
+    }
+
+    /// Get the cost in USD of a request.
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input + output_tokens as f64 * self.output) / 1_000_000.0
+    }
+}
+
+/// Information about a model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Name of the provider, e.g.: `openai` (the name of its module in [providers](crate::providers))
+    pub provider: String,
+    /// Name of the model, e.g.: `gpt-4o`
+    pub id: String,
+    /// Maximum number of tokens of the input and output of a request
+    pub context_window: u64,
+    /// Maximum number of output tokens of a request
+    pub max_output: u64,
+    pub supports_tools: bool,
+    /// Whether the model accepts images as input
+    pub supports_vision: bool,
+    /// Price of the model, if known
+    pub price: Option<Pricing>,
+}
+
+impl ModelInfo {
+    /// Create the information of a model, without tools, vision nor price.
+    pub fn new(
+        provider: impl Into<String>,
+        id: impl Into<String>,
+        context_window: u64,
+        max_output: u64,
+    ) -> Self {
+        Self {
+            provider: provider.into(),
+            id: id.into(),
+            context_window,
+            max_output,
+            supports_tools: false,
+            supports_vision: false,
+            price: None,
+        }
+    }
+
+    pub fn supports_tools(mut self, supports_tools: bool) -> Self {
+        self.supports_tools = supports_tools;
+        self
+    }
+
+    pub fn supports_vision(mut self, supports_vision: bool) -> Self {
+        self.supports_vision = supports_vision;
+        self
+    }
+
+    /// Set the price of the model, in USD per million input and output tokens.
+    pub fn price(mut self, input: f64, output: f64) -> Self {
+        self.price = Some(Pricing::new(input, output));
+        self
+    }
+
+    /// Get the cost in USD of a request, if the price of the model is known.
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> Option<f64> {
+        self.price
+            .map(|price| price.cost(input_tokens, output_tokens))
+    }
+
+    /// Check whether this is the information of `model`: either the model itself or one of its
+    /// versions (e.g.: `gpt-4o-2024-08-06` or `claude-3-5-sonnet-latest`).
+    fn matches(&self, model: &str) -> bool {
+        model
+            .strip_prefix(self.id.as_str())
+            .is_some_and(|suffix| suffix.is_empty() || suffix.starts_with('-'))
+    }
+}
+
+/// Queryable catalog of models.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ModelCatalog {
+    models: Vec<ModelInfo>,
+}
+
+impl ModelCatalog {
+    /// Create an empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a catalog of the models of the bundled providers.
+    pub fn bundled() -> Self {
+        BUNDLED_CATALOG.clone()
+    }
+
+    /// Add a model to the catalog, replacing the existing model with the same provider and id.
+    pub fn register(&mut self, model: ModelInfo) -> &mut Self {
+        match self
+            .models
+            .iter_mut()
+            .find(|existing| existing.provider == model.provider && existing.id == model.id)
+        {
+            Some(existing) => *existing = model,
+            None => self.models.push(model),
+        }
+        self
+    }
+
+    /// Get the information of a model of a provider. Versions of the models of the catalog (e.g.:
+    /// `gpt-4o-2024-08-06`) match their model, the longest match being used.
+    pub fn get(&self, provider: &str, model: &str) -> Option<&ModelInfo> {
+        self.models
+            .iter()
+            .filter(|info| info.provider == provider && info.matches(model))
+            .max_by_key(|info| info.id.len())
+    }
+
+    /// Get the information of a model of any provider (e.g.: of an OpenAI model served by Azure).
+    pub fn find(&self, model: &str) -> Option<&ModelInfo> {
+        self.models
+            .iter()
+            .filter(|info| info.matches(model))
+            .max_by_key(|info| info.id.len())
+    }
+
+    /// Iterate over the models of the catalog.
+    pub fn iter(&self) -> impl Iterator<Item = &ModelInfo> {
+        self.models.iter()
+    }
+
+    /// Iterate over the models of a provider.
+    pub fn provider_models<'a>(&'a self, provider: &'a str) -> impl Iterator<Item = &'a ModelInfo> {
+        self.models
+            .iter()
+            .filter(move |info| info.provider == provider)
+    }
+}
+
+impl Extend<ModelInfo> for ModelCatalog {
+    fn extend<T: IntoIterator<Item = ModelInfo>>(&mut self, models: T) {
+        for model in models {
+            self.register(model);
+        }
+    }
+}
+
+/// Model of the bundled providers: (provider, id, context window, max output, tools, vision,
+/// input and output price).
+type BundledModel = (
+    &'static str,
+    &'static str,
+    u64,
+    u64,
+    bool,
+    bool,
+    Option<(f64, f64)>,
+);
+
+#[rustfmt::skip]
+const BUNDLED: &[BundledModel] = &[
+    ("openai", "gpt-4.1", 1_047_576, 32_768, true, true, Some((2.0, 8.0))),
+    ("openai", "gpt-4.1-mini", 1_047_576, 32_768, true, true, Some((0.4, 1.6))),
+    ("openai", "gpt-4.1-nano", 1_047_576, 32_768, true, true, Some((0.1, 0.4))),
+    ("openai", "gpt-4.5-preview", 128_000, 16_384, true, true, Some((75.0, 150.0))),
+    ("openai", "gpt-4o", 128_000, 16_384, true, true, Some((2.5, 10.0))),
+    ("openai", "gpt-4o-2024-05-13", 128_000, 4_096, true, true, Some((5.0, 15.0))),
+    ("openai", "gpt-4o-mini", 128_000, 16_384, true, true, Some((0.15, 0.6))),
+    ("openai", "gpt-4-turbo", 128_000, 4_096, true, true, Some((10.0, 30.0))),
+    ("openai", "gpt-4-0125-preview", 128_000, 4_096, true, false, Some((10.0, 30.0))),
+    ("openai", "gpt-4-1106", 128_000, 4_096, true, false, Some((10.0, 30.0))),
+    ("openai", "gpt-4-1106-vision-preview", 128_000, 4_096, false, true, Some((10.0, 30.0))),
+    ("openai", "gpt-4-vision-preview", 128_000, 4_096, false, true, Some((10.0, 30.0))),
+    ("openai", "gpt-4", 8_192, 8_192, true, false, Some((30.0, 60.0))),
+    ("openai", "gpt-4-32k", 32_768, 8_192, true, false, Some((60.0, 120.0))),
+    ("openai", "gpt-3.5-turbo", 16_385, 4_096, true, false, Some((0.5, 1.5))),
+    ("openai", "gpt-3.5-turbo-1106", 16_385, 4_096, true, false, Some((1.0, 2.0))),
+    ("openai", "gpt-3.5-turbo-instruct", 4_096, 4_096, false, false, Some((1.5, 2.0))),
+    ("openai", "o4-mini", 200_000, 100_000, true, true, Some((1.1, 4.4))),
+    ("openai", "o3", 200_000, 100_000, true, true, Some((2.0, 8.0))),
+    ("openai", "o3-mini", 200_000, 100_000, true, false, Some((1.1, 4.4))),
+    ("openai", "o1", 200_000, 100_000, true, true, Some((15.0, 60.0))),
+    ("openai", "o1-pro", 200_000, 100_000, true, true, Some((150.0, 600.0))),
+    ("openai", "o1-preview", 128_000, 32_768, false, false, Some((15.0, 60.0))),
+    ("openai", "o1-mini", 128_000, 65_536, false, false, Some((1.1, 4.4))),
+    ("anthropic", "claude-opus-4", 200_000, 32_000, true, true, Some((15.0, 75.0))),
+    ("anthropic", "claude-sonnet-4", 200_000, 64_000, true, true, Some((3.0, 15.0))),
+    ("anthropic", "claude-3-7-sonnet", 200_000, 64_000, true, true, Some((3.0, 15.0))),
+    ("anthropic", "claude-3-5-sonnet", 200_000, 8_192, true, true, Some((3.0, 15.0))),
+    ("anthropic", "claude-3-5-haiku", 200_000, 8_192, true, true, Some((0.8, 4.0))),
+    ("anthropic", "claude-3-opus", 200_000, 4_096, true, true, Some((15.0, 75.0))),
+    ("anthropic", "claude-3-sonnet", 200_000, 4_096, true, true, Some((3.0, 15.0))),
+    ("anthropic", "claude-3-haiku", 200_000, 4_096, true, true, Some((0.25, 1.25))),
+    ("gemini", "gemini-2.0-flash", 1_048_576, 8_192, true, true, Some((0.1, 0.4))),
+    ("gemini", "gemini-1.5-flash", 1_048_576, 8_192, true, true, Some((0.075, 0.3))),
+    ("gemini", "gemini-1.5-flash-8b", 1_048_576, 8_192, true, true, Some((0.0375, 0.15))),
+    ("gemini", "gemini-1.5-pro", 2_097_152, 8_192, true, true, Some((1.25, 5.0))),
+    ("gemini", "gemini-1.0-pro", 32_760, 8_192, true, false, Some((0.5, 1.5))),
+    ("cohere", "command-r-plus", 128_000, 4_000, true, false, Some((2.5, 10.0))),
+    ("cohere", "command-r", 128_000, 4_000, true, false, Some((0.15, 0.6))),
+    ("cohere", "command", 4_096, 4_000, false, false, Some((1.0, 2.0))),
+    ("cohere", "command-light", 4_096, 4_000, false, false, Some((0.3, 0.6))),
+    ("deepseek", "deepseek-chat", 65_536, 8_192, true, false, Some((0.27, 1.1))),
+    ("deepseek", "deepseek-reasoner", 65_536, 8_192, false, false, Some((0.55, 2.19))),
+    ("mistral", "mistral-large", 131_072, 131_072, true, false, Some((2.0, 6.0))),
+    ("mistral", "pixtral-large", 131_072, 131_072, true, true, Some((2.0, 6.0))),
+    ("mistral", "mistral-small", 131_072, 131_072, true, true, Some((0.1, 0.3))),
+    ("mistral", "mistral-saba", 32_768, 32_768, true, false, Some((0.2, 0.6))),
+    ("mistral", "codestral", 256_000, 256_000, true, false, Some((0.3, 0.9))),
+    ("mistral", "ministral-8b", 131_072, 131_072, true, false, Some((0.1, 0.1))),
+    ("mistral", "ministral-3b", 131_072, 131_072, true, false, Some((0.04, 0.04))),
+    ("mistral", "pixtral-12b", 131_072, 131_072, true, true, Some((0.15, 0.15))),
+    ("mistral", "open-mistral-nemo", 131_072, 131_072, true, false, Some((0.15, 0.15))),
+    ("mistral", "open-codestral-mamba", 256_000, 256_000, false, false, Some((0.25, 0.25))),
+    ("xai", "grok-3", 131_072, 131_072, true, false, Some((3.0, 15.0))),
+    ("xai", "grok-3-mini", 131_072, 131_072, true, false, Some((0.3, 0.5))),
+    ("xai", "grok-2", 131_072, 131_072, true, false, Some((2.0, 10.0))),
+    ("xai", "grok-2-vision", 32_768, 32_768, true, true, Some((2.0, 10.0))),
+    ("xai", "grok-beta", 131_072, 131_072, true, false, Some((5.0, 15.0))),
+    ("perplexity", "sonar", 127_072, 8_000, false, false, Some((1.0, 1.0))),
+    ("perplexity", "sonar-pro", 200_000, 8_000, false, false, Some((3.0, 15.0))),
+    ("moonshot", "moonshot-v1-8k", 8_192, 8_192, true, false, None),
+    ("moonshot", "moonshot-v1-32k", 32_768, 32_768, true, false, None),
+    ("moonshot", "moonshot-v1-128k", 131_072, 131_072, true, false, None),
+    ("groq", "llama-3.1-8b-instant", 131_072, 8_192, true, false, Some((0.05, 0.08))),
+    ("groq", "llama-3.3-70b-versatile", 131_072, 32_768, true, false, Some((0.59, 0.79))),
+    ("groq", "deepseek-r1-distill-llama-70b", 131_072, 131_072, true, false, Some((0.75, 0.99))),
+    ("groq", "gemma2-9b-it", 8_192, 8_192, true, false, Some((0.2, 0.2))),
+];
+
+pub(crate) static BUNDLED_CATALOG: LazyLock<ModelCatalog> = LazyLock::new(|| {
+    let mut catalog = ModelCatalog::new();
+    catalog.extend(bundled_models());
+    catalog
+});
+
+fn bundled_models() -> impl Iterator<Item = ModelInfo> {
+    BUNDLED.iter().map(
+        |&(provider, id, context_window, max_output, tools, vision, price)| {
+            let info = ModelInfo::new(provider, id, context_window, max_output)
+                .supports_tools(tools)
+                .supports_vision(vision);
+            match price {
+                Some((input, output)) => info.price(input, output),
+                None => info,
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{anthropic, openai};
+
+    #[test]
+    fn test_bundled_lookup() {
+        let catalog = ModelCatalog::bundled();
+
+        let gpt4o = catalog.get("openai", openai::GPT_4O_2024_11_20).unwrap();
+        assert_eq!(gpt4o.id, "gpt-4o");
+        assert_eq!(
+            catalog.get("openai", openai::GPT_4O_MINI).unwrap().id,
+            "gpt-4o-mini"
+        );
+        assert_eq!(
+            catalog.get("openai", openai::GPT_4_0613).unwrap().id,
+            "gpt-4"
+        );
+        assert_eq!(
+            catalog
+                .get("anthropic", anthropic::CLAUDE_3_5_SONNET)
+                .unwrap()
+                .max_output,
+            8_192
+        );
+
+        // Not a version of gpt-4
+        assert!(catalog.get("openai", "gpt-4x").is_none());
+        assert!(catalog.get("anthropic", openai::GPT_4O).is_none());
+        assert_eq!(catalog.find(openai::GPT_4O).unwrap().provider, "openai");
+
+        assert!((gpt4o.cost(1_000_000, 100_000).unwrap() - 3.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_register() {
+        let mut catalog = ModelCatalog::bundled();
+        let models = catalog.provider_models("openai").count();
+
+        catalog
+            .register(ModelInfo::new("openai", "gpt-4o", 128_000, 16_384).price(1.0, 1.0))
+            .register(ModelInfo::new("ollama", "llama3.2", 128_000, 2_048));
+
+        assert_eq!(catalog.provider_models("openai").count(), models);
+        assert_eq!(
+            catalog.get("openai", "gpt-4o").unwrap().price,
+            Some(Pricing::new(1.0, 1.0))
+        );
+        assert!(!catalog.get("ollama", "llama3.2").unwrap().supports_tools);
+    }
+}
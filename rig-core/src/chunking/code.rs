@@ -0,0 +1,195 @@
+//! Syntax-aware splitter of source code, parsing the code with tree-sitter (feature
+//! `tree-sitter`).
+
+use std::sync::Arc;
+
+use tree_sitter::{Language, LanguageError, Node, Parser};
+
+use super::{characters, merge_pieces, LengthFunction, RecursiveSplitter, TextSplitter};
+
+/// Splitter parsing source code with a [tree-sitter](https://tree-sitter.github.io) grammar,
+/// and splitting it between the nodes of its syntax tree.
+///
+/// The top-level nodes (e.g.: the items of a Rust module) are merged into chunks of at most
+/// the chunk size. The nodes that are still too large are split between their children (e.g.:
+/// the statements of a function body), recursively. The text between the nodes (whitespace,
+/// comments) is kept with the node following it. A single token larger than the chunk size
+/// (e.g.: a long string literal) is split like text by a [RecursiveSplitter].
+///
+/// Unlike [RecursiveSplitter::for_language], the definitions are found whatever their
+/// indentation or the layout of their signatures.
+///
+/// # Example
+/// ```rust
+/// use rig::chunking::{CodeSplitter, TextSplitter};
+///
+/// // With the grammar of the `tree-sitter-rust` crate
+/// let splitter = CodeSplitter::new(tree_sitter_rust::LANGUAGE, 1000)?.overlap(100);
+/// let chunks = splitter.split_text("fn main() {\n    println!(\"Hello\");\n}\n");
+/// ```
+#[derive(Clone)]
+pub struct CodeSplitter {
+    language: Language,
+    chunk_size: usize,
+    overlap: usize,
+    length: LengthFunction,
+}
+
+impl CodeSplitter {
+    /// Create a splitter of the code of the tree-sitter grammar `language`, with chunks of at
+    /// most `chunk_size` characters. Fails if the grammar is not compatible with the version of
+    /// tree-sitter.
+    pub fn new(language: impl Into<Language>, chunk_size: usize) -> Result<Self, LanguageError> {
+        let language = language.into();
+        Parser::new().set_language(&language)?;
+
+        Ok(Self {
+            language,
+            chunk_size: chunk_size.max(1),
+            overlap: 0,
+            length: characters(),
+        })
+    }
+
+    /// Set the maximum length repeated from the end of a chunk at the start of the next one
+    /// (default: 0).
+    pub fn overlap(mut self, overlap: usize) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Set the function measuring the length of the chunks (default: number of characters).
+    pub fn length_function(
+        mut self,
+        length: impl Fn(&str) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.length = Arc::new(length);
+        self
+    }
+
+    fn text_splitter(&self) -> RecursiveSplitter {
+        let length = self.length.clone();
+        RecursiveSplitter::new(self.chunk_size).length_function(move |text| length(text))
+    }
+
+    /// Split the children of `node`, the first one starting at `start` (with the text
+    /// preceding it) and the last one ending at `end` (with the text following it). The
+    /// `fitting` pieces preceding the node on its line (e.g.: the signature of a function whose
+    /// body is split) are merged with its first children.
+    fn split_node(
+        &self,
+        text: &str,
+        node: Node,
+        mut start: usize,
+        end: usize,
+        mut fitting: Vec<String>,
+    ) -> Vec<String> {
+        let mut chunks = vec![];
+
+        let mut cursor = node.walk();
+        let children = node.children(&mut cursor).collect::<Vec<_>>();
+        for (index, child) in children.iter().enumerate() {
+            let child_end = if index + 1 == children.len() {
+                end
+            } else {
+                child.end_byte()
+            };
+            let piece = &text[start..child_end];
+
+            if (self.length)(piece) <= self.chunk_size {
+                fitting.push(piece.to_string());
+            } else if child.child_count() > 0 {
+                // Keep the pieces of the line of the node with it, e.g.: `impl A` with `{`
+                let line = if starts_line(piece) {
+                    fitting.len()
+                } else {
+                    fitting
+                        .iter()
+                        .rposition(|piece| starts_line(piece))
+                        .unwrap_or(0)
+                };
+                let preceding = fitting.split_off(line);
+                chunks.extend(merge_pieces(
+                    std::mem::take(&mut fitting),
+                    self.chunk_size,
+                    self.overlap,
+                    &self.length,
+                ));
+                chunks.extend(self.split_node(text, *child, start, child_end, preceding));
+            } else {
+                chunks.extend(merge_pieces(
+                    std::mem::take(&mut fitting),
+                    self.chunk_size,
+                    self.overlap,
+                    &self.length,
+                ));
+                chunks.extend(self.text_splitter().split_text(piece));
+            }
+            start = child_end;
+        }
+        if start < end {
+            fitting.push(text[start..end].to_string());
+        }
+
+        chunks.extend(merge_pieces(
+            fitting,
+            self.chunk_size,
+            self.overlap,
+            &self.length,
+        ));
+        chunks
+    }
+}
+
+/// Whether `piece` starts on a new line (after the whitespace preceding it).
+fn starts_line(piece: &str) -> bool {
+    piece[..piece.len() - piece.trim_start().len()].contains('\n')
+}
+
+impl TextSplitter for CodeSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        let mut parser = Parser::new();
+        let tree = parser
+            .set_language(&self.language)
+            .ok()
+            .and_then(|()| parser.parse(text, None));
+
+        match tree {
+            Some(tree) => self.split_node(text, tree.root_node(), 0, text.len(), vec![]),
+            // Only if the parsing is cancelled
+            None => self.text_splitter().overlap(self.overlap).split_text(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_splitter() {
+        let code = "use std::fmt;\n\nimpl A {\n    fn a() {\n        1\n    }\n\n    \
+            fn b(\n        x: u32,\n    ) {\n        2\n    }\n}\n";
+        let splitter = CodeSplitter::new(tree_sitter_rust::LANGUAGE, 60).unwrap();
+        let chunks = splitter.split_text(code);
+
+        // The indented methods and the multi-line signatures are split as definitions
+        assert_eq!(
+            chunks,
+            vec![
+                "use std::fmt;",
+                "impl A {\n    fn a() {\n        1\n    }",
+                "fn b(\n        x: u32,\n    ) {\n        2\n    }\n}"
+            ]
+        );
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 60));
+
+        // The tokens larger than the chunk size are split like text
+        let code = "const S: &str = \"one two three four five six\";";
+        let chunks = CodeSplitter::new(tree_sitter_rust::LANGUAGE, 12)
+            .unwrap()
+            .split_text(code);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 12));
+        assert_eq!(chunks.concat().replace(' ', ""), code.replace(' ', ""));
+    }
+}
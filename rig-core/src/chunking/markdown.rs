@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use super::{Language, RecursiveSplitter, TextSplitter};
+
+/// Splitter for markdown documents, keeping track of the section of each chunk.
+///
+/// The document is split into sections by headers (ignoring `#` lines of code blocks), and each
+/// section is split with the inner splitter (by default a [RecursiveSplitter] using markdown
+/// separators). Each chunk is prefixed with the headers of its section, so that it can be
+/// embedded and retrieved with its context.
+///
+/// # Example
+/// ```rust
+/// use rig::chunking::{MarkdownSplitter, TextSplitter};
+///
+/// let chunks = MarkdownSplitter::new(1000).split_text("# Guide\n\n## Install\n\nRun `cargo add rig-core`.");
+///
+/// assert_eq!(chunks, vec!["# Guide\n## Install\n\nRun `cargo add rig-core`."]);
+/// ```
+#[derive(Clone)]
+pub struct MarkdownSplitter {
+    recursive: RecursiveSplitter,
+    splitter: Option<Arc<dyn TextSplitter>>,
+    include_headers: bool,
+}
+
+impl MarkdownSplitter {
+    /// Create a splitter with chunks of at most `chunk_size` characters (excluding the headers
+    /// prefix).
+    pub fn new(chunk_size: usize) -> Self {
+        Self {
+            recursive: RecursiveSplitter::for_language(Language::Markdown, chunk_size),
+            splitter: None,
+            include_headers: true,
+        }
+    }
+
+    /// Set the maximum length repeated from the end of a chunk at the start of the next one
+    /// within a section (default: 0).
+    pub fn overlap(mut self, overlap: usize) -> Self {
+        self.recursive = self.recursive.overlap(overlap);
+        self
+    }
+
+    /// Set the function measuring the length of the chunks (default: number of characters).
+    pub fn length_function(
+        mut self,
+        length: impl Fn(&str) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.recursive = self.recursive.length_function(length);
+        self
+    }
+
+    /// Use a custom splitter to split the sections, instead of the default [RecursiveSplitter].
+    pub fn splitter(mut self, splitter: impl TextSplitter + 'static) -> Self {
+        self.splitter = Some(Arc::new(splitter));
+        self
+    }
+
+    /// Prefix each chunk with the headers of its section (default: true).
+    pub fn include_headers(mut self, include_headers: bool) -> Self {
+        self.include_headers = include_headers;
+        self
+    }
+}
+
+/// Split a markdown document into `(headers, content)` sections.
+fn sections(text: &str) -> Vec<(Vec<String>, String)> {
+    let mut sections = vec![];
+    let mut headers: Vec<(usize, String)> = vec![];
+    let mut content = String::new();
+    let mut in_code_block = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+
+        let level = line.chars().take_while(|c| *c == '#').count();
+        let is_header = !in_code_block
+            && (1..=6).contains(&level)
+            && line[level..].starts_with(|c: char| c.is_whitespace());
+
+        if is_header {
+            sections.push((
+                headers.iter().map(|(_, header)| header.clone()).collect(),
+                std::mem::take(&mut content),
+            ));
+
+            headers.retain(|(header_level, _)| *header_level < level);
+            headers.push((level, line.trim().to_string()));
+        } else {
+            content.push_str(line);
+            content.push('\n');
+        }
+    }
+
+    sections.push((
+        headers.into_iter().map(|(_, header)| header).collect(),
+        content,
+    ));
+
+    sections
+        .into_iter()
+        .filter(|(_, content)| !content.trim().is_empty())
+        .collect()
+}
+
+impl TextSplitter for MarkdownSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        sections(text)
+            .into_iter()
+            .flat_map(|(headers, content)| {
+                let chunks = match &self.splitter {
+                    Some(splitter) => splitter.split_text(&content),
+                    None => self.recursive.split_text(&content),
+                };
+
+                chunks.into_iter().map(move |chunk| {
+                    if self.include_headers && !headers.is_empty() {
+                        format!("{}\n\n{}", headers.join("\n"), chunk)
+                    } else {
+                        chunk
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_splitter() {
+        let text = "Intro.\n# Title\n\n## A\n\nText of A.\n\n```sh\n# not a header\n```\n## B\n\nText of B is longer than the chunk size of forty.\n### C\nText of C.";
+        let chunks = MarkdownSplitter::new(40).split_text(text);
+
+        assert_eq!(
+            chunks,
+            vec![
+                "Intro.",
+                "# Title\n## A\n\nText of A.\n\n```sh\n# not a header\n```",
+                "# Title\n## B\n\nText of B is longer than the chunk size",
+                "# Title\n## B\n\nof forty.",
+                "# Title\n## B\n### C\n\nText of C.",
+            ]
+        );
+    }
+}
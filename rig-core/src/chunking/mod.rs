@@ -0,0 +1,144 @@
+//! This module provides text splitters, which split long documents into chunks small enough
+//! to be embedded (and retrieved) individually.
+//!
+//! The following splitters are provided:
+//! - [RecursiveSplitter]: splits text on a list of separators (paragraphs, lines, words, ...),
+//!   recursing into smaller separators until the chunks fit, like LangChain's
+//!   `RecursiveCharacterTextSplitter`. [RecursiveSplitter::for_language] uses the keywords
+//!   starting the lines of definitions and statements of a programming language (`fn`,
+//!   `class`, ...) as separators. The keywords are matched as text: the code is not parsed, so
+//!   e.g. indented definitions or multi-line signatures are split like plain text.
+//! - [CodeSplitter] (with the `tree-sitter` feature): parses source code with a tree-sitter
+//!   grammar, and splits it between the nodes of its syntax tree (definitions, statements, ...).
+//! - [SentenceSplitter]: groups whole sentences into chunks.
+//! - [MarkdownSplitter]: splits markdown documents by section, prefixing each chunk with the
+//!   headers of its section.
+//!
+//! Chunk sizes are measured in characters by default. Use the `length_function` method of the
+//! splitters to measure them in tokens instead (e.g.: with a tokenizer of the embedding model).
+//!
+//! Splitters implement the [TextSplitter] trait and can be set on the
+//! [EmbeddingsBuilder](crate::embeddings::EmbeddingsBuilder) to embed each chunk of the
+//! documents separately.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     chunking::{MarkdownSplitter, TextSplitter},
+//!     embeddings::EmbeddingsBuilder,
+//!     providers::openai,
+//! };
+//!
+//! let splitter = MarkdownSplitter::new(1000).overlap(100);
+//! let chunks = splitter.split_text("# Title\n\nSome text...");
+//!
+//! let openai = openai::Client::from_env();
+//! let embeddings = EmbeddingsBuilder::new(openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL))
+//!     .splitter(splitter)
+//!     .documents(documents)?
+//!     .build()
+//!     .await?;
+//! ```
+
+use std::sync::Arc;
+
+#[cfg(feature = "tree-sitter")]
+pub mod code;
+pub mod markdown;
+pub mod recursive;
+pub mod sentence;
+
+#[cfg(feature = "tree-sitter")]
+pub use code::CodeSplitter;
+pub use markdown::MarkdownSplitter;
+pub use recursive::{Language, RecursiveSplitter};
+pub use sentence::SentenceSplitter;
+
+/// Trait for text splitters.
+pub trait TextSplitter: Send + Sync {
+    /// Split `text` into chunks.
+    fn split_text(&self, text: &str) -> Vec<String>;
+}
+
+impl<F> TextSplitter for F
+where
+    F: Fn(&str) -> Vec<String> + Send + Sync,
+{
+    fn split_text(&self, text: &str) -> Vec<String> {
+        self(text)
+    }
+}
+
+/// Function measuring the length of a text (e.g.: number of characters or tokens).
+pub type LengthFunction = Arc<dyn Fn(&str) -> usize + Send + Sync>;
+
+pub(crate) fn characters() -> LengthFunction {
+    Arc::new(|text: &str| text.chars().count())
+}
+
+/// Merge consecutive pieces of text into chunks of at most `chunk_size`, repeating the last
+/// pieces of each chunk (up to `overlap`) at the beginning of the next one.
+///
+/// Pieces larger than `chunk_size` are kept as their own chunk.
+pub(crate) fn merge_pieces(
+    pieces: impl IntoIterator<Item = String>,
+    chunk_size: usize,
+    overlap: usize,
+    length: &LengthFunction,
+) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current: Vec<(String, usize)> = vec![];
+    let mut current_length = 0;
+
+    for piece in pieces {
+        let piece_length = length(&piece);
+
+        if current_length + piece_length > chunk_size && !current.is_empty() {
+            push_chunk(&mut chunks, &current);
+
+            // Keep the last pieces for the overlap, as long as the next piece still fits
+            while !current.is_empty()
+                && (current_length > overlap || current_length + piece_length > chunk_size)
+            {
+                current_length -= current.remove(0).1;
+            }
+        }
+
+        current_length += piece_length;
+        current.push((piece, piece_length));
+    }
+
+    push_chunk(&mut chunks, &current);
+    chunks
+}
+
+fn push_chunk(chunks: &mut Vec<String>, pieces: &[(String, usize)]) {
+    let chunk = pieces
+        .iter()
+        .map(|(piece, _)| piece.as_str())
+        .collect::<String>();
+    let chunk = chunk.trim();
+
+    if !chunk.is_empty() {
+        chunks.push(chunk.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_pieces() {
+        let pieces = ["aa ", "bb ", "cc ", "dd ", "ee"].map(String::from);
+
+        assert_eq!(
+            merge_pieces(pieces.clone(), 6, 0, &characters()),
+            vec!["aa bb", "cc dd", "ee"]
+        );
+        assert_eq!(
+            merge_pieces(pieces, 9, 3, &characters()),
+            vec!["aa bb cc", "cc dd ee"]
+        );
+    }
+}
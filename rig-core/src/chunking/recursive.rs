@@ -0,0 +1,298 @@
+use std::sync::Arc;
+
+use super::{characters, merge_pieces, LengthFunction, TextSplitter};
+
+/// Programming languages with separator presets for [RecursiveSplitter::for_language].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Go,
+    Java,
+    Cpp,
+    Markdown,
+}
+
+impl Language {
+    /// Separators of the language: line prefixes of definitions, then of statements (matched as
+    /// text, without parsing the code), then lines and words.
+    pub fn separators(&self) -> Vec<&'static str> {
+        let separators: &[&str] = match self {
+            Language::Rust => &[
+                "\nfn ",
+                "\npub fn ",
+                "\nimpl ",
+                "\npub struct ",
+                "\nstruct ",
+                "\npub enum ",
+                "\nenum ",
+                "\npub trait ",
+                "\ntrait ",
+                "\nmod ",
+                "\npub mod ",
+                "\nconst ",
+                "\nlet ",
+                "\nif ",
+                "\nmatch ",
+                "\nfor ",
+                "\nwhile ",
+                "\nloop ",
+            ],
+            Language::Python => &["\nclass ", "\ndef ", "\n\tdef ", "\n    def "],
+            Language::JavaScript => &[
+                "\nfunction ",
+                "\nconst ",
+                "\nlet ",
+                "\nvar ",
+                "\nclass ",
+                "\nexport ",
+                "\nif ",
+                "\nfor ",
+                "\nwhile ",
+                "\nswitch ",
+            ],
+            Language::TypeScript => &[
+                "\nenum ",
+                "\ninterface ",
+                "\nnamespace ",
+                "\ntype ",
+                "\nclass ",
+                "\nfunction ",
+                "\nconst ",
+                "\nlet ",
+                "\nvar ",
+                "\nexport ",
+                "\nif ",
+                "\nfor ",
+                "\nwhile ",
+                "\nswitch ",
+            ],
+            Language::Go => &[
+                "\nfunc ",
+                "\nvar ",
+                "\nconst ",
+                "\ntype ",
+                "\nif ",
+                "\nfor ",
+                "\nswitch ",
+            ],
+            Language::Java => &[
+                "\nclass ",
+                "\npublic ",
+                "\nprotected ",
+                "\nprivate ",
+                "\nstatic ",
+                "\nif ",
+                "\nfor ",
+                "\nwhile ",
+                "\nswitch ",
+            ],
+            Language::Cpp => &[
+                "\nclass ",
+                "\nstruct ",
+                "\nnamespace ",
+                "\nvoid ",
+                "\nint ",
+                "\nfloat ",
+                "\ndouble ",
+                "\nif ",
+                "\nfor ",
+                "\nwhile ",
+                "\nswitch ",
+            ],
+            Language::Markdown => &[
+                "\n# ",
+                "\n## ",
+                "\n### ",
+                "\n#### ",
+                "\n##### ",
+                "\n###### ",
+                "\n---",
+            ],
+        };
+
+        separators
+            .iter()
+            .copied()
+            .chain(["\n\n", "\n", " ", ""])
+            .collect()
+    }
+}
+
+/// Splitter recursively splitting text on a list of separators until the chunks fit in the
+/// chunk size.
+///
+/// The text is first split on the first separator found in the text, and the pieces are merged
+/// back into chunks of at most the chunk size. Pieces that are still too large are split on the
+/// next separators. The separators are kept at the beginning of the pieces they precede.
+///
+/// # Example
+/// ```rust
+/// use rig::chunking::{Language, RecursiveSplitter, TextSplitter};
+///
+/// // Split paragraphs, then lines, then words
+/// let chunks = RecursiveSplitter::new(500).overlap(50).split_text("...");
+///
+/// // Split Rust code on lines starting with `fn`, `impl`, ..., then `let`, `if`, ...
+/// let chunks = RecursiveSplitter::for_language(Language::Rust, 1000).split_text("...");
+/// ```
+#[derive(Clone)]
+pub struct RecursiveSplitter {
+    chunk_size: usize,
+    overlap: usize,
+    separators: Vec<String>,
+    length: LengthFunction,
+}
+
+impl RecursiveSplitter {
+    /// Create a splitter with chunks of at most `chunk_size` characters, splitting paragraphs,
+    /// then lines, then words.
+    pub fn new(chunk_size: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            overlap: 0,
+            separators: ["\n\n", "\n", " ", ""].map(String::from).to_vec(),
+            length: characters(),
+        }
+    }
+
+    /// Create a splitter for source code (or markdown) of the given language, using the
+    /// separators of [Language::separators]. See `CodeSplitter` (with the `tree-sitter`
+    /// feature) to split the code between the nodes of its syntax tree instead.
+    pub fn for_language(language: Language, chunk_size: usize) -> Self {
+        Self::new(chunk_size).separators(language.separators())
+    }
+
+    /// Set the maximum length repeated from the end of a chunk at the start of the next one
+    /// (default: 0).
+    pub fn overlap(mut self, overlap: usize) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Set the separators, by priority. The empty separator splits between characters.
+    pub fn separators(mut self, separators: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.separators = separators.into_iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Set the function measuring the length of the chunks (default: number of characters).
+    pub fn length_function(
+        mut self,
+        length: impl Fn(&str) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.length = Arc::new(length);
+        self
+    }
+
+    fn split(&self, text: &str, separators: &[String]) -> Vec<String> {
+        // Use the first separator found in the text
+        let position = separators
+            .iter()
+            .position(|separator| separator.is_empty() || text.contains(separator.as_str()))
+            .unwrap_or(separators.len());
+
+        let (pieces, rest) = match separators.get(position) {
+            Some(separator) => (split_keep(text, separator), &separators[position + 1..]),
+            None => (vec![text.to_string()], &separators[separators.len()..]),
+        };
+
+        let mut chunks = vec![];
+        let mut fitting = vec![];
+        for piece in pieces {
+            if (self.length)(&piece) <= self.chunk_size {
+                fitting.push(piece);
+            } else {
+                chunks.extend(merge_pieces(
+                    std::mem::take(&mut fitting),
+                    self.chunk_size,
+                    self.overlap,
+                    &self.length,
+                ));
+
+                if rest.is_empty() {
+                    // No smaller separator: keep the piece as is
+                    chunks.extend(merge_pieces([piece], self.chunk_size, 0, &self.length));
+                } else {
+                    chunks.extend(self.split(&piece, rest));
+                }
+            }
+        }
+
+        chunks.extend(merge_pieces(
+            fitting,
+            self.chunk_size,
+            self.overlap,
+            &self.length,
+        ));
+        chunks
+    }
+}
+
+/// Split `text` on `separator`, keeping the separator at the beginning of the following piece.
+fn split_keep(text: &str, separator: &str) -> Vec<String> {
+    if separator.is_empty() {
+        return text.chars().map(String::from).collect();
+    }
+
+    let mut pieces = vec![];
+    let mut start = 0;
+    for (index, _) in text.match_indices(separator) {
+        if index > start {
+            pieces.push(text[start..index].to_string());
+        }
+        start = index;
+    }
+    pieces.push(text[start..].to_string());
+    pieces
+}
+
+impl TextSplitter for RecursiveSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        self.split(text, &self.separators)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recursive_splitter() {
+        let text = "First paragraph.\n\nSecond paragraph is longer than the chunk size.";
+        let chunks = RecursiveSplitter::new(25).split_text(text);
+
+        assert_eq!(
+            chunks,
+            vec![
+                "First paragraph.",
+                "Second paragraph is",
+                "longer than the chunk",
+                "size."
+            ]
+        );
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 25));
+    }
+
+    #[test]
+    fn test_overlap() {
+        let chunks = RecursiveSplitter::new(11)
+            .overlap(6)
+            .split_text("one two three four");
+
+        assert_eq!(chunks, vec!["one two", "two three", "three four"]);
+    }
+
+    #[test]
+    fn test_code_splitter() {
+        let code = "use std::fmt;\n\nfn a() {\n    1\n}\n\nfn b() {\n    2\n}\n";
+        let chunks = RecursiveSplitter::for_language(Language::Rust, 40).split_text(code);
+
+        assert_eq!(
+            chunks,
+            vec!["use std::fmt;\n\nfn a() {\n    1\n}", "fn b() {\n    2\n}"]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use super::{characters, merge_pieces, LengthFunction, RecursiveSplitter, TextSplitter};
+
+/// Splitter grouping whole sentences into chunks.
+///
+/// Sentences end with `.`, `!` or `?` followed by whitespace, or with a blank line. Sentences
+/// longer than the chunk size are split on words.
+///
+/// # Example
+/// ```rust
+/// use rig::chunking::{SentenceSplitter, TextSplitter};
+///
+/// // Chunks of ~5 sentences, overlapping by ~1 sentence
+/// let chunks = SentenceSplitter::new(500).overlap(100).split_text("...");
+/// ```
+#[derive(Clone)]
+pub struct SentenceSplitter {
+    chunk_size: usize,
+    overlap: usize,
+    length: LengthFunction,
+}
+
+impl SentenceSplitter {
+    /// Create a splitter with chunks of at most `chunk_size` characters.
+    pub fn new(chunk_size: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            overlap: 0,
+            length: characters(),
+        }
+    }
+
+    /// Set the maximum length repeated from the end of a chunk at the start of the next one
+    /// (default: 0). Only whole sentences are repeated.
+    pub fn overlap(mut self, overlap: usize) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Set the function measuring the length of the chunks (default: number of characters).
+    pub fn length_function(
+        mut self,
+        length: impl Fn(&str) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.length = Arc::new(length);
+        self
+    }
+}
+
+/// Split `text` into sentences, keeping the whitespace following each sentence.
+pub(crate) fn sentences(text: &str) -> Vec<String> {
+    let mut sentences = vec![];
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let end_of_sentence = match (c, chars.peek()) {
+            ('.' | '!' | '?', Some((_, next))) => next.is_whitespace(),
+            ('\n', Some((_, '\n'))) => true,
+            _ => false,
+        };
+
+        if end_of_sentence {
+            // Include the following whitespace in the sentence
+            let mut end = index + c.len_utf8();
+            while let Some((next_index, next)) = chars.peek() {
+                if !next.is_whitespace() {
+                    break;
+                }
+                end = next_index + next.len_utf8();
+                chars.next();
+            }
+
+            sentences.push(text[start..end].to_string());
+            start = end;
+        }
+    }
+
+    if start < text.len() {
+        sentences.push(text[start..].to_string());
+    }
+    sentences
+}
+
+impl TextSplitter for SentenceSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        let words = RecursiveSplitter::new(self.chunk_size)
+            .separators([" ", ""])
+            .length_function({
+                let length = self.length.clone();
+                move |text| length(text)
+            });
+
+        let pieces = sentences(text).into_iter().flat_map(|sentence| {
+            if (self.length)(&sentence) <= self.chunk_size {
+                vec![sentence]
+            } else {
+                words
+                    .split_text(&sentence)
+                    .into_iter()
+                    .map(|chunk| chunk + " ")
+                    .collect()
+            }
+        });
+
+        merge_pieces(pieces, self.chunk_size, self.overlap, &self.length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentences() {
+        assert_eq!(
+            sentences("Hello world! How are you? Fine.\n\nNew paragraph 3.5 times"),
+            vec![
+                "Hello world! ",
+                "How are you? ",
+                "Fine.\n\n",
+                "New paragraph 3.5 times"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sentence_splitter() {
+        let chunks = SentenceSplitter::new(30)
+            .overlap(14)
+            .split_text("One sentence. Two sentence. Three sentence.");
+
+        assert_eq!(
+            chunks,
+            vec![
+                "One sentence. Two sentence.",
+                "Two sentence. Three sentence."
+            ]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! This module provides a high-level abstraction for classifying text into a set of labels using
+//! LLMs, with the confidence of the model in the chosen label.
+//!
+//! The models with [constrained decoding](crate::completion::Capabilities::constrained_decoding)
+//! are constrained to answer with one of the labels. The confidence is the probability of the
+//! tokens of the label in the answer, for the models returning the
+//! [log probabilities](crate::completion::Capabilities::logprobs) of their tokens.
+//!
+//! # Example
+//! ```
+//! use rig::{classifier::ClassifierBuilder, providers::openai};
+//!
+//! let openai = openai::Client::new("your-open-ai-api-key");
+//!
+//! let classifier = ClassifierBuilder::new(openai.completion_model(openai::GPT_4O_MINI))
+//!     .label("positive", "The text expresses a favorable opinion")
+//!     .label("negative", "The text expresses an unfavorable opinion")
+//!     .label("neutral", "The text expresses no opinion")
+//!     .build();
+//!
+//! let classification = classifier
+//!     .classify("The battery lasts two days, I love it!")
+//!     .await
+//!     .expect("Failed to classify the text");
+//!
+//! println!("{} ({:?})", classification.label, classification.confidence);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    completion::{
+        AssistantContent, CompletionError, CompletionModel, Message, OutputConstraint, TokenLogprob,
+    },
+    OneOrMany,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClassificationError {
+    /// The answer of the model is not one of the labels
+    #[error("UnknownLabel: {0}")]
+    UnknownLabel(String),
+
+    #[error("CompletionError: {0}")]
+    CompletionError(#[from] CompletionError),
+}
+
+/// Label of a [Classifier].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Label {
+    pub name: String,
+    /// Description of the texts of the label, for the model
+    pub description: Option<String>,
+}
+
+/// Label chosen by a [Classifier].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Classification {
+    pub label: String,
+    /// The probability of the label in the answer of the model (between 0 and 1), if the model
+    /// returned the log probabilities of its tokens
+    pub confidence: Option<f64>,
+}
+
+/// Classifier of texts into a set of labels.
+pub struct Classifier<M: CompletionModel> {
+    model: M,
+    preamble: String,
+    labels: Vec<Label>,
+}
+
+impl<M: CompletionModel> Classifier<M> {
+    /// The label of `text`.
+    pub async fn classify(
+        &self,
+        text: impl Into<Message> + Send,
+    ) -> Result<Classification, ClassificationError> {
+        let capabilities = self.model.capabilities();
+        let mut request = self
+            .model
+            .completion_request(text)
+            .preamble(self.preamble.clone());
+        if capabilities.constrained_decoding {
+            request = request.output_constraint(OutputConstraint::JsonSchema(json!({
+                "type": "string",
+                "enum": self.labels.iter().map(|label| &label.name).collect::<Vec<_>>(),
+            })));
+        }
+        if capabilities.logprobs {
+            request = request.logprobs(true);
+        }
+        let response = request.send().await?;
+
+        let answer = text_of(&response.choice);
+        // The constrained answers are JSON strings
+        let label_text = serde_json::from_str::<String>(answer.trim()).unwrap_or(answer.clone());
+        let label = self
+            .find_label(&label_text)
+            .ok_or_else(|| ClassificationError::UnknownLabel(answer.clone()))?;
+
+        Ok(Classification {
+            label: label.name.clone(),
+            confidence: response
+                .logprobs
+                .and_then(|logprobs| label_confidence(&logprobs, &label.name)),
+        })
+    }
+
+    /// The label named by `answer`, or else the label mentioned first in `answer`.
+    fn find_label(&self, answer: &str) -> Option<&Label> {
+        let answer = normalize(answer);
+        self.labels
+            .iter()
+            .find(|label| normalize(&label.name) == answer)
+            .or_else(|| {
+                self.labels
+                    .iter()
+                    .filter_map(|label| {
+                        let name = normalize(&label.name);
+                        answer
+                            .find(&name)
+                            .map(|position| (position, usize::MAX - name.len(), label))
+                    })
+                    .min_by_key(|(position, length, _)| (*position, *length))
+                    .map(|(_, _, label)| label)
+            })
+    }
+}
+
+fn text_of(choice: &OneOrMany<AssistantContent>) -> String {
+    choice
+        .iter()
+        .filter_map(|content| match content {
+            AssistantContent::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn normalize(text: &str) -> String {
+    text.trim()
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// The probability of the tokens spanning the first occurrence of `label` in the completion of
+/// `logprobs`.
+fn label_confidence(logprobs: &[TokenLogprob], label: &str) -> Option<f64> {
+    let text = logprobs
+        .iter()
+        .map(|token| token.token.as_str())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let start = text.find(&label.to_ascii_lowercase())?;
+    let end = start + label.len();
+
+    let mut offset = 0;
+    let mut logprob = 0.0;
+    for token in logprobs {
+        let token_end = offset + token.token.len();
+        if token_end > start && offset < end {
+            logprob += token.logprob;
+        }
+        offset = token_end;
+    }
+    Some(logprob.exp())
+}
+
+/// Builder of a [Classifier].
+pub struct ClassifierBuilder<M: CompletionModel> {
+    model: M,
+    instructions: Vec<String>,
+    labels: Vec<Label>,
+}
+
+impl<M: CompletionModel> ClassifierBuilder<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            instructions: vec![],
+            labels: vec![],
+        }
+    }
+
+    /// Add the label `name`, described to the model by `description`.
+    pub fn label(mut self, name: &str, description: &str) -> Self {
+        self.labels.push(Label {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+        });
+        self
+    }
+
+    /// Add the labels `names`, without descriptions.
+    pub fn labels<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.labels.extend(names.into_iter().map(|name| Label {
+            name: name.to_string(),
+            description: None,
+        }));
+        self
+    }
+
+    /// Add instructions to the classifier (e.g.: on how to break ties between labels).
+    pub fn preamble(mut self, preamble: &str) -> Self {
+        self.instructions.push(preamble.to_string());
+        self
+    }
+
+    pub fn build(self) -> Classifier<M> {
+        let labels = self
+            .labels
+            .iter()
+            .map(|label| match &label.description {
+                Some(description) => format!("- {}: {description}", label.name),
+                None => format!("- {}", label.name),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut preamble = format!(
+            "Classify the text into one of the following labels:\n{labels}\n\n\
+            Answer with the label only, exactly as written above."
+        );
+        for instructions in self.instructions {
+            preamble.push_str(&format!(
+                "\n=============== ADDITIONAL INSTRUCTIONS ===============\n{instructions}"
+            ));
+        }
+
+        Classifier {
+            model: self.model,
+            preamble,
+            labels: self.labels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{Capabilities, CompletionRequest, CompletionResponse};
+
+    /// Answers `answer`, with logprobs if `tokens` is not empty
+    #[derive(Clone)]
+    struct LabelModel {
+        answer: &'static str,
+        tokens: Vec<(&'static str, f64)>,
+        capabilities: Capabilities,
+    }
+
+    impl CompletionModel for LabelModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            assert!(request
+                .preamble
+                .unwrap()
+                .contains("- negative: Unfavorable"));
+            assert_eq!(
+                request.output_constraint.is_some(),
+                self.capabilities.constrained_decoding
+            );
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(self.answer)),
+                raw_response: (),
+                logprobs: (!self.tokens.is_empty()).then(|| {
+                    self.tokens
+                        .iter()
+                        .map(|(token, logprob)| TokenLogprob {
+                            token: token.to_string(),
+                            logprob: *logprob,
+                            top_logprobs: vec![],
+                        })
+                        .collect()
+                }),
+            })
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            self.capabilities
+        }
+    }
+
+    fn classifier(
+        answer: &'static str,
+        tokens: Vec<(&'static str, f64)>,
+        capabilities: Capabilities,
+    ) -> Classifier<LabelModel> {
+        ClassifierBuilder::new(LabelModel {
+            answer,
+            tokens,
+            capabilities,
+        })
+        .label("positive", "Favorable")
+        .label("negative", "Unfavorable")
+        .labels(["neutral"])
+        .build()
+    }
+
+    #[tokio::test]
+    async fn test_classify() {
+        let classification = classifier(
+            "\"negative\"",
+            vec![("\"", 0.0), ("neg", -0.1), ("ative", -0.2), ("\"", 0.0)],
+            Capabilities::all(),
+        )
+        .classify("I hate it")
+        .await
+        .unwrap();
+        assert_eq!(classification.label, "negative");
+        assert!((classification.confidence.unwrap() - (-0.3f64).exp()).abs() < 1e-9);
+
+        let classification = classifier("The label is: Neutral.", vec![], Capabilities::default())
+            .classify("It is a phone")
+            .await
+            .unwrap();
+        assert_eq!(classification.label, "neutral");
+        assert_eq!(classification.confidence, None);
+
+        assert!(matches!(
+            classifier("mixed", vec![], Capabilities::default())
+                .classify("It is a phone")
+                .await,
+            Err(ClassificationError::UnknownLabel(_))
+        ));
+    }
+}
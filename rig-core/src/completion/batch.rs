@@ -0,0 +1,322 @@
+//! This module provides functionality for submitting completion requests as provider
+//! batch jobs (e.g.: OpenAI Batch API, Anthropic Message Batches API).
+//!
+//! Batch jobs are processed asynchronously by the provider (usually within 24 hours) at a
+//! discounted price, which makes them well suited to offline workloads such as bulk extraction.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     completion::{batch::{BatchCompletion, BatchCompletionModel}, CompletionModel},
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let model = openai.completion_model(openai::GPT_4O_MINI);
+//!
+//! let mut batch = model.batch();
+//! for (i, review) in reviews.iter().enumerate() {
+//!     batch = batch.request(
+//!         format!("review-{i}"),
+//!         model
+//!             .completion_request(format!("Summarize this review: {review}"))
+//!             .build(),
+//!     );
+//! }
+//!
+//! // Submit the batch job and poll it until it is done
+//! let results = batch.submit().await?.wait().await?;
+//!
+//! for (id, result) in results {
+//!     println!("{id}: {:?}", result.map(|response| response.choice));
+//! }
+//! ```
+
+use std::{collections::HashMap, time::Duration};
+
+use super::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse};
+use crate::wasm_compat::WasmCompatSend;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error building one of the requests of the batch
+    #[error("CompletionError: {0}")]
+    CompletionError(#[from] CompletionError),
+
+    /// The batch job did not complete (e.g.: failed, expired or cancelled)
+    #[error("BatchNotCompleted: batch {id} is {status:?}")]
+    BatchNotCompleted { id: String, status: BatchStatus },
+
+    /// Error returned by the completion model provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+/// Status of a batch job.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchStatus {
+    /// The batch job is being validated or processed
+    InProgress,
+    /// The batch job is done and its results can be retrieved
+    Completed,
+    /// The batch job failed (e.g.: invalid input file)
+    Failed(String),
+    /// The batch job did not complete in the provider's completion window
+    Expired,
+    /// The batch job was cancelled
+    Cancelled,
+}
+
+impl BatchStatus {
+    /// Whether the batch job will not change status anymore
+    pub fn is_done(&self) -> bool {
+        !matches!(self, BatchStatus::InProgress)
+    }
+}
+
+/// Results of a batch job, by custom id of the request.
+pub type BatchResults<T> = HashMap<String, Result<CompletionResponse<T>, CompletionError>>;
+
+/// Trait defining a completion model that supports provider batch jobs.
+pub trait BatchCompletionModel: CompletionModel {
+    /// Submit the requests as a batch job and return the id of the job.
+    /// Each request is identified by a custom id, which is used to map the results back.
+    fn submit_batch(
+        &self,
+        requests: Vec<(String, CompletionRequest)>,
+    ) -> impl std::future::Future<Output = Result<String, BatchError>> + WasmCompatSend;
+
+    /// Get the status of the batch job `batch_id`.
+    fn batch_status(
+        &self,
+        batch_id: &str,
+    ) -> impl std::future::Future<Output = Result<BatchStatus, BatchError>> + WasmCompatSend;
+
+    /// Get the results of the completed batch job `batch_id`.
+    fn batch_results(
+        &self,
+        batch_id: &str,
+    ) -> impl std::future::Future<Output = Result<BatchResults<Self::Response>, BatchError>>
+           + WasmCompatSend;
+
+    /// Cancel the batch job `batch_id`.
+    fn cancel_batch(
+        &self,
+        batch_id: &str,
+    ) -> impl std::future::Future<Output = Result<(), BatchError>> + WasmCompatSend;
+
+    /// Create a batch builder for this model.
+    fn batch(&self) -> BatchCompletion<Self> {
+        BatchCompletion::new(self.clone())
+    }
+}
+
+/// Builder for batch jobs.
+pub struct BatchCompletion<M: BatchCompletionModel> {
+    model: M,
+    requests: Vec<(String, CompletionRequest)>,
+    poll_interval: Duration,
+}
+
+impl<M: BatchCompletionModel> BatchCompletion<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            requests: vec![],
+            poll_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Add a request to the batch, identified by `custom_id`.
+    pub fn request(mut self, custom_id: impl Into<String>, request: CompletionRequest) -> Self {
+        self.requests.push((custom_id.into(), request));
+        self
+    }
+
+    /// Add multiple requests to the batch.
+    pub fn requests(
+        mut self,
+        requests: impl IntoIterator<Item = (impl Into<String>, CompletionRequest)>,
+    ) -> Self {
+        self.requests.extend(
+            requests
+                .into_iter()
+                .map(|(custom_id, request)| (custom_id.into(), request)),
+        );
+        self
+    }
+
+    /// Set the interval at which the batch job status is polled by [BatchJob::wait] (default: 60s).
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Submit the batch job.
+    pub async fn submit(self) -> Result<BatchJob<M>, BatchError> {
+        let id = self.model.submit_batch(self.requests).await?;
+        tracing::info!(target: "rig", "Submitted batch job {}", id);
+
+        Ok(BatchJob {
+            model: self.model,
+            id,
+            poll_interval: self.poll_interval,
+        })
+    }
+}
+
+/// A submitted batch job.
+pub struct BatchJob<M: BatchCompletionModel> {
+    model: M,
+    id: String,
+    poll_interval: Duration,
+}
+
+impl<M: BatchCompletionModel> BatchJob<M> {
+    /// Resume a previously submitted batch job (e.g.: from another process).
+    pub fn from_id(model: M, id: &str) -> Self {
+        Self {
+            model,
+            id: id.to_string(),
+            poll_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Set the interval at which the batch job status is polled by [BatchJob::wait] (default: 60s).
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Id of the batch job
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub async fn status(&self) -> Result<BatchStatus, BatchError> {
+        self.model.batch_status(&self.id).await
+    }
+
+    pub async fn cancel(&self) -> Result<(), BatchError> {
+        self.model.cancel_batch(&self.id).await
+    }
+
+    /// Poll the batch job until it is done and return its results.
+    pub async fn wait(&self) -> Result<BatchResults<M::Response>, BatchError> {
+        loop {
+            match self.status().await? {
+                BatchStatus::InProgress => {
+                    tracing::debug!(target: "rig", "Batch job {} in progress", self.id);
+                    crate::runtime::sleep(self.poll_interval).await;
+                }
+                BatchStatus::Completed => return self.model.batch_results(&self.id).await,
+                status => {
+                    return Err(BatchError::BatchNotCompleted {
+                        id: self.id.clone(),
+                        status,
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use super::*;
+    use crate::{completion::AssistantContent, message::Message, OneOrMany};
+
+    #[derive(Clone, Default)]
+    struct MockModel {
+        polls: Arc<AtomicUsize>,
+        requests: Arc<Mutex<Vec<(String, CompletionRequest)>>>,
+    }
+
+    impl CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            unimplemented!()
+        }
+    }
+
+    impl BatchCompletionModel for MockModel {
+        async fn submit_batch(
+            &self,
+            requests: Vec<(String, CompletionRequest)>,
+        ) -> Result<String, BatchError> {
+            *self.requests.lock().unwrap() = requests;
+            Ok("batch_1".to_string())
+        }
+
+        async fn batch_status(&self, _batch_id: &str) -> Result<BatchStatus, BatchError> {
+            if self.polls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Ok(BatchStatus::InProgress)
+            } else {
+                Ok(BatchStatus::Completed)
+            }
+        }
+
+        async fn batch_results(&self, _batch_id: &str) -> Result<BatchResults<()>, BatchError> {
+            Ok(self
+                .requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(id, _)| {
+                    (
+                        id.clone(),
+                        Ok(CompletionResponse {
+                            choice: OneOrMany::one(AssistantContent::text(id)),
+                            raw_response: (),
+                            logprobs: None,
+                        }),
+                    )
+                })
+                .collect())
+        }
+
+        async fn cancel_batch(&self, _batch_id: &str) -> Result<(), BatchError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_wait() {
+        let model = MockModel::default();
+
+        let job = model
+            .batch()
+            .request("a", model.completion_request(Message::user("a")).build())
+            .request("b", model.completion_request(Message::user("b")).build())
+            .poll_interval(Duration::from_millis(1))
+            .submit()
+            .await
+            .unwrap();
+
+        let results = job.wait().await.unwrap();
+
+        assert_eq!(job.id(), "batch_1");
+        assert_eq!(model.polls.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            results["b"].as_ref().unwrap().choice.first(),
+            AssistantContent::text("b")
+        );
+    }
+}
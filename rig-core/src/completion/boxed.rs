@@ -0,0 +1,149 @@
+//! Type-erased completion models, to store heterogeneous models in collections (e.g.: a map of
+//! models by name) and choose them at runtime without generic parameters.
+//!
+//! The raw responses of the models are boxed as [BoxRawResponse], and can be downcast to the
+//! response type of the underlying model.
+//!
+//! # Example
+//! ```rust
+//! use std::collections::HashMap;
+//!
+//! use rig::{
+//!     agent::AgentBuilder,
+//!     completion::BoxCompletionModel,
+//!     providers::{anthropic, openai},
+//! };
+//!
+//! let mut models = HashMap::new();
+//! models.insert(
+//!     "gpt-4o",
+//!     BoxCompletionModel::new(openai::Client::from_env().completion_model(openai::GPT_4O)),
+//! );
+//! models.insert(
+//!     "claude",
+//!     BoxCompletionModel::new(
+//!         anthropic::Client::from_env().completion_model(anthropic::CLAUDE_3_5_SONNET),
+//!     ),
+//! );
+//!
+//! let agent = AgentBuilder::new(models["claude"].clone()).build();
+//! ```
+
+use std::{any::Any, sync::Arc};
+
+use super::{
+    Capabilities, CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
+};
+use crate::wasm_compat::WasmBoxedFuture;
+
+/// Raw response of a [BoxCompletionModel] (e.g.: downcast it with
+/// `response.raw_response.downcast_ref::<openai::CompletionResponse>()`).
+pub type BoxRawResponse = Box<dyn Any + Send + Sync>;
+
+/// Object-safe version of [CompletionModel], implemented by all the completion models.
+pub trait CompletionModelDyn: Send + Sync {
+    fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> WasmBoxedFuture<'_, Result<CompletionResponse<BoxRawResponse>, CompletionError>>;
+
+    fn capabilities(&self) -> Capabilities;
+}
+
+impl<M> CompletionModelDyn for M
+where
+    M: CompletionModel,
+    M::Response: 'static,
+{
+    fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> WasmBoxedFuture<'_, Result<CompletionResponse<BoxRawResponse>, CompletionError>> {
+        Box::pin(async move {
+            let response = CompletionModel::completion(self, request).await?;
+            Ok(CompletionResponse {
+                choice: response.choice,
+                raw_response: Box::new(response.raw_response) as BoxRawResponse,
+                logprobs: response.logprobs,
+            })
+        })
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CompletionModel::capabilities(self)
+    }
+}
+
+/// A type-erased [CompletionModel], cheap to clone.
+#[derive(Clone)]
+pub struct BoxCompletionModel(Arc<dyn CompletionModelDyn>);
+
+impl BoxCompletionModel {
+    pub fn new<M>(model: M) -> Self
+    where
+        M: CompletionModel + 'static,
+        M::Response: 'static,
+    {
+        Self(Arc::new(model))
+    }
+}
+
+impl CompletionModel for BoxCompletionModel {
+    type Response = BoxRawResponse;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        self.0.completion(request).await
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.0.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{completion::AssistantContent, OneOrMany};
+
+    #[derive(Clone)]
+    struct EchoModel;
+
+    impl CompletionModel for EchoModel {
+        type Response = String;
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<String>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text("echo")),
+                raw_response: format!("{} messages", request.chat_history.len()),
+                logprobs: None,
+            })
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::all()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_box_completion_model() {
+        let models = [BoxCompletionModel::new(EchoModel)];
+
+        let response = models[0].completion_request("Hello").send().await.unwrap();
+
+        assert_eq!(response.choice.first(), AssistantContent::text("echo"));
+        assert_eq!(
+            response.raw_response.downcast_ref::<String>().unwrap(),
+            "1 messages"
+        );
+        assert_eq!(
+            CompletionModel::capabilities(&models[0]),
+            Capabilities::all()
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Capabilities of the completion models, so that generic code can adapt to the model it is
+//! given (e.g.: emulate tool calls with a prompt, or skip the images of a request) instead of
+//! failing at runtime.
+//!
+//! # Example
+//! ```rust
+//! use rig::completion::CompletionModel;
+//!
+//! fn describe<M: CompletionModel>(model: &M) {
+//!     let capabilities = model.capabilities();
+//!     if !capabilities.tools {
+//!         // Describe the tools in the preamble and parse the calls from the response instead
+//!     }
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::catalog::BUNDLED_CATALOG;
+
+/// Features supported by a completion model (and by its integration in Rig).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// The model implements [StreamingCompletionModel](crate::streaming::StreamingCompletionModel)
+    pub streaming: bool,
+    /// The tools of the requests are sent to the model, which can call them
+    pub tools: bool,
+    /// The model can call multiple tools in a single response, and the provider supports
+    /// [CompletionRequest::parallel_tool_calls](super::CompletionRequest::parallel_tool_calls)
+    pub parallel_tool_calls: bool,
+    /// The model accepts images as input
+    pub vision: bool,
+    /// The model can be constrained to respond with JSON (e.g.: with the `response_format` of
+    /// the additional parameters of the requests)
+    pub json_mode: bool,
+    /// The model can return the log probabilities of the tokens of its responses
+    pub logprobs: bool,
+    /// The backend enforces the [OutputConstraint](super::OutputConstraint) of the requests
+    /// while decoding (e.g.: the local backends)
+    pub constrained_decoding: bool,
+    /// Maximum number of tokens of the input and output of a request, if known (e.g.: from the
+    /// [catalog](crate::catalog))
+    pub context_window: Option<u64>,
+    /// Maximum number of output tokens of a request, if known
+    pub max_output_tokens: Option<u64>,
+}
+
+impl Capabilities {
+    /// Capabilities of a model supporting all the features.
+    pub fn all() -> Self {
+        Self {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            vision: true,
+            json_mode: true,
+            logprobs: true,
+            constrained_decoding: true,
+            context_window: None,
+            max_output_tokens: None,
+        }
+    }
+
+    /// Override the tools and vision support, and the token limits, with the ones of the model
+    /// in the bundled [catalog](crate::catalog), if any.
+    pub(crate) fn with_catalog(mut self, provider: &str, model: &str) -> Self {
+        if let Some(info) = BUNDLED_CATALOG.get(provider, model) {
+            self.tools = info.supports_tools;
+            self.parallel_tool_calls &= info.supports_tools;
+            self.vision = info.supports_vision;
+            self.context_window = Some(info.context_window);
+            self.max_output_tokens = Some(info.max_output);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_catalog() {
+        let base = Capabilities {
+            tools: true,
+            parallel_tool_calls: true,
+            ..Default::default()
+        };
+
+        let o1_mini = base.with_catalog("openai", "o1-mini-2024-09-12");
+        assert!(!o1_mini.tools && !o1_mini.parallel_tool_calls && !o1_mini.vision);
+
+        let gpt4o = base.with_catalog("openai", "gpt-4o");
+        assert!(gpt4o.tools && gpt4o.vision);
+        assert!(gpt4o.context_window.is_some() && gpt4o.max_output_tokens.is_some());
+
+        // Unknown models keep the capabilities of the provider
+        assert_eq!(base.with_catalog("openai", "my-fine-tune"), base);
+    }
+}
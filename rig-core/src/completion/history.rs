@@ -0,0 +1,209 @@
+//! Versioned serialization of chat histories, for the conversations persisted across the
+//! upgrades of rig (e.g.: sessions or checkpoints).
+//!
+//! The histories are serialized with the version of their format:
+//! ```json
+//! { "version": 1, "messages": [{ "role": "user", "content": [{ "type": "text", "text": "Hi" }] }] }
+//! ```
+//! When the serialization of the [Message]s changes, the version is incremented and the histories
+//! of the previous versions are migrated when deserialized, so they keep loading. The histories
+//! of newer versions fail with [HistoryError::UnsupportedVersion] instead of being misread.
+//! The unversioned histories (the plain lists of messages serialized so far) are the version 0.
+//!
+//! # Example
+//! ```rust
+//! use rig::completion::{history, Message};
+//!
+//! let json = history::to_string(&[Message::user("Hello!")])?;
+//! let messages = history::from_str(&json)?;
+//! ```
+//!
+//! The fields of persisted structs can also be versioned with `#[serde(with)]`:
+//! ```rust
+//! #[derive(Serialize, Deserialize)]
+//! struct Session {
+//!     id: String,
+//!     #[serde(with = "rig::completion::history")]
+//!     messages: Vec<Message>,
+//! }
+//! ```
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+
+use super::Message;
+
+/// Version of the serialization of the histories.
+pub const HISTORY_VERSION: u64 = 1;
+
+/// Migration of the (JSON) messages of a version of the histories to the next version.
+type Migration = fn(Vec<Value>) -> Result<Vec<Value>, HistoryError>;
+
+/// Migrations of the histories, by version: the migration `n` converts the messages of the
+/// version `n` to the version `n + 1`.
+const MIGRATIONS: [Migration; HISTORY_VERSION as usize] = [
+    // The version 1 is the serialization of the unversioned histories
+    Ok,
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// The history was serialized by a newer version of rig
+    #[error("Unsupported history version {0} (latest supported version: {HISTORY_VERSION})")]
+    UnsupportedVersion(u64),
+
+    /// The history is neither a versioned history nor a list of messages
+    #[error("Invalid history: {0}")]
+    InvalidHistory(String),
+}
+
+/// The versioned JSON of `messages`.
+pub fn to_value(messages: &[Message]) -> Result<Value, HistoryError> {
+    Ok(json!({
+        "version": HISTORY_VERSION,
+        "messages": serde_json::to_value(messages)?,
+    }))
+}
+
+/// The messages of the versioned (or unversioned) history `value`, migrated from its version.
+pub fn from_value(value: Value) -> Result<Vec<Message>, HistoryError> {
+    let (version, messages) = match value {
+        Value::Array(messages) => (0, messages),
+        Value::Object(mut history) => {
+            let version = history
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| HistoryError::InvalidHistory("Missing version".to_string()))?;
+            match history.remove("messages") {
+                Some(Value::Array(messages)) => (version, messages),
+                _ => {
+                    return Err(HistoryError::InvalidHistory(
+                        "Missing list of messages".to_string(),
+                    ))
+                }
+            }
+        }
+        _ => {
+            return Err(HistoryError::InvalidHistory(
+                "Expected an object or a list".to_string(),
+            ))
+        }
+    };
+    if version > HISTORY_VERSION {
+        return Err(HistoryError::UnsupportedVersion(version));
+    }
+
+    let messages = MIGRATIONS[version as usize..]
+        .iter()
+        .try_fold(messages, |messages, migration| migration(messages))?;
+    Ok(serde_json::from_value(Value::Array(messages))?)
+}
+
+/// The versioned JSON string of `messages`.
+pub fn to_string(messages: &[Message]) -> Result<String, HistoryError> {
+    Ok(serde_json::to_string(&to_value(messages)?)?)
+}
+
+/// The messages of the versioned (or unversioned) history `json`, migrated from its version.
+pub fn from_str(json: &str) -> Result<Vec<Message>, HistoryError> {
+    from_value(serde_json::from_str(json)?)
+}
+
+/// Serialize `messages` as a versioned history (with
+/// `#[serde(with = "rig::completion::history")]`).
+pub fn serialize<S: Serializer>(messages: &[Message], serializer: S) -> Result<S::Ok, S::Error> {
+    to_value(messages)
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+/// Deserialize a versioned (or unversioned) history (with
+/// `#[serde(with = "rig::completion::history")]`).
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Message>, D::Error> {
+    from_value(Value::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::message::{
+            AssistantContent, ContentFormat, ImageMediaType, ToolResultContent, UserContent,
+        },
+        OneOrMany,
+    };
+
+    /// A history with all the kinds of contents
+    fn messages() -> Vec<Message> {
+        vec![
+            Message::User {
+                content: OneOrMany::many(vec![
+                    UserContent::text("What is in this image?"),
+                    UserContent::image(
+                        "aGVsbG8=",
+                        Some(ContentFormat::Base64),
+                        Some(ImageMediaType::PNG),
+                        None,
+                    ),
+                ])
+                .unwrap(),
+            },
+            Message::Assistant {
+                content: OneOrMany::many(vec![
+                    AssistantContent::text("Let me look."),
+                    AssistantContent::tool_call("call_1", "describe", json!({ "id": 1 })),
+                ])
+                .unwrap(),
+            },
+            Message::User {
+                content: OneOrMany::one(UserContent::tool_result(
+                    "call_1",
+                    OneOrMany::one(ToolResultContent::text("A cat")),
+                )),
+            },
+            Message::assistant("A cat."),
+        ]
+    }
+
+    /// The serialization of [messages] in the version 1. A change of the serialization of the
+    /// messages must increment [HISTORY_VERSION] with a migration.
+    const HISTORY_V1: &str = r#"{"messages":[{"content":[{"text":"What is in this image?","type":"text"},{"data":"aGVsbG8=","format":"base64","media_type":"png","type":"image"}],"role":"user"},{"content":[{"text":"Let me look."},{"function":{"arguments":{"id":1},"name":"describe"},"id":"call_1"}],"role":"assistant"},{"content":[{"content":[{"Text":{"text":"A cat"}}],"id":"call_1","type":"toolresult"}],"role":"user"},{"content":[{"text":"A cat."}],"role":"assistant"}],"version":1}"#;
+
+    #[test]
+    fn test_history_versions() {
+        assert_eq!(
+            to_value(&messages()).unwrap(),
+            serde_json::from_str::<Value>(HISTORY_V1).unwrap()
+        );
+        assert_eq!(from_str(HISTORY_V1).unwrap(), messages());
+
+        // Unversioned histories
+        let unversioned = serde_json::to_string(&messages()).unwrap();
+        assert_eq!(from_str(&unversioned).unwrap(), messages());
+
+        assert!(matches!(
+            from_str(r#"{"version":99,"messages":[]}"#),
+            Err(HistoryError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            from_str(r#"{"messages":[]}"#),
+            Err(HistoryError::InvalidHistory(_))
+        ));
+
+        #[derive(Serialize, Deserialize)]
+        struct Session {
+            #[serde(with = "super")]
+            messages: Vec<Message>,
+        }
+        let session = serde_json::to_value(Session {
+            messages: messages(),
+        })
+        .unwrap();
+        assert_eq!(session["messages"]["version"], HISTORY_VERSION);
+        let session: Session = serde_json::from_value(session).unwrap();
+        assert_eq!(session.messages, messages());
+    }
+}
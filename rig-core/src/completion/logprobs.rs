@@ -0,0 +1,128 @@
+//! Log probabilities of the tokens of completions, for the providers returning them (see
+//! [Capabilities::logprobs](super::Capabilities::logprobs)).
+//!
+//! The logprobs are requested with the [logprobs](super::CompletionRequestBuilder::logprobs) and
+//! [top_logprobs](super::CompletionRequestBuilder::top_logprobs) methods of the request builder,
+//! and returned in [CompletionResponse::logprobs](super::CompletionResponse::logprobs).
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::CompletionModel, providers::openai};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let model = openai::Client::from_env().completion_model(openai::GPT_4O);
+//!
+//! let response = model
+//!     .completion_request("Is the sky blue? Answer with yes or no.")
+//!     .top_logprobs(2)
+//!     .send()
+//!     .await?;
+//!
+//! if let Some(logprobs) = response.logprobs {
+//!     println!("Confidence: {:.2}", logprobs[0].probability());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// Log probability of a token of a completion.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    /// The token
+    pub token: String,
+    /// The log probability of the token
+    pub logprob: f64,
+    /// The most likely tokens at this position (and their log probabilities), if requested
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// Log probability of one of the most likely tokens at a position of a completion.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopLogprob {
+    /// The token
+    pub token: String,
+    /// The log probability of the token
+    pub logprob: f64,
+}
+
+impl TokenLogprob {
+    /// The probability of the token, between 0 and 1.
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+impl TopLogprob {
+    /// The probability of the token, between 0 and 1.
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+/// Log probability of a whole completion (i.e.: the sum of the log probabilities of its tokens).
+pub fn sequence_logprob(logprobs: &[TokenLogprob]) -> f64 {
+    logprobs.iter().map(|token| token.logprob).sum()
+}
+
+fn null_as_empty<'de, D>(deserializer: D) -> Result<Vec<TopLogprob>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<Vec<TopLogprob>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Parses the `logprobs` of a choice of the OpenAI chat completions API (also returned by many
+/// OpenAI compatible providers), that is `{"content": [{"token", "logprob", "top_logprobs"}]}`.
+pub(crate) fn from_openai_choice(
+    logprobs: Option<&serde_json::Value>,
+) -> Option<Vec<TokenLogprob>> {
+    let content = logprobs?.get("content")?;
+    match serde_json::from_value(content.clone()) {
+        Ok(logprobs) => logprobs,
+        Err(err) => {
+            tracing::warn!(target: "rig", "Invalid logprobs in completion response: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_openai_choice() {
+        let logprobs = serde_json::json!({
+            "content": [
+                {
+                    "token": "Yes",
+                    "logprob": -0.01,
+                    "bytes": [89, 101, 115],
+                    "top_logprobs": [
+                        {"token": "Yes", "logprob": -0.01, "bytes": [89, 101, 115]},
+                        {"token": "No", "logprob": -4.6, "bytes": [78, 111]}
+                    ]
+                },
+                {"token": ".", "logprob": -0.2, "bytes": [46], "top_logprobs": null}
+            ],
+            "refusal": null
+        });
+
+        let logprobs = from_openai_choice(Some(&logprobs)).unwrap();
+        assert_eq!(logprobs.len(), 2);
+        assert_eq!(logprobs[0].token, "Yes");
+        assert_eq!(logprobs[0].top_logprobs[1].token, "No");
+        assert!(logprobs[1].top_logprobs.is_empty());
+        assert!((logprobs[0].probability() - 0.99).abs() < 0.001);
+        assert!((sequence_logprob(&logprobs) + 0.21).abs() < 1e-9);
+
+        assert_eq!(from_openai_choice(None), None);
+        assert_eq!(
+            from_openai_choice(Some(&serde_json::json!({"content": null}))),
+            None
+        );
+    }
+}
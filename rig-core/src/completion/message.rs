@@ -123,13 +123,16 @@ pub struct Document {
     pub media_type: Option<DocumentMediaType>,
 }
 
-/// Describes the format of the content, which can be base64 or string.
+/// Describes the format of the content, which can be base64, string or the handle of a blob.
 #[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ContentFormat {
     #[default]
     Base64,
     String,
+    /// Handle of the content in a [BlobStore](crate::blob::BlobStore), resolved before the
+    /// requests are sent to the provider (see [BlobModel](crate::blob::BlobModel))
+    Blob,
 }
 
 /// Helper enum that tracks the media type of the content.
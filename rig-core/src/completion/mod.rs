@@ -1,5 +1,15 @@
+pub mod batch;
+pub mod boxed;
+pub mod capabilities;
+pub mod history;
+pub mod logprobs;
 pub mod message;
+pub mod postprocess;
 pub mod request;
 
+pub use boxed::{BoxCompletionModel, BoxRawResponse};
+pub use capabilities::Capabilities;
+pub use logprobs::{TokenLogprob, TopLogprob};
 pub use message::{AssistantContent, Message, MessageError};
+pub use postprocess::PostProcessor;
 pub use request::*;
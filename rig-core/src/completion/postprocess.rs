@@ -0,0 +1,282 @@
+//! Post-processing of the text of completion responses.
+//!
+//! The providers and models are not consistent in the formatting of their answers: some leave the
+//! stop sequences at the end of the text, wrap a JSON answer in markdown code fences or add
+//! whitespace around it. The [PostProcessor]s of a request (see
+//! [CompletionRequestBuilder::post_processor](super::CompletionRequestBuilder::post_processor)
+//! and [AgentBuilder::post_processor](crate::agent::AgentBuilder::post_processor)) are applied in
+//! order to the text of the responses, whatever their provider.
+//!
+//! The following post-processors are provided:
+//! - [StripStopSequences]: truncates the text at its first stop sequence (always applied first
+//!   with the [stop sequences](super::SamplingParams::stop) of the request).
+//! - [StripFences]: removes the markdown code fences wrapping the whole text.
+//! - [Trim]: trims the whitespace around the text.
+//! - [ExtractCodeBlock]: keeps the content of the first fenced code block of the text (of a
+//!   language).
+//!
+//! Closures taking and returning the text are post-processors too.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::postprocess::{ExtractCodeBlock, Trim}, providers::openai};
+//!
+//! let model = openai::Client::from_env().completion_model(openai::GPT_4O);
+//!
+//! let response = model
+//!     .completion_request("Write a Rust function adding two numbers")
+//!     .post_processor(ExtractCodeBlock::new().language("rust"))
+//!     .post_processor(Trim)
+//!     .send()
+//!     .await?;
+//! ```
+//!
+//! Note: the post-processors are not applied to the streamed responses.
+
+use crate::OneOrMany;
+
+use super::AssistantContent;
+
+/// Transformation of the text of completion responses.
+pub trait PostProcessor: Send + Sync {
+    fn process(&self, text: &str) -> String;
+}
+
+impl<F> PostProcessor for F
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    fn process(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+/// Truncates the text at the first occurrence of one of its stop sequences, for the providers
+/// which include the stop sequence in the text or ignore it.
+#[derive(Clone, Debug, Default)]
+pub struct StripStopSequences {
+    stop: Vec<String>,
+}
+
+impl StripStopSequences {
+    pub fn new(stop: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            stop: stop
+                .into_iter()
+                .map(Into::into)
+                .filter(|stop: &String| !stop.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl PostProcessor for StripStopSequences {
+    fn process(&self, text: &str) -> String {
+        let end = self
+            .stop
+            .iter()
+            .filter_map(|stop| text.find(stop.as_str()))
+            .min()
+            .unwrap_or(text.len());
+        text[..end].to_string()
+    }
+}
+
+/// Removes the markdown code fences (e.g.: ```` ```json ````) wrapping the whole text. The texts
+/// which are not wrapped in fences are kept as is.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StripFences;
+
+impl PostProcessor for StripFences {
+    fn process(&self, text: &str) -> String {
+        let trimmed = text.trim();
+        match code_blocks(trimmed).first() {
+            Some(block) if block.start == 0 && block.end == trimmed.len() => {
+                block.content.to_string()
+            }
+            _ => text.to_string(),
+        }
+    }
+}
+
+/// Trims the whitespace around the text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Trim;
+
+impl PostProcessor for Trim {
+    fn process(&self, text: &str) -> String {
+        text.trim().to_string()
+    }
+}
+
+/// Keeps the content of the first fenced code block of the text, or of the first block of the
+/// language if it is set. The texts without (matching) code blocks are kept as is.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractCodeBlock {
+    language: Option<String>,
+}
+
+impl ExtractCodeBlock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only extract the code blocks of `language` (e.g.: `rust`, case insensitive).
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+}
+
+impl PostProcessor for ExtractCodeBlock {
+    fn process(&self, text: &str) -> String {
+        code_blocks(text)
+            .into_iter()
+            .find(|block| match &self.language {
+                Some(language) => block.language.eq_ignore_ascii_case(language),
+                None => true,
+            })
+            .map_or_else(|| text.to_string(), |block| block.content.to_string())
+    }
+}
+
+/// Fenced code block of a markdown text.
+struct CodeBlock<'a> {
+    /// Byte offset of the opening fence
+    start: usize,
+    /// Byte offset of the end of the closing fence
+    end: usize,
+    language: &'a str,
+    content: &'a str,
+}
+
+/// The fenced code blocks of `text`, in order. The block of an unclosed fence ends with the text.
+fn code_blocks(text: &str) -> Vec<CodeBlock<'_>> {
+    let mut blocks = vec![];
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("```").map(|start| offset + start) {
+        let fence_len = text[start..].len() - text[start..].trim_start_matches('`').len();
+        let fence = &text[start..start + fence_len];
+        let info_end = text[start..]
+            .find('\n')
+            .map_or(text.len(), |newline| start + newline);
+        let language = text[start + fence_len..info_end].trim();
+        let content_start = (info_end + 1).min(text.len());
+
+        let (content_end, end) = match text[content_start..].find(fence) {
+            Some(close) => (content_start + close, content_start + close + fence_len),
+            None => (text.len(), text.len()),
+        };
+        blocks.push(CodeBlock {
+            start,
+            end,
+            language,
+            content: text[content_start..content_end].trim_end_matches(['\n', '\r']),
+        });
+        offset = end;
+    }
+    blocks
+}
+
+/// Apply `post_processors` in order to the texts of `choice`.
+pub(crate) fn apply(
+    post_processors: &[&dyn PostProcessor],
+    choice: &mut OneOrMany<AssistantContent>,
+) {
+    if post_processors.is_empty() {
+        return;
+    }
+    for content in choice.iter_mut() {
+        if let AssistantContent::Text(text) = content {
+            text.text = post_processors
+                .iter()
+                .fold(std::mem::take(&mut text.text), |text, post_processor| {
+                    post_processor.process(&text)
+                });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{
+        CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
+    };
+
+    /// Answers a fenced JSON object, followed by a stop sequence
+    #[derive(Clone)]
+    struct FencedModel;
+
+    impl CompletionModel for FencedModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(
+                    " ```json\n{\"a\": 1}\n```\nEND and more",
+                )),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_post_processors() {
+        let response = FencedModel
+            .completion_request("Hello")
+            .stop(["END"])
+            .post_processor(StripFences)
+            .send()
+            .await
+            .unwrap();
+        assert!(matches!(
+            response.choice.first(),
+            AssistantContent::Text(text) if text.text == "{\"a\": 1}"
+        ));
+    }
+
+    #[test]
+    fn test_post_processors() {
+        let stop = StripStopSequences::new(["</answer>", "\nUser:", ""]);
+        assert_eq!(stop.process("42</answer>\nUser: ok"), "42");
+        assert_eq!(stop.process("42\nUser: </answer>"), "42");
+        assert_eq!(stop.process("42"), "42");
+
+        assert_eq!(
+            StripFences.process("\n```json\n{\"a\": 1}\n```\n"),
+            "{\"a\": 1}"
+        );
+        assert_eq!(StripFences.process("````\na\n```\nb\n````"), "a\n```\nb");
+        let text = "Here it is:\n```json\n{}\n```";
+        assert_eq!(StripFences.process(text), text);
+
+        assert_eq!(Trim.process("  a b \n"), "a b");
+
+        let text = "Python:\n```python\nprint(1)\n```\nRust:\n```Rust\nfn main() {}\n```\n";
+        assert_eq!(ExtractCodeBlock::new().process(text), "print(1)");
+        assert_eq!(
+            ExtractCodeBlock::new().language("rust").process(text),
+            "fn main() {}"
+        );
+        assert_eq!(ExtractCodeBlock::new().language("go").process(text), text);
+        assert_eq!(ExtractCodeBlock::new().process("```\nunclosed"), "unclosed");
+
+        let upper = |text: &str| text.to_uppercase();
+        let mut choice = OneOrMany::many(vec![
+            AssistantContent::text("```\nok\n```"),
+            AssistantContent::tool_call("call_1", "tool", serde_json::json!({})),
+        ])
+        .unwrap();
+        apply(&[&StripFences, &upper], &mut choice);
+        assert!(matches!(choice.first(), AssistantContent::Text(text) if text.text == "OK"));
+        assert!(matches!(
+            choice.iter().nth(1),
+            Some(AssistantContent::ToolCall(_))
+        ));
+    }
+}
@@ -62,12 +62,15 @@
 //!
 //! For more information on how to use the completion functionality, refer to the documentation of
 //! the individual traits, structs, and enums defined in this module.
-use std::collections::HashMap;
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::cancellation::{with_cancellation, CancellationToken};
 use crate::streaming::{StreamingCompletionModel, StreamingCompletionResponse};
+use crate::tokens::{HeuristicTokenizer, Tokenizer};
+use crate::wasm_compat::WasmCompatSend;
 use crate::OneOrMany;
 use crate::{
     json_utils,
@@ -75,7 +78,11 @@ use crate::{
     tool::ToolSetError,
 };
 
-use super::message::{AssistantContent, ContentFormat, DocumentMediaType};
+use super::{
+    message::{AssistantContent, ContentFormat, DocumentMediaType},
+    postprocess::{self, PostProcessor, StripStopSequences},
+    Capabilities, TokenLogprob,
+};
 
 // Errors
 #[derive(Debug, Error)]
@@ -99,6 +106,48 @@ pub enum CompletionError {
     /// Error returned by the completion model provider
     #[error("ProviderError: {0}")]
     ProviderError(String),
+
+    /// The completion (or the agent loop) did not finish before its timeout
+    #[error("Timeout: no response after {0:?}")]
+    Timeout(Duration),
+
+    /// The completion was cancelled with its [CancellationToken]
+    #[error("Cancelled")]
+    Cancelled,
+
+    /// A tool of the agent has no implementation in its toolset (with strict tools, see
+    /// [AgentBuilder::strict_tools](crate::agent::AgentBuilder::strict_tools))
+    #[error("ToolNotFound: {0}")]
+    ToolNotFound(String),
+}
+
+/// Messages of the errors of the providers rejecting the requests exceeding the context window
+/// of the model (lowercase).
+const CONTEXT_LENGTH_ERRORS: &[&str] = &[
+    "context_length_exceeded",
+    "maximum context length",
+    "context window",
+    "prompt is too long",
+    "input is too long",
+    "too many tokens",
+    "exceeds the maximum number of tokens",
+    "reduce the length of the messages",
+];
+
+impl CompletionError {
+    /// Whether the provider rejected the request because it exceeds the context window of the
+    /// model.
+    pub fn is_context_length_exceeded(&self) -> bool {
+        match self {
+            CompletionError::ProviderError(message) => {
+                let message = message.to_lowercase();
+                CONTEXT_LENGTH_ERRORS
+                    .iter()
+                    .any(|pattern| message.contains(pattern))
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -115,9 +164,30 @@ pub enum PromptError {
         chat_history: Vec<Message>,
         prompt: Message,
     },
+
+    #[error("GuardrailError: {0}")]
+    GuardrailError(#[from] crate::guardrails::GuardrailError),
+
+    /// The prompt was cancelled with its [CancellationToken]. The chat history contains the
+    /// messages exchanged up to the cancellation, ending with the unanswered prompt (or tool
+    /// results).
+    #[error("Cancelled")]
+    Cancelled { chat_history: Vec<Message> },
+
+    /// The [Experiment](crate::agent::Experiment) has no version receiving traffic
+    #[error("ExperimentError: {0}")]
+    ExperimentError(String),
+
+    /// All the members of the [Ensemble](crate::agent::Ensemble) failed
+    #[error("EnsembleError: {0}")]
+    EnsembleError(String),
+
+    /// The [CheckpointStore](crate::agent::CheckpointStore) of the agent failed
+    #[error("CheckpointError: {0}")]
+    CheckpointError(#[from] crate::agent::CheckpointError),
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Document {
     pub id: String,
     pub text: String,
@@ -170,7 +240,7 @@ pub trait Prompt: Send + Sync {
     fn prompt(
         &self,
         prompt: impl Into<Message> + Send,
-    ) -> impl std::future::IntoFuture<Output = Result<String, PromptError>, IntoFuture: Send>;
+    ) -> impl std::future::IntoFuture<Output = Result<String, PromptError>, IntoFuture: WasmCompatSend>;
 }
 
 /// Trait defining a high-level LLM chat interface (i.e.: prompt and chat history in, response out).
@@ -187,7 +257,7 @@ pub trait Chat: Send + Sync {
         &self,
         prompt: impl Into<Message> + Send,
         chat_history: Vec<Message>,
-    ) -> impl std::future::IntoFuture<Output = Result<String, PromptError>, IntoFuture: Send>;
+    ) -> impl std::future::IntoFuture<Output = Result<String, PromptError>, IntoFuture: WasmCompatSend>;
 }
 
 /// Trait defining a low-level LLM completion interface
@@ -207,7 +277,8 @@ pub trait Completion<M: CompletionModel> {
         &self,
         prompt: impl Into<Message> + Send,
         chat_history: Vec<Message>,
-    ) -> impl std::future::Future<Output = Result<CompletionRequestBuilder<M>, CompletionError>> + Send;
+    ) -> impl std::future::Future<Output = Result<CompletionRequestBuilder<M>, CompletionError>>
+           + WasmCompatSend;
 }
 
 /// General completion response struct that contains the high-level completion choice
@@ -219,6 +290,9 @@ pub struct CompletionResponse<T> {
     pub choice: OneOrMany<AssistantContent>,
     /// The raw response returned by the completion model provider
     pub raw_response: T,
+    /// The log probabilities of the tokens of the completion, if requested (see
+    /// [CompletionRequestBuilder::logprobs]) and returned by the provider
+    pub logprobs: Option<Vec<TokenLogprob>>,
 }
 
 /// Trait defining a completion model that can be used to generate completion responses.
@@ -233,12 +307,18 @@ pub trait CompletionModel: Clone + Send + Sync {
         &self,
         request: CompletionRequest,
     ) -> impl std::future::Future<Output = Result<CompletionResponse<Self::Response>, CompletionError>>
-           + Send;
+           + WasmCompatSend;
 
     /// Generates a completion request builder for the given `prompt`.
     fn completion_request(&self, prompt: impl Into<Message>) -> CompletionRequestBuilder<Self> {
         CompletionRequestBuilder::new(self.clone(), prompt)
     }
+
+    /// Get the features supported by the model. By default, no feature is advertised: models
+    /// should override this method so that generic code can take advantage of their features.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
 }
 
 /// Struct representing a general completion request that can be sent to a completion model provider.
@@ -253,14 +333,336 @@ pub struct CompletionRequest {
     pub documents: Vec<Document>,
     /// The tools to be sent to the completion model provider
     pub tools: Vec<ToolDefinition>,
+    /// How the model should use the tools (ignored by the providers not supporting it)
+    pub tool_choice: ToolChoice,
+    /// Whether the model can call several tools at once, `None` for the default of the
+    /// provider (ignored by the providers not supporting it)
+    pub parallel_tool_calls: Option<bool>,
     /// The temperature to be sent to the completion model provider
     pub temperature: Option<f64>,
     /// The max tokens to be sent to the completion model provider
     pub max_tokens: Option<u64>,
+    /// Maximum duration of the completion, after which it is aborted with a
+    /// [CompletionError::Timeout] (enforced by [CompletionRequestBuilder::send] and the agents,
+    /// whatever the timeout of the HTTP client of the provider)
+    pub timeout: Option<Duration>,
+    /// Token cancelling the completion, after which it is aborted with a
+    /// [CompletionError::Cancelled] (enforced by [CompletionRequestBuilder::send] and the agents)
+    pub cancellation: Option<CancellationToken>,
+    /// Additional sampling (and logprobs) parameters (ignored with a warning by the providers not
+    /// supporting them)
+    pub sampling: SamplingParams,
+    /// Extra HTTP headers and query parameters of the request, added to those of the client
+    pub http_extras: HttpExtras,
+    /// Constraint of the output of the model, enforced while decoding (only supported by the
+    /// local backends, see [Capabilities::constrained_decoding])
+    pub output_constraint: Option<OutputConstraint>,
     /// Additional provider-specific parameters to be sent to the completion model provider
     pub additional_params: Option<serde_json::Value>,
 }
 
+/// How the model should use the tools of a completion request.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call tools
+    #[default]
+    Auto,
+    /// The model does not call tools
+    None,
+    /// The model calls at least one tool
+    Required,
+    /// The model calls the given tool (e.g.: to route a request with a router agent)
+    Tool(String),
+}
+
+impl ToolChoice {
+    /// The `tool_choice` of the OpenAI chat completions API (and of the compatible APIs).
+    pub fn to_openai_json(&self) -> serde_json::Value {
+        match self {
+            ToolChoice::Auto => "auto".into(),
+            ToolChoice::None => "none".into(),
+            ToolChoice::Required => "required".into(),
+            ToolChoice::Tool(name) => serde_json::json!({
+                "type": "function",
+                "function": { "name": name },
+            }),
+        }
+    }
+
+    /// The `tool_choice` and `parallel_tool_calls` (if set) fields of the OpenAI chat
+    /// completions API (and of the compatible APIs), for the requests with tools.
+    pub fn to_openai_params(&self, parallel_tool_calls: Option<bool>) -> serde_json::Value {
+        let mut params = serde_json::json!({ "tool_choice": self.to_openai_json() });
+        if let Some(parallel_tool_calls) = parallel_tool_calls {
+            params["parallel_tool_calls"] = parallel_tool_calls.into();
+        }
+        params
+    }
+
+    /// Warn that the tool choice (unless [ToolChoice::Auto]) is not supported by `provider`,
+    /// which ignores it.
+    pub(crate) fn warn_unsupported(&self, provider: &str) {
+        if *self != ToolChoice::Auto {
+            tracing::warn!(
+                target: "rig",
+                "The `tool_choice` parameter is not supported by {}, ignoring it",
+                provider
+            );
+        }
+    }
+}
+
+/// Warn that the `parallel_tool_calls` parameter (if set) is not supported by `provider`, which
+/// ignores it.
+pub(crate) fn warn_unsupported_parallel_tool_calls(
+    provider: &str,
+    parallel_tool_calls: Option<bool>,
+) {
+    if parallel_tool_calls.is_some() {
+        tracing::warn!(
+            target: "rig",
+            "The `parallel_tool_calls` parameter is not supported by {}, ignoring it",
+            provider
+        );
+    }
+}
+
+/// Constraint of the output of a model, enforced by the backend while decoding (e.g.: by
+/// llama.cpp), so that the output is guaranteed to be valid without retries.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum OutputConstraint {
+    /// JSON value matching the JSON schema
+    JsonSchema(serde_json::Value),
+    /// Text matching the [GBNF](https://github.com/ggml-org/llama.cpp/blob/master/grammars/README.md)
+    /// grammar (only supported by llama.cpp)
+    Grammar(String),
+}
+
+impl OutputConstraint {
+    /// JSON value deserializable as `T`.
+    pub fn json_schema_for<T: schemars::JsonSchema>() -> Self {
+        Self::JsonSchema(serde_json::json!(schemars::schema_for!(T)))
+    }
+
+    /// JSON call of one of `tools`, i.e.: an object with the `name` of the tool and its
+    /// `arguments`, deserializable as a [ToolFunction](crate::message::ToolFunction). Emulates
+    /// the tool calls with the models which do not support them.
+    pub fn tool_call(tools: &[ToolDefinition]) -> Self {
+        let calls = tools
+            .iter()
+            .map(|tool| {
+                serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "name": { "const": tool.name },
+                        "arguments": tool.parameters,
+                    },
+                    "required": ["name", "arguments"],
+                })
+            })
+            .collect::<Vec<_>>();
+        Self::JsonSchema(serde_json::json!({ "oneOf": calls }))
+    }
+}
+
+/// Extra HTTP headers and query parameters of a completion request (e.g.: the
+/// `OpenAI-Organization` header, routing hints of a gateway or tracing ids).
+///
+/// They are added to the HTTP request by the providers, after the headers of the client (e.g.:
+/// the authentication headers), except by the providers not sending their requests with
+/// [reqwest] (e.g.: AWS Bedrock).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HttpExtras {
+    pub headers: Vec<(String, String)>,
+    pub query: Vec<(String, String)>,
+}
+
+impl HttpExtras {
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.query.is_empty()
+    }
+
+    /// Add the headers and query parameters to `request`. Invalid headers make the request fail
+    /// with a builder error when it is sent.
+    pub fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut request = self.headers.iter().fold(request, |request, (name, value)| {
+            request.header(name.as_str(), value.as_str())
+        });
+        if !self.query.is_empty() {
+            request = request.query(&self.query);
+        }
+        request
+    }
+}
+
+/// Sampling parameters of a completion request, in addition to the temperature.
+///
+/// The parameters are mapped to the fields of each provider (e.g.: `stop` is sent as
+/// `stop_sequences` to Anthropic), and the parameters unsupported by a provider are ignored with
+/// a warning. The additional params of the requests take precedence over these parameters.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingParams {
+    /// Only sample from the `top_k` most likely tokens
+    pub top_k: Option<u64>,
+    /// Penalty of the tokens proportional to their frequency in the text so far
+    pub frequency_penalty: Option<f64>,
+    /// Penalty of the tokens already present in the text so far
+    pub presence_penalty: Option<f64>,
+    /// Sequences stopping the generation
+    pub stop: Option<Vec<String>>,
+    /// Seed of the sampling, for (best effort) reproducible completions
+    pub seed: Option<u64>,
+    /// Bias added to the logits of tokens, by token id
+    pub logit_bias: Option<HashMap<u32, f64>>,
+    /// Number of completions to generate
+    pub n: Option<u64>,
+    /// Return the log probabilities of the tokens of the completion
+    pub logprobs: Option<bool>,
+    /// Number of most likely tokens (and their log probabilities) to return at each position of
+    /// the completion, requires `logprobs`
+    pub top_logprobs: Option<u64>,
+}
+
+/// Names of the [SamplingParams] in the requests of a provider, `None` for the parameters not
+/// supported by the provider.
+#[derive(Clone, Copy, Debug)]
+pub struct SamplingParamNames {
+    pub top_k: Option<&'static str>,
+    pub frequency_penalty: Option<&'static str>,
+    pub presence_penalty: Option<&'static str>,
+    pub stop: Option<&'static str>,
+    pub seed: Option<&'static str>,
+    pub logit_bias: Option<&'static str>,
+    pub n: Option<&'static str>,
+    pub logprobs: Option<&'static str>,
+    pub top_logprobs: Option<&'static str>,
+}
+
+impl SamplingParamNames {
+    /// Names of the OpenAI chat completions API (which does not support `top_k`), without the
+    /// logprobs parameters which are not supported by most of the OpenAI compatible providers
+    /// (see [SamplingParamNames::with_logprobs]).
+    pub const OPENAI: Self = Self {
+        top_k: None,
+        frequency_penalty: Some("frequency_penalty"),
+        presence_penalty: Some("presence_penalty"),
+        stop: Some("stop"),
+        seed: Some("seed"),
+        logit_bias: Some("logit_bias"),
+        n: Some("n"),
+        logprobs: None,
+        top_logprobs: None,
+    };
+
+    /// No supported parameter
+    pub const NONE: Self = Self {
+        top_k: None,
+        frequency_penalty: None,
+        presence_penalty: None,
+        stop: None,
+        seed: None,
+        logit_bias: None,
+        n: None,
+        logprobs: None,
+        top_logprobs: None,
+    };
+
+    /// Add the `logprobs` and `top_logprobs` parameters of the OpenAI chat completions API.
+    pub const fn with_logprobs(self) -> Self {
+        Self {
+            logprobs: Some("logprobs"),
+            top_logprobs: Some("top_logprobs"),
+            ..self
+        }
+    }
+}
+
+impl SamplingParams {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Convert the parameters to the JSON object of the fields of a provider, named by `names`.
+    /// The parameters not supported by the provider are ignored with a warning.
+    pub fn to_json(&self, provider: &str, names: &SamplingParamNames) -> serde_json::Value {
+        let params = [
+            (
+                "top_k",
+                names.top_k,
+                self.top_k.map(serde_json::Value::from),
+            ),
+            (
+                "frequency_penalty",
+                names.frequency_penalty,
+                self.frequency_penalty.map(serde_json::Value::from),
+            ),
+            (
+                "presence_penalty",
+                names.presence_penalty,
+                self.presence_penalty.map(serde_json::Value::from),
+            ),
+            (
+                "stop",
+                names.stop,
+                self.stop.clone().map(serde_json::Value::from),
+            ),
+            ("seed", names.seed, self.seed.map(serde_json::Value::from)),
+            (
+                "logit_bias",
+                names.logit_bias,
+                self.logit_bias
+                    .as_ref()
+                    .map(|logit_bias| serde_json::json!(logit_bias)),
+            ),
+            ("n", names.n, self.n.map(serde_json::Value::from)),
+            (
+                "logprobs",
+                names.logprobs,
+                self.logprobs.map(serde_json::Value::from),
+            ),
+            (
+                "top_logprobs",
+                names.top_logprobs,
+                self.top_logprobs.map(serde_json::Value::from),
+            ),
+        ];
+
+        params
+            .into_iter()
+            .filter_map(|(param, name, value)| match (name, value) {
+                (Some(name), Some(value)) => Some((name.to_string(), value)),
+                (None, Some(_)) => {
+                    tracing::warn!(
+                        target: "rig",
+                        "The `{}` parameter is not supported by {}, ignoring it",
+                        param,
+                        provider
+                    );
+                    None
+                }
+                (_, None) => None,
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+}
+
+/// Abort `future` with a [CompletionError::Timeout] if it does not complete within `timeout`.
+pub(crate) async fn with_timeout<T, E: From<CompletionError>>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let Some(timeout) = timeout else {
+        return future.await;
+    };
+
+    crate::runtime::timeout(timeout, future)
+        .await
+        .unwrap_or_else(|_| Err(CompletionError::Timeout(timeout).into()))
+}
+
 impl CompletionRequest {
     /// Returns documents normalized into a message (if any).
     /// Most providers do not accept documents directly as input, so it needs to convert into a
@@ -290,6 +692,55 @@ impl CompletionRequest {
             content: OneOrMany::many(messages).expect("There will be atleast one document"),
         })
     }
+
+    /// Estimate the number of input tokens of the request (preamble, messages, documents and
+    /// tools) with `tokenizer`. The messages and tools are counted in JSON, which overestimates
+    /// them slightly.
+    pub fn estimated_input_tokens(&self, tokenizer: &dyn Tokenizer) -> usize {
+        fn count_json(tokenizer: &dyn Tokenizer, value: &impl Serialize) -> usize {
+            serde_json::to_string(value)
+                .map(|json| tokenizer.count_tokens(&json))
+                .unwrap_or_default()
+        }
+
+        tokenizer.count_tokens(self.preamble.as_deref().unwrap_or_default())
+            + self
+                .chat_history
+                .iter()
+                .map(|message| count_json(tokenizer, message))
+                .sum::<usize>()
+            + self
+                .documents
+                .iter()
+                .map(|document| tokenizer.count_tokens(&document.to_string()))
+                .sum::<usize>()
+            + self
+                .tools
+                .iter()
+                .map(|tool| count_json(tokenizer, tool))
+                .sum::<usize>()
+    }
+}
+
+/// Minimum number of tokens of the context window left unused by the
+/// [automatic max tokens](CompletionRequestBuilder::auto_max_tokens), since the input tokens are
+/// estimated.
+const MIN_SAFETY_MARGIN: u64 = 256;
+
+/// The max tokens of a request of `input_tokens` tokens to a model of `capabilities`: the
+/// context window minus the input tokens and a safety margin (10% of the input tokens, and at
+/// least [MIN_SAFETY_MARGIN]), capped to the max output tokens of the model. `None` if the
+/// context window of the model is unknown, or if the input does not leave room for the output.
+fn auto_max_tokens(capabilities: &Capabilities, input_tokens: usize) -> Option<u64> {
+    let context_window = capabilities.context_window?;
+    let input_tokens = input_tokens as u64;
+    let margin = (input_tokens / 10).max(MIN_SAFETY_MARGIN);
+    let available = context_window.checked_sub(input_tokens + margin)?;
+    let max_tokens = match capabilities.max_output_tokens {
+        Some(max_output_tokens) => available.min(max_output_tokens),
+        None => available,
+    };
+    (max_tokens > 0).then_some(max_tokens)
 }
 
 /// Builder struct for constructing a completion request.
@@ -343,8 +794,17 @@ pub struct CompletionRequestBuilder<M: CompletionModel> {
     chat_history: Vec<Message>,
     documents: Vec<Document>,
     tools: Vec<ToolDefinition>,
+    tool_choice: ToolChoice,
+    parallel_tool_calls: Option<bool>,
     temperature: Option<f64>,
     max_tokens: Option<u64>,
+    auto_max_tokens: bool,
+    timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+    sampling: SamplingParams,
+    http_extras: HttpExtras,
+    output_constraint: Option<OutputConstraint>,
+    post_processors: Vec<Arc<dyn PostProcessor>>,
     additional_params: Option<serde_json::Value>,
 }
 
@@ -357,8 +817,17 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
             chat_history: Vec::new(),
             documents: Vec::new(),
             tools: Vec::new(),
+            tool_choice: ToolChoice::default(),
+            parallel_tool_calls: None,
             temperature: None,
             max_tokens: None,
+            auto_max_tokens: true,
+            timeout: None,
+            cancellation: None,
+            sampling: SamplingParams::default(),
+            http_extras: HttpExtras::default(),
+            output_constraint: None,
+            post_processors: Vec::new(),
             additional_params: None,
         }
     }
@@ -447,6 +916,26 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
         self
     }
 
+    /// Sets how the model should use the tools of the completion request (e.g.: to force the
+    /// call of a specific tool).
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = tool_choice;
+        self
+    }
+
+    /// Sets whether the model can call several tools at once.
+    pub fn parallel_tool_calls(mut self, parallel_tool_calls: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel_tool_calls);
+        self
+    }
+
+    /// Sets whether the model can call several tools at once (`None` for the default of the
+    /// provider).
+    pub fn parallel_tool_calls_opt(mut self, parallel_tool_calls: Option<bool>) -> Self {
+        self.parallel_tool_calls = parallel_tool_calls;
+        self
+    }
+
     /// Sets the max tokens for the completion request.
     /// Note: This is required if using Anthropic
     pub fn max_tokens(mut self, max_tokens: u64) -> Self {
@@ -461,26 +950,205 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
         self
     }
 
+    /// Sets whether the max tokens of the request are computed from the context window of the
+    /// model when they are not set (default: `true`): the context window minus the estimated
+    /// input tokens and a safety margin, capped to the max output tokens of the model. The context
+    /// window and max output tokens are the ones of the [capabilities](CompletionModel::capabilities)
+    /// of the model (i.e.: of the [catalog](crate::catalog)), and the input tokens are estimated
+    /// with a [HeuristicTokenizer].
+    pub fn auto_max_tokens(mut self, auto_max_tokens: bool) -> Self {
+        self.auto_max_tokens = auto_max_tokens;
+        self
+    }
+
+    /// Sets the timeout of the completion request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout of the completion request.
+    pub fn timeout_opt(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the token cancelling the completion request (see [crate::cancellation]).
+    pub fn cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Sets the token cancelling the completion request (see [crate::cancellation]).
+    pub fn cancellation_opt(mut self, cancellation: Option<CancellationToken>) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Adds an HTTP header to the completion request (e.g.: `OpenAI-Project`), in addition to the
+    /// headers of the client.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.http_extras
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds a query parameter to the URL of the completion request.
+    pub fn query_param(mut self, name: &str, value: &str) -> Self {
+        self.http_extras
+            .query
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the extra HTTP headers and query parameters of the completion request.
+    pub fn http_extras(mut self, http_extras: HttpExtras) -> Self {
+        self.http_extras = http_extras;
+        self
+    }
+
+    /// Constrains the output of the model while decoding (see [OutputConstraint]).
+    pub fn output_constraint(mut self, output_constraint: OutputConstraint) -> Self {
+        self.output_constraint = Some(output_constraint);
+        self
+    }
+
+    /// Adds a post-processor of the text of the response (see [postprocess]). The
+    /// post-processors are applied in order, after the stop sequences of the request are
+    /// stripped.
+    pub fn post_processor(mut self, post_processor: impl PostProcessor + 'static) -> Self {
+        self.post_processors.push(Arc::new(post_processor));
+        self
+    }
+
+    /// Adds a list of post-processors of the text of the response.
+    pub fn post_processors(mut self, post_processors: Vec<Arc<dyn PostProcessor>>) -> Self {
+        self.post_processors.extend(post_processors);
+        self
+    }
+
+    /// Sets the top-k sampling of the completion request.
+    pub fn top_k(mut self, top_k: u64) -> Self {
+        self.sampling.top_k = Some(top_k);
+        self
+    }
+
+    /// Sets the frequency penalty of the completion request.
+    pub fn frequency_penalty(mut self, frequency_penalty: f64) -> Self {
+        self.sampling.frequency_penalty = Some(frequency_penalty);
+        self
+    }
+
+    /// Sets the presence penalty of the completion request.
+    pub fn presence_penalty(mut self, presence_penalty: f64) -> Self {
+        self.sampling.presence_penalty = Some(presence_penalty);
+        self
+    }
+
+    /// Sets the sequences stopping the generation of the completion.
+    pub fn stop(mut self, stop: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.sampling.stop = Some(stop.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets the seed of the sampling of the completion request.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.sampling.seed = Some(seed);
+        self
+    }
+
+    /// Sets the biases of the logits of tokens (by token id) of the completion request.
+    pub fn logit_bias(mut self, logit_bias: HashMap<u32, f64>) -> Self {
+        self.sampling.logit_bias = Some(logit_bias);
+        self
+    }
+
+    /// Sets the number of completions to generate.
+    pub fn n(mut self, n: u64) -> Self {
+        self.sampling.n = Some(n);
+        self
+    }
+
+    /// Sets whether to return the log probabilities of the tokens of the completion.
+    pub fn logprobs(mut self, logprobs: bool) -> Self {
+        self.sampling.logprobs = Some(logprobs);
+        self
+    }
+
+    /// Sets the number of most likely tokens (and their log probabilities) to return at each
+    /// position of the completion. Also enables the [logprobs](Self::logprobs).
+    pub fn top_logprobs(mut self, top_logprobs: u64) -> Self {
+        self.sampling.logprobs = Some(true);
+        self.sampling.top_logprobs = Some(top_logprobs);
+        self
+    }
+
+    /// Sets all the sampling parameters of the completion request.
+    pub fn sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// The documents of the request
+    pub fn attached_documents(&self) -> &[Document] {
+        &self.documents
+    }
+
     /// Builds the completion request.
     pub fn build(self) -> CompletionRequest {
+        let capabilities = self
+            .auto_max_tokens
+            .then(|| self.model.capabilities())
+            .filter(|capabilities| capabilities.context_window.is_some());
         let chat_history = OneOrMany::many([self.chat_history, vec![self.prompt]].concat())
             .expect("There will always be atleast the prompt");
 
-        CompletionRequest {
+        let mut request = CompletionRequest {
             preamble: self.preamble,
             chat_history,
             documents: self.documents,
             tools: self.tools,
+            tool_choice: self.tool_choice,
+            parallel_tool_calls: self.parallel_tool_calls,
             temperature: self.temperature,
             max_tokens: self.max_tokens,
+            timeout: self.timeout,
+            cancellation: self.cancellation,
+            http_extras: self.http_extras,
+            output_constraint: self.output_constraint,
+            sampling: self.sampling,
             additional_params: self.additional_params,
+        };
+        if let (None, Some(capabilities)) = (request.max_tokens, capabilities) {
+            let input_tokens = request.estimated_input_tokens(&HeuristicTokenizer::default());
+            request.max_tokens = auto_max_tokens(&capabilities, input_tokens);
         }
+        request
     }
 
     /// Sends the completion request to the completion model provider and returns the completion response.
     pub async fn send(self) -> Result<CompletionResponse<M::Response>, CompletionError> {
         let model = self.model.clone();
-        model.completion(self.build()).await
+        let stop = StripStopSequences::new(self.sampling.stop.clone().unwrap_or_default());
+        let post_processors = self.post_processors.clone();
+        let request = self.build();
+        let cancellation = request.cancellation.clone();
+        let mut response = with_cancellation(
+            cancellation.as_ref(),
+            with_timeout(request.timeout, model.completion(request)),
+        )
+        .await?;
+
+        let post_processors = std::iter::once(&stop as &dyn PostProcessor)
+            .chain(
+                post_processors
+                    .iter()
+                    .map(|post_processor| &**post_processor),
+            )
+            .collect::<Vec<_>>();
+        postprocess::apply(&post_processors, &mut response.choice);
+        Ok(response)
     }
 }
 
@@ -490,7 +1158,18 @@ impl<M: StreamingCompletionModel> CompletionRequestBuilder<M> {
         self,
     ) -> Result<StreamingCompletionResponse<M::StreamingResponse>, CompletionError> {
         let model = self.model.clone();
-        model.stream(self.build()).await
+        let request = self.build();
+        let cancellation = request.cancellation.clone();
+        let response = with_cancellation(
+            cancellation.as_ref(),
+            with_timeout(request.timeout, model.stream(request)),
+        )
+        .await?;
+
+        Ok(match cancellation {
+            Some(cancellation) => response.cancellation(cancellation),
+            None => response,
+        })
     }
 }
 
@@ -551,8 +1230,15 @@ mod tests {
             chat_history: OneOrMany::one("What is the capital of France?".into()),
             documents: vec![doc1, doc2],
             tools: Vec::new(),
+            tool_choice: Default::default(),
+            parallel_tool_calls: None,
             temperature: None,
             max_tokens: None,
+            timeout: None,
+            cancellation: None,
+            http_extras: Default::default(),
+            output_constraint: None,
+            sampling: Default::default(),
             additional_params: None,
         };
 
@@ -582,11 +1268,171 @@ mod tests {
             chat_history: OneOrMany::one("What is the capital of France?".into()),
             documents: Vec::new(),
             tools: Vec::new(),
+            tool_choice: Default::default(),
+            parallel_tool_calls: None,
             temperature: None,
             max_tokens: None,
+            timeout: None,
+            cancellation: None,
+            http_extras: Default::default(),
+            output_constraint: None,
+            sampling: Default::default(),
             additional_params: None,
         };
 
         assert_eq!(request.normalized_documents(), None);
     }
+
+    #[test]
+    fn test_sampling_params_to_json() {
+        let sampling = SamplingParams {
+            top_k: Some(40),
+            stop: Some(vec!["\n".to_string()]),
+            seed: Some(42),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            sampling.to_json(
+                "Anthropic",
+                &SamplingParamNames {
+                    top_k: Some("top_k"),
+                    stop: Some("stop_sequences"),
+                    ..SamplingParamNames::NONE
+                }
+            ),
+            serde_json::json!({ "top_k": 40, "stop_sequences": ["\n"] })
+        );
+        assert_eq!(
+            sampling.to_json("OpenAI", &SamplingParamNames::OPENAI),
+            serde_json::json!({ "stop": ["\n"], "seed": 42 })
+        );
+        assert_eq!(
+            SamplingParams::default().to_json("OpenAI", &SamplingParamNames::NONE),
+            serde_json::json!({})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout() {
+        let slow = async {
+            futures_timer::Delay::new(Duration::from_secs(5)).await;
+            Ok::<_, CompletionError>(())
+        };
+        assert!(matches!(
+            with_timeout(Some(Duration::from_millis(10)), slow).await,
+            Err(CompletionError::Timeout(timeout)) if timeout == Duration::from_millis(10)
+        ));
+
+        let fast = async { Ok::<_, PromptError>(1) };
+        assert_eq!(
+            with_timeout(Some(Duration::from_secs(5)), fast)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            with_timeout(None, async { Ok::<_, PromptError>(2) })
+                .await
+                .unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_http_extras() {
+        let http_extras = HttpExtras {
+            headers: vec![("OpenAI-Project".to_string(), "proj_123".to_string())],
+            query: vec![("route".to_string(), "eu".to_string())],
+        };
+
+        let request = http_extras
+            .apply(
+                reqwest::Client::new()
+                    .post("https://example.com/chat?api-version=1")
+                    .header("Authorization", "Bearer key"),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            "https://example.com/chat?api-version=1&route=eu"
+        );
+        assert_eq!(request.headers()["openai-project"], "proj_123");
+        assert_eq!(request.headers()["authorization"], "Bearer key");
+    }
+
+    #[derive(Clone)]
+    struct WindowModel(Capabilities);
+
+    impl CompletionModel for WindowModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            unimplemented!()
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_auto_max_tokens() {
+        let model = WindowModel(Capabilities {
+            context_window: Some(1000),
+            max_output_tokens: Some(500),
+            ..Default::default()
+        });
+        let request = |prompt: &str| CompletionRequestBuilder::new(model.clone(), prompt);
+
+        // Capped to the max output tokens
+        assert_eq!(request("Hello").build().max_tokens, Some(500));
+
+        // The context window minus the input tokens and the safety margin
+        let prompt = "a".repeat(1600);
+        let built = request(&prompt).build();
+        let input_tokens = built.estimated_input_tokens(&HeuristicTokenizer::default()) as u64;
+        assert!(input_tokens > 400);
+        assert_eq!(built.max_tokens, Some(1000 - input_tokens - 256));
+
+        // No room left for the output
+        assert_eq!(request(&"a".repeat(4000)).build().max_tokens, None);
+
+        // Explicit or disabled max tokens
+        assert_eq!(request("Hello").max_tokens(10).build().max_tokens, Some(10));
+        assert_eq!(
+            request("Hello").auto_max_tokens(false).build().max_tokens,
+            None
+        );
+        // Unknown context window
+        let model = WindowModel(Capabilities::default());
+        assert_eq!(
+            CompletionRequestBuilder::new(model, "Hello")
+                .build()
+                .max_tokens,
+            None
+        );
+    }
+
+    #[test]
+    fn test_openai_tool_params() {
+        let tool_choice = ToolChoice::Tool("route".to_string());
+
+        assert_eq!(
+            tool_choice.to_openai_params(Some(false)),
+            serde_json::json!({
+                "tool_choice": {"type": "function", "function": {"name": "route"}},
+                "parallel_tool_calls": false,
+            })
+        );
+        assert_eq!(
+            ToolChoice::Required.to_openai_params(None),
+            serde_json::json!({"tool_choice": "required"})
+        );
+    }
 }
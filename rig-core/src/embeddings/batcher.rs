@@ -0,0 +1,316 @@
+//! The module defines the [EmbeddingBatcher] struct, an embedding model wrapper that coalesces
+//! the embedding calls of concurrent tasks into batched provider requests.
+//!
+//! This is useful in high-QPS services (e.g.: RAG APIs) where every request embeds a single
+//! query: instead of one provider request per query, the queries received within a small time
+//! window are embedded in a single request.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use rig::{embeddings::{EmbeddingBatcher, EmbeddingModel}, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let model = EmbeddingBatcher::new(openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL))
+//!     .window(Duration::from_millis(5));
+//!
+//! // Both calls are embedded in a single request
+//! let (a, b) = futures::join!(model.embed_text("hello"), model.embed_text("world"));
+//! ```
+
+use std::{
+    sync::{Arc, Mutex},
+    task::Poll,
+    time::Duration,
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    future::Either,
+    stream::FuturesUnordered,
+    StreamExt,
+};
+
+use super::{Embedding, EmbeddingError, EmbeddingModel};
+use crate::wasm_compat::WasmBoxedFuture;
+
+struct PendingText {
+    text: String,
+    sender: oneshot::Sender<Result<Embedding, EmbeddingError>>,
+}
+
+/// The batch requests in flight, polled by the callers waiting for their embeddings.
+struct Driver {
+    flushes: mpsc::UnboundedReceiver<WasmBoxedFuture<'static, ()>>,
+    in_flight: FuturesUnordered<WasmBoxedFuture<'static, ()>>,
+}
+
+/// Embedding model wrapper that coalesces concurrent embedding calls into batched requests.
+///
+/// Texts are queued when embedded and flushed either when the batching window elapses or when
+/// the queue reaches the maximum batch size. The batch requests are driven by the callers
+/// themselves (whichever caller is waiting polls the requests of all the callers), so the
+/// batcher does not depend on a specific async runtime, and dropping a caller does not cancel
+/// the embeddings of the other callers of its batch.
+///
+/// Note: since [EmbeddingError] is not cloneable, the error of a failed batch request is
+/// returned to every caller of the batch as a [EmbeddingError::ProviderError].
+#[derive(Clone)]
+pub struct EmbeddingBatcher<M: EmbeddingModel> {
+    model: M,
+    window: Duration,
+    max_batch_size: usize,
+    pending: Arc<Mutex<Vec<PendingText>>>,
+    flushes: mpsc::UnboundedSender<WasmBoxedFuture<'static, ()>>,
+    driver: Arc<futures::lock::Mutex<Driver>>,
+}
+
+impl<M: EmbeddingModel + 'static> EmbeddingBatcher<M> {
+    pub fn new(model: M) -> Self {
+        let (flushes, receiver) = mpsc::unbounded();
+        Self {
+            model,
+            window: Duration::from_millis(10),
+            max_batch_size: M::MAX_DOCUMENTS,
+            pending: Arc::new(Mutex::new(vec![])),
+            flushes,
+            driver: Arc::new(futures::lock::Mutex::new(Driver {
+                flushes: receiver,
+                in_flight: FuturesUnordered::new(),
+            })),
+        }
+    }
+
+    /// Set the time window during which embedding calls are collected (default: 10ms).
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set the number of queued texts that triggers an immediate flush, which is also the
+    /// maximum size of the batch requests (default: [EmbeddingModel::MAX_DOCUMENTS]).
+    pub fn max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    /// Queue the batch requests embedding all the queued texts, in batches of at most
+    /// `max_batch_size` texts. The requests are polled by [Self::drive].
+    fn flush(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock().expect("Lock poisoned"));
+        let batch_size = self.max_batch_size.min(M::MAX_DOCUMENTS);
+
+        let mut pending = pending.into_iter().peekable();
+        while pending.peek().is_some() {
+            let (texts, senders): (Vec<_>, Vec<_>) = pending
+                .by_ref()
+                .take(batch_size)
+                .map(|PendingText { text, sender }| (text, sender))
+                .unzip();
+
+            let model = self.model.clone();
+            let _ = self.flushes.unbounded_send(Box::pin(async move {
+                tracing::debug!(target: "rig", "Embedding batch of {} texts", texts.len());
+
+                match model.embed_texts(texts).await {
+                    Ok(embeddings) if embeddings.len() == senders.len() => {
+                        for (sender, embedding) in senders.into_iter().zip(embeddings) {
+                            let _ = sender.send(Ok(embedding));
+                        }
+                    }
+                    Ok(_) => {
+                        for sender in senders {
+                            let _ = sender.send(Err(EmbeddingError::ResponseError(
+                                "Response data length does not match input length".into(),
+                            )));
+                        }
+                    }
+                    Err(err) => {
+                        let message = err.to_string();
+                        for sender in senders {
+                            let _ =
+                                sender.send(Err(EmbeddingError::ProviderError(message.clone())));
+                        }
+                    }
+                }
+            }));
+        }
+    }
+
+    /// Poll the batch requests in flight (of all the callers) until dropped. A single caller
+    /// drives the requests at a time; when it is dropped, the next waiting caller takes over.
+    async fn drive(&self) {
+        let mut driver = self.driver.lock().await;
+        let Driver { flushes, in_flight } = &mut *driver;
+
+        futures::future::poll_fn(|cx| {
+            while let Poll::Ready(Some(flush)) = flushes.poll_next_unpin(cx) {
+                in_flight.push(flush);
+            }
+            while let Poll::Ready(Some(())) = in_flight.poll_next_unpin(cx) {}
+            Poll::<()>::Pending
+        })
+        .await
+    }
+}
+
+impl<M: EmbeddingModel + 'static> EmbeddingModel for EmbeddingBatcher<M> {
+    const MAX_DOCUMENTS: usize = M::MAX_DOCUMENTS;
+
+    fn ndims(&self) -> usize {
+        self.model.ndims()
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let (receivers, full) = {
+            let mut pending = self.pending.lock().expect("Lock poisoned");
+            let receivers = texts
+                .into_iter()
+                .map(|text| {
+                    let (sender, receiver) = oneshot::channel();
+                    pending.push(PendingText { text, sender });
+                    receiver
+                })
+                .collect::<Vec<_>>();
+
+            (receivers, pending.len() >= self.max_batch_size)
+        };
+
+        if !full {
+            crate::runtime::sleep(self.window).await;
+        }
+
+        // Whichever caller wakes up first flushes the queue (including the texts of other
+        // callers). If the texts of this caller were already flushed, this is a no-op.
+        self.flush();
+
+        let embeddings = async {
+            let mut embeddings = Vec::with_capacity(receivers.len());
+            for receiver in receivers {
+                embeddings.push(receiver.await.map_err(|_| {
+                    EmbeddingError::ProviderError("Embedding batch was cancelled".into())
+                })??);
+            }
+            Ok(embeddings)
+        };
+        let drive = self.drive();
+        futures::pin_mut!(embeddings, drive);
+
+        match futures::future::select(embeddings, drive).await {
+            Either::Left((embeddings, _)) => embeddings,
+            Either::Right(((), embeddings)) => embeddings.await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct CountingModel {
+        requests: Arc<AtomicUsize>,
+        delay: Duration,
+    }
+
+    impl EmbeddingModel for CountingModel {
+        const MAX_DOCUMENTS: usize = 4;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            if !self.delay.is_zero() {
+                crate::runtime::sleep(self.delay).await;
+            }
+            Ok(texts
+                .into_iter()
+                .map(|text| Embedding {
+                    vec: vec![text.len() as f64],
+                    document: text,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_coalescing() {
+        let model = CountingModel::default();
+        let batcher = EmbeddingBatcher::new(model.clone()).window(Duration::from_millis(50));
+
+        let embeddings =
+            futures::future::join_all(["a", "bb", "ccc"].map(|text| batcher.embed_text(text)))
+                .await;
+
+        assert_eq!(model.requests.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            embeddings
+                .into_iter()
+                .map(|embedding| embedding.unwrap().vec[0])
+                .collect::<Vec<_>>(),
+            vec![1.0, 2.0, 3.0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_by_max_documents() {
+        let model = CountingModel::default();
+        let batcher = EmbeddingBatcher::new(model.clone());
+
+        let embeddings = batcher
+            .embed_texts((0..6).map(|i| i.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(embeddings.len(), 6);
+        assert_eq!(embeddings[5].document, "5");
+        assert_eq!(model.requests.load(Ordering::SeqCst), 2);
+
+        let batcher = EmbeddingBatcher::new(model.clone()).max_batch_size(2);
+        let embeddings = batcher
+            .embed_texts((0..5).map(|i| i.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(embeddings.len(), 5);
+        assert_eq!(model.requests.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_caller() {
+        let model = CountingModel {
+            delay: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let batcher = EmbeddingBatcher::new(model.clone())
+            .window(Duration::from_millis(50))
+            .max_batch_size(2);
+
+        let other = batcher.embed_text("bb");
+        futures::pin_mut!(other);
+        assert!(futures::poll!(other.as_mut()).is_pending());
+
+        // The second text fills the batch: this caller sends the request, and is dropped
+        // while the request is in flight
+        {
+            let caller = batcher.embed_text("a");
+            futures::pin_mut!(caller);
+            assert!(futures::poll!(caller.as_mut()).is_pending());
+        }
+
+        assert_eq!(other.await.unwrap().vec, vec![2.0]);
+        assert_eq!(model.requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -2,13 +2,15 @@
 //! and batch generates the embeddings for each object when built.
 //! Only types that implement the [Embed] trait can be added to the [EmbeddingsBuilder].
 
-use std::{cmp::max, collections::HashMap};
+use std::{cmp::max, collections::HashMap, sync::Arc};
 
 use futures::{stream, StreamExt};
 
 use crate::{
+    chunking::TextSplitter,
     embeddings::{
         embed::TextEmbedder, Embed, EmbedError, Embedding, EmbeddingError, EmbeddingModel,
+        EmbeddingUsage,
     },
     OneOrMany,
 };
@@ -17,7 +19,10 @@ use crate::{
 /// Note: `T` can be any type that implements the [Embed] trait.
 ///
 /// Using the builder is preferred over using [EmbeddingModel::embed_text] directly as
-/// it will batch the documents in a single request to the model provider.
+/// it will batch the documents in as few requests to the model provider as possible.
+/// Batches are limited by [EmbeddingModel::MAX_DOCUMENTS] and
+/// [EmbeddingModel::MAX_TOKENS_PER_REQUEST] (or the limits set on the builder), and are
+/// sent concurrently.
 ///
 /// # Example
 /// ```rust
@@ -50,6 +55,10 @@ use crate::{
 pub struct EmbeddingsBuilder<M: EmbeddingModel, T: Embed> {
     model: M,
     documents: Vec<(T, Vec<String>)>,
+    batch_size: usize,
+    max_tokens_per_batch: Option<usize>,
+    concurrency: usize,
+    splitter: Option<Arc<dyn TextSplitter>>,
 }
 
 impl<M: EmbeddingModel, T: Embed> EmbeddingsBuilder<M, T> {
@@ -58,9 +67,40 @@ impl<M: EmbeddingModel, T: Embed> EmbeddingsBuilder<M, T> {
         Self {
             model,
             documents: vec![],
+            batch_size: M::MAX_DOCUMENTS,
+            max_tokens_per_batch: M::MAX_TOKENS_PER_REQUEST,
+            concurrency: max(1, 1024 / M::MAX_DOCUMENTS),
+            splitter: None,
         }
     }
 
+    /// Set the maximum number of texts embedded per request.
+    /// The batch size is capped at [EmbeddingModel::MAX_DOCUMENTS].
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, M::MAX_DOCUMENTS);
+        self
+    }
+
+    /// Set the maximum (estimated) number of tokens embedded per request.
+    /// Token counts are estimated at 4 bytes of text per token.
+    pub fn max_tokens_per_batch(mut self, max_tokens: usize) -> Self {
+        self.max_tokens_per_batch = Some(max_tokens);
+        self
+    }
+
+    /// Set the maximum number of concurrent requests to the model provider.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = max(1, concurrency);
+        self
+    }
+
+    /// Split the texts of the documents with the given splitter, so that each chunk is embedded
+    /// separately (see [crate::chunking]). Each document gets one embedding per chunk.
+    pub fn splitter(mut self, splitter: impl TextSplitter + 'static) -> Self {
+        self.splitter = Some(Arc::new(splitter));
+        self
+    }
+
     /// Add a document to be embedded to the builder. `document` must implement the [Embed] trait.
     pub fn document(mut self, document: T) -> Result<Self, EmbedError> {
         let mut embedder = TextEmbedder::default();
@@ -86,6 +126,15 @@ impl<M: EmbeddingModel, T: Embed + Send> EmbeddingsBuilder<M, T> {
     /// Generate embeddings for all documents in the builder.
     /// Returns a vector of tuples, where the first element is the document and the second element is the embeddings (either one embedding or many).
     pub async fn build(self) -> Result<Vec<(T, OneOrMany<Embedding>)>, EmbeddingError> {
+        Ok(self.build_with_usage().await?.0)
+    }
+
+    /// Same as [EmbeddingsBuilder::build], but also returns the token usage aggregated over all
+    /// requests. Usage is only counted for models reporting it (see
+    /// [EmbeddingModel::embed_texts_with_usage]).
+    pub async fn build_with_usage(
+        self,
+    ) -> Result<(Vec<(T, OneOrMany<Embedding>)>, EmbeddingUsage), EmbeddingError> {
         use stream::TryStreamExt;
 
         // Store the documents and their texts in a HashMap for easy access.
@@ -95,41 +144,84 @@ impl<M: EmbeddingModel, T: Embed + Send> EmbeddingsBuilder<M, T> {
         // Iterate over all documents in the builder and insert their docs and texts into the lookup stores.
         for (i, (doc, doc_texts)) in self.documents.into_iter().enumerate() {
             docs.insert(i, doc);
-            texts.push((i, doc_texts));
+
+            let doc_texts = match &self.splitter {
+                Some(splitter) => doc_texts
+                    .into_iter()
+                    .flat_map(|text| match splitter.split_text(&text) {
+                        // Keep texts without chunks (e.g.: empty texts) so that every document has an embedding
+                        chunks if chunks.is_empty() => vec![text],
+                        chunks => chunks,
+                    })
+                    .collect(),
+                None => doc_texts,
+            };
+            texts.extend(doc_texts.into_iter().map(|text| (i, text)));
+        }
+
+        // Split the texts into batches. Each batch is at most the embedding API limit per request.
+        let mut batches: Vec<Vec<(usize, String)>> = vec![];
+        let mut batch_tokens = 0;
+        for (i, text) in texts {
+            let tokens = crate::tokens::estimate_tokens(&text);
+            let full = match batches.last() {
+                Some(batch) => {
+                    batch.len() >= self.batch_size
+                        || self
+                            .max_tokens_per_batch
+                            .is_some_and(|max_tokens| batch_tokens + tokens > max_tokens)
+                }
+                None => true,
+            };
+
+            if full {
+                batches.push(vec![]);
+                batch_tokens = 0;
+            }
+
+            batch_tokens += tokens;
+            batches
+                .last_mut()
+                .expect("There should be at least one batch")
+                .push((i, text));
         }
 
         // Compute the embeddings.
-        let mut embeddings = stream::iter(texts.into_iter())
-            // Merge the texts of each document into a single list of texts.
-            .flat_map(|(i, texts)| stream::iter(texts.into_iter().map(move |text| (i, text))))
-            // Chunk them into batches. Each batch size is at most the embedding API limit per request.
-            .chunks(M::MAX_DOCUMENTS)
+        let (mut embeddings, usage) = stream::iter(batches)
             // Generate the embeddings for each batch.
-            .map(|text| async {
-                let (ids, docs): (Vec<_>, Vec<_>) = text.into_iter().unzip();
-
-                let embeddings = self.model.embed_texts(docs).await?;
-                Ok::<_, EmbeddingError>(ids.into_iter().zip(embeddings).collect::<Vec<_>>())
+            .map(|batch| async {
+                let (ids, docs): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+
+                let (embeddings, usage) = self.model.embed_texts_with_usage(docs).await?;
+                Ok::<_, EmbeddingError>((
+                    ids.into_iter().zip(embeddings).collect::<Vec<_>>(),
+                    usage,
+                ))
             })
-            // Parallelize the embeddings generation over 10 concurrent requests
-            .buffer_unordered(max(1, 1024 / M::MAX_DOCUMENTS))
-            // Collect the embeddings into a HashMap.
+            // Parallelize the embeddings generation
+            .buffer_unordered(self.concurrency)
+            // Collect the embeddings into a HashMap and aggregate the usage.
             .try_fold(
-                HashMap::new(),
-                |mut acc: HashMap<_, OneOrMany<Embedding>>, embeddings| async move {
+                (HashMap::new(), EmbeddingUsage::default()),
+                |(mut acc, mut total_usage): (HashMap<_, OneOrMany<Embedding>>, _),
+                 (embeddings, usage)| async move {
                     embeddings.into_iter().for_each(|(i, embedding)| {
                         acc.entry(i)
                             .and_modify(|embeddings| embeddings.push(embedding.clone()))
                             .or_insert(OneOrMany::one(embedding.clone()));
                     });
 
-                    Ok(acc)
+                    if let Some(usage) = usage {
+                        total_usage += usage;
+                    }
+
+                    Ok((acc, total_usage))
                 },
             )
             .await?;
 
         // Merge the embeddings with their respective documents
-        Ok(docs
+        let documents = docs
             .into_iter()
             .map(|(i, doc)| {
                 (
@@ -137,14 +229,24 @@ impl<M: EmbeddingModel, T: Embed + Send> EmbeddingsBuilder<M, T> {
                     embeddings.remove(&i).expect("Document should be present"),
                 )
             })
-            .collect())
+            .collect();
+
+        Ok((documents, usage))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
     use crate::{
-        embeddings::{embed::EmbedError, embed::TextEmbedder, Embedding, EmbeddingModel},
+        embeddings::{
+            embed::EmbedError, embed::TextEmbedder, Embedding, EmbeddingError, EmbeddingModel,
+            EmbeddingUsage,
+        },
         Embed,
     };
 
@@ -174,6 +276,50 @@ mod tests {
         }
     }
 
+    /// Model counting requests and reporting one token per text
+    #[derive(Clone, Default)]
+    struct CountingModel {
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl EmbeddingModel for CountingModel {
+        const MAX_DOCUMENTS: usize = 3;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            documents: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(self.embed_texts_with_usage(documents).await?.0)
+        }
+
+        async fn embed_texts_with_usage(
+            &self,
+            documents: impl IntoIterator<Item = String> + Send,
+        ) -> Result<(Vec<Embedding>, Option<EmbeddingUsage>), EmbeddingError> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let embeddings = documents
+                .into_iter()
+                .map(|document| Embedding {
+                    document,
+                    vec: vec![0.0],
+                })
+                .collect::<Vec<_>>();
+            let tokens = embeddings.len() as u64;
+
+            Ok((
+                embeddings,
+                Some(EmbeddingUsage {
+                    prompt_tokens: tokens,
+                    total_tokens: tokens,
+                }),
+            ))
+        }
+    }
+
     #[derive(Clone, Debug)]
     struct WordDefinition {
         id: String,
@@ -384,4 +530,67 @@ mod tests {
             second_definition.1.rest()[0].document, "A fictional creature found in the distant, swampy marshlands of the planet Glibbo in the Andromeda galaxy.".to_string()
         )
     }
+
+    #[tokio::test]
+    async fn test_build_batching_and_usage() {
+        let texts = (0..7).map(|i| format!("text {i}")).collect::<Vec<_>>();
+
+        let model = CountingModel::default();
+        let (result, usage) = EmbeddingsBuilder::new(model.clone())
+            .documents(texts.clone())
+            .unwrap()
+            .build_with_usage()
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 7);
+        assert_eq!(model.requests.load(Ordering::SeqCst), 3);
+        assert_eq!(usage.total_tokens, 7);
+
+        // Each text is estimated at 2 tokens, so at most 2 texts fit in a batch
+        let model = CountingModel::default();
+        EmbeddingsBuilder::new(model.clone())
+            .documents(texts)
+            .unwrap()
+            .max_tokens_per_batch(4)
+            .concurrency(1)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(model.requests.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_build_with_splitter() {
+        let result = EmbeddingsBuilder::new(Model)
+            .splitter(crate::chunking::SentenceSplitter::new(20))
+            .documents(vec![
+                "First sentence. Second sentence.".to_string(),
+                String::new(),
+            ])
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        // The documents are not returned in order
+        let (_, embeddings) = result
+            .iter()
+            .find(|(document, _)| document == "First sentence. Second sentence.")
+            .unwrap();
+        assert_eq!(
+            embeddings
+                .iter()
+                .map(|embedding| embedding.document.as_str())
+                .collect::<Vec<_>>(),
+            vec!["First sentence.", "Second sentence."]
+        );
+        // The empty document is embedded as is
+        let (_, embeddings) = result
+            .iter()
+            .find(|(document, _)| document.is_empty())
+            .unwrap();
+        assert_eq!(embeddings.len(), 1);
+    }
 }
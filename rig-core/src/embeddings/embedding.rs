@@ -6,6 +6,7 @@
 //! Finally, the module defines the [EmbeddingError] enum, which represents various errors that
 //! can occur during embedding generation or processing.
 
+use crate::wasm_compat::WasmCompatSend;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, thiserror::Error)]
@@ -36,6 +37,10 @@ pub trait EmbeddingModel: Clone + Sync + Send {
     /// The maximum number of documents that can be embedded in a single request.
     const MAX_DOCUMENTS: usize;
 
+    /// The maximum total number of tokens that can be embedded in a single request, if the
+    /// provider enforces such a limit.
+    const MAX_TOKENS_PER_REQUEST: Option<usize> = None;
+
     /// The number of dimensions in the embedding vector.
     fn ndims(&self) -> usize;
 
@@ -43,13 +48,24 @@ pub trait EmbeddingModel: Clone + Sync + Send {
     fn embed_texts(
         &self,
         texts: impl IntoIterator<Item = String> + Send,
-    ) -> impl std::future::Future<Output = Result<Vec<Embedding>, EmbeddingError>> + Send;
+    ) -> impl std::future::Future<Output = Result<Vec<Embedding>, EmbeddingError>> + WasmCompatSend;
+
+    /// Embed multiple text documents in a single request, returning the token usage of
+    /// the request if reported by the provider.
+    fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> impl std::future::Future<
+        Output = Result<(Vec<Embedding>, Option<EmbeddingUsage>), EmbeddingError>,
+    > + WasmCompatSend {
+        async { Ok((self.embed_texts(texts).await?, None)) }
+    }
 
     /// Embed a single text document.
     fn embed_text(
         &self,
         text: &str,
-    ) -> impl std::future::Future<Output = Result<Embedding, EmbeddingError>> + Send {
+    ) -> impl std::future::Future<Output = Result<Embedding, EmbeddingError>> + WasmCompatSend {
         async {
             Ok(self
                 .embed_texts(vec![text.to_string()])
@@ -72,13 +88,13 @@ pub trait ImageEmbeddingModel: Clone + Sync + Send {
     fn embed_images(
         &self,
         images: impl IntoIterator<Item = Vec<u8>> + Send,
-    ) -> impl std::future::Future<Output = Result<Vec<Embedding>, EmbeddingError>> + Send;
+    ) -> impl std::future::Future<Output = Result<Vec<Embedding>, EmbeddingError>> + WasmCompatSend;
 
     /// Embed a single image from bytes.
     fn embed_image<'a>(
         &'a self,
         bytes: &'a [u8],
-    ) -> impl std::future::Future<Output = Result<Embedding, EmbeddingError>> + Send {
+    ) -> impl std::future::Future<Output = Result<Embedding, EmbeddingError>> + WasmCompatSend {
         async move {
             Ok(self
                 .embed_images(vec![bytes.to_owned()])
@@ -89,6 +105,30 @@ pub trait ImageEmbeddingModel: Clone + Sync + Send {
     }
 }
 
+/// Token usage of one or more embedding requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct EmbeddingUsage {
+    pub prompt_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl std::ops::Add for EmbeddingUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+        }
+    }
+}
+
+impl std::ops::AddAssign for EmbeddingUsage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
 /// Struct that holds a single document and its embedding.
 #[derive(Clone, Default, Deserialize, Serialize, Debug)]
 pub struct Embedding {
@@ -98,6 +138,22 @@ pub struct Embedding {
     pub vec: Vec<f64>,
 }
 
+impl Embedding {
+    /// Keep the first `ndims` dimensions of the embedding vector and renormalize it to unit length.
+    ///
+    /// This is only meaningful for models trained with Matryoshka representation learning
+    /// (e.g.: OpenAI `text-embedding-3`, `nomic-embed-text-v1.5`), whose leading dimensions
+    /// carry most of the information.
+    pub fn truncate(&mut self, ndims: usize) {
+        self.vec.truncate(ndims);
+
+        let magnitude = self.vec.iter().map(|x| x.powi(2)).sum::<f64>().sqrt();
+        if magnitude > 0.0 {
+            self.vec.iter_mut().for_each(|x| *x /= magnitude);
+        }
+    }
+}
+
 impl PartialEq for Embedding {
     fn eq(&self, other: &Self) -> bool {
         self.document == other.document
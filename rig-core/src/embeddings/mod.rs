@@ -3,13 +3,22 @@
 //! natural language processing (NLP) tasks such as text classification, information retrieval,
 //! and document similarity.
 
+pub mod batcher;
 pub mod builder;
 pub mod embed;
 pub mod embedding;
+pub mod quantization;
+pub mod simd;
+pub mod sparse;
 pub mod tool;
+pub mod truncate;
 
 pub mod distance;
+pub use batcher::EmbeddingBatcher;
 pub use builder::EmbeddingsBuilder;
 pub use embed::{to_texts, Embed, EmbedError, TextEmbedder};
-pub use embedding::{Embedding, EmbeddingError, EmbeddingModel};
+pub use embedding::{Embedding, EmbeddingError, EmbeddingModel, EmbeddingUsage};
+pub use quantization::{Quantization, QuantizedVector};
+pub use sparse::{SparseEmbedding, SparseEmbeddingModel};
 pub use tool::ToolSchema;
+pub use truncate::TruncatedEmbeddingModel;
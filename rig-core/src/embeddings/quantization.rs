@@ -0,0 +1,164 @@
+//! Embedding quantization utilities.
+//!
+//! Quantized embeddings trade a small loss of retrieval quality for a much smaller memory
+//! footprint, which matters for large local indexes:
+//! - [Quantization::Int8]: each dimension is stored as an `i8` scaled by the largest absolute
+//!   value of the vector (8x smaller than `f64`, 4x smaller than `f32`)
+//! - [Quantization::Binary]: each dimension is stored as its sign bit (64x smaller than `f64`,
+//!   32x smaller than `f32`) and vectors are compared with the Hamming distance
+//!
+//! See [QuantizedVectorStore](crate::vector_store::quantized_store::QuantizedVectorStore) for
+//! an in-memory vector store using quantized embeddings.
+
+use serde::{Deserialize, Serialize};
+
+/// Quantization scheme of embedding vectors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Quantization {
+    /// Scalar quantization to 8-bit integers
+    Int8,
+    /// Binary quantization (sign of each dimension)
+    Binary,
+}
+
+impl Quantization {
+    /// Quantize an embedding vector.
+    pub fn quantize(&self, vec: &[f64]) -> QuantizedVector {
+        match self {
+            Quantization::Int8 => {
+                let max = vec.iter().fold(0.0f64, |max, x| max.max(x.abs()));
+                let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+
+                QuantizedVector::Int8 {
+                    values: vec.iter().map(|x| (x / scale).round() as i8).collect(),
+                    scale: scale as f32,
+                }
+            }
+            Quantization::Binary => {
+                let mut bits = vec![0u64; vec.len().div_ceil(64)];
+                vec.iter()
+                    .enumerate()
+                    .filter(|(_, x)| **x > 0.0)
+                    .for_each(|(i, _)| bits[i / 64] |= 1 << (i % 64));
+
+                QuantizedVector::Binary {
+                    bits,
+                    ndims: vec.len(),
+                }
+            }
+        }
+    }
+}
+
+/// A quantized embedding vector.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum QuantizedVector {
+    /// `vec[i] ≈ values[i] * scale`
+    Int8 { values: Vec<i8>, scale: f32 },
+    /// Bit `i` is set if `vec[i] > 0`
+    Binary { bits: Vec<u64>, ndims: usize },
+}
+
+impl QuantizedVector {
+    /// Approximate the original embedding vector.
+    pub fn dequantize(&self) -> Vec<f64> {
+        match self {
+            QuantizedVector::Int8 { values, scale } => values
+                .iter()
+                .map(|value| *value as f64 * *scale as f64)
+                .collect(),
+            QuantizedVector::Binary { bits, ndims } => (0..*ndims)
+                .map(|i| {
+                    if bits[i / 64] & (1 << (i % 64)) != 0 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Get the similarity of two quantized vectors of the same quantization scheme:
+    /// - cosine similarity for [QuantizedVector::Int8] (computed with integer arithmetic)
+    /// - `1 - hamming_distance / ndims` for [QuantizedVector::Binary]
+    ///
+    /// Vectors of different quantization schemes have a similarity of 0.
+    pub fn similarity(&self, other: &Self) -> f64 {
+        match (self, other) {
+            (QuantizedVector::Int8 { values: a, .. }, QuantizedVector::Int8 { values: b, .. }) => {
+                let dot = a
+                    .iter()
+                    .zip(b)
+                    .map(|(x, y)| *x as i64 * *y as i64)
+                    .sum::<i64>();
+                let norm =
+                    |v: &[i8]| (v.iter().map(|x| *x as i64 * *x as i64).sum::<i64>() as f64).sqrt();
+
+                let norms = norm(a) * norm(b);
+                if norms == 0.0 {
+                    0.0
+                } else {
+                    dot as f64 / norms
+                }
+            }
+            (QuantizedVector::Binary { ndims, .. }, QuantizedVector::Binary { .. }) => match *ndims
+            {
+                0 => 0.0,
+                ndims => 1.0 - self.hamming_distance(other) as f64 / ndims as f64,
+            },
+            _ => 0.0,
+        }
+    }
+
+    /// Get the number of differing bits of two binary vectors (0 for int8 vectors).
+    pub fn hamming_distance(&self, other: &Self) -> u32 {
+        match (self, other) {
+            (QuantizedVector::Binary { bits: a, .. }, QuantizedVector::Binary { bits: b, .. }) => {
+                a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+            }
+            _ => 0,
+        }
+    }
+
+    /// Approximate memory footprint of the vector data, in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            QuantizedVector::Int8 { values, .. } => values.len() + std::mem::size_of::<f32>(),
+            QuantizedVector::Binary { bits, .. } => bits.len() * std::mem::size_of::<u64>(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_int8_quantization() {
+        let vec = vec![0.5, -1.0, 0.25, 0.0];
+        let quantized = Quantization::Int8.quantize(&vec);
+
+        assert!(matches!(
+            &quantized,
+            QuantizedVector::Int8 { values, .. } if values[1] == -127 && values[3] == 0
+        ));
+        quantized
+            .dequantize()
+            .iter()
+            .zip(&vec)
+            .for_each(|(x, y)| assert!((x - y).abs() < 0.01));
+        assert!((quantized.similarity(&quantized) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_binary_quantization() {
+        let a = Quantization::Binary.quantize(&(0..70).map(|i| i as f64 - 0.5).collect::<Vec<_>>());
+        let b = Quantization::Binary.quantize(&vec![1.0; 70]);
+
+        assert_eq!(a.hamming_distance(&b), 1);
+        assert_eq!(a.similarity(&b), 1.0 - 1.0 / 70.0);
+        assert_eq!(a.size(), 16);
+        assert_eq!(b.dequantize(), vec![1.0; 70]);
+    }
+}
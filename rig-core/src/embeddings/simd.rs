@@ -0,0 +1,67 @@
+//! Vectorized kernels for the similarity search over embedding vectors.
+//!
+//! The kernels accumulate the products of the vectors in [LANES] independent lanes. Since the
+//! lanes don't depend on each other, the compiler emits SIMD instructions (SSE2, AVX, NEON, ...)
+//! for the main loop on stable Rust, instead of a sequential reduction.
+
+/// Number of accumulators of the kernels (8 x f64 = one AVX-512 or two AVX2 registers).
+pub const LANES: usize = 8;
+
+#[inline(always)]
+fn fold_lanes(a: &[f64], b: &[f64], f: impl Fn(f64, f64) -> f64) -> f64 {
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+
+    let a_chunks = a.chunks_exact(LANES);
+    let b_chunks = b.chunks_exact(LANES);
+    let tail = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| f(*x, *y))
+        .sum::<f64>();
+
+    let mut lanes = [0.0; LANES];
+    for (x, y) in a_chunks.zip(b_chunks) {
+        for ((lane, x), y) in lanes.iter_mut().zip(x).zip(y) {
+            *lane += f(*x, *y);
+        }
+    }
+
+    lanes.iter().sum::<f64>() + tail
+}
+
+/// Dot product of two vectors (truncated to the shortest one).
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+    fold_lanes(a, b, |x, y| x * y)
+}
+
+/// Squared euclidean norm of a vector.
+pub fn squared_norm(a: &[f64]) -> f64 {
+    dot(a, a)
+}
+
+/// Squared euclidean distance of two vectors (truncated to the shortest one).
+pub fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    fold_lanes(a, b, |x, y| (x - y) * (x - y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernels() {
+        for len in [0, 3, LANES, 2 * LANES + 5] {
+            let a = (0..len).map(|i| i as f64 * 0.5 - 2.0).collect::<Vec<_>>();
+            let b = (0..len).map(|i| 1.0 - i as f64 * 0.25).collect::<Vec<_>>();
+
+            let expected_dot = a.iter().zip(&b).map(|(x, y)| x * y).sum::<f64>();
+            let expected_distance = a.iter().zip(&b).map(|(x, y)| (x - y).powi(2)).sum::<f64>();
+
+            assert!((dot(&a, &b) - expected_dot).abs() < 1e-9);
+            assert!((squared_distance(&a, &b) - expected_distance).abs() < 1e-9);
+            assert!((squared_norm(&a) - dot(&a, &a)).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! The module defines the [SparseEmbeddingModel] trait, which represents an embedding model that
+//! generates sparse embeddings for documents (e.g.: SPLADE, BM25-style term weights).
+//!
+//! Sparse embeddings only store the non-zero dimensions of a (typically vocabulary-sized)
+//! vector, as pairs of indices and values. They capture exact term matches that dense embeddings
+//! tend to miss, and are combined with dense embeddings by vector stores supporting hybrid
+//! retrieval (e.g.: Qdrant, Pinecone).
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::EmbeddingError;
+use crate::wasm_compat::WasmCompatSend;
+
+/// Trait for embedding models that can generate sparse embeddings for documents.
+pub trait SparseEmbeddingModel: Clone + Sync + Send {
+    /// The maximum number of documents that can be embedded in a single request.
+    const MAX_DOCUMENTS: usize;
+
+    /// Embed multiple text documents in a single request
+    fn embed_sparse_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> impl std::future::Future<Output = Result<Vec<SparseEmbedding>, EmbeddingError>> + WasmCompatSend;
+
+    /// Embed a single text document.
+    fn embed_sparse_text(
+        &self,
+        text: &str,
+    ) -> impl std::future::Future<Output = Result<SparseEmbedding, EmbeddingError>> + WasmCompatSend
+    {
+        async {
+            Ok(self
+                .embed_sparse_texts(vec![text.to_string()])
+                .await?
+                .pop()
+                .expect("There should be at least one embedding"))
+        }
+    }
+}
+
+/// Struct that holds a single document and its sparse embedding.
+///
+/// `indices` and `values` have the same length: `values[i]` is the value of the
+/// dimension `indices[i]`. All other dimensions are zero.
+#[derive(Clone, Default, Deserialize, Serialize, Debug)]
+pub struct SparseEmbedding {
+    /// The document that was embedded. Used for debugging.
+    pub document: String,
+    /// The indices of the non-zero dimensions
+    pub indices: Vec<u32>,
+    /// The values of the non-zero dimensions
+    pub values: Vec<f64>,
+}
+
+impl SparseEmbedding {
+    /// Create a sparse embedding from its non-zero dimensions, sorted by index.
+    pub fn new(document: &str, dimensions: impl IntoIterator<Item = (u32, f64)>) -> Self {
+        let mut dimensions = dimensions
+            .into_iter()
+            .filter(|(_, value)| *value != 0.0)
+            .collect::<Vec<_>>();
+        dimensions.sort_by_key(|(index, _)| *index);
+        dimensions.dedup_by_key(|(index, _)| *index);
+
+        let (indices, values) = dimensions.into_iter().unzip();
+
+        Self {
+            document: document.to_string(),
+            indices,
+            values,
+        }
+    }
+
+    /// The number of non-zero dimensions.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Iterate over the non-zero dimensions as `(index, value)` pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, f64)> + '_ {
+        self.indices
+            .iter()
+            .copied()
+            .zip(self.values.iter().copied())
+    }
+
+    /// Get dot product of two sparse embeddings
+    pub fn dot_product(&self, other: &Self) -> f64 {
+        let other = other.iter().collect::<HashMap<_, _>>();
+
+        self.iter()
+            .filter_map(|(index, value)| other.get(&index).map(|other| value * other))
+            .sum()
+    }
+
+    /// Get cosine similarity of two sparse embeddings
+    pub fn cosine_similarity(&self, other: &Self) -> f64 {
+        let magnitude1 = self.values.iter().map(|x| x.powi(2)).sum::<f64>().sqrt();
+        let magnitude2 = other.values.iter().map(|x| x.powi(2)).sum::<f64>().sqrt();
+
+        if magnitude1 == 0.0 || magnitude2 == 0.0 {
+            0.0
+        } else {
+            self.dot_product(other) / (magnitude1 * magnitude2)
+        }
+    }
+}
+
+impl PartialEq for SparseEmbedding {
+    fn eq(&self, other: &Self) -> bool {
+        self.document == other.document
+    }
+}
+
+impl Eq for SparseEmbedding {}
+
+#[cfg(test)]
+mod tests {
+    use super::SparseEmbedding;
+
+    #[test]
+    fn test_sparse_embedding() {
+        let a = SparseEmbedding::new("a", [(7, 2.0), (1, 1.0), (3, 0.0)]);
+        let b = SparseEmbedding::new("b", [(1, 3.0), (5, 1.0), (7, 1.0)]);
+
+        assert_eq!(a.indices, vec![1, 7]);
+        assert_eq!(a.values, vec![1.0, 2.0]);
+        assert_eq!(a.dot_product(&b), 5.0);
+        assert_eq!(a.dot_product(&SparseEmbedding::default()), 0.0);
+        assert!((a.cosine_similarity(&a) - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,112 @@
+//! The module defines the [TruncatedEmbeddingModel] struct, an embedding model wrapper that
+//! reduces the dimensions of the embeddings client-side (Matryoshka embeddings).
+//!
+//! Smaller embeddings reduce the storage and search costs of vector stores, at a small cost in
+//! retrieval quality. Prefer requesting reduced dimensions from the provider when supported
+//! (e.g.: [openai::EmbeddingModel::dimensions](crate::providers::openai::EmbeddingModel::dimensions)).
+//!
+//! # Example
+//! ```rust
+//! use rig::{embeddings::TruncatedEmbeddingModel, providers::ollama};
+//!
+//! let ollama = ollama::Client::new();
+//!
+//! // Keep the first 256 dimensions of the 768 dimensions of the embeddings
+//! let model = TruncatedEmbeddingModel::new(
+//!     ollama.embedding_model("nomic-embed-text"),
+//!     256,
+//! );
+//! ```
+
+use super::{Embedding, EmbeddingError, EmbeddingModel, EmbeddingUsage};
+
+/// Embedding model wrapper that keeps the first `ndims` dimensions of the embeddings and
+/// renormalizes them to unit length (see [Embedding::truncate]).
+#[derive(Clone)]
+pub struct TruncatedEmbeddingModel<M: EmbeddingModel> {
+    model: M,
+    ndims: usize,
+}
+
+impl<M: EmbeddingModel> TruncatedEmbeddingModel<M> {
+    /// Create a truncated embedding model. If `ndims` is larger than the dimensions of the
+    /// model, the embeddings are only renormalized.
+    pub fn new(model: M, ndims: usize) -> Self {
+        let ndims = match model.ndims() {
+            // Unknown number of dimensions
+            0 => ndims,
+            model_ndims => ndims.min(model_ndims),
+        };
+
+        Self { model, ndims }
+    }
+}
+
+impl<M: EmbeddingModel> EmbeddingModel for TruncatedEmbeddingModel<M> {
+    const MAX_DOCUMENTS: usize = M::MAX_DOCUMENTS;
+    const MAX_TOKENS_PER_REQUEST: Option<usize> = M::MAX_TOKENS_PER_REQUEST;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        Ok(self.embed_texts_with_usage(texts).await?.0)
+    }
+
+    async fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<(Vec<Embedding>, Option<EmbeddingUsage>), EmbeddingError> {
+        let (mut embeddings, usage) = self.model.embed_texts_with_usage(texts).await?;
+
+        embeddings
+            .iter_mut()
+            .for_each(|embedding| embedding.truncate(self.ndims));
+
+        Ok((embeddings, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct MockModel;
+
+    impl EmbeddingModel for MockModel {
+        const MAX_DOCUMENTS: usize = 10;
+
+        fn ndims(&self) -> usize {
+            4
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|document| Embedding {
+                    document,
+                    vec: vec![3.0, 4.0, 1.0, 1.0],
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncated_embedding_model() {
+        let model = TruncatedEmbeddingModel::new(MockModel, 2);
+        assert_eq!(model.ndims(), 2);
+
+        let embedding = model.embed_text("a").await.unwrap();
+        assert_eq!(embedding.vec, vec![0.6, 0.8]);
+
+        assert_eq!(TruncatedEmbeddingModel::new(MockModel, 8).ndims(), 4);
+    }
+}
@@ -0,0 +1,587 @@
+//! Text formats of the data extracted by an [Extractor](super::Extractor), for the models
+//! following formats such as XML or YAML more reliably than tool calls (see
+//! [ExtractorBuilder::format](super::ExtractorBuilder::format)).
+//!
+//! The scalars of the parsed data are then converted to the types of the JSON schema of the
+//! extracted type (e.g.: the text `42` of an XML element to the number of an integer field),
+//! since the XML and YAML outputs of the models carry no (or unreliable) types.
+
+use serde_json::{Map, Number, Value};
+
+use super::ExtractionError;
+
+/// Format of the response of the model, from which the extracted data is parsed.
+pub trait OutputFormat: Send + Sync {
+    /// Instructions of the model, to answer with the data matching the JSON schema `schema`.
+    fn instructions(&self, schema: &Value) -> String;
+
+    /// The data of the response `text` of the model.
+    fn parse(&self, text: &str, schema: &Value) -> Result<Value, ExtractionError>;
+}
+
+/// JSON object answered as text (optionally in a code block).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonFormat;
+
+impl OutputFormat for JsonFormat {
+    fn instructions(&self, schema: &Value) -> String {
+        format!(
+            "Answer with a JSON value matching the following JSON schema, and nothing else:\n{schema}"
+        )
+    }
+
+    fn parse(&self, text: &str, schema: &Value) -> Result<Value, ExtractionError> {
+        let value = serde_json::from_str(strip_code_block(text, "json"))?;
+        Ok(coerce(value, schema, schema))
+    }
+}
+
+/// Data answered as XML elements, named after the fields of the data, in a root element
+/// (`<data>` by default). The items of the lists are `<item>` elements.
+///
+/// The XML answers are parsed leniently: the text of the elements does not need to be escaped
+/// (except for its closing tag), and the text around the root element is ignored.
+#[derive(Clone, Debug)]
+pub struct XmlFormat {
+    root: String,
+}
+
+impl Default for XmlFormat {
+    fn default() -> Self {
+        Self {
+            root: "data".to_string(),
+        }
+    }
+}
+
+impl XmlFormat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the name of the root element of the answers.
+    pub fn root(mut self, root: &str) -> Self {
+        self.root = root.to_string();
+        self
+    }
+}
+
+impl OutputFormat for XmlFormat {
+    fn instructions(&self, schema: &Value) -> String {
+        format!(
+            "Answer with the data in a <{root}> XML element, with an element per field of the \
+            data (named after the field), and an <item> element per item of the lists. The data \
+            must match the following JSON schema:\n{schema}\n\nExample:\n<{root}>\n  \
+            <name>Jane</name>\n  <tags>\n    <item>first</item>\n    <item>second</item>\n  \
+            </tags>\n</{root}>",
+            root = self.root
+        )
+    }
+
+    fn parse(&self, text: &str, schema: &Value) -> Result<Value, ExtractionError> {
+        let content = xml_element(text, &self.root)
+            .map(|(content, _)| content)
+            .ok_or_else(|| {
+                ExtractionError::ParseError(format!("No <{}> element in the answer", self.root))
+            })?;
+        Ok(coerce(xml_value(content), schema, schema))
+    }
+}
+
+/// The content of the first `name` element of `text`, and the text after the element.
+fn xml_element<'a>(text: &'a str, name: &str) -> Option<(&'a str, &'a str)> {
+    let open = format!("<{name}>");
+    let close = format!("</{name}>");
+    let start = text.find(&open)? + open.len();
+    // The nested elements of the same name are skipped
+    let mut depth = 0;
+    let mut position = start;
+    loop {
+        let next_close = text[position..].find(&close)? + position;
+        match text[position..next_close].find(&open) {
+            Some(offset) => {
+                depth += 1;
+                position += offset + open.len();
+            }
+            None if depth > 0 => {
+                depth -= 1;
+                position = next_close + close.len();
+            }
+            None => return Some((&text[start..next_close], &text[next_close + close.len()..])),
+        }
+    }
+}
+
+/// The value of the content of an XML element: an object of its child elements (the values of
+/// the repeated elements in a list), or its text.
+fn xml_value(content: &str) -> Value {
+    let mut children: Vec<(String, Value)> = vec![];
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        let Some(name) = rest[start + 1..]
+            .split('>')
+            .next()
+            .filter(|name| is_xml_name(name))
+        else {
+            break;
+        };
+        let Some((child, after)) = xml_element(&rest[start..], name) else {
+            break;
+        };
+        children.push((name.to_string(), xml_value(child)));
+        rest = after;
+    }
+    if children.is_empty() {
+        return Value::String(xml_unescape(content.trim()));
+    }
+
+    let mut object = Map::new();
+    for (name, value) in children {
+        match object.get_mut(&name) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(previous) => *previous = Value::Array(vec![previous.take(), value]),
+            None => {
+                object.insert(name, value);
+            }
+        }
+    }
+    Value::Object(object)
+}
+
+fn is_xml_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Data answered as a YAML document (optionally in a code block).
+///
+/// The parser supports the block mappings and sequences, the flow sequences of scalars
+/// (`[a, b]`), the quoted scalars and the literal (`|`) and folded (`>`) block scalars, which
+/// cover the answers of the models.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct YamlFormat;
+
+impl OutputFormat for YamlFormat {
+    fn instructions(&self, schema: &Value) -> String {
+        format!(
+            "Answer with the data as a YAML document matching the following JSON schema, and \
+            nothing else:\n{schema}"
+        )
+    }
+
+    fn parse(&self, text: &str, schema: &Value) -> Result<Value, ExtractionError> {
+        let lines = strip_code_block(text, "yaml")
+            .lines()
+            .filter(|line| {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with('#') && line != "---"
+            })
+            .map(|line| {
+                let indent = line.len() - line.trim_start().len();
+                (indent, line.trim_end())
+            })
+            .collect::<Vec<_>>();
+        let mut position = 0;
+        let value = yaml_block(&lines, &mut position, 0)?;
+        if position < lines.len() {
+            return Err(ExtractionError::ParseError(format!(
+                "Unexpected YAML line: {}",
+                lines[position].1.trim()
+            )));
+        }
+        Ok(coerce(value, schema, schema))
+    }
+}
+
+/// The value of the block of `lines` starting at `position`, indented by at least `indent`.
+fn yaml_block(
+    lines: &[(usize, &str)],
+    position: &mut usize,
+    indent: usize,
+) -> Result<Value, ExtractionError> {
+    let Some(&(block_indent, line)) = lines.get(*position) else {
+        return Ok(Value::Null);
+    };
+    if block_indent < indent {
+        return Ok(Value::Null);
+    }
+    let line = line.trim_start();
+
+    if line == "-" || line.starts_with("- ") {
+        let mut items = vec![];
+        while let Some(&(item_indent, item)) = lines.get(*position) {
+            let item = item.trim_start();
+            if item_indent != block_indent || !(item == "-" || item.starts_with("- ")) {
+                break;
+            }
+            let rest = item[1..].trim_start();
+            if rest.is_empty() {
+                *position += 1;
+                items.push(yaml_block(lines, position, block_indent + 1)?);
+            } else if yaml_key(rest).is_some() {
+                // A mapping item, whose keys are aligned after the dash
+                let offset = item_indent + (item.len() - rest.len());
+                let mut item_lines = lines.to_vec();
+                item_lines[*position] = (offset, rest);
+                let mut item_position = *position;
+                items.push(yaml_block(&item_lines, &mut item_position, offset)?);
+                *position = item_position;
+            } else {
+                *position += 1;
+                items.push(yaml_scalar(rest));
+            }
+        }
+        return Ok(Value::Array(items));
+    }
+
+    if yaml_key(line).is_some() {
+        let mut object = Map::new();
+        while let Some(&(key_indent, entry)) = lines.get(*position) {
+            let Some((key, rest)) = yaml_key(entry.trim_start()) else {
+                break;
+            };
+            if key_indent != block_indent {
+                break;
+            }
+            *position += 1;
+            let value = match rest {
+                "" => match lines.get(*position) {
+                    // The sequences of a mapping can be indented like its keys
+                    Some(&(next_indent, next))
+                        if next_indent == block_indent && next.trim_start().starts_with('-') =>
+                    {
+                        yaml_block(lines, position, block_indent)?
+                    }
+                    _ => yaml_block(lines, position, block_indent + 1)?,
+                },
+                "|" | "|-" | ">" | ">-" => {
+                    let mut text = vec![];
+                    while let Some(&(text_indent, text_line)) = lines.get(*position) {
+                        if text_indent <= block_indent {
+                            break;
+                        }
+                        text.push(text_line.trim_start());
+                        *position += 1;
+                    }
+                    let separator = if rest.starts_with('|') { "\n" } else { " " };
+                    Value::String(text.join(separator))
+                }
+                rest => yaml_scalar(rest),
+            };
+            object.insert(key, value);
+        }
+        return Ok(Value::Object(object));
+    }
+
+    *position += 1;
+    Ok(yaml_scalar(line))
+}
+
+/// The key and the rest of a `key: value` line, if it is one.
+fn yaml_key(line: &str) -> Option<(String, &str)> {
+    let (key, rest) = if let Some(quoted) = line.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        (
+            quoted[..end].to_string(),
+            quoted[end + 1..].strip_prefix(':')?,
+        )
+    } else {
+        let end = line
+            .find(": ")
+            .or_else(|| line.ends_with(':').then(|| line.len() - 1))?;
+        let key = &line[..end];
+        if key.is_empty() || key.starts_with(['[', '{', '\'']) {
+            return None;
+        }
+        (key.trim().to_string(), &line[end + 1..])
+    };
+    (rest.is_empty() || rest.starts_with(' ')).then(|| (key, rest.trim()))
+}
+
+fn yaml_scalar(text: &str) -> Value {
+    let text = match text.find(" #") {
+        Some(comment) if !text.starts_with(['"', '\'']) => text[..comment].trim_end(),
+        _ => text,
+    };
+    if let Some(quoted) = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+    {
+        return Value::String(quoted.replace("\\\"", "\"").replace("\\n", "\n"));
+    }
+    if let Some(quoted) = text
+        .strip_prefix('\'')
+        .and_then(|text| text.strip_suffix('\''))
+    {
+        return Value::String(quoted.replace("''", "'"));
+    }
+    if let Some(items) = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+    {
+        return Value::Array(
+            items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(yaml_scalar)
+                .collect(),
+        );
+    }
+    match text {
+        "null" | "~" | "" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => text
+            .parse::<i64>()
+            .map(Value::from)
+            .ok()
+            .or_else(|| {
+                text.parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+            })
+            .unwrap_or_else(|| Value::String(text.to_string())),
+    }
+}
+
+/// The content of the code block `language` of `text` (or of its first code block), or `text`.
+fn strip_code_block<'a>(text: &'a str, language: &str) -> &'a str {
+    let text = text.trim();
+    let Some(start) = text.find("```") else {
+        return text;
+    };
+    let block = &text[start + 3..];
+    let block = block.strip_prefix(language).unwrap_or(block);
+    let block =
+        block.split_once('\n').map_or(
+            block,
+            |(first, rest)| {
+                if first.trim().is_empty() {
+                    rest
+                } else {
+                    block
+                }
+            },
+        );
+    block.split("```").next().unwrap_or(block).trim()
+}
+
+/// Convert the scalars of `value` to the types of `schema` (a subschema of `root`, for its
+/// references), when they differ.
+pub(crate) fn coerce(value: Value, schema: &Value, root: &Value) -> Value {
+    let schema = resolve(schema, root);
+    for key in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(schemas)) = schema.get(key) {
+            // The first variant accepting the value, e.g.: the non-null variant of an option
+            return schemas
+                .iter()
+                .map(|schema| coerce(value.clone(), schema, root))
+                .find(|coerced| matches_type(coerced, resolve(schema, root)))
+                .unwrap_or(value);
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        return schemas
+            .iter()
+            .fold(value, |value, schema| coerce(value, schema, root));
+    }
+
+    let types = match schema.get("type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => return value,
+    };
+    if value.is_null() || types.iter().any(|ty| matches_type_name(&value, ty)) {
+        return match value {
+            Value::Object(object) if types.contains(&"object") => {
+                coerce_object(object, schema, root)
+            }
+            Value::Array(items) if types.contains(&"array") => coerce_items(items, schema, root),
+            value => value,
+        };
+    }
+
+    for ty in types {
+        let coerced = match (ty, &value) {
+            ("null", Value::String(text)) if text.is_empty() || text == "null" => Some(Value::Null),
+            ("integer", Value::String(text)) => text.trim().parse::<i64>().ok().map(Value::from),
+            ("number", Value::String(text)) => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            ("boolean", Value::String(text)) => match text.trim() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            ("string", Value::Number(number)) => Some(Value::String(number.to_string())),
+            ("string", Value::Bool(boolean)) => Some(Value::String(boolean.to_string())),
+            // The lists of a single `<item>` element, or the empty ones
+            ("array", Value::Object(object)) if object.len() <= 1 => {
+                let items = match object.values().next().cloned() {
+                    Some(Value::Array(items)) => items,
+                    Some(item) => vec![item],
+                    None => vec![],
+                };
+                Some(coerce_items(items, schema, root))
+            }
+            ("array", Value::String(text)) if text.is_empty() => Some(Value::Array(vec![])),
+            ("array", value) => Some(coerce_items(vec![value.clone()], schema, root)),
+            ("object", Value::String(text)) if text.is_empty() => {
+                Some(coerce_object(Map::new(), schema, root))
+            }
+            _ => None,
+        };
+        if let Some(coerced) = coerced {
+            return coerced;
+        }
+    }
+    value
+}
+
+fn coerce_object(object: Map<String, Value>, schema: &Value, root: &Value) -> Value {
+    let properties = schema.get("properties");
+    Value::Object(
+        object
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match properties.and_then(|properties| properties.get(&key)) {
+                    Some(property) => coerce(value, property, root),
+                    None => match schema.get("additionalProperties") {
+                        Some(additional @ Value::Object(_)) => coerce(value, additional, root),
+                        _ => value,
+                    },
+                };
+                (key, value)
+            })
+            .collect(),
+    )
+}
+
+fn coerce_items(items: Vec<Value>, schema: &Value, root: &Value) -> Value {
+    Value::Array(match schema.get("items") {
+        Some(item_schema) => items
+            .into_iter()
+            .map(|item| coerce(item, item_schema, root))
+            .collect(),
+        None => items,
+    })
+}
+
+/// The schema referenced by `schema` (in the definitions of `root`), if it is a reference.
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .map(|schema| resolve(schema, root))
+            .unwrap_or(schema),
+        None => schema,
+    }
+}
+
+fn matches_type(value: &Value, schema: &Value) -> bool {
+    if let Some(Value::Array(values)) = schema.get("enum") {
+        return values.contains(value);
+    }
+    if let Some(constant) = schema.get("const") {
+        return constant == value;
+    }
+    match schema.get("type") {
+        Some(Value::String(ty)) => matches_type_name(value, ty),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|ty| matches_type_name(value, ty)),
+        _ => true,
+    }
+}
+
+fn matches_type_name(value: &Value, ty: &str) -> bool {
+    match (ty, value) {
+        ("null", Value::Null) | ("boolean", Value::Bool(_)) | ("string", Value::String(_)) => true,
+        ("integer", Value::Number(number)) => number.is_i64() || number.is_u64(),
+        ("number", Value::Number(_)) => true,
+        ("array", Value::Array(_)) | ("object", Value::Object(_)) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::{schema_for, JsonSchema};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize, JsonSchema)]
+    struct Order {
+        id: u32,
+        customer: Option<String>,
+        express: bool,
+        items: Vec<Item>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, JsonSchema)]
+    struct Item {
+        name: String,
+        quantity: f64,
+    }
+
+    fn order() -> Order {
+        Order {
+            id: 42,
+            customer: None,
+            express: true,
+            items: vec![Item {
+                name: "tea & biscuits".to_string(),
+                quantity: 2.0,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let schema = json!(schema_for!(Order));
+
+        let xml = "Here is the order:\n<data>\n  <id>42</id>\n  \
+            <express>true</express>\n  <items>\n    <item><name>tea & biscuits</name>\
+            <quantity>2</quantity></item>\n  </items>\n</data>";
+        let value = XmlFormat::new().parse(xml, &schema).unwrap();
+        assert_eq!(serde_json::from_value::<Order>(value).unwrap(), order());
+
+        let yaml = "```yaml\nid: 42\ncustomer: null\nexpress: true\nitems:\n- name: \
+            \"tea & biscuits\"\n  quantity: 2\n```";
+        let value = YamlFormat.parse(yaml, &schema).unwrap();
+        assert_eq!(serde_json::from_value::<Order>(value).unwrap(), order());
+
+        let json = r#"{"id": "42", "customer": null, "express": true, "items": [{"name": "tea & biscuits", "quantity": 2}]}"#;
+        let value = JsonFormat.parse(json, &schema).unwrap();
+        assert_eq!(serde_json::from_value::<Order>(value).unwrap(), order());
+
+        assert_eq!(
+            YamlFormat
+                .parse("notes: |\n  first\n  second\ntags: [a, b]\n", &json!({}))
+                .unwrap(),
+            json!({"notes": "first\nsecond", "tags": ["a", "b"]})
+        );
+        assert!(XmlFormat::new().parse("<order></order>", &schema).is_err());
+    }
+}
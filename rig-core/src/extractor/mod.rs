@@ -2,6 +2,10 @@
 //!
 //! Note: The target structure must implement the `serde::Deserialize`, `serde::Serialize`,
 //! and `schemars::JsonSchema` traits. Those can be easily derived using the `derive` macro.
+//! The target can be any type with a schema (e.g.: an enum with data, or a struct with nested
+//! structs, lists and options), whose doc comments describe its fields to the model (see the
+//! [schema] module). With the `derive` feature, the fields can also be given descriptions and
+//! examples with the `#[rig(desc = "...", example = "...")]` attributes of `rig_derive::rig_schema`.
 //!
 //! # Example
 //! ```
@@ -27,12 +31,26 @@
 //!     .await
 //!     .expect("Failed to extract data from text");
 //! ```
+//!
+//! By default, the data is submitted by the model with a tool call. The models following other
+//! formats more reliably can answer with the data as XML or YAML instead (see
+//! [ExtractorBuilder::format] and the [format] module):
+//! ```
+//! use rig::extractor::format::XmlFormat;
+//!
+//! let extractor = openai.extractor::<Person>(openai::GPT_4O)
+//!     .format(XmlFormat::new())
+//!     .build();
+//! ```
 
-use std::marker::PhantomData;
+pub mod format;
+pub mod schema;
 
-use schemars::{schema_for, JsonSchema};
+use std::{marker::PhantomData, sync::Arc};
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::Value;
 
 use crate::{
     agent::{Agent, AgentBuilder},
@@ -41,8 +59,21 @@ use crate::{
     tool::Tool,
 };
 
+use format::OutputFormat;
+
 const SUBMIT_TOOL_NAME: &str = "submit";
 
+const SUBMIT_PREAMBLE: &str = "\
+    You are an AI assistant whose purpose is to extract structured data from the provided text.\n\
+    You will have access to a `submit` function that defines the structure of the data to extract from the provided text.\n\
+    Use the `submit` function to submit the structured data.\n\
+    Be sure to fill out every field and ALWAYS CALL THE `submit` function, even with default values!!!.
+";
+
+const FORMAT_PREAMBLE: &str = "\
+    You are an AI assistant whose purpose is to extract structured data from the provided text.\n\
+    Be sure to fill out every field, even with default values.\n";
+
 #[derive(Debug, thiserror::Error)]
 pub enum ExtractionError {
     #[error("No data extracted")]
@@ -53,11 +84,16 @@ pub enum ExtractionError {
 
     #[error("CompletionError: {0}")]
     CompletionError(#[from] CompletionError),
+
+    #[error("Failed to parse the extracted data: {0}")]
+    ParseError(String),
 }
 
 /// Extractor for structured data from text
 pub struct Extractor<M: CompletionModel, T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync> {
     agent: Agent<M>,
+    format: Option<Arc<dyn OutputFormat>>,
+    schema: Value,
     _t: PhantomData<T>,
 }
 
@@ -68,6 +104,22 @@ where
     pub async fn extract(&self, text: impl Into<Message> + Send) -> Result<T, ExtractionError> {
         let response = self.agent.completion(text, vec![]).await?.send().await?;
 
+        if let Some(format) = &self.format {
+            let text = response
+                .choice
+                .into_iter()
+                .filter_map(|content| match content {
+                    AssistantContent::Text(text) => Some(text.text),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            if text.trim().is_empty() {
+                return Err(ExtractionError::NoData);
+            }
+            return Ok(serde_json::from_value(format.parse(&text, &self.schema)?)?);
+        }
+
         let arguments = response
             .choice
             .into_iter()
@@ -100,6 +152,17 @@ where
         } else {
             return Err(ExtractionError::NoData);
         };
+        // The values which are not objects are submitted wrapped in an object
+        let raw_data = if schema::is_object(&self.schema) {
+            raw_data
+        } else {
+            match raw_data {
+                Value::Object(mut object) => object
+                    .remove(schema::VALUE_FIELD)
+                    .ok_or(ExtractionError::NoData)?,
+                raw_data => raw_data,
+            }
+        };
 
         Ok(serde_json::from_value(raw_data)?)
     }
@@ -111,6 +174,8 @@ pub struct ExtractorBuilder<
     M: CompletionModel,
 > {
     agent_builder: AgentBuilder<M>,
+    instructions: Vec<String>,
+    format: Option<Arc<dyn OutputFormat>>,
     _t: PhantomData<T>,
 }
 
@@ -119,23 +184,24 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync, M: Compl
 {
     pub fn new(model: M) -> Self {
         Self {
-            agent_builder: AgentBuilder::new(model)
-                .preamble("\
-                    You are an AI assistant whose purpose is to extract structured data from the provided text.\n\
-                    You will have access to a `submit` function that defines the structure of the data to extract from the provided text.\n\
-                    Use the `submit` function to submit the structured data.\n\
-                    Be sure to fill out every field and ALWAYS CALL THE `submit` function, even with default values!!!.
-                ")
-                .tool(SubmitTool::<T> {_t: PhantomData}),
+            agent_builder: AgentBuilder::new(model),
+            instructions: vec![],
+            format: None,
             _t: PhantomData,
         }
     }
 
     /// Add additional preamble to the extractor
     pub fn preamble(mut self, preamble: &str) -> Self {
-        self.agent_builder = self.agent_builder.append_preamble(&format!(
-            "\n=============== ADDITIONAL INSTRUCTIONS ===============\n{preamble}"
-        ));
+        self.instructions.push(preamble.to_string());
+        self
+    }
+
+    /// Have the model answer with the data as text in the format `format` (e.g.:
+    /// [XmlFormat](format::XmlFormat) or [YamlFormat](format::YamlFormat)), instead of
+    /// submitting it with a tool call.
+    pub fn format(mut self, format: impl OutputFormat + 'static) -> Self {
+        self.format = Some(Arc::new(format));
         self
     }
 
@@ -147,8 +213,27 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync, M: Compl
 
     /// Build the Extractor
     pub fn build(self) -> Extractor<M, T> {
+        let schema = schema::schema_for::<T>();
+        let mut agent_builder = match &self.format {
+            Some(format) => self.agent_builder.preamble(&format!(
+                "{FORMAT_PREAMBLE}{}",
+                format.instructions(&schema)
+            )),
+            None => self
+                .agent_builder
+                .preamble(SUBMIT_PREAMBLE)
+                .tool(SubmitTool::<T> { _t: PhantomData }),
+        };
+        for instructions in self.instructions {
+            agent_builder = agent_builder.append_preamble(&format!(
+                "\n=============== ADDITIONAL INSTRUCTIONS ===============\n{instructions}"
+            ));
+        }
+
         Extractor {
-            agent: self.agent_builder.build(),
+            agent: agent_builder.build(),
+            format: self.format,
+            schema,
             _t: PhantomData,
         }
     }
@@ -174,7 +259,10 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync> Tool for
             name: Self::NAME.to_string(),
             description: "Submit the structured data you extracted from the provided text."
                 .to_string(),
-            parameters: json!(schema_for!(T)),
+            parameters: match schema::schema_for::<T>() {
+                parameters if schema::is_object(&parameters) => parameters,
+                parameters => schema::wrap(parameters),
+            },
         }
     }
 
@@ -0,0 +1,232 @@
+//! JSON schemas of the extracted types, as accepted by the providers.
+//!
+//! The schemas are generated by [schemars] from the derived [JsonSchema] implementations, so the
+//! enums (with or without data), the lists and options of nested structs and the descriptions of
+//! the doc comments are supported. Their references to the definitions of the nested types,
+//! which most providers do not resolve, are inlined (except the references of the recursive
+//! types, which cannot be).
+
+use std::collections::BTreeSet;
+
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+/// Field of the object wrapping the extracted values which are not objects (e.g.: enums or
+/// lists), since the arguments of the tool calls must be objects.
+pub(crate) const VALUE_FIELD: &str = "value";
+
+/// The JSON schema of `T`, with the references to its non-recursive definitions inlined.
+pub fn schema_for<T: JsonSchema>() -> Value {
+    inline_definitions(
+        serde_json::to_value(schemars::schema_for!(T)).expect("Schema should serialize"),
+    )
+}
+
+/// Inline the references of `schema` to its `definitions`. The references to the recursive
+/// definitions are kept, with the definitions they reference.
+pub fn inline_definitions(mut schema: Value) -> Value {
+    let Some(root) = schema.as_object_mut() else {
+        return schema;
+    };
+    root.remove("$schema");
+    let definitions = match root.remove("definitions") {
+        Some(Value::Object(definitions)) => definitions,
+        _ => Map::new(),
+    };
+    // The root schema is the definition of the root type, if it is referenced
+    let mut stack = root
+        .get("title")
+        .and_then(Value::as_str)
+        .filter(|title| definitions.contains_key(*title))
+        .map(|title| vec![title.to_string()])
+        .unwrap_or_default();
+
+    let mut recursive = BTreeSet::new();
+    let mut schema = inline(schema, &definitions, &mut stack, &mut recursive);
+
+    let mut kept = Map::new();
+    while let Some(name) = recursive
+        .iter()
+        .find(|name| !kept.contains_key(*name))
+        .cloned()
+    {
+        let definition = inline(
+            definitions[&name].clone(),
+            &definitions,
+            &mut vec![name.clone()],
+            &mut recursive,
+        );
+        kept.insert(name, definition);
+    }
+    if !kept.is_empty() {
+        schema["definitions"] = Value::Object(kept);
+    }
+    schema
+}
+
+fn inline(
+    value: Value,
+    definitions: &Map<String, Value>,
+    stack: &mut Vec<String>,
+    recursive: &mut BTreeSet<String>,
+) -> Value {
+    match value {
+        Value::Object(mut object) => {
+            let name = object
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| reference.strip_prefix("#/definitions/"))
+                .filter(|name| definitions.contains_key(*name))
+                .map(str::to_string);
+            if let Some(name) = name {
+                if stack.contains(&name) {
+                    recursive.insert(name);
+                    return Value::Object(object);
+                }
+
+                stack.push(name.clone());
+                let definition = inline(definitions[&name].clone(), definitions, stack, recursive);
+                stack.pop();
+                object.remove("$ref");
+                return merge(definition, object);
+            }
+
+            let mut object = object
+                .into_iter()
+                .map(|(key, value)| (key, inline(value, definitions, stack, recursive)))
+                .collect::<Map<_, _>>();
+            // The subschemas of the described fields of nested types (e.g.: `{"description":
+            // "...", "allOf": [{"$ref": "..."}]}`) are merged into the fields
+            match object.remove("allOf") {
+                Some(Value::Array(mut schemas)) if schemas.len() == 1 => {
+                    merge(schemas.remove(0), object)
+                }
+                Some(schemas) => {
+                    object.insert("allOf".to_string(), schemas);
+                    Value::Object(object)
+                }
+                None => Value::Object(object),
+            }
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| inline(value, definitions, stack, recursive))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// `schema`, with the keywords of `overrides` (e.g.: the description of a field).
+fn merge(schema: Value, overrides: Map<String, Value>) -> Value {
+    match schema {
+        Value::Object(mut schema) => {
+            schema.extend(overrides);
+            Value::Object(schema)
+        }
+        schema if overrides.is_empty() => schema,
+        // Boolean schemas
+        schema => {
+            let mut overrides = overrides;
+            overrides.insert("allOf".to_string(), json!([schema]));
+            Value::Object(overrides)
+        }
+    }
+}
+
+/// Whether the values of `schema` are objects.
+pub(crate) fn is_object(schema: &Value) -> bool {
+    schema.get("type").and_then(Value::as_str) == Some("object")
+}
+
+/// The schema of the object wrapping the values of `schema` in its [VALUE_FIELD] field.
+pub(crate) fn wrap(mut schema: Value) -> Value {
+    let definitions = schema
+        .as_object_mut()
+        .and_then(|schema| schema.remove("definitions"));
+    let mut wrapper = json!({
+        "type": "object",
+        "properties": { VALUE_FIELD: schema },
+        "required": [VALUE_FIELD],
+    });
+    // The references are relative to the root schema
+    if let Some(definitions) = definitions {
+        wrapper["definitions"] = definitions;
+    }
+    wrapper
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    /// An invoice
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Invoice {
+        /// The client billed
+        client: Client,
+        lines: Vec<Line>,
+        payment: Option<Payment>,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Client {
+        name: String,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Line {
+        product: String,
+        /// The lines of the products of a bundle
+        parts: Vec<Line>,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    enum Payment {
+        Cash,
+        Card { last_digits: String },
+    }
+
+    #[test]
+    fn test_schema_for() {
+        let schema = schema_for::<Invoice>();
+        assert!(schema.get("$schema").is_none());
+        assert_eq!(
+            schema["properties"]["client"],
+            json!({
+                "description": "The client billed",
+                "type": "object",
+                "properties": { "name": { "type": "string" } },
+                "required": ["name"],
+            })
+        );
+        assert_eq!(
+            schema["properties"]["payment"]["anyOf"][0]["oneOf"][1]["properties"]["Card"]
+                ["properties"]["last_digits"],
+            json!({ "type": "string" })
+        );
+
+        // The recursive definitions are referenced
+        assert_eq!(
+            schema["properties"]["lines"]["items"]["properties"]["parts"]["items"],
+            json!({ "$ref": "#/definitions/Line" })
+        );
+        assert_eq!(
+            schema["definitions"]["Line"]["properties"]["parts"]["items"],
+            json!({ "$ref": "#/definitions/Line" })
+        );
+        assert_eq!(schema["definitions"].as_object().unwrap().len(), 1);
+
+        let schema = schema_for::<Payment>();
+        assert!(!is_object(&schema));
+        let wrapped = wrap(schema.clone());
+        assert_eq!(wrapped["properties"][VALUE_FIELD], schema);
+    }
+}
@@ -0,0 +1,669 @@
+//! This module provides output guardrails for agents (or any type implementing [Chat]).
+//!
+//! A [GuardedAgent] runs every response of the wrapped agent through a chain of [Validator]s.
+//! The module provides the following validators out of the box:
+//! - [RegexValidator]: the response must (or must not) match a regex
+//! - [JsonSchemaValidator]: the response must be a JSON value matching a JSON schema
+//! - [FnValidator]: custom validation closure
+//! - [ModelValidator]: a model (e.g.: another agent) checks the response against a set of criteria
+//!
+//! When a response violates one or more validators, the [OnFailure] policy decides whether
+//! an error is returned, or whether the model is re-prompted with the description of the
+//! violations so it can correct its answer.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     completion::Prompt,
+//!     guardrails::{GuardedAgent, JsonSchemaValidator, OnFailure, RegexValidator},
+//!     providers::openai,
+//! };
+//!
+//! #[derive(serde::Deserialize, schemars::JsonSchema)]
+//! struct Answer {
+//!     answer: String,
+//!     confidence: f64,
+//! }
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("Answer in JSON with the fields `answer` and `confidence`.")
+//!     .build();
+//!
+//! let guarded = GuardedAgent::new(agent)
+//!     .validator(JsonSchemaValidator::for_type::<Answer>())
+//!     .validator(RegexValidator::must_not_match(r"(?i)as an ai").unwrap())
+//!     .on_failure(OnFailure::Reask { max_attempts: 2 });
+//!
+//! let response = guarded.prompt("What is the capital of France?").await?;
+//! ```
+
+use regex::Regex;
+use schemars::{schema_for, JsonSchema};
+use serde_json::Value;
+
+use crate::completion::{Chat, Message, Prompt, PromptError};
+use crate::wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync};
+
+/// A violation of a validator by a response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// Name of the validator that was violated
+    pub validator: String,
+    /// Description of the violation. This description is sent back to the model
+    /// when re-asking, so it should be actionable.
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(validator: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            validator: validator.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.validator, self.message)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Response violated {} guardrail(s): {}", violations.len(), violations.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("; "))]
+pub struct GuardrailError {
+    /// Violations of the last response
+    pub violations: Vec<Violation>,
+    /// Last response returned by the model
+    pub response: String,
+}
+
+/// Trait for validators that check the responses of a model.
+pub trait Validator: WasmCompatSend + WasmCompatSync {
+    /// Name of the validator, used in violation reports.
+    fn name(&self) -> String;
+
+    /// Validate the response, returning a [Violation] if the response is not valid.
+    fn validate<'a>(&'a self, response: &'a str) -> WasmBoxedFuture<'a, Result<(), Violation>>;
+}
+
+/// Validator that checks the response against a regex.
+pub struct RegexValidator {
+    pattern: Regex,
+    must_match: bool,
+}
+
+impl RegexValidator {
+    /// The response must contain a match of `pattern`.
+    pub fn must_match(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            must_match: true,
+        })
+    }
+
+    /// The response must not contain any match of `pattern`.
+    pub fn must_not_match(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            must_match: false,
+        })
+    }
+}
+
+impl Validator for RegexValidator {
+    fn name(&self) -> String {
+        "regex".to_string()
+    }
+
+    fn validate<'a>(&'a self, response: &'a str) -> WasmBoxedFuture<'a, Result<(), Violation>> {
+        Box::pin(async move {
+            match (self.must_match, self.pattern.find(response)) {
+                (true, None) => Err(Violation::new(
+                    self.name(),
+                    format!(
+                        "The response must match the pattern `{}`",
+                        self.pattern.as_str()
+                    ),
+                )),
+                (false, Some(m)) => Err(Violation::new(
+                    self.name(),
+                    format!("The response must not contain `{}`", m.as_str()),
+                )),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+/// Validator that checks that the response is a JSON value matching a JSON schema.
+/// Markdown code fences around the JSON value are ignored.
+///
+/// Supported schema keywords: `type`, `enum`, `const`, `properties`, `required`,
+/// `additionalProperties`, `items`, `minItems`, `maxItems`, `minLength`, `maxLength`,
+/// `minimum`, `maximum`, `anyOf`, `oneOf`, `allOf` and local `$ref`s.
+pub struct JsonSchemaValidator {
+    schema: Value,
+}
+
+impl JsonSchemaValidator {
+    pub fn new(schema: Value) -> Self {
+        Self { schema }
+    }
+
+    /// Create a validator from the JSON schema of the type `T`.
+    pub fn for_type<T: JsonSchema>() -> Self {
+        Self::new(serde_json::to_value(schema_for!(T)).expect("Schema should serialize"))
+    }
+
+    fn resolve<'a>(&'a self, schema: &'a Value) -> &'a Value {
+        match schema.get("$ref").and_then(Value::as_str) {
+            Some(reference) => reference
+                .strip_prefix("#/")
+                .map(|path| {
+                    path.split('/')
+                        .try_fold(&self.schema, |schema, key| schema.get(key))
+                })
+                .and_then(|schema| schema)
+                .unwrap_or(schema),
+            None => schema,
+        }
+    }
+
+    fn check(&self, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+        let schema = self.resolve(schema);
+        let path_or_root = if path.is_empty() { "$" } else { path };
+
+        if let Some(types) = schema.get("type") {
+            let types = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                Value::String(t) => vec![t.as_str()],
+                _ => vec![],
+            };
+            if !types.iter().any(|t| json_type_matches(t, value)) {
+                return Err(format!(
+                    "`{path_or_root}` must be of type {}",
+                    types.join(" or ")
+                ));
+            }
+        }
+
+        if let Some(variants) = schema.get("enum").and_then(Value::as_array) {
+            if !variants.contains(value) {
+                return Err(format!(
+                    "`{path_or_root}` must be one of {}",
+                    Value::Array(variants.clone())
+                ));
+            }
+        }
+
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                return Err(format!("`{path_or_root}` must be equal to {constant}"));
+            }
+        }
+
+        for keyword in ["anyOf", "oneOf"] {
+            if let Some(schemas) = schema.get(keyword).and_then(Value::as_array) {
+                if !schemas.iter().any(|s| self.check(s, value, path).is_ok()) {
+                    return Err(format!(
+                        "`{path_or_root}` does not match any of the allowed schemas"
+                    ));
+                }
+            }
+        }
+
+        if let Some(schemas) = schema.get("allOf").and_then(Value::as_array) {
+            for s in schemas {
+                self.check(s, value, path)?;
+            }
+        }
+
+        match value {
+            Value::Object(map) => {
+                if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                    for key in required.iter().filter_map(Value::as_str) {
+                        if !map.contains_key(key) {
+                            return Err(format!("`{path}.{key}` is required"));
+                        }
+                    }
+                }
+
+                let properties = schema.get("properties").and_then(Value::as_object);
+                for (key, field) in map {
+                    let field_path = format!("{path}.{key}");
+                    match properties.and_then(|properties| properties.get(key)) {
+                        Some(field_schema) => self.check(field_schema, field, &field_path)?,
+                        None => match schema.get("additionalProperties") {
+                            Some(Value::Bool(false)) => {
+                                return Err(format!("`{field_path}` is not allowed"))
+                            }
+                            Some(additional @ Value::Object(_)) => {
+                                self.check(additional, field, &field_path)?
+                            }
+                            _ => (),
+                        },
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                    if (items.len() as u64) < min {
+                        return Err(format!("`{path_or_root}` must have at least {min} items"));
+                    }
+                }
+                if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                    if (items.len() as u64) > max {
+                        return Err(format!("`{path_or_root}` must have at most {max} items"));
+                    }
+                }
+                if let Some(item_schema) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        self.check(item_schema, item, &format!("{path}[{i}]"))?;
+                    }
+                }
+            }
+            Value::String(s) => {
+                let len = s.chars().count() as u64;
+                if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                    if len < min {
+                        return Err(format!(
+                            "`{path_or_root}` must be at least {min} characters long"
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                    if len > max {
+                        return Err(format!(
+                            "`{path_or_root}` must be at most {max} characters long"
+                        ));
+                    }
+                }
+            }
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                    if n < min {
+                        return Err(format!("`{path_or_root}` must be >= {min}"));
+                    }
+                }
+                if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                    if n > max {
+                        return Err(format!("`{path_or_root}` must be <= {max}"));
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+fn json_type_matches(t: &str, value: &Value) -> bool {
+    match t {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Strip the markdown code fences (e.g.: ```` ```json ````) surrounding `text`, if any.
+pub(crate) fn strip_code_fences(text: &str) -> &str {
+    let trimmed = text.trim();
+    match trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+    {
+        Some(inner) => inner
+            .split_once('\n')
+            .map(|(_, body)| body)
+            .unwrap_or(inner)
+            .trim(),
+        None => trimmed,
+    }
+}
+
+impl Validator for JsonSchemaValidator {
+    fn name(&self) -> String {
+        "json_schema".to_string()
+    }
+
+    fn validate<'a>(&'a self, response: &'a str) -> WasmBoxedFuture<'a, Result<(), Violation>> {
+        Box::pin(async move {
+            let value: Value = serde_json::from_str(strip_code_fences(response)).map_err(|e| {
+                Violation::new(
+                    self.name(),
+                    format!("The response must be valid JSON ({e})"),
+                )
+            })?;
+
+            self.check(&self.schema, &value, "")
+                .map_err(|message| Violation::new(self.name(), message))
+        })
+    }
+}
+
+/// Validator defined by a closure returning an error message on failure.
+pub struct FnValidator<F> {
+    name: String,
+    f: F,
+}
+
+impl<F> FnValidator<F>
+where
+    F: Fn(&str) -> Result<(), String> + Send + Sync,
+{
+    pub fn new(name: &str, f: F) -> Self {
+        Self {
+            name: name.to_string(),
+            f,
+        }
+    }
+}
+
+impl<F> Validator for FnValidator<F>
+where
+    F: Fn(&str) -> Result<(), String> + Send + Sync,
+{
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn validate<'a>(&'a self, response: &'a str) -> WasmBoxedFuture<'a, Result<(), Violation>> {
+        Box::pin(async move { (self.f)(response).map_err(|e| Violation::new(self.name(), e)) })
+    }
+}
+
+/// Validator that asks a model (e.g.: an agent) whether the response satisfies some criteria.
+/// The model is expected to answer `PASS`, or `FAIL: <reason>`.
+pub struct ModelValidator<P: Prompt> {
+    judge: P,
+    criteria: String,
+}
+
+impl<P: Prompt> ModelValidator<P> {
+    pub fn new(judge: P, criteria: &str) -> Self {
+        Self {
+            judge,
+            criteria: criteria.to_string(),
+        }
+    }
+}
+
+impl<P: Prompt> Validator for ModelValidator<P> {
+    fn name(&self) -> String {
+        "model".to_string()
+    }
+
+    fn validate<'a>(&'a self, response: &'a str) -> WasmBoxedFuture<'a, Result<(), Violation>> {
+        Box::pin(async move {
+            let verdict = self
+                .judge
+                .prompt(format!(
+                    "Check whether the following response satisfies the criteria.\n\
+                    Answer with `PASS` if it does, or `FAIL: <reason>` if it does not.\n\n\
+                    <criteria>\n{}\n</criteria>\n\n<response>\n{}\n</response>",
+                    self.criteria, response
+                ))
+                .await
+                .map_err(|e| Violation::new(self.name(), format!("Validation failed: {e}")))?;
+
+            let verdict = verdict.trim();
+            if verdict.to_uppercase().starts_with("PASS") {
+                Ok(())
+            } else {
+                let reason = verdict
+                    .split_once(':')
+                    .map(|(_, reason)| reason.trim())
+                    .unwrap_or(verdict);
+                Err(Violation::new(self.name(), reason))
+            }
+        })
+    }
+}
+
+/// Policy applied when a response violates one or more validators.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OnFailure {
+    /// Return a [GuardrailError]
+    #[default]
+    Error,
+    /// Re-prompt the model with the description of the violations, at most
+    /// `max_attempts` times, before returning a [GuardrailError]
+    Reask { max_attempts: usize },
+}
+
+/// Wrapper around an agent (or any type implementing [Chat]) that validates its responses.
+pub struct GuardedAgent<A: Chat> {
+    agent: A,
+    validators: Vec<Box<dyn Validator>>,
+    on_failure: OnFailure,
+}
+
+impl<A: Chat> GuardedAgent<A> {
+    pub fn new(agent: A) -> Self {
+        Self {
+            agent,
+            validators: vec![],
+            on_failure: OnFailure::default(),
+        }
+    }
+
+    /// Add a validator to the chain. Validators are run in the order they are added.
+    pub fn validator(mut self, validator: impl Validator + 'static) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    /// Set the policy applied when a response is not valid
+    pub fn on_failure(mut self, on_failure: OnFailure) -> Self {
+        self.on_failure = on_failure;
+        self
+    }
+
+    /// Run all validators on `response` and collect the violations.
+    pub async fn violations(&self, response: &str) -> Vec<Violation> {
+        let mut violations = vec![];
+        for validator in &self.validators {
+            if let Err(violation) = validator.validate(response).await {
+                violations.push(violation);
+            }
+        }
+        violations
+    }
+
+    async fn guarded_chat(
+        &self,
+        prompt: Message,
+        mut chat_history: Vec<Message>,
+    ) -> Result<String, PromptError> {
+        let max_attempts = match self.on_failure {
+            OnFailure::Error => 0,
+            OnFailure::Reask { max_attempts } => max_attempts,
+        };
+
+        let mut prompt = prompt;
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .agent
+                .chat(prompt.clone(), chat_history.clone())
+                .await?;
+
+            let violations = self.violations(&response).await;
+            if violations.is_empty() {
+                return Ok(response);
+            }
+
+            if attempt >= max_attempts {
+                return Err(GuardrailError {
+                    violations,
+                    response,
+                }
+                .into());
+            }
+
+            attempt += 1;
+            tracing::info!(target: "rig",
+                "Response violated {} guardrail(s), re-asking ({}/{})",
+                violations.len(),
+                attempt,
+                max_attempts
+            );
+
+            chat_history.push(prompt);
+            chat_history.push(Message::assistant(response));
+            prompt = Message::user(format!(
+                "Your previous response is not valid:\n{}\n\nPlease answer again, fixing these issues.",
+                violations
+                    .iter()
+                    .map(|violation| format!("- {}", violation.message))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+    }
+}
+
+#[allow(refining_impl_trait)]
+impl<A: Chat> Prompt for GuardedAgent<A> {
+    async fn prompt(&self, prompt: impl Into<Message> + Send) -> Result<String, PromptError> {
+        self.guarded_chat(prompt.into(), vec![]).await
+    }
+}
+
+#[allow(refining_impl_trait)]
+impl<A: Chat> Chat for GuardedAgent<A> {
+    async fn chat(
+        &self,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<String, PromptError> {
+        self.guarded_chat(prompt.into(), chat_history).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// Mock agent answering with the responses in order
+    struct MockAgent {
+        responses: Vec<&'static str>,
+        calls: AtomicUsize,
+    }
+
+    #[allow(refining_impl_trait)]
+    impl Chat for MockAgent {
+        async fn chat(
+            &self,
+            _prompt: impl Into<Message> + Send,
+            _chat_history: Vec<Message>,
+        ) -> Result<String, PromptError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.responses[call.min(self.responses.len() - 1)].to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_schema_validator() {
+        let validator = JsonSchemaValidator::new(json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["name", "age"]
+        }));
+
+        assert!(validator
+            .validate("```json\n{\"name\": \"John\", \"age\": 30}\n```")
+            .await
+            .is_ok());
+        assert_eq!(
+            validator.validate(r#"{"name": "John"}"#).await,
+            Err(Violation::new("json_schema", "`.age` is required"))
+        );
+        assert_eq!(
+            validator.validate(r#"{"name": "John", "age": -1}"#).await,
+            Err(Violation::new("json_schema", "`.age` must be >= 0"))
+        );
+        assert!(validator.validate("not json").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_json_schema_validator_for_type() {
+        #[derive(JsonSchema)]
+        #[allow(dead_code)]
+        struct Person {
+            name: String,
+            pets: Vec<Pet>,
+        }
+
+        #[derive(JsonSchema)]
+        #[allow(dead_code)]
+        struct Pet {
+            kind: String,
+        }
+
+        let validator = JsonSchemaValidator::for_type::<Person>();
+
+        assert!(validator
+            .validate(r#"{"name": "John", "pets": [{"kind": "cat"}]}"#)
+            .await
+            .is_ok());
+        assert!(validator
+            .validate(r#"{"name": "John", "pets": [{"kind": 1}]}"#)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reask() {
+        let agent = GuardedAgent::new(MockAgent {
+            responses: vec!["As an AI, I can't", "Paris"],
+            calls: AtomicUsize::new(0),
+        })
+        .validator(RegexValidator::must_not_match("(?i)as an ai").unwrap())
+        .on_failure(OnFailure::Reask { max_attempts: 1 });
+
+        let response = agent.prompt("What is the capital of France?").await;
+
+        assert_eq!(response.unwrap(), "Paris");
+    }
+
+    #[tokio::test]
+    async fn test_error_on_failure() {
+        let agent = GuardedAgent::new(MockAgent {
+            responses: vec!["too long response"],
+            calls: AtomicUsize::new(0),
+        })
+        .validator(FnValidator::new("length", |response| {
+            if response.len() > 5 {
+                Err("The response must be at most 5 characters long".to_string())
+            } else {
+                Ok(())
+            }
+        }))
+        .on_failure(OnFailure::Reask { max_attempts: 2 });
+
+        let Err(PromptError::GuardrailError(err)) = agent.prompt("Hi").await else {
+            panic!("Expected a guardrail error");
+        };
+
+        assert_eq!(err.violations[0].validator, "length");
+        assert_eq!(agent.agent.calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::wasm_compat::WasmCompatSend;
 use serde_json::Value;
 use thiserror::Error;
 
@@ -38,7 +39,7 @@ pub trait ImageGeneration<M: ImageGenerationModel> {
         size: &(u32, u32),
     ) -> impl std::future::Future<
         Output = Result<ImageGenerationRequestBuilder<M>, ImageGenerationError>,
-    > + Send;
+    > + WasmCompatSend;
 }
 
 #[derive(Debug)]
@@ -55,7 +56,7 @@ pub trait ImageGenerationModel: Clone + Send + Sync {
         request: ImageGenerationRequest,
     ) -> impl std::future::Future<
         Output = Result<ImageGenerationResponse<Self::Response>, ImageGenerationError>,
-    > + Send;
+    > + WasmCompatSend;
 
     fn image_generation_request(&self) -> ImageGenerationRequestBuilder<Self> {
         ImageGenerationRequestBuilder::new(self.clone())
@@ -0,0 +1,634 @@
+//! This module provides a high-level ingestion pipeline, loading the files of a directory (or a
+//! git repository), splitting them into chunks, embedding the chunks and storing them in a
+//! vector store in one call.
+//!
+//! The files are loaded with a [Loader] (by default, the [AutoLoader], which picks a loader
+//! based on the extension of each file) and can be filtered with glob patterns. The chunks are
+//! embedded in batches of [EmbeddingModel::MAX_DOCUMENTS], with a configurable number of
+//! concurrent requests, and stored as they are embedded in any [VectorStoreWriter] (e.g.: an
+//! [InMemoryVectorStore]).
+//!
+//! The hash of the content of each chunk is stored in its `content_hash` metadata, so that
+//! [Ingestion::sync] can re-embed only the chunks that changed since the previous ingestion and
+//! delete the documents of the removed files.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     chunking::MarkdownSplitter,
+//!     ingest::Ingestion,
+//!     providers::openai,
+//!     vector_store::in_memory_store::InMemoryVectorStore,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//! let mut store = InMemoryVectorStore::default();
+//!
+//! let report = Ingestion::git("path/to/repo")
+//!     .include("docs/**/*.md")?
+//!     .splitter(MarkdownSplitter::new(1000))
+//!     .concurrency(4)
+//!     .on_progress(|progress| println!("{}/{} chunks", progress.chunks_embedded, progress.chunks))
+//!     .run(&model, &mut store)
+//!     .await?;
+//!
+//! println!("Ingested {} files", report.files);
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
+
+use futures::{stream, StreamExt};
+use glob::Pattern;
+use sha2::{Digest, Sha256};
+
+use crate::wasm_compat::WasmCompatSend;
+use crate::{
+    chunking::TextSplitter,
+    completion::Document,
+    embeddings::{EmbeddingError, EmbeddingModel},
+    loaders::{AutoLoader, Loader, LoaderError},
+    vector_store::{
+        in_memory_store::InMemoryVectorStore, quantized_store::QuantizedVectorStore,
+        VectorStoreError, VectorStoreWriter,
+    },
+    OneOrMany,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum IngestError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Pattern error: {0}")]
+    PatternError(#[from] glob::PatternError),
+
+    #[error("Git error: {0}")]
+    GitError(String),
+
+    #[error("Embedding error: {0}")]
+    EmbeddingError(#[from] EmbeddingError),
+
+    #[error("Vector store error: {0}")]
+    VectorStoreError(#[from] VectorStoreError),
+}
+
+/// Ids of stored documents with their metadata.
+pub type StoredDocuments = Vec<(String, HashMap<String, String>)>;
+
+/// Trait for the destinations of the ingested documents: vector stores that can list the ids
+/// and metadata of their documents, used by [Ingestion::sync] to find the changed and removed
+/// documents. [Ingestion::run] only requires a [VectorStoreWriter].
+pub trait IngestSink: VectorStoreWriter<Document> {
+    /// Get the ids of the stored documents with their metadata.
+    fn stored_documents(
+        &self,
+    ) -> impl Future<Output = Result<StoredDocuments, VectorStoreError>> + WasmCompatSend;
+}
+
+impl IngestSink for InMemoryVectorStore<Document> {
+    async fn stored_documents(&self) -> Result<StoredDocuments, VectorStoreError> {
+        Ok(self
+            .iter()
+            .map(|(id, (document, _))| (id.clone(), document.additional_props.clone()))
+            .collect())
+    }
+}
+
+impl IngestSink for QuantizedVectorStore<Document> {
+    async fn stored_documents(&self) -> Result<StoredDocuments, VectorStoreError> {
+        Ok(self
+            .documents()
+            .map(|(id, document)| (id.clone(), document.additional_props.clone()))
+            .collect())
+    }
+}
+
+/// Progress of an ingestion, reported after the files are loaded and after each embedded batch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IngestProgress {
+    /// Number of files loaded
+    pub files: usize,
+    /// Total number of chunks to embed
+    pub chunks: usize,
+    /// Number of chunks embedded and stored
+    pub chunks_embedded: usize,
+}
+
+/// Result of an ingestion.
+#[derive(Debug, Default)]
+pub struct IngestReport {
+    /// Number of files loaded
+    pub files: usize,
+    /// Number of documents produced by the loader
+    pub documents: usize,
+    /// Number of chunks embedded and stored
+    pub chunks: usize,
+    /// Number of chunks left untouched because their content did not change (see [Ingestion::sync])
+    pub unchanged: usize,
+    /// Number of stored documents deleted because they no longer exist (see [Ingestion::sync])
+    pub deleted: usize,
+    /// Files that could not be loaded, with the loader error
+    pub failures: Vec<(PathBuf, LoaderError)>,
+}
+
+type ProgressCallback = Box<dyn Fn(&IngestProgress) + Send + Sync>;
+
+enum Source {
+    Directory(PathBuf),
+    Git(PathBuf),
+}
+
+/// Ingestion pipeline of the files of a directory or a git repository (see the [module
+/// documentation](self)).
+pub struct Ingestion {
+    source: Source,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    loader: Box<dyn Loader>,
+    splitter: Option<Arc<dyn TextSplitter>>,
+    batch_size: Option<usize>,
+    concurrency: usize,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Ingestion {
+    fn new(source: Source) -> Self {
+        Self {
+            source,
+            include: vec![],
+            exclude: vec![],
+            loader: Box::new(AutoLoader::default()),
+            splitter: None,
+            batch_size: None,
+            concurrency: 1,
+            on_progress: None,
+        }
+    }
+
+    /// Ingest the files of the directory at `path` and its subdirectories, ignoring hidden files
+    /// and directories (e.g.: `.git`).
+    pub fn dir(path: impl AsRef<Path>) -> Self {
+        Self::new(Source::Directory(path.as_ref().to_path_buf()))
+    }
+
+    /// Ingest the files of the git repository at `path` that are tracked or not ignored by its
+    /// `.gitignore` files. Requires the `git` command.
+    pub fn git(path: impl AsRef<Path>) -> Self {
+        Self::new(Source::Git(path.as_ref().to_path_buf()))
+    }
+
+    /// Only ingest the files whose path (relative to the directory) matches the glob `pattern`.
+    /// If called multiple times, the files matching any of the patterns are ingested.
+    pub fn include(mut self, pattern: &str) -> Result<Self, IngestError> {
+        self.include.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Skip the files whose path (relative to the directory) matches the glob `pattern`.
+    pub fn exclude(mut self, pattern: &str) -> Result<Self, IngestError> {
+        self.exclude.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Set the loader of the files (default: [AutoLoader]). Files not supported by the loader
+    /// are skipped.
+    pub fn loader(mut self, loader: impl Loader + 'static) -> Self {
+        self.loader = Box::new(loader);
+        self
+    }
+
+    /// Split the documents into chunks with the given splitter before embedding them. The id of
+    /// each chunk is the id of its document followed by `#chunk={n}`, and its index is stored in
+    /// its `chunk` metadata.
+    pub fn splitter(mut self, splitter: impl TextSplitter + 'static) -> Self {
+        self.splitter = Some(Arc::new(splitter));
+        self
+    }
+
+    /// Set the number of chunks embedded per request (default: [EmbeddingModel::MAX_DOCUMENTS]).
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size.max(1));
+        self
+    }
+
+    /// Set the maximum number of concurrent embedding requests (default: 1).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set a callback called with the progress of the ingestion.
+    pub fn on_progress(
+        mut self,
+        on_progress: impl Fn(&IngestProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// List the files to ingest, as `(absolute path, relative path)` tuples sorted by path.
+    fn files(&self) -> Result<Vec<(PathBuf, PathBuf)>, IngestError> {
+        let (root, mut files) = match &self.source {
+            Source::Directory(root) => (root, walk(root)?),
+            Source::Git(root) => (root, git_files(root)?),
+        };
+        files.sort();
+
+        Ok(files
+            .into_iter()
+            .filter(|path| {
+                (self.include.is_empty() || self.include.iter().any(|p| p.matches_path(path)))
+                    && !self.exclude.iter().any(|p| p.matches_path(path))
+            })
+            .map(|path| (root.join(&path), path))
+            .filter(|(path, _)| self.loader.supports(path))
+            .collect())
+    }
+
+    fn report_progress(&self, progress: &IngestProgress) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(progress);
+        }
+    }
+
+    /// Load and split the files, returning the chunks with their content hash.
+    fn load_chunks(&self) -> Result<(IngestReport, Vec<Document>), IngestError> {
+        let mut report = IngestReport::default();
+        let mut documents = vec![];
+
+        for (path, _) in self.files()? {
+            match self.loader.load(&path) {
+                Ok(loaded) => {
+                    report.files += 1;
+                    report.documents += loaded.len();
+                    documents.extend(loaded);
+                }
+                Err(error) => report.failures.push((path, error)),
+            }
+        }
+
+        let mut chunks: Vec<Document> = match &self.splitter {
+            Some(splitter) => documents
+                .into_iter()
+                .flat_map(|document| split_document(splitter.as_ref(), document))
+                .collect(),
+            None => documents,
+        };
+
+        for chunk in chunks.iter_mut() {
+            chunk
+                .additional_props
+                .insert(CONTENT_HASH.to_string(), content_hash(&chunk.text));
+        }
+
+        Ok((report, chunks))
+    }
+
+    /// Embed the chunks in concurrent batches, storing each batch once embedded.
+    async fn embed_and_store<M: EmbeddingModel>(
+        &self,
+        chunks: Vec<Document>,
+        model: &M,
+        sink: &mut impl VectorStoreWriter<Document>,
+        report: &mut IngestReport,
+    ) -> Result<(), IngestError> {
+        let mut progress = IngestProgress {
+            files: report.files,
+            chunks: chunks.len(),
+            chunks_embedded: 0,
+        };
+        self.report_progress(&progress);
+
+        let batch_size = self.batch_size.unwrap_or(M::MAX_DOCUMENTS).max(1);
+        let mut batches = vec![];
+        let mut chunks = chunks.into_iter().peekable();
+        while chunks.peek().is_some() {
+            batches.push(chunks.by_ref().take(batch_size).collect::<Vec<_>>());
+        }
+
+        let mut embedded = stream::iter(batches.into_iter().map(|batch| async move {
+            let texts = batch
+                .iter()
+                .map(|chunk| chunk.text.clone())
+                .collect::<Vec<_>>();
+            let embeddings = model.embed_texts(texts).await?;
+            Ok::<_, EmbeddingError>((batch, embeddings))
+        }))
+        .buffered(self.concurrency);
+
+        while let Some(result) = embedded.next().await {
+            let (batch, embeddings) = result?;
+            let embedded_chunks = batch.len();
+
+            sink.upsert(
+                batch
+                    .into_iter()
+                    .zip(embeddings)
+                    .map(|(chunk, embedding)| (chunk.id.clone(), chunk, OneOrMany::one(embedding)))
+                    .collect(),
+            )
+            .await?;
+
+            report.chunks += embedded_chunks;
+            progress.chunks_embedded += embedded_chunks;
+            self.report_progress(&progress);
+        }
+
+        Ok(())
+    }
+
+    /// Run the ingestion: load, split and embed the files with `model`, and store them in `sink`.
+    ///
+    /// Files that cannot be loaded are skipped and listed in the [IngestReport::failures]. The
+    /// ingestion stops at the first embedding or storage error, with the chunks embedded so far
+    /// already stored.
+    pub async fn run<M: EmbeddingModel>(
+        self,
+        model: &M,
+        sink: &mut impl VectorStoreWriter<Document>,
+    ) -> Result<IngestReport, IngestError> {
+        let (mut report, chunks) = self.load_chunks()?;
+        self.embed_and_store(chunks, model, sink, &mut report)
+            .await?;
+
+        Ok(report)
+    }
+
+    /// Run the ingestion incrementally: only the chunks that are new or whose content changed
+    /// since the previous ingestion (according to the `content_hash` metadata of the stored
+    /// documents) are embedded, and the stored documents of the directory that no longer exist
+    /// are deleted (except those of the files that failed to load).
+    ///
+    /// This makes repeated ingestions of a directory cheap, as long as the ids of the documents
+    /// are stable (which is the case for the default loaders, whose ids are the file paths).
+    pub async fn sync<M: EmbeddingModel>(
+        self,
+        model: &M,
+        sink: &mut impl IngestSink,
+    ) -> Result<IngestReport, IngestError> {
+        let (mut report, chunks) = self.load_chunks()?;
+        let stored = sink.stored_documents().await?;
+
+        let root = match &self.source {
+            Source::Directory(root) | Source::Git(root) => root,
+        };
+        let current_ids = chunks
+            .iter()
+            .map(|chunk| chunk.id.as_str())
+            .collect::<HashSet<_>>();
+
+        let deleted = stored
+            .iter()
+            .filter(|(id, _)| !current_ids.contains(id.as_str()))
+            .filter(|(_, metadata)| {
+                metadata.get("source").is_some_and(|source| {
+                    let source = Path::new(source);
+                    source.starts_with(root)
+                        && !report.failures.iter().any(|(path, _)| path == source)
+                })
+            })
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+
+        let stored_hashes = stored
+            .iter()
+            .filter_map(|(id, metadata)| Some((id.as_str(), metadata.get(CONTENT_HASH)?)))
+            .collect::<HashMap<_, _>>();
+        let (unchanged, changed): (Vec<_>, Vec<_>) = chunks.into_iter().partition(|chunk| {
+            stored_hashes.get(chunk.id.as_str()).copied()
+                == chunk.additional_props.get(CONTENT_HASH)
+        });
+
+        report.unchanged = unchanged.len();
+        report.deleted = deleted.len();
+
+        for id in &deleted {
+            sink.delete_by_id(id).await?;
+        }
+        self.embed_and_store(changed, model, sink, &mut report)
+            .await?;
+
+        Ok(report)
+    }
+}
+
+/// Metadata key of the hash of the content of the ingested documents.
+pub const CONTENT_HASH: &str = "content_hash";
+
+/// Compute the content hash of a text (hex-encoded SHA-256).
+pub fn content_hash(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+/// Split a document into chunk documents, keeping the metadata of the document.
+fn split_document(splitter: &dyn TextSplitter, document: Document) -> Vec<Document> {
+    splitter
+        .split_text(&document.text)
+        .into_iter()
+        .enumerate()
+        .map(|(index, text)| {
+            let mut additional_props = document.additional_props.clone();
+            additional_props.insert("chunk".to_string(), index.to_string());
+
+            Document {
+                id: format!("{}#chunk={}", document.id, index),
+                text,
+                additional_props,
+            }
+        })
+        .collect()
+}
+
+/// List the files of `root` and its subdirectories (relative to `root`), skipping hidden entries.
+fn walk(root: &Path) -> Result<Vec<PathBuf>, IngestError> {
+    let mut files = vec![];
+    let mut directories = vec![PathBuf::new()];
+
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(root.join(&directory))? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            let path = directory.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                directories.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// List the files of the git repository at `root` (relative to `root`) that are tracked or not
+/// ignored.
+fn git_files(root: &Path) -> Result<Vec<PathBuf>, IngestError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(IngestError::GitError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        // Deleted files are still listed by `git ls-files` until the deletion is staged
+        .filter(|path| root.join(path).is_file())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use assert_fs::{prelude::*, TempDir};
+
+    use super::*;
+    use crate::{chunking::RecursiveSplitter, embeddings::Embedding};
+
+    #[derive(Clone)]
+    struct MockModel;
+
+    impl EmbeddingModel for MockModel {
+        const MAX_DOCUMENTS: usize = 2;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|document| Embedding {
+                    vec: vec![document.len() as f64],
+                    document,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingest_directory() {
+        let dir = TempDir::new().unwrap();
+        dir.child("docs/guide.md")
+            .write_str("# Guide\n\nFirst paragraph.\n\nSecond paragraph.")
+            .unwrap();
+        dir.child("notes.txt").write_str("Some notes.").unwrap();
+        dir.child("target/build.txt")
+            .write_str("Build output.")
+            .unwrap();
+        dir.child(".cache/cached.txt").write_str("Cached.").unwrap();
+        dir.child("image.png").write_binary(&[0, 1, 2]).unwrap();
+
+        let progress = Arc::new(Mutex::new(vec![]));
+        let mut store = InMemoryVectorStore::default();
+
+        let report = Ingestion::dir(dir.path())
+            .exclude("target/**")
+            .unwrap()
+            .splitter(RecursiveSplitter::new(20))
+            .concurrency(2)
+            .on_progress({
+                let progress = progress.clone();
+                move |p| progress.lock().unwrap().push(p.chunks_embedded)
+            })
+            .run(&MockModel, &mut store)
+            .await
+            .unwrap();
+
+        assert_eq!(report.files, 2);
+        assert_eq!(report.documents, 2);
+        assert_eq!(report.chunks, 4);
+        assert!(report.failures.is_empty());
+        assert_eq!(*progress.lock().unwrap(), vec![0, 2, 4]);
+
+        let mut ids = store
+            .iter()
+            .map(|(id, (document, _))| (id.clone(), document.text.clone()))
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        let guide = dir
+            .path()
+            .join("docs/guide.md")
+            .to_string_lossy()
+            .to_string();
+        let notes = dir.path().join("notes.txt").to_string_lossy().to_string();
+        assert_eq!(
+            ids,
+            vec![
+                (format!("{guide}#chunk=0"), "# Guide".to_string()),
+                (format!("{guide}#chunk=1"), "First paragraph.".to_string()),
+                (format!("{guide}#chunk=2"), "Second paragraph.".to_string()),
+                (format!("{notes}#chunk=0"), "Some notes.".to_string()),
+            ]
+        );
+        let notes_chunk: Option<Document> =
+            store.get_by_id(&format!("{notes}#chunk=0")).await.unwrap();
+        assert_eq!(notes_chunk.unwrap().additional_props["chunk"], "0");
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let dir = TempDir::new().unwrap();
+        dir.child("a.txt").write_str("A").unwrap();
+        dir.child("b.txt").write_str("B").unwrap();
+        dir.child("c.txt").write_str("C").unwrap();
+
+        let mut store = InMemoryVectorStore::default();
+        let report = Ingestion::dir(dir.path())
+            .sync(&MockModel, &mut store)
+            .await
+            .unwrap();
+        assert_eq!((report.chunks, report.unchanged, report.deleted), (3, 0, 0));
+
+        dir.child("b.txt").write_str("B, updated").unwrap();
+        dir.child("d.txt").write_str("D").unwrap();
+        std::fs::remove_file(dir.child("c.txt").path()).unwrap();
+
+        let report = Ingestion::dir(dir.path())
+            .sync(&MockModel, &mut store)
+            .await
+            .unwrap();
+        assert_eq!((report.chunks, report.unchanged, report.deleted), (2, 1, 1));
+
+        let mut texts = store
+            .iter()
+            .map(|(_, (document, _))| document.text.clone())
+            .collect::<Vec<_>>();
+        texts.sort();
+        assert_eq!(texts, vec!["A", "B, updated", "D"]);
+
+        let (_, (document, _)) = store.iter().next().unwrap();
+        assert_eq!(
+            document.additional_props[CONTENT_HASH],
+            content_hash(&document.text)
+        );
+    }
+}
@@ -10,6 +10,7 @@
 //! - Full support for LLM completion and embedding workflows
 //! - Simple but powerful common abstractions over LLM providers (e.g. OpenAI, Cohere) and vector stores (e.g. MongoDB, in-memory)
 //! - Integrate LLMs in your app with minimal boilerplate
+//! - Runs natively and in the browser (`wasm32-unknown-unknown`, see [wasm_compat])
 //!
 //! # Simple example:
 //! ```
@@ -35,6 +36,9 @@
 //! Note: using `#[tokio::main]` requires you enable tokio's `macros` and `rt-multi-thread` features
 //! or just `full` to enable all features (`cargo add tokio --features macros,rt-multi-thread`).
 //!
+//! Synchronous contexts (e.g.: scripts) can use the [blocking](crate::blocking) facade instead,
+//! with the `blocking` feature.
+//!
 //! # Core concepts
 //! ## Completion and embedding models
 //! Rig provides a consistent API for working with LLMs and embeddings. Specifically,
@@ -83,21 +87,46 @@
 pub mod agent;
 #[cfg(feature = "audio")]
 pub mod audio_generation;
+pub mod auth;
+pub mod blackboard;
+pub mod blob;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
+pub mod cancellation;
+pub mod catalog;
+pub mod chunking;
+pub mod classifier;
 pub mod cli_chatbot;
 pub mod completion;
 pub mod embeddings;
 pub mod extractor;
+pub mod guardrails;
 #[cfg(feature = "image")]
 pub mod image_generation;
+pub mod ingest;
 pub(crate) mod json_utils;
 pub mod loaders;
 pub mod one_or_many;
 pub mod pipeline;
 pub mod providers;
+pub mod rate_limit;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+pub mod redaction;
+pub mod runtime;
+pub mod scheduler;
 pub mod streaming;
+pub mod tasks;
+pub mod testing;
+pub mod tokens;
 pub mod tool;
+pub mod tools;
 pub mod transcription;
 pub mod vector_store;
+#[cfg(feature = "vision")]
+pub mod vision;
+pub mod wasm_compat;
 
 // Re-export commonly used types and traits
 pub use completion::message;
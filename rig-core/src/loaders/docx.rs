@@ -0,0 +1,147 @@
+use std::io::{Cursor, Read};
+
+use quick_xml::{events::Event, Reader};
+use zip::ZipArchive;
+
+use super::loader::{document, Loader, LoaderError};
+use crate::completion::Document;
+
+/// [Loader] for DOCX (Word) files, producing one document per file with the text of its
+/// paragraphs (one per line). The title of the document is the title of the file's properties.
+#[derive(Clone, Debug, Default)]
+pub struct DocxLoader;
+
+fn docx_error(error: impl ToString) -> LoaderError {
+    LoaderError::DocxError(error.to_string())
+}
+
+/// Read the file at `name` of the DOCX archive, if it exists.
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<Option<String>, LoaderError> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(error) => return Err(docx_error(error)),
+    };
+
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents)?;
+    Ok(Some(contents))
+}
+
+/// Extract the text of the `<w:t>` elements of `word/document.xml`, one paragraph per line.
+fn document_text(xml: &str) -> Result<String, LoaderError> {
+    let mut reader = Reader::from_str(xml);
+    let mut text = String::new();
+    let mut in_text = false;
+
+    loop {
+        match reader.read_event().map_err(docx_error)? {
+            Event::Start(element) if element.local_name().as_ref() == b"t" => in_text = true,
+            Event::End(element) => match element.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Empty(element) => match element.local_name().as_ref() {
+                b"tab" => text.push('\t'),
+                b"br" | b"cr" => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(content) if in_text => {
+                text.push_str(&content.unescape().map_err(docx_error)?);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(text.trim_end().to_string())
+}
+
+/// Extract the `<dc:title>` of `docProps/core.xml`.
+fn title(xml: &str) -> Result<Option<String>, LoaderError> {
+    let mut reader = Reader::from_str(xml);
+    let mut in_title = false;
+
+    loop {
+        match reader.read_event().map_err(docx_error)? {
+            Event::Start(element) if element.local_name().as_ref() == b"title" => in_title = true,
+            Event::Text(content) if in_title => {
+                let title = content.unescape().map_err(docx_error)?.trim().to_string();
+                return Ok(Some(title).filter(|title| !title.is_empty()));
+            }
+            Event::End(element) if element.local_name().as_ref() == b"title" => in_title = false,
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+impl Loader for DocxLoader {
+    fn extensions(&self) -> &[&'static str] {
+        &["docx"]
+    }
+
+    fn load_bytes(&self, source: &str, bytes: &[u8]) -> Result<Vec<Document>, LoaderError> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(docx_error)?;
+
+        let text = match read_entry(&mut archive, "word/document.xml")? {
+            Some(xml) => document_text(&xml)?,
+            None => return Err(docx_error("missing word/document.xml")),
+        };
+        let title = match read_entry(&mut archive, "docProps/core.xml")? {
+            Some(xml) => title(&xml)?,
+            None => None,
+        };
+
+        Ok(vec![document(
+            source.to_string(),
+            source,
+            "docx",
+            text,
+            title.map(|title| ("title".to_string(), title)),
+        )])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    #[test]
+    fn test_docx_loader() {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        writer
+            .start_file("word/document.xml", SimpleFileOptions::default())
+            .unwrap();
+        writer
+            .write_all(
+                br#"<w:document xmlns:w="w"><w:body>
+                <w:p><w:r><w:t>Hello</w:t></w:r><w:r><w:t xml:space="preserve"> world &amp; all</w:t></w:r></w:p>
+                <w:p><w:r><w:t>Second</w:t><w:tab/><w:t>paragraph</w:t></w:r></w:p>
+                </w:body></w:document>"#,
+            )
+            .unwrap();
+        writer
+            .start_file("docProps/core.xml", SimpleFileOptions::default())
+            .unwrap();
+        writer
+            .write_all(br#"<cp:coreProperties xmlns:dc="dc"><dc:title>Report</dc:title></cp:coreProperties>"#)
+            .unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let documents = DocxLoader.load_bytes("report.docx", &bytes).unwrap();
+
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].text, "Hello world & all\nSecond\tparagraph");
+        assert_eq!(documents[0].additional_props["title"], "Report");
+        assert_eq!(documents[0].additional_props["format"], "docx");
+    }
+}
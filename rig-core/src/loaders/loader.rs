@@ -0,0 +1,382 @@
+use std::{collections::HashMap, path::Path, sync::LazyLock};
+
+use regex::Regex;
+use thiserror::Error;
+
+use crate::completion::Document;
+
+#[derive(Error, Debug)]
+pub enum LoaderError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("UTF-8 conversion error: {0}")]
+    Utf8Error(#[from] std::string::FromUtf8Error),
+
+    #[cfg(feature = "pdf")]
+    #[error("PDF error: {0}")]
+    PdfError(#[from] lopdf::Error),
+
+    #[cfg(feature = "docx")]
+    #[error("DOCX error: {0}")]
+    DocxError(String),
+
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
+}
+
+/// Trait for loaders converting raw files into [Document]s ready to be embedded.
+///
+/// The documents produced by the loaders carry the metadata of their source in their
+/// `additional_props`: `source` (the path of the file), `format` and, when available, `title`
+/// (and `page` for documents split by page).
+///
+/// # Example
+/// ```rust
+/// use rig::loaders::{AutoLoader, Loader};
+///
+/// let documents = AutoLoader::default().load("docs/guide.md".as_ref())?;
+/// ```
+pub trait Loader: Send + Sync {
+    /// File extensions supported by the loader (lowercase, without the leading dot).
+    fn extensions(&self) -> &[&'static str];
+
+    /// Load the documents of the raw `bytes` of a file, `source` being its path (or URL).
+    fn load_bytes(&self, source: &str, bytes: &[u8]) -> Result<Vec<Document>, LoaderError>;
+
+    /// Load the documents of the file at `path`.
+    fn load(&self, path: &Path) -> Result<Vec<Document>, LoaderError> {
+        let bytes = std::fs::read(path)?;
+        self.load_bytes(&path.to_string_lossy(), &bytes)
+    }
+
+    /// Whether the file at `path` is supported by the loader, based on its extension.
+    fn supports(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .is_some_and(|extension| self.extensions().contains(&extension.as_str()))
+    }
+}
+
+/// Create a document of the given format with the source metadata.
+pub(crate) fn document(
+    id: String,
+    source: &str,
+    format: &str,
+    text: String,
+    metadata: impl IntoIterator<Item = (String, String)>,
+) -> Document {
+    let mut additional_props = HashMap::from([
+        ("source".to_string(), source.to_string()),
+        ("format".to_string(), format.to_string()),
+    ]);
+    additional_props.extend(metadata);
+
+    Document {
+        id,
+        text,
+        additional_props,
+    }
+}
+
+// ================================================================
+// Text
+// ================================================================
+
+/// Loader for plain text files, producing one document per file.
+#[derive(Clone, Debug, Default)]
+pub struct TextLoader;
+
+impl Loader for TextLoader {
+    fn extensions(&self) -> &[&'static str] {
+        &["txt", "text", "log", "csv", "json", "rst"]
+    }
+
+    fn load_bytes(&self, source: &str, bytes: &[u8]) -> Result<Vec<Document>, LoaderError> {
+        let text = String::from_utf8(bytes.to_vec())?;
+
+        Ok(vec![document(source.to_string(), source, "text", text, [])])
+    }
+}
+
+// ================================================================
+// Markdown
+// ================================================================
+
+/// Loader for markdown files, producing one document per file.
+///
+/// The markdown is kept as is (so that the documents can be split with the
+/// [MarkdownSplitter](crate::chunking::MarkdownSplitter)), except for the YAML front matter whose
+/// `key: value` entries are added to the metadata of the document. The title of the document is
+/// the `title` of the front matter, or the first header of the file.
+#[derive(Clone, Debug, Default)]
+pub struct MarkdownLoader;
+
+/// Split the YAML front matter of a markdown document from its content.
+fn front_matter(text: &str) -> (Vec<(String, String)>, &str) {
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (vec![], text);
+    };
+
+    let Some(end) = rest.find("\n---") else {
+        return (vec![], text);
+    };
+
+    let metadata = rest[..end]
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| {
+            (
+                key.trim().to_string(),
+                value.trim().trim_matches(['"', '\'']).to_string(),
+            )
+        })
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .collect();
+
+    let content = rest[end + 4..].trim_start_matches(['-', '\r', '\n']);
+    (metadata, content)
+}
+
+impl Loader for MarkdownLoader {
+    fn extensions(&self) -> &[&'static str] {
+        &["md", "markdown", "mdx"]
+    }
+
+    fn load_bytes(&self, source: &str, bytes: &[u8]) -> Result<Vec<Document>, LoaderError> {
+        let text = String::from_utf8(bytes.to_vec())?;
+        let (mut metadata, content) = front_matter(&text);
+
+        if !metadata.iter().any(|(key, _)| key == "title") {
+            if let Some(title) = content
+                .lines()
+                .find_map(|line| line.strip_prefix("# "))
+                .map(|title| title.trim().to_string())
+            {
+                metadata.push(("title".to_string(), title));
+            }
+        }
+
+        Ok(vec![document(
+            source.to_string(),
+            source,
+            "markdown",
+            content.to_string(),
+            metadata,
+        )])
+    }
+}
+
+// ================================================================
+// HTML
+// ================================================================
+
+/// Loader for HTML files, producing one document per file with the text of the page.
+///
+/// Scripts, styles and comments are removed, block elements are converted to line breaks and
+/// HTML entities are decoded. The title of the document is the `<title>` of the page.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlLoader;
+
+static TITLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+pub(crate) static IGNORED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<!--.*?-->|<(script|style|noscript|template|svg)\b.*?</(script|style|noscript|template|svg)>|<head\b.*?</head>").unwrap()
+});
+pub(crate) static BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)<(br|/?p|/?div|/?li|/?ul|/?ol|/?tr|/?table|/?h[1-6]|/?section|/?article|/?header|/?footer|/?blockquote|/?pre|hr)\b[^>]*>").unwrap()
+});
+pub(crate) static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static ENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap());
+
+/// Decode the HTML entities of `text`.
+pub(crate) fn decode_entities(text: &str) -> String {
+    ENTITY
+        .replace_all(text, |captures: &regex::Captures| {
+            let entity = &captures[1];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16).ok())
+                    .unwrap_or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+
+            decoded.map_or_else(|| captures[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+/// Extract the `<title>` of an HTML page.
+pub(crate) fn html_title(html: &str) -> Option<String> {
+    TITLE
+        .captures(html)
+        .map(|captures| decode_entities(TAG.replace_all(&captures[1], "").trim()))
+        .filter(|title| !title.is_empty())
+}
+
+/// Extract the title and the text of an HTML page.
+pub(crate) fn html_to_text(html: &str) -> (Option<String>, String) {
+    let title = html_title(html);
+
+    let text = IGNORED.replace_all(html, "");
+    let text = BLOCK.replace_all(&text, "\n");
+    let text = decode_entities(&TAG.replace_all(&text, ""));
+
+    // Collapse the whitespace of each line, and the empty lines between paragraphs
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+
+    (title, lines.join("\n").trim().to_string())
+}
+
+impl Loader for HtmlLoader {
+    fn extensions(&self) -> &[&'static str] {
+        &["html", "htm", "xhtml"]
+    }
+
+    fn load_bytes(&self, source: &str, bytes: &[u8]) -> Result<Vec<Document>, LoaderError> {
+        let html = String::from_utf8_lossy(bytes);
+        let (title, text) = html_to_text(&html);
+
+        Ok(vec![document(
+            source.to_string(),
+            source,
+            "html",
+            text,
+            title.map(|title| ("title".to_string(), title)),
+        )])
+    }
+}
+
+// ================================================================
+// Any format
+// ================================================================
+
+/// Loader dispatching each file to the first of its loaders supporting the file's extension.
+///
+/// The default [AutoLoader] supports text, markdown and HTML files, as well as PDF and DOCX
+/// files when the `pdf` and `docx` features are enabled.
+pub struct AutoLoader {
+    loaders: Vec<Box<dyn Loader>>,
+}
+
+impl AutoLoader {
+    /// Create an [AutoLoader] without any loader.
+    pub fn empty() -> Self {
+        Self { loaders: vec![] }
+    }
+
+    /// Add a loader, with a higher priority than the existing ones.
+    pub fn loader(mut self, loader: impl Loader + 'static) -> Self {
+        self.loaders.insert(0, Box::new(loader));
+        self
+    }
+}
+
+impl Default for AutoLoader {
+    fn default() -> Self {
+        let loaders = Self::empty()
+            .loader(TextLoader)
+            .loader(MarkdownLoader)
+            .loader(HtmlLoader);
+
+        #[cfg(feature = "pdf")]
+        let loaders = loaders.loader(super::pdf::PdfLoader::default());
+
+        #[cfg(feature = "docx")]
+        let loaders = loaders.loader(super::docx::DocxLoader);
+
+        loaders
+    }
+}
+
+impl Loader for AutoLoader {
+    fn extensions(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn load_bytes(&self, source: &str, bytes: &[u8]) -> Result<Vec<Document>, LoaderError> {
+        match self
+            .loaders
+            .iter()
+            .find(|loader| loader.supports(Path::new(source)))
+        {
+            Some(loader) => loader.load_bytes(source, bytes),
+            None => Err(LoaderError::UnsupportedFormat(source.to_string())),
+        }
+    }
+
+    fn supports(&self, path: &Path) -> bool {
+        self.loaders.iter().any(|loader| loader.supports(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_loader() {
+        let markdown = "---\ntitle: \"Guide\"\nauthor: rig\n---\n# Install\n\nRun it.";
+        let documents = MarkdownLoader
+            .load_bytes("docs/guide.md", markdown.as_bytes())
+            .unwrap();
+
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].id, "docs/guide.md");
+        assert_eq!(documents[0].text, "# Install\n\nRun it.");
+        assert_eq!(documents[0].additional_props["title"], "Guide");
+        assert_eq!(documents[0].additional_props["author"], "rig");
+        assert_eq!(documents[0].additional_props["format"], "markdown");
+        assert_eq!(documents[0].additional_props["source"], "docs/guide.md");
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<html><head><title>Rig &amp; Co</title><style>p { color: red; }</style></head>
+            <body><!-- nav --><h1>Hello</h1><p>Some   <b>bold</b> text&nbsp;&#8212; done.</p>
+            <script>alert("hi")</script><ul><li>One</li><li>Two</li></ul></body></html>"#;
+
+        let (title, text) = html_to_text(html);
+
+        assert_eq!(title.as_deref(), Some("Rig & Co"));
+        assert_eq!(text, "Hello\n\nSome bold text \u{2014} done.\n\nOne\n\nTwo");
+    }
+
+    #[test]
+    fn test_auto_loader() {
+        let loader = AutoLoader::default();
+
+        assert!(loader.supports(Path::new("notes.TXT")));
+        assert!(!loader.supports(Path::new("image.png")));
+
+        let documents = loader.load_bytes("page.html", b"<p>Hi</p>").unwrap();
+        assert_eq!(documents[0].text, "Hi");
+        assert_eq!(documents[0].additional_props["format"], "html");
+
+        assert!(matches!(
+            loader.load_bytes("image.png", b""),
+            Err(LoaderError::UnsupportedFormat(_))
+        ));
+    }
+}
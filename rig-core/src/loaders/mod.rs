@@ -15,19 +15,41 @@
 //! and keeping track of the chapter numbers along with their contents.
 //!
 //! Note: The [EpubFileLoader] requires the `epub` feature to be enabled in the `Cargo.toml` file.
+//!
+//! The [Loader] trait converts raw files into [Document](crate::completion::Document)s carrying the
+//! metadata of their source, ready to be split and embedded. It is implemented by the
+//! [TextLoader], [MarkdownLoader] and [HtmlLoader], as well as the [PdfLoader] (with the `pdf`
+//! feature) and the [DocxLoader] (with the `docx` feature). The [AutoLoader] picks the loader of
+//! each file based on its extension.
+//!
+//! The [WebLoader] fetches web pages and extracts their main content, removing their boilerplate.
 
 pub mod file;
 
 pub use file::FileLoader;
 
+pub mod loader;
+
+pub use loader::{AutoLoader, HtmlLoader, Loader, LoaderError, MarkdownLoader, TextLoader};
+
+pub mod web;
+
+pub use web::WebLoader;
+
 #[cfg(feature = "pdf")]
 pub mod pdf;
 
 #[cfg(feature = "pdf")]
-pub use pdf::PdfFileLoader;
+pub use pdf::{PdfFileLoader, PdfLoader};
 
 #[cfg(feature = "epub")]
 pub mod epub;
 
 #[cfg(feature = "epub")]
 pub use epub::{EpubFileLoader, RawTextProcessor, StripXmlProcessor, TextProcessor};
+
+#[cfg(feature = "docx")]
+pub mod docx;
+
+#[cfg(feature = "docx")]
+pub use docx::DocxLoader;
@@ -4,7 +4,10 @@ use glob::glob;
 use lopdf::{Document, Error as LopdfError};
 use thiserror::Error;
 
-use super::file::FileLoaderError;
+use super::{
+    file::FileLoaderError,
+    loader::{document, Loader, LoaderError},
+};
 
 #[derive(Error, Debug)]
 pub enum PdfLoaderError {
@@ -404,11 +407,71 @@ impl<T> Iterator for IntoIter<'_, T> {
     }
 }
 
+// ================================================================
+// Loader implementation
+// ================================================================
+
+/// [Loader] for PDF files, producing one document per page (with its `page` number, starting at
+/// 1, in the metadata), or one document per file with [PdfLoader::merge_pages].
+#[derive(Clone, Debug, Default)]
+pub struct PdfLoader {
+    merge_pages: bool,
+}
+
+impl PdfLoader {
+    /// Produce a single document with the text of all the pages of the file.
+    pub fn merge_pages(mut self) -> Self {
+        self.merge_pages = true;
+        self
+    }
+}
+
+impl Loader for PdfLoader {
+    fn extensions(&self) -> &[&'static str] {
+        &["pdf"]
+    }
+
+    fn load_bytes(
+        &self,
+        source: &str,
+        bytes: &[u8],
+    ) -> Result<Vec<crate::completion::Document>, LoaderError> {
+        let doc = Document::load_mem(bytes)?;
+        let pages = doc
+            .get_pages()
+            .into_keys()
+            .map(|page_no| Ok((page_no, doc.extract_text(&[page_no])?)))
+            .collect::<Result<Vec<_>, LoaderError>>()?;
+
+        if self.merge_pages {
+            let text = pages
+                .into_iter()
+                .map(|(_, text)| text)
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Ok(vec![document(source.to_string(), source, "pdf", text, [])]);
+        }
+
+        Ok(pages
+            .into_iter()
+            .map(|(page_no, text)| {
+                document(
+                    format!("{source}#page={page_no}"),
+                    source,
+                    "pdf",
+                    text,
+                    [("page".to_string(), page_no.to_string())],
+                )
+            })
+            .collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
-    use super::PdfFileLoader;
+    use super::{Loader, PdfFileLoader, PdfLoader};
 
     #[test]
     fn test_pdf_loader() {
@@ -453,4 +516,16 @@ mod tests {
         assert!(!actual.is_empty());
         assert!(expected == actual)
     }
+
+    #[test]
+    fn test_pdf_document_loader() {
+        let documents = PdfLoader::default()
+            .load("tests/data/dummy.pdf".as_ref())
+            .unwrap();
+
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].id, "tests/data/dummy.pdf#page=1");
+        assert_eq!(documents[0].text, "Test\nPDF\nDocument\n");
+        assert_eq!(documents[0].additional_props["page"], "1");
+    }
 }
@@ -0,0 +1,281 @@
+use std::sync::LazyLock;
+
+use futures::future::try_join_all;
+use regex::Regex;
+use reqwest::Url;
+
+use super::loader::{
+    decode_entities, document, html_title, html_to_text, Loader, LoaderError, BLOCK, IGNORED, TAG,
+};
+use crate::completion::Document;
+
+/// Loader fetching web pages and extracting their main content.
+///
+/// By default, the boilerplate of the pages (navigation, headers, footers, sidebars, forms, link
+/// lists, ...) is removed readability-style: the main content is the `<article>` (or `<main>`) of
+/// the page when there is one, and blocks consisting mostly of links are dropped. Use
+/// [WebLoader::readability] to keep the whole text of the pages instead.
+///
+/// The documents are identified by the canonical URL of the pages (`<link rel="canonical">`),
+/// which is also stored in their `canonical_url` metadata, along with the `url` they were fetched
+/// from and their `title`.
+///
+/// # Example
+/// ```rust
+/// use rig::loaders::WebLoader;
+///
+/// let documents = WebLoader::new()
+///     .load_urls(["https://docs.rig.rs", "https://docs.rig.rs/docs/concepts"])
+///     .await?;
+/// ```
+#[derive(Clone)]
+pub struct WebLoader {
+    http_client: reqwest::Client,
+    readability: bool,
+    max_link_density: f64,
+}
+
+static CANONICAL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<link\b[^>]*\brel\s*=\s*["']?canonical["']?[^>]*>|<meta\b[^>]*\bproperty\s*=\s*["']og:url["'][^>]*>"#).unwrap()
+});
+static HREF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)\b(?:href|content)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap()
+});
+static BOILERPLATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<(nav|header|footer|aside|form|iframe|button|menu|dialog)\b.*?</(?:nav|header|footer|aside|form|iframe|button|menu|dialog)>").unwrap()
+});
+static ARTICLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<article\b[^>]*>(.*?)</article>").unwrap());
+static MAIN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<main\b[^>]*>(.*?)</main>").unwrap());
+static BODY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<body\b[^>]*>(.*)</body>").unwrap());
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<a\b[^>]*>(.*?)</a>").unwrap());
+
+impl Default for WebLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebLoader {
+    pub fn new() -> Self {
+        Self::with_client(reqwest::Client::new())
+    }
+
+    /// Create a [WebLoader] fetching the pages with the given HTTP client (e.g.: to set a user
+    /// agent, a proxy or timeouts).
+    pub fn with_client(http_client: reqwest::Client) -> Self {
+        Self {
+            http_client,
+            readability: true,
+            max_link_density: 0.5,
+        }
+    }
+
+    /// Extract the main content of the pages, removing their boilerplate (default: true).
+    pub fn readability(mut self, readability: bool) -> Self {
+        self.readability = readability;
+        self
+    }
+
+    /// Set the maximum proportion of the text of a block that can be links before the block is
+    /// considered boilerplate (default: 0.5).
+    pub fn max_link_density(mut self, max_link_density: f64) -> Self {
+        self.max_link_density = max_link_density;
+        self
+    }
+
+    /// Fetch the page at `url` and load its document.
+    pub async fn load_url(&self, url: &str) -> Result<Vec<Document>, LoaderError> {
+        let response = self.http_client.get(url).send().await?.error_for_status()?;
+
+        let final_url = response.url().to_string();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok());
+        let is_html = !matches!(content_type, Some(content_type) if !content_type.contains("html"));
+        let body = response.text().await?;
+
+        if !is_html {
+            return Ok(vec![document(
+                final_url.clone(),
+                url,
+                "text",
+                body,
+                [("url".to_string(), final_url)],
+            )]);
+        }
+
+        Ok(vec![self.html_document(url, &final_url, &body)])
+    }
+
+    /// Fetch the pages at `urls` concurrently and load their documents.
+    pub async fn load_urls(
+        &self,
+        urls: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Vec<Document>, LoaderError> {
+        let urls = urls
+            .into_iter()
+            .map(|url| url.as_ref().to_string())
+            .collect::<Vec<_>>();
+
+        Ok(try_join_all(urls.iter().map(|url| self.load_url(url)))
+            .await?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    fn html_document(&self, source: &str, url: &str, html: &str) -> Document {
+        let canonical_url = canonical_url(html, url).unwrap_or_else(|| url.to_string());
+
+        let (title, text) = if self.readability {
+            readability(html, self.max_link_density)
+        } else {
+            html_to_text(html)
+        };
+
+        let metadata = [
+            ("url".to_string(), url.to_string()),
+            ("canonical_url".to_string(), canonical_url.clone()),
+        ]
+        .into_iter()
+        .chain(title.map(|title| ("title".to_string(), title)));
+
+        document(canonical_url, source, "html", text, metadata)
+    }
+}
+
+/// Find the canonical URL of an HTML page, resolved against the `url` of the page.
+fn canonical_url(html: &str, url: &str) -> Option<String> {
+    let tag = CANONICAL.find(html)?.as_str();
+    let captures = HREF.captures(tag)?;
+    let href = decode_entities(
+        captures
+            .get(1)
+            .or_else(|| captures.get(2))
+            .or_else(|| captures.get(3))?
+            .as_str()
+            .trim(),
+    );
+
+    match Url::parse(url) {
+        Ok(base) => base.join(&href).ok().map(String::from),
+        Err(_) => Some(href).filter(|href| !href.is_empty()),
+    }
+}
+
+/// Extract the title and the main content of an HTML page.
+fn readability(html: &str, max_link_density: f64) -> (Option<String>, String) {
+    let title = html_title(html);
+
+    let html = IGNORED.replace_all(html, "");
+    let html = BOILERPLATE.replace_all(&html, "");
+
+    // Use the largest article, the main element or the body of the page as content
+    let content = ARTICLE
+        .captures_iter(&html)
+        .filter_map(|captures| captures.get(1))
+        .max_by_key(|article| article.len())
+        .or_else(|| MAIN.captures(&html).and_then(|captures| captures.get(1)))
+        .or_else(|| BODY.captures(&html).and_then(|captures| captures.get(1)))
+        .map_or(html.as_ref(), |content| content.as_str());
+
+    let text_of = |html: &str| {
+        decode_entities(&TAG.replace_all(html, ""))
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    // Drop the blocks made mostly of links (menus, related articles, tags, ...)
+    let blocks = BLOCK
+        .split(content)
+        .filter_map(|block| {
+            let text = text_of(block);
+            if text.is_empty() {
+                return None;
+            }
+
+            let link_length = LINK
+                .captures_iter(block)
+                .map(|captures| text_of(&captures[1]).len())
+                .sum::<usize>();
+
+            (link_length as f64 / text.len() as f64 <= max_link_density).then_some(text)
+        })
+        .collect::<Vec<_>>();
+
+    (title, blocks.join("\n\n"))
+}
+
+impl Loader for WebLoader {
+    fn extensions(&self) -> &[&'static str] {
+        &["html", "htm", "xhtml"]
+    }
+
+    /// Load the document of a page saved locally (or fetched separately), `source` being its URL.
+    fn load_bytes(&self, source: &str, bytes: &[u8]) -> Result<Vec<Document>, LoaderError> {
+        Ok(vec![self.html_document(
+            source,
+            source,
+            &String::from_utf8_lossy(bytes),
+        )])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html>
+        <head>
+            <title>Rig - Agents</title>
+            <link rel="canonical" href="/docs/agents" />
+        </head>
+        <body>
+            <nav><a href="/">Home</a> <a href="/docs">Docs</a></nav>
+            <div class="layout">
+                <div class="sidebar"><a href="/a">Page A</a><br><a href="/b">Page B</a></div>
+                <article>
+                    <h1>Agents</h1>
+                    <p>An agent combines a model with a preamble and tools.</p>
+                    <p>See <a href="/tools">tools</a> for more &amp; details.</p>
+                </article>
+            </div>
+            <footer>Copyright</footer>
+        </body>
+    </html>"#;
+
+    #[test]
+    fn test_readability() {
+        let documents = WebLoader::new()
+            .load_bytes("https://docs.rig.rs/docs/agents?ref=home", PAGE.as_bytes())
+            .unwrap();
+
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].id, "https://docs.rig.rs/docs/agents");
+        assert_eq!(
+            documents[0].text,
+            "Agents\n\nAn agent combines a model with a preamble and tools.\n\nSee tools for more & details."
+        );
+        assert_eq!(documents[0].additional_props["title"], "Rig - Agents");
+        assert_eq!(
+            documents[0].additional_props["url"],
+            "https://docs.rig.rs/docs/agents?ref=home"
+        );
+    }
+
+    #[test]
+    fn test_without_readability() {
+        let documents = WebLoader::new()
+            .readability(false)
+            .load_bytes("https://docs.rig.rs/docs/agents", PAGE.as_bytes())
+            .unwrap();
+
+        assert!(documents[0].text.starts_with("Home Docs\n\nPage A\nPage B"));
+        assert!(documents[0].text.ends_with("Copyright"));
+    }
+}
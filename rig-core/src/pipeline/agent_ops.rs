@@ -1,5 +1,6 @@
 use std::future::IntoFuture;
 
+use crate::wasm_compat::WasmCompatSend;
 use crate::{
     completion::{self, CompletionModel},
     extractor::{ExtractionError, Extractor},
@@ -88,7 +89,10 @@ where
     type Input = In;
     type Output = Result<String, completion::PromptError>;
 
-    fn call(&self, input: Self::Input) -> impl std::future::Future<Output = Self::Output> + Send {
+    fn call(
+        &self,
+        input: Self::Input,
+    ) -> impl std::future::Future<Output = Self::Output> + WasmCompatSend {
         self.prompt.prompt(input.into()).into_future()
     }
 }
@@ -0,0 +1,272 @@
+//! Conditional branching: an op routing its input to one of several sub-pipelines, chosen
+//! by a predicate (or any other function of the input) or by a model-based classifier.
+//!
+//! Unlike [conditional!](crate::conditional!), which dispatches on the variant of an enum,
+//! a [Branch] classifies its input itself. All the branches have the same input and output
+//! types.
+//!
+//! # Example
+//! ```rust
+//! use rig::pipeline::{self, branch, map, Op};
+//!
+//! let pipeline = pipeline::new()
+//!     .chain(
+//!         branch(|x: &i32| *x >= 0)
+//!             .route(true, map(|x: i32| format!("{x} is positive")))
+//!             .route(false, map(|x: i32| format!("{x} is negative"))),
+//!     );
+//!
+//! let result = pipeline.call(-1).await?;
+//! assert_eq!(result, "-1 is negative");
+//! ```
+//!
+//! With a model-based classifier:
+//! ```rust
+//! use rig::pipeline::{self, branch, classifier, Op};
+//!
+//! let router = branch(classifier(&classifier_agent, ["billing", "technical"]))
+//!     .route("billing", pipeline::new().prompt(billing_agent))
+//!     .route("technical", pipeline::new().prompt(technical_agent))
+//!     .default(pipeline::new().prompt(support_agent));
+//!
+//! let response = router.call("My invoice is wrong".to_string()).await??;
+//! ```
+
+use std::future::{Future, IntoFuture};
+
+use crate::{
+    completion::{self, PromptError},
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend},
+};
+
+use super::Op;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BranchError {
+    /// The classifier failed to classify the input
+    #[error("Failed to classify input: {0}")]
+    ClassificationError(#[from] PromptError),
+
+    /// No branch matches the label of the input, and there is no default branch
+    #[error("No branch for label: {0}")]
+    NoBranch(String),
+}
+
+/// Classifier choosing the branch of each input of a [Branch], by labeling it.
+///
+/// Implemented by the functions of a reference to the input (e.g.: predicates) and by the
+/// model-based [PromptClassifier].
+pub trait Classifier<In>: Send + Sync {
+    type Label: PartialEq + std::fmt::Debug + Send + Sync;
+
+    fn classify(
+        &self,
+        input: &In,
+    ) -> impl Future<Output = Result<Self::Label, BranchError>> + WasmCompatSend;
+}
+
+impl<F, In, L> Classifier<In> for F
+where
+    F: Fn(&In) -> L + Send + Sync,
+    In: Sync,
+    L: PartialEq + std::fmt::Debug + Send + Sync,
+{
+    type Label = L;
+
+    async fn classify(&self, input: &In) -> Result<Self::Label, BranchError> {
+        Ok(self(input))
+    }
+}
+
+/// Model-based [Classifier], prompting an agent (or any other type implementing the
+/// [Prompt](completion::Prompt) trait) to choose the label of the input among a set of labels.
+///
+/// The answer of the model is matched against the labels case-insensitively. An answer which is
+/// none of the labels is returned as is (and thus routed to the default branch, if any).
+pub struct PromptClassifier<P> {
+    prompt: P,
+    labels: Vec<String>,
+}
+
+impl<P> PromptClassifier<P> {
+    pub fn new(prompt: P, labels: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            prompt,
+            labels: labels.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    fn instructions(&self, input: &str) -> String {
+        format!(
+            "Classify the following input into exactly one of these categories: {}.\n\
+            Answer with the name of the category only.\n\nInput: {input}",
+            self.labels.join(", ")
+        )
+    }
+
+    fn label(&self, answer: &str) -> String {
+        let answer = answer
+            .trim()
+            .trim_matches(|c: char| c == '"' || c == '\'' || c == '`' || c == '.');
+        self.labels
+            .iter()
+            .find(|label| label.eq_ignore_ascii_case(answer))
+            .cloned()
+            .unwrap_or_else(|| answer.to_string())
+    }
+}
+
+impl<P, In> Classifier<In> for PromptClassifier<P>
+where
+    P: completion::Prompt,
+    In: AsRef<str> + Sync,
+{
+    type Label = String;
+
+    async fn classify(&self, input: &In) -> Result<Self::Label, BranchError> {
+        let answer = self
+            .prompt
+            .prompt(self.instructions(input.as_ref()))
+            .into_future()
+            .await?;
+        Ok(self.label(&answer))
+    }
+}
+
+/// Create a new model-based classifier choosing among `labels`.
+/// See [PromptClassifier].
+pub fn classifier<P>(
+    prompt: P,
+    labels: impl IntoIterator<Item = impl Into<String>>,
+) -> PromptClassifier<P>
+where
+    P: completion::Prompt,
+{
+    PromptClassifier::new(prompt, labels)
+}
+
+/// Object-safe version of [Op], for the branches of different types.
+trait DynOp<In, Out>: Send + Sync {
+    fn call_dyn<'a>(&'a self, input: In) -> WasmBoxedFuture<'a, Out>
+    where
+        In: 'a;
+}
+
+impl<T: Op> DynOp<T::Input, T::Output> for T {
+    fn call_dyn<'a>(&'a self, input: T::Input) -> WasmBoxedFuture<'a, T::Output>
+    where
+        T::Input: 'a,
+    {
+        Box::pin(self.call(input))
+    }
+}
+
+type BoxedOp<In, Out> = Box<dyn DynOp<In, Out>>;
+
+/// Op routing its input to the branch matching the label given by its classifier, or to the
+/// default branch if none matches.
+pub struct Branch<C, In, Out>
+where
+    C: Classifier<In>,
+{
+    classifier: C,
+    routes: Vec<(C::Label, BoxedOp<In, Out>)>,
+    default: Option<BoxedOp<In, Out>>,
+}
+
+impl<C, In, Out> Branch<C, In, Out>
+where
+    C: Classifier<In>,
+    In: Send + Sync + 'static,
+    Out: Send + Sync + 'static,
+{
+    pub(crate) fn new(classifier: C) -> Self {
+        Self {
+            classifier,
+            routes: Vec::new(),
+            default: None,
+        }
+    }
+
+    /// Add a branch taking the inputs labeled `label`.
+    /// If several branches have the same label, the first one is taken.
+    pub fn route(
+        mut self,
+        label: impl Into<C::Label>,
+        op: impl Op<Input = In, Output = Out> + 'static,
+    ) -> Self {
+        self.routes.push((label.into(), Box::new(op)));
+        self
+    }
+
+    /// Set the branch taking the inputs whose label matches no other branch.
+    pub fn default(mut self, op: impl Op<Input = In, Output = Out> + 'static) -> Self {
+        self.default = Some(Box::new(op));
+        self
+    }
+}
+
+impl<C, In, Out> Op for Branch<C, In, Out>
+where
+    C: Classifier<In>,
+    In: Send + Sync,
+    Out: Send + Sync,
+{
+    type Input = In;
+    type Output = Result<Out, BranchError>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let label = self.classifier.classify(&input).await?;
+
+        let op = self
+            .routes
+            .iter()
+            .find(|(route, _)| *route == label)
+            .map(|(_, op)| op)
+            .or(self.default.as_ref())
+            .ok_or_else(|| BranchError::NoBranch(format!("{label:?}")))?;
+
+        Ok(op.call_dyn(input).await)
+    }
+}
+
+/// Create a new branching op, routing each input to the branch matching its label given by
+/// `classifier` (e.g.: a predicate `|x: &i32| *x > 0` for the branches `true` and `false`).
+pub fn branch<C, In, Out>(classifier: C) -> Branch<C, In, Out>
+where
+    C: Classifier<In>,
+    In: Send + Sync + 'static,
+    Out: Send + Sync + 'static,
+{
+    Branch::new(classifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{self, agent_ops::tests::MockModel, map};
+
+    #[tokio::test]
+    async fn test_branch() {
+        let op = branch(|x: &i32| *x >= 0)
+            .route(true, map(|x: i32| x * 2))
+            .route(false, pipeline::new().map(|x: i32| -x).map(|x| x + 1));
+
+        assert_eq!(op.call(2).await.unwrap(), 4);
+        assert_eq!(op.call(-2).await.unwrap(), 3);
+
+        let op = branch(|x: &i32| x % 3).route(0, map(|x: i32| x / 3));
+        assert_eq!(op.call(6).await.unwrap(), 2);
+        assert!(matches!(op.call(5).await, Err(BranchError::NoBranch(label)) if label == "2"));
+
+        // MockModel answers "Mock response: <prompt>", i.e.: none of the labels
+        let op = branch(classifier(MockModel, ["billing", "technical"]))
+            .route("billing", map(|_: String| "billing"))
+            .default(map(|_: String| "default"));
+        assert_eq!(op.call("Hello".to_string()).await.unwrap(), "default");
+
+        let classifier = PromptClassifier::new(MockModel, ["Billing", "Technical"]);
+        assert_eq!(classifier.label(" technical.\n"), "Technical");
+        assert_eq!(classifier.label("\"BILLING\""), "Billing");
+    }
+}
@@ -0,0 +1,221 @@
+//! Memoization of the expensive steps of a pipeline (e.g.: retrieval or extraction stages).
+//!
+//! The outputs of a cached op are stored in a [CacheBackend] under its name and the hash of
+//! their input, so that the op is only called for new inputs (or after the expiration of the
+//! cached outputs). See [Op::cached] and [TryOp::try_cached].
+//!
+//! # Example
+//! ```rust
+//! use rig::{cache::DiskCache, pipeline::{self, TryOp}};
+//!
+//! let pipeline = pipeline::new()
+//!     .prompt(summarizer)
+//!     .try_cached("summarize", DiskCache::new(".rig-cache/summaries")?)
+//!     .ttl(Duration::from_secs(24 * 3600));
+//!
+//! let summary = pipeline.try_call(document).await?;
+//! ```
+//!
+//! Errors of the cache backend are logged and the op is called as if the output was not cached.
+
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{op, TryOp};
+use crate::cache::CacheBackend;
+
+/// Cache configuration shared by [Cached] and [TryCached].
+struct Cache<B> {
+    backend: B,
+    name: String,
+    ttl: Option<Duration>,
+}
+
+impl<B: CacheBackend> Cache<B> {
+    fn new(name: &str, backend: B) -> Self {
+        Self {
+            backend,
+            name: name.to_string(),
+            ttl: None,
+        }
+    }
+
+    fn key(&self, input: &impl Serialize) -> Option<String> {
+        match serde_json::to_string(input) {
+            Ok(input) => Some(format!(
+                "{}-{:x}",
+                self.name,
+                Sha256::digest(input.as_bytes())
+            )),
+            Err(err) => {
+                tracing::warn!(target: "rig", "Failed to hash the input of a cached op: {}", err);
+                None
+            }
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        match self.backend.get(key).await {
+            Ok(value) => value.and_then(|value| serde_json::from_value(value).ok()),
+            Err(err) => {
+                tracing::warn!(target: "rig", "Pipeline cache lookup failed: {}", err);
+                None
+            }
+        }
+    }
+
+    async fn set(&self, key: &str, output: &impl Serialize) {
+        let result = match serde_json::to_value(output) {
+            Ok(value) => self.backend.set(key, value, self.ttl).await,
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = result {
+            tracing::warn!(target: "rig", "Failed to cache the output of an op: {}", err);
+        }
+    }
+}
+
+macro_rules! cache_builder {
+    ($name:ident) => {
+        impl<Op, B: CacheBackend> $name<Op, B> {
+            pub(crate) fn new(op: Op, name: &str, backend: B) -> Self {
+                Self {
+                    op,
+                    cache: Cache::new(name, backend),
+                }
+            }
+
+            /// Set the time to live of the cached outputs (default: no expiration).
+            pub fn ttl(mut self, ttl: Duration) -> Self {
+                self.cache.ttl = Some(ttl);
+                self
+            }
+
+            /// Get the cache backend
+            pub fn backend(&self) -> &B {
+                &self.cache.backend
+            }
+        }
+    };
+}
+
+/// Op memoizing the outputs of an op by input hash. See [Op::cached](op::Op::cached).
+pub struct Cached<Op, B> {
+    op: Op,
+    cache: Cache<B>,
+}
+
+cache_builder!(Cached);
+
+impl<Op, B> op::Op for Cached<Op, B>
+where
+    Op: op::Op,
+    Op::Input: Serialize,
+    Op::Output: Serialize + DeserializeOwned,
+    B: CacheBackend,
+{
+    type Input = Op::Input;
+    type Output = Op::Output;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let Some(key) = self.cache.key(&input) else {
+            return self.op.call(input).await;
+        };
+        if let Some(output) = self.cache.get(&key).await {
+            return output;
+        }
+
+        let output = self.op.call(input).await;
+        self.cache.set(&key, &output).await;
+        output
+    }
+}
+
+/// Op memoizing the successful outputs of a fallible op by input hash (the errors are not
+/// cached). See [TryOp::try_cached].
+pub struct TryCached<Op, B> {
+    op: Op,
+    cache: Cache<B>,
+}
+
+cache_builder!(TryCached);
+
+impl<Op, B> op::Op for TryCached<Op, B>
+where
+    Op: TryOp,
+    Op::Input: Serialize,
+    Op::Output: Serialize + DeserializeOwned,
+    B: CacheBackend,
+{
+    type Input = Op::Input;
+    type Output = Result<Op::Output, Op::Error>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let Some(key) = self.cache.key(&input) else {
+            return self.op.try_call(input).await;
+        };
+        if let Some(output) = self.cache.get(&key).await {
+            return Ok(output);
+        }
+
+        let output = self.op.try_call(input).await?;
+        self.cache.set(&key, &output).await;
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        cache::InMemoryCache,
+        pipeline::{map, Op},
+    };
+
+    #[tokio::test]
+    async fn test_cached() {
+        let calls = AtomicUsize::new(0);
+        let op = map(|x: i32| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            x * 2
+        })
+        .cached("double", InMemoryCache::new());
+
+        assert_eq!(op.call(1).await, 2);
+        assert_eq!(op.call(1).await, 2);
+        assert_eq!(op.call(2).await, 4);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(op.backend().len(), 2);
+
+        // Errors are not cached
+        let calls = AtomicUsize::new(0);
+        let op = map(|x: i32| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            if x >= 0 {
+                Ok(x.to_string())
+            } else {
+                Err("negative")
+            }
+        })
+        .try_cached("to_string", InMemoryCache::new())
+        .ttl(Duration::from_secs(60));
+
+        assert_eq!(op.call(1).await, Ok("1".to_string()));
+        assert_eq!(op.call(1).await, Ok("1".to_string()));
+        assert_eq!(op.call(-1).await, Err("negative"));
+        assert_eq!(op.call(-1).await, Err("negative"));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // The outputs of the ops sharing a backend are stored under their names
+        let backend = InMemoryCache::new();
+        let double = map(|x: i32| x * 2).cached("double", backend.clone());
+        let square = map(|x: i32| x * x).cached("square", backend.clone());
+        assert_eq!(double.call(3).await, 6);
+        assert_eq!(square.call(3).await, 9);
+        assert_eq!(backend.len(), 2);
+    }
+}
@@ -0,0 +1,366 @@
+//! Named entity extraction.
+//!
+//! The [EntityExtractor] extracts the entities of a set of types (people, organizations, dates,
+//! locations or custom types) from texts with an [Extractor]. The long texts are split into
+//! chunks extracted concurrently, and the mentions of the same entity are deduplicated, with the
+//! spans of all their occurrences in the text.
+//!
+//! The entities can then be stored in a memory or a knowledge graph, e.g.: as nodes of their
+//! type linked to the documents mentioning them.
+//!
+//! # Example
+//! ```rust
+//! use rig::{pipeline::entities::{EntityExtractor, EntityType}, providers::openai};
+//!
+//! let model = openai::Client::from_env().completion_model(openai::GPT_4O_MINI);
+//!
+//! let extractor = EntityExtractor::new(model)
+//!     .entity_type(EntityType::new("product", "A product or a service"))
+//!     .concurrency(8);
+//!
+//! let entities = extractor
+//!     .extract("Tim Cook presented the iPhone 16 in Cupertino on September 9, 2024.")
+//!     .await?;
+//! for entity in entities {
+//!     println!("{} ({}): {:?}", entity.text, entity.kind, entity.spans);
+//! }
+//! ```
+//!
+//! The extractor is also an op, to be used in pipelines:
+//! ```rust
+//! let pipeline = pipeline::new()
+//!     .chain(EntityExtractor::new(model))
+//!     .map_ok(|entities| entities.len());
+//! ```
+
+use std::{collections::HashSet, ops::Range, sync::Arc};
+
+use futures::{stream, StreamExt, TryStreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::Op;
+use crate::{
+    chunking::{RecursiveSplitter, TextSplitter},
+    completion::CompletionModel,
+    extractor::{ExtractionError, Extractor, ExtractorBuilder},
+};
+
+pub const PERSON: &str = "person";
+pub const ORGANIZATION: &str = "organization";
+pub const DATE: &str = "date";
+pub const LOCATION: &str = "location";
+
+/// Type of the entities extracted by an [EntityExtractor].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntityType {
+    pub name: String,
+    /// Description of the entities of the type, for the model
+    pub description: String,
+}
+
+impl EntityType {
+    pub fn new(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+        }
+    }
+}
+
+/// Entity extracted from a text.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entity {
+    /// The name of the type of the entity
+    pub kind: String,
+    /// The entity, as first mentioned in the text
+    pub text: String,
+    /// The byte ranges of the mentions of the entity in the text, in order. Empty if the model
+    /// did not quote the entity as it is written in the text
+    pub spans: Vec<Range<usize>>,
+}
+
+/// Entities submitted by the model.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Mentions {
+    /// The entities mentioned in the text
+    entities: Vec<Mention>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Mention {
+    /// The type of the entity
+    kind: String,
+    /// The entity, exactly as it is written in the text
+    text: String,
+}
+
+/// Extractor of the named entities of texts.
+pub struct EntityExtractor<M: CompletionModel> {
+    model: M,
+    entity_types: Vec<EntityType>,
+    splitter: Arc<dyn TextSplitter>,
+    concurrency: usize,
+    extractor: Arc<Extractor<M, Mentions>>,
+}
+
+impl<M: CompletionModel> EntityExtractor<M> {
+    /// Create an entity extractor of people, organizations, dates and locations, with the
+    /// default configuration: chunks of 8000 characters and 4 concurrent requests.
+    pub fn new(model: M) -> Self {
+        let entity_types = vec![
+            EntityType::new(PERSON, "The name of a person"),
+            EntityType::new(ORGANIZATION, "The name of a company, institution or group"),
+            EntityType::new(DATE, "A date or a period of time"),
+            EntityType::new(LOCATION, "The name of a place, city, region or country"),
+        ];
+        Self {
+            extractor: Arc::new(build_extractor(model.clone(), &entity_types)),
+            model,
+            entity_types,
+            splitter: Arc::new(RecursiveSplitter::new(8000)),
+            concurrency: 4,
+        }
+    }
+
+    /// Extract the entities of the type `entity_type` too.
+    pub fn entity_type(mut self, entity_type: EntityType) -> Self {
+        self.entity_types.push(entity_type);
+        self.rebuild()
+    }
+
+    /// Only extract the entities of the types `entity_types`.
+    pub fn entity_types(mut self, entity_types: Vec<EntityType>) -> Self {
+        self.entity_types = entity_types;
+        self.rebuild()
+    }
+
+    /// Set the splitter of the texts into chunks, which should fit in the context window of the
+    /// model with the instructions.
+    pub fn splitter(mut self, splitter: impl TextSplitter + 'static) -> Self {
+        self.splitter = Arc::new(splitter);
+        self
+    }
+
+    /// Set the maximum number of concurrent requests.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    fn rebuild(mut self) -> Self {
+        self.extractor = Arc::new(build_extractor(self.model.clone(), &self.entity_types));
+        self
+    }
+
+    /// The entities of `text`, in the order of their first mention.
+    pub async fn extract(&self, text: &str) -> Result<Vec<Entity>, ExtractionError> {
+        let chunks = self
+            .splitter
+            .split_text(text)
+            .into_iter()
+            .filter(|chunk| !chunk.trim().is_empty())
+            .collect::<Vec<_>>();
+        let mentions: Vec<Mentions> = stream::iter(chunks)
+            .map(|chunk| self.extractor.extract(chunk))
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+
+        Ok(self.resolve(
+            text,
+            mentions.into_iter().flat_map(|mentions| mentions.entities),
+        ))
+    }
+
+    /// The entities of each text of `texts`, extracted concurrently.
+    pub async fn extract_batch(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<Vec<Entity>>, ExtractionError> {
+        stream::iter(texts)
+            .map(|text| self.extract(text))
+            .buffered(self.concurrency)
+            .try_collect()
+            .await
+    }
+
+    /// Deduplicate the mentions of the entities (by type and case-insensitive text), and find
+    /// their spans in `text`. The mentions of the unknown types are dropped.
+    fn resolve(&self, text: &str, mentions: impl Iterator<Item = Mention>) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = vec![];
+        let mut seen = HashSet::new();
+        for mention in mentions {
+            let Some(entity_type) = self
+                .entity_types
+                .iter()
+                .find(|entity_type| entity_type.name.eq_ignore_ascii_case(mention.kind.trim()))
+            else {
+                tracing::debug!(target: "rig",
+                    "Dropping the entity {:?} of the unknown type {:?}", mention.text, mention.kind
+                );
+                continue;
+            };
+            let mention_text = mention.text.trim();
+            if mention_text.is_empty() {
+                continue;
+            }
+
+            let key = (entity_type.name.clone(), mention_text.to_lowercase());
+            if seen.insert(key) {
+                entities.push(Entity {
+                    kind: entity_type.name.clone(),
+                    text: mention_text.to_string(),
+                    spans: find_spans(text, mention_text),
+                });
+            }
+        }
+
+        entities.sort_by_key(|entity| entity.spans.first().map_or(usize::MAX, |span| span.start));
+        entities
+    }
+}
+
+impl<M: CompletionModel> Op for EntityExtractor<M> {
+    type Input = String;
+    type Output = Result<Vec<Entity>, ExtractionError>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        self.extract(&input).await
+    }
+}
+
+fn build_extractor<M: CompletionModel>(
+    model: M,
+    entity_types: &[EntityType],
+) -> Extractor<M, Mentions> {
+    let types = entity_types
+        .iter()
+        .map(|entity_type| format!("- {}: {}", entity_type.name, entity_type.description))
+        .collect::<Vec<_>>()
+        .join("\n");
+    ExtractorBuilder::new(model)
+        .preamble(&format!(
+            "Extract all the entities of the following types mentioned in the text:\n{types}\n\n\
+            Submit every entity once, with its type and its text exactly as it is written in \
+            the text. Submit an empty list if the text mentions no entity."
+        ))
+        .build()
+}
+
+/// The byte ranges of the occurrences of `mention` in `text` as a whole word (or else, case
+/// insensitively).
+fn find_spans(text: &str, mention: &str) -> Vec<Range<usize>> {
+    let spans = word_matches(text, mention);
+    if !spans.is_empty() {
+        return spans;
+    }
+    // The ASCII lowercase keeps the byte offsets
+    word_matches(&text.to_ascii_lowercase(), &mention.to_ascii_lowercase())
+}
+
+fn word_matches(text: &str, mention: &str) -> Vec<Range<usize>> {
+    let is_boundary = |c: Option<char>| !c.is_some_and(char::is_alphanumeric);
+    text.match_indices(mention)
+        .map(|(start, _)| start..start + mention.len())
+        .filter(|span| {
+            is_boundary(text[..span.start].chars().next_back())
+                && is_boundary(text[span.end..].chars().next())
+        })
+        .collect()
+}
+
+/// Create a new entity extractor with the default configuration.
+/// See [EntityExtractor].
+pub fn entities<M: CompletionModel>(model: M) -> EntityExtractor<M> {
+    EntityExtractor::new(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::{
+            AssistantContent, CompletionError, CompletionRequest, CompletionResponse, Message,
+        },
+        OneOrMany,
+    };
+
+    /// Submits the capitalized words of the prompt as people, except the ones named in the
+    /// preamble as products
+    #[derive(Clone)]
+    struct NameModel;
+
+    impl CompletionModel for NameModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let prompt = request
+                .chat_history
+                .iter()
+                .last()
+                .and_then(Message::rag_text)
+                .unwrap_or_default();
+            let products = request.preamble.unwrap_or_default().contains("- product:");
+            let entities = prompt
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| word.starts_with(char::is_uppercase))
+                .map(|word| {
+                    let kind = if products && word.starts_with("Rig") {
+                        "product"
+                    } else if word == "Acme" {
+                        "brand"
+                    } else {
+                        "Person"
+                    };
+                    serde_json::json!({ "kind": kind, "text": word })
+                })
+                .collect::<Vec<_>>();
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::tool_call(
+                    "call_1",
+                    "submit",
+                    serde_json::json!({ "entities": entities }),
+                )),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_entities() {
+        let extractor = entities(NameModel)
+            .entity_type(EntityType::new("product", "A product"))
+            .splitter(|text: &str| text.split(". ").map(str::to_string).collect());
+
+        let text = "Alice met Bob at Acme. Bob showed Alice Rigel. Alicia left.";
+        let extracted = extractor.extract(text).await.unwrap();
+        let extracted = extracted
+            .into_iter()
+            .map(|entity| {
+                let spans = entity.spans.iter().map(|span| (span.start, span.end));
+                (entity.kind, entity.text, spans.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            extracted,
+            vec![
+                (PERSON.into(), "Alice".into(), vec![(0, 5), (34, 39)]),
+                (PERSON.into(), "Bob".into(), vec![(10, 13), (23, 26)]),
+                ("product".into(), "Rigel".into(), vec![(40, 45)]),
+                (PERSON.into(), "Alicia".into(), vec![(47, 53)]),
+            ]
+        );
+
+        let batch = extractor
+            .extract_batch(&["Carol".to_string(), "".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(batch[0][0].text, "Carol");
+        assert!(batch[1].is_empty());
+    }
+}
@@ -63,6 +63,9 @@
 //! assert_eq!(result, "Result: 2, 0");
 //! ```
 //!
+//! The results can also be joined into a struct, with one field per op, e.g.:
+//! `parallel!(Context { query: passthrough(), docs: agent_ops::lookup(index, 3) })`.
+//!
 //! Notes:
 //! - The [chain](Op::chain) method is similar to the [map](Op::map) method but it allows
 //!   for chaining arbitrary operations, as long as they implement the [Op] trait.
@@ -86,9 +89,22 @@
 //!             ▼              
 //!          Output           
 //! ```
+//!
+//! ## Branching
+//! The [branch] op routes its input to one of several sub-pipelines, chosen by a predicate or by
+//! a model-based [classifier] (see the [branch](self::branch) module).
+//!
+//! ## Ready-made pipelines
+//! - [summarize]: map-reduce summarization of long documents.
 
 pub mod agent_ops;
+pub mod branch;
+pub mod cached;
+pub mod entities;
 pub mod op;
+pub mod retry;
+pub mod summarize;
+pub mod trace;
 pub mod try_op;
 #[macro_use]
 pub mod parallel;
@@ -97,6 +113,7 @@ pub mod conditional;
 
 use std::future::Future;
 
+pub use branch::{branch, classifier};
 pub use op::{map, passthrough, then, Op};
 pub use try_op::TryOp;
 
@@ -286,6 +303,9 @@ pub enum ChainError {
 
     #[error("Failed to lookup documents: {0}")]
     LookupError(#[from] vector_store::VectorStoreError),
+
+    #[error("{0}")]
+    Timeout(#[from] crate::runtime::Elapsed),
 }
 
 pub fn new() -> PipelineBuilder<ChainError> {
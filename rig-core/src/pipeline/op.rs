@@ -1,9 +1,15 @@
-use std::future::Future;
+use std::{future::Future, time::Duration};
 
 #[allow(unused_imports)] // Needed since this is used in a macro rule
 use futures::join;
 use futures::stream;
 
+use super::{
+    cached::Cached,
+    trace::{Named, Reported},
+};
+use crate::{cache::CacheBackend, runtime::Elapsed, wasm_compat::WasmCompatSend};
+
 // ================================================================
 // Core Op trait
 // ================================================================
@@ -11,11 +17,15 @@ pub trait Op: Send + Sync {
     type Input: Send + Sync;
     type Output: Send + Sync;
 
-    fn call(&self, input: Self::Input) -> impl Future<Output = Self::Output> + Send;
+    fn call(&self, input: Self::Input) -> impl Future<Output = Self::Output> + WasmCompatSend;
 
     /// Execute the current pipeline with the given inputs. `n` is the number of concurrent
     /// inputs that will be processed concurrently.
-    fn batch_call<I>(&self, n: usize, input: I) -> impl Future<Output = Vec<Self::Output>> + Send
+    fn batch_call<I>(
+        &self,
+        n: usize,
+        input: I,
+    ) -> impl Future<Output = Vec<Self::Output>> + WasmCompatSend
     where
         I: IntoIterator<Item = Self::Input> + Send,
         I::IntoIter: Send,
@@ -167,6 +177,93 @@ pub trait Op: Send + Sync {
     {
         Sequential::new(self, Prompt::new(prompt))
     }
+
+    /// Fail with the error [Elapsed] if the current op does not complete within `timeout`
+    /// (the op is then aborted). See [TryOp::try_timeout](super::TryOp::try_timeout) for the
+    /// fallible ops.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::pipeline::{self, Op};
+    ///
+    /// let op = pipeline::new()
+    ///     .then(|x: i32| async move { x + 1 })
+    ///     .timeout(Duration::from_secs(1));
+    ///
+    /// let result = op.call(1).await;
+    /// assert_eq!(result, Ok(2));
+    /// ```
+    fn timeout(self, timeout: Duration) -> Timeout<Self>
+    where
+        Self: Sized,
+    {
+        Timeout::new(self, timeout)
+    }
+
+    /// Memoize the outputs of the current op by input hash in the cache `backend`, so that the
+    /// op is only called for new inputs. The outputs are stored under `name`, which must be
+    /// unique among the ops sharing the backend. See [TryOp::try_cached](super::TryOp::try_cached)
+    /// for the fallible ops, whose errors should not be cached.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::{cache::InMemoryCache, pipeline::{self, Op}};
+    ///
+    /// let op = pipeline::new()
+    ///     .then(|query: String| async move { expensive_retrieval(query).await })
+    ///     .cached("retrieval", InMemoryCache::new())
+    ///     .ttl(Duration::from_secs(3600));
+    /// ```
+    fn cached<B>(self, name: &str, backend: B) -> Cached<Self, B>
+    where
+        B: CacheBackend,
+        Self::Input: serde::Serialize,
+        Self::Output: serde::Serialize + serde::de::DeserializeOwned,
+        Self: Sized,
+    {
+        Cached::new(self, name, backend)
+    }
+
+    /// Name the current op: it runs in a `pipeline_step` tracing span with this name, and its
+    /// timing is recorded in the report of the enclosing [with_report](Op::with_report) op,
+    /// if any. See the [trace](super::trace) module.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::pipeline::{self, Op};
+    ///
+    /// let op = pipeline::new()
+    ///     .prompt(agent)
+    ///     .named("answer");
+    /// ```
+    fn named(self, name: &str) -> Named<Self>
+    where
+        Self: Sized,
+    {
+        Named::new(self, name)
+    }
+
+    /// Return the output of the current op with the [PipelineReport](super::trace::PipelineReport)
+    /// of the timings of the named steps which ran within it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::pipeline::{self, Op};
+    ///
+    /// let pipeline = pipeline::new()
+    ///     .chain(retrieval.named("retrieve"))
+    ///     .chain(generation.named("generate"))
+    ///     .with_report();
+    ///
+    /// let (output, report) = pipeline.call(query).await;
+    /// println!("Slowest step: {:?}", report.slowest());
+    /// ```
+    fn with_report(self) -> Reported<Self>
+    where
+        Self: Sized,
+    {
+        Reported::new(self)
+    }
 }
 
 impl<T: Op> Op for &T {
@@ -324,6 +421,27 @@ where
     Then::new(f)
 }
 
+pub struct Timeout<Op> {
+    op: Op,
+    timeout: Duration,
+}
+
+impl<Op> Timeout<Op> {
+    pub(crate) fn new(op: Op, timeout: Duration) -> Self {
+        Self { op, timeout }
+    }
+}
+
+impl<T: Op> Op for Timeout<T> {
+    type Input = T::Input;
+    type Output = Result<T::Output, Elapsed>;
+
+    #[inline]
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        crate::runtime::timeout(self.timeout, self.op.call(input)).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -350,6 +468,18 @@ mod tests {
         assert_eq!(result, 12);
     }
 
+    #[tokio::test]
+    async fn test_timeout() {
+        let op = then(|x: i32| async move {
+            crate::runtime::sleep(Duration::from_millis(x as u64)).await;
+            x
+        })
+        .timeout(Duration::from_millis(50));
+
+        assert_eq!(op.call(1).await, Ok(1));
+        assert_eq!(op.call(5000).await, Err(Elapsed(Duration::from_millis(50))));
+    }
+
     // #[tokio::test]
     // async fn test_flatten() {
     //     let op = Parallel::new(
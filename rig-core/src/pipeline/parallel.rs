@@ -158,8 +158,38 @@ macro_rules! tuple_pattern {
     };
 }
 
+/// Creates an `Op` running the given ops concurrently over (clones of) the same input, and
+/// joining their outputs into a tuple, or into a struct with one field per op.
+///
+/// # Example
+/// ```rust
+/// use rig::pipeline::{self, agent_ops, passthrough, Op};
+/// use rig::parallel;
+///
+/// struct Context {
+///     query: String,
+///     docs: Result<Vec<(f64, String, Doc)>, VectorStoreError>,
+///     category: Result<String, PromptError>,
+/// }
+///
+/// // Retrieve the documents while classifying the query
+/// let pipeline = pipeline::new().chain(parallel!(Context {
+///     query: passthrough(),
+///     docs: agent_ops::lookup(index, 3),
+///     category: agent_ops::prompt(classifier_agent),
+/// }));
+///
+/// let context = pipeline.call("What is a flurbo?".to_string()).await;
+/// ```
+///
+/// The struct form requires at least two fields.
 #[macro_export]
 macro_rules! parallel {
+    ($name:ident { $($field:ident : $es:expr),+ $(,)? }) => {{
+        use $crate::pipeline::op::Op;
+
+        $crate::parallel!($($es),+).map(|($($field),+)| $name { $($field),+ })
+    }};
     ($($es:expr),+ $(,)?) => {
         $crate::parallel_internal! {
             current_position: []
@@ -258,8 +288,15 @@ macro_rules! try_parallel_internal {
     })
 }
 
+/// Same as [parallel!](crate::parallel!), but for `TryOp`s: the op fails as soon as one of
+/// the ops fails.
 #[macro_export]
 macro_rules! try_parallel {
+    ($name:ident { $($field:ident : $es:expr),+ $(,)? }) => {{
+        use $crate::pipeline::try_op::TryOp;
+
+        $crate::try_parallel!($($es),+).map_ok(|($($field),+)| $name { $($field),+ })
+    }};
     ($($es:expr),+ $(,)?) => {
         $crate::try_parallel_internal! {
             current_position: []
@@ -379,6 +416,56 @@ mod tests {
         assert_eq!(result, (1, 2, "1 is the number!".to_string(), true));
     }
 
+    #[tokio::test]
+    async fn test_parallel_macro_struct() {
+        #[derive(Debug, PartialEq)]
+        struct Joined {
+            input: i32,
+            double: i32,
+            label: String,
+        }
+
+        let pipeline = pipeline::new().chain(parallel!(Joined {
+            input: passthrough(),
+            double: map(|x: i32| x * 2),
+            label: map(|x: i32| format!("{} is the number!", x)),
+        }));
+
+        let result = pipeline.call(1).await;
+        assert_eq!(
+            result,
+            Joined {
+                input: 1,
+                double: 2,
+                label: "1 is the number!".to_string()
+            }
+        );
+
+        #[derive(Debug, PartialEq)]
+        struct TryJoined {
+            input: i32,
+            even: bool,
+        }
+
+        let pipeline = try_parallel!(TryJoined {
+            input: map(|x: i32| Ok::<_, String>(x)),
+            even: map(|x: i32| if x > 0 {
+                Ok(x % 2 == 0)
+            } else {
+                Err("negative".to_string())
+            }),
+        });
+
+        assert_eq!(
+            pipeline.try_call(2).await,
+            Ok(TryJoined {
+                input: 2,
+                even: true
+            })
+        );
+        assert_eq!(pipeline.try_call(-2).await, Err("negative".to_string()));
+    }
+
     #[tokio::test]
     async fn test_try_parallel_chain_compile_check() {
         let chain = pipeline::new().chain(
@@ -0,0 +1,134 @@
+//! Retries of the fallible ops of a pipeline (e.g.: provider calls or external APIs), with an
+//! exponential backoff. See [TryOp::retry].
+
+use std::time::Duration;
+
+use super::{op, TryOp};
+
+/// Policy of the retries of a failing op: the op is retried up to `max_retries` times, waiting
+/// `initial_backoff` before the first retry, then `multiplier` times longer before each
+/// following one (up to `max_backoff`).
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    initial_backoff: Duration,
+    multiplier: f64,
+    max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_retries` times, with the default backoff (500ms, doubled at each retry,
+    /// up to 30s).
+    pub fn new(max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Set the duration to wait before the first retry.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Set the factor by which the backoff grows at each retry (1.0 for a constant backoff).
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Set the maximum duration to wait before a retry.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// The duration to wait before the retry number `retry` (starting at 0).
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let factor = self.multiplier.powi(retry.min(i32::MAX as usize) as i32);
+        Duration::try_from_secs_f64(self.initial_backoff.as_secs_f64() * factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+pub struct Retry<Op> {
+    op: Op,
+    policy: RetryPolicy,
+}
+
+impl<Op> Retry<Op> {
+    pub(crate) fn new(op: Op, policy: RetryPolicy) -> Self {
+        Self { op, policy }
+    }
+}
+
+impl<Op> op::Op for Retry<Op>
+where
+    Op: TryOp,
+    Op::Input: Clone,
+{
+    type Input = Op::Input;
+    type Output = Result<Op::Output, Op::Error>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let mut retries = 0;
+        loop {
+            match self.op.try_call(input.clone()).await {
+                Err(_) if retries < self.policy.max_retries => {
+                    let backoff = self.policy.backoff(retries);
+                    tracing::warn!(target: "rig",
+                        "Pipeline op failed, retrying in {:?} ({}/{})",
+                        backoff, retries + 1, self.policy.max_retries
+                    );
+                    crate::runtime::sleep(backoff).await;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::pipeline::{map, Op};
+
+    #[tokio::test]
+    async fn test_retry() {
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300));
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(300));
+
+        // Fails twice, then succeeds
+        let calls = AtomicUsize::new(0);
+        let policy = RetryPolicy::new(2).initial_backoff(Duration::from_millis(1));
+        let op = map(|x: i32| match calls.fetch_add(1, Ordering::SeqCst) {
+            0 | 1 => Err("flaky"),
+            _ => Ok(x + 1),
+        })
+        .retry(policy.clone());
+        assert_eq!(op.call(1).await, Ok(2));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let op = map(|_: i32| Err::<i32, _>("down")).retry(policy);
+        assert_eq!(op.call(1).await, Err("down"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Map-reduce summarization of long documents.
+//!
+//! The [Summarizer] splits a document into chunks, summarizes the chunks concurrently (map),
+//! then recursively summarizes the summaries by groups of `fan_in` (reduce) until a single
+//! summary remains.
+//!
+//! The number of concurrent requests is bounded by the `concurrency` of the summarizer. When the
+//! provider rate limits the requests anyway, the clients supporting rate limit coordination
+//! (see [rate_limit](crate::rate_limit)) pause all the requests and retry them.
+//!
+//! # Example
+//! ```rust
+//! use rig::{chunking::RecursiveSplitter, pipeline::summarize, providers::openai};
+//!
+//! let agent = openai::Client::from_env().agent(openai::GPT_4O_MINI).build();
+//!
+//! let summarizer = summarize::Summarizer::new(agent)
+//!     .splitter(RecursiveSplitter::new(12_000).overlap(500))
+//!     .concurrency(8)
+//!     .fan_in(4);
+//!
+//! let summary = summarizer.summarize(&long_document).await?;
+//! ```
+//!
+//! The summarizer is also an op, to be used in pipelines:
+//! ```rust
+//! let pipeline = pipeline::new()
+//!     .chain(summarize::summarize(agent))
+//!     .map_ok(|summary| format!("Summary: {summary}"));
+//! ```
+
+use std::{future::IntoFuture, sync::Arc};
+
+use futures::{stream, StreamExt, TryStreamExt};
+
+use super::Op;
+use crate::{
+    chunking::{RecursiveSplitter, TextSplitter},
+    completion::{Prompt, PromptError},
+};
+
+const MAP_INSTRUCTIONS: &str = "Write a concise summary of the following text, keeping its \
+    key facts, figures and conclusions.";
+
+const REDUCE_INSTRUCTIONS: &str = "The following are summaries of consecutive parts of a \
+    document. Combine them into a single concise summary, keeping the key facts, figures and \
+    conclusions.";
+
+/// Map-reduce summarizer of long documents, prompting an agent (or any other type implementing
+/// the [Prompt] trait).
+pub struct Summarizer<P> {
+    prompt: P,
+    splitter: Arc<dyn TextSplitter>,
+    concurrency: usize,
+    fan_in: usize,
+    map_instructions: String,
+    reduce_instructions: String,
+}
+
+impl<P: Prompt> Summarizer<P> {
+    /// Create a summarizer with the default configuration: chunks of 8000 characters (with an
+    /// overlap of 200), 4 concurrent requests and a fan-in of 5.
+    pub fn new(prompt: P) -> Self {
+        Self {
+            prompt,
+            splitter: Arc::new(RecursiveSplitter::new(8000).overlap(200)),
+            concurrency: 4,
+            fan_in: 5,
+            map_instructions: MAP_INSTRUCTIONS.to_string(),
+            reduce_instructions: REDUCE_INSTRUCTIONS.to_string(),
+        }
+    }
+
+    /// Set the splitter of the documents into chunks, which should fit in the context window
+    /// of the model with the instructions.
+    pub fn splitter(mut self, splitter: impl TextSplitter + 'static) -> Self {
+        self.splitter = Arc::new(splitter);
+        self
+    }
+
+    /// Set the maximum number of concurrent requests.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set the number of summaries combined by each reduce request (at least 2).
+    pub fn fan_in(mut self, fan_in: usize) -> Self {
+        self.fan_in = fan_in.max(2);
+        self
+    }
+
+    /// Set the instructions of the summaries of the chunks.
+    pub fn map_instructions(mut self, instructions: &str) -> Self {
+        self.map_instructions = instructions.to_string();
+        self
+    }
+
+    /// Set the instructions of the combination of the summaries.
+    pub fn reduce_instructions(mut self, instructions: &str) -> Self {
+        self.reduce_instructions = instructions.to_string();
+        self
+    }
+
+    /// Summarize `text`. An empty text has an empty summary.
+    pub async fn summarize(&self, text: &str) -> Result<String, PromptError> {
+        let chunks = self
+            .splitter
+            .split_text(text)
+            .into_iter()
+            .filter(|chunk| !chunk.trim().is_empty());
+        let mut summaries = self.prompt_all(&self.map_instructions, chunks).await?;
+
+        let mut depth = 0;
+        while summaries.len() > 1 {
+            depth += 1;
+            tracing::debug!(target: "rig",
+                "Reducing {} summaries (depth {})", summaries.len(), depth
+            );
+            let groups = summaries
+                .chunks(self.fan_in)
+                .map(|group| group.join("\n\n---\n\n"))
+                .collect::<Vec<_>>();
+            summaries = self
+                .prompt_all(&self.reduce_instructions, groups.into_iter())
+                .await?;
+        }
+
+        Ok(summaries.pop().unwrap_or_default())
+    }
+
+    async fn prompt_all(
+        &self,
+        instructions: &str,
+        texts: impl Iterator<Item = String> + Send,
+    ) -> Result<Vec<String>, PromptError> {
+        stream::iter(texts)
+            .map(|text| {
+                self.prompt
+                    .prompt(format!("{instructions}\n\n<text>\n{text}\n</text>"))
+                    .into_future()
+            })
+            .buffered(self.concurrency)
+            .try_collect()
+            .await
+    }
+}
+
+impl<P: Prompt> Op for Summarizer<P> {
+    type Input = String;
+    type Output = Result<String, PromptError>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        self.summarize(&input).await
+    }
+}
+
+/// Create a new map-reduce summarizer with the default configuration.
+/// See [Summarizer].
+pub fn summarize<P: Prompt>(prompt: P) -> Summarizer<P> {
+    Summarizer::new(prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::completion::Message;
+
+    /// Answers with the number of the request
+    #[derive(Default)]
+    struct CountingModel {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl Prompt for CountingModel {
+        #[allow(refining_impl_trait)]
+        async fn prompt(&self, prompt: impl Into<Message> + Send) -> Result<String, PromptError> {
+            let Message::User { content } = prompt.into() else {
+                unreachable!()
+            };
+            let crate::message::UserContent::Text(text) = content.first() else {
+                unreachable!()
+            };
+            let mut prompts = self.prompts.lock().unwrap();
+            prompts.push(text.text);
+            Ok(format!("summary {}", prompts.len()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summarize() {
+        // 7 chunks, reduced to 3 (fan-in of 3), then to 1
+        let summarizer = summarize(CountingModel::default())
+            .splitter(|text: &str| text.split(' ').map(str::to_string).collect())
+            .fan_in(3);
+
+        let summary = summarizer.summarize("a b c d e f g").await.unwrap();
+        assert_eq!(summary, "summary 11");
+        assert_eq!(summarizer.summarize("").await.unwrap(), "");
+
+        let prompts = summarizer.prompt.prompts.lock().unwrap();
+        assert!(prompts[0].starts_with(MAP_INSTRUCTIONS));
+        assert!(prompts[0].contains("<text>\na\n</text>"));
+        assert!(prompts[7].starts_with(REDUCE_INSTRUCTIONS));
+        assert!(prompts[9].contains("summary 7\n</text>"));
+        assert!(prompts[10].contains("summary 8\n\n---\n\nsummary 9\n\n---\n\nsummary 10"));
+    }
+}
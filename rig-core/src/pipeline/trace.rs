@@ -0,0 +1,231 @@
+//! Tracing and timing of the steps of a pipeline.
+//!
+//! - A named step (see [Op::named]) runs in a `pipeline_step` tracing span (target `rig`)
+//!   carrying its name, and logs its duration when it completes.
+//! - A reported op (see [Op::with_report]) returns its output with a [PipelineReport] of the
+//!   timings of the named steps which ran within it, so that the slow stages of multi-op
+//!   pipelines can be identified without manual logging.
+//!
+//! # Example
+//! ```rust
+//! use rig::pipeline::{self, agent_ops, parallel, passthrough, Op};
+//!
+//! let pipeline = pipeline::new()
+//!     .chain(parallel!(
+//!         passthrough(),
+//!         agent_ops::lookup::<_, _, Document>(index, 3).named("retrieve"),
+//!     ))
+//!     .map(|(query, docs)| format_prompt(query, docs))
+//!     .chain(agent_ops::prompt(agent).named("answer"))
+//!     .with_report();
+//!
+//! let (answer, report) = pipeline.call("What is a flurbo?".to_string()).await;
+//! println!("{report}");
+//! ```
+//!
+//! The steps are recorded by the reported op they run within, whatever the async runtime, as
+//! long as they are not spawned on another task.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tracing::Instrument;
+use web_time::Instant;
+
+use super::op;
+
+/// Timing of a named step of a pipeline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepTiming {
+    /// Name of the step
+    pub name: String,
+    /// Start of the step, relative to the start of the reported op
+    pub started_at: Duration,
+    /// Duration of the step
+    pub duration: Duration,
+}
+
+/// Report of the timings of the named steps of a pipeline run, in the order of their
+/// completion.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PipelineReport {
+    /// Duration of the whole run
+    pub total: Duration,
+    pub steps: Vec<StepTiming>,
+}
+
+impl PipelineReport {
+    /// The slowest step, if any.
+    pub fn slowest(&self) -> Option<&StepTiming> {
+        self.steps.iter().max_by_key(|step| step.duration)
+    }
+
+    /// The timings of the steps named `name` (a step can run several times, e.g.: in batches).
+    pub fn step<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a StepTiming> {
+        self.steps.iter().filter(move |step| step.name == name)
+    }
+}
+
+impl fmt::Display for PipelineReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Pipeline run: {:?}", self.total)?;
+        for step in &self.steps {
+            writeln!(
+                f,
+                "  {}: {:?} (started at {:?})",
+                step.name, step.duration, step.started_at
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct Recorder {
+    start: Instant,
+    steps: Mutex<Vec<StepTiming>>,
+}
+
+thread_local! {
+    /// Recorder of the reported op being polled on the current thread, if any.
+    static RECORDER: RefCell<Option<Arc<Recorder>>> = const { RefCell::new(None) };
+}
+
+/// Future polling its inner future with `recorder` as the current recorder.
+struct WithRecorder<F> {
+    future: Pin<Box<F>>,
+    recorder: Arc<Recorder>,
+}
+
+impl<F: Future> Future for WithRecorder<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let previous = RECORDER.with(|current| current.replace(Some(self.recorder.clone())));
+        let poll = self.future.as_mut().poll(cx);
+        RECORDER.with(|current| *current.borrow_mut() = previous);
+        poll
+    }
+}
+
+/// Op running its inner op in a named tracing span, and recording its timing in the report of
+/// the enclosing reported op, if any. See [Op::named](op::Op::named).
+pub struct Named<Op> {
+    op: Op,
+    name: String,
+}
+
+impl<Op> Named<Op> {
+    pub(crate) fn new(op: Op, name: &str) -> Self {
+        Self {
+            op,
+            name: name.to_string(),
+        }
+    }
+}
+
+impl<Op: op::Op> op::Op for Named<Op> {
+    type Input = Op::Input;
+    type Output = Op::Output;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let recorder = RECORDER.with(|current| current.borrow().clone());
+        let start = Instant::now();
+
+        let span = tracing::info_span!(target: "rig", "pipeline_step", name = %self.name);
+        let output = self.op.call(input).instrument(span).await;
+
+        let duration = start.elapsed();
+        tracing::debug!(target: "rig", "Pipeline step {} completed in {:?}", self.name, duration);
+        if let Some(recorder) = recorder {
+            recorder.steps.lock().unwrap().push(StepTiming {
+                name: self.name.clone(),
+                started_at: start.saturating_duration_since(recorder.start),
+                duration,
+            });
+        }
+        output
+    }
+}
+
+/// Op returning the output of its inner op with the [PipelineReport] of the named steps which
+/// ran within it. See [Op::with_report](op::Op::with_report).
+pub struct Reported<Op> {
+    op: Op,
+}
+
+impl<Op> Reported<Op> {
+    pub(crate) fn new(op: Op) -> Self {
+        Self { op }
+    }
+}
+
+impl<Op: op::Op> op::Op for Reported<Op> {
+    type Input = Op::Input;
+    type Output = (Op::Output, PipelineReport);
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let recorder = Arc::new(Recorder {
+            start: Instant::now(),
+            steps: Default::default(),
+        });
+
+        let output = WithRecorder {
+            future: Box::pin(self.op.call(input)),
+            recorder: recorder.clone(),
+        }
+        .await;
+
+        let report = PipelineReport {
+            total: recorder.start.elapsed(),
+            steps: std::mem::take(&mut *recorder.steps.lock().unwrap()),
+        };
+        (output, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parallel,
+        pipeline::{self, map, then, Op},
+    };
+
+    #[tokio::test]
+    async fn test_report() {
+        let sleep = |millis: u64| {
+            then(move |x: i32| async move {
+                crate::runtime::sleep(Duration::from_millis(millis)).await;
+                x
+            })
+        };
+
+        let pipeline = pipeline::new()
+            .chain(sleep(10).named("first"))
+            .chain(parallel!(
+                sleep(50).named("slow"),
+                map(|x: i32| x * 2).named("fast"),
+            ))
+            .map(|(x, y)| x + y)
+            .with_report();
+
+        let (output, report) = pipeline.call(1).await;
+        assert_eq!(output, 3);
+
+        let names = report.steps.iter().map(|s| &s.name[..]).collect::<Vec<_>>();
+        assert_eq!(names, vec!["first", "fast", "slow"]);
+        assert_eq!(report.slowest().unwrap().name, "slow");
+        assert!(report.step("slow").next().unwrap().started_at >= Duration::from_millis(10));
+        assert!(report.total >= Duration::from_millis(60));
+
+        // Steps outside of a reported op are only traced
+        assert_eq!(sleep(1).named("alone").call(1).await, 1);
+    }
+}
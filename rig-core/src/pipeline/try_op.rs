@@ -1,10 +1,16 @@
-use std::future::Future;
+use std::{future::Future, time::Duration};
 
 use futures::stream;
 #[allow(unused_imports)] // Needed since this is used in a macro rule
 use futures::try_join;
 
-use super::op::{self};
+use super::{
+    cached::TryCached,
+    op::{self},
+    retry::{Retry, RetryPolicy},
+};
+use crate::wasm_compat::WasmCompatSend;
+use crate::{cache::CacheBackend, runtime::Elapsed};
 
 // ================================================================
 // Core TryOp trait
@@ -18,7 +24,7 @@ pub trait TryOp: Send + Sync {
     fn try_call(
         &self,
         input: Self::Input,
-    ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send;
+    ) -> impl Future<Output = Result<Self::Output, Self::Error>> + WasmCompatSend;
 
     /// Execute the current op with the given inputs. `n` is the number of concurrent
     /// inputs that will be processed concurrently.
@@ -40,7 +46,7 @@ pub trait TryOp: Send + Sync {
         &self,
         n: usize,
         input: I,
-    ) -> impl Future<Output = Result<Vec<Self::Output>, Self::Error>> + Send
+    ) -> impl Future<Output = Result<Vec<Self::Output>, Self::Error>> + WasmCompatSend
     where
         I: IntoIterator<Item = Self::Input> + Send,
         I::IntoIter: Send,
@@ -186,6 +192,72 @@ pub trait TryOp: Send + Sync {
     {
         TrySequential::new(self, op)
     }
+
+    /// Retry the current op when it fails, according to `policy` (i.e.: up to a maximum number
+    /// of times, with an exponential backoff). The op is retried with a clone of its input.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::pipeline::{self, retry::RetryPolicy, TryOp};
+    ///
+    /// let op = pipeline::new()
+    ///     .prompt(agent)
+    ///     .retry(RetryPolicy::new(3));
+    ///
+    /// let result = op.try_call("What is a flurbo?").await?;
+    /// ```
+    fn retry(self, policy: RetryPolicy) -> Retry<Self>
+    where
+        Self::Input: Clone,
+        Self: Sized,
+    {
+        Retry::new(self, policy)
+    }
+
+    /// Fail with the error `Elapsed` (converted to the error type of the current op) if the
+    /// current op does not complete within `timeout`. See [Op::timeout](op::Op::timeout) for
+    /// the ops which are not fallible.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::pipeline::{self, TryOp};
+    ///
+    /// // `ChainError` converts from `Elapsed`
+    /// let op = pipeline::new()
+    ///     .lookup::<_, _, Document>(index, 3)
+    ///     .map_err(ChainError::from)
+    ///     .try_timeout(Duration::from_secs(5))
+    ///     .retry(RetryPolicy::new(2));
+    /// ```
+    fn try_timeout(self, timeout: Duration) -> TryTimeout<Self>
+    where
+        Self::Error: From<Elapsed>,
+        Self: Sized,
+    {
+        TryTimeout::new(self, timeout)
+    }
+
+    /// Memoize the successful outputs of the current op by input hash in the cache `backend`,
+    /// so that the op is only called for new inputs (or inputs for which it failed). The
+    /// outputs are stored under `name`, which must be unique among the ops sharing the backend.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::{cache::DiskCache, pipeline::{self, TryOp}};
+    ///
+    /// let op = pipeline::new()
+    ///     .extract(extractor)
+    ///     .try_cached("extract", DiskCache::new(".rig-cache/extractions")?);
+    /// ```
+    fn try_cached<B>(self, name: &str, backend: B) -> TryCached<Self, B>
+    where
+        B: CacheBackend,
+        Self::Input: serde::Serialize,
+        Self::Output: serde::Serialize + serde::de::DeserializeOwned,
+        Self: Sized,
+    {
+        TryCached::new(self, name, backend)
+    }
 }
 
 impl<Op, T, E> TryOp for Op
@@ -345,6 +417,31 @@ where
     }
 }
 
+pub struct TryTimeout<Op> {
+    op: Op,
+    timeout: Duration,
+}
+
+impl<Op> TryTimeout<Op> {
+    pub(crate) fn new(op: Op, timeout: Duration) -> Self {
+        Self { op, timeout }
+    }
+}
+
+impl<Op> op::Op for TryTimeout<Op>
+where
+    Op: TryOp,
+    Op::Error: From<Elapsed>,
+{
+    type Input = Op::Input;
+    type Output = Result<Op::Output, Op::Error>;
+
+    #[inline]
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        crate::runtime::timeout(self.timeout, self.op.try_call(input)).await?
+    }
+}
+
 // TODO: Implement TryParallel
 // pub struct TryParallel<Op1, Op2> {
 //     op1: Op1,
@@ -472,4 +569,20 @@ mod tests {
         let result = pipeline.try_call(1).await.unwrap();
         assert_eq!(result, 15);
     }
+
+    #[tokio::test]
+    async fn test_try_timeout() {
+        let pipeline = then(|x: u64| async move {
+            crate::runtime::sleep(Duration::from_millis(x)).await;
+            Ok::<_, crate::pipeline::ChainError>(x)
+        })
+        .try_timeout(Duration::from_millis(50));
+
+        assert_eq!(pipeline.try_call(2).await.unwrap(), 2);
+        assert!(matches!(
+            pipeline.try_call(5000).await,
+            Err(crate::pipeline::ChainError::Timeout(Elapsed(timeout)))
+                if timeout == Duration::from_millis(50)
+        ));
+    }
 }
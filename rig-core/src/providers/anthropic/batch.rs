@@ -0,0 +1,171 @@
+//! Anthropic Message Batches API implementation
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::completion::{CompletionModel, CompletionResponse};
+use crate::completion::{
+    batch::{BatchCompletionModel, BatchError, BatchResults, BatchStatus},
+    CompletionError, CompletionRequest,
+};
+
+#[derive(Debug, Deserialize)]
+struct MessageBatch {
+    id: String,
+    processing_status: String,
+}
+
+/// A line of the results of a message batch
+#[derive(Debug, Deserialize)]
+struct MessageBatchResult {
+    custom_id: String,
+    result: MessageBatchResultType,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MessageBatchResultType {
+    Succeeded { message: CompletionResponse },
+    Errored { error: serde_json::Value },
+    Canceled,
+    Expired,
+}
+
+impl CompletionModel {
+    async fn get_batch(&self, batch_id: &str) -> Result<MessageBatch, BatchError> {
+        let response = self
+            .client
+            .get(&format!("/v1/messages/batches/{batch_id}"))
+            .await?
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response.json::<MessageBatch>().await?)
+        } else {
+            Err(BatchError::ProviderError(response.text().await?))
+        }
+    }
+}
+
+impl BatchCompletionModel for CompletionModel {
+    async fn submit_batch(
+        &self,
+        requests: Vec<(String, CompletionRequest)>,
+    ) -> Result<String, BatchError> {
+        let requests = requests
+            .into_iter()
+            .map(|(custom_id, request)| {
+                Ok(json!({
+                    "custom_id": custom_id,
+                    "params": self.create_completion_request(request)?,
+                }))
+            })
+            .collect::<Result<Vec<_>, BatchError>>()?;
+
+        let response = self
+            .client
+            .post("/v1/messages/batches")
+            .await?
+            .json(&json!({ "requests": requests }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response.json::<MessageBatch>().await?.id)
+        } else {
+            Err(BatchError::ProviderError(response.text().await?))
+        }
+    }
+
+    async fn batch_status(&self, batch_id: &str) -> Result<BatchStatus, BatchError> {
+        // Individual requests of an ended batch can still be errored, canceled or expired,
+        // which is reported in the results.
+        match self.get_batch(batch_id).await?.processing_status.as_str() {
+            "ended" => Ok(BatchStatus::Completed),
+            _ => Ok(BatchStatus::InProgress),
+        }
+    }
+
+    async fn batch_results(
+        &self,
+        batch_id: &str,
+    ) -> Result<BatchResults<Self::Response>, BatchError> {
+        let response = self
+            .client
+            .get(&format!("/v1/messages/batches/{batch_id}/results"))
+            .await?
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            parse_batch_results(&response.text().await?)
+        } else {
+            Err(BatchError::ProviderError(response.text().await?))
+        }
+    }
+
+    async fn cancel_batch(&self, batch_id: &str) -> Result<(), BatchError> {
+        let response = self
+            .client
+            .post(&format!("/v1/messages/batches/{batch_id}/cancel"))
+            .await?
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(BatchError::ProviderError(response.text().await?))
+        }
+    }
+}
+
+fn parse_batch_results(content: &str) -> Result<BatchResults<CompletionResponse>, BatchError> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let MessageBatchResult { custom_id, result } = serde_json::from_str(line)?;
+
+            let result = match result {
+                MessageBatchResultType::Succeeded { message } => message.try_into(),
+                MessageBatchResultType::Errored { error } => {
+                    Err(CompletionError::ProviderError(error.to_string()))
+                }
+                MessageBatchResultType::Canceled => Err(CompletionError::ProviderError(
+                    "Request was canceled".into(),
+                )),
+                MessageBatchResultType::Expired => {
+                    Err(CompletionError::ProviderError("Request expired".into()))
+                }
+            };
+
+            Ok((custom_id, result))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::AssistantContent;
+
+    #[test]
+    fn test_parse_batch_results() {
+        let content = r#"
+{"custom_id":"request-1","result":{"type":"succeeded","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-3-5-sonnet-20240620","content":[{"type":"text","text":"Hello."}],"stop_reason":"end_turn","stop_sequence":null,"usage":{"input_tokens":10,"output_tokens":5}}}}
+{"custom_id":"request-2","result":{"type":"errored","error":{"type":"error","error":{"type":"invalid_request_error","message":"Invalid model"}}}}
+{"custom_id":"request-3","result":{"type":"expired"}}
+"#;
+
+        let results = parse_batch_results(content).unwrap();
+
+        assert_eq!(
+            results["request-1"].as_ref().unwrap().choice.first(),
+            AssistantContent::text("Hello.")
+        );
+        assert!(results["request-2"].is_err());
+        assert!(results["request-3"].is_err());
+    }
+}
@@ -1,6 +1,13 @@
 //! Anthropic client api implementation
 
-use crate::{agent::AgentBuilder, extractor::ExtractorBuilder};
+use std::sync::Arc;
+
+use crate::{
+    agent::AgentBuilder,
+    auth::{self, AuthError, AuthProvider},
+    extractor::ExtractorBuilder,
+    rate_limit::RateLimiter,
+};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -75,7 +82,10 @@ impl<'a> ClientBuilder<'a> {
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
+    headers: reqwest::header::HeaderMap,
+    auth: Option<Arc<dyn AuthProvider>>,
     http_client: reqwest::Client,
+    rate_limiter: RateLimiter,
 }
 
 impl Client {
@@ -89,27 +99,29 @@ impl Client {
     pub fn new(api_key: &str, base_url: &str, betas: Option<Vec<&str>>, version: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
-            http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert("x-api-key", api_key.parse().expect("API key should parse"));
+            headers: {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert("x-api-key", api_key.parse().expect("API key should parse"));
+                headers.insert(
+                    "anthropic-version",
+                    version.parse().expect("Anthropic version should parse"),
+                );
+                if let Some(betas) = betas {
                     headers.insert(
-                        "anthropic-version",
-                        version.parse().expect("Anthropic version should parse"),
+                        "anthropic-beta",
+                        betas
+                            .join(",")
+                            .parse()
+                            .expect("Anthropic betas should parse"),
                     );
-                    if let Some(betas) = betas {
-                        headers.insert(
-                            "anthropic-beta",
-                            betas
-                                .join(",")
-                                .parse()
-                                .expect("Anthropic betas should parse"),
-                        );
-                    }
-                    headers
-                })
+                }
+                headers
+            },
+            auth: None,
+            http_client: reqwest::Client::builder()
                 .build()
                 .expect("Anthropic reqwest client should build"),
+            rate_limiter: RateLimiter::default(),
         }
     }
 
@@ -120,9 +132,47 @@ impl Client {
         ClientBuilder::new(&api_key).build()
     }
 
-    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Authenticate the requests with `auth` (e.g.: a key rotated in an environment variable with
+    /// [EnvKey](crate::auth::EnvKey), or the bearer tokens of a gateway) instead of the API key
+    /// of the client.
+    pub fn with_auth(mut self, auth: impl AuthProvider + 'static) -> Self {
+        self.headers.remove("x-api-key");
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    /// Share the rate limit state of the client with other clients (see [crate::rate_limit]).
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Send `request` once the requests of the client are not paused by a rate limit.
+    pub(crate) async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        self.rate_limiter.send(request).await
+    }
+
+    pub async fn post(&self, path: &str) -> Result<reqwest::RequestBuilder, AuthError> {
+        let url = format!("{}/{}", self.base_url, path).replace("//", "/");
+        let request = self.http_client.post(url).headers(self.headers.clone());
+        auth::authorize(request, self.auth.as_deref()).await
+    }
+
+    pub async fn get(&self, path: &str) -> Result<reqwest::RequestBuilder, AuthError> {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
-        self.http_client.post(url)
+        let request = self.http_client.get(url).headers(self.headers.clone());
+        auth::authorize(request, self.auth.as_deref()).await
     }
 
     pub fn completion_model(&self, model: &str) -> CompletionModel {
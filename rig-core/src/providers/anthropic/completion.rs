@@ -122,6 +122,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
         Ok(completion::CompletionResponse {
             choice,
             raw_response: response,
+            logprobs: None,
         })
     }
 }
@@ -250,6 +251,9 @@ impl TryFrom<message::ContentFormat> for SourceType {
             message::ContentFormat::String => Err(MessageError::ConversionError(
                 "Image urls are not supported in Anthropic".to_owned(),
             )),
+            message::ContentFormat::Blob => Err(MessageError::ConversionError(
+                "Blob handles must be resolved before being sent to Anthropic".to_owned(),
+            )),
         }
     }
 }
@@ -494,50 +498,11 @@ impl CompletionModel {
             default_max_tokens: calculate_max_tokens(model),
         }
     }
-}
-
-/// Anthropic requires a `max_tokens` parameter to be set, which is dependent on the model. If not
-/// set or if set too high, the request will fail. The following values are based on the models
-/// available at the time of writing.
-///
-/// Dev Note: This is really bad design, I'm not sure why they did it like this..
-fn calculate_max_tokens(model: &str) -> Option<u64> {
-    if model.starts_with("claude-3-5-sonnet") || model.starts_with("claude-3-5-haiku") {
-        Some(8192)
-    } else if model.starts_with("claude-3-opus")
-        || model.starts_with("claude-3-sonnet")
-        || model.starts_with("claude-3-haiku")
-    {
-        Some(4096)
-    } else {
-        None
-    }
-}
 
-#[derive(Debug, Deserialize, Serialize)]
-struct Metadata {
-    user_id: Option<String>,
-}
-
-#[derive(Default, Debug, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum ToolChoice {
-    #[default]
-    Auto,
-    Any,
-    Tool {
-        name: String,
-    },
-}
-
-impl completion::CompletionModel for CompletionModel {
-    type Response = CompletionResponse;
-
-    #[cfg_attr(feature = "worker", worker::send)]
-    async fn completion(
+    pub(crate) fn create_completion_request(
         &self,
         completion_request: completion::CompletionRequest,
-    ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+    ) -> Result<serde_json::Value, CompletionError> {
         // Note: Ideally we'd introduce provider-specific Request models to handle the
         // specific requirements of each provider. For now, we just manually check while
         // building the request as a raw JSON document.
@@ -588,23 +553,120 @@ impl completion::CompletionModel for CompletionModel {
                             input_schema: tool.parameters,
                         })
                         .collect::<Vec<_>>(),
-                    "tool_choice": ToolChoice::Auto,
+                    "tool_choice": tool_choice_json(
+                        completion_request.tool_choice,
+                        completion_request.parallel_tool_calls,
+                    ),
                 }),
             );
         }
 
+        json_utils::merge_inplace(
+            &mut request,
+            completion_request.sampling.to_json(
+                "Anthropic",
+                &completion::SamplingParamNames {
+                    top_k: Some("top_k"),
+                    stop: Some("stop_sequences"),
+                    ..completion::SamplingParamNames::NONE
+                },
+            ),
+        );
+
         if let Some(ref params) = completion_request.additional_params {
             json_utils::merge_inplace(&mut request, params.clone())
         }
 
+        Ok(request)
+    }
+}
+
+/// Anthropic requires a `max_tokens` parameter to be set, which is dependent on the model. If not
+/// set or if set too high, the request will fail. The following values are based on the models
+/// available at the time of writing.
+///
+/// Dev Note: This is really bad design, I'm not sure why they did it like this..
+fn calculate_max_tokens(model: &str) -> Option<u64> {
+    if model.starts_with("claude-3-5-sonnet") || model.starts_with("claude-3-5-haiku") {
+        Some(8192)
+    } else if model.starts_with("claude-3-opus")
+        || model.starts_with("claude-3-sonnet")
+        || model.starts_with("claude-3-haiku")
+    {
+        Some(4096)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Metadata {
+    user_id: Option<String>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    #[default]
+    Auto,
+    Any,
+    Tool {
+        name: String,
+    },
+    None,
+}
+
+impl From<completion::ToolChoice> for ToolChoice {
+    fn from(tool_choice: completion::ToolChoice) -> Self {
+        match tool_choice {
+            completion::ToolChoice::Auto => ToolChoice::Auto,
+            completion::ToolChoice::None => ToolChoice::None,
+            completion::ToolChoice::Required => ToolChoice::Any,
+            completion::ToolChoice::Tool(name) => ToolChoice::Tool { name },
+        }
+    }
+}
+
+/// The `tool_choice` of a request, disabling the parallel tool use if requested.
+pub(super) fn tool_choice_json(
+    tool_choice: completion::ToolChoice,
+    parallel_tool_calls: Option<bool>,
+) -> serde_json::Value {
+    let mut tool_choice = json!(ToolChoice::from(tool_choice));
+    if parallel_tool_calls == Some(false) {
+        tool_choice["disable_parallel_tool_use"] = true.into();
+    }
+    tool_choice
+}
+
+impl completion::CompletionModel for CompletionModel {
+    type Response = CompletionResponse;
+
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            vision: true,
+            ..Default::default()
+        }
+        .with_catalog("anthropic", &self.model)
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn completion(
+        &self,
+        completion_request: completion::CompletionRequest,
+    ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
+        let request = self.create_completion_request(completion_request)?;
+
         tracing::debug!("Anthropic completion request: {request}");
 
-        let response = self
-            .client
-            .post("/v1/messages")
-            .json(&request)
-            .send()
-            .await?;
+        let builder = http_extras
+            .apply(self.client.post("/v1/messages").await?)
+            .json(&request);
+        let response = self.client.send(builder).await?;
 
         if response.status().is_success() {
             match response.json::<ApiResponse<CompletionResponse>>().await? {
@@ -9,6 +9,7 @@
 //! let sonnet = client.completion_model(anthropic::CLAUDE_3_5_SONNET);
 //! ```
 
+pub mod batch;
 pub mod client;
 pub mod completion;
 pub mod decoders;
@@ -3,12 +3,16 @@ use futures::StreamExt;
 use serde::Deserialize;
 use serde_json::json;
 
-use super::completion::{CompletionModel, Content, Message, ToolChoice, ToolDefinition, Usage};
+use super::completion::{
+    tool_choice_json, CompletionModel, Content, Message, ToolDefinition, Usage,
+};
 use super::decoders::sse::from_response as sse_from_response;
-use crate::completion::{CompletionError, CompletionRequest};
+use crate::completion::{CompletionError, CompletionRequest, SamplingParamNames};
 use crate::json_utils::merge_inplace;
 use crate::streaming;
-use crate::streaming::{RawStreamingChoice, StreamingCompletionModel, StreamingResult};
+use crate::streaming::{
+    RawStreamingChoice, StreamingCompletionModel, StreamingResult, ToolCallDelta,
+};
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -87,6 +91,7 @@ impl StreamingCompletionModel for CompletionModel {
         completion_request: CompletionRequest,
     ) -> Result<streaming::StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>
     {
+        let http_extras = completion_request.http_extras.clone();
         let max_tokens = if let Some(tokens) = completion_request.max_tokens {
             tokens
         } else if let Some(tokens) = self.default_max_tokens {
@@ -133,21 +138,34 @@ impl StreamingCompletionModel for CompletionModel {
                             input_schema: tool.parameters,
                         })
                         .collect::<Vec<_>>(),
-                    "tool_choice": ToolChoice::Auto,
+                    "tool_choice": tool_choice_json(
+                        completion_request.tool_choice,
+                        completion_request.parallel_tool_calls,
+                    ),
                 }),
             );
         }
 
+        merge_inplace(
+            &mut request,
+            completion_request.sampling.to_json(
+                "Anthropic",
+                &SamplingParamNames {
+                    top_k: Some("top_k"),
+                    stop: Some("stop_sequences"),
+                    ..SamplingParamNames::NONE
+                },
+            ),
+        );
+
         if let Some(ref params) = completion_request.additional_params {
             merge_inplace(&mut request, params.clone())
         }
 
-        let response = self
-            .client
-            .post("/v1/messages")
-            .json(&request)
-            .send()
-            .await?;
+        let builder = http_extras
+            .apply(self.client.post("/v1/messages").await?)
+            .json(&request);
+        let response = self.client.send(builder).await?;
 
         if !response.status().is_success() {
             return Err(CompletionError::ProviderError(response.text().await?));
@@ -223,10 +241,12 @@ fn handle_event(
                 None
             }
             ContentDelta::InputJsonDelta { partial_json } => {
-                if let Some(ref mut tool_call) = current_tool_call {
-                    tool_call.input_json.push_str(partial_json);
-                }
-                None
+                let tool_call = current_tool_call.as_mut()?;
+                tool_call.input_json.push_str(partial_json);
+                Some(Ok(RawStreamingChoice::ToolCallDelta {
+                    id: tool_call.id.clone(),
+                    delta: ToolCallDelta::Arguments(partial_json.clone()),
+                }))
             }
         },
         StreamingEvent::ContentBlockStart { content_block, .. } => match content_block {
@@ -236,7 +256,10 @@ fn handle_event(
                     id: id.clone(),
                     input_json: String::new(),
                 });
-                None
+                Some(Ok(RawStreamingChoice::ToolCallDelta {
+                    id: id.clone(),
+                    delta: ToolCallDelta::Name(name.clone()),
+                }))
             }
             // Handle other content types - they don't need special handling
             _ => None,
@@ -11,6 +11,9 @@
 
 use super::openai::{send_compatible_streaming_request, TranscriptionResponse};
 
+use std::sync::Arc;
+
+use crate::auth::{self, AuthError, AuthProvider};
 use crate::json_utils::merge;
 use crate::streaming::{StreamingCompletionModel, StreamingCompletionResponse};
 use crate::{
@@ -35,6 +38,8 @@ use serde_json::json;
 pub struct Client {
     api_version: String,
     azure_endpoint: String,
+    headers: reqwest::header::HeaderMap,
+    auth: Option<Arc<dyn AuthProvider>>,
     http_client: reqwest::Client,
 }
 
@@ -42,6 +47,8 @@ pub struct Client {
 pub enum AzureOpenAIAuth {
     ApiKey(String),
     Token(String),
+    /// Credentials refreshed by an [AuthProvider] (e.g.: Azure AD tokens)
+    Provider(Arc<dyn AuthProvider>),
 }
 
 impl From<String> for AzureOpenAIAuth {
@@ -55,11 +62,12 @@ impl Client {
     ///
     /// # Arguments
     ///
-    /// * `auth` - Azure OpenAI API key or token required for authentication
+    /// * `auth` - Azure OpenAI API key, token or auth provider required for authentication
     /// * `api_version` - API version to use (e.g., "2024-10-21" for GA, "2024-10-01-preview" for preview)
     /// * `azure_endpoint` - Azure OpenAI endpoint URL, for example: https://{your-resource-name}.openai.azure.com
     pub fn new(auth: impl Into<AzureOpenAIAuth>, api_version: &str, azure_endpoint: &str) -> Self {
         let mut headers = reqwest::header::HeaderMap::new();
+        let mut auth_provider = None;
         match auth.into() {
             AzureOpenAIAuth::ApiKey(api_key) => {
                 headers.insert("api-key", api_key.parse().expect("API key should parse"));
@@ -72,13 +80,15 @@ impl Client {
                         .expect("Token should parse"),
                 );
             }
+            AzureOpenAIAuth::Provider(provider) => auth_provider = Some(provider),
         }
 
         Self {
             api_version: api_version.to_string(),
             azure_endpoint: azure_endpoint.to_string(),
+            headers,
+            auth: auth_provider,
             http_client: reqwest::Client::builder()
-                .default_headers(headers)
                 .build()
                 .expect("Azure OpenAI reqwest client should build"),
         }
@@ -114,6 +124,26 @@ impl Client {
         )
     }
 
+    /// Creates a new Azure OpenAI client authenticated by an [AuthProvider] (e.g.: Azure AD
+    /// credentials with [ClientCredentials::azure_ad](crate::auth::ClientCredentials::azure_ad)).
+    ///
+    /// # Arguments
+    ///
+    /// * `auth` - Provider of the credentials required for authentication
+    /// * `api_version` - API version to use (e.g., "2024-10-21" for GA, "2024-10-01-preview" for preview)
+    /// * `azure_endpoint` - Azure OpenAI endpoint URL
+    pub fn from_auth_provider(
+        auth: impl AuthProvider + 'static,
+        api_version: &str,
+        azure_endpoint: &str,
+    ) -> Self {
+        Self::new(
+            AzureOpenAIAuth::Provider(Arc::new(auth)),
+            api_version,
+            azure_endpoint,
+        )
+    }
+
     /// Create a new Azure OpenAI client from the `AZURE_API_KEY` or `AZURE_TOKEN`, `AZURE_API_VERSION`, and `AZURE_ENDPOINT` environment variables.
     pub fn from_env() -> Self {
         let auth = if let Ok(api_key) = std::env::var("AZURE_API_KEY") {
@@ -130,51 +160,86 @@ impl Client {
         Self::new(auth, &api_version, &azure_endpoint)
     }
 
-    fn post_embedding(&self, deployment_id: &str) -> reqwest::RequestBuilder {
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Authenticate the requests with `auth` (in addition to the API key or token of the client,
+    /// if any).
+    pub fn with_auth(mut self, auth: impl AuthProvider + 'static) -> Self {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    async fn post(&self, url: String) -> Result<reqwest::RequestBuilder, AuthError> {
+        let request = self.http_client.post(url).headers(self.headers.clone());
+        auth::authorize(request, self.auth.as_deref()).await
+    }
+
+    async fn post_embedding(
+        &self,
+        deployment_id: &str,
+    ) -> Result<reqwest::RequestBuilder, AuthError> {
         let url = format!(
             "{}/openai/deployments/{}/embeddings?api-version={}",
             self.azure_endpoint, deployment_id, self.api_version
         )
         .replace("//", "/");
-        self.http_client.post(url)
+        self.post(url).await
     }
 
-    fn post_chat_completion(&self, deployment_id: &str) -> reqwest::RequestBuilder {
+    async fn post_chat_completion(
+        &self,
+        deployment_id: &str,
+    ) -> Result<reqwest::RequestBuilder, AuthError> {
         let url = format!(
             "{}/openai/deployments/{}/chat/completions?api-version={}",
             self.azure_endpoint, deployment_id, self.api_version
         )
         .replace("//", "/");
-        self.http_client.post(url)
+        self.post(url).await
     }
 
-    fn post_transcription(&self, deployment_id: &str) -> reqwest::RequestBuilder {
+    async fn post_transcription(
+        &self,
+        deployment_id: &str,
+    ) -> Result<reqwest::RequestBuilder, AuthError> {
         let url = format!(
             "{}/openai/deployments/{}/audio/translations?api-version={}",
             self.azure_endpoint, deployment_id, self.api_version
         )
         .replace("//", "/");
-        self.http_client.post(url)
+        self.post(url).await
     }
 
     #[cfg(feature = "image")]
-    fn post_image_generation(&self, deployment_id: &str) -> reqwest::RequestBuilder {
+    async fn post_image_generation(
+        &self,
+        deployment_id: &str,
+    ) -> Result<reqwest::RequestBuilder, AuthError> {
         let url = format!(
             "{}/openai/deployments/{}/images/generations?api-version={}",
             self.azure_endpoint, deployment_id, self.api_version
         )
         .replace("//", "/");
-        self.http_client.post(url)
+        self.post(url).await
     }
 
     #[cfg(feature = "audio")]
-    fn post_audio_generation(&self, deployment_id: &str) -> reqwest::RequestBuilder {
+    async fn post_audio_generation(
+        &self,
+        deployment_id: &str,
+    ) -> Result<reqwest::RequestBuilder, AuthError> {
         let url = format!(
             "{}/openai/deployments/{}/audio/speech?api-version={}",
             self.azure_endpoint, deployment_id, self.api_version
         )
         .replace("//", "/");
-        self.http_client.post(url)
+        self.post(url).await
     }
 
     /// Create an embedding model with the given name.
@@ -383,6 +448,7 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         let response = self
             .client
             .post_embedding(&self.model)
+            .await?
             .json(&json!({
                 "input": documents,
             }))
@@ -506,15 +572,27 @@ impl CompletionModel {
                 "temperature": completion_request.temperature,
             })
         } else {
-            json!({
-                "model": self.model,
-                "messages": full_history,
-                "temperature": completion_request.temperature,
-                "tools": completion_request.tools.into_iter().map(openai::ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
-            })
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "temperature": completion_request.temperature,
+                    "tools": completion_request.tools.into_iter().map(openai::ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
         };
 
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "Azure OpenAI",
+                &completion::SamplingParamNames::OPENAI.with_logprobs(),
+            ),
+        );
+
         let request = if let Some(params) = completion_request.additional_params {
             json_utils::merge(request, params)
         } else {
@@ -528,16 +606,31 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = openai::CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        // The reasoning models (o1, o3, ...) do not return log probabilities
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            vision: true,
+            json_mode: true,
+            logprobs: !self.model.starts_with('o'),
+            constrained_decoding: false,
+            ..Default::default()
+        }
+        .with_catalog("openai", &self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<openai::CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post_chat_completion(&self.model)
+        let response = http_extras
+            .apply(self.client.post_chat_completion(&self.model).await?)
             .json(&request)
             .send()
             .await?;
@@ -571,6 +664,7 @@ impl StreamingCompletionModel for CompletionModel {
         &self,
         request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        let http_extras = request.http_extras.clone();
         let mut request = self.create_completion_request(request)?;
 
         request = merge(
@@ -581,7 +675,8 @@ impl StreamingCompletionModel for CompletionModel {
         let builder = self
             .client
             .post_chat_completion(self.model.as_str())
-            .json(&request);
+            .await?;
+        let builder = http_extras.apply(builder).json(&request);
 
         send_compatible_streaming_request(builder).await
     }
@@ -645,6 +740,7 @@ impl transcription::TranscriptionModel for TranscriptionModel {
         let response = self
             .client
             .post_transcription(&self.model)
+            .await?
             .multipart(body)
             .send()
             .await?;
@@ -701,6 +797,7 @@ mod image_generation {
             let response = self
                 .client
                 .post_image_generation(&self.model)
+                .await?
                 .json(&request)
                 .send()
                 .await?;
@@ -761,6 +858,7 @@ mod audio_generation {
             let response = self
                 .client
                 .post_audio_generation("/audio/speech")
+                .await?
                 .json(&request)
                 .send()
                 .await?;
@@ -819,8 +917,15 @@ mod azure_tests {
                 chat_history: OneOrMany::one("Hello!".into()),
                 documents: vec![],
                 max_tokens: Some(100),
+                timeout: None,
+                cancellation: None,
+                http_extras: Default::default(),
+                output_constraint: None,
                 temperature: Some(0.0),
                 tools: vec![],
+                tool_choice: Default::default(),
+                parallel_tool_calls: None,
+                sampling: Default::default(),
                 additional_params: None,
             })
             .await
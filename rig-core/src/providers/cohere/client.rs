@@ -27,6 +27,7 @@ const COHERE_API_BASE_URL: &str = "https://api.cohere.ai";
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
+    headers: reqwest::header::HeaderMap,
     http_client: reqwest::Client,
 }
 
@@ -38,17 +39,17 @@ impl Client {
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
+            headers: {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(
+                    "Authorization",
+                    format!("Bearer {}", api_key)
+                        .parse()
+                        .expect("Bearer token should parse"),
+                );
+                headers
+            },
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
                 .build()
                 .expect("Cohere reqwest client should build"),
         }
@@ -61,9 +62,17 @@ impl Client {
         Self::new(&api_key)
     }
 
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
     pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
-        self.http_client.post(url)
+        self.http_client.post(url).headers(self.headers.clone())
     }
 
     /// Note: default embedding dimension of 0 will be used if model is not known.
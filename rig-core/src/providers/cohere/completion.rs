@@ -107,6 +107,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
         Ok(completion::CompletionResponse {
             choice: OneOrMany::many(model_response).expect("There is atleast one content"),
             raw_response: response,
+            logprobs: None,
         })
     }
 }
@@ -461,13 +462,49 @@ impl CompletionModel {
                 .collect::<Vec<_>>(),
         );
 
-        let request = json!({
+        completion::request::warn_unsupported_parallel_tool_calls(
+            "Cohere",
+            completion_request.parallel_tool_calls,
+        );
+        // Cohere cannot force a specific tool: it is forced as the only tool of the request
+        let (tools, tool_choice) = match completion_request.tool_choice {
+            completion::ToolChoice::Auto => (completion_request.tools, None),
+            completion::ToolChoice::None => (completion_request.tools, Some("NONE")),
+            completion::ToolChoice::Required => (completion_request.tools, Some("REQUIRED")),
+            completion::ToolChoice::Tool(name) => (
+                completion_request
+                    .tools
+                    .into_iter()
+                    .filter(|tool| tool.name == name)
+                    .collect(),
+                Some("REQUIRED"),
+            ),
+        };
+
+        let mut request = json!({
             "model": self.model,
             "messages": full_history,
             "documents": completion_request.documents,
             "temperature": completion_request.temperature,
-            "tools": completion_request.tools.into_iter().map(Tool::from).collect::<Vec<_>>(),
+            "tools": tools.into_iter().map(Tool::from).collect::<Vec<_>>(),
         });
+        if let Some(tool_choice) = tool_choice {
+            request["tool_choice"] = tool_choice.into();
+        }
+
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "Cohere",
+                &completion::SamplingParamNames {
+                    top_k: Some("k"),
+                    stop: Some("stop_sequences"),
+                    logit_bias: None,
+                    n: None,
+                    ..completion::SamplingParamNames::OPENAI
+                },
+            ),
+        );
 
         if let Some(ref params) = completion_request.additional_params {
             Ok(json_utils::merge(request.clone(), params.clone()))
@@ -480,18 +517,35 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            // Cohere calls tools in parallel, but they cannot be disabled
+            parallel_tool_calls: false,
+            json_mode: true,
+            ..Default::default()
+        }
+        .with_catalog("cohere", &self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
         tracing::debug!(
             "Cohere request: {}",
             serde_json::to_string_pretty(&request)?
         );
 
-        let response = self.client.post("/v2/chat").json(&request).send().await?;
+        let response = http_extras
+            .apply(self.client.post("/v2/chat"))
+            .json(&request)
+            .send()
+            .await?;
 
         if response.status().is_success() {
             let text_response = response.text().await?;
@@ -615,4 +669,25 @@ mod tests {
         let completion_message: completion::Message = message.clone().try_into().unwrap();
         let _converted_back: Vec<Message> = completion_message.try_into().unwrap();
     }
+
+    #[test]
+    fn test_tool_choice() {
+        use crate::completion::CompletionModel as _;
+
+        let model = super::super::Client::new("key").completion_model(super::super::COMMAND_R);
+        let tool = |name: &str| completion::ToolDefinition {
+            name: name.to_string(),
+            description: format!("The {name} tool"),
+            parameters: serde_json::json!({"type": "object", "properties": {}}),
+        };
+        let request = model
+            .completion_request("Search the news")
+            .tools(vec![tool("search"), tool("calculator")])
+            .tool_choice(completion::ToolChoice::Tool("search".to_string()))
+            .build();
+        let request = model.create_completion_request(request).unwrap();
+        assert_eq!(request["tool_choice"], "REQUIRED");
+        assert_eq!(request["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(request["tools"][0]["function"]["name"], "search");
+    }
 }
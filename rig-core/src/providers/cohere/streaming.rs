@@ -68,6 +68,7 @@ impl StreamingCompletionModel for CompletionModel {
         request: CompletionRequest,
     ) -> Result<streaming::StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>
     {
+        let http_extras = request.http_extras.clone();
         let request = self.create_completion_request(request)?;
         let request = json_utils::merge(request, json!({"stream": true}));
 
@@ -76,7 +77,11 @@ impl StreamingCompletionModel for CompletionModel {
             serde_json::to_string_pretty(&request)?
         );
 
-        let response = self.client.post("/v2/chat").json(&request).send().await?;
+        let response = http_extras
+            .apply(self.client.post("/v2/chat"))
+            .json(&request)
+            .send()
+            .await?;
 
         if !response.status().is_success() {
             return Err(CompletionError::ProviderError(format!(
@@ -0,0 +1,339 @@
+//! Converters between rig's [Message]s and the wire formats of the messages of OpenAI, Anthropic
+//! and Gemini, in both directions. They are the conversions used by the providers, exposed to be
+//! reused, e.g.: by proxies between providers or to migrate stored transcripts.
+//!
+//! Each [WireFormat] converts:
+//! - lists of messages, to and from the message types of the provider;
+//! - [Transcript]s (the messages with their system prompt), to and from the fields of the request
+//!   bodies of the provider holding them (e.g.: `system` and `messages` for Anthropic).
+//!
+//! # Example
+//! ```rust
+//! use rig::providers::convert::{self, Anthropic, OpenAI, WireFormat};
+//!
+//! // The messages of an OpenAI chat completion request, e.g.: received by a proxy
+//! let body: serde_json::Value = serde_json::from_str(&request)?;
+//!
+//! let transcript = OpenAI::from_wire(&body)?;
+//! println!("{:?}: {} messages", transcript.system, transcript.messages.len());
+//!
+//! // The `system` and `messages` of the equivalent Anthropic request
+//! let anthropic_body = convert::convert::<OpenAI, Anthropic>(&body)?;
+//! ```
+//!
+//! The conversions are lossy where the formats differ, e.g.: the tool calls of Gemini are
+//! identified by the name of their function, and the tool results sent to OpenAI are text only.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::completion::message::{Message, MessageError};
+
+use super::{anthropic, gemini::completion::gemini_api_types, openai};
+
+/// Messages of a conversation, with its system prompt.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    pub system: Option<String>,
+    pub messages: Vec<Message>,
+}
+
+impl Transcript {
+    pub fn new(system: Option<String>, messages: Vec<Message>) -> Self {
+        Self { system, messages }
+    }
+}
+
+/// Wire format of the messages of a provider.
+pub trait WireFormat {
+    /// Message of the provider
+    type Message: Serialize + DeserializeOwned;
+
+    /// Convert rig's `messages` to the messages of the provider.
+    fn to_messages(messages: Vec<Message>) -> Result<Vec<Self::Message>, MessageError>;
+
+    /// Convert the `messages` of the provider to rig's messages.
+    fn from_messages(messages: Vec<Self::Message>) -> Result<Vec<Message>, MessageError>;
+
+    /// The fields of the request bodies of the provider holding `transcript`.
+    fn to_wire(transcript: &Transcript) -> Result<Value, MessageError>;
+
+    /// The transcript of a request body of the provider (the other fields are ignored).
+    fn from_wire(body: &Value) -> Result<Transcript, MessageError>;
+}
+
+/// Convert the transcript of a request body of the provider `F` to the fields of the request
+/// bodies of the provider `T`.
+pub fn convert<F: WireFormat, T: WireFormat>(body: &Value) -> Result<Value, MessageError> {
+    T::to_wire(&F::from_wire(body)?)
+}
+
+/// The format of the OpenAI chat completions API: the system prompt is the first message.
+pub struct OpenAI;
+
+impl WireFormat for OpenAI {
+    type Message = openai::Message;
+
+    fn to_messages(messages: Vec<Message>) -> Result<Vec<openai::Message>, MessageError> {
+        Ok(messages
+            .into_iter()
+            .map(Vec::<openai::Message>::try_from)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// The consecutive tool results (sent as separate messages to OpenAI) are merged in one user
+    /// message. The system messages are converted to user messages (see [OpenAI::from_wire]).
+    fn from_messages(messages: Vec<openai::Message>) -> Result<Vec<Message>, MessageError> {
+        let mut converted: Vec<Message> = vec![];
+        let mut previous_tool_result = false;
+        for message in messages {
+            let tool_result = matches!(message, openai::Message::ToolResult { .. });
+            let message = Message::try_from(message)?;
+            match (converted.last_mut(), message) {
+                (Some(Message::User { content: previous }), Message::User { content })
+                    if tool_result && previous_tool_result =>
+                {
+                    content
+                        .into_iter()
+                        .for_each(|content| previous.push(content));
+                }
+                (_, message) => converted.push(message),
+            }
+            previous_tool_result = tool_result;
+        }
+        Ok(converted)
+    }
+
+    fn to_wire(transcript: &Transcript) -> Result<Value, MessageError> {
+        let mut messages = vec![];
+        if let Some(system) = &transcript.system {
+            messages.push(openai::Message::system(system));
+        }
+        messages.extend(Self::to_messages(transcript.messages.clone())?);
+        Ok(json!({ "messages": messages }))
+    }
+
+    /// The system (or developer) messages are joined as the system prompt.
+    fn from_wire(body: &Value) -> Result<Transcript, MessageError> {
+        let (system, messages): (Vec<_>, Vec<_>) = field(body, "messages")?
+            .as_array()
+            .ok_or_else(|| error("`messages` should be an array"))?
+            .iter()
+            .partition(|message| {
+                matches!(
+                    message.get("role").and_then(Value::as_str),
+                    Some("system" | "developer")
+                )
+            });
+
+        Ok(Transcript {
+            system: join_texts(
+                system
+                    .into_iter()
+                    .filter_map(|message| message.get("content")),
+            ),
+            messages: Self::from_messages(deserialize(Value::Array(
+                messages.into_iter().cloned().collect(),
+            ))?)?,
+        })
+    }
+}
+
+/// The format of the Anthropic messages API: the system prompt is the `system` field.
+pub struct Anthropic;
+
+impl WireFormat for Anthropic {
+    type Message = anthropic::completion::Message;
+
+    fn to_messages(
+        messages: Vec<Message>,
+    ) -> Result<Vec<anthropic::completion::Message>, MessageError> {
+        messages.into_iter().map(TryFrom::try_from).collect()
+    }
+
+    fn from_messages(
+        messages: Vec<anthropic::completion::Message>,
+    ) -> Result<Vec<Message>, MessageError> {
+        messages.into_iter().map(TryFrom::try_from).collect()
+    }
+
+    fn to_wire(transcript: &Transcript) -> Result<Value, MessageError> {
+        let mut body = json!({ "messages": Self::to_messages(transcript.messages.clone())? });
+        if let Some(system) = &transcript.system {
+            body["system"] = system.clone().into();
+        }
+        Ok(body)
+    }
+
+    /// The `system` field is either a text, or a list of text blocks.
+    fn from_wire(body: &Value) -> Result<Transcript, MessageError> {
+        Ok(Transcript {
+            system: join_texts(body.get("system")),
+            messages: Self::from_messages(deserialize(field(body, "messages")?.clone())?)?,
+        })
+    }
+}
+
+/// The format of the Gemini `generateContent` API: the system prompt is the `systemInstruction`
+/// field.
+pub struct Gemini;
+
+impl WireFormat for Gemini {
+    type Message = gemini_api_types::Content;
+
+    fn to_messages(messages: Vec<Message>) -> Result<Vec<gemini_api_types::Content>, MessageError> {
+        messages.into_iter().map(TryFrom::try_from).collect()
+    }
+
+    fn from_messages(
+        messages: Vec<gemini_api_types::Content>,
+    ) -> Result<Vec<Message>, MessageError> {
+        messages.into_iter().map(TryFrom::try_from).collect()
+    }
+
+    fn to_wire(transcript: &Transcript) -> Result<Value, MessageError> {
+        let mut body = json!({ "contents": Self::to_messages(transcript.messages.clone())? });
+        if let Some(system) = &transcript.system {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        Ok(body)
+    }
+
+    fn from_wire(body: &Value) -> Result<Transcript, MessageError> {
+        let system = body
+            .get("systemInstruction")
+            .or_else(|| body.get("system_instruction"))
+            .and_then(|system| system.get("parts"));
+        Ok(Transcript {
+            system: join_texts(system),
+            messages: Self::from_messages(deserialize(field(body, "contents")?.clone())?)?,
+        })
+    }
+}
+
+fn error(message: &str) -> MessageError {
+    MessageError::ConversionError(message.to_string())
+}
+
+fn field<'a>(body: &'a Value, name: &str) -> Result<&'a Value, MessageError> {
+    body.get(name)
+        .ok_or_else(|| error(&format!("Missing field `{name}`")))
+}
+
+fn deserialize<T: DeserializeOwned>(value: Value) -> Result<T, MessageError> {
+    serde_json::from_value(value).map_err(|e| MessageError::ConversionError(e.to_string()))
+}
+
+/// The texts of `contents` (texts, or lists of text parts), joined by blank lines. `None` if
+/// there is no text.
+fn join_texts<'a>(contents: impl IntoIterator<Item = &'a Value>) -> Option<String> {
+    fn push_texts<'a>(content: &'a Value, texts: &mut Vec<&'a str>) {
+        match content {
+            Value::String(text) => texts.push(text),
+            Value::Array(parts) => parts.iter().for_each(|part| push_texts(part, texts)),
+            Value::Object(part) => texts.extend(part.get("text").and_then(Value::as_str)),
+            _ => {}
+        }
+    }
+
+    let mut texts = vec![];
+    contents
+        .into_iter()
+        .for_each(|content| push_texts(content, &mut texts));
+    (!texts.is_empty()).then(|| texts.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::message::{AssistantContent, ToolResultContent, UserContent},
+        OneOrMany,
+    };
+
+    /// A transcript supported by all the formats (e.g.: with the tool calls identified by the
+    /// name of their function, for Gemini)
+    fn transcript() -> Transcript {
+        let tool_result = |id: &str, result: &str| {
+            UserContent::tool_result(id, OneOrMany::one(ToolResultContent::text(result)))
+        };
+        Transcript::new(
+            Some("You are a weather bot.".to_string()),
+            vec![
+                Message::user("What is the weather in Paris, and the time?"),
+                Message::Assistant {
+                    content: OneOrMany::many(vec![
+                        AssistantContent::text("Let me check."),
+                        AssistantContent::tool_call(
+                            "get_weather",
+                            "get_weather",
+                            json!({ "city": "Paris" }),
+                        ),
+                        AssistantContent::tool_call("get_time", "get_time", json!({})),
+                    ])
+                    .unwrap(),
+                },
+                Message::User {
+                    content: OneOrMany::many(vec![
+                        tool_result("get_weather", r#"{"temperature":20}"#),
+                        tool_result("get_time", r#"{"time":"12:00"}"#),
+                    ])
+                    .unwrap(),
+                },
+                Message::assistant("It is 20°C at 12:00."),
+            ],
+        )
+    }
+
+    fn round_trip<F: WireFormat>() -> Transcript {
+        F::from_wire(&F::to_wire(&transcript()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_wire_formats() {
+        assert_eq!(round_trip::<OpenAI>(), transcript());
+        assert_eq!(round_trip::<Anthropic>(), transcript());
+        assert_eq!(round_trip::<Gemini>(), transcript());
+
+        let openai = OpenAI::to_wire(&transcript()).unwrap();
+        assert_eq!(openai["messages"][0]["role"], "system");
+        // The tool results are separate messages
+        assert_eq!(openai["messages"].as_array().unwrap().len(), 6);
+
+        let anthropic = convert::<OpenAI, Anthropic>(&openai).unwrap();
+        assert_eq!(anthropic["system"], "You are a weather bot.");
+        assert_eq!(
+            anthropic["messages"][1]["content"][1],
+            json!({
+                "type": "tool_use",
+                "id": "get_weather",
+                "name": "get_weather",
+                "input": { "city": "Paris" },
+            })
+        );
+
+        let gemini = convert::<Anthropic, Gemini>(&anthropic).unwrap();
+        assert_eq!(
+            gemini["systemInstruction"]["parts"][0]["text"],
+            "You are a weather bot."
+        );
+        assert_eq!(
+            gemini["contents"][2]["parts"][0]["functionResponse"]["response"],
+            json!({ "temperature": 20 })
+        );
+
+        // The system prompts in blocks
+        let transcript = Anthropic::from_wire(&json!({
+            "system": [{ "type": "text", "text": "Be brief." }, { "type": "text", "text": "Be nice." }],
+            "messages": [{ "role": "user", "content": "Hi" }],
+        }))
+        .unwrap();
+        assert_eq!(transcript.system.as_deref(), Some("Be brief.\n\nBe nice."));
+        assert_eq!(transcript.messages, vec![Message::user("Hi")]);
+
+        assert!(OpenAI::from_wire(&json!({})).is_err());
+    }
+}
@@ -31,6 +31,7 @@ const DEEPSEEK_API_BASE_URL: &str = "https://api.deepseek.com";
 #[derive(Clone)]
 pub struct Client {
     pub base_url: String,
+    headers: reqwest::header::HeaderMap,
     http_client: HttpClient,
 }
 
@@ -51,25 +52,33 @@ impl Client {
         // Possibly configure a custom HTTP client here if needed.
         Self {
             base_url: base_url.to_string(),
+            headers: {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(
+                    "Authorization",
+                    format!("Bearer {}", api_key)
+                        .parse()
+                        .expect("Bearer token should parse"),
+                );
+                headers
+            },
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
                 .build()
                 .expect("DeepSeek reqwest client should build"),
         }
     }
 
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
     fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
-        self.http_client.post(url)
+        self.http_client.post(url).headers(self.headers.clone())
     }
 
     /// Creates a DeepSeek completion model with the given `model_name`.
@@ -355,6 +364,8 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
             )),
         }?;
 
+        let logprobs = completion::logprobs::from_openai_choice(choice.logprobs.as_ref());
+
         let choice = OneOrMany::many(content).map_err(|_| {
             CompletionError::ResponseError(
                 "Response contained no message or tool call (empty)".to_owned(),
@@ -364,6 +375,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
         Ok(completion::CompletionResponse {
             choice,
             raw_response: response,
+            logprobs,
         })
     }
 }
@@ -410,15 +422,32 @@ impl DeepSeekCompletionModel {
                 "temperature": completion_request.temperature,
             })
         } else {
-            json!({
-                "model": self.model,
-                "messages": full_history,
-                "temperature": completion_request.temperature,
-                "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
-            })
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "temperature": completion_request.temperature,
+                    "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
         };
 
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "DeepSeek",
+                &completion::SamplingParamNames {
+                    seed: None,
+                    logit_bias: None,
+                    n: None,
+                    ..completion::SamplingParamNames::OPENAI.with_logprobs()
+                },
+            ),
+        );
+
         let request = if let Some(params) = completion_request.additional_params {
             json_utils::merge(request, params)
         } else {
@@ -432,6 +461,18 @@ impl DeepSeekCompletionModel {
 impl CompletionModel for DeepSeekCompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            json_mode: true,
+            logprobs: self.model != DEEPSEEK_REASONER,
+            ..Default::default()
+        }
+        .with_catalog("deepseek", &self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -440,11 +481,11 @@ impl CompletionModel for DeepSeekCompletionModel {
         completion::CompletionResponse<CompletionResponse>,
         crate::completion::CompletionError,
     > {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("/chat/completions")
+        let response = http_extras
+            .apply(self.client.post("/chat/completions"))
             .json(&request)
             .send()
             .await?;
@@ -469,6 +510,7 @@ impl StreamingCompletionModel for DeepSeekCompletionModel {
         &self,
         completion_request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let mut request = self.create_completion_request(completion_request)?;
 
         request = merge(
@@ -476,7 +518,9 @@ impl StreamingCompletionModel for DeepSeekCompletionModel {
             json!({"stream": true, "stream_options": {"include_usage": true}}),
         );
 
-        let builder = self.client.post("/v1/chat/completions").json(&request);
+        let builder = http_extras
+            .apply(self.client.post("/v1/chat/completions"))
+            .json(&request);
         send_compatible_streaming_request(builder).await
     }
 }
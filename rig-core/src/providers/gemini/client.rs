@@ -1,5 +1,8 @@
+use std::sync::Arc;
+
 use crate::{
     agent::AgentBuilder,
+    auth::{self, AuthError, AuthProvider},
     embeddings::{self},
     extractor::ExtractorBuilder,
     Embed,
@@ -20,6 +23,8 @@ const GEMINI_API_BASE_URL: &str = "https://generativelanguage.googleapis.com";
 pub struct Client {
     base_url: String,
     api_key: String,
+    headers: reqwest::header::HeaderMap,
+    auth: Option<Arc<dyn AuthProvider>>,
     http_client: reqwest::Client,
 }
 
@@ -31,15 +36,16 @@ impl Client {
         Self {
             base_url: base_url.to_string(),
             api_key: api_key.to_string(),
+            headers: {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(
+                    reqwest::header::CONTENT_TYPE,
+                    "application/json".parse().unwrap(),
+                );
+                headers
+            },
+            auth: None,
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        reqwest::header::CONTENT_TYPE,
-                        "application/json".parse().unwrap(),
-                    );
-                    headers
-                })
                 .build()
                 .expect("Gemini reqwest client should build"),
         }
@@ -52,19 +58,53 @@ impl Client {
         Self::new(&api_key)
     }
 
-    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
-        let url = format!("{}/{}?key={}", self.base_url, path, self.api_key).replace("//", "/");
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Authenticate the requests with `auth` (e.g.: Google Cloud access tokens with
+    /// [CloudMetadata::gcp](crate::auth::CloudMetadata::gcp)) instead of the API key of the
+    /// client.
+    pub fn with_auth(mut self, auth: impl AuthProvider + 'static) -> Self {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
 
-        tracing::debug!("POST {}/{}?key={}", self.base_url, path, "****");
-        self.http_client.post(url)
+    pub async fn post(&self, path: &str) -> Result<reqwest::RequestBuilder, AuthError> {
+        self.post_with_query(path, "").await
     }
 
-    pub fn post_sse(&self, path: &str) -> reqwest::RequestBuilder {
-        let url =
-            format!("{}/{}?alt=sse&key={}", self.base_url, path, self.api_key).replace("//", "/");
+    pub async fn post_sse(&self, path: &str) -> Result<reqwest::RequestBuilder, AuthError> {
+        self.post_with_query(path, "alt=sse&").await
+    }
 
-        tracing::debug!("POST {}/{}?alt=sse&key={}", self.base_url, path, "****");
-        self.http_client.post(url)
+    async fn post_with_query(
+        &self,
+        path: &str,
+        query: &str,
+    ) -> Result<reqwest::RequestBuilder, AuthError> {
+        let Some(auth) = &self.auth else {
+            let url = format!("{}/{}?{}key={}", self.base_url, path, query, self.api_key)
+                .replace("//", "/");
+
+            tracing::debug!("POST {}/{}?{}key={}", self.base_url, path, query, "****");
+            return Ok(self.http_client.post(url).headers(self.headers.clone()));
+        };
+
+        let url = format!("{}/{}?{}", self.base_url, path, query)
+            .trim_end_matches(['?', '&'])
+            .replace("//", "/");
+
+        tracing::debug!("POST {}", url);
+        auth::authorize(
+            self.http_client.post(url).headers(self.headers.clone()),
+            Some(auth.as_ref()),
+        )
+        .await
     }
 
     /// Create an embedding model with the given name.
@@ -15,8 +15,9 @@ pub const GEMINI_1_5_PRO_8B: &str = "gemini-1.5-pro-8b";
 pub const GEMINI_1_0_PRO: &str = "gemini-1.0-pro";
 
 use gemini_api_types::{
-    Content, FunctionDeclaration, GenerateContentRequest, GenerateContentResponse,
-    GenerationConfig, Part, Role, Tool,
+    Content, FunctionCallingConfig, FunctionCallingMode, FunctionDeclaration,
+    GenerateContentRequest, GenerateContentResponse, GenerationConfig, Part, Role, Tool,
+    ToolConfig,
 };
 use serde_json::{Map, Value};
 use std::convert::TryFrom;
@@ -52,11 +53,27 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = GenerateContentResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            // Gemini calls tools in parallel, but they cannot be disabled
+            parallel_tool_calls: false,
+            vision: true,
+            json_mode: true,
+            logprobs: true,
+            constrained_decoding: false,
+            ..Default::default()
+        }
+        .with_catalog("gemini", &self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<GenerateContentResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = create_request_body(completion_request)?;
 
         tracing::debug!(
@@ -64,12 +81,11 @@ impl completion::CompletionModel for CompletionModel {
             serde_json::to_string_pretty(&request)?
         );
 
-        let response = self
+        let builder = self
             .client
             .post(&format!("/v1beta/models/{}:generateContent", self.model))
-            .json(&request)
-            .send()
             .await?;
+        let response = http_extras.apply(builder).json(&request).send().await?;
 
         if response.status().is_success() {
             let response = response.json::<GenerateContentResponse>().await?;
@@ -112,6 +128,47 @@ pub(crate) fn create_request_body(
         generation_config.max_output_tokens = Some(max_tokens);
     }
 
+    // The additional params take precedence over the sampling params
+    let sampling = completion_request.sampling;
+    if sampling.logit_bias.is_some() {
+        tracing::warn!(target: "rig", "The `logit_bias` parameter is not supported by Gemini, ignoring it");
+    }
+    generation_config.top_k = generation_config
+        .top_k
+        .or(sampling.top_k.map(|top_k| top_k as i32));
+    generation_config.frequency_penalty = generation_config
+        .frequency_penalty
+        .or(sampling.frequency_penalty);
+    generation_config.presence_penalty = generation_config
+        .presence_penalty
+        .or(sampling.presence_penalty);
+    generation_config.stop_sequences = generation_config.stop_sequences.or(sampling.stop);
+    generation_config.seed = generation_config.seed.or(sampling.seed);
+    generation_config.candidate_count = generation_config
+        .candidate_count
+        .or(sampling.n.map(|n| n as i32));
+    generation_config.response_logprobs = generation_config.response_logprobs.or(sampling.logprobs);
+    generation_config.logprobs = generation_config.logprobs.or(sampling
+        .top_logprobs
+        .map(|top_logprobs| top_logprobs as i32));
+
+    completion::request::warn_unsupported_parallel_tool_calls(
+        "Gemini",
+        completion_request.parallel_tool_calls,
+    );
+    let tool_config = match completion_request.tool_choice {
+        completion::ToolChoice::Auto => None,
+        completion::ToolChoice::None => Some((FunctionCallingMode::None, None)),
+        completion::ToolChoice::Required => Some((FunctionCallingMode::Any, None)),
+        completion::ToolChoice::Tool(name) => Some((FunctionCallingMode::Any, Some(vec![name]))),
+    }
+    .map(|(mode, allowed_function_names)| ToolConfig {
+        function_calling_config: FunctionCallingConfig {
+            mode,
+            allowed_function_names,
+        },
+    });
+
     let system_instruction = completion_request.preamble.clone().map(|preamble| Content {
         parts: OneOrMany::one(preamble.into()),
         role: Some(Role::Model),
@@ -134,7 +191,7 @@ pub(crate) fn create_request_body(
                 .map(Tool::try_from)
                 .collect::<Result<Vec<_>, _>>()?,
         ),
-        tool_config: None,
+        tool_config,
         system_instruction,
     };
 
@@ -197,9 +254,35 @@ impl TryFrom<GenerateContentResponse> for completion::CompletionResponse<Generat
             )
         })?;
 
+        let logprobs = candidate.logprobs_result.as_ref().map(|logprobs_result| {
+            logprobs_result
+                .chosen_candidate
+                .iter()
+                .enumerate()
+                .map(|(i, chosen)| completion::TokenLogprob {
+                    token: chosen.token.clone(),
+                    logprob: chosen.log_probability,
+                    top_logprobs: logprobs_result
+                        .top_candidate
+                        .get(i)
+                        .map(|top| {
+                            top.candidates
+                                .iter()
+                                .map(|candidate| completion::TopLogprob {
+                                    token: candidate.token.clone(),
+                                    logprob: candidate.log_probability,
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                })
+                .collect()
+        });
+
         Ok(completion::CompletionResponse {
             choice,
             raw_response: response,
+            logprobs,
         })
     }
 }
@@ -301,6 +384,20 @@ pub mod gemini_api_types {
                     content: content.parts.try_map(|part| {
                         Ok(match part {
                             Part::Text(text) => message::UserContent::text(text),
+                            // The function calls are identified by the name of their function
+                            Part::FunctionResponse(function_response) => {
+                                message::UserContent::tool_result(
+                                    function_response.name,
+                                    OneOrMany::one(message::ToolResultContent::text(
+                                        serde_json::to_string(&function_response.response)
+                                            .map_err(|e| {
+                                                message::MessageError::ConversionError(
+                                                    e.to_string(),
+                                                )
+                                            })?,
+                                    )),
+                                )
+                            }
                             Part::InlineData(inline_data) => {
                                 let mime_type =
                                     message::MediaType::from_mime_type(&inline_data.mime_type);
@@ -709,12 +806,18 @@ pub mod gemini_api_types {
     #[derive(Debug, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub struct LogprobsResult {
+        /// Length = total number of decoding steps.
+        #[serde(rename = "topCandidates", default)]
         pub top_candidate: Vec<TopCandidate>,
+        /// Length = total number of decoding steps. The chosen candidates may or may not be in
+        /// topCandidates.
+        #[serde(rename = "chosenCandidates", default)]
         pub chosen_candidate: Vec<LogProbCandidate>,
     }
 
     #[derive(Debug, Deserialize)]
     pub struct TopCandidate {
+        #[serde(default)]
         pub candidates: Vec<LogProbCandidate>,
     }
 
@@ -722,7 +825,7 @@ pub mod gemini_api_types {
     #[serde(rename_all = "camelCase")]
     pub struct LogProbCandidate {
         pub token: String,
-        pub token_id: String,
+        pub token_id: Option<i64>,
         pub log_probability: f64,
     }
 
@@ -792,6 +895,9 @@ pub mod gemini_api_types {
         /// the model to  repeating a common token until it hits the maxOutputTokens limit: "...the the the the the...".
         #[serde(skip_serializing_if = "Option::is_none")]
         pub frequency_penalty: Option<f64>,
+        /// Seed used in decoding. If not set, the request uses a randomly generated seed.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub seed: Option<u64>,
         /// If true, export the logprobs results in response.
         #[serde(skip_serializing_if = "Option::is_none")]
         pub response_logprobs: Option<bool>,
@@ -814,6 +920,7 @@ pub mod gemini_api_types {
                 top_k: None,
                 presence_penalty: None,
                 frequency_penalty: None,
+                seed: None,
                 response_logprobs: None,
                 logprobs: None,
             }
@@ -958,7 +1065,28 @@ pub mod gemini_api_types {
     #[derive(Debug, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ToolConfig {
-        pub schema: Option<Schema>,
+        pub function_calling_config: FunctionCallingConfig,
+    }
+
+    /// How the model calls the functions (the tools).
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FunctionCallingConfig {
+        pub mode: FunctionCallingMode,
+        /// The functions the model can call, with [FunctionCallingMode::Any]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub allowed_function_names: Option<Vec<String>>,
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    pub enum FunctionCallingMode {
+        /// The model decides whether to call functions
+        Auto,
+        /// The model calls at least one function
+        Any,
+        /// The model does not call functions
+        None,
     }
 
     #[derive(Debug, Serialize)]
@@ -1149,4 +1277,65 @@ mod tests {
             panic!("Expected function call part");
         }
     }
+
+    #[test]
+    fn test_response_logprobs() {
+        let response: GenerateContentResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "Yes"}], "role": "model"},
+                "finishReason": "STOP",
+                "avgLogprobs": -0.05,
+                "logprobsResult": {
+                    "topCandidates": [{
+                        "candidates": [
+                            {"token": "Yes", "tokenId": 3553, "logProbability": -0.05},
+                            {"token": "No", "tokenId": 1294, "logProbability": -3.1}
+                        ]
+                    }],
+                    "chosenCandidates": [
+                        {"token": "Yes", "tokenId": 3553, "logProbability": -0.05}
+                    ]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let response: completion::CompletionResponse<_> = response.try_into().unwrap();
+        let logprobs = response.logprobs.unwrap();
+        assert_eq!(logprobs.len(), 1);
+        assert_eq!(logprobs[0].token, "Yes");
+        assert_eq!(logprobs[0].logprob, -0.05);
+        assert_eq!(logprobs[0].top_logprobs[1].token, "No");
+    }
+
+    #[test]
+    fn test_tool_choice() {
+        use crate::completion::CompletionModel as _;
+
+        let model = super::super::Client::new("key").completion_model(GEMINI_2_0_FLASH);
+        let tool = completion::ToolDefinition {
+            name: "search".to_string(),
+            description: "Search the web".to_string(),
+            parameters: json!({"type": "object", "properties": {}}),
+        };
+        let request = model
+            .completion_request("Search the news")
+            .tool(tool.clone())
+            .build();
+        let body = serde_json::to_value(create_request_body(request).unwrap()).unwrap();
+        assert_eq!(body["toolConfig"], Value::Null);
+
+        let request = model
+            .completion_request("Search the news")
+            .tool(tool)
+            .tool_choice(completion::ToolChoice::Tool("search".to_string()))
+            .build();
+        let body = serde_json::to_value(create_request_body(request).unwrap()).unwrap();
+        assert_eq!(
+            body["toolConfig"],
+            json!({
+                "functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": ["search"]}
+            })
+        );
+    }
 }
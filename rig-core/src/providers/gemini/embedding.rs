@@ -34,8 +34,10 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
     const MAX_DOCUMENTS: usize = 1024;
 
     fn ndims(&self) -> usize {
-        match self.model.as_str() {
-            EMBEDDING_001 | EMBEDDING_004 => 768,
+        match (self.ndims, self.model.as_str()) {
+            // Requested `output_dimensionality`
+            (Some(ndims), _) => ndims,
+            (None, EMBEDDING_001 | EMBEDDING_004) => 768,
             _ => 0, // Default to 0 for unknown models
         }
     }
@@ -71,6 +73,7 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         let response = self
             .client
             .post(&format!("/v1beta/models/{}:batchEmbedContents", self.model))
+            .await?
             .json(&request_body)
             .send()
             .await?
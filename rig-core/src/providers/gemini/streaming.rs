@@ -35,17 +35,17 @@ impl StreamingCompletionModel for CompletionModel {
         completion_request: CompletionRequest,
     ) -> Result<streaming::StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>
     {
+        let http_extras = completion_request.http_extras.clone();
         let request = create_request_body(completion_request)?;
 
-        let response = self
+        let builder = self
             .client
             .post_sse(&format!(
                 "/v1beta/models/{}:streamGenerateContent",
                 self.model
             ))
-            .json(&request)
-            .send()
             .await?;
+        let response = http_extras.apply(builder).json(&request).send().await?;
 
         if !response.status().is_success() {
             return Err(CompletionError::ProviderError(format!(
@@ -94,6 +94,7 @@ impl transcription::TranscriptionModel for TranscriptionModel {
         let response = self
             .client
             .post(&format!("/v1beta/models/{}:generateContent", self.model))
+            .await?
             .json(&request)
             .send()
             .await?;
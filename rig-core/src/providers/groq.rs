@@ -35,6 +35,7 @@ const GROQ_API_BASE_URL: &str = "https://api.groq.com/openai/v1";
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
+    headers: reqwest::header::HeaderMap,
     http_client: reqwest::Client,
 }
 
@@ -48,17 +49,17 @@ impl Client {
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
+            headers: {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(
+                    "Authorization",
+                    format!("Bearer {}", api_key)
+                        .parse()
+                        .expect("Bearer token should parse"),
+                );
+                headers
+            },
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
                 .build()
                 .expect("Groq reqwest client should build"),
         }
@@ -71,9 +72,17 @@ impl Client {
         Self::new(&api_key)
     }
 
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
     fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
-        self.http_client.post(url)
+        self.http_client.post(url).headers(self.headers.clone())
     }
 
     /// Create a completion model with the given name.
@@ -313,15 +322,31 @@ impl CompletionModel {
                 "temperature": completion_request.temperature,
             })
         } else {
-            json!({
-                "model": self.model,
-                "messages": full_history,
-                "temperature": completion_request.temperature,
-                "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
-            })
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "temperature": completion_request.temperature,
+                    "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
         };
 
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "Groq",
+                &completion::SamplingParamNames {
+                    logit_bias: None,
+                    n: None,
+                    ..completion::SamplingParamNames::OPENAI
+                },
+            ),
+        );
+
         let request = if let Some(params) = completion_request.additional_params {
             json_utils::merge(request, params)
         } else {
@@ -335,16 +360,28 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            vision: self.model.contains("vision"),
+            json_mode: true,
+            ..Default::default()
+        }
+        .with_catalog("groq", &self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("/chat/completions")
+        let response = http_extras
+            .apply(self.client.post("/chat/completions"))
             .json(&request)
             .send()
             .await?;
@@ -372,6 +409,7 @@ impl StreamingCompletionModel for CompletionModel {
         &self,
         request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        let http_extras = request.http_extras.clone();
         let mut request = self.create_completion_request(request)?;
 
         request = merge(
@@ -379,7 +417,9 @@ impl StreamingCompletionModel for CompletionModel {
             json!({"stream": true, "stream_options": {"include_usage": true}}),
         );
 
-        let builder = self.client.post("/chat/completions").json(&request);
+        let builder = http_extras
+            .apply(self.client.post("/chat/completions"))
+            .json(&request);
 
         send_compatible_streaming_request(builder).await
     }
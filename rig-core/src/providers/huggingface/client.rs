@@ -139,6 +139,7 @@ impl ClientBuilder {
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
+    headers: reqwest::header::HeaderMap,
     http_client: reqwest::Client,
     pub(crate) sub_provider: SubProvider,
 }
@@ -153,28 +154,27 @@ impl Client {
 
     /// Create a new Client with the given API key and base API URL.
     pub fn from_url(api_key: &str, base_url: &str, sub_provider: SubProvider) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {api_key}")
+                .parse()
+                .expect("Failed to parse API key"),
+        );
+        headers.insert(
+            "Content-Type",
+            "application/json"
+                .parse()
+                .expect("Failed to parse Content-Type"),
+        );
+
         let http_client = reqwest::Client::builder()
-            .default_headers({
-                let mut headers = reqwest::header::HeaderMap::new();
-                headers.insert(
-                    "Authorization",
-                    format!("Bearer {api_key}")
-                        .parse()
-                        .expect("Failed to parse API key"),
-                );
-                headers.insert(
-                    "Content-Type",
-                    "application/json"
-                        .parse()
-                        .expect("Failed to parse Content-Type"),
-                );
-                headers
-            })
             .build()
             .expect("Failed to build HTTP client");
 
         Self {
             base_url: base_url.to_owned(),
+            headers,
             http_client,
             sub_provider,
         }
@@ -186,9 +186,17 @@ impl Client {
         Self::new(&api_key)
     }
 
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
     pub(crate) fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
-        self.http_client.post(url)
+        self.http_client.post(url).headers(self.headers.clone())
     }
 
     /// Create a new completion model with the given name
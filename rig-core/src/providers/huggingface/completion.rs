@@ -475,6 +475,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
         Ok(completion::CompletionResponse {
             choice,
             raw_response: response,
+            logprobs: None,
         })
     }
 }
@@ -528,26 +529,45 @@ impl CompletionModel {
                 "temperature": completion_request.temperature,
             })
         } else {
-            json!({
-                "model": model,
-                "messages": full_history,
-                "temperature": completion_request.temperature,
-                "tools": completion_request.tools.clone().into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
-            })
+            json_utils::merge(
+                json!({
+                    "model": model,
+                    "messages": full_history,
+                    "temperature": completion_request.temperature,
+                    "tools": completion_request.tools.clone().into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
         };
-        Ok(request)
+
+        Ok(json_utils::merge(
+            request,
+            completion_request
+                .sampling
+                .to_json("Hugging Face", &completion::SamplingParamNames::OPENAI),
+        ))
     }
 }
 
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            ..Default::default()
+        }
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_request_body(&completion_request)?;
 
         let path = self.client.sub_provider.completion_endpoint(&self.model);
@@ -558,7 +578,11 @@ impl completion::CompletionModel for CompletionModel {
             request
         };
 
-        let response = self.client.post(&path).json(&request).send().await?;
+        let response = http_extras
+            .apply(self.client.post(&path))
+            .json(&request)
+            .send()
+            .await?;
 
         if response.status().is_success() {
             let t = response.text().await?;
@@ -61,6 +61,7 @@ impl StreamingCompletionModel for CompletionModel {
         completion_request: CompletionRequest,
     ) -> Result<streaming::StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>
     {
+        let http_extras = completion_request.http_extras.clone();
         let mut request = self.create_request_body(&completion_request)?;
 
         // Enable streaming
@@ -76,7 +77,7 @@ impl StreamingCompletionModel for CompletionModel {
         // HF Inference API uses the model in the path even though its specified in the request body
         let path = self.client.sub_provider.completion_endpoint(&self.model);
 
-        let builder = self.client.post(&path).json(&request);
+        let builder = http_extras.apply(self.client.post(&path)).json(&request);
 
         send_compatible_streaming_request(builder).await
     }
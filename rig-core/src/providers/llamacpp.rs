@@ -0,0 +1,337 @@
+//! llama.cpp server (`llama-server`) client and Rig integration, for the models running locally.
+//!
+//! The server supports grammar-constrained decoding: the
+//! [OutputConstraint](crate::completion::OutputConstraint) of the requests (a JSON schema or a
+//! GBNF grammar) is enforced while sampling, so that the structured outputs are always valid.
+//!
+//! # Example
+//! ```
+//! use rig::{completion::OutputConstraint, providers::llamacpp};
+//!
+//! // Create a new llama.cpp client (defaults to http://localhost:8080)
+//! let client = llamacpp::Client::new();
+//!
+//! // The server serves the model it was started with, whatever the model name
+//! let model = client.completion_model("qwen2.5-7b-instruct");
+//!
+//! let response = model
+//!     .completion_request("Is the sky blue?")
+//!     .output_constraint(OutputConstraint::Grammar(r#"root ::= "yes" | "no""#.to_string()))
+//!     .send()
+//!     .await?;
+//! ```
+
+use crate::json_utils::merge;
+use crate::message;
+use crate::providers::openai::send_compatible_streaming_request;
+use crate::streaming::{StreamingCompletionModel, StreamingCompletionResponse};
+use crate::{
+    agent::AgentBuilder,
+    completion::{self, CompletionError, CompletionRequest, OutputConstraint},
+    extractor::ExtractorBuilder,
+    json_utils,
+    providers::openai,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// ================================================================
+// Main llama.cpp Client
+// ================================================================
+const LLAMACPP_API_BASE_URL: &str = "http://localhost:8080/v1";
+
+#[derive(Clone)]
+pub struct Client {
+    base_url: String,
+    headers: reqwest::header::HeaderMap,
+    http_client: reqwest::Client,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Create a new llama.cpp client, for the server running at `http://localhost:8080`.
+    pub fn new() -> Self {
+        Self::from_url(LLAMACPP_API_BASE_URL)
+    }
+
+    /// Create a new llama.cpp client with the given base API URL (e.g.:
+    /// `http://localhost:8080/v1`).
+    pub fn from_url(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            headers: reqwest::header::HeaderMap::new(),
+            http_client: reqwest::Client::builder()
+                .build()
+                .expect("llama.cpp reqwest client should build"),
+        }
+    }
+
+    /// Authenticate the requests with the API key of the server (set with its `--api-key`
+    /// option).
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.headers.insert(
+            "Authorization",
+            format!("Bearer {}", api_key)
+                .parse()
+                .expect("Bearer token should parse"),
+        );
+        self
+    }
+
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        self.http_client.post(url).headers(self.headers.clone())
+    }
+
+    /// Create a completion model with the given name (only reported by the server, which
+    /// serves the model it was started with).
+    pub fn completion_model(&self, model: &str) -> CompletionModel {
+        CompletionModel::new(self.clone(), model)
+    }
+
+    /// Create an agent builder with the given completion model.
+    pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
+        AgentBuilder::new(self.completion_model(model))
+    }
+
+    /// Create an extractor builder with the given completion model.
+    pub fn extractor<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync>(
+        &self,
+        model: &str,
+    ) -> ExtractorBuilder<T, CompletionModel> {
+        ExtractorBuilder::new(self.completion_model(model))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorResponse {
+    error: LlamaCppError,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlamaCppError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ApiResponse<T> {
+    Ok(T),
+    Err(ApiErrorResponse),
+}
+
+// ================================================================
+// llama.cpp Completion API
+// ================================================================
+#[derive(Clone)]
+pub struct CompletionModel {
+    client: Client,
+    pub model: String,
+}
+
+impl CompletionModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+
+    fn create_completion_request(
+        &self,
+        completion_request: CompletionRequest,
+    ) -> Result<Value, CompletionError> {
+        // Build up the order of messages (context, chat_history)
+        let mut partial_history = vec![];
+        if let Some(docs) = completion_request.normalized_documents() {
+            partial_history.push(docs);
+        }
+        partial_history.extend(completion_request.chat_history);
+
+        // Initialize full history with preamble (or empty if non-existent)
+        let mut full_history: Vec<openai::Message> = completion_request
+            .preamble
+            .map_or_else(Vec::new, |preamble| {
+                vec![openai::Message::system(&preamble)]
+            });
+
+        // Convert and extend the rest of the history
+        full_history.extend(
+            partial_history
+                .into_iter()
+                .map(message::Message::try_into)
+                .collect::<Result<Vec<Vec<openai::Message>>, _>>()?
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>(),
+        );
+
+        let request = if completion_request.tools.is_empty() {
+            json!({
+                "model": self.model,
+                "messages": full_history,
+                "temperature": completion_request.temperature,
+            })
+        } else {
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "temperature": completion_request.temperature,
+                    "tools": completion_request.tools.into_iter().map(openai::ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
+        };
+
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "llama.cpp",
+                &completion::SamplingParamNames {
+                    top_k: Some("top_k"),
+                    ..completion::SamplingParamNames::OPENAI
+                },
+            ),
+        );
+
+        // The constraint is converted to a grammar by the server, and enforced while sampling
+        let request = match completion_request.output_constraint {
+            Some(OutputConstraint::JsonSchema(schema)) => {
+                json_utils::merge(request, json!({ "json_schema": schema }))
+            }
+            Some(OutputConstraint::Grammar(grammar)) => {
+                json_utils::merge(request, json!({ "grammar": grammar }))
+            }
+            None => request,
+        };
+
+        let request = if let Some(params) = completion_request.additional_params {
+            json_utils::merge(request, params)
+        } else {
+            request
+        };
+
+        Ok(request)
+    }
+}
+
+impl completion::CompletionModel for CompletionModel {
+    type Response = openai::CompletionResponse;
+
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            json_mode: true,
+            logprobs: true,
+            constrained_decoding: true,
+            ..Default::default()
+        }
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn completion(
+        &self,
+        completion_request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<openai::CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
+        let request = self.create_completion_request(completion_request)?;
+
+        let response = http_extras
+            .apply(self.client.post("/chat/completions"))
+            .json(&request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let t = response.text().await?;
+            tracing::debug!(target: "rig", "llama.cpp completion response: {}", t);
+
+            match serde_json::from_str::<ApiResponse<openai::CompletionResponse>>(&t)? {
+                ApiResponse::Ok(response) => {
+                    tracing::info!(target: "rig",
+                        "llama.cpp completion token usage: {:?}",
+                        response.usage.clone().map(|usage| format!("{usage}")).unwrap_or("N/A".to_string())
+                    );
+                    response.try_into()
+                }
+                ApiResponse::Err(err) => Err(CompletionError::ProviderError(err.error.message)),
+            }
+        } else {
+            Err(CompletionError::ProviderError(response.text().await?))
+        }
+    }
+}
+
+impl StreamingCompletionModel for CompletionModel {
+    type StreamingResponse = openai::StreamingCompletionResponse;
+
+    async fn stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        let http_extras = request.http_extras.clone();
+        let mut request = self.create_completion_request(request)?;
+
+        request = merge(
+            request,
+            json!({"stream": true, "stream_options": {"include_usage": true}}),
+        );
+
+        let builder = http_extras
+            .apply(self.client.post("/chat/completions"))
+            .json(&request);
+
+        send_compatible_streaming_request(builder).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{CompletionModel as _, ToolDefinition};
+
+    #[test]
+    fn test_output_constraint() {
+        let model = Client::new().completion_model("local");
+        let tools = [ToolDefinition {
+            name: "add".to_string(),
+            description: "Add two numbers".to_string(),
+            parameters: json!({"type": "object", "properties": {"x": {"type": "number"}}}),
+        }];
+        let request = model
+            .completion_request("1 + 2?")
+            .output_constraint(OutputConstraint::tool_call(&tools))
+            .build();
+        let payload = model.create_completion_request(request).unwrap();
+        assert_eq!(
+            payload["json_schema"]["oneOf"][0]["properties"]["name"],
+            json!({"const": "add"})
+        );
+        assert!(payload.get("grammar").is_none());
+
+        let request = model
+            .completion_request("Is the sky blue?")
+            .output_constraint(OutputConstraint::Grammar(r#"root ::= "yes" | "no""#.into()))
+            .build();
+        let payload = model.create_completion_request(request).unwrap();
+        assert_eq!(payload["grammar"], r#"root ::= "yes" | "no""#);
+    }
+}
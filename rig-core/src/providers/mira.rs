@@ -161,6 +161,14 @@ impl Client {
         Ok(client)
     }
 
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.client = http_client;
+        self
+    }
+
     /// List available models
     pub async fn list_models(&self) -> Result<Vec<String>, MiraError> {
         let url = format!("{}/v1/models", self.base_url);
@@ -299,18 +307,33 @@ impl CompletionModel {
             "stream": false
         });
 
-        Ok(request)
+        // Sampling parameters are not supported by Mira
+        Ok(merge(
+            request,
+            completion_request
+                .sampling
+                .to_json("Mira", &completion::SamplingParamNames::NONE),
+        ))
     }
 }
 
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        // Tools are not supported by Mira
+        completion::Capabilities {
+            streaming: true,
+            ..Default::default()
+        }
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         if !completion_request.tools.is_empty() {
             tracing::warn!(target: "rig",
                 "Tool calls are not supported by the Mira provider. {} tools will be ignored.",
@@ -320,10 +343,12 @@ impl completion::CompletionModel for CompletionModel {
 
         let mira_request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .client
-            .post(format!("{}/v1/chat/completions", self.client.base_url))
+        let response = http_extras
+            .apply(
+                self.client
+                    .client
+                    .post(format!("{}/v1/chat/completions", self.client.base_url)),
+            )
             .headers(self.client.headers.clone())
             .json(&mira_request)
             .send()
@@ -354,14 +379,17 @@ impl StreamingCompletionModel for CompletionModel {
         &self,
         completion_request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let mut request = self.create_completion_request(completion_request)?;
 
         request = merge(request, json!({"stream": true}));
 
-        let builder = self
-            .client
-            .client
-            .post(format!("{}/v1/chat/completions", self.client.base_url))
+        let builder = http_extras
+            .apply(
+                self.client
+                    .client
+                    .post(format!("{}/v1/chat/completions", self.client.base_url)),
+            )
             .headers(self.client.headers.clone())
             .json(&request);
 
@@ -436,6 +464,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
         Ok(completion::CompletionResponse {
             choice,
             raw_response: response,
+            logprobs: None,
         })
     }
 }
@@ -292,12 +292,16 @@ impl CompletionModel {
 
             })
         } else {
-            json!({
-                "model": self.model,
-                "messages": full_history,
-                "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
-            })
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
         };
 
         let request = if let Some(temperature) = completion_request.temperature {
@@ -311,6 +315,18 @@ impl CompletionModel {
             request
         };
 
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "Mistral",
+                &completion::SamplingParamNames {
+                    seed: Some("random_seed"),
+                    logit_bias: None,
+                    ..completion::SamplingParamNames::OPENAI
+                },
+            ),
+        );
+
         let request = if let Some(params) = completion_request.additional_params {
             json_utils::merge(request, params)
         } else {
@@ -379,6 +395,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
         Ok(completion::CompletionResponse {
             choice,
             raw_response: response,
+            logprobs: None,
         })
     }
 }
@@ -386,16 +403,26 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            tools: true,
+            parallel_tool_calls: true,
+            json_mode: true,
+            ..Default::default()
+        }
+        .with_catalog("mistral", &self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("v1/chat/completions")
+        let response = http_extras
+            .apply(self.client.post("v1/chat/completions"))
             .json(&request)
             .send()
             .await?;
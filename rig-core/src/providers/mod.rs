@@ -48,12 +48,14 @@
 pub mod anthropic;
 pub mod azure;
 pub mod cohere;
+pub mod convert;
 pub mod deepseek;
 pub mod galadriel;
 pub mod gemini;
 pub mod groq;
 pub mod huggingface;
 pub mod hyperbolic;
+pub mod llamacpp;
 pub mod mira;
 pub mod mistral;
 pub mod moonshot;
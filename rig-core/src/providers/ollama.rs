@@ -42,7 +42,7 @@ use crate::json_utils::merge_inplace;
 use crate::streaming::{RawStreamingChoice, StreamingCompletionModel};
 use crate::{
     agent::AgentBuilder,
-    completion::{self, CompletionError, CompletionRequest},
+    completion::{self, CompletionError, CompletionRequest, OutputConstraint},
     embeddings::{self, EmbeddingError, EmbeddingsBuilder},
     extractor::ExtractorBuilder,
     json_utils, message,
@@ -85,6 +85,12 @@ impl Client {
                 .expect("Ollama reqwest client should build"),
         }
     }
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings).
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
     fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path);
         self.http_client.post(url)
@@ -296,6 +302,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
                 Ok(completion::CompletionResponse {
                     choice,
                     raw_response,
+                    logprobs: None,
                 })
             }
             _ => Err(CompletionError::ResponseError(
@@ -346,13 +353,22 @@ impl CompletionModel {
         );
 
         // Convert internal prompt into a provider Message
+        let options = json_utils::merge(
+            json!({ "temperature": completion_request.temperature }),
+            completion_request.sampling.to_json(
+                "Ollama",
+                &completion::SamplingParamNames {
+                    logit_bias: None,
+                    n: None,
+                    top_k: Some("top_k"),
+                    ..completion::SamplingParamNames::OPENAI
+                },
+            ),
+        );
         let options = if let Some(extra) = completion_request.additional_params {
-            json_utils::merge(
-                json!({ "temperature": completion_request.temperature }),
-                extra,
-            )
+            json_utils::merge(options, extra)
         } else {
-            json!({ "temperature": completion_request.temperature })
+            options
         };
 
         let mut request_payload = json!({
@@ -362,12 +378,26 @@ impl CompletionModel {
             "stream": false,
         });
         if !completion_request.tools.is_empty() {
+            completion_request.tool_choice.warn_unsupported("Ollama");
+            completion::request::warn_unsupported_parallel_tool_calls(
+                "Ollama",
+                completion_request.parallel_tool_calls,
+            );
             request_payload["tools"] = json!(completion_request
                 .tools
                 .into_iter()
                 .map(|tool| tool.into())
                 .collect::<Vec<ToolDefinition>>());
         }
+        match completion_request.output_constraint {
+            Some(OutputConstraint::JsonSchema(schema)) => request_payload["format"] = schema,
+            Some(OutputConstraint::Grammar(_)) => {
+                return Err(CompletionError::RequestError(
+                    "Ollama does not support the GBNF grammars, use a JSON schema".into(),
+                ))
+            }
+            None => {}
+        }
 
         tracing::debug!(target: "rig", "Chat mode payload: {}", request_payload);
 
@@ -380,16 +410,27 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            vision: self.model.contains("llava") || self.model.contains("vision"),
+            json_mode: true,
+            constrained_decoding: true,
+            ..Default::default()
+        }
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<Self::Response>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request_payload = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("api/chat")
+        let response = http_extras
+            .apply(self.client.post("api/chat"))
             .json(&request_payload)
             .send()
             .await
@@ -433,12 +474,12 @@ impl StreamingCompletionModel for CompletionModel {
         request: CompletionRequest,
     ) -> Result<streaming::StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>
     {
+        let http_extras = request.http_extras.clone();
         let mut request_payload = self.create_completion_request(request)?;
         merge_inplace(&mut request_payload, json!({"stream": true}));
 
-        let response = self
-            .client
-            .post("api/chat")
+        let response = http_extras
+            .apply(self.client.post("api/chat"))
             .json(&request_payload)
             .send()
             .await
@@ -924,4 +965,24 @@ mod tests {
         let params = &ollama_tool.function.parameters;
         assert_eq!(params["properties"]["location"]["type"], "string");
     }
+
+    #[test]
+    fn test_output_constraint() {
+        use crate::completion::CompletionModel as _;
+
+        let model = Client::new().completion_model("llama3.2");
+        let schema = json!({"type": "object", "properties": {"city": {"type": "string"}}});
+        let request = model
+            .completion_request("Where is the Eiffel tower?")
+            .output_constraint(OutputConstraint::JsonSchema(schema.clone()))
+            .build();
+        let payload = model.create_completion_request(request).unwrap();
+        assert_eq!(payload["format"], schema);
+
+        let request = model
+            .completion_request("Is the sky blue?")
+            .output_constraint(OutputConstraint::Grammar(r#"root ::= "yes" | "no""#.into()))
+            .build();
+        assert!(model.create_completion_request(request).is_err());
+    }
 }
@@ -37,12 +37,8 @@ impl audio_generation::AudioGenerationModel for AudioGenerationModel {
             "speed": request.speed,
         });
 
-        let response = self
-            .client
-            .post("/audio/speech")
-            .json(&request)
-            .send()
-            .await?;
+        let builder = self.client.post("/audio/speech").await?.json(&request);
+        let response = self.client.send(builder).await?;
 
         if !response.status().is_success() {
             return Err(AudioGenerationError::ProviderError(format!(
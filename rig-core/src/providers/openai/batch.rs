@@ -0,0 +1,265 @@
+//! OpenAI Batch API implementation
+//!
+//! Requests are uploaded as a JSONL file and processed against the `/v1/chat/completions`
+//! endpoint within a 24h completion window.
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{ApiErrorResponse, ApiResponse, CompletionModel, CompletionResponse};
+use crate::completion::{
+    self,
+    batch::{BatchCompletionModel, BatchError, BatchResults, BatchStatus},
+    CompletionError, CompletionRequest,
+};
+
+#[derive(Debug, Deserialize)]
+struct File {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Batch {
+    id: String,
+    status: String,
+    output_file_id: Option<String>,
+    error_file_id: Option<String>,
+    errors: Option<BatchErrors>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchErrors {
+    data: Vec<ApiErrorResponse>,
+}
+
+impl From<Batch> for BatchStatus {
+    fn from(batch: Batch) -> Self {
+        match batch.status.as_str() {
+            "completed" => BatchStatus::Completed,
+            "failed" => BatchStatus::Failed(
+                batch
+                    .errors
+                    .map(|errors| {
+                        errors
+                            .data
+                            .into_iter()
+                            .map(|error| error.message)
+                            .collect::<Vec<_>>()
+                            .join("; ")
+                    })
+                    .unwrap_or_default(),
+            ),
+            "expired" => BatchStatus::Expired,
+            "cancelling" | "cancelled" => BatchStatus::Cancelled,
+            // validating, in_progress, finalizing
+            _ => BatchStatus::InProgress,
+        }
+    }
+}
+
+/// A line of a batch output (or error) file
+#[derive(Debug, Deserialize)]
+struct BatchOutput {
+    custom_id: String,
+    response: Option<BatchOutputResponse>,
+    error: Option<ApiErrorResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchOutputResponse {
+    status_code: u16,
+    body: serde_json::Value,
+}
+
+impl BatchOutput {
+    fn into_result(
+        self,
+    ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        match (self.response, self.error) {
+            (_, Some(error)) => Err(CompletionError::ProviderError(error.message)),
+            (Some(response), None) if response.status_code == 200 => {
+                serde_json::from_value::<CompletionResponse>(response.body)?.try_into()
+            }
+            (Some(response), None) => {
+                Err(CompletionError::ProviderError(response.body.to_string()))
+            }
+            (None, None) => Err(CompletionError::ResponseError(
+                "Batch output has neither a response nor an error".into(),
+            )),
+        }
+    }
+}
+
+impl CompletionModel {
+    async fn get_batch(&self, batch_id: &str) -> Result<Batch, BatchError> {
+        let response = self
+            .client
+            .get(&format!("/batches/{batch_id}"))
+            .await?
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            match response.json::<ApiResponse<Batch>>().await? {
+                ApiResponse::Ok(batch) => Ok(batch),
+                ApiResponse::Err(err) => Err(BatchError::ProviderError(err.message)),
+            }
+        } else {
+            Err(BatchError::ProviderError(response.text().await?))
+        }
+    }
+
+    async fn file_content(&self, file_id: &str) -> Result<String, BatchError> {
+        let response = self
+            .client
+            .get(&format!("/files/{file_id}/content"))
+            .await?
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response.text().await?)
+        } else {
+            Err(BatchError::ProviderError(response.text().await?))
+        }
+    }
+}
+
+impl BatchCompletionModel for CompletionModel {
+    async fn submit_batch(
+        &self,
+        requests: Vec<(String, CompletionRequest)>,
+    ) -> Result<String, BatchError> {
+        let lines = requests
+            .into_iter()
+            .map(|(custom_id, request)| {
+                Ok(json!({
+                    "custom_id": custom_id,
+                    "method": "POST",
+                    "url": "/v1/chat/completions",
+                    "body": self.create_completion_request(request)?,
+                })
+                .to_string())
+            })
+            .collect::<Result<Vec<_>, BatchError>>()?;
+
+        let form = reqwest::multipart::Form::new()
+            .text("purpose", "batch")
+            .part(
+                "file",
+                reqwest::multipart::Part::bytes(lines.join("\n").into_bytes())
+                    .file_name("batch.jsonl"),
+            );
+
+        let response = self
+            .client
+            .post("/files")
+            .await?
+            .multipart(form)
+            .send()
+            .await?;
+
+        let file = if response.status().is_success() {
+            match response.json::<ApiResponse<File>>().await? {
+                ApiResponse::Ok(file) => file,
+                ApiResponse::Err(err) => return Err(BatchError::ProviderError(err.message)),
+            }
+        } else {
+            return Err(BatchError::ProviderError(response.text().await?));
+        };
+
+        let response = self
+            .client
+            .post("/batches")
+            .await?
+            .json(&json!({
+                "input_file_id": file.id,
+                "endpoint": "/v1/chat/completions",
+                "completion_window": "24h",
+            }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            match response.json::<ApiResponse<Batch>>().await? {
+                ApiResponse::Ok(batch) => Ok(batch.id),
+                ApiResponse::Err(err) => Err(BatchError::ProviderError(err.message)),
+            }
+        } else {
+            Err(BatchError::ProviderError(response.text().await?))
+        }
+    }
+
+    async fn batch_status(&self, batch_id: &str) -> Result<BatchStatus, BatchError> {
+        Ok(self.get_batch(batch_id).await?.into())
+    }
+
+    async fn batch_results(
+        &self,
+        batch_id: &str,
+    ) -> Result<BatchResults<Self::Response>, BatchError> {
+        let batch = self.get_batch(batch_id).await?;
+
+        let mut content = String::new();
+        for file_id in [batch.output_file_id, batch.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            content.push_str(&self.file_content(&file_id).await?);
+            content.push('\n');
+        }
+
+        parse_batch_output(&content)
+    }
+
+    async fn cancel_batch(&self, batch_id: &str) -> Result<(), BatchError> {
+        let response = self
+            .client
+            .post(&format!("/batches/{batch_id}/cancel"))
+            .await?
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(BatchError::ProviderError(response.text().await?))
+        }
+    }
+}
+
+fn parse_batch_output(content: &str) -> Result<BatchResults<CompletionResponse>, BatchError> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let output = serde_json::from_str::<BatchOutput>(line)?;
+            Ok((output.custom_id.clone(), output.into_result()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::AssistantContent;
+
+    #[test]
+    fn test_parse_batch_output() {
+        let content = r#"
+{"id": "batch_req_1", "custom_id": "request-1", "response": {"status_code": 200, "request_id": "req_1", "body": {"id": "chatcmpl-1", "object": "chat.completion", "created": 1711652795, "model": "gpt-4o-mini", "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hello."}, "logprobs": null, "finish_reason": "stop"}]}}, "error": null}
+{"id": "batch_req_2", "custom_id": "request-2", "response": null, "error": {"code": "invalid_request", "message": "Invalid model"}}
+"#;
+
+        let results = parse_batch_output(content).unwrap();
+
+        assert_eq!(
+            results["request-1"].as_ref().unwrap().choice.first(),
+            AssistantContent::text("Hello.")
+        );
+        assert!(matches!(
+            &results["request-2"],
+            Err(CompletionError::ProviderError(message)) if message == "Invalid model"
+        ));
+    }
+}
@@ -7,12 +7,18 @@ use super::embedding::{
 
 #[cfg(feature = "image")]
 use super::image_generation::ImageGenerationModel;
+#[cfg(feature = "realtime")]
+use super::realtime::RealtimeModel;
 use super::transcription::TranscriptionModel;
 use crate::agent::AgentBuilder;
+use crate::auth::{self, AuthError, AuthProvider};
 use crate::embeddings::EmbeddingsBuilder;
 use crate::extractor::ExtractorBuilder;
+use crate::rate_limit::RateLimiter;
 
 use crate::Embed;
+use std::sync::Arc;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -23,7 +29,10 @@ const OPENAI_API_BASE_URL: &str = "https://api.openai.com/v1";
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
+    headers: reqwest::header::HeaderMap,
+    auth: Option<Arc<dyn AuthProvider>>,
     http_client: reqwest::Client,
+    rate_limiter: RateLimiter,
 }
 
 impl Client {
@@ -36,19 +45,21 @@ impl Client {
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
+            headers: {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(
+                    "Authorization",
+                    format!("Bearer {}", api_key)
+                        .parse()
+                        .expect("Bearer token should parse"),
+                );
+                headers
+            },
+            auth: None,
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
                 .build()
                 .expect("OpenAI reqwest client should build"),
+            rate_limiter: RateLimiter::default(),
         }
     }
 
@@ -59,9 +70,76 @@ impl Client {
         Self::new(&api_key)
     }
 
-    pub(crate) fn post(&self, path: &str) -> reqwest::RequestBuilder {
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Authenticate the requests with `auth` (e.g.: a key rotated in an environment variable with
+    /// [EnvKey](crate::auth::EnvKey), or the tokens of an OpenAI-compatible gateway) instead of
+    /// the API key of the client.
+    pub fn with_auth(mut self, auth: impl AuthProvider + 'static) -> Self {
+        self.headers.remove(reqwest::header::AUTHORIZATION);
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    /// Share the rate limit state of the client with other clients (see [crate::rate_limit]).
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Send `request` once the requests of the client are not paused by a rate limit.
+    pub(crate) async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        self.rate_limiter.send(request).await
+    }
+
+    pub(crate) async fn post(&self, path: &str) -> Result<reqwest::RequestBuilder, AuthError> {
+        let url = format!("{}/{}", self.base_url, path).replace("//", "/");
+        let request = self.http_client.post(url).headers(self.headers.clone());
+        auth::authorize(request, self.auth.as_deref()).await
+    }
+
+    pub(crate) async fn get(&self, path: &str) -> Result<reqwest::RequestBuilder, AuthError> {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
-        self.http_client.post(url)
+        let request = self.http_client.get(url).headers(self.headers.clone());
+        auth::authorize(request, self.auth.as_deref()).await
+    }
+
+    /// The WebSocket handshake request of `path`, with the headers of the client.
+    #[cfg(feature = "realtime")]
+    pub(crate) async fn websocket_request(
+        &self,
+        path: &str,
+    ) -> Result<
+        tokio_tungstenite::tungstenite::handshake::client::Request,
+        crate::realtime::RealtimeError,
+    > {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let url = format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+            .replacen("https://", "wss://", 1)
+            .replacen("http://", "ws://", 1);
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| crate::realtime::RealtimeError::ConnectionError(e.into()))?;
+        let headers = request.headers_mut();
+        headers.extend(self.headers.clone());
+        if let Some(auth) = &self.auth {
+            headers.extend(auth.headers().await?);
+        }
+        headers.insert(
+            "OpenAI-Beta",
+            "realtime=v1".parse().expect("Header should parse"),
+        );
+        Ok(request)
     }
 
     /// Create an embedding model with the given name.
@@ -208,6 +286,22 @@ impl Client {
     pub fn audio_generation_model(&self, model: &str) -> AudioGenerationModel {
         AudioGenerationModel::new(self.clone(), model)
     }
+
+    /// Create a realtime model with the given name, for voice sessions.
+    ///
+    /// # Example
+    /// ```
+    /// use rig::providers::openai::{Client, self};
+    ///
+    /// // Initialize the OpenAI client
+    /// let openai = Client::new("your-open-ai-api-key");
+    ///
+    /// let realtime = openai.realtime_model(openai::GPT_4O_REALTIME_PREVIEW);
+    /// ```
+    #[cfg(feature = "realtime")]
+    pub fn realtime_model(&self, model: &str) -> RealtimeModel {
+        RealtimeModel::new(self.clone(), model)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -530,4 +624,35 @@ mod tests {
         assert_eq!(original_user_message[0], user_message);
         assert_eq!(original_assistant_message[0], assistant_message);
     }
+
+    #[tokio::test]
+    async fn test_with_client() {
+        let http_client = reqwest::Client::builder()
+            .user_agent("my-app")
+            .build()
+            .unwrap();
+        let client = super::Client::new("sk-test").with_client(http_client);
+
+        let request = client
+            .post("/chat/completions")
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://api.openai.com/v1/chat/completions"
+        );
+        assert_eq!(request.headers()["Authorization"], "Bearer sk-test");
+
+        let client = client.with_auth(crate::auth::StaticKey::bearer("gateway-token"));
+        let request = client
+            .post("/chat/completions")
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers().get_all("Authorization").iter().count(), 1);
+        assert_eq!(request.headers()["Authorization"], "Bearer gateway-token");
+    }
 }
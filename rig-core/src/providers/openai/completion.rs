@@ -153,6 +153,8 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
             )),
         }?;
 
+        let logprobs = completion::logprobs::from_openai_choice(choice.logprobs.as_ref());
+
         let choice = OneOrMany::many(content).map_err(|_| {
             CompletionError::ResponseError(
                 "Response contained no message or tool call (empty)".to_owned(),
@@ -162,6 +164,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
         Ok(completion::CompletionResponse {
             choice,
             raw_response: response,
+            logprobs,
         })
     }
 }
@@ -655,12 +658,16 @@ impl CompletionModel {
 
             })
         } else {
-            json!({
-                "model": self.model,
-                "messages": full_history,
-                "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
-            })
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
         };
 
         // only include temperature if it exists
@@ -676,6 +683,14 @@ impl CompletionModel {
             request
         };
 
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "OpenAI",
+                &completion::SamplingParamNames::OPENAI.with_logprobs(),
+            ),
+        );
+
         let request = if let Some(params) = completion_request.additional_params {
             json_utils::merge(request, params)
         } else {
@@ -689,19 +704,33 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        // The reasoning models (o1, o3, ...) do not return log probabilities
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            vision: true,
+            json_mode: true,
+            logprobs: !self.model.starts_with('o'),
+            constrained_decoding: false,
+            ..Default::default()
+        }
+        .with_catalog("openai", &self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("/chat/completions")
-            .json(&request)
-            .send()
-            .await?;
+        let builder = http_extras
+            .apply(self.client.post("/chat/completions").await?)
+            .json(&request);
+        let response = self.client.send(builder).await?;
 
         if response.status().is_success() {
             let t = response.text().await?;
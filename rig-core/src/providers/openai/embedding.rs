@@ -49,6 +49,7 @@ pub struct EmbeddingModel {
     client: Client,
     pub model: String,
     ndims: usize,
+    dimensions: Option<usize>,
 }
 
 impl embeddings::EmbeddingModel for EmbeddingModel {
@@ -58,22 +59,40 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         self.ndims
     }
 
+    const MAX_TOKENS_PER_REQUEST: Option<usize> = Some(300_000);
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn embed_texts(
         &self,
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
         let documents = documents.into_iter().collect::<Vec<_>>();
+        Ok(self.embed_texts_with_usage(documents).await?.0)
+    }
 
-        let response = self
-            .client
-            .post("/embeddings")
-            .json(&json!({
-                "model": self.model,
-                "input": documents,
-            }))
-            .send()
-            .await?;
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn embed_texts_with_usage(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<
+        (
+            Vec<embeddings::Embedding>,
+            Option<embeddings::EmbeddingUsage>,
+        ),
+        EmbeddingError,
+    > {
+        let documents = documents.into_iter().collect::<Vec<_>>();
+
+        let mut body = json!({
+            "model": self.model,
+            "input": documents,
+        });
+        if let Some(dimensions) = self.dimensions {
+            body["dimensions"] = json!(dimensions);
+        }
+
+        let builder = self.client.post("/embeddings").await?.json(&body);
+        let response = self.client.send(builder).await?;
 
         if response.status().is_success() {
             match response.json::<ApiResponse<EmbeddingResponse>>().await? {
@@ -89,15 +108,22 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                         ));
                     }
 
-                    Ok(response
+                    let usage = embeddings::EmbeddingUsage {
+                        prompt_tokens: response.usage.prompt_tokens as u64,
+                        total_tokens: response.usage.total_tokens as u64,
+                    };
+
+                    let embeddings = response
                         .data
                         .into_iter()
-                        .zip(documents.into_iter())
+                        .zip(documents)
                         .map(|(embedding, document)| embeddings::Embedding {
                             document,
                             vec: embedding.embedding,
                         })
-                        .collect())
+                        .collect();
+
+                    Ok((embeddings, Some(usage)))
                 }
                 ApiResponse::Err(err) => Err(EmbeddingError::ProviderError(err.message)),
             }
@@ -113,6 +139,16 @@ impl EmbeddingModel {
             client,
             model: model.to_string(),
             ndims,
+            dimensions: None,
         }
     }
+
+    /// Request embeddings with a reduced number of dimensions (only supported by the
+    /// `text-embedding-3` models and later). The embeddings are shortened by the API, which
+    /// preserves their semantic properties (Matryoshka representation).
+    pub fn dimensions(mut self, dimensions: usize) -> Self {
+        self.ndims = dimensions;
+        self.dimensions = Some(dimensions);
+        self
+    }
 }
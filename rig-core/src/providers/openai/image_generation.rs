@@ -75,12 +75,12 @@ impl image_generation::ImageGenerationModel for ImageGenerationModel {
             "response_format": "b64_json"
         });
 
-        let response = self
+        let builder = self
             .client
             .post("/images/generations")
-            .json(&request)
-            .send()
-            .await?;
+            .await?
+            .json(&request);
+        let response = self.client.send(builder).await?;
 
         if !response.status().is_success() {
             return Err(ImageGenerationError::ProviderError(format!(
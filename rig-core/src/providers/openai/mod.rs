@@ -8,6 +8,7 @@
 //!
 //! let gpt4o = client.completion_model(openai::GPT_4O);
 //! ```
+pub mod batch;
 pub mod client;
 pub mod completion;
 pub mod embedding;
@@ -16,6 +17,8 @@ pub mod embedding;
 pub mod audio_generation;
 #[cfg(feature = "image")]
 pub mod image_generation;
+#[cfg(feature = "realtime")]
+pub mod realtime;
 pub mod streaming;
 pub mod transcription;
 
@@ -28,5 +31,7 @@ pub use audio_generation::{TTS_1, TTS_1_HD};
 
 #[cfg(feature = "image")]
 pub use image_generation::*;
+#[cfg(feature = "realtime")]
+pub use realtime::{GPT_4O_MINI_REALTIME_PREVIEW, GPT_4O_REALTIME_PREVIEW};
 pub use streaming::*;
 pub use transcription::*;
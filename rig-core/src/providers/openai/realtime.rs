@@ -0,0 +1,442 @@
+//! OpenAI Realtime API: speech-to-speech sessions over a WebSocket (with the `realtime`
+//! feature), see the [realtime](crate::realtime) module.
+//!
+//! The audio is PCM 16 bits, 24 kHz mono, in both directions.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{Error as WsError, Message as WsMessage},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use super::Client;
+use crate::{
+    json_utils,
+    realtime::{
+        self, RealtimeError, RealtimeEvent, RealtimeSession as _, SessionConfig, TurnDetection,
+    },
+    tool::ToolSet,
+};
+
+// ================================================================
+// OpenAI Realtime API
+// ================================================================
+/// `gpt-4o-realtime-preview` realtime model
+pub const GPT_4O_REALTIME_PREVIEW: &str = "gpt-4o-realtime-preview";
+/// `gpt-4o-mini-realtime-preview` realtime model
+pub const GPT_4O_MINI_REALTIME_PREVIEW: &str = "gpt-4o-mini-realtime-preview";
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Clone)]
+pub struct RealtimeModel {
+    client: Client,
+    /// Name of the model (e.g.: gpt-4o-realtime-preview)
+    pub model: String,
+}
+
+impl RealtimeModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+}
+
+impl realtime::RealtimeModel for RealtimeModel {
+    type Session = RealtimeSession;
+
+    async fn connect(&self, config: SessionConfig) -> Result<RealtimeSession, RealtimeError> {
+        let request = self
+            .client
+            .websocket_request(&format!("realtime?model={}", self.model))
+            .await?;
+        let (socket, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(connection_error)?;
+
+        let mut session = RealtimeSession {
+            socket,
+            tools: config.tools.clone(),
+            pending: VecDeque::new(),
+            continue_response: false,
+        };
+        session
+            .send(json!({
+                "type": "session.update",
+                "session": session_params(&config).await,
+            }))
+            .await?;
+        Ok(session)
+    }
+}
+
+/// The parameters of the `session.update` event of `config`.
+async fn session_params(config: &SessionConfig) -> Value {
+    let mut names = config.tools.tools.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut tools = vec![];
+    for name in names {
+        if let Some(definition) = config.tools.definition(name, "").await {
+            tools.push(json!({
+                "type": "function",
+                "name": definition.name,
+                "description": definition.description,
+                "parameters": definition.parameters,
+            }));
+        }
+    }
+
+    let mut params = json!({
+        "modalities": if config.text_only { json!(["text"]) } else { json!(["text", "audio"]) },
+        "input_audio_format": "pcm16",
+        "output_audio_format": "pcm16",
+        "turn_detection": match config.turn_detection {
+            TurnDetection::ServerVad => json!({ "type": "server_vad" }),
+            TurnDetection::Manual => Value::Null,
+        },
+    });
+    if !tools.is_empty() {
+        params["tools"] = tools.into();
+        params["tool_choice"] = "auto".into();
+    }
+    if let Some(instructions) = &config.instructions {
+        params["instructions"] = instructions.clone().into();
+    }
+    if let Some(voice) = &config.voice {
+        params["voice"] = voice.clone().into();
+    }
+    if let Some(temperature) = config.temperature {
+        params["temperature"] = temperature.into();
+    }
+    if let Some(model) = &config.input_transcription {
+        params["input_audio_transcription"] = json!({ "model": model });
+    }
+    match &config.additional_params {
+        Some(additional_params) => json_utils::merge(params, additional_params.clone()),
+        None => params,
+    }
+}
+
+/// A session of the OpenAI Realtime API.
+pub struct RealtimeSession {
+    socket: Socket,
+    tools: Arc<ToolSet>,
+    /// Events to return before reading the next events of the socket
+    pending: VecDeque<RealtimeEvent>,
+    /// Whether tool results were sent during the current response, which is continued once done
+    continue_response: bool,
+}
+
+impl RealtimeSession {
+    async fn send(&mut self, event: Value) -> Result<(), RealtimeError> {
+        self.socket
+            .send(WsMessage::Text(event.to_string()))
+            .await
+            .map_err(|e| match e {
+                WsError::ConnectionClosed | WsError::AlreadyClosed => RealtimeError::Closed,
+                e => connection_error(e),
+            })
+    }
+
+    /// The event of the server event `event` (`None` if there is nothing to report), calling the tools
+    /// of the session.
+    async fn handle(&mut self, event: Value) -> Result<Option<RealtimeEvent>, RealtimeError> {
+        let field = |name: &str| event[name].as_str().unwrap_or_default().to_string();
+
+        Ok(Some(match event["type"].as_str().unwrap_or_default() {
+            // The events of the beta API, and of the GA API
+            "response.audio.delta" | "response.output_audio.delta" => RealtimeEvent::Audio(
+                BASE64_STANDARD
+                    .decode(field("delta"))
+                    .map_err(|e| RealtimeError::ResponseError(e.to_string()))?,
+            ),
+            "response.audio_transcript.delta" | "response.output_audio_transcript.delta" => {
+                RealtimeEvent::AudioTranscript(field("delta"))
+            }
+            "response.text.delta" | "response.output_text.delta" => {
+                RealtimeEvent::Text(field("delta"))
+            }
+            "conversation.item.input_audio_transcription.completed" => {
+                RealtimeEvent::InputTranscript(field("transcript"))
+            }
+            "input_audio_buffer.speech_started" => RealtimeEvent::SpeechStarted,
+            "input_audio_buffer.speech_stopped" => RealtimeEvent::SpeechStopped,
+            "response.function_call_arguments.done" => {
+                let (id, name, arguments) = (field("call_id"), field("name"), field("arguments"));
+                if self.tools.contains(&name) {
+                    // The errors are sent to the model, which can recover from them
+                    let tools = self.tools.clone();
+                    let result = match tools.call(&name, arguments.clone()).await {
+                        Ok(result) => result,
+                        Err(e) => e.to_string(),
+                    };
+                    self.send_tool_result(&id, &result).await?;
+                    self.continue_response = true;
+                    self.pending.push_back(RealtimeEvent::ToolResult {
+                        id: id.clone(),
+                        name: name.clone(),
+                        result,
+                    });
+                }
+                RealtimeEvent::ToolCall {
+                    id,
+                    name,
+                    arguments,
+                }
+            }
+            "response.done" => {
+                if std::mem::take(&mut self.continue_response) {
+                    self.create_response().await?;
+                }
+                let usage = &event["response"]["usage"];
+                RealtimeEvent::ResponseDone {
+                    input_tokens: usage["input_tokens"].as_u64(),
+                    output_tokens: usage["output_tokens"].as_u64(),
+                }
+            }
+            "error" => RealtimeEvent::Error(
+                event["error"]["message"]
+                    .as_str()
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+            ),
+            // The acknowledgments of the client events
+            "input_audio_buffer.committed"
+            | "input_audio_buffer.cleared"
+            | "conversation.item.created"
+            | "response.created" => return Ok(None),
+            _ => RealtimeEvent::Other(event),
+        }))
+    }
+}
+
+impl realtime::RealtimeSession for RealtimeSession {
+    async fn send_audio(&mut self, audio: &[u8]) -> Result<(), RealtimeError> {
+        self.send(json!({
+            "type": "input_audio_buffer.append",
+            "audio": BASE64_STANDARD.encode(audio),
+        }))
+        .await
+    }
+
+    async fn commit_audio(&mut self) -> Result<(), RealtimeError> {
+        self.send(json!({ "type": "input_audio_buffer.commit" }))
+            .await
+    }
+
+    async fn send_text(&mut self, text: &str) -> Result<(), RealtimeError> {
+        self.send(json!({
+            "type": "conversation.item.create",
+            "item": {
+                "type": "message",
+                "role": "user",
+                "content": [{ "type": "input_text", "text": text }],
+            },
+        }))
+        .await?;
+        self.create_response().await
+    }
+
+    async fn create_response(&mut self) -> Result<(), RealtimeError> {
+        self.send(json!({ "type": "response.create" })).await
+    }
+
+    async fn cancel_response(&mut self) -> Result<(), RealtimeError> {
+        self.send(json!({ "type": "response.cancel" })).await
+    }
+
+    async fn send_tool_result(&mut self, id: &str, result: &str) -> Result<(), RealtimeError> {
+        self.send(json!({
+            "type": "conversation.item.create",
+            "item": {
+                "type": "function_call_output",
+                "call_id": id,
+                "output": result,
+            },
+        }))
+        .await
+    }
+
+    async fn next_event(&mut self) -> Option<Result<RealtimeEvent, RealtimeError>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(Ok(event));
+        }
+        loop {
+            let text = match self.socket.next().await? {
+                Ok(WsMessage::Text(text)) => text,
+                Ok(WsMessage::Close(_)) => return None,
+                Ok(_) => continue,
+                Err(e) => return Some(Err(connection_error(e))),
+            };
+            let event = match serde_json::from_str(&text) {
+                Ok(event) => event,
+                Err(e) => return Some(Err(e.into())),
+            };
+            match self.handle(event).await {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    async fn close(&mut self) -> Result<(), RealtimeError> {
+        self.socket.close(None).await.map_err(connection_error)
+    }
+}
+
+fn connection_error(error: impl std::error::Error + Send + Sync + 'static) -> RealtimeError {
+    RealtimeError::ConnectionError(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{completion::ToolDefinition, realtime::RealtimeModel as _, tool::Tool};
+
+    #[derive(Deserialize)]
+    struct AddArgs {
+        x: i64,
+        y: i64,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Add error")]
+    struct AddError;
+
+    struct Add;
+
+    impl Tool for Add {
+        const NAME: &'static str = "add";
+
+        type Error = AddError;
+        type Args = AddArgs;
+        type Output = i64;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Add two numbers".to_string(),
+                parameters: json!({ "type": "object" }),
+            }
+        }
+
+        async fn call(&self, args: AddArgs) -> Result<i64, AddError> {
+            Ok(args.x + args.y)
+        }
+    }
+
+    /// Reads the next event of the client
+    async fn receive(socket: &mut WebSocketStream<TcpStream>) -> Value {
+        match socket.next().await.unwrap().unwrap() {
+            WsMessage::Text(text) => serde_json::from_str(&text).unwrap(),
+            message => panic!("Unexpected message {message:?}"),
+        }
+    }
+
+    #[test]
+    fn test_shared_config_tools() {
+        let config = SessionConfig::new().text_only();
+        let shared = config.clone();
+        let config = config.tool(Add);
+
+        assert!(config.tools.contains("add"));
+        assert!(!shared.tools.contains("add"));
+    }
+
+    #[tokio::test]
+    async fn test_realtime_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            let update = receive(&mut socket).await;
+            assert_eq!(update["type"], "session.update");
+            assert_eq!(update["session"]["voice"], "alloy");
+            assert_eq!(update["session"]["tools"][0]["name"], "add");
+            assert_eq!(update["session"]["turn_detection"]["type"], "server_vad");
+
+            for event in [
+                json!({ "type": "response.created" }),
+                json!({ "type": "response.audio.delta", "delta": BASE64_STANDARD.encode([1, 2, 3]) }),
+                json!({
+                    "type": "response.function_call_arguments.done",
+                    "call_id": "call_1",
+                    "name": "add",
+                    "arguments": r#"{"x":1,"y":2}"#,
+                }),
+                json!({
+                    "type": "response.done",
+                    "response": { "usage": { "input_tokens": 10, "output_tokens": 5 } },
+                }),
+            ] {
+                socket
+                    .send(WsMessage::Text(event.to_string()))
+                    .await
+                    .unwrap();
+            }
+
+            let output = receive(&mut socket).await;
+            assert_eq!(output["item"]["type"], "function_call_output");
+            assert_eq!(output["item"]["call_id"], "call_1");
+            assert_eq!(output["item"]["output"], "3");
+            // The response continues with the result of the tool
+            assert_eq!(receive(&mut socket).await["type"], "response.create");
+
+            assert_eq!(
+                receive(&mut socket).await["type"],
+                "input_audio_buffer.append"
+            );
+            socket.close(None).await.unwrap();
+        });
+
+        let model = Client::from_url("sk-test", &format!("http://{address}/v1"))
+            .realtime_model(GPT_4O_REALTIME_PREVIEW);
+        let mut session = model
+            .connect(SessionConfig::new().voice("alloy").tool(Add))
+            .await
+            .unwrap();
+
+        let mut events = vec![];
+        for _ in 0..4 {
+            events.push(session.next_event().await.unwrap().unwrap());
+        }
+        assert_eq!(
+            events,
+            vec![
+                RealtimeEvent::Audio(vec![1, 2, 3]),
+                RealtimeEvent::ToolCall {
+                    id: "call_1".to_string(),
+                    name: "add".to_string(),
+                    arguments: r#"{"x":1,"y":2}"#.to_string(),
+                },
+                RealtimeEvent::ToolResult {
+                    id: "call_1".to_string(),
+                    name: "add".to_string(),
+                    result: "3".to_string(),
+                },
+                RealtimeEvent::ResponseDone {
+                    input_tokens: Some(10),
+                    output_tokens: Some(5),
+                },
+            ]
+        );
+
+        session.send_audio(&[0; 480]).await.unwrap();
+        assert!(session.next_event().await.is_none());
+        server.await.unwrap();
+    }
+}
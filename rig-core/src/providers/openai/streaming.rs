@@ -4,7 +4,7 @@ use crate::json_utils;
 use crate::json_utils::merge;
 use crate::providers::openai::Usage;
 use crate::streaming;
-use crate::streaming::{RawStreamingChoice, StreamingCompletionModel};
+use crate::streaming::{RawStreamingChoice, StreamingCompletionModel, ToolCallDelta};
 use async_stream::stream;
 use futures::StreamExt;
 use reqwest::RequestBuilder;
@@ -62,14 +62,18 @@ impl StreamingCompletionModel for CompletionModel {
         completion_request: CompletionRequest,
     ) -> Result<streaming::StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>
     {
+        let http_extras = completion_request.http_extras.clone();
         let mut request = self.create_completion_request(completion_request)?;
         request = merge(
             request,
             json!({"stream": true, "stream_options": {"include_usage": true}}),
         );
 
-        let builder = self.client.post("/chat/completions").json(&request);
-        send_compatible_streaming_request(builder).await
+        let builder = http_extras
+            .apply(self.client.post("/chat/completions").await?)
+            .json(&request);
+        let response = self.client.send(builder).await?;
+        compatible_streaming_response(response).await
     }
 }
 
@@ -77,7 +81,13 @@ pub async fn send_compatible_streaming_request(
     request_builder: RequestBuilder,
 ) -> Result<streaming::StreamingCompletionResponse<StreamingCompletionResponse>, CompletionError> {
     let response = request_builder.send().await?;
+    compatible_streaming_response(response).await
+}
 
+/// Stream the completion of an OpenAI compatible streaming response.
+pub(crate) async fn compatible_streaming_response(
+    response: reqwest::Response,
+) -> Result<streaming::StreamingCompletionResponse<StreamingCompletionResponse>, CompletionError> {
     if !response.status().is_success() {
         return Err(CompletionError::ProviderError(format!(
             "{}: {}",
@@ -159,8 +169,10 @@ pub async fn send_compatible_streaming_request(
                             // arguments: None
                             if function.name.is_some() && function.arguments.is_empty() {
                                 let id = tool_call.id.clone().unwrap_or("".to_string());
+                                let name = function.name.clone().unwrap();
 
-                                calls.insert(tool_call.index, (id, function.name.clone().unwrap(), "".to_string()));
+                                calls.insert(tool_call.index, (id.clone(), name.clone(), "".to_string()));
+                                yield Ok(RawStreamingChoice::ToolCallDelta { id, delta: ToolCallDelta::Name(name) });
                             }
                             // Part of tool call
                             // name: None
@@ -173,8 +185,10 @@ pub async fn send_compatible_streaming_request(
 
                                 let new_arguments = &tool_call.function.arguments;
                                 let arguments = format!("{}{}", arguments, new_arguments);
+                                let id = id.clone();
 
                                 calls.insert(tool_call.index, (id.clone(), name.clone(), arguments));
+                                yield Ok(RawStreamingChoice::ToolCallDelta { id, delta: ToolCallDelta::Arguments(new_arguments.clone()) });
                             }
                             // Entire tool call
                             else {
@@ -81,12 +81,12 @@ impl transcription::TranscriptionModel for TranscriptionModel {
             }
         }
 
-        let response = self
+        let builder = self
             .client
             .post("audio/transcriptions")
-            .multipart(body)
-            .send()
-            .await?;
+            .await?
+            .multipart(body);
+        let response = self.client.send(builder).await?;
 
         if response.status().is_success() {
             match response
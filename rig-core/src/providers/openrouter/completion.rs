@@ -5,7 +5,7 @@ use super::client::{ApiErrorResponse, ApiResponse, Client, Usage};
 use crate::{
     completion::{self, CompletionError, CompletionRequest},
     json_utils,
-    providers::openai::Message,
+    providers::openai::{Message, ToolDefinition},
     OneOrMany,
 };
 use serde_json::{json, Value};
@@ -96,6 +96,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
         Ok(completion::CompletionResponse {
             choice,
             raw_response: response,
+            logprobs: None,
         })
     }
 }
@@ -152,12 +153,36 @@ impl CompletionModel {
         // Combine all messages into a single history
         full_history.extend(chat_history);
 
-        let request = json!({
-            "model": self.model,
-            "messages": full_history,
-            "temperature": completion_request.temperature,
-            "tool_calls": completion_request.tools
-        });
+        let request = if completion_request.tools.is_empty() {
+            json!({
+                "model": self.model,
+                "messages": full_history,
+                "temperature": completion_request.temperature,
+            })
+        } else {
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "temperature": completion_request.temperature,
+                    "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
+        };
+
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "OpenRouter",
+                &completion::SamplingParamNames {
+                    top_k: Some("top_k"),
+                    ..completion::SamplingParamNames::OPENAI
+                },
+            ),
+        );
 
         let request = if let Some(params) = completion_request.additional_params {
             json_utils::merge(request, params)
@@ -172,16 +197,26 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            json_mode: true,
+            ..Default::default()
+        }
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("/chat/completions")
+        let response = http_extras
+            .apply(self.client.post("/chat/completions"))
             .json(&request)
             .send()
             .await?;
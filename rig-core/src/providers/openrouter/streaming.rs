@@ -109,11 +109,14 @@ impl StreamingCompletionModel for super::CompletionModel {
         completion_request: CompletionRequest,
     ) -> Result<streaming::StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>
     {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
         let request = json_utils::merge(request, json!({"stream": true}));
 
-        let builder = self.client.post("/chat/completions").json(&request);
+        let builder = http_extras
+            .apply(self.client.post("/chat/completions"))
+            .json(&request);
 
         send_streaming_request(builder).await
     }
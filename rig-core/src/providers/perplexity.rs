@@ -33,6 +33,7 @@ const PERPLEXITY_API_BASE_URL: &str = "https://api.perplexity.ai";
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
+    headers: reqwest::header::HeaderMap,
     http_client: reqwest::Client,
 }
 
@@ -51,25 +52,33 @@ impl Client {
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
+            headers: {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(
+                    "Authorization",
+                    format!("Bearer {}", api_key)
+                        .parse()
+                        .expect("Bearer token should parse"),
+                );
+                headers
+            },
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
                 .build()
                 .expect("Perplexity reqwest client should build"),
         }
     }
 
+    /// Use a custom HTTP client for the requests to the API (e.g.: with a proxy, custom root
+    /// certificates or connection pool settings). The authentication headers of the client are
+    /// added to each request.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
     pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
-        self.http_client.post(url)
+        self.http_client.post(url).headers(self.headers.clone())
     }
 
     pub fn completion_model(&self, model: &str) -> CompletionModel {
@@ -179,6 +188,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
             } => Ok(completion::CompletionResponse {
                 choice: OneOrMany::one(content.clone().into()),
                 raw_response: response,
+                logprobs: None,
             }),
             _ => Err(CompletionError::ResponseError(
                 "Response contained no assistant message".to_owned(),
@@ -232,12 +242,32 @@ impl CompletionModel {
         );
 
         // Compose request
+        if !completion_request.tools.is_empty() {
+            tracing::warn!(target: "rig",
+                "Tool calls are not supported by the Perplexity provider. {} tools will be ignored.",
+                completion_request.tools.len()
+            );
+        }
+
         let request = json!({
             "model": self.model,
             "messages": full_history,
             "temperature": completion_request.temperature,
         });
 
+        let request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "Perplexity",
+                &completion::SamplingParamNames {
+                    top_k: Some("top_k"),
+                    frequency_penalty: Some("frequency_penalty"),
+                    presence_penalty: Some("presence_penalty"),
+                    ..completion::SamplingParamNames::NONE
+                },
+            ),
+        );
+
         let request = if let Some(ref params) = completion_request.additional_params {
             json_utils::merge(request, params.clone())
         } else {
@@ -311,16 +341,23 @@ impl From<Message> for message::Message {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            ..Default::default()
+        }
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("/chat/completions")
+        let response = http_extras
+            .apply(self.client.post("/chat/completions"))
             .json(&request)
             .send()
             .await?;
@@ -348,11 +385,14 @@ impl StreamingCompletionModel for CompletionModel {
         &self,
         completion_request: completion::CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let mut request = self.create_completion_request(completion_request)?;
 
         request = merge(request, json!({"stream": true}));
 
-        let builder = self.client.post("/chat/completions").json(&request);
+        let builder = http_extras
+            .apply(self.client.post("/chat/completions"))
+            .json(&request);
 
         send_compatible_streaming_request(builder).await
     }
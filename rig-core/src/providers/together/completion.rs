@@ -170,14 +170,29 @@ impl CompletionModel {
                 "temperature": completion_request.temperature,
             })
         } else {
-            json!({
-                "model": self.model,
-                "messages": full_history,
-                "temperature": completion_request.temperature,
-                "tools": completion_request.tools.into_iter().map(openai::ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
-            })
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "temperature": completion_request.temperature,
+                    "tools": completion_request.tools.into_iter().map(openai::ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
         };
+        request = json_utils::merge(
+            request,
+            completion_request.sampling.to_json(
+                "Together",
+                &completion::SamplingParamNames {
+                    top_k: Some("top_k"),
+                    ..completion::SamplingParamNames::OPENAI
+                },
+            ),
+        );
+
         request = if let Some(params) = completion_request.additional_params {
             json_utils::merge(request, params)
         } else {
@@ -190,16 +205,27 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = openai::CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            vision: self.model.contains("Vision") || self.model.contains("llava"),
+            json_mode: true,
+            logprobs: true,
+            ..Default::default()
+        }
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
     ) -> Result<completion::CompletionResponse<openai::CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("/v1/chat/completions")
+        let response = http_extras
+            .apply(self.client.post("/v1/chat/completions"))
             .json(&request)
             .send()
             .await?;
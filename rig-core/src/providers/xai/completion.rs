@@ -68,15 +68,26 @@ impl CompletionModel {
                 "temperature": completion_request.temperature,
             })
         } else {
-            json!({
-                "model": self.model,
-                "messages": full_history,
-                "temperature": completion_request.temperature,
-                "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
-            })
+            json_utils::merge(
+                json!({
+                    "model": self.model,
+                    "messages": full_history,
+                    "temperature": completion_request.temperature,
+                    "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
+                }),
+                completion_request
+                    .tool_choice
+                    .to_openai_params(completion_request.parallel_tool_calls),
+            )
         };
 
+        request = json_utils::merge(
+            request,
+            completion_request
+                .sampling
+                .to_json("xAI", &completion::SamplingParamNames::OPENAI),
+        );
+
         request = if let Some(params) = completion_request.additional_params {
             json_utils::merge(request, params)
         } else {
@@ -96,16 +107,28 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn capabilities(&self) -> completion::Capabilities {
+        completion::Capabilities {
+            streaming: true,
+            tools: true,
+            parallel_tool_calls: true,
+            vision: self.model.contains("vision"),
+            json_mode: true,
+            ..Default::default()
+        }
+        .with_catalog("xai", &self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let request = self.create_completion_request(completion_request)?;
 
-        let response = self
-            .client
-            .post("/v1/chat/completions")
+        let response = http_extras
+            .apply(self.client.post("/v1/chat/completions"))
             .json(&request)
             .send()
             .await?;
@@ -181,6 +204,7 @@ pub mod xai_api_types {
             Ok(completion::CompletionResponse {
                 choice,
                 raw_response: response,
+                logprobs: None,
             })
         }
     }
@@ -12,11 +12,14 @@ impl StreamingCompletionModel for CompletionModel {
         &self,
         completion_request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        let http_extras = completion_request.http_extras.clone();
         let mut request = self.create_completion_request(completion_request)?;
 
         request = merge(request, json!({"stream": true}));
 
-        let builder = self.client.post("/v1/chat/completions").json(&request);
+        let builder = http_extras
+            .apply(self.client.post("/v1/chat/completions"))
+            .json(&request);
 
         send_compatible_streaming_request(builder).await
     }
@@ -0,0 +1,207 @@
+//! Coordination of the requests to a provider when it is rate limited.
+//!
+//! When a provider answers a request with a `429 Too Many Requests` and a `Retry-After` header,
+//! the [RateLimiter] of its client pauses all the requests of the client (and of its clones and
+//! models) for the indicated duration: the requests sent in the meantime wait for the end of the
+//! pause instead of failing independently, and the rate-limited request is retried after it (up
+//! to [RateLimiter::max_retries] times).
+//!
+//! The clients supporting rate limit coordination (e.g.:
+//! [openai::Client](crate::providers::openai::Client) and
+//! [anthropic::Client](crate::providers::anthropic::Client)) have a limiter by default, which
+//! can be shared between several clients of the same account with `with_rate_limiter`.
+//!
+//! # Example
+//! ```rust
+//! use rig::{providers::openai, rate_limit::RateLimiter};
+//!
+//! let limiter = RateLimiter::new().max_retries(5);
+//!
+//! // Both clients are paused when one of them is rate limited
+//! let client = openai::Client::from_env().with_rate_limiter(limiter.clone());
+//! let other = openai::Client::from_url("sk-...", "https://gateway.example.com/v1")
+//!     .with_rate_limiter(limiter);
+//! ```
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{header::HeaderMap, RequestBuilder, Response, StatusCode};
+use web_time::{Instant, SystemTime};
+
+/// Maximum duration of a pause, whatever the `Retry-After` of the provider.
+const MAX_PAUSE: Duration = Duration::from_secs(300);
+
+/// State of the rate limit of a provider, shared by the clones of a client (and its models).
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    paused_until: Arc<Mutex<Option<Instant>>>,
+    max_retries: usize,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            paused_until: Default::default(),
+            max_retries: 3,
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of times a rate-limited request is retried (3 by default).
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Pause the requests for `duration` (or until the end of the current pause, if later).
+    pub fn pause_for(&self, duration: Duration) {
+        let until = Instant::now() + duration.min(MAX_PAUSE);
+        let mut paused_until = self.paused_until.lock().unwrap();
+        if !matches!(*paused_until, Some(current) if current >= until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    /// The remaining duration of the current pause, if any.
+    pub fn remaining_pause(&self) -> Option<Duration> {
+        let paused_until = (*self.paused_until.lock().unwrap())?;
+        paused_until.checked_duration_since(Instant::now())
+    }
+
+    /// Wait for the end of the current pause, if any (including the pauses starting while
+    /// waiting).
+    pub async fn wait(&self) {
+        while let Some(remaining) = self.remaining_pause() {
+            crate::runtime::sleep(remaining).await;
+        }
+    }
+
+    /// Send `request` once the requests are not paused anymore. If the provider answers with a
+    /// `429` and a `Retry-After`, the requests are paused and `request` is retried after the
+    /// pause (if it can be cloned, i.e.: if its body is not a stream).
+    pub(crate) async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let mut request = request;
+        let mut retries = 0;
+
+        loop {
+            self.wait().await;
+
+            let retry = (retries < self.max_retries)
+                .then(|| request.try_clone())
+                .flatten();
+            let response = request.send().await?;
+
+            let Some(retry_after) = retry_after(&response) else {
+                return Ok(response);
+            };
+            self.pause_for(retry_after);
+            tracing::warn!(target: "rig",
+                "Rate limited by provider, pausing requests for {:?}", retry_after
+            );
+
+            match retry {
+                Some(retry) => {
+                    request = retry;
+                    retries += 1;
+                }
+                None => return Ok(response),
+            }
+        }
+    }
+}
+
+/// The duration to wait before retrying a rate-limited request, from the `retry-after-ms` or
+/// `Retry-After` (in seconds or as an HTTP date) headers of a `429` response.
+pub(crate) fn retry_after(response: &Response) -> Option<Duration> {
+    if response.status() != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    parse_retry_after(response.headers(), SystemTime::now())
+}
+
+fn parse_retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(millis) = header("retry-after-ms").and_then(|ms| ms.trim().parse::<f64>().ok()) {
+        return Some(Duration::from_secs_f64(millis.max(0.0) / 1000.0));
+    }
+
+    let retry_after = header("retry-after")?.trim();
+    match retry_after.parse::<f64>() {
+        Ok(secs) => Some(Duration::from_secs_f64(secs.max(0.0))),
+        Err(_) => {
+            // httpdate uses the system clock of std, which is not the clock of the browser on
+            // wasm32
+            let date = httpdate::parse_http_date(retry_after)
+                .ok()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()?;
+            let now = now.duration_since(web_time::UNIX_EPOCH).ok()?;
+            Some(date.saturating_sub(now))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = SystemTime::now();
+        let headers = |pairs: &[(&'static str, &str)]| {
+            HeaderMap::from_iter(
+                pairs
+                    .iter()
+                    .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap())),
+            )
+        };
+
+        assert_eq!(
+            parse_retry_after(&headers(&[("retry-after", "2")]), now),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            parse_retry_after(
+                &headers(&[("retry-after", "2"), ("retry-after-ms", "1500")]),
+                now
+            ),
+            Some(Duration::from_millis(1500))
+        );
+
+        let date = httpdate::fmt_http_date(now + Duration::from_secs(30));
+        let delay = parse_retry_after(&headers(&[("retry-after", &date)]), now).unwrap();
+        assert!(delay <= Duration::from_secs(30) && delay >= Duration::from_secs(29));
+
+        assert_eq!(parse_retry_after(&headers(&[]), now), None);
+        assert_eq!(
+            parse_retry_after(&headers(&[("retry-after", "soon")]), now),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shared_pause() {
+        let limiter = RateLimiter::new();
+        let other = limiter.clone();
+        assert_eq!(other.remaining_pause(), None);
+
+        limiter.pause_for(Duration::from_millis(50));
+        // A shorter pause does not shorten the current one
+        other.pause_for(Duration::from_millis(1));
+        assert!(other.remaining_pause().unwrap() > Duration::from_millis(10));
+
+        let start = Instant::now();
+        other.wait().await;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert_eq!(limiter.remaining_pause(), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! This module provides the realtime sessions of voice agents (with the `realtime` feature):
+//! bidirectional sessions with a model streaming audio (or text) in and out, e.g.: the OpenAI
+//! Realtime API (see [providers::openai::realtime](crate::providers::openai::realtime)).
+//!
+//! A [RealtimeModel] connects a [RealtimeSession] configured by a [SessionConfig]. The audio of
+//! the user is streamed with [RealtimeSession::send_audio], and the session answers with
+//! [RealtimeEvent]s: chunks of audio and transcripts of the response, detection of the turns of
+//! the user, tool calls, etc. The rig [tools](crate::tool::Tool) of the session are called
+//! automatically when the model calls them, and their results are sent back to the model.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     providers::openai,
+//!     realtime::{RealtimeEvent, RealtimeModel, RealtimeSession, SessionConfig},
+//! };
+//!
+//! let model = openai::Client::from_env().realtime_model(openai::GPT_4O_REALTIME_PREVIEW);
+//! let mut session = model
+//!     .connect(
+//!         SessionConfig::new()
+//!             .instructions("You are a helpful voice assistant.")
+//!             .voice("alloy")
+//!             .tool(WeatherTool),
+//!     )
+//!     .await?;
+//!
+//! // Stream the audio of the microphone (PCM 16 bits, 24 kHz mono)
+//! session.send_audio(&microphone_chunk).await?;
+//!
+//! while let Some(event) = session.next_event().await {
+//!     match event? {
+//!         RealtimeEvent::Audio(audio) => speaker.play(&audio),
+//!         RealtimeEvent::SpeechStarted => speaker.stop(),
+//!         RealtimeEvent::AudioTranscript(text) => print!("{text}"),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use std::sync::Arc;
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    tool::{Tool, ToolSet},
+    wasm_compat::WasmCompatSend,
+};
+
+#[derive(Debug, Error)]
+pub enum RealtimeError {
+    /// Error of the connection (e.g.: WebSocket handshake, connection closed unexpectedly)
+    #[error("ConnectionError: {0}")]
+    ConnectionError(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error parsing an event of the session
+    #[error("ResponseError: {0}")]
+    ResponseError(String),
+
+    /// The session is closed
+    #[error("The session is closed")]
+    Closed,
+}
+
+/// An event of a realtime session.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RealtimeEvent {
+    /// A chunk of the audio of the response (in the output audio format of the session)
+    Audio(Vec<u8>),
+    /// A chunk of the transcript of the audio of the response
+    AudioTranscript(String),
+    /// A chunk of the text of the response
+    Text(String),
+    /// The transcript of an utterance of the user (if the input transcription is enabled)
+    InputTranscript(String),
+    /// The user started speaking: the playback of the response should be interrupted
+    SpeechStarted,
+    /// The user stopped speaking
+    SpeechStopped,
+    /// The model called a tool. The tools of the session are called automatically (see
+    /// [RealtimeEvent::ToolResult]), the others must be answered with
+    /// [RealtimeSession::send_tool_result].
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    /// A tool of the session was called, and its result (or error) sent to the model
+    ToolResult {
+        id: String,
+        name: String,
+        result: String,
+    },
+    /// The response is complete
+    ResponseDone {
+        input_tokens: Option<u64>,
+        output_tokens: Option<u64>,
+    },
+    /// Error reported by the provider (the session remains open)
+    Error(String),
+    /// Other event of the provider
+    Other(Value),
+}
+
+/// How the turns of the user are detected.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum TurnDetection {
+    /// The provider detects the end of the turns of the user from the silences, and responds
+    #[default]
+    ServerVad,
+    /// The client commits the audio of the turns ([RealtimeSession::commit_audio]) and requests
+    /// the responses ([RealtimeSession::create_response]), e.g.: push-to-talk
+    Manual,
+}
+
+/// Configuration of a realtime session.
+#[derive(Clone, Default)]
+pub struct SessionConfig {
+    pub instructions: Option<String>,
+    pub voice: Option<String>,
+    /// Respond with text only, instead of audio and its transcript
+    pub text_only: bool,
+    pub temperature: Option<f64>,
+    /// Model transcribing the audio of the user (e.g.: `whisper-1`), if any
+    pub input_transcription: Option<String>,
+    pub turn_detection: TurnDetection,
+    pub tools: Arc<ToolSet>,
+    /// Additional parameters of the session, merged with the provider's
+    pub additional_params: Option<Value>,
+}
+
+impl SessionConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn instructions(mut self, instructions: &str) -> Self {
+        self.instructions = Some(instructions.to_string());
+        self
+    }
+
+    pub fn voice(mut self, voice: &str) -> Self {
+        self.voice = Some(voice.to_string());
+        self
+    }
+
+    pub fn text_only(mut self) -> Self {
+        self.text_only = true;
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Transcribe the audio of the user with the model `model` (see
+    /// [RealtimeEvent::InputTranscript]).
+    pub fn input_transcription(mut self, model: &str) -> Self {
+        self.input_transcription = Some(model.to_string());
+        self
+    }
+
+    pub fn turn_detection(mut self, turn_detection: TurnDetection) -> Self {
+        self.turn_detection = turn_detection;
+        self
+    }
+
+    /// Add a tool called automatically by the session. The tools shared with other configs
+    /// (e.g.: of a cloned config) are copied first, so that the other configs are unchanged.
+    pub fn tool(mut self, tool: impl Tool + 'static) -> Self {
+        Arc::make_mut(&mut self.tools).add_tool(tool);
+        self
+    }
+
+    /// Set the tools called automatically by the session.
+    pub fn tools(mut self, tools: ToolSet) -> Self {
+        self.tools = Arc::new(tools);
+        self
+    }
+
+    pub fn additional_params(mut self, additional_params: Value) -> Self {
+        self.additional_params = Some(additional_params);
+        self
+    }
+}
+
+/// Model supporting realtime sessions.
+pub trait RealtimeModel: Clone + Send + Sync {
+    type Session: RealtimeSession;
+
+    /// Connect a session configured by `config`.
+    fn connect(
+        &self,
+        config: SessionConfig,
+    ) -> impl std::future::Future<Output = Result<Self::Session, RealtimeError>> + WasmCompatSend;
+}
+
+/// A realtime session with a model.
+pub trait RealtimeSession: Send {
+    /// Stream a chunk of the audio of the user (in the input audio format of the session).
+    fn send_audio(
+        &mut self,
+        audio: &[u8],
+    ) -> impl std::future::Future<Output = Result<(), RealtimeError>> + WasmCompatSend;
+
+    /// Commit the audio streamed since the last commit as a turn of the user (with
+    /// [TurnDetection::Manual]).
+    fn commit_audio(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), RealtimeError>> + WasmCompatSend;
+
+    /// Send a text message of the user, and request a response.
+    fn send_text(
+        &mut self,
+        text: &str,
+    ) -> impl std::future::Future<Output = Result<(), RealtimeError>> + WasmCompatSend;
+
+    /// Request a response to the conversation.
+    fn create_response(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), RealtimeError>> + WasmCompatSend;
+
+    /// Interrupt the response in progress (e.g.: when the user interrupts it).
+    fn cancel_response(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), RealtimeError>> + WasmCompatSend;
+
+    /// Send the result of the tool call `id` of a tool which is not a tool of the session. The
+    /// response continues after a call to [RealtimeSession::create_response].
+    fn send_tool_result(
+        &mut self,
+        id: &str,
+        result: &str,
+    ) -> impl std::future::Future<Output = Result<(), RealtimeError>> + WasmCompatSend;
+
+    /// The next event of the session (`None` once the session is closed). The tools of the
+    /// session are called while the events are polled, so the events must be polled to make
+    /// progress.
+    fn next_event(
+        &mut self,
+    ) -> impl std::future::Future<Output = Option<Result<RealtimeEvent, RealtimeError>>> + WasmCompatSend;
+
+    /// Close the session.
+    fn close(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), RealtimeError>> + WasmCompatSend;
+}
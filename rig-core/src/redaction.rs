@@ -0,0 +1,538 @@
+//! This module provides a redaction layer that scrubs sensitive information (e.g.: emails,
+//! phone numbers, API keys) from completion requests before they reach the completion model
+//! provider, and restores the original values in the model's response.
+//!
+//! The [Redactor] struct holds a set of regex based [RedactionRule]s and optionally an
+//! [EntityRecognizer] (e.g.: a local NER model) used to detect additional entities.
+//! Each detected value is replaced by a placeholder of the form `[LABEL_n]` (e.g.: `[EMAIL_1]`).
+//! The same value is always replaced by the same placeholder within a request, so the model
+//! can still reason about it.
+//!
+//! The [RedactingModel] struct wraps any [CompletionModel] and applies a [Redactor] to every
+//! request (preamble, chat history, documents and tool call arguments).
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     agent::AgentBuilder,
+//!     providers::openai,
+//!     redaction::{RedactingModel, Redactor},
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let model = RedactingModel::new(
+//!     openai.completion_model(openai::GPT_4O),
+//!     Redactor::with_defaults(),
+//! );
+//!
+//! let agent = AgentBuilder::new(model)
+//!     .preamble("You are a helpful assistant.")
+//!     .build();
+//!
+//! // The provider will receive "Send an email to [EMAIL_1]" and any occurrence
+//! // of `[EMAIL_1]` in the response will be restored to "john.doe@example.com".
+//! let response = agent.prompt("Send an email to john.doe@example.com").await?;
+//! ```
+
+use std::{collections::HashMap, sync::Arc};
+
+use regex::Regex;
+
+use crate::{
+    completion::{self, CompletionError, CompletionModel, CompletionRequest, CompletionResponse},
+    message::{AssistantContent, Message, Text, ToolResultContent, UserContent},
+    OneOrMany,
+};
+
+/// Regex pattern used to detect email addresses.
+pub const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+/// Regex pattern used to detect phone numbers (e.g.: `+1 (555) 123-4567`, `555.123.4567`).
+pub const PHONE_PATTERN: &str = r"(?:\+\d{1,3}[\s.-]?)?\(?\b\d{3}\)?[\s.-]?\d{3}[\s.-]?\d{4}\b";
+/// Regex pattern used to detect common API keys and tokens (OpenAI, AWS, GitHub, Slack).
+pub const API_KEY_PATTERN: &str = r"\b(?:sk|pk|rk)-[A-Za-z0-9_-]{16,}|\bAKIA[0-9A-Z]{16}\b|\bgh[pousr]_[A-Za-z0-9]{36}\b|\bxox[abpr]-[A-Za-z0-9-]{10,}";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RedactionError {
+    /// The pattern of a redaction rule is not a valid regex
+    #[error("InvalidPattern: {0}")]
+    InvalidPattern(#[from] regex::Error),
+}
+
+/// An entity detected in a text, identified by its byte offsets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entity {
+    /// Label of the entity (e.g.: `PERSON`). Used to build the placeholder.
+    pub label: String,
+    /// Byte offset of the start of the entity in the text.
+    pub start: usize,
+    /// Byte offset of the end of the entity in the text (exclusive).
+    pub end: usize,
+}
+
+/// Trait for recognizers that detect entities which cannot be matched by a regex
+/// (e.g.: person names detected by a local NER model).
+pub trait EntityRecognizer: Send + Sync {
+    /// Returns the entities found in `text`.
+    fn recognize(&self, text: &str) -> Vec<Entity>;
+}
+
+impl<F> EntityRecognizer for F
+where
+    F: Fn(&str) -> Vec<Entity> + Send + Sync,
+{
+    fn recognize(&self, text: &str) -> Vec<Entity> {
+        self(text)
+    }
+}
+
+/// A regex based redaction rule.
+#[derive(Clone, Debug)]
+pub struct RedactionRule {
+    /// Label of the rule (e.g.: `EMAIL`). Used to build the placeholder.
+    pub label: String,
+    /// Pattern matching the values to redact.
+    pub pattern: Regex,
+}
+
+impl RedactionRule {
+    pub fn new(label: &str, pattern: &str) -> Result<Self, RedactionError> {
+        Ok(Self {
+            label: label.to_uppercase(),
+            pattern: Regex::new(pattern)?,
+        })
+    }
+}
+
+/// Mapping between placeholders and the original values they replace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Placeholders {
+    /// Placeholder -> original value
+    values: HashMap<String, String>,
+    /// Original value -> placeholder
+    placeholders: HashMap<String, String>,
+    /// Number of placeholders generated per label
+    counters: HashMap<String, usize>,
+}
+
+impl Placeholders {
+    fn placeholder_for(&mut self, label: &str, value: &str) -> String {
+        if let Some(placeholder) = self.placeholders.get(value) {
+            return placeholder.clone();
+        }
+
+        let counter = self.counters.entry(label.to_string()).or_default();
+        *counter += 1;
+        let placeholder = format!("[{label}_{counter}]");
+
+        self.placeholders
+            .insert(value.to_string(), placeholder.clone());
+        self.values.insert(placeholder.clone(), value.to_string());
+        placeholder
+    }
+
+    /// Get the original value replaced by `placeholder`, if any.
+    pub fn get(&self, placeholder: &str) -> Option<&str> {
+        self.values.get(placeholder).map(String::as_str)
+    }
+
+    /// Number of distinct values that were redacted.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Replace every placeholder found in `text` by its original value.
+    pub fn restore(&self, text: &str) -> String {
+        self.values
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, value)| {
+                text.replace(placeholder, value)
+            })
+    }
+}
+
+/// Redacts sensitive values from text using a set of [RedactionRule]s and an
+/// optional [EntityRecognizer].
+#[derive(Clone, Default)]
+pub struct Redactor {
+    rules: Vec<RedactionRule>,
+    recognizer: Option<Arc<dyn EntityRecognizer>>,
+}
+
+impl Redactor {
+    /// Create a new redactor without any rule.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new redactor with rules for emails, phone numbers and API keys.
+    pub fn with_defaults() -> Self {
+        Self::new()
+            .rule(RedactionRule::new("EMAIL", EMAIL_PATTERN).expect("Email pattern should compile"))
+            .rule(RedactionRule::new("KEY", API_KEY_PATTERN).expect("Key pattern should compile"))
+            .rule(RedactionRule::new("PHONE", PHONE_PATTERN).expect("Phone pattern should compile"))
+    }
+
+    /// Add a redaction rule to the redactor.
+    /// Rules are applied in the order they are added: when two matches overlap,
+    /// the match of the rule added first wins.
+    pub fn rule(mut self, rule: RedactionRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Add a redaction rule from a label and a regex pattern.
+    pub fn pattern(self, label: &str, pattern: &str) -> Result<Self, RedactionError> {
+        Ok(self.rule(RedactionRule::new(label, pattern)?))
+    }
+
+    /// Set the entity recognizer (e.g.: a NER model) used in addition to the rules.
+    /// Entities found by the recognizer take precedence over rule matches.
+    pub fn recognizer(mut self, recognizer: impl EntityRecognizer + 'static) -> Self {
+        self.recognizer = Some(Arc::new(recognizer));
+        self
+    }
+
+    /// Redact `text`, recording the generated placeholders in `placeholders`.
+    pub fn redact_with(&self, text: &str, placeholders: &mut Placeholders) -> String {
+        let mut entities = self
+            .recognizer
+            .as_ref()
+            .map(|recognizer| recognizer.recognize(text))
+            .unwrap_or_default();
+
+        entities.extend(self.rules.iter().flat_map(|rule| {
+            rule.pattern.find_iter(text).map(|m| Entity {
+                label: rule.label.clone(),
+                start: m.start(),
+                end: m.end(),
+            })
+        }));
+
+        // Keep entities in order of precedence, then drop the ones overlapping a previous one
+        let mut selected: Vec<Entity> = vec![];
+        for entity in entities {
+            if entity.start >= entity.end
+                || entity.end > text.len()
+                || !text.is_char_boundary(entity.start)
+                || !text.is_char_boundary(entity.end)
+            {
+                tracing::warn!(target: "rig", "Ignoring invalid entity span: {:?}", entity);
+                continue;
+            }
+
+            if selected
+                .iter()
+                .all(|other| entity.end <= other.start || entity.start >= other.end)
+            {
+                selected.push(entity);
+            }
+        }
+        selected.sort_by_key(|entity| entity.start);
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for entity in selected {
+            redacted.push_str(&text[cursor..entity.start]);
+            redacted.push_str(
+                &placeholders.placeholder_for(&entity.label, &text[entity.start..entity.end]),
+            );
+            cursor = entity.end;
+        }
+        redacted.push_str(&text[cursor..]);
+
+        redacted
+    }
+
+    /// Redact `text` and return the redacted text along with the generated placeholders.
+    pub fn redact(&self, text: &str) -> (String, Placeholders) {
+        let mut placeholders = Placeholders::default();
+        let redacted = self.redact_with(text, &mut placeholders);
+        (redacted, placeholders)
+    }
+
+    fn redact_json(
+        &self,
+        value: serde_json::Value,
+        placeholders: &mut Placeholders,
+    ) -> serde_json::Value {
+        map_json_strings(value, &mut |s| self.redact_with(&s, placeholders))
+    }
+
+    fn redact_message(&self, message: Message, placeholders: &mut Placeholders) -> Message {
+        match message {
+            Message::User { content } => Message::User {
+                content: content.map(|content| match content {
+                    UserContent::Text(Text { text }) => {
+                        UserContent::text(self.redact_with(&text, placeholders))
+                    }
+                    UserContent::ToolResult(mut tool_result) => {
+                        tool_result.content = tool_result.content.map(|content| match content {
+                            ToolResultContent::Text(Text { text }) => {
+                                ToolResultContent::text(self.redact_with(&text, placeholders))
+                            }
+                            content => content,
+                        });
+                        UserContent::ToolResult(tool_result)
+                    }
+                    UserContent::Document(mut document)
+                        if document.format == Some(crate::message::ContentFormat::String) =>
+                    {
+                        document.data = self.redact_with(&document.data, placeholders);
+                        UserContent::Document(document)
+                    }
+                    content => content,
+                }),
+            },
+            Message::Assistant { content } => Message::Assistant {
+                content: content.map(|content| match content {
+                    AssistantContent::Text(Text { text }) => {
+                        AssistantContent::text(self.redact_with(&text, placeholders))
+                    }
+                    AssistantContent::ToolCall(mut tool_call) => {
+                        tool_call.function.arguments =
+                            self.redact_json(tool_call.function.arguments, placeholders);
+                        AssistantContent::ToolCall(tool_call)
+                    }
+                }),
+            },
+        }
+    }
+
+    /// Redact every piece of text contained in the completion request (preamble, chat history,
+    /// documents and tool call arguments).
+    pub fn redact_request(
+        &self,
+        mut request: CompletionRequest,
+        placeholders: &mut Placeholders,
+    ) -> CompletionRequest {
+        request.preamble = request
+            .preamble
+            .map(|preamble| self.redact_with(&preamble, placeholders));
+        request.chat_history = request
+            .chat_history
+            .map(|message| self.redact_message(message, placeholders));
+        request.documents = request
+            .documents
+            .into_iter()
+            .map(|mut document| {
+                document.text = self.redact_with(&document.text, placeholders);
+                document
+            })
+            .collect();
+        request
+    }
+}
+
+/// Restore the placeholders found in the content returned by the completion model.
+pub fn restore_choice(
+    choice: OneOrMany<AssistantContent>,
+    placeholders: &Placeholders,
+) -> OneOrMany<AssistantContent> {
+    choice.map(|content| match content {
+        AssistantContent::Text(Text { text }) => {
+            AssistantContent::text(placeholders.restore(&text))
+        }
+        AssistantContent::ToolCall(mut tool_call) => {
+            tool_call.function.arguments =
+                map_json_strings(tool_call.function.arguments, &mut |s| {
+                    placeholders.restore(&s)
+                });
+            AssistantContent::ToolCall(tool_call)
+        }
+    })
+}
+
+fn map_json_strings(
+    value: serde_json::Value,
+    f: &mut impl FnMut(String) -> String,
+) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) => serde_json::Value::String(f(s)),
+        serde_json::Value::Array(values) => serde_json::Value::Array(
+            values
+                .into_iter()
+                .map(|value| map_json_strings(value, f))
+                .collect(),
+        ),
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, map_json_strings(value, f)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Completion model wrapper that redacts every request sent to the inner model
+/// and restores the placeholders in its responses.
+#[derive(Clone)]
+pub struct RedactingModel<M: CompletionModel> {
+    model: M,
+    redactor: Redactor,
+    restore: bool,
+}
+
+impl<M: CompletionModel> RedactingModel<M> {
+    pub fn new(model: M, redactor: Redactor) -> Self {
+        Self {
+            model,
+            redactor,
+            restore: true,
+        }
+    }
+
+    /// Set whether placeholders found in the model's responses should be replaced by
+    /// their original values (default: `true`).
+    pub fn restore_responses(mut self, restore: bool) -> Self {
+        self.restore = restore;
+        self
+    }
+}
+
+impl<M: CompletionModel> completion::CompletionModel for RedactingModel<M> {
+    type Response = M::Response;
+
+    fn capabilities(&self) -> completion::Capabilities {
+        self.model.capabilities()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let mut placeholders = Placeholders::default();
+        let request = self.redactor.redact_request(request, &mut placeholders);
+
+        if !placeholders.is_empty() {
+            tracing::debug!(target: "rig",
+                "Redacted {} value(s) from completion request",
+                placeholders.len()
+            );
+        }
+
+        let mut response = self.model.completion(request).await?;
+
+        if self.restore {
+            response.choice = restore_choice(response.choice, &placeholders);
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_redact_defaults() {
+        let redactor = Redactor::with_defaults();
+
+        let (redacted, placeholders) = redactor.redact(
+            "Contact john.doe@example.com or +1 555-123-4567 using key sk-abcdefghijklmnopqrstuvwx",
+        );
+
+        assert_eq!(redacted, "Contact [EMAIL_1] or [PHONE_1] using key [KEY_1]");
+        assert_eq!(placeholders.get("[EMAIL_1]"), Some("john.doe@example.com"));
+        assert_eq!(placeholders.get("[PHONE_1]"), Some("+1 555-123-4567"));
+        assert_eq!(
+            placeholders.get("[KEY_1]"),
+            Some("sk-abcdefghijklmnopqrstuvwx")
+        );
+    }
+
+    #[test]
+    fn test_same_value_same_placeholder() {
+        let redactor = Redactor::with_defaults();
+
+        let (redacted, placeholders) = redactor.redact("a@b.io, c@d.io and a@b.io again");
+
+        assert_eq!(redacted, "[EMAIL_1], [EMAIL_2] and [EMAIL_1] again");
+        assert_eq!(placeholders.len(), 2);
+        assert_eq!(
+            placeholders.restore(&redacted),
+            "a@b.io, c@d.io and a@b.io again"
+        );
+    }
+
+    #[test]
+    fn test_recognizer() {
+        let redactor = Redactor::with_defaults().recognizer(|text: &str| {
+            text.find("Alice")
+                .map(|start| Entity {
+                    label: "PERSON".to_string(),
+                    start,
+                    end: start + "Alice".len(),
+                })
+                .into_iter()
+                .collect()
+        });
+
+        let (redacted, _) = redactor.redact("Alice's email is alice@example.com");
+
+        assert_eq!(redacted, "[PERSON_1]'s email is [EMAIL_1]");
+    }
+
+    #[derive(Clone, Default)]
+    struct MockModel {
+        received: Arc<Mutex<Vec<CompletionRequest>>>,
+    }
+
+    impl completion::CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let prompt = match request.chat_history.first() {
+                Message::User { content } => match content.first() {
+                    UserContent::Text(Text { text }) => text,
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            };
+            self.received.lock().unwrap().push(request);
+
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!("Echo: {prompt}"))),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redacting_model() {
+        let mock = MockModel::default();
+        let model = RedactingModel::new(mock.clone(), Redactor::with_defaults());
+
+        let response = model
+            .completion_request("Email alice@example.com")
+            .preamble("Never leak bob@example.com".to_string())
+            .send()
+            .await
+            .unwrap();
+
+        let received = mock.received.lock().unwrap();
+        assert_eq!(
+            received[0].preamble.as_deref(),
+            Some("Never leak [EMAIL_1]")
+        );
+        assert_eq!(
+            received[0].chat_history.first(),
+            Message::user("Email [EMAIL_2]")
+        );
+        assert_eq!(
+            response.choice.first(),
+            AssistantContent::text("Echo: Email alice@example.com")
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Runtime-agnostic async primitives.
+//!
+//! Rig does not depend on a specific async runtime: its futures run under tokio, smol,
+//! async-std, `futures::executor` or embedded executors alike.
+//! - Rig never spawns tasks: the concurrency (e.g.: of the tool calls, of the dynamic context
+//!   queries or of [pipeline](crate::pipeline) batches) is driven by the future being polled.
+//! - The timers (timeouts, rate limit pauses, batch polling, ...) are [Sleep] futures, backed by
+//!   a global timer thread on native targets and by the timers of the browser on `wasm32`.
+//! - The locks held across `.await` points are from [futures::lock].
+//! - The blocking operations (e.g.: of the disk cache or of the WebAssembly tools) run on a
+//!   thread of their own, or on the blocking threads of Tokio when called within a Tokio
+//!   runtime with the `tokio` feature (enabled by the features requiring tokio).
+//!
+//! The only runtime requirement comes from the HTTP client: on native targets, the network
+//! I/O of [reqwest] runs on tokio's reactor. Under another runtime, run the futures of rig
+//! inside a tokio context, e.g.: with the `async-compat` crate:
+//! ```rust
+//! use async_compat::Compat;
+//! use rig::{completion::Prompt, providers::openai};
+//!
+//! let agent = openai::Client::from_env().agent(openai::GPT_4O).build();
+//!
+//! let response = smol::block_on(Compat::new(agent.prompt("Hello!").into_future()))?;
+//! ```
+//! The agents, pipelines and tools which do not use a built-in provider (e.g.: local models)
+//! have no runtime requirement at all.
+
+use std::{future::Future, time::Duration};
+
+/// Future completing after a duration, whatever the async runtime.
+pub type Sleep = futures_timer::Delay;
+
+/// Wait for `duration`.
+pub fn sleep(duration: Duration) -> Sleep {
+    futures_timer::Delay::new(duration)
+}
+
+/// Error of a [timeout] whose future did not complete in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Timeout: no result after {0:?}")]
+pub struct Elapsed(pub Duration);
+
+/// Run `future`, aborting it (i.e.: dropping it) if it does not complete within `duration`.
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    futures::pin_mut!(future);
+    match futures::future::select(future, sleep(duration)).await {
+        futures::future::Either::Left((output, _)) => Ok(output),
+        futures::future::Either::Right(_) => Err(Elapsed(duration)),
+    }
+}
+
+/// Run the blocking operation `f` (e.g.: file system I/O) on the blocking threads of the
+/// current Tokio runtime (with the `tokio` feature), or on a new thread if there is none.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    #[cfg(feature = "tokio")]
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        return match handle.spawn_blocking(f).await {
+            Ok(value) => value,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        };
+    }
+
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)));
+    });
+    match receiver.await {
+        Ok(Ok(value)) => value,
+        Ok(Err(panic)) => std::panic::resume_unwind(panic),
+        Err(_) => unreachable!("The blocking thread always sends its result"),
+    }
+}
+
+/// Run the blocking operation `f` inline (there are no threads on `wasm32`).
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn spawn_blocking<T>(f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse, Prompt, PromptError,
+        },
+        OneOrMany,
+    };
+
+    /// Answers after a delay
+    #[derive(Clone)]
+    struct SlowModel(Duration);
+
+    impl CompletionModel for SlowModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            sleep(self.0).await;
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text("Done")),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    // No tokio runtime in these tests
+    #[test]
+    fn test_without_tokio() {
+        futures::executor::block_on(async {
+            assert_eq!(
+                timeout(Duration::from_millis(10), sleep(Duration::from_secs(5))).await,
+                Err(Elapsed(Duration::from_millis(10)))
+            );
+            assert_eq!(timeout(Duration::from_secs(5), async { 1 }).await, Ok(1));
+            assert_eq!(spawn_blocking(|| 1).await, 1);
+
+            let agent = AgentBuilder::new(SlowModel(Duration::from_millis(10))).build();
+            assert_eq!(agent.prompt("Hi").await.unwrap(), "Done");
+
+            let agent = AgentBuilder::new(SlowModel(Duration::from_secs(5)))
+                .timeout(Duration::from_millis(10))
+                .build();
+            assert!(matches!(
+                agent.prompt("Hi").await,
+                Err(PromptError::CompletionError(CompletionError::Timeout(_)))
+            ));
+        });
+    }
+}
@@ -0,0 +1,310 @@
+//! Priority-based scheduling of the requests to a completion model with a tokens-per-minute
+//! quota, so that interactive traffic (e.g.: chat turns) goes before background traffic (e.g.:
+//! summarization or evaluation jobs) when the quota is tight.
+//!
+//! A [Scheduler] tracks the (estimated) tokens of the requests started in the last minute. The
+//! requests start right away while the quota is not exhausted; otherwise they wait in a queue
+//! ordered by [Priority] (then by arrival), and start as soon as the tokens of the oldest
+//! requests leave the window. The tokens of a request are estimated from its content (see
+//! [estimate_tokens](crate::tokens::estimate_tokens)) plus its `max_tokens`, if any.
+//!
+//! Scheduled models are created with [Scheduler::model]: the models of the same scheduler share
+//! its quota and queue.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     agent::AgentBuilder,
+//!     providers::openai,
+//!     scheduler::{Priority, Scheduler},
+//! };
+//!
+//! let model = openai::Client::from_env().completion_model(openai::GPT_4O);
+//! let scheduler = Scheduler::new(30_000);
+//!
+//! let chat = AgentBuilder::new(scheduler.model(model.clone(), Priority::Interactive)).build();
+//! let summarizer = AgentBuilder::new(scheduler.model(model, Priority::Background))
+//!     .preamble("Summarize the conversation.")
+//!     .build();
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use web_time::Instant;
+
+use crate::{
+    completion::{self, CompletionError, CompletionModel, CompletionRequest, CompletionResponse},
+    runtime::{sleep, Sleep},
+    tokens::estimate_tokens,
+};
+
+/// Priority of the requests of a scheduled model.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Background traffic (e.g.: summarization, evaluations), started last
+    Background,
+    #[default]
+    Normal,
+    /// Interactive traffic (e.g.: chat turns), started first
+    Interactive,
+}
+
+/// Scheduler of the requests of its models, sharing a tokens-per-minute quota.
+#[derive(Clone)]
+pub struct Scheduler {
+    tokens_per_window: u64,
+    window: Duration,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Tokens of the requests started in the window, by start time
+    started: VecDeque<(Instant, u64)>,
+    /// Waiting requests, ordered by priority then arrival
+    queue: BTreeSet<(std::cmp::Reverse<Priority>, u64)>,
+    wakers: HashMap<u64, Waker>,
+    next_id: u64,
+}
+
+impl State {
+    fn used(&mut self, now: Instant, window: Duration) -> u64 {
+        while matches!(self.started.front(), Some((start, _)) if now.duration_since(*start) >= window)
+        {
+            self.started.pop_front();
+        }
+        self.started.iter().map(|(_, tokens)| tokens).sum()
+    }
+
+    fn wake_all(&mut self) {
+        self.wakers.drain().for_each(|(_, waker)| waker.wake());
+    }
+}
+
+impl Scheduler {
+    /// Create a scheduler with a quota of `tokens_per_minute`.
+    pub fn new(tokens_per_minute: u64) -> Self {
+        Self {
+            tokens_per_window: tokens_per_minute,
+            window: Duration::from_secs(60),
+            state: Default::default(),
+        }
+    }
+
+    /// Set the window of the quota (one minute by default), e.g.: to enforce a quota of tokens
+    /// per hour.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Schedule the requests of `model` with the given priority.
+    pub fn model<M: CompletionModel>(&self, model: M, priority: Priority) -> ScheduledModel<M> {
+        ScheduledModel {
+            model,
+            scheduler: self.clone(),
+            priority,
+        }
+    }
+
+    /// Wait for the turn of a request of `tokens` with the given priority, and count its tokens
+    /// in the quota. A request larger than the whole quota starts once the window is empty.
+    pub async fn acquire(&self, priority: Priority, tokens: u64) {
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.queue.insert((std::cmp::Reverse(priority), id));
+            id
+        };
+
+        Admission {
+            scheduler: self,
+            key: (std::cmp::Reverse(priority), id),
+            tokens,
+            delay: None,
+            admitted: false,
+        }
+        .await
+    }
+
+    /// The tokens counted in the current window.
+    pub fn used_tokens(&self) -> u64 {
+        self.state.lock().unwrap().used(Instant::now(), self.window)
+    }
+}
+
+/// Future of a request waiting for its turn in the queue of a scheduler.
+struct Admission<'a> {
+    scheduler: &'a Scheduler,
+    key: (std::cmp::Reverse<Priority>, u64),
+    tokens: u64,
+    /// Timer until the oldest tokens leave the window, when the request is first in the queue
+    delay: Option<Sleep>,
+    admitted: bool,
+}
+
+impl Future for Admission<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if let Some(delay) = &mut self.delay {
+                if Pin::new(delay).poll(cx).is_pending() {
+                    break;
+                }
+                self.delay = None;
+            }
+
+            let scheduler = self.scheduler;
+            let mut state = scheduler.state.lock().unwrap();
+            let now = Instant::now();
+            let used = state.used(now, scheduler.window);
+
+            if state.queue.first() != Some(&self.key) {
+                state.wakers.insert(self.key.1, cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            if used == 0 || used + self.tokens <= scheduler.tokens_per_window {
+                state.queue.remove(&self.key);
+                state.wakers.remove(&self.key.1);
+                state.started.push_back((now, self.tokens));
+                // The next request of the queue may start too
+                state.wake_all();
+                self.admitted = true;
+                return Poll::Ready(());
+            }
+
+            let (oldest, _) = state
+                .started
+                .front()
+                .copied()
+                .expect("The window is not empty");
+            drop(state);
+            self.delay = Some(sleep(
+                (oldest + scheduler.window).saturating_duration_since(now),
+            ));
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if !self.admitted {
+            let mut state = self.scheduler.state.lock().unwrap();
+            state.queue.remove(&self.key);
+            state.wakers.remove(&self.key.1);
+            state.wake_all();
+        }
+    }
+}
+
+/// A completion model whose requests are scheduled by a [Scheduler].
+#[derive(Clone)]
+pub struct ScheduledModel<M> {
+    model: M,
+    scheduler: Scheduler,
+    priority: Priority,
+}
+
+/// Estimated tokens of a request: its content, plus the maximum tokens of the completion.
+fn request_tokens(request: &CompletionRequest) -> u64 {
+    let content = request.preamble.iter().cloned().chain(
+        request
+            .chat_history
+            .iter()
+            .map(|message| serde_json::to_string(message).unwrap_or_default())
+            .chain(
+                request
+                    .documents
+                    .iter()
+                    .map(|document| document.to_string()),
+            )
+            .chain(
+                request
+                    .tools
+                    .iter()
+                    .map(|tool| serde_json::to_string(tool).unwrap_or_default()),
+            ),
+    );
+
+    content
+        .map(|text| estimate_tokens(&text) as u64)
+        .sum::<u64>()
+        + request.max_tokens.unwrap_or_default()
+}
+
+impl<M: CompletionModel> CompletionModel for ScheduledModel<M> {
+    type Response = M::Response;
+
+    fn capabilities(&self) -> completion::Capabilities {
+        self.model.capabilities()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        self.scheduler
+            .acquire(self.priority, request_tokens(&request))
+            .await;
+        self.model.completion(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_interactive_requests_go_first() {
+        let scheduler = Scheduler::new(100).window(Duration::from_millis(100));
+        let order = Mutex::new(vec![]);
+
+        // Exhaust the quota
+        scheduler.acquire(Priority::Background, 80).await;
+        assert_eq!(scheduler.used_tokens(), 80);
+
+        let request = |priority, tokens, name| {
+            let (scheduler, order) = (&scheduler, &order);
+            async move {
+                scheduler.acquire(priority, tokens).await;
+                order.lock().unwrap().push(name);
+            }
+        };
+        let late_interactive = async {
+            futures_timer::Delay::new(Duration::from_millis(20)).await;
+            request(Priority::Interactive, 60, "interactive").await
+        };
+
+        futures::join!(
+            request(Priority::Background, 60, "background"),
+            late_interactive,
+            // Fits in the quota, and goes before the queued background request
+            request(Priority::Normal, 10, "normal"),
+        );
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["normal", "interactive", "background"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_large_request_starts_on_empty_window() {
+        let scheduler = Scheduler::new(10).window(Duration::from_millis(50));
+        scheduler.acquire(Priority::Normal, 1000).await;
+        scheduler.acquire(Priority::Normal, 1000).await;
+        assert_eq!(scheduler.used_tokens(), 1000);
+    }
+}
@@ -8,18 +8,42 @@
 //! - [StreamingCompletion]: Defines a low-level streaming LLM completion interface
 //! - [StreamingCompletionModel]: Defines a streaming completion model interface
 //!
+//! The tool calls are streamed as complete calls by default. When the provider streams their
+//! arguments, the fragments of the calls being generated (their name first, then the fragments
+//! of their arguments) can be streamed too, e.g.: to show `calling search(…)` live in a UI:
+//! ```rust
+//! let mut stream = agent.stream_prompt("What's the weather in Paris?").await?
+//!     .with_tool_call_deltas();
+//!
+//! while let Some(chunk) = stream.next().await {
+//!     match chunk? {
+//!         StreamedChunk::ToolCallDelta { delta: ToolCallDelta::Name(name), .. } => {
+//!             print!("calling {name}(")
+//!         }
+//!         StreamedChunk::ToolCallDelta { delta: ToolCallDelta::Arguments(args), .. } => {
+//!             print!("{args}")
+//!         }
+//!         StreamedChunk::Content(AssistantContent::ToolCall(_)) => println!(")"),
+//!         StreamedChunk::Content(content) => { /* ... */ }
+//!     }
+//! }
+//! ```
 
 use crate::agent::Agent;
+use crate::cancellation::CancellationToken;
 use crate::completion::{
     CompletionError, CompletionModel, CompletionRequest, CompletionRequestBuilder,
     CompletionResponse, Message,
 };
 use crate::message::{AssistantContent, ToolCall, ToolFunction};
+use crate::wasm_compat::{WasmBoxedStream, WasmCompatSend, WasmCompatSync};
 use crate::OneOrMany;
 use futures::{Stream, StreamExt};
-use std::boxed::Box;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 /// Enum representing a streaming chunk from the model
@@ -35,44 +59,260 @@ pub enum RawStreamingChoice<R: Clone> {
         arguments: serde_json::Value,
     },
 
+    /// A fragment of a tool call being generated, for the providers streaming the arguments of
+    /// the tool calls. The provider should yield the complete `ToolCall` (with the same `id`)
+    /// once generated, otherwise the calls are assembled from their fragments at the end of the
+    /// stream.
+    ToolCallDelta { id: String, delta: ToolCallDelta },
+
     /// The final response object, must be yielded if you want the
     /// `response` field to be populated on the `StreamingCompletionResponse`
     FinalResponse(R),
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub type StreamingResult<R> =
-    Pin<Box<dyn Stream<Item = Result<RawStreamingChoice<R>, CompletionError>> + Send>>;
+/// A fragment of a tool call being generated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ToolCallDelta {
+    /// The name of the tool, starting the call
+    Name(String),
+    /// A fragment of the (JSON) arguments of the call
+    Arguments(String),
+}
+
+/// A chunk of a streaming response, including the fragments of the tool calls being
+/// generated. See [StreamingCompletionResponse::with_tool_call_deltas].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamedChunk {
+    /// A text chunk, or a complete tool call
+    Content(AssistantContent),
+    /// A fragment of the tool call `id`
+    ToolCallDelta { id: String, delta: ToolCallDelta },
+}
+
+/// A tool call being assembled from its fragments.
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
 
-#[cfg(target_arch = "wasm32")]
 pub type StreamingResult<R> =
-    Pin<Box<dyn Stream<Item = Result<RawStreamingChoice<R>, CompletionError>>>>;
+    WasmBoxedStream<'static, Result<RawStreamingChoice<R>, CompletionError>>;
 
 /// The response from a streaming completion request;
 /// message and response are populated at the end of the
 /// `inner` stream.
 pub struct StreamingCompletionResponse<R: Clone + Unpin> {
-    inner: StreamingResult<R>,
+    /// The stream of the provider, `None` once it is over (or interrupted)
+    inner: Option<StreamingResult<R>>,
     text: String,
     tool_calls: Vec<ToolCall>,
+    /// The tool calls streamed as fragments, not completed by the provider yet
+    partial_tool_calls: Vec<PartialToolCall>,
+    /// The tool calls assembled from their fragments at the end of the stream, to forward
+    assembled_tool_calls: VecDeque<ToolCall>,
     /// The final aggregated message from the stream
     /// contains all text and tool calls generated
     pub choice: OneOrMany<AssistantContent>,
     /// The final response from the stream, may be `None`
     /// if the provider didn't yield it during the stream
     pub response: Option<R>,
+    /// Token ending the stream early (with the choice generated so far) when cancelled
+    cancellation: Option<CancellationToken>,
+    /// Set if the stream was cancelled or failed before its end
+    interruption: Option<StreamInterruption>,
+    /// Called with each streamed text fragment
+    text_callbacks: Vec<TextCallback>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type TextCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+#[cfg(target_arch = "wasm32")]
+type TextCallback = Arc<dyn Fn(&str)>;
+
+/// The reason of the interruption of a stream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "message", rename_all = "snake_case")]
+pub enum InterruptionReason {
+    /// The stream was cancelled with its [CancellationToken]
+    Cancelled,
+    /// The stream failed (e.g.: the connection dropped) with the given error
+    Error(String),
+}
+
+/// State of a stream interrupted before its end, with the output generated until the
+/// interruption, so that it can be resumed (e.g.: by prompting the model to continue after its
+/// partial message, see [StreamInterruption::resume_history]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamInterruption {
+    /// The text and (complete) tool calls streamed before the interruption
+    pub partial: OneOrMany<AssistantContent>,
+    pub reason: InterruptionReason,
+}
+
+impl StreamInterruption {
+    /// Prompt asking the model to continue its partial message.
+    pub const CONTINUE_PROMPT: &'static str =
+        "Your previous message was interrupted. Continue it exactly where it stopped, without repeating it.";
+
+    /// The partial text streamed before the interruption.
+    pub fn partial_text(&self) -> String {
+        self.partial
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The messages to append to the chat history of the interrupted request to resume it: the
+    /// partial message of the model, and the [StreamInterruption::CONTINUE_PROMPT].
+    pub fn resume_history(&self) -> Vec<Message> {
+        vec![
+            Message::Assistant {
+                content: self.partial.clone(),
+            },
+            Message::user(Self::CONTINUE_PROMPT),
+        ]
+    }
 }
 
 impl<R: Clone + Unpin> StreamingCompletionResponse<R> {
     pub fn new(inner: StreamingResult<R>) -> StreamingCompletionResponse<R> {
         Self {
-            inner,
+            inner: Some(inner),
             text: "".to_string(),
             tool_calls: vec![],
+            partial_tool_calls: vec![],
+            assembled_tool_calls: VecDeque::new(),
             choice: OneOrMany::one(AssistantContent::text("")),
             response: None,
+            cancellation: None,
+            interruption: None,
+            text_callbacks: vec![],
+        }
+    }
+
+    /// The state of the stream if it was cancelled or failed before its end (in which case the
+    /// `choice` of the response also contains the partial output).
+    pub fn interruption(&self) -> Option<&StreamInterruption> {
+        self.interruption.as_ref()
+    }
+
+    /// Record the interruption of the stream, with the output generated so far.
+    fn interrupt(&mut self, reason: InterruptionReason) {
+        self.aggregate_choice();
+        self.interruption = Some(StreamInterruption {
+            partial: self.choice.clone(),
+            reason,
+        });
+    }
+
+    /// End the stream early when `cancellation` is cancelled, dropping the underlying stream
+    /// (i.e.: aborting the request to the provider). The `choice` of the response then contains
+    /// the partial output generated before the cancellation.
+    pub fn cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Call `callback` with each text fragment, as it is streamed (e.g.: to update a UI while
+    /// the stream is consumed elsewhere).
+    pub fn on_text(
+        mut self,
+        callback: impl Fn(&str) + WasmCompatSend + WasmCompatSync + 'static,
+    ) -> Self {
+        self.text_callbacks.push(Arc::new(callback));
+        self
+    }
+
+    /// Stream the fragments of the tool calls being generated too, along with the text and the
+    /// complete tool calls.
+    pub fn with_tool_call_deltas(self) -> ToolCallDeltaStream<R> {
+        ToolCallDeltaStream { response: self }
+    }
+
+    /// Whether the stream was ended early by its cancellation token.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(
+            self.interruption,
+            Some(StreamInterruption {
+                reason: InterruptionReason::Cancelled,
+                ..
+            })
+        )
+    }
+
+    /// Track the fragment `delta` of the tool call `id`.
+    fn push_tool_call_delta(&mut self, id: &str, delta: &ToolCallDelta) {
+        match delta {
+            ToolCallDelta::Name(name) => self.partial_tool_calls.push(PartialToolCall {
+                id: id.to_string(),
+                name: name.clone(),
+                arguments: String::new(),
+            }),
+            ToolCallDelta::Arguments(arguments) => {
+                match self
+                    .partial_tool_calls
+                    .iter_mut()
+                    .find(|call| call.id == id)
+                {
+                    Some(call) => call.arguments.push_str(arguments),
+                    None => tracing::debug!(target: "rig",
+                        "Arguments streamed for the unknown tool call {}", id
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Assemble the tool calls streamed as fragments but never completed by the provider.
+    fn assemble_tool_calls(&mut self) {
+        for call in std::mem::take(&mut self.partial_tool_calls) {
+            let arguments = if call.arguments.trim().is_empty() {
+                Ok(serde_json::json!({}))
+            } else {
+                serde_json::from_str(&call.arguments)
+            };
+            match arguments {
+                Ok(arguments) => {
+                    let tool_call = ToolCall {
+                        id: call.id,
+                        function: ToolFunction {
+                            name: call.name,
+                            arguments,
+                        },
+                    };
+                    self.tool_calls.push(tool_call.clone());
+                    self.assembled_tool_calls.push_back(tool_call);
+                }
+                Err(err) => tracing::warn!(target: "rig",
+                    "Invalid arguments streamed for the tool call {} ({}): {}",
+                    call.id, call.name, err
+                ),
+            }
         }
     }
+
+    /// Collect all the text and tool calls streamed so far into the `choice`.
+    fn aggregate_choice(&mut self) {
+        let mut choice = vec![];
+
+        self.tool_calls.iter().for_each(|tc| {
+            choice.push(AssistantContent::ToolCall(tc.clone()));
+        });
+
+        // This is required to ensure there's always at least one item in the content
+        if choice.is_empty() || !self.text.is_empty() {
+            choice.insert(0, AssistantContent::text(self.text.clone()));
+        }
+
+        self.choice =
+            OneOrMany::many(choice).expect("There should be at least one assistant message");
+    }
 }
 
 impl<R: Clone + Unpin> From<StreamingCompletionResponse<R>> for CompletionResponse<Option<R>> {
@@ -80,72 +320,152 @@ impl<R: Clone + Unpin> From<StreamingCompletionResponse<R>> for CompletionRespon
         CompletionResponse {
             choice: value.choice,
             raw_response: value.response,
+            logprobs: None,
         }
     }
 }
 
-impl<R: Clone + Unpin> Stream for StreamingCompletionResponse<R> {
-    type Item = Result<AssistantContent, CompletionError>;
+impl<R: Clone + Unpin> StreamingCompletionResponse<R> {
+    fn poll_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<StreamedChunk, CompletionError>>> {
+        if let Some(cancellation) = &self.cancellation {
+            if cancellation.poll_cancelled(cx) {
+                // Drop the inner stream to abort the request, and end the stream with the
+                // partial output
+                if self.inner.take().is_some() {
+                    self.interrupt(InterruptionReason::Cancelled);
+                }
+                self.assembled_tool_calls.clear();
+                return Poll::Ready(None);
+            }
+        }
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let stream = self.get_mut();
+        if let Some(tool_call) = self.assembled_tool_calls.pop_front() {
+            return Poll::Ready(Some(Ok(StreamedChunk::Content(
+                AssistantContent::ToolCall(tool_call),
+            ))));
+        }
 
-        match stream.inner.as_mut().poll_next(cx) {
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        match inner.as_mut().poll_next(cx) {
             Poll::Pending => Poll::Pending,
             Poll::Ready(None) => {
                 // This is run at the end of the inner stream to collect all tokens into
                 // a single unified `Message`.
-                let mut choice = vec![];
+                self.inner = None;
+                self.assemble_tool_calls();
+                self.aggregate_choice();
+
+                self.poll_chunk(cx)
+            }
+            Poll::Ready(Some(Err(err))) => {
+                // Keep the output streamed so far instead of discarding it, in case the stream
+                // ends after the error (e.g.: the connection dropped)
+                self.interrupt(InterruptionReason::Error(err.to_string()));
+                Poll::Ready(Some(Err(err)))
+            }
+            Poll::Ready(Some(Ok(choice))) => {
+                // The stream recovered from its last error (if any)
+                self.interruption = None;
 
-                stream.tool_calls.iter().for_each(|tc| {
-                    choice.push(AssistantContent::ToolCall(tc.clone()));
-                });
+                match choice {
+                    RawStreamingChoice::Message(text) => {
+                        // Forward the streaming tokens to the outer stream
+                        // and concat the text together
+                        self.text = format!("{}{}", self.text, text.clone());
+                        for callback in &self.text_callbacks {
+                            callback(&text);
+                        }
+                        Poll::Ready(Some(Ok(StreamedChunk::Content(AssistantContent::text(
+                            text,
+                        )))))
+                    }
+                    RawStreamingChoice::ToolCall {
+                        id,
+                        name,
+                        arguments,
+                    } => {
+                        // Keep track of each tool call to aggregate the final message later
+                        // and pass it to the outer stream
+                        self.partial_tool_calls.retain(|call| call.id != id);
+                        self.tool_calls.push(ToolCall {
+                            id: id.clone(),
+                            function: ToolFunction {
+                                name: name.clone(),
+                                arguments: arguments.clone(),
+                            },
+                        });
+                        Poll::Ready(Some(Ok(StreamedChunk::Content(
+                            AssistantContent::tool_call(id, name, arguments),
+                        ))))
+                    }
+                    RawStreamingChoice::ToolCallDelta { id, delta } => {
+                        self.push_tool_call_delta(&id, &delta);
+                        Poll::Ready(Some(Ok(StreamedChunk::ToolCallDelta { id, delta })))
+                    }
+                    RawStreamingChoice::FinalResponse(response) => {
+                        // Set the final response field and return the next item in the stream
+                        self.response = Some(response);
 
-                // This is required to ensure there's always at least one item in the content
-                if choice.is_empty() || !stream.text.is_empty() {
-                    choice.insert(0, AssistantContent::text(stream.text.clone()));
+                        self.poll_chunk(cx)
+                    }
                 }
+            }
+        }
+    }
+}
 
-                stream.choice = OneOrMany::many(choice)
-                    .expect("There should be at least one assistant message");
+impl<R: Clone + Unpin> Stream for StreamingCompletionResponse<R> {
+    type Item = Result<AssistantContent, CompletionError>;
 
-                Poll::Ready(None)
-            }
-            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
-            Poll::Ready(Some(Ok(choice))) => match choice {
-                RawStreamingChoice::Message(text) => {
-                    // Forward the streaming tokens to the outer stream
-                    // and concat the text together
-                    stream.text = format!("{}{}", stream.text, text.clone());
-                    Poll::Ready(Some(Ok(AssistantContent::text(text))))
-                }
-                RawStreamingChoice::ToolCall {
-                    id,
-                    name,
-                    arguments,
-                } => {
-                    // Keep track of each tool call to aggregate the final message later
-                    // and pass it to the outer stream
-                    stream.tool_calls.push(ToolCall {
-                        id: id.clone(),
-                        function: ToolFunction {
-                            name: name.clone(),
-                            arguments: arguments.clone(),
-                        },
-                    });
-                    Poll::Ready(Some(Ok(AssistantContent::tool_call(id, name, arguments))))
-                }
-                RawStreamingChoice::FinalResponse(response) => {
-                    // Set the final response field and return the next item in the stream
-                    stream.response = Some(response);
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let stream = self.get_mut();
 
-                    stream.poll_next_unpin(cx)
+        loop {
+            return match stream.poll_chunk(cx) {
+                // The fragments of the tool calls are only tracked
+                Poll::Ready(Some(Ok(StreamedChunk::ToolCallDelta { .. }))) => continue,
+                Poll::Ready(Some(Ok(StreamedChunk::Content(content)))) => {
+                    Poll::Ready(Some(Ok(content)))
                 }
-            },
+                Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
+            };
         }
     }
 }
 
+/// Stream of a streaming response including the fragments of the tool calls being generated.
+/// See [StreamingCompletionResponse::with_tool_call_deltas].
+pub struct ToolCallDeltaStream<R: Clone + Unpin> {
+    response: StreamingCompletionResponse<R>,
+}
+
+impl<R: Clone + Unpin> ToolCallDeltaStream<R> {
+    /// The streaming response, with its aggregated `choice` once the stream is over.
+    pub fn response(&self) -> &StreamingCompletionResponse<R> {
+        &self.response
+    }
+
+    pub fn into_inner(self) -> StreamingCompletionResponse<R> {
+        self.response
+    }
+}
+
+impl<R: Clone + Unpin> Stream for ToolCallDeltaStream<R> {
+    type Item = Result<StreamedChunk, CompletionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().response.poll_chunk(cx)
+    }
+}
+
 /// Trait for high-level streaming prompt interface
 pub trait StreamingPrompt<R: Clone + Unpin>: Send + Sync {
     /// Stream a simple prompt to the model
@@ -179,21 +499,12 @@ pub trait StreamingCompletion<M: StreamingCompletionModel> {
 pub trait StreamingCompletionModel: CompletionModel {
     type StreamingResponse: Clone + Unpin;
     /// Stream a completion response for the given request
-    #[cfg(not(target_arch = "wasm32"))]
     fn stream(
         &self,
         request: CompletionRequest,
     ) -> impl Future<
         Output = Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>,
-    > + Send;
-
-    #[cfg(target_arch = "wasm32")]
-    fn stream(
-        &self,
-        request: CompletionRequest,
-    ) -> impl Future<
-        Output = Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>,
-    >;
+    > + WasmCompatSend;
 }
 
 /// helper function to stream a completion request to stdout
@@ -230,3 +541,129 @@ pub async fn stream_to_stdout<M: StreamingCompletionModel>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_interrupted_stream() {
+        let inner = futures::stream::iter([
+            Ok(RawStreamingChoice::<()>::Message("Once upon".to_string())),
+            Ok(RawStreamingChoice::Message(" a time".to_string())),
+            Err(CompletionError::ProviderError(
+                "connection reset".to_string(),
+            )),
+        ]);
+        let mut stream = StreamingCompletionResponse::new(Box::pin(inner));
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(stream.interruption().is_none());
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+
+        let interruption = stream.interruption().unwrap();
+        assert_eq!(interruption.partial_text(), "Once upon a time");
+        assert_eq!(
+            interruption.reason,
+            InterruptionReason::Error("ProviderError: connection reset".to_string())
+        );
+        assert_eq!(
+            interruption.resume_history(),
+            vec![
+                Message::assistant("Once upon a time"),
+                Message::user(StreamInterruption::CONTINUE_PROMPT)
+            ]
+        );
+        assert_eq!(
+            stream.choice.first(),
+            AssistantContent::text("Once upon a time")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_text_callbacks() {
+        let inner = futures::stream::iter([
+            Ok(RawStreamingChoice::<()>::Message("Hello".to_string())),
+            Ok(RawStreamingChoice::Message(" world".to_string())),
+        ]);
+        let streamed = Arc::new(std::sync::Mutex::new(String::new()));
+        let text = streamed.clone();
+        let mut stream = StreamingCompletionResponse::new(Box::pin(inner))
+            .on_text(move |fragment| text.lock().unwrap().push_str(fragment));
+
+        while stream.next().await.is_some() {}
+        assert_eq!(*streamed.lock().unwrap(), "Hello world");
+    }
+
+    #[tokio::test]
+    async fn test_recovered_stream() {
+        let inner = futures::stream::iter([
+            Err(CompletionError::ResponseError("invalid event".to_string())),
+            Ok(RawStreamingChoice::<()>::Message("Hello".to_string())),
+        ]);
+        let mut stream = StreamingCompletionResponse::new(Box::pin(inner));
+
+        while stream.next().await.is_some() {}
+
+        assert!(stream.interruption().is_none());
+        assert_eq!(stream.choice.first(), AssistantContent::text("Hello"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_deltas() {
+        let delta = |id: &str, delta| {
+            Ok(RawStreamingChoice::<()>::ToolCallDelta {
+                id: id.to_string(),
+                delta,
+            })
+        };
+        let inner = futures::stream::iter([
+            delta("1", ToolCallDelta::Name("search".to_string())),
+            delta("1", ToolCallDelta::Arguments("{\"query\": ".to_string())),
+            delta("2", ToolCallDelta::Name("add".to_string())),
+            delta("1", ToolCallDelta::Arguments("\"rig\"}".to_string())),
+            Ok(RawStreamingChoice::ToolCall {
+                id: "1".to_string(),
+                name: "search".to_string(),
+                arguments: serde_json::json!({"query": "rig"}),
+            }),
+            // Never completed by the provider
+            delta("2", ToolCallDelta::Arguments("{\"x\": 1}".to_string())),
+        ]);
+        let mut stream = StreamingCompletionResponse::new(Box::pin(inner)).with_tool_call_deltas();
+
+        let mut chunks = vec![];
+        while let Some(chunk) = stream.next().await {
+            chunks.push(chunk.unwrap());
+        }
+        assert_eq!(chunks.len(), 7);
+        assert_eq!(
+            chunks[0],
+            StreamedChunk::ToolCallDelta {
+                id: "1".to_string(),
+                delta: ToolCallDelta::Name("search".to_string())
+            }
+        );
+        assert_eq!(
+            chunks[6],
+            StreamedChunk::Content(AssistantContent::tool_call(
+                "2",
+                "add",
+                serde_json::json!({"x": 1})
+            ))
+        );
+
+        let tool_calls = stream
+            .response()
+            .choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::ToolCall(call) => Some(call.function.name.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tool_calls, vec!["search", "add"]);
+    }
+}
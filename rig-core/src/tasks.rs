@@ -0,0 +1,612 @@
+//! Scheduled background tasks of agents: prompts run after a delay, at a given time, at a fixed
+//! interval or on a cron schedule (e.g.: a daily report, or "follow up in 2 hours").
+//!
+//! A [TaskScheduler] keeps the scheduled tasks, and runs the due tasks with an agent: either
+//! with [TaskScheduler::run_due] (e.g.: from an existing timer or cron job), or with
+//! [TaskScheduler::run], a future running the tasks as they become due. Like the rest of rig,
+//! the scheduler does not spawn tasks: the [TaskScheduler::run] future must be polled, e.g.:
+//! spawned by the application.
+//!
+//! The agents can schedule future work for themselves with the [ScheduleTask] tool of the
+//! scheduler (see [TaskScheduler::tool]).
+//!
+//! The cron schedules have the 5 standard fields (minute, hour, day of the month, month and day
+//! of the week), evaluated in UTC, see [CronSchedule].
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use rig::tasks::{CronSchedule, Schedule, TaskScheduler};
+//!
+//! let scheduler = TaskScheduler::new();
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You are the assistant of the support team.")
+//!     .tool(scheduler.tool())
+//!     .build();
+//!
+//! scheduler.schedule(
+//!     "Summarize the tickets opened yesterday.",
+//!     Schedule::Cron("0 9 * * 1-5".parse::<CronSchedule>()?),
+//! )?;
+//! scheduler.schedule(
+//!     "Check the status of the deployment.",
+//!     Schedule::after(Duration::from_secs(2 * 3600)),
+//! )?;
+//!
+//! scheduler
+//!     .multi_turn(2)
+//!     .run(&agent, |run| println!("{}: {:?}", run.task_id, run.result))
+//!     .await;
+//! ```
+
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    agent::{AgentDyn, DynPromptRequest},
+    completion::{PromptError, ToolDefinition},
+    runtime::sleep,
+    tool::Tool,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TaskError {
+    /// Invalid cron expression
+    #[error("InvalidCron: {0}")]
+    InvalidCron(String),
+
+    /// Invalid schedule (e.g.: a scheduled task without delay nor cron expression)
+    #[error("InvalidSchedule: {0}")]
+    InvalidSchedule(String),
+}
+
+/// Schedule of a cron expression with the 5 standard fields `minute hour day month weekday`,
+/// evaluated in UTC.
+///
+/// Each field is `*`, a value, a range `a-b`, or a comma-separated list of them, optionally
+/// followed by a step (e.g.: `*/15`, `1-5`, `0,30`). The days of the week are 0 (or 7) to 6 from
+/// Sunday; the month and day names are not supported. Like cron, when both the day of the month
+/// and the day of the week are restricted, the days matching either field match.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+/// Parse a cron field, as a bit set of its values between `min` and `max`
+fn parse_field(field: &str, min: u32, max: u32) -> Option<u64> {
+    let mut values = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)?),
+            None => (item, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+                None => {
+                    let value = range.parse().ok()?;
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return None;
+        }
+        for value in (start..=end).step_by(step as usize) {
+            values |= 1 << value;
+        }
+    }
+    Some(values)
+}
+
+impl FromStr for CronSchedule {
+    type Err = TaskError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let invalid = || TaskError::InvalidCron(expression.to_string());
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(invalid());
+        };
+        let mut weekdays_set = parse_field(weekdays, 0, 7).ok_or_else(invalid)?;
+        // Sunday is 0 or 7
+        if weekdays_set & (1 << 7) != 0 {
+            weekdays_set = (weekdays_set | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: parse_field(minutes, 0, 59).ok_or_else(invalid)?,
+            hours: parse_field(hours, 0, 23).ok_or_else(invalid)?,
+            days: parse_field(days, 1, 31).ok_or_else(invalid)?,
+            months: parse_field(months, 1, 12).ok_or_else(invalid)?,
+            weekdays: weekdays_set,
+            days_restricted: days != "*",
+            weekdays_restricted: weekdays != "*",
+        })
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = TaskError;
+
+    fn try_from(expression: String) -> Result<Self, Self::Error> {
+        expression.parse()
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.expression
+    }
+}
+
+/// The (year, month, day) of the day `days` since the Unix epoch (see
+/// <https://howardhinnant.github.io/date_algorithms.html>)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+impl CronSchedule {
+    fn matches_day(&self, days: i64) -> bool {
+        let (_, month, day) = civil_from_days(days);
+        // The Unix epoch is a Thursday
+        let weekday = (days + 4).rem_euclid(7);
+        let day_matches = self.days & (1 << day) != 0;
+        let weekday_matches = self.weekdays & (1 << weekday) != 0;
+        self.months & (1 << month) != 0
+            && if self.days_restricted && self.weekdays_restricted {
+                day_matches || weekday_matches
+            } else {
+                day_matches && weekday_matches
+            }
+    }
+
+    /// The first time strictly after `time` matching the schedule (`None` if it never matches,
+    /// e.g.: `0 0 31 2 *`).
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+        let mut minute = seconds / 60 + 1;
+        // All the combinations of days of the month and of the week repeat within 28 years
+        let last_day = minute / 1440 + 366 * 28;
+        while minute / 1440 <= last_day {
+            let (days, minute_of_day) = (minute / 1440, minute % 1440);
+            if !self.matches_day(days) {
+                minute = (days + 1) * 1440;
+            } else if self.hours & (1 << (minute_of_day / 60)) == 0 {
+                minute = (minute / 60 + 1) * 60;
+            } else if self.minutes & (1 << (minute_of_day % 60)) == 0 {
+                minute += 1;
+            } else {
+                return Some(UNIX_EPOCH + Duration::from_secs(minute as u64 * 60));
+            }
+        }
+        None
+    }
+}
+
+/// When a scheduled task runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// Once, at the given time
+    At(SystemTime),
+    /// At a fixed interval, starting one interval after the task is scheduled
+    Every(Duration),
+    /// On a cron schedule
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// Once, after `delay`
+    pub fn after(delay: Duration) -> Self {
+        Self::At(SystemTime::now() + delay)
+    }
+
+    /// The next run of the schedule after `time` (`None` for a one-off task which ran already)
+    fn next_after(&self, time: SystemTime, first: bool) -> Option<SystemTime> {
+        match self {
+            Self::At(at) => first.then_some(*at),
+            Self::Every(interval) => Some(time + *interval),
+            Self::Cron(cron) => cron.next_after(time),
+        }
+    }
+}
+
+/// A task of a [TaskScheduler].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledTask {
+    pub id: String,
+    /// Prompt sent to the agent
+    pub prompt: String,
+    pub schedule: Schedule,
+    /// Time of the next run of the task
+    pub next_run: SystemTime,
+}
+
+/// A run of a [ScheduledTask].
+#[derive(Debug)]
+pub struct TaskRun {
+    pub task_id: String,
+    pub prompt: String,
+    pub result: Result<String, PromptError>,
+}
+
+#[derive(Default)]
+struct Tasks {
+    tasks: Vec<ScheduledTask>,
+    next_id: u64,
+}
+
+/// Scheduler of the background tasks of agents, cheap to clone (the clones share the tasks).
+#[derive(Clone)]
+pub struct TaskScheduler {
+    tasks: Arc<Mutex<Tasks>>,
+    max_depth: usize,
+    poll_interval: Duration,
+}
+
+impl Default for TaskScheduler {
+    fn default() -> Self {
+        Self {
+            tasks: Default::default(),
+            max_depth: 0,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl TaskScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum depth of the prompts of the tasks (see
+    /// [PromptRequest::multi_turn](crate::agent::PromptRequest::multi_turn)), e.g.: to let the
+    /// agents call their tools
+    pub fn multi_turn(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Set the maximum delay of [TaskScheduler::run] between two checks of the due tasks (1
+    /// second by default), i.e.: the maximum lateness of the tasks scheduled while it waits
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Schedule the prompt `prompt`, returning the id of the task.
+    pub fn schedule(&self, prompt: &str, schedule: Schedule) -> Result<String, TaskError> {
+        let next_run = schedule
+            .next_after(SystemTime::now(), true)
+            .ok_or_else(|| TaskError::InvalidSchedule("The schedule never runs".to_string()))?;
+        let mut tasks = self.tasks.lock().expect("Tasks lock poisoned");
+        tasks.next_id += 1;
+        let id = format!("task-{}", tasks.next_id);
+        tasks.tasks.push(ScheduledTask {
+            id: id.clone(),
+            prompt: prompt.to_string(),
+            schedule,
+            next_run,
+        });
+        Ok(id)
+    }
+
+    /// Cancel the task `id`, returning whether it was scheduled.
+    pub fn cancel(&self, id: &str) -> bool {
+        let mut tasks = self.tasks.lock().expect("Tasks lock poisoned");
+        let count = tasks.tasks.len();
+        tasks.tasks.retain(|task| task.id != id);
+        tasks.tasks.len() < count
+    }
+
+    /// The scheduled tasks, by time of their next run.
+    pub fn tasks(&self) -> Vec<ScheduledTask> {
+        let mut tasks = self
+            .tasks
+            .lock()
+            .expect("Tasks lock poisoned")
+            .tasks
+            .clone();
+        tasks.sort_by_key(|task| task.next_run);
+        tasks
+    }
+
+    /// Take the tasks due at `now`, rescheduling the recurring ones. A recurring task late by
+    /// several runs (e.g.: while the process was stopped) runs once.
+    fn take_due(&self, now: SystemTime) -> Vec<ScheduledTask> {
+        let mut tasks = self.tasks.lock().expect("Tasks lock poisoned");
+        let mut due = vec![];
+        tasks.tasks.retain_mut(|task| {
+            if task.next_run > now {
+                return true;
+            }
+            due.push(task.clone());
+            match task.schedule.next_after(now, false) {
+                Some(next_run) => {
+                    task.next_run = next_run;
+                    true
+                }
+                None => false,
+            }
+        });
+        due.sort_by_key(|task| task.next_run);
+        due
+    }
+
+    /// Run the tasks due at `now` with `agent`, one after the other.
+    pub async fn run_due_at(&self, agent: &dyn AgentDyn, now: SystemTime) -> Vec<TaskRun> {
+        let mut runs = vec![];
+        for task in self.take_due(now) {
+            let result = DynPromptRequest::new(agent, task.prompt.as_str())
+                .multi_turn(self.max_depth)
+                .await;
+            runs.push(TaskRun {
+                task_id: task.id,
+                prompt: task.prompt,
+                result,
+            });
+        }
+        runs
+    }
+
+    /// Run the due tasks with `agent`, one after the other.
+    pub async fn run_due(&self, agent: &dyn AgentDyn) -> Vec<TaskRun> {
+        self.run_due_at(agent, SystemTime::now()).await
+    }
+
+    /// Run the tasks with `agent` as they become due, passing their runs to `on_run`. The future
+    /// never completes, drop it to stop the scheduler.
+    pub async fn run(&self, agent: &dyn AgentDyn, mut on_run: impl FnMut(TaskRun)) {
+        loop {
+            for run in self.run_due(agent).await {
+                on_run(run);
+            }
+            let now = SystemTime::now();
+            let wait = self
+                .tasks()
+                .first()
+                .map(|task| task.next_run.duration_since(now).unwrap_or_default())
+                .unwrap_or(self.poll_interval)
+                .min(self.poll_interval);
+            sleep(wait).await;
+        }
+    }
+
+    /// The tool scheduling tasks for the agent (see [ScheduleTask]).
+    pub fn tool(&self) -> ScheduleTask {
+        ScheduleTask(self.clone())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleTaskArgs {
+    /// Prompt of the task, i.e.: the instructions of the agent for its future self
+    pub prompt: String,
+    /// Delay of the task, in seconds
+    pub delay_seconds: Option<u64>,
+    /// Cron expression of a recurring task
+    pub cron: Option<String>,
+}
+
+/// Tool scheduling a task of the [TaskScheduler] (after a delay, or on a cron schedule), named
+/// `schedule_task`, e.g.: so that the agent follows up on its work later.
+#[derive(Clone)]
+pub struct ScheduleTask(TaskScheduler);
+
+impl Tool for ScheduleTask {
+    const NAME: &'static str = "schedule_task";
+
+    type Error = TaskError;
+    type Args = ScheduleTaskArgs;
+    type Output = serde_json::Value;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Schedule a task for yourself, to run later: either once after a \
+                delay, or on a recurring cron schedule (in UTC)."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "prompt": {
+                        "type": "string",
+                        "description": "The instructions of the task, for your future self"
+                    },
+                    "delay_seconds": {
+                        "type": "integer",
+                        "description": "Run the task once, after this delay in seconds"
+                    },
+                    "cron": {
+                        "type": "string",
+                        "description": "Run the task on this cron schedule (minute hour day month weekday)"
+                    }
+                },
+                "required": ["prompt"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let schedule = match (args.delay_seconds, args.cron) {
+            (Some(delay), None) => Schedule::after(Duration::from_secs(delay)),
+            (None, Some(cron)) => Schedule::Cron(cron.parse()?),
+            _ => {
+                return Err(TaskError::InvalidSchedule(
+                    "Expected either a delay or a cron expression".to_string(),
+                ))
+            }
+        };
+        let task_id = self.0.schedule(&args.prompt, schedule)?;
+        let next_run = self
+            .0
+            .tasks()
+            .into_iter()
+            .find(|task| task.id == task_id)
+            .and_then(|task| task.next_run.duration_since(UNIX_EPOCH).ok())
+            .map(|time| time.as_secs());
+        Ok(json!({"task_id": task_id, "next_run_unix": next_run}))
+    }
+
+    fn static_definition(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse,
+        },
+        OneOrMany,
+    };
+
+    #[derive(Clone)]
+    struct EchoModel;
+
+    impl CompletionModel for EchoModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(
+                    request
+                        .chat_history
+                        .iter()
+                        .last()
+                        .and_then(|message| message.rag_text())
+                        .unwrap_or_default(),
+                )),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    fn time(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        // 2024-01-01T00:00:00Z, a Monday
+        let monday = 1_704_067_200;
+
+        let cron = "30 9 * * 1-5".parse::<CronSchedule>().unwrap();
+        assert_eq!(
+            cron.next_after(time(monday)),
+            Some(time(monday + 9 * 3600 + 1800))
+        );
+        // Friday 2024-01-05 09:30, then Monday 2024-01-08 09:30
+        let friday = monday + 4 * 86_400 + 9 * 3600 + 1800;
+        assert_eq!(
+            cron.next_after(time(friday)),
+            Some(time(monday + 7 * 86_400 + 9 * 3600 + 1800))
+        );
+
+        let cron = "*/15 * * * *".parse::<CronSchedule>().unwrap();
+        assert_eq!(cron.next_after(time(monday + 60)), Some(time(monday + 900)));
+
+        // The 29th of February
+        let cron = "0 0 29 2 *".parse::<CronSchedule>().unwrap();
+        assert_eq!(cron.next_after(time(monday)), Some(time(1_709_164_800)));
+        let cron = "0 0 31 2 *".parse::<CronSchedule>().unwrap();
+        assert_eq!(cron.next_after(time(monday)), None);
+
+        for invalid in ["* * * *", "60 * * * *", "*/0 * * * *", "a * * * *"] {
+            assert!(invalid.parse::<CronSchedule>().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_scheduler() {
+        let scheduler = TaskScheduler::new();
+        let agent = AgentBuilder::new(EchoModel).build();
+        let hour = Duration::from_secs(3600);
+        let now = SystemTime::now();
+
+        let follow_up = scheduler
+            .schedule("Follow up", Schedule::At(now + 2 * hour))
+            .unwrap();
+        scheduler
+            .schedule("Check the status", Schedule::Every(hour))
+            .unwrap();
+        let cancelled = scheduler.schedule("Cancelled", Schedule::At(now)).unwrap();
+        assert!(scheduler.cancel(&cancelled));
+        assert!(!scheduler.cancel(&cancelled));
+
+        assert!(scheduler.run_due_at(&agent, now).await.is_empty());
+        let runs = scheduler.run_due_at(&agent, now + 2 * hour).await;
+        let responses = runs
+            .into_iter()
+            .map(|run| run.result.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(responses, ["Check the status", "Follow up"]);
+
+        // The recurring task is rescheduled, the one-off task is done
+        let tasks = scheduler.tasks();
+        assert_eq!(tasks.len(), 1);
+        assert_ne!(tasks[0].id, follow_up);
+        assert_eq!(tasks[0].next_run, now + 3 * hour);
+
+        // The agents schedule the tasks with the tool
+        let output = scheduler
+            .tool()
+            .call(ScheduleTaskArgs {
+                prompt: "Send the report".to_string(),
+                delay_seconds: None,
+                cron: Some("0 9 * * *".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(output["task_id"], "task-4");
+        assert!(scheduler
+            .tool()
+            .call(ScheduleTaskArgs {
+                prompt: "Send the report".to_string(),
+                delay_seconds: Some(60),
+                cron: Some("0 9 * * *".to_string()),
+            })
+            .await
+            .is_err());
+        assert_eq!(scheduler.tasks().len(), 2);
+    }
+}
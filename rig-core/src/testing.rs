@@ -0,0 +1,284 @@
+//! Helpers to make the behavior of agents reproducible, so that it can be snapshot-tested.
+//!
+//! An agent built with [AgentBuilder::deterministic](crate::agent::AgentBuilder::deterministic)
+//! samples its completions with a temperature of 0 and a fixed seed (best effort, depending on
+//! the provider), and a [ScriptedTool] injected with
+//! [AgentBuilder::scripted_tool](crate::agent::AgentBuilder::scripted_tool) returns canned results
+//! instead of calling the actual tool.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, providers::openai, testing::ScriptedTool};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let weather = ScriptedTool::new("get_weather").returns("Sunny, 21°C");
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent(openai::GPT_4O)
+//!     .preamble("You are a weather assistant.")
+//!     .tool(GetWeather)
+//!     // Keep the definition of the `get_weather` tool, but return a canned result
+//!     .scripted_tool(weather.clone())
+//!     .deterministic(42)
+//!     .build();
+//!
+//! let response = agent.prompt("What's the weather in Paris?").multi_turn(2).await?;
+//!
+//! assert_eq!(weather.calls().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+
+use crate::wasm_compat::WasmBoxedFuture;
+use crate::{
+    completion::ToolDefinition,
+    tool::{ToolDyn, ToolError, ToolType},
+};
+
+/// Error returned by a [ScriptedTool], either scripted with [ScriptedTool::fails] or because the
+/// tool was called more times than it was scripted for.
+#[derive(Debug, thiserror::Error)]
+#[error("Scripted tool error: {0}")]
+pub struct ScriptedToolError(String);
+
+impl From<ScriptedToolError> for ToolError {
+    fn from(e: ScriptedToolError) -> Self {
+        ToolError::ToolCallError(Box::new(e))
+    }
+}
+
+/// A tool returning canned results (in order, one per call) and recording the arguments it is
+/// called with.
+///
+/// Clones share the script and the recorded calls, so a clone can be kept to inspect the calls
+/// after injecting the tool in an agent.
+#[derive(Clone)]
+pub struct ScriptedTool {
+    name: String,
+    definition: Option<ToolDefinition>,
+    script: Arc<Mutex<VecDeque<Result<String, String>>>>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl ScriptedTool {
+    /// Create a scripted tool named `name`. When injected in an agent already having a tool with
+    /// this name, the definition of that tool is kept (see [ScriptedTool::definition] otherwise).
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            definition: None,
+            script: Default::default(),
+            calls: Default::default(),
+        }
+    }
+
+    /// Set the definition of the tool sent to the model.
+    pub fn definition(mut self, definition: ToolDefinition) -> Self {
+        self.name = definition.name.clone();
+        self.definition = Some(definition);
+        self
+    }
+
+    /// Return `output` (serialized to JSON, like the output of a [Tool](crate::tool::Tool)) on
+    /// the next unscripted call.
+    pub fn returns(self, output: impl Serialize) -> Self {
+        let output = serde_json::to_string(&output).expect("Tool output should serialize");
+        self.script.lock().unwrap().push_back(Ok(output));
+        self
+    }
+
+    /// Fail with `message` on the next unscripted call.
+    pub fn fails(self, message: &str) -> Self {
+        self.script
+            .lock()
+            .unwrap()
+            .push_back(Err(message.to_string()));
+        self
+    }
+
+    /// The arguments of the calls of the tool so far.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The number of scripted results not returned yet.
+    pub fn remaining(&self) -> usize {
+        self.script.lock().unwrap().len()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A [ScriptedTool] replacing the tool of the same name of a toolset, if any (which is only used
+/// for its definition).
+pub(crate) struct InjectedTool {
+    pub(crate) scripted: ScriptedTool,
+    pub(crate) replaced: Option<ToolType>,
+}
+
+impl ToolDyn for InjectedTool {
+    fn name(&self) -> String {
+        self.scripted.name.clone()
+    }
+
+    fn definition(&self, prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        Box::pin(async move {
+            match (&self.scripted.definition, &self.replaced) {
+                (Some(definition), _) => definition.clone(),
+                (None, Some(replaced)) => replaced.definition(prompt).await,
+                (None, None) => ToolDefinition {
+                    name: self.scripted.name.clone(),
+                    description: String::new(),
+                    parameters: serde_json::json!({"type": "object", "properties": {}}),
+                },
+            }
+        })
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        Box::pin(async move {
+            self.scripted.calls.lock().unwrap().push(args);
+
+            match self.scripted.script.lock().unwrap().pop_front() {
+                Some(Ok(output)) => Ok(output),
+                Some(Err(message)) => Err(ScriptedToolError(message).into()),
+                None => Err(ScriptedToolError(format!(
+                    "No scripted result left for tool `{}`",
+                    self.scripted.name
+                ))
+                .into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            self, AssistantContent, CompletionError, CompletionRequest, CompletionResponse, Prompt,
+        },
+        message::Message,
+        tool::Tool,
+        OneOrMany,
+    };
+
+    struct Add;
+
+    impl Tool for Add {
+        const NAME: &'static str = "add";
+
+        type Error = ScriptedToolError;
+        type Args = serde_json::Value;
+        type Output = i64;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Add two numbers".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            }
+        }
+
+        async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+            panic!("The scripted tool should be called instead")
+        }
+    }
+
+    /// Calls the `add` tool, then answers with the result of the tool
+    #[derive(Clone, Default)]
+    struct MockModel {
+        received: Arc<Mutex<Vec<CompletionRequest>>>,
+    }
+
+    impl completion::CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let choice = match request.chat_history.iter().last() {
+                Some(Message::User { content }) => match content.first() {
+                    completion::message::UserContent::ToolResult(result) => {
+                        match result.content.first() {
+                            completion::message::ToolResultContent::Text(text) => {
+                                AssistantContent::text(text.text)
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                    _ => AssistantContent::tool_call(
+                        "call_1",
+                        "add",
+                        serde_json::json!({"x": 1, "y": 2}),
+                    ),
+                },
+                _ => unreachable!(),
+            };
+            self.received.lock().unwrap().push(request);
+
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_agent_with_scripted_tool() {
+        let model = MockModel::default();
+        let add = ScriptedTool::new("add").returns(4).fails("overflow");
+
+        let agent = AgentBuilder::new(model.clone())
+            .tool(Add)
+            .scripted_tool(add.clone())
+            .deterministic(42)
+            .build();
+
+        let response = agent.prompt("1 + 2?").multi_turn(1).await.unwrap();
+
+        assert_eq!(response, "4");
+        assert_eq!(add.calls(), vec![r#"{"x":1,"y":2}"#.to_string()]);
+        assert_eq!(add.remaining(), 1);
+
+        let received = model.received.lock().unwrap();
+        assert_eq!(received[0].temperature, Some(0.0));
+        assert_eq!(received[0].sampling.seed, Some(42));
+        assert_eq!(received[0].tools[0].description, "Add two numbers");
+    }
+
+    #[tokio::test]
+    async fn test_scripted_tool_exhausted() {
+        let tool = InjectedTool {
+            scripted: ScriptedTool::new("lookup").fails("not found"),
+            replaced: None,
+        };
+
+        assert!(tool
+            .call("{}".to_string())
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("not found"));
+        assert!(tool
+            .call("{}".to_string())
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("No scripted result left"));
+        assert_eq!(tool.definition(String::new()).await.name, "lookup");
+    }
+}
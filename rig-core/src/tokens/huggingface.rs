@@ -0,0 +1,44 @@
+//! Tokenizer wrapping the tokenizers of the Hugging Face `tokenizers` library.
+
+use std::path::Path;
+
+use super::{estimate_tokens, Tokenizer, TokenizerError};
+
+/// Tokenizer of an open weights model, loaded from its `tokenizer.json`.
+#[derive(Clone)]
+pub struct HuggingFaceTokenizer {
+    tokenizer: tokenizers::Tokenizer,
+}
+
+impl From<tokenizers::Tokenizer> for HuggingFaceTokenizer {
+    fn from(tokenizer: tokenizers::Tokenizer) -> Self {
+        Self { tokenizer }
+    }
+}
+
+impl HuggingFaceTokenizer {
+    /// Load a tokenizer from a `tokenizer.json` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TokenizerError> {
+        tokenizers::Tokenizer::from_file(path)
+            .map(Self::from)
+            .map_err(|err| TokenizerError::InvalidData(err.to_string()))
+    }
+
+    /// Load a tokenizer from the content of a `tokenizer.json` file.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, TokenizerError> {
+        tokenizers::Tokenizer::from_bytes(bytes)
+            .map(Self::from)
+            .map_err(|err| TokenizerError::InvalidData(err.to_string()))
+    }
+}
+
+impl Tokenizer for HuggingFaceTokenizer {
+    /// Count the tokens of `text`, without the special tokens added by the post-processor of the
+    /// tokenizer (e.g.: `<s>`). Falls back to an estimate if the text cannot be encoded.
+    fn count_tokens(&self, text: &str) -> usize {
+        self.tokenizer
+            .encode(text, false)
+            .map(|encoding| encoding.len())
+            .unwrap_or_else(|_| estimate_tokens(text))
+    }
+}
@@ -0,0 +1,199 @@
+//! This module provides tokenizers to count the tokens of texts, e.g.: to manage the context
+//! window of the models, estimate the cost of requests or pack documents into a token budget.
+//!
+//! The following tokenizers are provided:
+//! - [TiktokenTokenizer]: exact token counts for OpenAI models, using the BPE encodings of
+//!   `tiktoken` (see [Encoding]).
+//! - [HuggingFaceTokenizer] (with the `tokenizers` feature): wraps a tokenizer of the Hugging
+//!   Face `tokenizers` library (e.g.: the `tokenizer.json` of an open weights model).
+//! - [HeuristicTokenizer]: estimates the number of tokens from the length of the texts, for
+//!   models without a known tokenizer.
+//!
+//! Tokenizers implement the [Tokenizer] trait, and can be looked up by model name with a
+//! [TokenizerRegistry].
+//!
+//! # Example
+//! ```rust
+//! use rig::tokens::{Encoding, TiktokenTokenizer, Tokenizer, TokenizerRegistry};
+//!
+//! let encoding = Encoding::for_model("gpt-4o").unwrap();
+//! let tokenizer = TiktokenTokenizer::fetch(encoding).await?;
+//!
+//! let registry = TokenizerRegistry::new().register("gpt-4o", tokenizer);
+//! let tokens = registry.get("gpt-4o-mini").count_tokens("Hello, world!");
+//! ```
+
+use std::sync::Arc;
+
+use crate::chunking::LengthFunction;
+
+#[cfg(feature = "tokenizers")]
+pub mod huggingface;
+pub mod tiktoken;
+
+#[cfg(feature = "tokenizers")]
+pub use huggingface::HuggingFaceTokenizer;
+pub use tiktoken::{Encoding, TiktokenTokenizer};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenizerError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// The tokenizer data (e.g.: a `.tiktoken` file) is invalid
+    #[error("Invalid tokenizer data: {0}")]
+    InvalidData(String),
+}
+
+/// Trait for tokenizers.
+pub trait Tokenizer: Send + Sync {
+    /// Count the tokens of `text`.
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Truncate `text` to its longest prefix (on a character boundary) of at most `max_tokens`
+    /// tokens.
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        if self.count_tokens(text) <= max_tokens {
+            return text;
+        }
+
+        // Binary search of the longest prefix fitting in `max_tokens`
+        let boundaries = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .collect::<Vec<_>>();
+        let fits = boundaries
+            .partition_point(|&end| self.count_tokens(&text[..end]) <= max_tokens)
+            .max(1);
+
+        &text[..boundaries[fits - 1]]
+    }
+}
+
+impl<T: Tokenizer + ?Sized> Tokenizer for Arc<T> {
+    fn count_tokens(&self, text: &str) -> usize {
+        (**self).count_tokens(text)
+    }
+
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        (**self).truncate(text, max_tokens)
+    }
+}
+
+/// Estimate the number of tokens of `text` (about 4 bytes per token for English text).
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Tokenizer estimating the number of tokens of texts from their length in bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct HeuristicTokenizer {
+    bytes_per_token: usize,
+}
+
+impl Default for HeuristicTokenizer {
+    fn default() -> Self {
+        Self { bytes_per_token: 4 }
+    }
+}
+
+impl HeuristicTokenizer {
+    /// Create a tokenizer estimating that a token is `bytes_per_token` bytes long (default: 4).
+    pub fn new(bytes_per_token: usize) -> Self {
+        Self {
+            bytes_per_token: bytes_per_token.max(1),
+        }
+    }
+}
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.len().div_ceil(self.bytes_per_token)
+    }
+}
+
+/// Create a [LengthFunction] measuring texts in tokens, e.g.: to set the chunk sizes of the
+/// [chunking](crate::chunking) splitters in tokens.
+pub fn length_function(tokenizer: impl Tokenizer + 'static) -> LengthFunction {
+    Arc::new(move |text: &str| tokenizer.count_tokens(text))
+}
+
+/// Registry of the tokenizers of models, looked up by model name prefix.
+///
+/// Models without a registered tokenizer use a [HeuristicTokenizer] by default.
+#[derive(Clone)]
+pub struct TokenizerRegistry {
+    tokenizers: Vec<(String, Arc<dyn Tokenizer>)>,
+    fallback: Arc<dyn Tokenizer>,
+}
+
+impl Default for TokenizerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenizerRegistry {
+    pub fn new() -> Self {
+        Self {
+            tokenizers: vec![],
+            fallback: Arc::new(HeuristicTokenizer::default()),
+        }
+    }
+
+    /// Register the tokenizer of the models whose name starts with `model_prefix`. When
+    /// multiple prefixes match a model, the longest one is used.
+    pub fn register(mut self, model_prefix: &str, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizers
+            .retain(|(prefix, _)| prefix.as_str() != model_prefix);
+        self.tokenizers
+            .push((model_prefix.to_string(), Arc::new(tokenizer)));
+        self
+    }
+
+    /// Set the tokenizer of the models without a registered tokenizer.
+    pub fn fallback(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.fallback = Arc::new(tokenizer);
+        self
+    }
+
+    /// Get the tokenizer of a model.
+    pub fn get(&self, model: &str) -> Arc<dyn Tokenizer> {
+        self.tokenizers
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or_else(|| self.fallback.clone(), |(_, tokenizer)| tokenizer.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heuristic_truncate() {
+        let tokenizer = HeuristicTokenizer::default();
+
+        assert_eq!(tokenizer.count_tokens("Hello, world!"), 4);
+        assert_eq!(tokenizer.truncate("Hello, world!", 2), "Hello, w");
+        assert_eq!(tokenizer.truncate("Hello, world!", 10), "Hello, world!");
+        // Truncation happens on character boundaries
+        assert_eq!(tokenizer.truncate("héllo", 1), "hél");
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = TokenizerRegistry::new()
+            .register("gpt-4", HeuristicTokenizer::new(1))
+            .register("gpt-4o", HeuristicTokenizer::new(2));
+
+        assert_eq!(registry.get("gpt-4o-mini").count_tokens("abcd"), 2);
+        assert_eq!(registry.get("gpt-4-turbo").count_tokens("abcd"), 4);
+        assert_eq!(registry.get("claude-3-5-sonnet").count_tokens("abcd"), 1);
+    }
+}
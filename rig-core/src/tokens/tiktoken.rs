@@ -0,0 +1,313 @@
+//! Tokenizer compatible with the BPE encodings of OpenAI's `tiktoken`.
+//!
+//! The encodings are loaded from their `.tiktoken` rank files (one base64-encoded token and its
+//! rank per line), either fetched from OpenAI (see [TiktokenTokenizer::fetch]) or read from a
+//! local copy (see [TiktokenTokenizer::from_file]).
+
+use std::{collections::HashMap, path::Path};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use regex::Regex;
+
+use super::{Tokenizer, TokenizerError};
+
+/// BPE encodings of `tiktoken`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Encoding of GPT-4, GPT-3.5 and the `text-embedding-3` and `text-embedding-ada-002` models
+    Cl100kBase,
+    /// Encoding of GPT-4o, GPT-4.1 and the o-series reasoning models
+    O200kBase,
+}
+
+// The `\s+(?!\S)` alternative of the original patterns is emulated in
+// `TiktokenTokenizer::pieces` since the regex crate does not support lookarounds: the whitespace
+// matched by the last capture group is returned without its last character when followed by
+// non-whitespace.
+const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|(\s+)";
+const O200K_PATTERN: &str = r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|(\s+)";
+
+impl Encoding {
+    /// Get the encoding of an OpenAI model, e.g.: `gpt-4o-mini` or `text-embedding-3-small`.
+    pub fn for_model(model: &str) -> Option<Self> {
+        const O200K: [&str; 8] = [
+            "gpt-4o",
+            "gpt-4.1",
+            "gpt-4.5",
+            "gpt-5",
+            "chatgpt-4o",
+            "o1",
+            "o3",
+            "o4",
+        ];
+        const CL100K: [&str; 4] = ["gpt-4", "gpt-3.5", "gpt-35", "text-embedding-"];
+
+        // Fine-tuned models are named `ft:<base model>:...`
+        let model = model.strip_prefix("ft:").unwrap_or(model);
+
+        if O200K.iter().any(|prefix| model.starts_with(prefix)) {
+            Some(Encoding::O200kBase)
+        } else if CL100K.iter().any(|prefix| model.starts_with(prefix)) {
+            Some(Encoding::Cl100kBase)
+        } else {
+            None
+        }
+    }
+
+    /// Get the name of the encoding, e.g.: `cl100k_base`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Cl100kBase => "cl100k_base",
+            Encoding::O200kBase => "o200k_base",
+        }
+    }
+
+    /// Get the URL of the `.tiktoken` rank file of the encoding.
+    pub fn url(&self) -> String {
+        format!(
+            "https://openaipublic.blob.core.windows.net/encodings/{}.tiktoken",
+            self.name()
+        )
+    }
+
+    fn pattern(&self) -> &'static str {
+        match self {
+            Encoding::Cl100kBase => CL100K_PATTERN,
+            Encoding::O200kBase => O200K_PATTERN,
+        }
+    }
+}
+
+/// Tokenizer compatible with `tiktoken`, counting the tokens of the texts exactly as the OpenAI
+/// models do (special tokens such as `<|endoftext|>` are counted as ordinary text).
+#[derive(Clone, Debug)]
+pub struct TiktokenTokenizer {
+    encoding: Encoding,
+    ranks: HashMap<Vec<u8>, u32>,
+    pattern: Regex,
+}
+
+impl TiktokenTokenizer {
+    /// Create a tokenizer from the ranks of the tokens of an encoding.
+    pub fn new(encoding: Encoding, ranks: HashMap<Vec<u8>, u32>) -> Self {
+        Self {
+            encoding,
+            ranks,
+            pattern: Regex::new(encoding.pattern()).expect("Encoding pattern should be valid"),
+        }
+    }
+
+    /// Create a tokenizer from the content of the `.tiktoken` rank file of an encoding.
+    pub fn from_tiktoken(encoding: Encoding, data: &str) -> Result<Self, TokenizerError> {
+        let ranks = data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let invalid = || TokenizerError::InvalidData(format!("Invalid line: {line}"));
+                let (token, rank) = line.split_once(' ').ok_or_else(invalid)?;
+                Ok((
+                    BASE64_STANDARD.decode(token).map_err(|_| invalid())?,
+                    rank.trim().parse().map_err(|_| invalid())?,
+                ))
+            })
+            .collect::<Result<_, TokenizerError>>()?;
+
+        Ok(Self::new(encoding, ranks))
+    }
+
+    /// Create a tokenizer from a local copy of the `.tiktoken` rank file of an encoding.
+    pub fn from_file(encoding: Encoding, path: impl AsRef<Path>) -> Result<Self, TokenizerError> {
+        Self::from_tiktoken(encoding, &std::fs::read_to_string(path)?)
+    }
+
+    /// Fetch the `.tiktoken` rank file of an encoding from OpenAI and create its tokenizer.
+    pub async fn fetch(encoding: Encoding) -> Result<Self, TokenizerError> {
+        let data = reqwest::get(encoding.url())
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Self::from_tiktoken(encoding, &data)
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Split `text` into the pieces encoded independently by the BPE.
+    fn pieces<'a>(&'a self, text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let mut start = 0;
+
+        std::iter::from_fn(move || {
+            let captures = self.pattern.captures_at(text, start)?;
+            let piece = captures.get(0)?;
+            let mut end = piece.end();
+
+            // Emulate `\s+(?!\S)`: leave the last whitespace character to the next piece
+            if captures.get(1).is_some() && end < text.len() {
+                if let Some((last, _)) = piece.as_str().char_indices().last() {
+                    if last > 0 {
+                        end = piece.start() + last;
+                    }
+                }
+            }
+
+            start = end;
+            Some(&text[piece.start()..end])
+        })
+    }
+
+    /// Count the tokens of a piece, merging its bytes by rank.
+    fn count_piece_tokens(&self, piece: &[u8]) -> usize {
+        if piece.len() < 2 || self.ranks.contains_key(piece) {
+            return piece.len().min(1);
+        }
+
+        let rank = |start: usize, end: usize| {
+            self.ranks
+                .get(&piece[start..end])
+                .copied()
+                .unwrap_or(u32::MAX)
+        };
+
+        // Start offsets of the parts of the piece, and rank of the merge of each part with the
+        // next one
+        let mut parts = (0..piece.len())
+            .map(|i| {
+                let merged = if i + 2 <= piece.len() {
+                    rank(i, i + 2)
+                } else {
+                    u32::MAX
+                };
+                (i, merged)
+            })
+            .collect::<Vec<_>>();
+        parts.push((piece.len(), u32::MAX));
+
+        while let Some((i, _)) = parts[..parts.len() - 1]
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, merged))| *merged != u32::MAX)
+            .min_by_key(|(_, (_, merged))| *merged)
+        {
+            parts.remove(i + 1);
+
+            let merged_rank = |j: usize| {
+                if j + 2 < parts.len() {
+                    rank(parts[j].0, parts[j + 2].0)
+                } else {
+                    u32::MAX
+                }
+            };
+            let (merged, previous) = (merged_rank(i), (i > 0).then(|| merged_rank(i - 1)));
+            parts[i].1 = merged;
+            if let Some(previous) = previous {
+                parts[i - 1].1 = previous;
+            }
+        }
+
+        parts.len() - 1
+    }
+}
+
+impl Tokenizer for TiktokenTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.pieces(text)
+            .map(|piece| self.count_piece_tokens(piece.as_bytes()))
+            .sum()
+    }
+
+    /// Truncate `text` to its longest prefix made of whole pieces (words, numbers, punctuation or
+    /// whitespace) fitting in `max_tokens` tokens.
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        let mut tokens = 0;
+        let mut end = 0;
+
+        for piece in self.pieces(text) {
+            tokens += self.count_piece_tokens(piece.as_bytes());
+            if tokens > max_tokens {
+                break;
+            }
+            end += piece.len();
+        }
+
+        &text[..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenizer() -> TiktokenTokenizer {
+        // All the bytes and a few merges
+        let merges = ["ll", "he", "hell", " w", "or", " wor"];
+        let ranks = (0..=255u8)
+            .map(|byte| vec![byte])
+            .chain(merges.iter().map(|merge| merge.as_bytes().to_vec()))
+            .enumerate()
+            .map(|(rank, token)| (token, rank as u32))
+            .collect();
+
+        TiktokenTokenizer::new(Encoding::Cl100kBase, ranks)
+    }
+
+    #[test]
+    fn test_encoding_for_model() {
+        assert_eq!(
+            Encoding::for_model("gpt-4o-mini"),
+            Some(Encoding::O200kBase)
+        );
+        assert_eq!(Encoding::for_model("o3-mini"), Some(Encoding::O200kBase));
+        assert_eq!(
+            Encoding::for_model("gpt-4-turbo"),
+            Some(Encoding::Cl100kBase)
+        );
+        assert_eq!(
+            Encoding::for_model("ft:gpt-3.5-turbo:org::id"),
+            Some(Encoding::Cl100kBase)
+        );
+        assert_eq!(
+            Encoding::for_model("text-embedding-3-small"),
+            Some(Encoding::Cl100kBase)
+        );
+        assert_eq!(Encoding::for_model("claude-3-5-sonnet"), None);
+    }
+
+    #[test]
+    fn test_pieces() {
+        let tokenizer = tokenizer();
+
+        assert_eq!(
+            tokenizer
+                .pieces("Hello  world's 1234!\n\n")
+                .collect::<Vec<_>>(),
+            vec!["Hello", " ", " world", "'s", " ", "123", "4", "!\n\n"]
+        );
+        assert_eq!(
+            tokenizer.pieces("a \n  b   ").collect::<Vec<_>>(),
+            vec!["a", " \n", " ", " b", "   "]
+        );
+    }
+
+    #[test]
+    fn test_count_tokens() {
+        let tokenizer = tokenizer();
+
+        // "hell" + "o", " wor" + "l" + "d"
+        assert_eq!(tokenizer.count_tokens("hello world"), 5);
+        assert_eq!(tokenizer.count_tokens(""), 0);
+        assert_eq!(tokenizer.truncate("hello world", 4), "hello");
+        assert_eq!(tokenizer.truncate("hello world", 5), "hello world");
+    }
+
+    #[test]
+    fn test_from_tiktoken() {
+        let tokenizer =
+            TiktokenTokenizer::from_tiktoken(Encoding::Cl100kBase, "aGk= 0\nIQ== 1\n").unwrap();
+        assert_eq!(tokenizer.ranks.get(b"hi".as_slice()), Some(&0));
+
+        assert!(TiktokenTokenizer::from_tiktoken(Encoding::Cl100kBase, "aGk=").is_err());
+    }
+}
@@ -9,11 +9,17 @@
 //! The [ToolSet] struct is a collection of tools that can be used by an [Agent](crate::agent::Agent)
 //! and optionally RAGged.
 
-use std::{collections::HashMap, pin::Pin};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use futures::Future;
 use serde::{Deserialize, Serialize};
+use web_time::Instant;
 
+use crate::wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync};
 use crate::{
     completion::{self, ToolDefinition},
     embeddings::{embed::EmbedError, tool::ToolSchema},
@@ -102,7 +108,10 @@ pub trait Tool: Sized + Send + Sync {
 
     /// A method returning the tool definition. The user prompt can be used to
     /// tailor the definition to the specific use case.
-    fn definition(&self, _prompt: String) -> impl Future<Output = ToolDefinition> + Send + Sync;
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> impl Future<Output = ToolDefinition> + WasmCompatSend + WasmCompatSync;
 
     /// The tool execution method.
     /// Both the arguments and return value are a String since these values are meant to
@@ -110,7 +119,32 @@ pub trait Tool: Sized + Send + Sync {
     fn call(
         &self,
         args: Self::Args,
-    ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send + Sync;
+    ) -> impl Future<Output = Result<Self::Output, Self::Error>> + WasmCompatSend + WasmCompatSync;
+
+    /// Whether the tool has side effects (e.g.: sending an email or a payment), which must not
+    /// be repeated when a call is retried. The successful outputs of the side-effecting tools
+    /// are recorded by their [IdempotencyKey], and returned again instead of calling the tool
+    /// for the same key.
+    fn side_effecting(&self) -> bool {
+        false
+    }
+
+    /// Whether the definition of the tool does not depend on the prompt, so that the toolset
+    /// resolves it once and caches it instead of resolving it for each request.
+    fn static_definition(&self) -> bool {
+        false
+    }
+
+    /// Call the tool with the idempotency key of the call, e.g.: to forward the key to the
+    /// idempotent API called by the tool. Calls [Tool::call] by default.
+    fn call_idempotent(
+        &self,
+        args: Self::Args,
+        _key: &IdempotencyKey,
+    ) -> impl Future<Output = Result<Self::Output, Self::Error>> + WasmCompatSend + WasmCompatSync
+    {
+        self.call(args)
+    }
 }
 
 /// Trait that represents an LLM tool that can be stored in a vector store and RAGged
@@ -144,15 +178,28 @@ pub trait ToolEmbedding: Tool {
 pub trait ToolDyn: Send + Sync {
     fn name(&self) -> String;
 
-    fn definition(
-        &self,
-        prompt: String,
-    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>>;
+    fn definition(&self, prompt: String) -> WasmBoxedFuture<'_, ToolDefinition>;
 
-    fn call(
-        &self,
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>>;
+
+    /// See [Tool::side_effecting]
+    fn side_effecting(&self) -> bool {
+        false
+    }
+
+    /// See [Tool::static_definition]
+    fn static_definition(&self) -> bool {
+        false
+    }
+
+    /// See [Tool::call_idempotent]
+    fn call_idempotent<'a>(
+        &'a self,
         args: String,
-    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>>;
+        _key: &'a IdempotencyKey,
+    ) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+        self.call(args)
+    }
 }
 
 impl<T: Tool> ToolDyn for T {
@@ -160,17 +207,11 @@ impl<T: Tool> ToolDyn for T {
         self.name()
     }
 
-    fn definition(
-        &self,
-        prompt: String,
-    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+    fn definition(&self, prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
         Box::pin(<Self as Tool>::definition(self, prompt))
     }
 
-    fn call(
-        &self,
-        args: String,
-    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
         Box::pin(async move {
             match serde_json::from_str(&args) {
                 Ok(args) => <Self as Tool>::call(self, args)
@@ -183,6 +224,28 @@ impl<T: Tool> ToolDyn for T {
             }
         })
     }
+
+    fn side_effecting(&self) -> bool {
+        <Self as Tool>::side_effecting(self)
+    }
+
+    fn static_definition(&self) -> bool {
+        <Self as Tool>::static_definition(self)
+    }
+
+    fn call_idempotent<'a>(
+        &'a self,
+        args: String,
+        key: &'a IdempotencyKey,
+    ) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let args = serde_json::from_str(&args)?;
+            let output = <Self as Tool>::call_idempotent(self, args, key)
+                .await
+                .map_err(|e| ToolError::ToolCallError(Box::new(e)))?;
+            Ok(serde_json::to_string(&output)?)
+        })
+    }
 }
 
 #[cfg(feature = "mcp")]
@@ -247,10 +310,7 @@ where
         self.definition.name.clone()
     }
 
-    fn definition(
-        &self,
-        _prompt: String,
-    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+    fn definition(&self, _prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
         Box::pin(async move {
             ToolDefinition {
                 name: self.definition.name.clone(),
@@ -263,10 +323,7 @@ where
         })
     }
 
-    fn call(
-        &self,
-        args: String,
-    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
         let name = self.definition.name.clone();
         let args_clone = args.clone();
         let args: serde_json::Value = serde_json::from_str(&args_clone).unwrap_or_default();
@@ -343,9 +400,10 @@ impl<T: ToolEmbedding> ToolEmbeddingDyn for T {
     }
 }
 
+#[derive(Clone)]
 pub(crate) enum ToolType {
-    Simple(Box<dyn ToolDyn>),
-    Embedding(Box<dyn ToolEmbeddingDyn>),
+    Simple(Arc<dyn ToolDyn>),
+    Embedding(Arc<dyn ToolEmbeddingDyn>),
 }
 
 impl ToolType {
@@ -369,6 +427,31 @@ impl ToolType {
             ToolType::Embedding(tool) => tool.call(args).await,
         }
     }
+
+    pub fn side_effecting(&self) -> bool {
+        match self {
+            ToolType::Simple(tool) => tool.side_effecting(),
+            ToolType::Embedding(tool) => tool.side_effecting(),
+        }
+    }
+
+    pub fn static_definition(&self) -> bool {
+        match self {
+            ToolType::Simple(tool) => tool.static_definition(),
+            ToolType::Embedding(tool) => tool.static_definition(),
+        }
+    }
+
+    pub async fn call_idempotent(
+        &self,
+        args: String,
+        key: &IdempotencyKey,
+    ) -> Result<String, ToolError> {
+        match self {
+            ToolType::Simple(tool) => tool.call_idempotent(args, key).await,
+            ToolType::Embedding(tool) => tool.call_idempotent(args, key).await,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -383,12 +466,255 @@ pub enum ToolSetError {
     // TODO: Revisit this
     #[error("JsonError: {0}")]
     JsonError(#[from] serde_json::Error),
+
+    /// The [IdempotencyStore] failed
+    #[error("IdempotencyError: {0}")]
+    IdempotencyError(Box<dyn std::error::Error + Send + Sync>),
+
+    /// Another call with the same [IdempotencyKey] is in progress
+    #[error("CallInProgress: the call with the key {0} is already in progress")]
+    CallInProgress(IdempotencyKey),
+}
+
+/// Key identifying a tool call across retries, derived from the conversation, the turn of
+/// the conversation and the id of the call given by the model.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IdempotencyKey(String);
+
+impl IdempotencyKey {
+    /// The key of the call `call_id` at the turn `turn` (e.g.: the index of the message of the
+    /// call in the chat history) of the conversation `conversation_id`.
+    pub fn derive(conversation_id: &str, turn: usize, call_id: &str) -> Self {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        for part in [conversation_id, &turn.to_string(), call_id] {
+            hasher.update(part.len().to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        Self(format!("{:x}", hasher.finalize()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for IdempotencyKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// State of an [IdempotencyKey] claimed with [IdempotencyStore::claim].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// The key was free, and is now reserved by the caller, which must call the tool
+    Claimed,
+    /// The key is reserved by another call, still in progress
+    Pending,
+    /// The call of the key completed with this output
+    Done(String),
+}
+
+/// Storage of the outputs of the side-effecting tool calls, by [IdempotencyKey] (e.g.: a table
+/// shared by the replicas of a service).
+///
+/// A key is reserved with [IdempotencyStore::claim] before calling the tool, so that the
+/// concurrent (or redelivered) calls with the same key do not run the tool twice. The claim
+/// must be atomic (e.g.: an insert failing if the key exists), and the stores shared across
+/// processes should expire the pending claims of the processes which crashed during a call.
+pub trait IdempotencyStore: WasmCompatSend + WasmCompatSync {
+    /// The output recorded for `key`, if any.
+    fn get<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+    ) -> WasmBoxedFuture<'a, Result<Option<String>, ToolSetError>>;
+
+    /// Reserve `key` with a pending marker if it is absent, returning its previous state.
+    fn claim<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+    ) -> WasmBoxedFuture<'a, Result<IdempotencyClaim, ToolSetError>>;
+
+    /// Remove the pending marker of `key` (e.g.: the call failed), so that the call can be
+    /// retried.
+    fn release<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+    ) -> WasmBoxedFuture<'a, Result<(), ToolSetError>>;
+
+    /// Record the output of the call of `key`, replacing its pending marker.
+    fn put<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+        output: &'a str,
+    ) -> WasmBoxedFuture<'a, Result<(), ToolSetError>>;
+}
+
+/// [IdempotencyStore] keeping the outputs in memory, for the lifetime of the process.
+#[derive(Clone, Default)]
+pub struct MemoryIdempotencyStore {
+    /// Outputs of the calls, `None` for the pending calls
+    outputs: Arc<Mutex<HashMap<IdempotencyKey, Option<String>>>>,
+}
+
+impl MemoryIdempotencyStore {
+    fn outputs(&self) -> std::sync::MutexGuard<'_, HashMap<IdempotencyKey, Option<String>>> {
+        self.outputs
+            .lock()
+            .expect("Idempotency store lock poisoned")
+    }
+}
+
+impl IdempotencyStore for MemoryIdempotencyStore {
+    fn get<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+    ) -> WasmBoxedFuture<'a, Result<Option<String>, ToolSetError>> {
+        let output = self.outputs().get(key).cloned().flatten();
+        Box::pin(async move { Ok(output) })
+    }
+
+    fn claim<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+    ) -> WasmBoxedFuture<'a, Result<IdempotencyClaim, ToolSetError>> {
+        let claim = match self.outputs().entry(key.clone()) {
+            std::collections::hash_map::Entry::Occupied(entry) => match entry.get() {
+                Some(output) => IdempotencyClaim::Done(output.clone()),
+                None => IdempotencyClaim::Pending,
+            },
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(None);
+                IdempotencyClaim::Claimed
+            }
+        };
+        Box::pin(async move { Ok(claim) })
+    }
+
+    fn release<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+    ) -> WasmBoxedFuture<'a, Result<(), ToolSetError>> {
+        let mut outputs = self.outputs();
+        if matches!(outputs.get(key), Some(None)) {
+            outputs.remove(key);
+        }
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn put<'a>(
+        &'a self,
+        key: &'a IdempotencyKey,
+        output: &'a str,
+    ) -> WasmBoxedFuture<'a, Result<(), ToolSetError>> {
+        self.outputs().insert(key.clone(), Some(output.to_string()));
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+/// Number of the latest calls of a tool from which the latency percentiles are computed
+const LATENCY_WINDOW: usize = 1024;
+
+/// Usage statistics of a tool of a [ToolSet], see [ToolSet::stats].
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ToolStats {
+    /// Number of calls of the tool
+    pub calls: u64,
+    /// Number of calls of the tool which returned an error
+    pub errors: u64,
+    /// Mean latency of the calls
+    pub mean_latency: Duration,
+    /// Latency percentiles of the latest calls (at most 1024)
+    pub p50_latency: Duration,
+    pub p90_latency: Duration,
+    pub p99_latency: Duration,
+    /// Maximum latency of the calls
+    pub max_latency: Duration,
+}
+
+impl ToolStats {
+    /// Fraction of the calls which returned an error (0 if the tool was never called)
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct ToolUsage {
+    calls: u64,
+    errors: u64,
+    total_latency: Duration,
+    max_latency: Duration,
+    latencies: VecDeque<Duration>,
+}
+
+impl ToolUsage {
+    fn record(&mut self, latency: Duration, is_error: bool) {
+        self.calls += 1;
+        self.errors += is_error as u64;
+        self.total_latency += latency;
+        self.max_latency = self.max_latency.max(latency);
+        if self.latencies.len() == LATENCY_WINDOW {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    fn stats(&self) -> ToolStats {
+        let mut latencies = self.latencies.iter().copied().collect::<Vec<_>>();
+        latencies.sort_unstable();
+        let percentile = |p: usize| {
+            latencies
+                .get((latencies.len() * p).div_ceil(100).saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        ToolStats {
+            calls: self.calls,
+            errors: self.errors,
+            mean_latency: if self.calls == 0 {
+                Duration::ZERO
+            } else {
+                self.total_latency.div_f64(self.calls as f64)
+            },
+            p50_latency: percentile(50),
+            p90_latency: percentile(90),
+            p99_latency: percentile(99),
+            max_latency: self.max_latency,
+        }
+    }
 }
 
 /// A struct that holds a set of tools
 #[derive(Default)]
 pub struct ToolSet {
     pub(crate) tools: HashMap<String, ToolType>,
+    /// Usage statistics of the tools, by tool name
+    usage: Mutex<HashMap<String, ToolUsage>>,
+    /// Cached definitions of the tools with a static definition, by tool name
+    definitions: Mutex<HashMap<String, ToolDefinition>>,
+}
+
+/// The clones share the tools, with a copy of the usage statistics and of the cached definitions.
+impl Clone for ToolSet {
+    fn clone(&self) -> Self {
+        Self {
+            tools: self.tools.clone(),
+            usage: Mutex::new(self.usage.lock().expect("Tool usage lock poisoned").clone()),
+            definitions: Mutex::new(
+                self.definitions
+                    .lock()
+                    .expect("Tool definitions lock poisoned")
+                    .clone(),
+            ),
+        }
+    }
 }
 
 impl ToolSet {
@@ -413,17 +739,97 @@ impl ToolSet {
 
     /// Add a tool to the toolset
     pub fn add_tool(&mut self, tool: impl ToolDyn + 'static) {
+        self.definitions
+            .get_mut()
+            .expect("Tool definitions lock poisoned")
+            .remove(&tool.name());
         self.tools
-            .insert(tool.name(), ToolType::Simple(Box::new(tool)));
+            .insert(tool.name(), ToolType::Simple(Arc::new(tool)));
     }
 
     /// Merge another toolset into this one
     pub fn add_tools(&mut self, toolset: ToolSet) {
+        let definitions = self
+            .definitions
+            .get_mut()
+            .expect("Tool definitions lock poisoned");
+        for toolname in toolset.tools.keys() {
+            definitions.remove(toolname);
+        }
+        definitions.extend(
+            toolset
+                .definitions
+                .into_inner()
+                .expect("Tool definitions lock poisoned"),
+        );
         self.tools.extend(toolset.tools);
+        let usage = toolset
+            .usage
+            .into_inner()
+            .expect("Tool usage lock poisoned");
+        self.usage
+            .get_mut()
+            .expect("Tool usage lock poisoned")
+            .extend(usage);
+    }
+
+    /// Usage statistics of the tool `toolname` (`None` if it was never called)
+    pub fn stats(&self, toolname: &str) -> Option<ToolStats> {
+        self.usage
+            .lock()
+            .expect("Tool usage lock poisoned")
+            .get(toolname)
+            .map(ToolUsage::stats)
+    }
+
+    /// Usage statistics of all the called tools, by tool name
+    pub fn all_stats(&self) -> HashMap<String, ToolStats> {
+        self.usage
+            .lock()
+            .expect("Tool usage lock poisoned")
+            .iter()
+            .map(|(toolname, usage)| (toolname.clone(), usage.stats()))
+            .collect()
     }
 
-    pub(crate) fn get(&self, toolname: &str) -> Option<&ToolType> {
-        self.tools.get(toolname)
+    /// Clear the usage statistics of the tools
+    pub fn reset_stats(&self) {
+        self.usage.lock().expect("Tool usage lock poisoned").clear();
+    }
+
+    fn record_call(&self, toolname: &str, latency: Duration, is_error: bool) {
+        self.usage
+            .lock()
+            .expect("Tool usage lock poisoned")
+            .entry(toolname.to_string())
+            .or_default()
+            .record(latency, is_error);
+    }
+
+    /// Definition of the tool `toolname` for `prompt` (`None` if the toolset has no such tool).
+    /// The definitions of the tools with a [static definition](Tool::static_definition) are
+    /// resolved once, and then returned from the cache of the toolset.
+    pub async fn definition(&self, toolname: &str, prompt: &str) -> Option<ToolDefinition> {
+        let tool = self.tools.get(toolname)?;
+        if !tool.static_definition() {
+            return Some(tool.definition(prompt.into()).await);
+        }
+
+        let cached = self
+            .definitions
+            .lock()
+            .expect("Tool definitions lock poisoned")
+            .get(toolname)
+            .cloned();
+        if let Some(definition) = cached {
+            return Some(definition);
+        }
+        let definition = tool.definition(prompt.into()).await;
+        self.definitions
+            .lock()
+            .expect("Tool definitions lock poisoned")
+            .insert(toolname.to_string(), definition.clone());
+        Some(definition)
     }
 
     /// Call a tool with the given name and arguments
@@ -433,12 +839,72 @@ impl ToolSet {
                 "Calling tool {toolname} with args:\n{}",
                 serde_json::to_string_pretty(&args).unwrap_or_else(|_| args.clone())
             );
-            Ok(tool.call(args).await?)
+            let started = Instant::now();
+            let result = tool.call(args).await;
+            self.record_call(toolname, started.elapsed(), result.is_err());
+            Ok(result?)
         } else {
             Err(ToolSetError::ToolNotFoundError(toolname.to_string()))
         }
     }
 
+    /// Call a tool with the idempotency key of the call: the outputs of the side-effecting tools
+    /// (see [Tool::side_effecting]) are recorded in `store`, and returned again instead of
+    /// calling the tool when the call is retried with the same key.
+    ///
+    /// The key is claimed in `store` before calling the tool: a call whose key is claimed by
+    /// another call in progress fails with [ToolSetError::CallInProgress]. A failed call releases
+    /// its claim, so that it can be retried. Once the tool succeeded, its output is returned even
+    /// if it cannot be recorded (the failure is logged), so that the caller does not retry it.
+    pub async fn call_idempotent(
+        &self,
+        toolname: &str,
+        args: String,
+        key: &IdempotencyKey,
+        store: &dyn IdempotencyStore,
+    ) -> Result<String, ToolSetError> {
+        let Some(tool) = self.tools.get(toolname) else {
+            return Err(ToolSetError::ToolNotFoundError(toolname.to_string()));
+        };
+        if !tool.side_effecting() {
+            return self.call(toolname, args).await;
+        }
+
+        match store.claim(key).await? {
+            IdempotencyClaim::Claimed => {}
+            IdempotencyClaim::Pending => return Err(ToolSetError::CallInProgress(key.clone())),
+            IdempotencyClaim::Done(output) => {
+                tracing::info!(target: "rig",
+                    "Skipping the side-effecting tool {toolname}, already called with the key {key}"
+                );
+                return Ok(output);
+            }
+        }
+        tracing::info!(target: "rig", "Calling tool {toolname} with the key {key}");
+        let started = Instant::now();
+        let result = tool.call_idempotent(args, key).await;
+        self.record_call(toolname, started.elapsed(), result.is_err());
+
+        let output = match result {
+            Ok(output) => output,
+            Err(err) => {
+                if let Err(release_err) = store.release(key).await {
+                    tracing::warn!(target: "rig",
+                        "Failed to release the key {key} of the failed tool {toolname}: {release_err}"
+                    );
+                }
+                return Err(err.into());
+            }
+        };
+        if let Err(err) = store.put(key, &output).await {
+            // The side effect happened: returning an error would make the caller retry it
+            tracing::warn!(target: "rig",
+                "Failed to record the output of the tool {toolname} with the key {key}: {err}"
+            );
+        }
+        Ok(output)
+    }
+
     /// Get the documents of all the tools in the toolset
     pub async fn documents(&self) -> Result<Vec<completion::Document>, ToolSetError> {
         let mut docs = Vec::new();
@@ -503,12 +969,12 @@ pub struct ToolSetBuilder {
 
 impl ToolSetBuilder {
     pub fn static_tool(mut self, tool: impl ToolDyn + 'static) -> Self {
-        self.tools.push(ToolType::Simple(Box::new(tool)));
+        self.tools.push(ToolType::Simple(Arc::new(tool)));
         self
     }
 
     pub fn dynamic_tool(mut self, tool: impl ToolEmbeddingDyn + 'static) -> Self {
-        self.tools.push(ToolType::Embedding(Box::new(tool)));
+        self.tools.push(ToolType::Embedding(Arc::new(tool)));
         self
     }
 
@@ -519,6 +985,282 @@ impl ToolSetBuilder {
                 .into_iter()
                 .map(|tool| (tool.name(), tool))
                 .collect(),
+            usage: Mutex::default(),
+            definitions: Mutex::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Payment error")]
+    struct PaymentError;
+
+    struct Pay {
+        calls: Arc<AtomicUsize>,
+        side_effecting: bool,
+    }
+
+    impl Tool for Pay {
+        const NAME: &'static str = "pay";
+        type Error = PaymentError;
+        type Args = serde_json::Value;
+        type Output = usize;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Make a payment".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            if args["fail"].as_bool().unwrap_or_default() {
+                return Err(PaymentError);
+            }
+            Ok(self.calls.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+
+        fn side_effecting(&self) -> bool {
+            self.side_effecting
+        }
+    }
+
+    /// Tool whose definition describes the prompt, counting its resolutions
+    struct Search {
+        definitions: Arc<AtomicUsize>,
+        static_definition: bool,
+    }
+
+    impl Tool for Search {
+        const NAME: &'static str = "search";
+        type Error = PaymentError;
+        type Args = serde_json::Value;
+        type Output = ();
+
+        async fn definition(&self, prompt: String) -> ToolDefinition {
+            self.definitions.fetch_add(1, Ordering::SeqCst);
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: format!("Search for {prompt}"),
+                parameters: serde_json::json!({"type": "object"}),
+            }
+        }
+
+        async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(())
+        }
+
+        fn static_definition(&self) -> bool {
+            self.static_definition
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_definitions() {
+        for static_definition in [false, true] {
+            let definitions = Arc::new(AtomicUsize::new(0));
+            let mut toolset = ToolSet::from_tools(vec![Search {
+                definitions: definitions.clone(),
+                static_definition,
+            }]);
+            let description = |definition: Option<ToolDefinition>| definition.unwrap().description;
+
+            assert_eq!(
+                description(toolset.definition("search", "cats").await),
+                "Search for cats"
+            );
+            let expected = if static_definition {
+                "Search for cats"
+            } else {
+                "Search for dogs"
+            };
+            assert_eq!(
+                description(toolset.definition("search", "dogs").await),
+                expected
+            );
+            assert_eq!(
+                definitions.load(Ordering::SeqCst),
+                if static_definition { 1 } else { 2 }
+            );
+            assert!(toolset.definition("missing", "cats").await.is_none());
+
+            // Replacing the tool invalidates its cached definition
+            toolset.add_tool(Search {
+                definitions: definitions.clone(),
+                static_definition,
+            });
+            assert_eq!(
+                description(toolset.definition("search", "dogs").await),
+                "Search for dogs"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_idempotent() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let toolset = ToolSet::from_tools(vec![Pay {
+            calls: calls.clone(),
+            side_effecting: true,
+        }]);
+        let store = MemoryIdempotencyStore::default();
+        let key = IdempotencyKey::derive("conversation", 1, "call_1");
+        assert_ne!(key, IdempotencyKey::derive("conversation", 1, "call_2"));
+        assert_ne!(key, IdempotencyKey::derive("conversation1", 1, "call_"));
+
+        for _ in 0..2 {
+            let output = toolset
+                .call_idempotent("pay", "{}".to_string(), &key, &store)
+                .await
+                .unwrap();
+            assert_eq!(output, "1");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let other_key = IdempotencyKey::derive("conversation", 3, "call_1");
+        let output = toolset
+            .call_idempotent("pay", "{}".to_string(), &other_key, &store)
+            .await
+            .unwrap();
+        assert_eq!(output, "2");
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let toolset = ToolSet::from_tools(vec![Pay {
+            calls: calls.clone(),
+            side_effecting: false,
+        }]);
+        for _ in 0..2 {
+            toolset
+                .call_idempotent("pay", "{}".to_string(), &key, &store)
+                .await
+                .unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    /// Store whose outputs cannot be recorded
+    struct FailingStore(MemoryIdempotencyStore);
+
+    impl IdempotencyStore for FailingStore {
+        fn get<'a>(
+            &'a self,
+            key: &'a IdempotencyKey,
+        ) -> WasmBoxedFuture<'a, Result<Option<String>, ToolSetError>> {
+            self.0.get(key)
+        }
+
+        fn claim<'a>(
+            &'a self,
+            key: &'a IdempotencyKey,
+        ) -> WasmBoxedFuture<'a, Result<IdempotencyClaim, ToolSetError>> {
+            self.0.claim(key)
+        }
+
+        fn release<'a>(
+            &'a self,
+            key: &'a IdempotencyKey,
+        ) -> WasmBoxedFuture<'a, Result<(), ToolSetError>> {
+            self.0.release(key)
+        }
+
+        fn put<'a>(
+            &'a self,
+            _key: &'a IdempotencyKey,
+            _output: &'a str,
+        ) -> WasmBoxedFuture<'a, Result<(), ToolSetError>> {
+            Box::pin(async { Err(ToolSetError::IdempotencyError("Unavailable".into())) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_claims() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let toolset = ToolSet::from_tools(vec![Pay {
+            calls: calls.clone(),
+            side_effecting: true,
+        }]);
+        let store = MemoryIdempotencyStore::default();
+        let key = IdempotencyKey::derive("conversation", 1, "call_1");
+
+        // A call in progress (claimed by another caller) is not run again
+        assert_eq!(store.claim(&key).await.unwrap(), IdempotencyClaim::Claimed);
+        assert_eq!(store.claim(&key).await.unwrap(), IdempotencyClaim::Pending);
+        assert!(matches!(
+            toolset
+                .call_idempotent("pay", "{}".to_string(), &key, &store)
+                .await,
+            Err(ToolSetError::CallInProgress(_))
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        store.release(&key).await.unwrap();
+
+        // A failed call releases its claim, so that it can be retried
+        assert!(toolset
+            .call_idempotent("pay", r#"{"fail": true}"#.to_string(), &key, &store)
+            .await
+            .is_err());
+        assert_eq!(store.claim(&key).await.unwrap(), IdempotencyClaim::Claimed);
+        store.release(&key).await.unwrap();
+
+        // The output is returned even if it cannot be recorded
+        let store = FailingStore(MemoryIdempotencyStore::default());
+        let output = toolset
+            .call_idempotent("pay", "{}".to_string(), &key, &store)
+            .await
+            .unwrap();
+        assert!(!output.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tool_stats() {
+        let toolset = ToolSet::from_tools(vec![Pay {
+            calls: Arc::new(AtomicUsize::new(0)),
+            side_effecting: false,
+        }]);
+        assert_eq!(toolset.stats("pay"), None);
+
+        for _ in 0..3 {
+            toolset.call("pay", "{}".to_string()).await.unwrap();
+        }
+        toolset
+            .call("pay", r#"{"fail": true}"#.to_string())
+            .await
+            .unwrap_err();
+        toolset.call("missing", "{}".to_string()).await.unwrap_err();
+
+        let stats = toolset.stats("pay").unwrap();
+        assert_eq!(stats.calls, 4);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.error_rate(), 0.25);
+        assert!(stats.p50_latency <= stats.p99_latency);
+        assert!(stats.p99_latency <= stats.max_latency);
+        assert_eq!(toolset.all_stats().len(), 1);
+
+        toolset.reset_stats();
+        assert!(toolset.all_stats().is_empty());
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut usage = ToolUsage::default();
+        for millis in 1..=100 {
+            usage.record(Duration::from_millis(millis), false);
         }
+        let stats = usage.stats();
+        assert_eq!(stats.p50_latency, Duration::from_millis(50));
+        assert_eq!(stats.p90_latency, Duration::from_millis(90));
+        assert_eq!(stats.p99_latency, Duration::from_millis(99));
+        assert_eq!(stats.max_latency, Duration::from_millis(100));
+        assert_eq!(stats.mean_latency, Duration::from_micros(50_500));
     }
 }
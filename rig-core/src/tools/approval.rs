@@ -0,0 +1,93 @@
+//! Human-in-the-loop approval of the actions of the tools.
+//!
+//! The tools performing sensitive actions (e.g.: the `shell` tool) ask an
+//! [ApprovalHandler] before acting, which can prompt the user, apply a policy or log the
+//! request. A denied action is not an error: the model receives the denial (and its reason) as
+//! the output of the tool, and can carry on.
+//!
+//! # Example
+//! ```rust
+//! use rig::tools::approval::{Approval, ApprovalRequest};
+//!
+//! // Ask on the terminal
+//! let approval = |request: ApprovalRequest| async move {
+//!     println!("{} wants to run: {}. Approve? [y/N]", request.tool, request.action);
+//!     let mut answer = String::new();
+//!     std::io::stdin().read_line(&mut answer).unwrap();
+//!     if answer.trim() == "y" {
+//!         Approval::Approve
+//!     } else {
+//!         Approval::deny("The user refused")
+//!     }
+//! };
+//! ```
+
+use std::future::Future;
+
+use crate::wasm_compat::{WasmCompatSend, WasmCompatSync};
+
+/// An action of a tool awaiting approval.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApprovalRequest {
+    /// Name of the tool
+    pub tool: String,
+    /// Human-readable description of the action (e.g.: the command to run)
+    pub action: String,
+    /// Arguments of the tool call
+    pub args: serde_json::Value,
+}
+
+/// Decision of an [ApprovalHandler].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Approval {
+    Approve,
+    /// Deny the action, with the reason given to the model
+    Deny {
+        reason: String,
+    },
+}
+
+impl Approval {
+    pub fn deny(reason: impl Into<String>) -> Self {
+        Self::Deny {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Decides whether the actions of the tools are performed.
+///
+/// Implemented by the async functions `Fn(ApprovalRequest) -> impl Future<Output = Approval>`.
+pub trait ApprovalHandler: WasmCompatSend + WasmCompatSync {
+    fn approve(&self, request: ApprovalRequest) -> impl Future<Output = Approval> + WasmCompatSend;
+}
+
+impl<F, Fut> ApprovalHandler for F
+where
+    F: Fn(ApprovalRequest) -> Fut + WasmCompatSend + WasmCompatSync,
+    Fut: Future<Output = Approval> + WasmCompatSend,
+{
+    fn approve(&self, request: ApprovalRequest) -> impl Future<Output = Approval> + WasmCompatSend {
+        self(request)
+    }
+}
+
+/// Approve all the actions (e.g.: in a sandboxed environment).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AutoApprove;
+
+impl ApprovalHandler for AutoApprove {
+    async fn approve(&self, _request: ApprovalRequest) -> Approval {
+        Approval::Approve
+    }
+}
+
+/// Deny all the actions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DenyAll;
+
+impl ApprovalHandler for DenyAll {
+    async fn approve(&self, _request: ApprovalRequest) -> Approval {
+        Approval::deny("The action is not allowed")
+    }
+}
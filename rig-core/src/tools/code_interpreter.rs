@@ -0,0 +1,547 @@
+//! Code interpreter tool, running the Python or JavaScript code of the model in a
+//! resource-limited subprocess or container (feature `code-interpreter`).
+//!
+//! **Running code written by a model is dangerous**: the subprocess isolation only limits the
+//! memory, the CPU time and the output of the code, which runs with the permissions of the
+//! application. Prefer the [Container](Isolation::Container) isolation (e.g.: with Docker),
+//! which also denies network access, for untrusted inputs.
+//!
+//! Each execution runs in a fresh temporary directory, with an empty environment (except
+//! `PATH`). The files written by the code in this directory are the artifacts of the
+//! execution: they are listed to the model, and copied to the artifacts directory if one is
+//! configured.
+//!
+//! # Example
+//! ```rust
+//! use rig::tools::code_interpreter::{CodeInterpreter, Isolation, Language};
+//!
+//! let interpreter = CodeInterpreter::new()
+//!     .languages([Language::Python])
+//!     .isolation(Isolation::docker())
+//!     .artifacts_dir("./artifacts")
+//!     .timeout(Duration::from_secs(20));
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("Answer the questions of the user, running Python code to compute the results.")
+//!     .dyn_tool(interpreter)
+//!     .build();
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::shell::{capture, kill_process_group};
+use crate::{
+    completion::ToolDefinition,
+    runtime,
+    tool::{ToolDyn, ToolError},
+    wasm_compat::WasmBoxedFuture,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodeInterpreterError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("LanguageNotAllowed: {0}")]
+    LanguageNotAllowed(String),
+
+    #[error(transparent)]
+    Timeout(#[from] runtime::Elapsed),
+}
+
+/// Language of the code run by the [CodeInterpreter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Python,
+    #[serde(alias = "js", alias = "node")]
+    JavaScript,
+}
+
+impl Language {
+    fn file_name(&self) -> &'static str {
+        match self {
+            Self::Python => "main.py",
+            Self::JavaScript => "main.js",
+        }
+    }
+}
+
+/// Isolation of the code run by the [CodeInterpreter].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Isolation {
+    /// Run the interpreter in a subprocess, limiting its memory and CPU time with `ulimit`
+    /// (on Unix)
+    Subprocess,
+    /// Run the interpreter in a container without network access, with the working directory
+    /// mounted on `/workspace`
+    Container {
+        /// Container runtime (e.g.: `docker` or `podman`)
+        runtime: String,
+        python_image: String,
+        javascript_image: String,
+    },
+}
+
+impl Isolation {
+    /// Run the code in Docker containers, with the official Python and Node.js images.
+    pub fn docker() -> Self {
+        Self::Container {
+            runtime: "docker".to_string(),
+            python_image: "python:3-slim".to_string(),
+            javascript_image: "node:lts-slim".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CodeArgs {
+    language: Language,
+    code: String,
+}
+
+/// A file written by the code of an [Execution].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Path of the file, relative to the working directory of the execution
+    pub name: String,
+    pub size: u64,
+    /// Path of the copy of the file in the artifacts directory, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+/// Result of the code run by the [CodeInterpreter].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Execution {
+    /// Exit code of the interpreter (`None` if it was killed by a signal)
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether stdout or stderr exceeded the maximum size and were truncated
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+}
+
+/// Tool running Python or JavaScript code, named `code_interpreter`.
+///
+/// The tool is a [ToolDyn], added to the agents with
+/// [AgentBuilder::dyn_tool](crate::agent::AgentBuilder::dyn_tool). The subprocesses are
+/// spawned with tokio: the tool must run inside a tokio runtime.
+pub struct CodeInterpreter {
+    languages: Vec<Language>,
+    python: String,
+    javascript: String,
+    isolation: Isolation,
+    timeout: Duration,
+    max_memory_bytes: u64,
+    max_output_bytes: usize,
+    artifacts_dir: Option<PathBuf>,
+}
+
+impl Default for CodeInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CodeInterpreter {
+    pub const NAME: &'static str = "code_interpreter";
+
+    /// Create a code interpreter running Python (`python3`) and JavaScript (`node`) in
+    /// subprocesses, with a timeout of 30 seconds, 512 MiB of memory and outputs of at most
+    /// 64 KiB.
+    pub fn new() -> Self {
+        Self {
+            languages: vec![Language::Python, Language::JavaScript],
+            python: "python3".to_string(),
+            javascript: "node".to_string(),
+            isolation: Isolation::Subprocess,
+            timeout: Duration::from_secs(30),
+            max_memory_bytes: 512 << 20,
+            max_output_bytes: 64 * 1024,
+            artifacts_dir: None,
+        }
+    }
+
+    /// Set the languages the model may run.
+    pub fn languages(mut self, languages: impl IntoIterator<Item = Language>) -> Self {
+        self.languages = languages.into_iter().collect();
+        self
+    }
+
+    /// Set the interpreter program of `language` (e.g.: the `python` of a virtual environment).
+    pub fn interpreter(mut self, language: Language, program: &str) -> Self {
+        match language {
+            Language::Python => self.python = program.to_string(),
+            Language::JavaScript => self.javascript = program.to_string(),
+        }
+        self
+    }
+
+    pub fn isolation(mut self, isolation: Isolation) -> Self {
+        self.isolation = isolation;
+        self
+    }
+
+    /// Set the duration after which the executions are killed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the maximum memory of the interpreter.
+    pub fn max_memory_bytes(mut self, max_memory_bytes: u64) -> Self {
+        self.max_memory_bytes = max_memory_bytes;
+        self
+    }
+
+    /// Set the maximum size of the captured stdout and stderr (each).
+    pub fn max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Copy the artifacts of the executions to `artifacts_dir`, in a subdirectory per
+    /// execution.
+    pub fn artifacts_dir(mut self, artifacts_dir: impl Into<PathBuf>) -> Self {
+        self.artifacts_dir = Some(artifacts_dir.into());
+        self
+    }
+
+    fn command(&self, language: Language, workdir: &Path, id: &str) -> tokio::process::Command {
+        let (program, file_name) = match language {
+            Language::Python => (&self.python, language.file_name()),
+            Language::JavaScript => (&self.javascript, language.file_name()),
+        };
+
+        let mut command = match &self.isolation {
+            Isolation::Subprocess if cfg!(unix) => {
+                // V8 reserves more virtual memory than it uses: node limits its heap instead
+                let (memory_limit, args) = match language {
+                    Language::Python => (
+                        format!("ulimit -v {} && ", self.max_memory_bytes / 1024),
+                        vec![file_name.to_string()],
+                    ),
+                    Language::JavaScript => (
+                        String::new(),
+                        vec![
+                            format!("--max-old-space-size={}", self.max_memory_bytes >> 20),
+                            file_name.to_string(),
+                        ],
+                    ),
+                };
+                let mut command = tokio::process::Command::new("sh");
+                command
+                    .arg("-c")
+                    .arg(format!(
+                        "{memory_limit}ulimit -t {} && exec \"$0\" \"$@\"",
+                        self.timeout.as_secs().max(1)
+                    ))
+                    .arg(program)
+                    .args(args);
+                command
+            }
+            Isolation::Subprocess => {
+                let mut command = tokio::process::Command::new(program);
+                command.arg(file_name);
+                command
+            }
+            Isolation::Container {
+                runtime,
+                python_image,
+                javascript_image,
+            } => {
+                let image = match language {
+                    Language::Python => python_image,
+                    Language::JavaScript => javascript_image,
+                };
+                let mut command = tokio::process::Command::new(runtime);
+                command
+                    .args(["run", "--rm", "--init", "--name", id])
+                    .args(["--network", "none", "--pids-limit", "64"])
+                    .arg(format!("--memory={}", self.max_memory_bytes))
+                    .arg(format!("--volume={}:/workspace", workdir.display()))
+                    .args(["--workdir", "/workspace", image, program, file_name]);
+                command
+            }
+        };
+        command
+            .current_dir(workdir)
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .env("HOME", workdir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // In its own process group, killed with the processes started by the code
+        #[cfg(unix)]
+        command.process_group(0);
+        command
+    }
+
+    /// Run `code`, in a fresh working directory.
+    pub async fn execute(
+        &self,
+        language: Language,
+        code: &str,
+    ) -> Result<Execution, CodeInterpreterError> {
+        if !self.languages.contains(&language) {
+            return Err(CodeInterpreterError::LanguageNotAllowed(format!(
+                "{language:?}"
+            )));
+        }
+
+        // A new directory with a random name, private to the user, so that other users cannot
+        // create it in advance
+        let workdir =
+            runtime::spawn_blocking(|| tempfile::Builder::new().prefix("rig-code-").tempdir())
+                .await?;
+        let path = workdir.path().to_path_buf();
+        let id = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+
+        let execution = self.run(language, code, &path, &id).await;
+        if let Err(err) = runtime::spawn_blocking(move || workdir.close()).await {
+            tracing::warn!(target: "rig",
+                "Failed to remove the working directory {}: {}", path.display(), err
+            );
+        }
+        execution
+    }
+
+    async fn run(
+        &self,
+        language: Language,
+        code: &str,
+        workdir: &Path,
+        id: &str,
+    ) -> Result<Execution, CodeInterpreterError> {
+        let code_file = workdir.join(language.file_name());
+        let code = code.to_string();
+        runtime::spawn_blocking(move || std::fs::write(code_file, code)).await?;
+
+        let mut child = self.command(language, workdir, id).spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let result = runtime::timeout(self.timeout, async {
+            futures::try_join!(
+                capture(stdout, self.max_output_bytes),
+                capture(stderr, self.max_output_bytes),
+                child.wait(),
+            )
+        })
+        .await;
+        if result.is_err() {
+            kill_process_group(&child);
+            if let Isolation::Container { runtime, .. } = &self.isolation {
+                // Killing the client of the container runtime does not stop the container
+                let _ = tokio::process::Command::new(runtime)
+                    .args(["kill", id])
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .await;
+            }
+        }
+        let ((stdout, stdout_truncated), (stderr, stderr_truncated), status) = result??;
+
+        let workdir = workdir.to_path_buf();
+        let target = self.artifacts_dir.as_ref().map(|dir| dir.join(id));
+        let artifacts = runtime::spawn_blocking(move || {
+            let mut artifacts = vec![];
+            collect_artifacts(&workdir, &workdir, language.file_name(), &mut artifacts)?;
+            if let Some(target) = target {
+                copy_artifacts(&workdir, &target, &mut artifacts)?;
+            }
+            Ok::<_, std::io::Error>(artifacts)
+        })
+        .await?;
+
+        Ok(Execution {
+            exit_code: status.code(),
+            stdout,
+            stderr,
+            truncated: stdout_truncated || stderr_truncated,
+            artifacts,
+        })
+    }
+}
+
+/// List the regular files of `dir` (recursively), except the code file. The symbolic links
+/// are skipped, so that the code cannot expose files outside of its working directory.
+fn collect_artifacts(
+    workdir: &Path,
+    dir: &Path,
+    code_file: &str,
+    artifacts: &mut Vec<Artifact>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            continue;
+        }
+        let path = entry.path();
+        let name = path
+            .strip_prefix(workdir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .into_owned();
+        if file_type.is_dir() {
+            collect_artifacts(workdir, &path, code_file, artifacts)?;
+        } else if file_type.is_file() && name != code_file {
+            artifacts.push(Artifact {
+                name,
+                size: entry.metadata()?.len(),
+                path: None,
+            });
+        }
+    }
+    artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(())
+}
+
+/// Copy the `artifacts` of `workdir` to `target`, setting their paths.
+fn copy_artifacts(
+    workdir: &Path,
+    target: &Path,
+    artifacts: &mut [Artifact],
+) -> std::io::Result<()> {
+    for artifact in artifacts {
+        // The code may have replaced the file since it was listed
+        let source = workdir.join(&artifact.name);
+        if !std::fs::symlink_metadata(&source)?.is_file() {
+            continue;
+        }
+        let path = target.join(&artifact.name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&source, &path)?;
+        artifact.path = Some(path);
+    }
+    Ok(())
+}
+
+impl ToolDyn for CodeInterpreter {
+    fn name(&self) -> String {
+        Self::NAME.to_string()
+    }
+
+    fn definition(&self, _prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        Box::pin(async move {
+            let languages = self
+                .languages
+                .iter()
+                .map(|language| match language {
+                    Language::Python => "python",
+                    Language::JavaScript => "javascript",
+                })
+                .collect::<Vec<_>>();
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: format!(
+                    "Run code, returning its exit code, stdout, stderr and the files it wrote in \
+                    the current directory. The code runs without persistent state{}, and is \
+                    killed after {} seconds.",
+                    if matches!(self.isolation, Isolation::Container { .. }) {
+                        " nor network access"
+                    } else {
+                        ""
+                    },
+                    self.timeout.as_secs()
+                ),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "language": {
+                            "type": "string",
+                            "enum": languages,
+                        },
+                        "code": {
+                            "type": "string",
+                            "description": "The code to run, printing its results"
+                        }
+                    },
+                    "required": ["language", "code"]
+                }),
+            }
+        })
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        Box::pin(async move {
+            let args = serde_json::from_str::<CodeArgs>(&args)?;
+            let execution = self
+                .execute(args.language, &args.code)
+                .await
+                .map_err(|err| ToolError::ToolCallError(Box::new(err)))?;
+            Ok(serde_json::to_string(&execution)?)
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_code_interpreter() {
+        let artifacts_dir =
+            std::env::temp_dir().join(format!("rig-artifacts-{}", std::process::id()));
+        // Run the code with `sh`, so that the test does not depend on the installed interpreters
+        let interpreter = CodeInterpreter::new()
+            .languages([Language::Python])
+            .interpreter(Language::Python, "sh")
+            .artifacts_dir(&artifacts_dir)
+            .max_output_bytes(5);
+
+        let output = interpreter
+            .call(
+                json!({
+                    "language": "python",
+                    "code": "mkdir plots && echo data > plots/a.csv; ln -s / root; ln -s /etc/hostname plots/b.csv; echo hello world; echo $CARGO_MANIFEST_DIR >&2"
+                })
+                .to_string(),
+            )
+            .await
+            .unwrap();
+        let execution = serde_json::from_str::<Execution>(&output).unwrap();
+        assert_eq!(execution.exit_code, Some(0));
+        assert_eq!(execution.stdout, "hello");
+        assert!(execution.truncated);
+        // The environment of the application is not inherited
+        assert_eq!(execution.stderr, "\n");
+        assert_eq!(execution.artifacts.len(), 1);
+        assert_eq!(execution.artifacts[0].name, "plots/a.csv");
+        let copy = execution.artifacts[0].path.as_ref().unwrap();
+        assert_eq!(std::fs::read_to_string(copy).unwrap(), "data\n");
+        std::fs::remove_dir_all(&artifacts_dir).unwrap();
+
+        assert!(matches!(
+            interpreter.execute(Language::JavaScript, "1").await,
+            Err(CodeInterpreterError::LanguageNotAllowed(_))
+        ));
+
+        let interpreter = interpreter.timeout(Duration::from_millis(100));
+        assert!(matches!(
+            interpreter.execute(Language::Python, "sleep 5").await,
+            Err(CodeInterpreterError::Timeout(_))
+        ));
+    }
+}
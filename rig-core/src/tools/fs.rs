@@ -0,0 +1,565 @@
+//! Filesystem tools, confined to the root directory of a [FsSandbox].
+//!
+//! - [ReadFile] (`read_file`): read a text file.
+//! - [WriteFile] (`write_file`): write (or append to) a text file, creating its parent
+//!   directories.
+//! - [ListDirectory] (`list_directory`): list the entries of a directory.
+//! - [GlobFiles] (`glob_files`): find the files matching a glob pattern.
+//!
+//! The paths given by the model are relative to the root of the sandbox (absolute paths are
+//! accepted within the root), and the paths escaping the root (with `..` or symbolic links) are
+//! rejected. The sandbox also limits the size of the files read and written, and optionally
+//! their extensions.
+//!
+//! # Example
+//! ```rust
+//! use rig::tools::fs::{FsSandbox, GlobFiles, ListDirectory, ReadFile, WriteFile};
+//!
+//! let sandbox = FsSandbox::new("./workspace")?
+//!     .max_file_bytes(256 * 1024)
+//!     .allow_extensions(["rs", "toml", "md"]);
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You are a coding assistant working in the user's project.")
+//!     .tool(ReadFile::new(sandbox.clone()))
+//!     .tool(WriteFile::new(sandbox.clone()))
+//!     .tool(ListDirectory::new(sandbox.clone()))
+//!     .tool(GlobFiles::new(sandbox))
+//!     .build();
+//! ```
+
+use std::{
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{completion::ToolDefinition, tool::Tool};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FsToolError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// The path is outside of the root of the sandbox
+    #[error("OutsideRoot: {0}")]
+    OutsideRoot(String),
+
+    #[error("ExtensionNotAllowed: {0}")]
+    ExtensionNotAllowed(String),
+
+    #[error("FileTooLarge: {path} ({size} bytes)")]
+    FileTooLarge { path: String, size: u64 },
+
+    #[error("PatternError: {0}")]
+    PatternError(String),
+}
+
+/// Root directory and policies of the filesystem tools.
+#[derive(Clone, Debug)]
+pub struct FsSandbox {
+    root: PathBuf,
+    max_file_bytes: u64,
+    allowed_extensions: Option<Vec<String>>,
+    max_results: usize,
+}
+
+impl FsSandbox {
+    /// Create a sandbox rooted at the existing directory `root`, allowing all the extensions
+    /// and files of at most 1 MiB.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, FsToolError> {
+        Ok(Self {
+            root: root.as_ref().canonicalize()?,
+            max_file_bytes: 1 << 20,
+            allowed_extensions: None,
+            max_results: 1000,
+        })
+    }
+
+    /// Set the maximum size of the files read and written.
+    pub fn max_file_bytes(mut self, max_file_bytes: u64) -> Self {
+        self.max_file_bytes = max_file_bytes;
+        self
+    }
+
+    /// Only allow reading and writing the files with the given extensions (without the dot).
+    pub fn allow_extensions<'a>(mut self, extensions: impl IntoIterator<Item = &'a str>) -> Self {
+        self.allowed_extensions = Some(
+            extensions
+                .into_iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Set the maximum number of entries returned by the listings and globs (default: 1000).
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve `path` (relative to the root) to a path within the root.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, FsToolError> {
+        let outside = || FsToolError::OutsideRoot(path.to_string());
+
+        let mut resolved = PathBuf::new();
+        for component in self.root.join(path).components() {
+            match component {
+                Component::ParentDir => {
+                    if !resolved.pop() {
+                        return Err(outside());
+                    }
+                }
+                Component::CurDir => {}
+                component => resolved.push(component),
+            }
+        }
+        if !resolved.starts_with(&self.root) {
+            return Err(outside());
+        }
+
+        // Reject the symbolic links pointing outside of the root, and the dangling ones (whose
+        // target would be created wherever they point to)
+        let mut current = self.root.clone();
+        for component in resolved.strip_prefix(&self.root).map_err(|_| outside())? {
+            current.push(component);
+            match std::fs::symlink_metadata(&current) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    let target = current.canonicalize().map_err(|_| outside())?;
+                    if !target.starts_with(&self.root) {
+                        return Err(outside());
+                    }
+                }
+                Ok(_) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// The path of `path` relative to the root, as shown to the model.
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn check_extension(&self, path: &Path) -> Result<(), FsToolError> {
+        let Some(allowed_extensions) = &self.allowed_extensions else {
+            return Ok(());
+        };
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if allowed_extensions.contains(&extension) {
+            Ok(())
+        } else {
+            Err(FsToolError::ExtensionNotAllowed(self.relative(path)))
+        }
+    }
+
+    fn check_size(&self, path: &Path, size: u64) -> Result<(), FsToolError> {
+        if size > self.max_file_bytes {
+            return Err(FsToolError::FileTooLarge {
+                path: self.relative(path),
+                size,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn path_definition(name: &str, description: &str, properties: serde_json::Value) -> ToolDefinition {
+    let required = properties
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .filter(|(_, property)| property.get("default").is_none())
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    ToolDefinition {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReadFileArgs {
+    pub path: String,
+}
+
+/// Tool reading a text file of the sandbox, named `read_file`.
+#[derive(Clone)]
+pub struct ReadFile(FsSandbox);
+
+impl ReadFile {
+    pub fn new(sandbox: FsSandbox) -> Self {
+        Self(sandbox)
+    }
+}
+
+impl Tool for ReadFile {
+    const NAME: &'static str = "read_file";
+
+    type Error = FsToolError;
+    type Args = ReadFileArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        path_definition(
+            Self::NAME,
+            "Read a text file of the project.",
+            json!({
+                "path": {"type": "string", "description": "The path of the file"}
+            }),
+        )
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let path = self.0.resolve(&args.path)?;
+        self.0.check_extension(&path)?;
+        self.0.check_size(&path, std::fs::metadata(&path)?.len())?;
+
+        let content = std::fs::read(&path)?;
+        Ok(String::from_utf8_lossy(&content).into_owned())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WriteFileArgs {
+    pub path: String,
+    pub content: String,
+    #[serde(default)]
+    pub append: bool,
+}
+
+/// Tool writing a text file of the sandbox (creating its parent directories), named
+/// `write_file`.
+#[derive(Clone)]
+pub struct WriteFile(FsSandbox);
+
+impl WriteFile {
+    pub fn new(sandbox: FsSandbox) -> Self {
+        Self(sandbox)
+    }
+}
+
+impl Tool for WriteFile {
+    const NAME: &'static str = "write_file";
+
+    type Error = FsToolError;
+    type Args = WriteFileArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        path_definition(
+            Self::NAME,
+            "Write a text file of the project, replacing its content (or appending to it).",
+            json!({
+                "path": {"type": "string", "description": "The path of the file"},
+                "content": {"type": "string", "description": "The content to write"},
+                "append": {
+                    "type": "boolean",
+                    "description": "Append the content to the file instead of replacing it",
+                    "default": false
+                }
+            }),
+        )
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let path = self.0.resolve(&args.path)?;
+        self.0.check_extension(&path)?;
+
+        let existing = match (args.append, std::fs::metadata(&path)) {
+            (true, Ok(metadata)) => metadata.len(),
+            _ => 0,
+        };
+        self.0
+            .check_size(&path, existing + args.content.len() as u64)?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // The path is checked again once its parents exist (they may have been replaced by
+        // symbolic links meanwhile), and the opened file before it is truncated or written
+        let path = self.0.resolve(&args.path)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(args.append)
+            .truncate(false)
+            .open(&path)?;
+        if !path.canonicalize()?.starts_with(&self.0.root) {
+            return Err(FsToolError::OutsideRoot(args.path));
+        }
+        if !args.append {
+            file.set_len(0)?;
+        }
+        file.write_all(args.content.as_bytes())?;
+
+        Ok(format!(
+            "Wrote {} bytes to {}",
+            args.content.len(),
+            self.0.relative(&path)
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListDirectoryArgs {
+    #[serde(default = "current_directory")]
+    pub path: String,
+}
+
+fn current_directory() -> String {
+    ".".to_string()
+}
+
+/// An entry of a directory listed by [ListDirectory].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DirEntry {
+    /// Path of the entry, relative to the root of the sandbox
+    pub path: String,
+    pub is_dir: bool,
+    /// Size of the file in bytes (0 for the directories)
+    pub size: u64,
+}
+
+/// Tool listing the entries of a directory of the sandbox, named `list_directory`.
+#[derive(Clone)]
+pub struct ListDirectory(FsSandbox);
+
+impl ListDirectory {
+    pub fn new(sandbox: FsSandbox) -> Self {
+        Self(sandbox)
+    }
+}
+
+impl Tool for ListDirectory {
+    const NAME: &'static str = "list_directory";
+
+    type Error = FsToolError;
+    type Args = ListDirectoryArgs;
+    type Output = Vec<DirEntry>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        path_definition(
+            Self::NAME,
+            "List the files and directories of a directory of the project.",
+            json!({
+                "path": {
+                    "type": "string",
+                    "description": "The path of the directory",
+                    "default": "."
+                }
+            }),
+        )
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let path = self.0.resolve(&args.path)?;
+
+        let mut entries = std::fs::read_dir(&path)?
+            .map(|entry| {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                Ok(DirEntry {
+                    path: self.0.relative(&entry.path()),
+                    is_dir: metadata.is_dir(),
+                    size: if metadata.is_dir() { 0 } else { metadata.len() },
+                })
+            })
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries.truncate(self.0.max_results);
+
+        Ok(entries)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GlobFilesArgs {
+    pub pattern: String,
+}
+
+/// Tool finding the files of the sandbox matching a glob pattern (e.g.: `src/**/*.rs`), named
+/// `glob_files`.
+#[derive(Clone)]
+pub struct GlobFiles(FsSandbox);
+
+impl GlobFiles {
+    pub fn new(sandbox: FsSandbox) -> Self {
+        Self(sandbox)
+    }
+}
+
+impl Tool for GlobFiles {
+    const NAME: &'static str = "glob_files";
+
+    type Error = FsToolError;
+    type Args = GlobFilesArgs;
+    type Output = Vec<String>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        path_definition(
+            Self::NAME,
+            "Find the files of the project matching a glob pattern.",
+            json!({
+                "pattern": {
+                    "type": "string",
+                    "description": "The glob pattern, relative to the root of the project (e.g.: src/**/*.rs)"
+                }
+            }),
+        )
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if Path::new(&args.pattern)
+            .components()
+            .any(|component| matches!(component, Component::ParentDir))
+        {
+            return Err(FsToolError::OutsideRoot(args.pattern));
+        }
+        let pattern = self.0.root.join(args.pattern.trim_start_matches('/'));
+
+        let mut paths = vec![];
+        for path in glob::glob(&pattern.to_string_lossy())
+            .map_err(|err| FsToolError::PatternError(err.to_string()))?
+        {
+            let Ok(path) = path else { continue };
+            // Skip the symbolic links pointing outside of the root
+            if path
+                .canonicalize()
+                .is_ok_and(|target| target.starts_with(&self.0.root))
+            {
+                paths.push(self.0.relative(&path));
+            }
+            if paths.len() >= self.0.max_results {
+                break;
+            }
+        }
+
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fs_tools() {
+        let root = std::env::temp_dir().join(format!("rig-fs-tools-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let sandbox = FsSandbox::new(&root)
+            .unwrap()
+            .max_file_bytes(20)
+            .allow_extensions(["txt"]);
+
+        let write = WriteFile::new(sandbox.clone());
+        let write_args = |path: &str, content: &str| WriteFileArgs {
+            path: path.to_string(),
+            content: content.to_string(),
+            append: false,
+        };
+        write
+            .call(write_args("notes/a.txt", "Hello"))
+            .await
+            .unwrap();
+        write.call(write_args("b.txt", "World")).await.unwrap();
+        assert!(matches!(
+            write.call(write_args("../escape.txt", "x")).await,
+            Err(FsToolError::OutsideRoot(_))
+        ));
+        assert!(matches!(
+            write.call(write_args("/etc/passwd", "x")).await,
+            Err(FsToolError::OutsideRoot(_))
+        ));
+        assert!(matches!(
+            write.call(write_args("script.sh", "x")).await,
+            Err(FsToolError::ExtensionNotAllowed(_))
+        ));
+        assert!(matches!(
+            write.call(write_args("big.txt", &"x".repeat(21))).await,
+            Err(FsToolError::FileTooLarge { .. })
+        ));
+
+        let read = ReadFile::new(sandbox.clone());
+        let content = read
+            .call(ReadFileArgs {
+                path: "./notes/../notes/a.txt".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(content, "Hello");
+
+        let entries = ListDirectory::new(sandbox.clone())
+            .call(ListDirectoryArgs {
+                path: ".".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                DirEntry {
+                    path: "b.txt".to_string(),
+                    is_dir: false,
+                    size: 5
+                },
+                DirEntry {
+                    path: "notes".to_string(),
+                    is_dir: true,
+                    size: 0
+                },
+            ]
+        );
+
+        let paths = GlobFiles::new(sandbox)
+            .call(GlobFilesArgs {
+                pattern: "**/*.txt".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(paths.len(), 2);
+
+        // The symbolic links to the outside of the root are rejected, even if dangling
+        #[cfg(unix)]
+        {
+            let outside = root.with_extension("outside.txt");
+            std::os::unix::fs::symlink(&outside, root.join("link.txt")).unwrap();
+            std::os::unix::fs::symlink(std::env::temp_dir(), root.join("tmp")).unwrap();
+            assert!(matches!(
+                write.call(write_args("link.txt", "x")).await,
+                Err(FsToolError::OutsideRoot(_))
+            ));
+            assert!(matches!(
+                write.call(write_args("tmp/escape.txt", "x")).await,
+                Err(FsToolError::OutsideRoot(_))
+            ));
+            assert!(!outside.exists());
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
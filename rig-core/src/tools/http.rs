@@ -0,0 +1,542 @@
+//! HTTP request tool, restricted to allowed domains and methods.
+//!
+//! The [HttpTool] lets the model send HTTP requests (method, URL, headers and body) to the
+//! domains of its allowlist (all domains but those of its denylist by default), with a limit
+//! on the size of the responses and a timeout.
+//!
+//! # Example
+//! ```rust
+//! use rig::tools::http::HttpTool;
+//!
+//! let http = HttpTool::new()
+//!     .allow_domain("api.github.com")
+//!     .allow_methods(["GET"])
+//!     .default_header("Authorization", &format!("Bearer {token}"))
+//!     .max_response_bytes(100_000);
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You answer questions about GitHub repositories with their REST API.")
+//!     .dyn_tool(http)
+//!     .build();
+//! ```
+
+use std::{collections::HashMap, time::Duration};
+
+use futures::StreamExt;
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    completion::ToolDefinition,
+    runtime::{timeout, Elapsed},
+    tool::{ToolDyn, ToolError},
+    wasm_compat::WasmBoxedFuture,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpToolError {
+    #[error("InvalidUrl: {0}")]
+    InvalidUrl(String),
+
+    /// The domain of the URL is not allowed (or is denied)
+    #[error("DomainNotAllowed: {0}")]
+    DomainNotAllowed(String),
+
+    #[error("MethodNotAllowed: {0}")]
+    MethodNotAllowed(String),
+
+    /// The body of the request exceeds the maximum size
+    #[error("RequestTooLarge: {0} bytes")]
+    RequestTooLarge(usize),
+
+    /// The response is still a redirect after the maximum number of redirects
+    #[error("TooManyRedirects: {0}")]
+    TooManyRedirects(String),
+
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Timeout(#[from] Elapsed),
+}
+
+/// Request of the model to the [HttpTool].
+#[derive(Clone, Debug, Deserialize)]
+pub struct HttpRequest {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// Response of the [HttpTool] to the model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub body: String,
+    /// Whether the body was truncated to the maximum size of the responses
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// Maximum number of redirects followed by the [HttpTool].
+const MAX_REDIRECTS: usize = 10;
+
+/// Tool sending HTTP requests, named `http_request`.
+///
+/// A domain of the allowlist or denylist matches itself and its subdomains (e.g.:
+/// `example.com` matches `api.example.com`). With an empty allowlist, all the domains which are
+/// not denied are allowed. The redirects are followed by the tool (up to 10), which checks
+/// their URLs like the URLs of the requests; the default headers are not sent to the other
+/// hosts. On `wasm32`, the redirects are followed by the browser and not checked.
+///
+/// The tool is a [ToolDyn] (the HTTP requests are not `Sync`), added to the agents with
+/// [AgentBuilder::dyn_tool](crate::agent::AgentBuilder::dyn_tool).
+#[derive(Clone)]
+pub struct HttpTool {
+    http_client: reqwest::Client,
+    allowed_domains: Vec<String>,
+    denied_domains: Vec<String>,
+    allowed_methods: Vec<Method>,
+    default_headers: Vec<(String, String)>,
+    max_request_bytes: usize,
+    max_response_bytes: usize,
+    timeout: Duration,
+}
+
+impl Default for HttpTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpTool {
+    pub const NAME: &'static str = "http_request";
+
+    /// Create an HTTP tool allowing all the domains and the `GET`, `POST`, `PUT`, `PATCH`,
+    /// `DELETE` and `HEAD` methods, with responses of at most 1 MiB (truncated beyond) and a
+    /// timeout of 30s.
+    pub fn new() -> Self {
+        Self {
+            http_client: http_client(),
+            allowed_domains: vec![],
+            denied_domains: vec![],
+            allowed_methods: vec![
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+                Method::HEAD,
+            ],
+            default_headers: vec![],
+            max_request_bytes: 1 << 20,
+            max_response_bytes: 1 << 20,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Allow the requests to `domain` (and its subdomains). Once a domain is allowed, the
+    /// requests to the other domains are rejected.
+    pub fn allow_domain(mut self, domain: &str) -> Self {
+        self.allowed_domains.push(domain.to_lowercase());
+        self
+    }
+
+    /// Reject the requests to `domain` (and its subdomains), even if allowed.
+    pub fn deny_domain(mut self, domain: &str) -> Self {
+        self.denied_domains.push(domain.to_lowercase());
+        self
+    }
+
+    /// Set the allowed methods (e.g.: `["GET"]` for a read-only tool). Invalid methods are
+    /// ignored.
+    pub fn allow_methods<'a>(mut self, methods: impl IntoIterator<Item = &'a str>) -> Self {
+        self.allowed_methods = methods
+            .into_iter()
+            .filter_map(|method| Method::from_bytes(method.to_uppercase().as_bytes()).ok())
+            .collect();
+        self
+    }
+
+    /// Add a header to all the requests (e.g.: an authentication header, which the model does
+    /// not see).
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        self.default_headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set the maximum size of the bodies of the requests.
+    pub fn max_request_bytes(mut self, max_request_bytes: usize) -> Self {
+        self.max_request_bytes = max_request_bytes;
+        self
+    }
+
+    /// Set the maximum size of the bodies of the responses, truncated beyond.
+    pub fn max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Set the timeout of the requests (including the download of the responses).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use a custom HTTP client for the requests (e.g.: with a proxy). The client should not
+    /// follow the redirects (i.e.: built with [reqwest::redirect::Policy::none]), so that the
+    /// tool checks their URLs.
+    pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Check that the request is allowed, returning its method and URL.
+    fn check(&self, request: &HttpRequest) -> Result<(Method, Url), HttpToolError> {
+        let method = Method::from_bytes(request.method.to_uppercase().as_bytes())
+            .ok()
+            .filter(|method| self.allowed_methods.contains(method))
+            .ok_or_else(|| HttpToolError::MethodNotAllowed(request.method.clone()))?;
+
+        let url = Url::parse(&request.url)
+            .map_err(|err| HttpToolError::InvalidUrl(format!("{}: {err}", request.url)))?;
+        self.check_url(&url)?;
+
+        let body_size = request.body.as_ref().map_or(0, String::len);
+        if body_size > self.max_request_bytes {
+            return Err(HttpToolError::RequestTooLarge(body_size));
+        }
+
+        Ok((method, url))
+    }
+
+    /// Check that the scheme and domain of `url` are allowed.
+    fn check_url(&self, url: &Url) -> Result<(), HttpToolError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(HttpToolError::InvalidUrl(format!(
+                "unsupported scheme {}",
+                url.scheme()
+            )));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| HttpToolError::InvalidUrl(format!("{url}: no host")))?
+            .to_lowercase();
+
+        let matches = |domain: &String| host == *domain || host.ends_with(&format!(".{domain}"));
+        if self.denied_domains.iter().any(matches)
+            || !(self.allowed_domains.is_empty() || self.allowed_domains.iter().any(matches))
+        {
+            return Err(HttpToolError::DomainNotAllowed(host));
+        }
+
+        Ok(())
+    }
+
+    /// The method and URL of the request following the redirect `response`, if it is a redirect
+    /// to an allowed URL (the body of the request is dropped if the method changes to `GET`).
+    fn redirect(
+        &self,
+        method: &Method,
+        url: &Url,
+        response: &reqwest::Response,
+    ) -> Result<Option<(Method, Url)>, HttpToolError> {
+        let Some(location) = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .filter(|_| response.status().is_redirection())
+        else {
+            return Ok(None);
+        };
+
+        let next = url
+            .join(location)
+            .map_err(|err| HttpToolError::InvalidUrl(format!("{location}: {err}")))?;
+        self.check_url(&next)?;
+
+        let method = match response.status().as_u16() {
+            301..=303 if *method != Method::HEAD => Method::GET,
+            _ => method.clone(),
+        };
+        if !self.allowed_methods.contains(&method) {
+            return Err(HttpToolError::MethodNotAllowed(method.to_string()));
+        }
+
+        Ok(Some((method, next)))
+    }
+
+    /// Send the request, if allowed.
+    pub async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpToolError> {
+        let (mut method, mut url) = self.check(&request)?;
+        tracing::info!(target: "rig", "HTTP tool request: {} {}", method, url);
+        let host = url.host_str().map(str::to_string);
+        let mut body = request.body;
+
+        timeout(self.timeout, async {
+            let mut redirects = 0;
+            let response = loop {
+                let mut builder = self.http_client.request(method.clone(), url.clone());
+                for (name, value) in &request.headers {
+                    builder = builder.header(name, value);
+                }
+                // The default headers (e.g.: credentials) are only sent to the requested host
+                if url.host_str().map(str::to_string) == host {
+                    for (name, value) in &self.default_headers {
+                        builder = builder.header(name, value);
+                    }
+                }
+                if let Some(body) = &body {
+                    builder = builder.body(body.clone());
+                }
+
+                let response = builder.send().await?;
+                let Some((next_method, next_url)) = self.redirect(&method, &url, &response)? else {
+                    break response;
+                };
+                if redirects == MAX_REDIRECTS {
+                    return Err(HttpToolError::TooManyRedirects(next_url.to_string()));
+                }
+                redirects += 1;
+
+                tracing::info!(target: "rig", "HTTP tool redirect: {} {}", next_method, next_url);
+                if next_method != method {
+                    body = None;
+                }
+                (method, url) = (next_method, next_url);
+            };
+
+            let status = response.status().as_u16();
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+
+            let mut body = Vec::new();
+            let mut truncated = false;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                let remaining = self.max_response_bytes - body.len();
+                if chunk.len() > remaining {
+                    body.extend_from_slice(&chunk[..remaining]);
+                    truncated = true;
+                    break;
+                }
+                body.extend_from_slice(&chunk);
+            }
+
+            Ok(HttpResponse {
+                status,
+                content_type,
+                body: String::from_utf8_lossy(&body).into_owned(),
+                truncated,
+            })
+        })
+        .await?
+    }
+}
+
+/// HTTP client not following the redirects, which are followed (and checked) by the tool.
+fn http_client() -> reqwest::Client {
+    #[cfg(not(target_arch = "wasm32"))]
+    let builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+    #[cfg(target_arch = "wasm32")]
+    let builder = reqwest::Client::builder();
+
+    builder
+        .build()
+        .expect("HTTP tool reqwest client should build")
+}
+
+impl ToolDyn for HttpTool {
+    fn name(&self) -> String {
+        Self::NAME.to_string()
+    }
+
+    fn definition(&self, _prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        let methods = self
+            .allowed_methods
+            .iter()
+            .map(Method::to_string)
+            .collect::<Vec<_>>();
+        let mut description = "Send an HTTP request, returning the status, content type and \
+            body of the response."
+            .to_string();
+        if !self.allowed_domains.is_empty() {
+            description.push_str(&format!(
+                " Only the following domains (and their subdomains) are allowed: {}.",
+                self.allowed_domains.join(", ")
+            ));
+        }
+
+        Box::pin(async move {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description,
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "method": {
+                            "type": "string",
+                            "enum": methods,
+                            "description": "The HTTP method (default: GET)"
+                        },
+                        "url": {
+                            "type": "string",
+                            "description": "The URL of the request"
+                        },
+                        "headers": {
+                            "type": "object",
+                            "additionalProperties": {"type": "string"},
+                            "description": "The headers of the request"
+                        },
+                        "body": {
+                            "type": "string",
+                            "description": "The body of the request"
+                        }
+                    },
+                    "required": ["url"]
+                }),
+            }
+        })
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        Box::pin(async move {
+            let request = serde_json::from_str::<HttpRequest>(&args)?;
+            let response = self
+                .send(request)
+                .await
+                .map_err(|err| ToolError::ToolCallError(Box::new(err)))?;
+            Ok(serde_json::to_string(&response)?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    fn request(method: &str, url: &str) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: HashMap::new(),
+            body: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_tool() {
+        let tool = HttpTool::new()
+            .allow_domain("example.com")
+            .allow_domain("127.0.0.1")
+            .deny_domain("internal.example.com")
+            .allow_methods(["get"])
+            .max_response_bytes(10)
+            .with_client(
+                reqwest::Client::builder()
+                    .no_proxy()
+                    .redirect(reqwest::redirect::Policy::none())
+                    .build()
+                    .unwrap(),
+            );
+
+        assert!(tool
+            .check(&request("GET", "https://api.example.com/x"))
+            .is_ok());
+        assert!(matches!(
+            tool.check(&request("GET", "https://example.org")),
+            Err(HttpToolError::DomainNotAllowed(_))
+        ));
+        assert!(matches!(
+            tool.check(&request("GET", "https://a.internal.example.com")),
+            Err(HttpToolError::DomainNotAllowed(_))
+        ));
+        assert!(matches!(
+            tool.check(&request("GET", "https://notexample.com")),
+            Err(HttpToolError::DomainNotAllowed(_))
+        ));
+        assert!(matches!(
+            tool.check(&request("POST", "https://example.com")),
+            Err(HttpToolError::MethodNotAllowed(_))
+        ));
+        assert!(matches!(
+            tool.check(&request("GET", "file:///etc/passwd")),
+            Err(HttpToolError::InvalidUrl(_))
+        ));
+
+        // The responses are truncated to their maximum size
+        let port = serve(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 26\r\n\r\nabcdefghijklmnopqrstuvwxyz",
+        ])
+        .await;
+
+        let output = tool
+            .call(json!({"url": format!("http://127.0.0.1:{port}/")}).to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<HttpResponse>(&output).unwrap(),
+            HttpResponse {
+                status: 200,
+                content_type: Some("text/plain".to_string()),
+                body: "abcdefghij".to_string(),
+                truncated: true,
+            }
+        );
+
+        // The redirects are followed if their URL is allowed
+        let port = serve(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: /moved\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+        ])
+        .await;
+        let response = tool
+            .send(request("GET", &format!("http://127.0.0.1:{port}/")))
+            .await
+            .unwrap();
+        assert_eq!((response.status, response.body.as_str()), (200, "ok"));
+
+        let port = serve(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: http://internal.example.com/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        assert!(matches!(
+            tool.send(request("GET", &format!("http://127.0.0.1:{port}/")))
+                .await,
+            Err(HttpToolError::DomainNotAllowed(_))
+        ));
+    }
+
+    /// Serve `responses` to the consecutive connections to a local port, returning the port.
+    async fn serve(responses: Vec<&'static [u8]>) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0; 1024];
+                let _ = socket.read(&mut buffer).await.unwrap();
+                socket.write_all(response).await.unwrap();
+            }
+        });
+        port
+    }
+}
@@ -0,0 +1,34 @@
+//! Ready-made tools for agents.
+//!
+//! - [web_search]: search the web with Tavily, Brave or Serper.
+//! - [http]: send HTTP requests to allowed domains.
+//! - [fs]: read, write, list and glob the files of a sandbox directory.
+//! - `shell`: run shell commands after their approval (feature `shell`).
+//! - `code_interpreter`: run Python or JavaScript code in a resource-limited subprocess or
+//!   container (feature `code-interpreter`).
+//! - `sql`: run read-only SQL queries on Postgres, MySQL or SQLite (feature `sql`).
+//! - [wasm]: run tools compiled to WebAssembly with restricted capabilities.
+//! - [approval]: human-in-the-loop approval of the actions of the tools.
+
+pub mod approval;
+#[cfg(feature = "code-interpreter")]
+pub mod code_interpreter;
+pub mod fs;
+pub mod http;
+#[cfg(feature = "shell")]
+pub mod shell;
+#[cfg(feature = "sql")]
+pub mod sql;
+pub mod wasm;
+pub mod web_search;
+
+#[cfg(feature = "code-interpreter")]
+pub use code_interpreter::CodeInterpreter;
+pub use fs::FsSandbox;
+pub use http::HttpTool;
+#[cfg(feature = "shell")]
+pub use shell::ShellTool;
+#[cfg(feature = "sql")]
+pub use sql::SqlTool;
+pub use wasm::WasmTool;
+pub use web_search::WebSearch;
@@ -0,0 +1,378 @@
+//! Shell command tool, running the commands of the model after their approval (feature
+//! `shell`).
+//!
+//! Every command is submitted to an [ApprovalHandler] before running: a denied command is
+//! reported to the model with the reason of the denial. The commands run with `sh -c` (`cmd /C`
+//! on Windows), or without a shell if the programs are restricted with
+//! [ShellTool::allow_commands], in the configured working directory. They are killed after a
+//! timeout (on Unix, with the processes they started), and their stdout and stderr are
+//! captured up to a maximum size. In dry-run mode, the approved commands are reported to the
+//! model without running.
+//!
+//! The processes are spawned with tokio: the tool must run inside a tokio runtime.
+//!
+//! # Example
+//! ```rust
+//! use rig::tools::{approval::AutoApprove, shell::ShellTool};
+//!
+//! let shell = ShellTool::new(AutoApprove)
+//!     .working_dir("./workspace")
+//!     .allow_commands(["cargo", "ls", "cat", "grep"])
+//!     .timeout(Duration::from_secs(120));
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You are a coding assistant working in the user's project.")
+//!     .dyn_tool(shell)
+//!     .build();
+//! ```
+
+use std::{path::PathBuf, process::Stdio, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::approval::{Approval, ApprovalHandler, ApprovalRequest};
+use crate::{
+    completion::ToolDefinition,
+    runtime,
+    tool::{ToolDyn, ToolError},
+    wasm_compat::WasmBoxedFuture,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShellToolError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// The program of the command is not in the allowed commands
+    #[error("CommandNotAllowed: {0}")]
+    CommandNotAllowed(String),
+
+    #[error(transparent)]
+    Timeout(#[from] runtime::Elapsed),
+}
+
+#[derive(Deserialize)]
+struct ShellArgs {
+    command: String,
+}
+
+/// Result of a command run by the [ShellTool].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShellOutput {
+    /// Exit code of the command (`None` if it was killed by a signal)
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether stdout or stderr exceeded the maximum size and were truncated
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// Tool running shell commands approved by an [ApprovalHandler], named `shell`.
+///
+/// The tool is a [ToolDyn], added to the agents with
+/// [AgentBuilder::dyn_tool](crate::agent::AgentBuilder::dyn_tool).
+pub struct ShellTool<A> {
+    approval: A,
+    working_dir: Option<PathBuf>,
+    env: Vec<(String, String)>,
+    allowed_commands: Option<Vec<String>>,
+    timeout: Duration,
+    max_output_bytes: usize,
+    dry_run: bool,
+}
+
+impl<A: ApprovalHandler> ShellTool<A> {
+    pub const NAME: &'static str = "shell";
+
+    /// Create a shell tool submitting the commands to `approval`, with a timeout of 60 seconds
+    /// and outputs of at most 64 KiB.
+    pub fn new(approval: A) -> Self {
+        Self {
+            approval,
+            working_dir: None,
+            env: vec![],
+            allowed_commands: None,
+            timeout: Duration::from_secs(60),
+            max_output_bytes: 64 * 1024,
+            dry_run: false,
+        }
+    }
+
+    /// Set the working directory of the commands (default: the current directory).
+    pub fn working_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// Set an environment variable of the commands.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Only allow the commands whose program (first word) is one of `commands`. The commands
+    /// chaining programs (`;`, `&&`, `|`, ...) are then rejected, and the other commands are run
+    /// without a shell: their words are split like in a shell (with quotes), but the rest of
+    /// the shell syntax (redirections, variables, globs, `~`, ...) is passed as is to the
+    /// program.
+    pub fn allow_commands<'a>(mut self, commands: impl IntoIterator<Item = &'a str>) -> Self {
+        self.allowed_commands = Some(commands.into_iter().map(str::to_string).collect());
+        self
+    }
+
+    /// Set the duration after which the commands are killed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the maximum size of the captured stdout and stderr (each).
+    pub fn max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Report the approved commands to the model instead of running them.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Check that `command` is allowed, returning its words if it runs without a shell (i.e.:
+    /// if the programs are restricted).
+    fn check_allowed(&self, command: &str) -> Result<Option<Vec<String>>, ShellToolError> {
+        let Some(allowed_commands) = &self.allowed_commands else {
+            return Ok(None);
+        };
+        let not_allowed = || ShellToolError::CommandNotAllowed(command.to_string());
+
+        let chained = command.contains([';', '&', '|', '`', '\n']) || command.contains("$(");
+        let words = shlex::split(command).ok_or_else(not_allowed)?;
+        match words.first() {
+            Some(program) if !chained && allowed_commands.contains(program) => Ok(Some(words)),
+            _ => Err(not_allowed()),
+        }
+    }
+
+    /// Run `command`, without approval.
+    pub async fn run(&self, command: &str) -> Result<ShellOutput, ShellToolError> {
+        let mut process = match self.check_allowed(command)? {
+            Some(words) => {
+                let mut process = tokio::process::Command::new(&words[0]);
+                process.args(&words[1..]);
+                process
+            }
+            None if cfg!(windows) => {
+                let mut process = tokio::process::Command::new("cmd");
+                process.args(["/C", command]);
+                process
+            }
+            None => {
+                let mut process = tokio::process::Command::new("sh");
+                process.args(["-c", command]);
+                process
+            }
+        };
+        if let Some(working_dir) = &self.working_dir {
+            process.current_dir(working_dir);
+        }
+        process
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // In its own process group, killed with the processes started by the command (the
+        // shell alone is killed on drop)
+        #[cfg(unix)]
+        process.process_group(0);
+
+        let mut child = process.spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let result = runtime::timeout(self.timeout, async {
+            futures::try_join!(
+                capture(stdout, self.max_output_bytes),
+                capture(stderr, self.max_output_bytes),
+                child.wait(),
+            )
+        })
+        .await;
+        if result.is_err() {
+            kill_process_group(&child);
+        }
+        let ((stdout, stdout_truncated), (stderr, stderr_truncated), status) = result??;
+
+        Ok(ShellOutput {
+            exit_code: status.code(),
+            stdout,
+            stderr,
+            truncated: stdout_truncated || stderr_truncated,
+        })
+    }
+}
+
+/// Kill the process group of `child`, spawned as the leader of its own group, so that the
+/// processes started by the child are killed with it.
+pub(super) fn kill_process_group(child: &tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child
+        .id()
+        .and_then(|pid| rustix::process::Pid::from_raw(pid as i32))
+    {
+        let _ = rustix::process::kill_process_group(pid, rustix::process::Signal::KILL);
+    }
+    #[cfg(not(unix))]
+    let _ = child;
+}
+
+/// Read `reader` to the end, keeping its first `max_bytes` bytes.
+pub(super) async fn capture(
+    reader: Option<impl AsyncRead + Unpin>,
+    max_bytes: usize,
+) -> std::io::Result<(String, bool)> {
+    let Some(mut reader) = reader else {
+        return Ok((String::new(), false));
+    };
+    let mut output = vec![];
+    (&mut reader)
+        .take(max_bytes as u64)
+        .read_to_end(&mut output)
+        .await?;
+    // Drain the rest so that the command does not block on a full pipe
+    let dropped = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok((String::from_utf8_lossy(&output).into_owned(), dropped > 0))
+}
+
+impl<A: ApprovalHandler> ToolDyn for ShellTool<A> {
+    fn name(&self) -> String {
+        Self::NAME.to_string()
+    }
+
+    fn definition(&self, _prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        Box::pin(async move {
+            let mut description =
+                "Run a shell command, returning its exit code, stdout and stderr.".to_string();
+            if let Some(allowed_commands) = &self.allowed_commands {
+                description.push_str(&format!(
+                    " Allowed programs: {}.",
+                    allowed_commands.join(", ")
+                ));
+            }
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description,
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "command": {
+                            "type": "string",
+                            "description": "The command to run"
+                        }
+                    },
+                    "required": ["command"]
+                }),
+            }
+        })
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        Box::pin(async move {
+            let value = serde_json::from_str::<serde_json::Value>(&args)?;
+            let args = serde_json::from_value::<ShellArgs>(value.clone())?;
+            self.check_allowed(&args.command)
+                .map_err(|err| ToolError::ToolCallError(Box::new(err)))?;
+
+            let request = ApprovalRequest {
+                tool: Self::NAME.to_string(),
+                action: args.command.clone(),
+                args: value,
+            };
+            if let Approval::Deny { reason } = self.approval.approve(request).await {
+                return Ok(format!("The command was denied: {reason}"));
+            }
+            if self.dry_run {
+                return Ok(format!(
+                    "Dry run, the command was not run: {}",
+                    args.command
+                ));
+            }
+
+            let output = self
+                .run(&args.command)
+                .await
+                .map_err(|err| ToolError::ToolCallError(Box::new(err)))?;
+            Ok(serde_json::to_string(&output)?)
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::tools::approval::{AutoApprove, DenyAll};
+
+    #[tokio::test]
+    async fn test_shell_tool() {
+        let shell = ShellTool::new(AutoApprove).max_output_bytes(8);
+        let output = shell
+            .call(r#"{"command": "echo hello world; echo oops >&2; exit 3"}"#.to_string())
+            .await
+            .unwrap();
+        let output = serde_json::from_str::<ShellOutput>(&output).unwrap();
+        assert_eq!(output.exit_code, Some(3));
+        assert_eq!(output.stdout, "hello wo");
+        assert_eq!(output.stderr, "oops\n");
+        assert!(output.truncated);
+
+        let denied = ShellTool::new(DenyAll)
+            .call(r#"{"command": "rm -rf /"}"#.to_string())
+            .await
+            .unwrap();
+        assert!(denied.starts_with("The command was denied"));
+
+        let dry_run = ShellTool::new(|request: ApprovalRequest| async move {
+            assert_eq!(request.action, "touch /tmp/rig-shell-dry-run");
+            Approval::Approve
+        })
+        .dry_run(true)
+        .call(r#"{"command": "touch /tmp/rig-shell-dry-run"}"#.to_string())
+        .await
+        .unwrap();
+        assert!(dry_run.starts_with("Dry run"));
+
+        let shell = ShellTool::new(AutoApprove).allow_commands(["echo"]);
+        assert!(shell.run("echo ok && rm -rf /").await.is_err());
+        assert!(shell
+            .run("sleep 1")
+            .await
+            .is_err_and(|err| matches!(err, ShellToolError::CommandNotAllowed(_))));
+        // The allowed commands are not interpreted by a shell
+        let output = shell.run("echo 'a  b' $HOME ~ * > out").await.unwrap();
+        assert_eq!(output.stdout, "a  b $HOME ~ * > out\n");
+
+        let shell = ShellTool::new(AutoApprove).timeout(Duration::from_millis(100));
+        assert!(matches!(
+            shell.run("sleep 5").await,
+            Err(ShellToolError::Timeout(_))
+        ));
+
+        // The processes started by the command are killed with it
+        let pid_file = std::env::temp_dir().join(format!("rig-shell-{}.pid", std::process::id()));
+        let command = format!("sleep 5 & echo $! > {}; wait", pid_file.display());
+        assert!(shell.run(&command).await.is_err());
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        std::fs::remove_file(&pid_file).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        #[cfg(target_os = "linux")]
+        if let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid.trim())) {
+            assert!(
+                stat.contains(") Z "),
+                "The background process is alive: {stat}"
+            );
+        }
+    }
+}
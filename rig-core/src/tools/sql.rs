@@ -0,0 +1,548 @@
+//! Read-only SQL query tool for text-to-SQL agents (feature `sql`, with the drivers of the
+//! features `sql-postgres`, `sql-mysql` and `sql-sqlite`).
+//!
+//! The [SqlTool] runs the queries of the model on a [sqlx] pool, returning the columns and
+//! rows of the results as JSON. The schema of the database can be introspected into the
+//! description of the tool, so that the model knows the tables and columns it can query.
+//!
+//! The queries are read-only:
+//! - the statement must start with an allowed keyword (`SELECT` and `WITH` by default), and
+//!   must not contain several statements nor data-modifying keywords (`INSERT`, `DROP`,
+//!   `INTO`, ...);
+//! - the query then runs in a read-only transaction (Postgres and MySQL) or with
+//!   `PRAGMA query_only` (SQLite), which is rolled back.
+//!
+//! Read-only access should still be enforced by the database, with a user limited to `SELECT`
+//! privileges.
+//!
+//! # Example
+//! ```rust
+//! use rig::tools::sql::SqlTool;
+//!
+//! let sql = SqlTool::connect("postgres://readonly@localhost/shop")
+//!     .await?
+//!     .introspect()
+//!     .await?
+//!     .max_rows(50);
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("Answer the questions of the user about the shop with SQL queries.")
+//!     .dyn_tool(sql)
+//!     .build();
+//! ```
+
+use std::fmt;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{
+    any::{AnyPoolOptions, AnyRow, AnyTypeInfoKind},
+    AnyPool, Column, Row, ValueRef,
+};
+
+use crate::{
+    completion::ToolDefinition,
+    tool::{ToolDyn, ToolError},
+    wasm_compat::WasmBoxedFuture,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SqlToolError {
+    #[error("DatabaseError: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    /// The statement is not an allowed read-only statement
+    #[error("StatementNotAllowed: {0}")]
+    StatementNotAllowed(String),
+
+    #[error("UnsupportedDatabase: {0}")]
+    UnsupportedDatabase(String),
+}
+
+/// Database of a [SqlTool].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SqlBackend {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+impl SqlBackend {
+    /// The backend of the database URL `url`.
+    pub fn from_url(url: &str) -> Result<Self, SqlToolError> {
+        match url.split(':').next().unwrap_or_default() {
+            "postgres" | "postgresql" => Ok(Self::Postgres),
+            "mysql" | "mariadb" => Ok(Self::MySql),
+            "sqlite" => Ok(Self::Sqlite),
+            scheme => Err(SqlToolError::UnsupportedDatabase(scheme.to_string())),
+        }
+    }
+
+    /// Query listing the columns of the tables, as (table, column, type) rows.
+    fn schema_query(&self) -> &'static str {
+        match self {
+            Self::Postgres => {
+                "SELECT CAST(table_schema || '.' || table_name AS TEXT), \
+                    CAST(column_name AS TEXT), CAST(data_type AS TEXT) \
+                FROM information_schema.columns \
+                WHERE table_schema NOT IN ('pg_catalog', 'information_schema') \
+                ORDER BY table_schema, table_name, ordinal_position"
+            }
+            Self::MySql => {
+                "SELECT CAST(table_name AS CHAR), CAST(column_name AS CHAR), \
+                    CAST(column_type AS CHAR) \
+                FROM information_schema.columns \
+                WHERE table_schema = DATABASE() \
+                ORDER BY table_name, ordinal_position"
+            }
+            Self::Sqlite => {
+                "SELECT m.name, p.name, p.type \
+                FROM sqlite_master m JOIN pragma_table_info(m.name) p \
+                WHERE m.type IN ('table', 'view') AND m.name NOT LIKE 'sqlite_%' \
+                ORDER BY m.name, p.cid"
+            }
+        }
+    }
+}
+
+impl fmt::Display for SqlBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Postgres => write!(f, "PostgreSQL"),
+            Self::MySql => write!(f, "MySQL"),
+            Self::Sqlite => write!(f, "SQLite"),
+        }
+    }
+}
+
+/// Keywords rejected anywhere in the queries (outside of the literals and quoted identifiers),
+/// unless they are called as functions (e.g.: `REPLACE(name, 'a', 'b')`). The keywords only
+/// starting statements (e.g.: `SET`, `DO`) are rejected by the allowed first keywords.
+const FORBIDDEN_KEYWORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "UPSERT", "REPLACE", "CREATE", "ALTER", "DROP",
+    "TRUNCATE", "RENAME", "GRANT", "REVOKE", "INTO", "COPY", "ATTACH", "DETACH", "PRAGMA",
+    "VACUUM", "REINDEX", "CALL", "EXEC", "EXECUTE", "LOCK", "LOAD", "HANDLER",
+];
+
+/// Check that `query` is a single statement starting with one of the `allowed` keywords and
+/// without data-modifying keywords, returning the statement without its trailing `;`.
+pub fn check_read_only(query: &str, allowed: &[String]) -> Result<String, SqlToolError> {
+    let not_allowed = |reason: &str| SqlToolError::StatementNotAllowed(reason.to_string());
+
+    let query = query.trim().trim_end_matches(';').trim_end();
+    let words = sql_words(query)
+        .ok_or_else(|| not_allowed("unterminated or backslash-escaped literal or comment"))?;
+
+    let Some(first) = words.iter().find(|word| *word != "(") else {
+        return Err(not_allowed("empty statement"));
+    };
+    if !allowed
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(first))
+    {
+        return Err(not_allowed(&format!("{first} statements are not allowed")));
+    }
+    let forbidden = words.iter().enumerate().find_map(|(i, word)| {
+        let function = words.get(i + 1).is_some_and(|next| next == "(");
+        (word == ";"
+            || !function
+                && FORBIDDEN_KEYWORDS
+                    .iter()
+                    .any(|keyword| keyword.eq_ignore_ascii_case(word)))
+        .then_some(word)
+    });
+    if let Some(keyword) = forbidden {
+        return Err(if keyword == ";" {
+            not_allowed("multiple statements are not allowed")
+        } else {
+            not_allowed(&format!("{keyword} is not allowed"))
+        });
+    }
+
+    Ok(query.to_string())
+}
+
+/// The words (and `;`, `(`) of `query`, outside of its literals, quoted identifiers and
+/// comments.
+/// `None` if a literal or comment is not terminated, or if a literal contains a backslash.
+fn sql_words(query: &str) -> Option<Vec<String>> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        match c {
+            '\'' | '"' | '`' => loop {
+                match chars.next()? {
+                    quote if quote == c => {
+                        // Doubled quotes are escaped quotes
+                        if chars.peek() != Some(&c) {
+                            break;
+                        }
+                        chars.next();
+                    }
+                    // Reject the backslashes, escaping quotes in MySQL but not in standard SQL
+                    '\\' => return None,
+                    _ => {}
+                }
+            },
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    let c = chars.next()?;
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            ';' | '(' => words.push(c.to_string()),
+            _ => {}
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    Some(words)
+}
+
+#[derive(Deserialize)]
+struct SqlArgs {
+    query: String,
+}
+
+/// Result of a query run by the [SqlTool].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SqlOutput {
+    pub columns: Vec<String>,
+    /// Values of the rows, in the order of the columns (the blobs are encoded in base64)
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// Whether the result had more rows than the maximum and was truncated
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// Tool running read-only SQL queries, named `sql_query`.
+///
+/// The tool is a [ToolDyn] (the futures of sqlx are not `Sync`), added to the agents with
+/// [AgentBuilder::dyn_tool](crate::agent::AgentBuilder::dyn_tool).
+pub struct SqlTool {
+    pool: AnyPool,
+    backend: SqlBackend,
+    schema: Option<String>,
+    allowed_statements: Vec<String>,
+    max_rows: usize,
+}
+
+impl SqlTool {
+    pub const NAME: &'static str = "sql_query";
+
+    /// Connect to the database `url` with the drivers enabled by the features.
+    pub async fn connect(url: &str) -> Result<Self, SqlToolError> {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new().connect(url).await?;
+        Self::from_pool(pool)
+    }
+
+    /// Create a SQL tool from an existing pool, returning at most 100 rows by default.
+    ///
+    /// The drivers of the pool must have been installed, e.g.: with
+    /// [sqlx::any::install_default_drivers].
+    pub fn from_pool(pool: AnyPool) -> Result<Self, SqlToolError> {
+        let backend = SqlBackend::from_url(pool.connect_options().database_url.as_str())?;
+        Ok(Self {
+            pool,
+            backend,
+            schema: None,
+            allowed_statements: vec!["SELECT".to_string(), "WITH".to_string()],
+            max_rows: 100,
+        })
+    }
+
+    /// Introspect the tables and columns of the database into the description of the tool.
+    pub async fn introspect(mut self) -> Result<Self, SqlToolError> {
+        let rows = self.fetch(self.backend.schema_query(), usize::MAX).await?.0;
+
+        let mut tables: Vec<(String, Vec<String>)> = vec![];
+        for row in rows {
+            let (table, column, ty) = (
+                row.try_get::<String, _>(0)?,
+                row.try_get::<String, _>(1)?,
+                row.try_get::<Option<String>, _>(2)?.unwrap_or_default(),
+            );
+            let column = format!("{column} {ty}").trim_end().to_string();
+            match tables.last_mut() {
+                Some((last, columns)) if *last == table => columns.push(column),
+                _ => tables.push((table, vec![column])),
+            }
+        }
+
+        self.schema = Some(
+            tables
+                .iter()
+                .map(|(table, columns)| format!("- {table}({})", columns.join(", ")))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        Ok(self)
+    }
+
+    /// Set the description of the schema given to the model (e.g.: the tables it may query,
+    /// with comments), instead of introspecting it.
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    /// Set the keywords the statements may start with (default: `SELECT` and `WITH`).
+    pub fn allow_statements<'a>(mut self, keywords: impl IntoIterator<Item = &'a str>) -> Self {
+        self.allowed_statements = keywords.into_iter().map(str::to_uppercase).collect();
+        self
+    }
+
+    /// Set the maximum number of rows returned to the model.
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows.max(1);
+        self
+    }
+
+    /// Run the read-only `query`.
+    pub async fn query(&self, query: &str) -> Result<SqlOutput, SqlToolError> {
+        let query = check_read_only(query, &self.allowed_statements)?;
+        let (rows, truncated) = self.fetch(&query, self.max_rows).await?;
+
+        let columns = rows
+            .first()
+            .map(|row| {
+                row.columns()
+                    .iter()
+                    .map(|column| column.name().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let rows = rows
+            .iter()
+            .map(|row| (0..row.len()).map(|i| json_value(row, i)).collect())
+            .collect::<Result<_, _>>()?;
+
+        Ok(SqlOutput {
+            columns,
+            rows,
+            truncated,
+        })
+    }
+
+    /// Fetch the first `max_rows` rows of `query` in a read-only transaction, and whether there
+    /// were more rows.
+    async fn fetch(
+        &self,
+        query: &str,
+        max_rows: usize,
+    ) -> Result<(Vec<AnyRow>, bool), SqlToolError> {
+        use sqlx::{Connection, Executor};
+
+        let mut connection = self.pool.acquire().await?;
+        match self.backend {
+            SqlBackend::MySql => {
+                connection.execute("SET TRANSACTION READ ONLY").await?;
+            }
+            SqlBackend::Sqlite => {
+                connection.execute("PRAGMA query_only = ON").await?;
+            }
+            SqlBackend::Postgres => {}
+        }
+
+        let mut transaction = connection.begin().await?;
+        if self.backend == SqlBackend::Postgres {
+            transaction.execute("SET TRANSACTION READ ONLY").await?;
+        }
+        let rows = sqlx::query(query)
+            .fetch(&mut *transaction)
+            .take(max_rows.saturating_add(1))
+            .try_collect::<Vec<_>>()
+            .await;
+        transaction.rollback().await?;
+
+        if self.backend == SqlBackend::Sqlite {
+            connection.execute("PRAGMA query_only = OFF").await?;
+        }
+
+        let mut rows = rows?;
+        let truncated = rows.len() > max_rows;
+        rows.truncate(max_rows);
+        Ok((rows, truncated))
+    }
+}
+
+/// The value of the column `i` of `row` as JSON.
+fn json_value(row: &AnyRow, i: usize) -> Result<serde_json::Value, sqlx::Error> {
+    let kind = row.try_get_raw(i)?.type_info().kind();
+    Ok(match kind {
+        AnyTypeInfoKind::Null => serde_json::Value::Null,
+        AnyTypeInfoKind::Bool => row.try_get::<bool, _>(i)?.into(),
+        AnyTypeInfoKind::SmallInt | AnyTypeInfoKind::Integer | AnyTypeInfoKind::BigInt => {
+            row.try_get::<i64, _>(i)?.into()
+        }
+        AnyTypeInfoKind::Real | AnyTypeInfoKind::Double => row.try_get::<f64, _>(i)?.into(),
+        AnyTypeInfoKind::Text => row.try_get::<String, _>(i)?.into(),
+        AnyTypeInfoKind::Blob => BASE64_STANDARD.encode(row.try_get::<Vec<u8>, _>(i)?).into(),
+    })
+}
+
+impl ToolDyn for SqlTool {
+    fn name(&self) -> String {
+        Self::NAME.to_string()
+    }
+
+    fn definition(&self, _prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        Box::pin(async move {
+            let mut description = format!(
+                "Run a read-only SQL query ({} statement) on the {} database, returning at most \
+                {} rows.",
+                self.allowed_statements.join(" or "),
+                self.backend,
+                self.max_rows
+            );
+            if let Some(schema) = &self.schema {
+                description.push_str("\nSchema:\n");
+                description.push_str(schema);
+            }
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description,
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": format!("The SQL query, in the {} dialect", self.backend)
+                        }
+                    },
+                    "required": ["query"]
+                }),
+            }
+        })
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        Box::pin(async move {
+            let args = serde_json::from_str::<SqlArgs>(&args)?;
+            let output = self
+                .query(&args.query)
+                .await
+                .map_err(|err| ToolError::ToolCallError(Box::new(err)))?;
+            Ok(serde_json::to_string(&output)?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_read_only() {
+        let allowed = ["SELECT".to_string(), "WITH".to_string()];
+        let check = |query: &str| check_read_only(query, &allowed);
+
+        assert_eq!(
+            check("  select * from users; ").unwrap(),
+            "select * from users"
+        );
+        assert!(check("WITH t AS (SELECT 1) SELECT * FROM t").is_ok());
+        // Keywords in literals, quoted identifiers and comments are ignored
+        assert!(check("SELECT 'drop; table', \"insert\" FROM t -- delete\n").is_ok());
+        assert!(check("SELECT 'it''s' /* update */ FROM t").is_ok());
+        // Functions named like keywords
+        assert!(check("SELECT REPLACE (name, 'a', 'b') FROM users").is_ok());
+        assert!(check("(SELECT 1) UNION (SELECT 2)").is_ok());
+
+        for query in [
+            "DELETE FROM users",
+            "SELECT 1; DROP TABLE users",
+            "WITH d AS (DELETE FROM users RETURNING *) SELECT * FROM d",
+            "SELECT * INTO backup FROM users",
+            "REPLACE INTO users (name) VALUES ('a')",
+            "SET ROLE admin",
+            "SELECT 'unterminated",
+            "SELECT 'a\\' ; DELETE FROM users; --'",
+            "",
+        ] {
+            assert!(
+                matches!(check(query), Err(SqlToolError::StatementNotAllowed(_))),
+                "{query}"
+            );
+        }
+    }
+
+    #[cfg(feature = "sql-sqlite")]
+    #[tokio::test]
+    async fn test_sql_tool_sqlite() {
+        use sqlx::Executor;
+
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        pool.execute(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL);
+            INSERT INTO users (name, score) VALUES ('alice', 1.5), ('bob', NULL), ('carol', 3);",
+        )
+        .await
+        .unwrap();
+
+        let tool = SqlTool::from_pool(pool)
+            .unwrap()
+            .max_rows(2)
+            .introspect()
+            .await
+            .unwrap();
+        assert_eq!(
+            tool.schema.as_deref(),
+            Some("- users(id INTEGER, name TEXT, score REAL)")
+        );
+
+        let output = tool
+            .call(r#"{"query": "SELECT id, name, score FROM users ORDER BY id"}"#.to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<SqlOutput>(&output).unwrap(),
+            SqlOutput {
+                columns: vec!["id".to_string(), "name".to_string(), "score".to_string()],
+                rows: vec![
+                    vec![json!(1), json!("alice"), json!(1.5)],
+                    vec![json!(2), json!("bob"), json!(null)],
+                ],
+                truncated: true,
+            }
+        );
+
+        // The writes which would pass the statement checks are rejected by the database
+        assert!(tool.fetch("DELETE FROM users", 10).await.is_err());
+        assert_eq!(
+            tool.fetch("SELECT * FROM users", 10).await.unwrap().0.len(),
+            3
+        );
+    }
+}
@@ -0,0 +1,408 @@
+//! Tools compiled to WebAssembly, running in a sandboxed WASM instance with a
+//! capability-restricted host interface.
+//!
+//! A [WasmTool] loads a module implementing the tool ABI below, and runs each call in a fresh
+//! instance created by a [WasmEngine]: e.g.: the [WasmtimeEngine] (with the `wasmtime` feature),
+//! which enforces the [WasmLimits] of the instances with fuel and memory limits. The module can
+//! only reach the host through the imports of [WasmHost], each of them requiring a [Capability]
+//! granted to the tool. The calls run on the blocking threads of Tokio (see
+//! [runtime](crate::runtime)), so that they do not block the async executor.
+//!
+//! # ABI
+//! The module exports:
+//! - `memory`: its linear memory;
+//! - `rig_alloc(len: i32) -> i32`: allocate `len` bytes, returning their offset;
+//! - `rig_definition() -> i64`: the JSON [ToolDefinition] of the tool;
+//! - `rig_call(ptr: i32, len: i32) -> i64`: call the tool with the JSON arguments at `ptr`,
+//!   returning a JSON [WasmCallResult].
+//!
+//! The `i64` results are the offset (high 32 bits) and length (low 32 bits) of a buffer of the
+//! memory. The module may import the functions of the module `rig` (see [WasmHost]).
+//!
+//! # Example
+//! ```rust
+//! use rig::tools::wasm::{Capability, WasmTool, WasmtimeEngine};
+//!
+//! let tool = WasmTool::from_file(WasmtimeEngine::new()?, "plugins/weather.wasm")?
+//!     .capability(Capability::Log)
+//!     .capability(Capability::Env(vec!["WEATHER_UNITS".to_string()]));
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .dyn_tool(tool)
+//!     .build();
+//! ```
+
+use std::{path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::ToolDefinition,
+    runtime::spawn_blocking,
+    tool::{ToolDyn, ToolError},
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+};
+
+#[cfg(all(feature = "wasmtime", not(target_arch = "wasm32")))]
+mod wasmtime;
+#[cfg(all(feature = "wasmtime", not(target_arch = "wasm32")))]
+pub use self::wasmtime::WasmtimeEngine;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WasmToolError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// The module does not implement the tool ABI
+    #[error("AbiError: {0}")]
+    AbiError(String),
+
+    /// The module trapped or exceeded its limits
+    #[error("RuntimeError: {0}")]
+    RuntimeError(String),
+
+    /// The tool returned an error
+    #[error("ToolError: {0}")]
+    ToolError(String),
+}
+
+/// Result of the `rig_call` export of a module.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WasmCallResult {
+    Ok(String),
+    Error(String),
+}
+
+/// Access to the host granted to a [WasmTool].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Capability {
+    /// Import `log(ptr, len)`: log a message with [tracing]
+    Log,
+    /// Import `env(ptr, len) -> i64`: read the given environment variables
+    Env(Vec<String>),
+    /// Import `now_ms() -> i64`: read the current time (in milliseconds since the epoch)
+    Clock,
+}
+
+/// Limits of the instances of a [WasmTool], enforced by the [WasmEngine].
+#[derive(Clone, Debug, PartialEq)]
+pub struct WasmLimits {
+    /// Maximum size of the linear memory
+    pub max_memory_bytes: usize,
+    /// Maximum number of instructions (e.g.: the fuel of wasmtime) of a call
+    pub max_instructions: u64,
+}
+
+impl Default for WasmLimits {
+    /// 64 MiB of memory and a billion instructions.
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: 64 << 20,
+            max_instructions: 1_000_000_000,
+        }
+    }
+}
+
+/// Host interface of a [WasmTool], linked by the [WasmEngine] as the imports of the module
+/// `rig`.
+///
+/// The engine copies the buffers of the module from and to its memory: [WasmHost::call_import]
+/// receives the bytes of the `(ptr, len)` arguments and returns the bytes of the result
+/// (`None` being returned to the module as `0`).
+#[derive(Clone, Debug)]
+pub struct WasmHost {
+    tool: String,
+    capabilities: Vec<Capability>,
+}
+
+impl WasmHost {
+    /// Call the import `name` of the module `rig`, if the tool has its capability.
+    pub fn call_import(&self, name: &str, input: &[u8]) -> Result<Option<Vec<u8>>, WasmToolError> {
+        let denied = || {
+            WasmToolError::RuntimeError(format!(
+                "The tool {} does not have the capability of the import rig.{name}",
+                self.tool
+            ))
+        };
+
+        match name {
+            "log" if self.capabilities.contains(&Capability::Log) => {
+                tracing::info!(target: "rig", "[{}] {}", self.tool, String::from_utf8_lossy(input));
+                Ok(None)
+            }
+            "env" => {
+                let key = String::from_utf8_lossy(input);
+                let allowed = self.capabilities.iter().any(|capability| {
+                    matches!(capability, Capability::Env(keys) if keys.iter().any(|k| *k == key))
+                });
+                if !allowed {
+                    return Err(denied());
+                }
+                Ok(std::env::var(&*key).ok().map(String::into_bytes))
+            }
+            "now_ms" if self.capabilities.contains(&Capability::Clock) => {
+                let now = web_time::SystemTime::now()
+                    .duration_since(web_time::UNIX_EPOCH)
+                    .unwrap_or_default();
+                Ok(Some((now.as_millis() as i64).to_le_bytes().to_vec()))
+            }
+            _ => Err(denied()),
+        }
+    }
+}
+
+/// An instance of a module, created by a [WasmEngine].
+pub trait WasmInstance {
+    /// Call the export `function` with `params`, returning its result (0 if it returns
+    /// nothing).
+    fn call(&mut self, function: &str, params: &[i32]) -> Result<i64, WasmToolError>;
+
+    fn read_memory(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, WasmToolError>;
+
+    fn write_memory(&mut self, offset: usize, bytes: &[u8]) -> Result<(), WasmToolError>;
+}
+
+/// A WebAssembly runtime (e.g.: wasmtime) instantiating the modules of the [WasmTool]s.
+/// Its calls may block, and run on the blocking threads of Tokio.
+pub trait WasmEngine: WasmCompatSend + WasmCompatSync {
+    /// Instantiate `module`, linking the imports of the module `rig` to `host` and enforcing
+    /// `limits`.
+    fn instantiate(
+        &self,
+        module: &[u8],
+        host: WasmHost,
+        limits: &WasmLimits,
+    ) -> Result<Box<dyn WasmInstance>, WasmToolError>;
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    (
+        ((packed as u64) >> 32) as usize,
+        (packed as u64 & 0xffff_ffff) as usize,
+    )
+}
+
+fn read_buffer(instance: &mut dyn WasmInstance, packed: i64) -> Result<Vec<u8>, WasmToolError> {
+    let (offset, len) = unpack(packed);
+    instance.read_memory(offset, len)
+}
+
+/// Tool implemented by a WebAssembly module, named by the definition of the module.
+///
+/// The tool is a [ToolDyn], added to the agents with
+/// [AgentBuilder::dyn_tool](crate::agent::AgentBuilder::dyn_tool). Each call runs in a fresh
+/// instance, so that the calls do not share any state.
+pub struct WasmTool<E> {
+    engine: Arc<E>,
+    module: Arc<[u8]>,
+    definition: ToolDefinition,
+    capabilities: Vec<Capability>,
+    limits: WasmLimits,
+}
+
+impl<E: WasmEngine + 'static> WasmTool<E> {
+    /// Load the tool from the bytes of its module, reading its definition.
+    pub fn new(engine: E, module: impl Into<Arc<[u8]>>) -> Result<Self, WasmToolError> {
+        let mut tool = Self {
+            engine: Arc::new(engine),
+            module: module.into(),
+            definition: ToolDefinition {
+                name: String::new(),
+                description: String::new(),
+                parameters: serde_json::Value::Null,
+            },
+            capabilities: vec![],
+            limits: WasmLimits::default(),
+        };
+
+        let mut instance = tool.instantiate()()?;
+        let definition = instance.call("rig_definition", &[])?;
+        let definition = read_buffer(&mut *instance, definition)?;
+        tool.definition = serde_json::from_slice(&definition)?;
+        Ok(tool)
+    }
+
+    /// Load the tool from the module file `path`.
+    pub fn from_file(engine: E, path: impl AsRef<Path>) -> Result<Self, WasmToolError> {
+        Self::new(engine, std::fs::read(path)?)
+    }
+
+    /// Grant `capability` to the tool.
+    pub fn capability(mut self, capability: Capability) -> Self {
+        self.capabilities.push(capability);
+        self
+    }
+
+    pub fn limits(mut self, limits: WasmLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    /// Closure creating a fresh instance of the module, which can be moved to another thread.
+    fn instantiate(&self) -> impl FnOnce() -> Result<Box<dyn WasmInstance>, WasmToolError> {
+        let engine = self.engine.clone();
+        let module = self.module.clone();
+        let host = WasmHost {
+            tool: self.definition.name.clone(),
+            capabilities: self.capabilities.clone(),
+        };
+        let limits = self.limits.clone();
+        move || engine.instantiate(&module, host, &limits)
+    }
+
+    /// Call the tool with the JSON `args`, in a fresh instance.
+    pub async fn invoke(&self, args: &str) -> Result<String, WasmToolError> {
+        let instantiate = self.instantiate();
+        let args = args.to_string();
+        spawn_blocking(move || call(instantiate()?, &args)).await
+    }
+}
+
+fn call(mut instance: Box<dyn WasmInstance>, args: &str) -> Result<String, WasmToolError> {
+    let len = i32::try_from(args.len())
+        .map_err(|_| WasmToolError::AbiError("The arguments are too large".to_string()))?;
+    let ptr = instance.call("rig_alloc", &[len])?;
+    let ptr = i32::try_from(ptr)
+        .map_err(|_| WasmToolError::AbiError(format!("Invalid allocation: {ptr}")))?;
+    instance.write_memory(ptr as usize, args.as_bytes())?;
+
+    let result = instance.call("rig_call", &[ptr, len])?;
+    let result = read_buffer(&mut *instance, result)?;
+    match serde_json::from_slice(&result)? {
+        WasmCallResult::Ok(output) => Ok(output),
+        WasmCallResult::Error(error) => Err(WasmToolError::ToolError(error)),
+    }
+}
+
+impl<E: WasmEngine + 'static> ToolDyn for WasmTool<E> {
+    fn name(&self) -> String {
+        self.definition.name.clone()
+    }
+
+    fn definition(&self, _prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        Box::pin(async move { self.definition.clone() })
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        Box::pin(async move {
+            self.invoke(&args)
+                .await
+                .map_err(|err| ToolError::ToolCallError(Box::new(err)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Engine running a "module" written in Rust which implements the ABI: an `echo` tool
+    /// reading the environment variable given in its arguments.
+    struct MockEngine;
+
+    struct MockInstance {
+        memory: Vec<u8>,
+        host: WasmHost,
+    }
+
+    impl MockInstance {
+        fn write_result(&mut self, bytes: &[u8]) -> i64 {
+            let offset = self.memory.len();
+            self.memory.extend_from_slice(bytes);
+            ((offset as i64) << 32) | bytes.len() as i64
+        }
+    }
+
+    impl WasmInstance for MockInstance {
+        fn call(&mut self, function: &str, params: &[i32]) -> Result<i64, WasmToolError> {
+            match (function, params) {
+                ("rig_alloc", [len]) => {
+                    let offset = self.memory.len();
+                    self.memory.resize(offset + *len as usize, 0);
+                    Ok(offset as i64)
+                }
+                ("rig_definition", []) => {
+                    let definition = json!({
+                        "name": "echo_env",
+                        "description": "Echo an environment variable",
+                        "parameters": {"type": "object"}
+                    });
+                    Ok(self.write_result(definition.to_string().as_bytes()))
+                }
+                ("rig_call", [ptr, len]) => {
+                    let args = self.read_memory(*ptr as usize, *len as usize)?;
+                    let args = serde_json::from_slice::<serde_json::Value>(&args)?;
+                    let key = args["key"].as_str().unwrap_or_default();
+                    let result = match self.host.call_import("env", key.as_bytes()) {
+                        Ok(value) => WasmCallResult::Ok(
+                            String::from_utf8(value.unwrap_or_default()).unwrap(),
+                        ),
+                        Err(err) => WasmCallResult::Error(err.to_string()),
+                    };
+                    Ok(self.write_result(&serde_json::to_vec(&result)?))
+                }
+                _ => Err(WasmToolError::AbiError(format!(
+                    "Unknown export {function}"
+                ))),
+            }
+        }
+
+        fn read_memory(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, WasmToolError> {
+            self.memory
+                .get(offset..offset + len)
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| WasmToolError::RuntimeError("Out of bounds".to_string()))
+        }
+
+        fn write_memory(&mut self, offset: usize, bytes: &[u8]) -> Result<(), WasmToolError> {
+            self.memory[offset..offset + bytes.len()].copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    impl WasmEngine for MockEngine {
+        fn instantiate(
+            &self,
+            _module: &[u8],
+            host: WasmHost,
+            _limits: &WasmLimits,
+        ) -> Result<Box<dyn WasmInstance>, WasmToolError> {
+            Ok(Box::new(MockInstance {
+                memory: vec![0; 8],
+                host,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wasm_tool() {
+        let tool = WasmTool::new(MockEngine, vec![0u8; 4])
+            .unwrap()
+            .capability(Capability::Env(vec!["CARGO_PKG_NAME".to_string()]));
+        assert_eq!(ToolDyn::name(&tool), "echo_env");
+
+        let output = tool
+            .call(json!({"key": "CARGO_PKG_NAME"}).to_string())
+            .await
+            .unwrap();
+        assert_eq!(output, env!("CARGO_PKG_NAME"));
+
+        // The variables which were not granted are denied
+        let err = tool
+            .invoke(&json!({"key": "HOME"}).to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WasmToolError::ToolError(message) if message.contains("capability")));
+        assert!(tool.instantiate()().unwrap().call("missing", &[]).is_err());
+    }
+}
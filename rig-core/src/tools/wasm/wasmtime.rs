@@ -0,0 +1,294 @@
+//! [WasmEngine] backed by [wasmtime] (with the `wasmtime` feature).
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use sha2::{Digest, Sha256};
+use wasmtime::{
+    Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder, Val,
+};
+
+use super::{WasmEngine, WasmHost, WasmInstance, WasmLimits, WasmToolError};
+
+/// State of a store: the host interface of the tool and the limits of its instance.
+struct State {
+    host: WasmHost,
+    limits: StoreLimits,
+}
+
+/// [WasmEngine] running the modules with wasmtime (compiled with Cranelift).
+///
+/// The [WasmLimits] are enforced with the fuel of wasmtime (one unit per instruction, for the
+/// whole call) and the limits of its stores (the linear memory cannot grow beyond
+/// `max_memory_bytes`). The compiled modules are cached by the hash of their bytes.
+#[derive(Clone)]
+pub struct WasmtimeEngine {
+    engine: Engine,
+    linker: Arc<Linker<State>>,
+    modules: Arc<Mutex<HashMap<[u8; 32], Module>>>,
+}
+
+impl WasmtimeEngine {
+    pub fn new() -> Result<Self, WasmToolError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(runtime_error)?;
+
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap(
+                "rig",
+                "log",
+                |mut caller: Caller<'_, State>, ptr: i32, len: i32| -> wasmtime::Result<()> {
+                    let input = read(&mut caller, ptr, len)?;
+                    import(&caller, "log", &input)?;
+                    Ok(())
+                },
+            )
+            .and_then(|linker| {
+                linker.func_wrap(
+                    "rig",
+                    "env",
+                    |mut caller: Caller<'_, State>, ptr: i32, len: i32| -> wasmtime::Result<i64> {
+                        let input = read(&mut caller, ptr, len)?;
+                        match import(&caller, "env", &input)? {
+                            Some(value) => write(&mut caller, &value),
+                            None => Ok(0),
+                        }
+                    },
+                )
+            })
+            .and_then(|linker| {
+                linker.func_wrap(
+                    "rig",
+                    "now_ms",
+                    |caller: Caller<'_, State>| -> wasmtime::Result<i64> {
+                        let now = import(&caller, "now_ms", &[])?.unwrap_or_default();
+                        let now = <[u8; 8]>::try_from(now.as_slice())
+                            .map_err(|_| wasmtime::format_err!("Invalid time"))?;
+                        Ok(i64::from_le_bytes(now))
+                    },
+                )
+            })
+            .map_err(runtime_error)?;
+
+        Ok(Self {
+            engine,
+            linker: Arc::new(linker),
+            modules: Default::default(),
+        })
+    }
+
+    fn module(&self, bytes: &[u8]) -> Result<Module, WasmToolError> {
+        let hash = Sha256::digest(bytes).into();
+        if let Some(module) = self.modules.lock().unwrap().get(&hash) {
+            return Ok(module.clone());
+        }
+
+        let module = Module::new(&self.engine, bytes)
+            .map_err(|err| WasmToolError::AbiError(format!("{err:#}")))?;
+        self.modules.lock().unwrap().insert(hash, module.clone());
+        Ok(module)
+    }
+}
+
+impl WasmEngine for WasmtimeEngine {
+    fn instantiate(
+        &self,
+        module: &[u8],
+        host: WasmHost,
+        limits: &WasmLimits,
+    ) -> Result<Box<dyn WasmInstance>, WasmToolError> {
+        let module = self.module(module)?;
+        let state = State {
+            host,
+            limits: StoreLimitsBuilder::new()
+                .memory_size(limits.max_memory_bytes)
+                .instances(1)
+                .build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store
+            .set_fuel(limits.max_instructions)
+            .map_err(runtime_error)?;
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, &module)
+            .map_err(runtime_error)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| WasmToolError::AbiError("Missing export memory".to_string()))?;
+
+        Ok(Box::new(WasmtimeInstance {
+            store,
+            instance,
+            memory,
+        }))
+    }
+}
+
+struct WasmtimeInstance {
+    store: Store<State>,
+    instance: Instance,
+    memory: Memory,
+}
+
+impl WasmInstance for WasmtimeInstance {
+    fn call(&mut self, function: &str, params: &[i32]) -> Result<i64, WasmToolError> {
+        let func = self
+            .instance
+            .get_func(&mut self.store, function)
+            .ok_or_else(|| WasmToolError::AbiError(format!("Missing export {function}")))?;
+        let params = params.iter().copied().map(Val::I32).collect::<Vec<_>>();
+        let mut results = vec![Val::I64(0); func.ty(&self.store).results().len()];
+        func.call(&mut self.store, &params, &mut results)
+            .map_err(runtime_error)?;
+
+        match results.first() {
+            None => Ok(0),
+            Some(Val::I32(result)) => Ok(*result as i64),
+            Some(Val::I64(result)) => Ok(*result),
+            Some(result) => Err(WasmToolError::AbiError(format!(
+                "Invalid result of {function}: {result:?}"
+            ))),
+        }
+    }
+
+    fn read_memory(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, WasmToolError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.memory.data(&self.store).get(offset..end))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| WasmToolError::RuntimeError("Out of bounds".to_string()))
+    }
+
+    fn write_memory(&mut self, offset: usize, bytes: &[u8]) -> Result<(), WasmToolError> {
+        self.memory
+            .write(&mut self.store, offset, bytes)
+            .map_err(|err| WasmToolError::RuntimeError(err.to_string()))
+    }
+}
+
+fn runtime_error(err: wasmtime::Error) -> WasmToolError {
+    WasmToolError::RuntimeError(format!("{err:#}"))
+}
+
+/// Call the import `name` of the host, trapping if it fails (e.g.: missing capability).
+fn import(
+    caller: &Caller<'_, State>,
+    name: &str,
+    input: &[u8],
+) -> wasmtime::Result<Option<Vec<u8>>> {
+    caller
+        .data()
+        .host
+        .call_import(name, input)
+        .map_err(|err| wasmtime::format_err!("{err}"))
+}
+
+fn memory(caller: &mut Caller<'_, State>) -> wasmtime::Result<Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => wasmtime::bail!("Missing export memory"),
+    }
+}
+
+/// Read the buffer `(ptr, len)` of the memory of the module.
+fn read(caller: &mut Caller<'_, State>, ptr: i32, len: i32) -> wasmtime::Result<Vec<u8>> {
+    let mut buffer = vec![0; usize::try_from(len)?];
+    memory(caller)?.read(&mut *caller, usize::try_from(ptr)?, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Write `bytes` to a buffer allocated with `rig_alloc`, returning its packed offset and length.
+fn write(caller: &mut Caller<'_, State>, bytes: &[u8]) -> wasmtime::Result<i64> {
+    let len = i32::try_from(bytes.len())?;
+    let alloc = match caller.get_export("rig_alloc") {
+        Some(Extern::Func(alloc)) => alloc.typed::<i32, i32>(&*caller)?,
+        _ => wasmtime::bail!("Missing export rig_alloc"),
+    };
+    let ptr = alloc.call(&mut *caller, len)?;
+    memory(caller)?.write(&mut *caller, usize::try_from(ptr)?, bytes)?;
+    Ok(((ptr as i64) << 32) | len as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::tools::wasm::{Capability, WasmTool};
+
+    /// Module of a `ping` tool, whose `rig_call` runs `body` before returning `pong`.
+    fn module(memory_pages: u32, body: &str) -> Vec<u8> {
+        let definition = json!({
+            "name": "ping",
+            "description": "Answer pong",
+            "parameters": {"type": "object"}
+        })
+        .to_string();
+        let result = r#"{"ok":"pong"}"#;
+        let escape = |text: &str| text.replace('"', "\\\"");
+        wat::parse_str(format!(
+            r#"(module
+                (import "rig" "now_ms" (func $now_ms (result i64)))
+                (memory (export "memory") {memory_pages})
+                (global $next (mut i32) (i32.const 1024))
+                (data (i32.const 0) "{definition}")
+                (data (i32.const 512) "{result}")
+                (func (export "rig_alloc") (param $len i32) (result i32)
+                    (local $ptr i32)
+                    (local.set $ptr (global.get $next))
+                    (global.set $next (i32.add (global.get $next) (local.get $len)))
+                    (local.get $ptr))
+                (func (export "rig_definition") (result i64)
+                    (i64.const {definition_len}))
+                (func (export "rig_call") (param i32 i32) (result i64)
+                    {body}
+                    (i64.const {result_packed})))"#,
+            definition = escape(&definition),
+            definition_len = definition.len(),
+            result = escape(result),
+            result_packed = (512i64 << 32) | result.len() as i64,
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wasmtime_engine() {
+        let engine = WasmtimeEngine::new().unwrap();
+        let tool = WasmTool::new(engine.clone(), module(1, "")).unwrap();
+        assert_eq!(tool.definition().name, "ping");
+        assert_eq!(tool.invoke("{}").await.unwrap(), "pong");
+
+        // The imports require their capability
+        let clock = module(1, "(drop (call $now_ms))");
+        let tool = WasmTool::new(engine.clone(), clock.clone()).unwrap();
+        let err = tool.invoke("{}").await.unwrap_err();
+        assert!(
+            matches!(err, WasmToolError::RuntimeError(message) if message.contains("capability"))
+        );
+        let tool = tool.capability(Capability::Clock);
+        assert_eq!(tool.invoke("{}").await.unwrap(), "pong");
+
+        // The calls are limited by the fuel
+        let tool = WasmTool::new(engine.clone(), module(1, "(loop $forever (br $forever))"))
+            .unwrap()
+            .limits(WasmLimits {
+                max_instructions: 1_000_000,
+                ..Default::default()
+            });
+        assert!(matches!(
+            tool.invoke("{}").await,
+            Err(WasmToolError::RuntimeError(_))
+        ));
+
+        // The memory is limited (2048 pages of 64 KiB = 128 MiB)
+        assert!(WasmTool::new(engine, module(2048, "")).is_err());
+    }
+}
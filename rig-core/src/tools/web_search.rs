@@ -0,0 +1,435 @@
+//! Web search tool, returning the title, URL and snippet of the results of a query.
+//!
+//! The [WebSearch] tool searches the web with a [SearchBackend]: [Tavily], [Brave] or
+//! [Serper] (or any other type implementing the trait).
+//!
+//! # Example
+//! ```rust
+//! use rig::tools::web_search::{Tavily, WebSearch};
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("Answer the questions of the user, searching the web when needed.")
+//!     .dyn_tool(WebSearch::new(Tavily::from_env()))
+//!     .build();
+//! ```
+
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    completion::ToolDefinition,
+    tool::{ToolDyn, ToolError},
+    wasm_compat::{WasmBoxedFuture, WasmCompatSend, WasmCompatSync},
+};
+
+/// A result of a web search.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    /// Extract of the page relevant to the query
+    pub snippet: String,
+    /// Publication date of the page, as given by the search engine (if known)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_date: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebSearchError {
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// The search engine returned an error
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+/// Search engine of the [WebSearch] tool.
+pub trait SearchBackend: WasmCompatSend + WasmCompatSync {
+    /// Search the web for `query`, returning at most `max_results` results.
+    fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, WebSearchError>> + WasmCompatSend;
+}
+
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, WebSearchError> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(WebSearchError::ProviderError(format!(
+            "{}: {}",
+            response.status(),
+            response.text().await?
+        )));
+    }
+    Ok(response)
+}
+
+macro_rules! backend_client {
+    ($name:ident, $base_url:expr, $env:literal) => {
+        impl $name {
+            pub fn new(api_key: &str) -> Self {
+                Self {
+                    api_key: api_key.to_string(),
+                    base_url: $base_url.to_string(),
+                    http_client: reqwest::Client::new(),
+                }
+            }
+
+            #[doc = concat!("Create a new client from the `", $env, "` environment variable.")]
+            #[doc = "Panics if the environment variable is not set."]
+            pub fn from_env() -> Self {
+                let api_key = std::env::var($env).expect(concat!($env, " not set"));
+                Self::new(&api_key)
+            }
+
+            /// Use a custom base URL (e.g.: a proxy).
+            pub fn base_url(mut self, base_url: &str) -> Self {
+                self.base_url = base_url.to_string();
+                self
+            }
+
+            /// Use a custom HTTP client for the requests.
+            pub fn with_client(mut self, http_client: reqwest::Client) -> Self {
+                self.http_client = http_client;
+                self
+            }
+        }
+    };
+}
+
+/// [Tavily](https://tavily.com) search API backend.
+#[derive(Clone)]
+pub struct Tavily {
+    api_key: String,
+    base_url: String,
+    http_client: reqwest::Client,
+}
+
+backend_client!(Tavily, "https://api.tavily.com", "TAVILY_API_KEY");
+
+#[derive(Deserialize)]
+struct TavilyResponse {
+    results: Vec<TavilyResult>,
+}
+
+#[derive(Deserialize)]
+struct TavilyResult {
+    title: String,
+    url: String,
+    content: String,
+    #[serde(default)]
+    published_date: Option<String>,
+}
+
+impl From<TavilyResponse> for Vec<SearchResult> {
+    fn from(response: TavilyResponse) -> Self {
+        response
+            .results
+            .into_iter()
+            .map(|result| SearchResult {
+                title: result.title,
+                url: result.url,
+                snippet: result.content,
+                published_date: result.published_date,
+            })
+            .collect()
+    }
+}
+
+impl SearchBackend for Tavily {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Vec<SearchResult>, WebSearchError> {
+        let request = self
+            .http_client
+            .post(format!("{}/search", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&json!({ "query": query, "max_results": max_results }));
+        let response = send(request).await?.json::<TavilyResponse>().await?;
+        Ok(response.into())
+    }
+}
+
+/// [Brave](https://brave.com/search/api) search API backend.
+#[derive(Clone)]
+pub struct Brave {
+    api_key: String,
+    base_url: String,
+    http_client: reqwest::Client,
+}
+
+backend_client!(
+    Brave,
+    "https://api.search.brave.com/res/v1",
+    "BRAVE_API_KEY"
+);
+
+#[derive(Deserialize)]
+struct BraveResponse {
+    #[serde(default)]
+    web: Option<BraveResults>,
+}
+
+#[derive(Deserialize)]
+struct BraveResults {
+    results: Vec<BraveResult>,
+}
+
+#[derive(Deserialize)]
+struct BraveResult {
+    title: String,
+    url: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    page_age: Option<String>,
+}
+
+impl From<BraveResponse> for Vec<SearchResult> {
+    fn from(response: BraveResponse) -> Self {
+        response
+            .web
+            .map(|web| web.results)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|result| SearchResult {
+                title: result.title,
+                url: result.url,
+                snippet: result.description,
+                published_date: result.page_age,
+            })
+            .collect()
+    }
+}
+
+impl SearchBackend for Brave {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Vec<SearchResult>, WebSearchError> {
+        let request = self
+            .http_client
+            .get(format!("{}/web/search", self.base_url))
+            .header("X-Subscription-Token", &self.api_key)
+            .query(&[("q", query), ("count", &max_results.to_string())]);
+        let response = send(request).await?.json::<BraveResponse>().await?;
+        Ok(response.into())
+    }
+}
+
+/// [Serper](https://serper.dev) (Google search) API backend.
+#[derive(Clone)]
+pub struct Serper {
+    api_key: String,
+    base_url: String,
+    http_client: reqwest::Client,
+}
+
+backend_client!(Serper, "https://google.serper.dev", "SERPER_API_KEY");
+
+#[derive(Deserialize)]
+struct SerperResponse {
+    #[serde(default)]
+    organic: Vec<SerperResult>,
+}
+
+#[derive(Deserialize)]
+struct SerperResult {
+    title: String,
+    link: String,
+    #[serde(default)]
+    snippet: String,
+    #[serde(default)]
+    date: Option<String>,
+}
+
+impl From<SerperResponse> for Vec<SearchResult> {
+    fn from(response: SerperResponse) -> Self {
+        response
+            .organic
+            .into_iter()
+            .map(|result| SearchResult {
+                title: result.title,
+                url: result.link,
+                snippet: result.snippet,
+                published_date: result.date,
+            })
+            .collect()
+    }
+}
+
+impl SearchBackend for Serper {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Vec<SearchResult>, WebSearchError> {
+        let request = self
+            .http_client
+            .post(format!("{}/search", self.base_url))
+            .header("X-API-KEY", &self.api_key)
+            .json(&json!({ "q": query, "num": max_results }));
+        let response = send(request).await?.json::<SerperResponse>().await?;
+        Ok(response.into())
+    }
+}
+
+#[derive(Deserialize)]
+struct WebSearchArgs {
+    query: String,
+    #[serde(default)]
+    max_results: Option<usize>,
+}
+
+/// Tool searching the web with a [SearchBackend], named `web_search`.
+///
+/// The tool is a [ToolDyn] (the HTTP requests of the backends are not `Sync`), added to the
+/// agents with [AgentBuilder::dyn_tool](crate::agent::AgentBuilder::dyn_tool).
+pub struct WebSearch<B> {
+    backend: B,
+    max_results: usize,
+}
+
+impl<B: SearchBackend> WebSearch<B> {
+    pub const NAME: &'static str = "web_search";
+
+    /// Create a web search tool returning 5 results by default.
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            max_results: 5,
+        }
+    }
+
+    /// Set the maximum number of results of the searches (the model can ask for fewer).
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results.max(1);
+        self
+    }
+
+    /// Search the web for `query`.
+    pub async fn search(&self, query: &str) -> Result<Vec<SearchResult>, WebSearchError> {
+        self.backend.search(query, self.max_results).await
+    }
+}
+
+impl<B: SearchBackend> ToolDyn for WebSearch<B> {
+    fn name(&self) -> String {
+        Self::NAME.to_string()
+    }
+
+    fn definition(&self, _prompt: String) -> WasmBoxedFuture<'_, ToolDefinition> {
+        Box::pin(async move {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Search the web, returning the title, URL and a snippet of the \
+                    most relevant pages."
+                    .to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "The search query"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": format!(
+                                "The maximum number of results (at most {})",
+                                self.max_results
+                            )
+                        }
+                    },
+                    "required": ["query"]
+                }),
+            }
+        })
+    }
+
+    fn call(&self, args: String) -> WasmBoxedFuture<'_, Result<String, ToolError>> {
+        Box::pin(async move {
+            let args = serde_json::from_str::<WebSearchArgs>(&args)?;
+            let max_results = args
+                .max_results
+                .map_or(self.max_results, |max| max.clamp(1, self.max_results));
+            let results = self
+                .backend
+                .search(&args.query, max_results)
+                .await
+                .map_err(|err| ToolError::ToolCallError(Box::new(err)))?;
+            Ok(serde_json::to_string(&results)?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockBackend;
+
+    impl SearchBackend for MockBackend {
+        async fn search(
+            &self,
+            query: &str,
+            max_results: usize,
+        ) -> Result<Vec<SearchResult>, WebSearchError> {
+            Ok((0..max_results)
+                .map(|i| SearchResult {
+                    title: format!("{query} {i}"),
+                    url: format!("https://example.com/{i}"),
+                    snippet: String::new(),
+                    published_date: None,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_web_search() {
+        let tool = WebSearch::new(MockBackend).max_results(3);
+        let output = tool
+            .call(r#"{"query": "rig", "max_results": 10}"#.to_string())
+            .await
+            .unwrap();
+        let results = serde_json::from_str::<Vec<SearchResult>>(&output).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].title, "rig 0");
+
+        let tavily = serde_json::from_value::<TavilyResponse>(json!({
+            "query": "rig",
+            "results": [{"title": "Rig", "url": "https://rig.rs", "content": "LLM apps", "score": 0.9}]
+        }))
+        .unwrap();
+        let brave = serde_json::from_value::<BraveResponse>(json!({
+            "web": {"results": [{"title": "Rig", "url": "https://rig.rs", "description": "LLM apps"}]}
+        }))
+        .unwrap();
+        let serper = serde_json::from_value::<SerperResponse>(json!({
+            "organic": [{"title": "Rig", "link": "https://rig.rs", "snippet": "LLM apps", "position": 1}]
+        }))
+        .unwrap();
+        for results in [
+            Vec::<SearchResult>::from(tavily),
+            brave.into(),
+            serper.into(),
+        ] {
+            assert_eq!(
+                results,
+                vec![SearchResult {
+                    title: "Rig".to_string(),
+                    url: "https://rig.rs".to_string(),
+                    snippet: "LLM apps".to_string(),
+                    published_date: None,
+                }]
+            );
+        }
+    }
+}
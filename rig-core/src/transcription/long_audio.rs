@@ -0,0 +1,539 @@
+//! Transcription of audio exceeding the limits of the providers (e.g.: 25 MB for OpenAI).
+//!
+//! The audio is split into chunks by an [AudioSplitter], cutting at the quietest point (e.g.:
+//! a pause between sentences) before each limit so that no word is cut. The chunks are
+//! transcribed concurrently, and their transcriptions are stitched together with the
+//! timestamps of the chunks (see [TranscriptionRequestBuilder::send_chunked]).
+//!
+//! The audio is split when it is WAV (PCM or floating point samples). The audio of other
+//! formats is sent whole, and must already meet the limits.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{providers::openai, transcription::{long_audio::AudioSplitter, TranscriptionModel}};
+//!
+//! let model = openai::Client::from_env().transcription_model(openai::WHISPER_1);
+//!
+//! let transcription = model
+//!     .transcription_request()
+//!     .load_file("meeting.wav")
+//!     .send_chunked(AudioSplitter::new().max_duration(Duration::from_secs(600)))
+//!     .await?;
+//!
+//! for segment in transcription.segments {
+//!     println!("[{:?} - {:?}] {}", segment.start, segment.end, segment.text);
+//! }
+//! ```
+
+use std::time::Duration;
+
+use futures::{stream, StreamExt, TryStreamExt};
+
+use super::{
+    TranscriptionError, TranscriptionModel, TranscriptionRequest, TranscriptionRequestBuilder,
+};
+
+/// The duration of the windows over which the loudness of the audio is measured.
+const WINDOW: Duration = Duration::from_millis(10);
+
+/// The size of the header of the WAV chunks (without the format).
+const WAV_HEADER_SIZE: usize = 20;
+
+/// A chunk of audio, encoded in the format of the source audio.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioChunk {
+    pub data: Vec<u8>,
+    /// Offset of the chunk in the source audio
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// The transcription of a chunk of audio.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TranscriptSegment {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+/// The transcription of audio transcribed in chunks.
+#[derive(Debug)]
+pub struct ChunkedTranscription<T> {
+    /// The texts of the chunks, joined
+    pub text: String,
+    /// The transcriptions of the chunks, with their timestamps. Empty if the audio was not
+    /// split (i.e.: it is not WAV), as its duration is unknown.
+    pub segments: Vec<TranscriptSegment>,
+    /// The raw responses of the chunks
+    pub responses: Vec<T>,
+}
+
+/// Splits audio (WAV) into chunks meeting the limits of a provider, at its silences.
+#[derive(Clone, Debug)]
+pub struct AudioSplitter {
+    max_bytes: usize,
+    max_duration: Option<Duration>,
+    search_window: Duration,
+    min_silence: Duration,
+}
+
+impl Default for AudioSplitter {
+    fn default() -> Self {
+        Self {
+            // The 25 MB limit of OpenAI, with a margin for the multipart encoding
+            max_bytes: 24 * 1024 * 1024,
+            max_duration: None,
+            search_window: Duration::from_secs(30),
+            min_silence: Duration::from_millis(200),
+        }
+    }
+}
+
+impl AudioSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum size of the chunks, in bytes (default: 24 MiB).
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Set the maximum duration of the chunks (default: none).
+    pub fn max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Set how far before the limits of the chunks the silences are searched (default: 30
+    /// seconds). At most half of the chunks.
+    pub fn search_window(mut self, search_window: Duration) -> Self {
+        self.search_window = search_window;
+        self
+    }
+
+    /// Set the duration of the silences cut (default: 200 ms). The chunks are cut in the middle
+    /// of the quietest span of this duration.
+    pub fn min_silence(mut self, min_silence: Duration) -> Self {
+        self.min_silence = min_silence;
+        self
+    }
+
+    /// Split the WAV audio `data` into chunks meeting the limits.
+    pub fn split(&self, data: &[u8]) -> Result<Vec<AudioChunk>, TranscriptionError> {
+        let wav = Wav::parse(data)?;
+        let frames = wav.frames();
+
+        let mut max_frames =
+            (self.max_bytes.saturating_sub(wav.header_size()) / wav.block_align).min(frames.max(1));
+        if let Some(max_duration) = self.max_duration {
+            max_frames = max_frames.min(wav.frames_in(max_duration));
+        }
+        if max_frames == 0 {
+            return Err(audio_error("The limits are too small for a frame of audio"));
+        }
+
+        let window = wav.frames_in(WINDOW).max(1);
+        let loudness = wav.loudness(window);
+        let silence_windows = (wav.frames_in(self.min_silence) / window).max(1);
+        let search_frames = wav.frames_in(self.search_window).min(max_frames / 2);
+
+        let mut chunks = vec![];
+        let mut start = 0;
+        while start < frames {
+            let end = if frames - start <= max_frames {
+                frames
+            } else {
+                let limit = start + max_frames;
+                quietest_point(
+                    &loudness,
+                    window,
+                    silence_windows,
+                    limit - search_frames,
+                    limit,
+                )
+                .filter(|end| *end > start)
+                .unwrap_or(limit)
+            };
+            chunks.push(AudioChunk {
+                data: wav.encode(start, end),
+                start: wav.timestamp(start),
+                end: wav.timestamp(end),
+            });
+            start = end;
+        }
+        Ok(chunks)
+    }
+}
+
+impl<M: TranscriptionModel> TranscriptionRequestBuilder<M> {
+    /// Send the transcription request in chunks split by `splitter` (see the
+    /// [module](crate::transcription::long_audio) documentation), transcribing up to 4 chunks
+    /// concurrently. The audio which is not WAV is sent whole.
+    pub async fn send_chunked(
+        self,
+        splitter: AudioSplitter,
+    ) -> Result<ChunkedTranscription<M::Response>, TranscriptionError> {
+        self.send_chunked_concurrently(splitter, 4).await
+    }
+
+    /// Send the transcription request in chunks split by `splitter`, transcribing up to
+    /// `concurrency` chunks concurrently.
+    pub async fn send_chunked_concurrently(
+        self,
+        splitter: AudioSplitter,
+        concurrency: usize,
+    ) -> Result<ChunkedTranscription<M::Response>, TranscriptionError> {
+        if !Wav::is_wav(&self.data) {
+            let response = self.send().await?;
+            return Ok(ChunkedTranscription {
+                text: response.text,
+                segments: vec![],
+                responses: vec![response.response],
+            });
+        }
+
+        let chunks = splitter.split(&self.data)?;
+        let model = self.model.clone();
+        let request = self.build();
+        let stem = request
+            .filename
+            .rsplit_once('.')
+            .map_or(request.filename.as_str(), |(stem, _)| stem);
+
+        let responses = stream::iter(chunks.iter().enumerate())
+            .map(|(i, chunk)| {
+                model.transcription(TranscriptionRequest {
+                    data: chunk.data.clone(),
+                    filename: format!("{stem}_{i}.wav"),
+                    language: request.language.clone(),
+                    prompt: request.prompt.clone(),
+                    temperature: request.temperature,
+                    additional_params: request.additional_params.clone(),
+                })
+            })
+            .buffered(concurrency.max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let segments = chunks
+            .iter()
+            .zip(&responses)
+            .map(|(chunk, response)| TranscriptSegment {
+                start: chunk.start,
+                end: chunk.end,
+                text: response.text.trim().to_string(),
+            })
+            .collect::<Vec<_>>();
+        Ok(ChunkedTranscription {
+            text: segments
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+            segments,
+            responses: responses
+                .into_iter()
+                .map(|response| response.response)
+                .collect(),
+        })
+    }
+}
+
+/// The frame in the middle of the quietest span of `silence_windows` windows (of `window`
+/// frames) between the frames `from` and `to`.
+fn quietest_point(
+    loudness: &[f32],
+    window: usize,
+    silence_windows: usize,
+    from: usize,
+    to: usize,
+) -> Option<usize> {
+    let (first, last) = (from / window, (to / window).min(loudness.len()));
+    if last < first + silence_windows {
+        return None;
+    }
+
+    let mut loudest = f32::MAX;
+    let mut quietest = None;
+    let mut sum: f32 = loudness[first..first + silence_windows].iter().sum();
+    for span in first..=last - silence_windows {
+        if span > first {
+            sum += loudness[span + silence_windows - 1] - loudness[span - 1];
+        }
+        if sum < loudest {
+            loudest = sum;
+            quietest = Some(span);
+        }
+    }
+    quietest.map(|span| (span * window + silence_windows * window / 2).min(to))
+}
+
+/// The encoding of an audio sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SampleFormat {
+    Int,
+    Float,
+}
+
+/// An audio file in the WAV format.
+struct Wav<'a> {
+    /// The `fmt ` chunk
+    format: &'a [u8],
+    samples: &'a [u8],
+    sample_format: SampleFormat,
+    channels: usize,
+    sample_rate: usize,
+    bits_per_sample: usize,
+    block_align: usize,
+}
+
+impl<'a> Wav<'a> {
+    fn is_wav(data: &[u8]) -> bool {
+        data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WAVE"
+    }
+
+    fn parse(data: &'a [u8]) -> Result<Self, TranscriptionError> {
+        if !Self::is_wav(data) {
+            return Err(audio_error("Only WAV audio can be split"));
+        }
+
+        let (mut format, mut samples) = (None, None);
+        let mut offset = 12;
+        while offset + 8 <= data.len() {
+            let id = &data[offset..offset + 4];
+            let size = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap());
+            let body = offset + 8;
+            // The size of the data of streamed files may be unknown
+            let end = body.saturating_add(size as usize).min(data.len());
+            match id {
+                b"fmt " => format = Some(&data[body..end]),
+                b"data" => samples = Some(&data[body..end]),
+                _ => {}
+            }
+            // The chunks are padded to an even size
+            offset = end + (end - body) % 2;
+        }
+
+        let format = format.ok_or_else(|| audio_error("Missing WAV format"))?;
+        let samples = samples.ok_or_else(|| audio_error("Missing WAV data"))?;
+        if format.len() < 16 {
+            return Err(audio_error("Invalid WAV format"));
+        }
+        let field = |offset: usize, size: usize| {
+            format[offset..offset + size]
+                .iter()
+                .rev()
+                .fold(0usize, |value, byte| value << 8 | *byte as usize)
+        };
+
+        let mut tag = field(0, 2);
+        // WAVE_FORMAT_EXTENSIBLE: the format is the start of the sub-format GUID
+        if tag == 0xFFFE && format.len() >= 26 {
+            tag = field(24, 2);
+        }
+        let bits_per_sample = field(14, 2);
+        let sample_format = match (tag, bits_per_sample) {
+            (1, 8 | 16 | 24 | 32) => SampleFormat::Int,
+            (3, 32 | 64) => SampleFormat::Float,
+            _ => {
+                return Err(audio_error(&format!(
+                    "Unsupported WAV encoding (format {tag}, {bits_per_sample} bits)"
+                )))
+            }
+        };
+
+        let wav = Self {
+            format,
+            samples,
+            sample_format,
+            channels: field(2, 2),
+            sample_rate: field(4, 4),
+            bits_per_sample,
+            block_align: field(12, 2),
+        };
+        if wav.channels == 0
+            || wav.sample_rate == 0
+            || wav.block_align != wav.channels * bits_per_sample / 8
+        {
+            return Err(audio_error("Invalid WAV format"));
+        }
+        Ok(wav)
+    }
+
+    fn frames(&self) -> usize {
+        self.samples.len() / self.block_align
+    }
+
+    fn frames_in(&self, duration: Duration) -> usize {
+        (duration.as_secs_f64() * self.sample_rate as f64) as usize
+    }
+
+    fn timestamp(&self, frame: usize) -> Duration {
+        Duration::from_secs_f64(frame as f64 / self.sample_rate as f64)
+    }
+
+    fn header_size(&self) -> usize {
+        WAV_HEADER_SIZE + self.format.len() + self.format.len() % 2 + 8
+    }
+
+    /// The root mean square of the (normalized) samples of each window of `window` frames.
+    fn loudness(&self, window: usize) -> Vec<f32> {
+        let sample_size = self.bits_per_sample / 8;
+        self.samples
+            .chunks(self.block_align * window)
+            .map(|frames| {
+                let samples = frames.chunks_exact(sample_size);
+                let count = samples.len().max(1);
+                let squares = samples
+                    .map(|sample| self.sample(sample).powi(2))
+                    .sum::<f64>();
+                (squares / count as f64).sqrt() as f32
+            })
+            .collect()
+    }
+
+    /// The value of an (little endian) `sample`, from -1 to 1.
+    fn sample(&self, sample: &[u8]) -> f64 {
+        match (self.sample_format, sample.len()) {
+            // 8 bits samples are unsigned
+            (SampleFormat::Int, 1) => (sample[0] as f64 - 128.0) / 128.0,
+            (SampleFormat::Int, 2) => i16::from_le_bytes([sample[0], sample[1]]) as f64 / 32768.0,
+            (SampleFormat::Int, 3) => {
+                i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) as f64 / 2147483648.0
+            }
+            (SampleFormat::Int, _) => {
+                i32::from_le_bytes(sample.try_into().unwrap()) as f64 / 2147483648.0
+            }
+            (SampleFormat::Float, 4) => f32::from_le_bytes(sample.try_into().unwrap()) as f64,
+            (SampleFormat::Float, _) => f64::from_le_bytes(sample.try_into().unwrap()),
+        }
+    }
+
+    /// The WAV file of the frames from `start` to `end`.
+    fn encode(&self, start: usize, end: usize) -> Vec<u8> {
+        let samples = &self.samples[start * self.block_align..end * self.block_align];
+        let padding = self.format.len() % 2;
+        let riff_size = 4 + 8 + self.format.len() + padding + 8 + samples.len();
+
+        let mut data = Vec::with_capacity(riff_size + 8);
+        data.extend(b"RIFF");
+        data.extend((riff_size as u32).to_le_bytes());
+        data.extend(b"WAVEfmt ");
+        data.extend((self.format.len() as u32).to_le_bytes());
+        data.extend(self.format);
+        data.extend(std::iter::repeat_n(0, padding));
+        data.extend(b"data");
+        data.extend((samples.len() as u32).to_le_bytes());
+        data.extend(samples);
+        data
+    }
+}
+
+fn audio_error(message: &str) -> TranscriptionError {
+    TranscriptionError::AudioError(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::TranscriptionResponse;
+
+    const SAMPLE_RATE: usize = 8000;
+
+    /// A 16 bits mono WAV of a tone, with silences from 3.2 to 3.6 and from 7.0 to 7.3 seconds
+    fn speech() -> Vec<u8> {
+        let samples = (0..SAMPLE_RATE * 10)
+            .flat_map(|i| {
+                let time = i as f64 / SAMPLE_RATE as f64;
+                let silent = (3.2..3.6).contains(&time) || (7.0..7.3).contains(&time);
+                let sample = if silent {
+                    0
+                } else {
+                    ((time * 440.0 * std::f64::consts::TAU).sin() * 10000.0) as i16
+                };
+                sample.to_le_bytes()
+            })
+            .collect::<Vec<_>>();
+
+        let mut format = vec![1, 0, 1, 0];
+        format.extend((SAMPLE_RATE as u32).to_le_bytes());
+        format.extend((SAMPLE_RATE as u32 * 2).to_le_bytes());
+        format.extend([2, 0, 16, 0]);
+        Wav {
+            format: &format,
+            samples: &samples,
+            sample_format: SampleFormat::Int,
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            block_align: 2,
+        }
+        .encode(0, samples.len() / 2)
+    }
+
+    /// Transcribes the audio as its duration, in milliseconds
+    #[derive(Clone)]
+    struct DurationModel;
+
+    impl TranscriptionModel for DurationModel {
+        type Response = usize;
+
+        async fn transcription(
+            &self,
+            request: TranscriptionRequest,
+        ) -> Result<TranscriptionResponse<usize>, TranscriptionError> {
+            let wav = Wav::parse(&request.data)?;
+            let duration = wav.timestamp(wav.frames()).as_millis() as usize;
+            Ok(TranscriptionResponse {
+                text: format!("{duration}ms "),
+                response: duration,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chunked_transcription() {
+        let splitter = AudioSplitter::new().max_duration(Duration::from_secs(4));
+        let chunks = splitter.split(&speech()).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert!((3.2..3.6).contains(&chunks[1].start.as_secs_f64()));
+        assert!((7.0..7.3).contains(&chunks[2].start.as_secs_f64()));
+        assert_eq!(chunks[2].end, Duration::from_secs(10));
+
+        let transcription = DurationModel
+            .transcription_request()
+            .data(speech())
+            .filename(Some("speech.wav".to_string()))
+            .send_chunked(splitter)
+            .await
+            .unwrap();
+        assert_eq!(transcription.responses.iter().sum::<usize>(), 10000);
+        assert_eq!(transcription.segments.len(), 3);
+        assert_eq!(transcription.segments[1].start, chunks[1].start);
+        assert_eq!(
+            transcription.text,
+            transcription
+                .responses
+                .iter()
+                .map(|duration| format!("{duration}ms"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+
+        // Limited by size
+        let chunks = AudioSplitter::new()
+            .max_bytes(16_000 * 3)
+            .split(&speech())
+            .unwrap();
+        assert!(chunks.iter().all(|chunk| chunk.data.len() <= 16_000 * 3));
+
+        assert!(matches!(
+            AudioSplitter::new().split(b"ID3 not a wav"),
+            Err(TranscriptionError::AudioError(_))
+        ));
+    }
+}
@@ -7,6 +7,9 @@ use std::{fs, path::Path};
 use thiserror::Error;
 
 use crate::json_utils;
+use crate::wasm_compat::WasmCompatSend;
+
+pub mod long_audio;
 
 // Errors
 #[derive(Debug, Error)]
@@ -30,6 +33,10 @@ pub enum TranscriptionError {
     /// Error returned by the transcription model provider
     #[error("ProviderError: {0}")]
     ProviderError(String),
+
+    /// Error decoding or splitting the audio
+    #[error("AudioError: {0}")]
+    AudioError(String),
 }
 
 /// Trait defining a low-level LLM transcription interface
@@ -47,7 +54,7 @@ pub trait Transcription<M: TranscriptionModel> {
         filename: &str,
         data: &[u8],
     ) -> impl std::future::Future<Output = Result<TranscriptionRequestBuilder<M>, TranscriptionError>>
-           + Send;
+           + WasmCompatSend;
 }
 
 /// General transcription response struct that contains the transcription text
@@ -70,7 +77,7 @@ pub trait TranscriptionModel: Clone + Send + Sync {
         request: TranscriptionRequest,
     ) -> impl std::future::Future<
         Output = Result<TranscriptionResponse<Self::Response>, TranscriptionError>,
-    > + Send;
+    > + WasmCompatSend;
 
     /// Generates a transcription request builder for the given `file`
     fn transcription_request(&self) -> TranscriptionRequestBuilder<Self> {
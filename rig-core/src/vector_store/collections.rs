@@ -0,0 +1,313 @@
+//! Provider-agnostic management of the collections (a.k.a. namespaces, tables or indexes) of
+//! vector stores.
+//!
+//! The [CollectionManager] trait creates, lists and drops collections configured with a
+//! [CollectionConfig] (number of dimensions and [DistanceMetric]), so that setup scripts do not
+//! depend on the SDK of each backend. It is implemented by [InMemoryCollections] and by the
+//! vector store integrations (e.g.: `rig-qdrant`, `rig-postgres`).
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     providers::openai,
+//!     vector_store::collections::{CollectionConfig, CollectionManager, DistanceMetric},
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! // `collections` is any CollectionManager, e.g.: rig_qdrant::QdrantCollections
+//! if !collections.collection_exists("documents").await? {
+//!     collections
+//!         .create_collection(
+//!             CollectionConfig::for_model("documents", &model).distance(DistanceMetric::Cosine),
+//!         )
+//!         .await?;
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{in_memory_store::InMemoryVectorStore, VectorStoreError};
+use crate::embeddings::{distance::VectorDistance, Embedding, EmbeddingModel};
+use crate::wasm_compat::WasmCompatSend;
+
+/// Distance metric used to compare the embeddings of a collection or an index.
+///
+/// The scores returned by the vector store indexes are normalized to a common 0-1 scale, where
+/// higher is more similar (see [DistanceMetric::normalize]), so that score thresholds are
+/// portable across metrics and backends. For unit-norm embeddings (as returned by most
+/// embedding models), all the metrics give the same normalized score: `(1 + cosine) / 2`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    /// Cosine similarity (recommended for most text embedding models)
+    #[default]
+    Cosine,
+    /// Dot product (inner product) of the embeddings
+    DotProduct,
+    /// Euclidean (L2) distance
+    Euclidean,
+}
+
+impl DistanceMetric {
+    /// Get the raw value of the metric for two embeddings, as returned by most backends: the
+    /// cosine similarity, the dot product or the euclidean distance.
+    pub fn raw(&self, a: &Embedding, b: &Embedding) -> f64 {
+        match self {
+            DistanceMetric::Cosine => a.cosine_similarity(b, false),
+            DistanceMetric::DotProduct => a.dot_product(b),
+            DistanceMetric::Euclidean => a.euclidean_distance(b),
+        }
+    }
+
+    /// Normalize a raw value of the metric (see [DistanceMetric::raw]) to a score between 0 and
+    /// 1, higher being more similar:
+    /// - cosine similarity `s` and dot product `s`: `(1 + s) / 2`
+    /// - euclidean distance `d`: `1 - d² / 4`
+    ///
+    /// The dot product and the euclidean distance are normalized assuming unit-norm embeddings,
+    /// and the scores are clamped to `[0, 1]`.
+    pub fn normalize(&self, raw: f64) -> f64 {
+        let score = match self {
+            DistanceMetric::Cosine | DistanceMetric::DotProduct => (1.0 + raw) / 2.0,
+            DistanceMetric::Euclidean => 1.0 - raw.powi(2) / 4.0,
+        };
+
+        if score.is_nan() {
+            0.0
+        } else {
+            score.clamp(0.0, 1.0)
+        }
+    }
+
+    /// Get the normalized score of two embeddings for the metric.
+    pub fn score(&self, a: &Embedding, b: &Embedding) -> f64 {
+        self.normalize(self.raw(a, b))
+    }
+}
+
+/// Configuration of a collection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollectionConfig {
+    pub name: String,
+    /// Number of dimensions of the embeddings
+    pub ndims: usize,
+    pub distance: DistanceMetric,
+}
+
+impl CollectionConfig {
+    /// Create the configuration of a collection of embeddings of `ndims` dimensions, using the
+    /// cosine similarity.
+    pub fn new(name: impl Into<String>, ndims: usize) -> Self {
+        Self {
+            name: name.into(),
+            ndims,
+            distance: DistanceMetric::default(),
+        }
+    }
+
+    /// Create the configuration of a collection of embeddings of the given model.
+    pub fn for_model<M: EmbeddingModel>(name: impl Into<String>, model: &M) -> Self {
+        Self::new(name, model.ndims())
+    }
+
+    /// Set the distance metric of the collection (default: [DistanceMetric::Cosine]).
+    pub fn distance(mut self, distance: DistanceMetric) -> Self {
+        self.distance = distance;
+        self
+    }
+}
+
+/// Trait for managing the collections of a vector store backend.
+pub trait CollectionManager: Send + Sync {
+    /// Create a collection. Creating a collection that already exists does nothing.
+    fn create_collection(
+        &self,
+        config: CollectionConfig,
+    ) -> impl Future<Output = Result<(), VectorStoreError>> + WasmCompatSend;
+
+    /// List the names of the collections.
+    fn list_collections(
+        &self,
+    ) -> impl Future<Output = Result<Vec<String>, VectorStoreError>> + WasmCompatSend;
+
+    /// Drop a collection and all its documents. Dropping a missing collection does nothing.
+    fn drop_collection(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = Result<(), VectorStoreError>> + WasmCompatSend;
+
+    /// Check whether a collection exists.
+    fn collection_exists(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = Result<bool, VectorStoreError>> + WasmCompatSend {
+        async move {
+            Ok(self
+                .list_collections()
+                .await?
+                .iter()
+                .any(|collection| collection == name))
+        }
+    }
+}
+
+type Collections<D> = HashMap<String, (CollectionConfig, InMemoryVectorStore<D>)>;
+
+/// In-memory collections of [InMemoryVectorStore]s, e.g.: for tests and prototypes of code
+/// using a [CollectionManager].
+#[derive(Clone)]
+pub struct InMemoryCollections<D: Serialize> {
+    collections: Arc<RwLock<Collections<D>>>,
+}
+
+impl<D: Serialize> Default for InMemoryCollections<D> {
+    fn default() -> Self {
+        Self {
+            collections: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+impl<D: Serialize + Clone> InMemoryCollections<D> {
+    /// Get the configuration of a collection.
+    pub fn config(&self, name: &str) -> Option<CollectionConfig> {
+        self.collections
+            .read()
+            .expect("Lock poisoned")
+            .get(name)
+            .map(|(config, _)| config.clone())
+    }
+
+    /// Get a copy of the store of a collection.
+    pub fn store(&self, name: &str) -> Option<InMemoryVectorStore<D>> {
+        self.collections
+            .read()
+            .expect("Lock poisoned")
+            .get(name)
+            .map(|(_, store)| store.clone())
+    }
+
+    /// Replace the store of an existing collection (e.g.: after adding documents to its copy).
+    pub fn set_store(
+        &self,
+        name: &str,
+        store: InMemoryVectorStore<D>,
+    ) -> Result<(), VectorStoreError> {
+        match self
+            .collections
+            .write()
+            .expect("Lock poisoned")
+            .get_mut(name)
+        {
+            Some((_, existing)) => {
+                *existing = store;
+                Ok(())
+            }
+            None => Err(VectorStoreError::DatastoreError(
+                format!("Collection {name} does not exist").into(),
+            )),
+        }
+    }
+}
+
+impl<D: Serialize + Send + Sync> CollectionManager for InMemoryCollections<D> {
+    async fn create_collection(&self, config: CollectionConfig) -> Result<(), VectorStoreError> {
+        self.collections
+            .write()
+            .expect("Lock poisoned")
+            .entry(config.name.clone())
+            .or_insert_with(|| (config, InMemoryVectorStore::default()));
+        Ok(())
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        let mut names = self
+            .collections
+            .read()
+            .expect("Lock poisoned")
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        self.collections
+            .write()
+            .expect("Lock poisoned")
+            .remove(name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_collections() {
+        let collections = InMemoryCollections::<String>::default();
+
+        collections
+            .create_collection(CollectionConfig::new("b", 3).distance(DistanceMetric::Euclidean))
+            .await
+            .unwrap();
+        collections
+            .create_collection(CollectionConfig::new("a", 2))
+            .await
+            .unwrap();
+        // Already exists: the configuration is unchanged
+        collections
+            .create_collection(CollectionConfig::new("b", 4))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            collections.list_collections().await.unwrap(),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            collections.config("b"),
+            Some(CollectionConfig::new("b", 3).distance(DistanceMetric::Euclidean))
+        );
+
+        collections.drop_collection("a").await.unwrap();
+        collections.drop_collection("missing").await.unwrap();
+
+        assert!(!collections.collection_exists("a").await.unwrap());
+        assert!(collections.collection_exists("b").await.unwrap());
+    }
+
+    #[test]
+    fn test_normalized_scores() {
+        let embedding = |vec: Vec<f64>| Embedding {
+            document: String::new(),
+            vec,
+        };
+        let a = embedding(vec![0.6, 0.8]);
+        let b = embedding(vec![1.0, 0.0]);
+
+        // (1 + 0.6) / 2 for the 3 metrics since the embeddings are unit-norm
+        for metric in [
+            DistanceMetric::Cosine,
+            DistanceMetric::DotProduct,
+            DistanceMetric::Euclidean,
+        ] {
+            assert!((metric.score(&a, &b) - 0.8).abs() < 1e-9, "{metric:?}");
+            assert!((metric.score(&a, &a) - 1.0).abs() < 1e-9, "{metric:?}");
+        }
+
+        assert_eq!(DistanceMetric::Cosine.normalize(-1.0), 0.0);
+        assert_eq!(DistanceMetric::DotProduct.normalize(3.0), 1.0);
+        assert_eq!(DistanceMetric::Euclidean.normalize(5.0), 0.0);
+    }
+}
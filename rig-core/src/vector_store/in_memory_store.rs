@@ -2,25 +2,75 @@
 use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap},
+    time::Duration,
 };
 
+use futures::{stream, TryStreamExt};
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
+use web_time::SystemTime;
 
-use super::{VectorStoreError, VectorStoreIndex};
+use super::{
+    quantized_store::QuantizedVectorStore, top_n_filtered_from_stream, validate_ndims,
+    DistanceMetric, ExpiringVectorStore, MetadataFilter, VectorStoreError, VectorStoreIndex,
+    VectorStoreWriter,
+};
+use crate::wasm_compat::WasmBoxedStream;
 use crate::{
-    embeddings::{distance::VectorDistance, Embedding, EmbeddingModel},
+    embeddings::{simd, Embedding, EmbeddingModel, Quantization},
     OneOrMany,
 };
 
 /// [InMemoryVectorStore] is a simple in-memory vector store that stores embeddings
 /// in-memory using a HashMap.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct InMemoryVectorStore<D: Serialize> {
     /// The embeddings are stored in a HashMap.
     /// Hashmap key is the document id.
     /// Hashmap value is a tuple of the serializable document and its corresponding embeddings.
     embeddings: HashMap<String, (D, OneOrMany<Embedding>)>,
+    /// Expiry and tombstones of the documents written with [ExpiringVectorStore] methods.
+    lifecycles: HashMap<String, Lifecycle>,
+}
+
+/// Expiry and tombstone of a document of an [InMemoryVectorStore].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Lifecycle {
+    expires_at: Option<SystemTime>,
+    deleted: bool,
+}
+
+impl Lifecycle {
+    fn is_live(&self, now: SystemTime) -> bool {
+        !self.deleted && !matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+}
+
+impl<D: Serialize> Default for InMemoryVectorStore<D> {
+    fn default() -> Self {
+        Self::from_embeddings(HashMap::new())
+    }
+}
+
+impl<D: Serialize> InMemoryVectorStore<D> {
+    fn from_embeddings(embeddings: HashMap<String, (D, OneOrMany<Embedding>)>) -> Self {
+        Self {
+            embeddings,
+            lifecycles: HashMap::new(),
+        }
+    }
+
+    /// Insert a document, replacing the document with the same id along with its expiry and
+    /// tombstone.
+    fn insert(&mut self, id: String, doc: D, embeddings: OneOrMany<Embedding>) {
+        self.lifecycles.remove(&id);
+        self.embeddings.insert(id, (doc, embeddings));
+    }
+
+    /// Check whether the document with the given id is neither expired nor soft-deleted.
+    fn is_live(&self, id: &str, now: SystemTime) -> bool {
+        !matches!(self.lifecycles.get(id), Some(lifecycle) if !lifecycle.is_live(now))
+    }
 }
 
 impl<D: Serialize + Eq> InMemoryVectorStore<D> {
@@ -36,7 +86,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
                 store.insert(format!("doc{i}"), (doc, embeddings));
             });
 
-        Self { embeddings: store }
+        Self::from_embeddings(store)
     }
 
     /// Create a new [InMemoryVectorStore] from documents and and their corresponding embeddings with ids.
@@ -48,7 +98,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
             store.insert(i.to_string(), (doc, embeddings));
         });
 
-        Self { embeddings: store }
+        Self::from_embeddings(store)
     }
 
     /// Create a new [InMemoryVectorStore] from documents and their corresponding embeddings.
@@ -62,46 +112,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
             store.insert(f(&doc), (doc, embeddings));
         });
 
-        Self { embeddings: store }
-    }
-
-    /// Implement vector search on [InMemoryVectorStore].
-    /// To be used by implementations of [VectorStoreIndex::top_n] and [VectorStoreIndex::top_n_ids] methods.
-    fn vector_search(&self, prompt_embedding: &Embedding, n: usize) -> EmbeddingRanking<D> {
-        // Sort documents by best embedding distance
-        let mut docs = BinaryHeap::new();
-
-        for (id, (doc, embeddings)) in self.embeddings.iter() {
-            // Get the best context for the document given the prompt
-            if let Some((distance, embed_doc)) = embeddings
-                .iter()
-                .map(|embedding| {
-                    (
-                        OrderedFloat(embedding.cosine_similarity(prompt_embedding, false)),
-                        &embedding.document,
-                    )
-                })
-                .max_by(|a, b| a.0.cmp(&b.0))
-            {
-                docs.push(Reverse(RankingItem(distance, id, doc, embed_doc)));
-            };
-
-            // If the heap size exceeds n, pop the least old element.
-            if docs.len() > n {
-                docs.pop();
-            }
-        }
-
-        // Log selected tools with their distances
-        tracing::info!(target: "rig",
-            "Selected documents: {}",
-            docs.iter()
-                .map(|Reverse(RankingItem(distance, id, _, _))| format!("{} ({})", id, distance))
-                .collect::<Vec<String>>()
-                .join(", ")
-        );
-
-        docs
+        Self::from_embeddings(store)
     }
 
     /// Add documents and their corresponding embeddings to the store.
@@ -116,8 +127,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
             .into_iter()
             .enumerate()
             .for_each(|(index, (doc, embeddings))| {
-                self.embeddings
-                    .insert(format!("doc{}", index + current_index), (doc, embeddings));
+                self.insert(format!("doc{}", index + current_index), doc, embeddings);
             });
     }
 
@@ -127,7 +137,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
         documents: impl IntoIterator<Item = (impl ToString, D, OneOrMany<Embedding>)>,
     ) {
         documents.into_iter().for_each(|(id, doc, embeddings)| {
-            self.embeddings.insert(id.to_string(), (doc, embeddings));
+            self.insert(id.to_string(), doc, embeddings);
         });
     }
 
@@ -140,11 +150,18 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
     ) {
         for (doc, embeddings) in documents {
             let id = f(&doc);
-            self.embeddings.insert(id, (doc, embeddings));
+            self.insert(id, doc, embeddings);
         }
     }
 
-    /// Get the document by its id and deserialize it into the given type.
+    /// Remove the document with the given id, returning it with its embeddings.
+    pub fn remove_document(&mut self, id: &str) -> Option<(D, OneOrMany<Embedding>)> {
+        self.lifecycles.remove(id);
+        self.embeddings.remove(id)
+    }
+
+    /// Get the document by its id and deserialize it into the given type. Expired and
+    /// soft-deleted documents are not returned.
     pub fn get_document<T: for<'a> Deserialize<'a>>(
         &self,
         id: &str,
@@ -152,6 +169,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
         Ok(self
             .embeddings
             .get(id)
+            .filter(|_| self.is_live(id, SystemTime::now()))
             .map(|(doc, _)| serde_json::from_str(&serde_json::to_string(doc)?))
             .transpose()?)
     }
@@ -175,11 +193,156 @@ impl<D: Serialize + Eq> PartialOrd for RankingItem<'_, D> {
 
 type EmbeddingRanking<'a, D> = BinaryHeap<Reverse<RankingItem<'a, D>>>;
 
+/// Scores embeddings against a query embedding with the normalized score of a metric, using
+/// the vectorized kernels of [simd] and computing the norm of the query once per search.
+struct QueryScorer<'a> {
+    metric: DistanceMetric,
+    query: &'a [f64],
+    query_norm: f64,
+}
+
+impl<'a> QueryScorer<'a> {
+    fn new(metric: DistanceMetric, query: &'a Embedding) -> Self {
+        Self {
+            metric,
+            query: &query.vec,
+            query_norm: simd::squared_norm(&query.vec).sqrt(),
+        }
+    }
+
+    fn score(&self, embedding: &Embedding) -> f64 {
+        let raw = match self.metric {
+            DistanceMetric::Cosine => {
+                simd::dot(&embedding.vec, self.query)
+                    / (simd::squared_norm(&embedding.vec).sqrt() * self.query_norm)
+            }
+            DistanceMetric::DotProduct => simd::dot(&embedding.vec, self.query),
+            DistanceMetric::Euclidean => simd::squared_distance(&embedding.vec, self.query).sqrt(),
+        };
+
+        self.metric.normalize(raw)
+    }
+}
+
+/// Get the best context (i.e.: embedding) of a document given the prompt.
+fn rank_document<'a, D: Serialize>(
+    scorer: &QueryScorer,
+    id: &'a String,
+    doc: &'a D,
+    embeddings: &'a OneOrMany<Embedding>,
+) -> Option<RankingItem<'a, D>> {
+    embeddings
+        .iter()
+        .map(|embedding| (OrderedFloat(scorer.score(embedding)), &embedding.document))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(distance, embed_doc)| RankingItem(distance, id, doc, embed_doc))
+}
+
+/// Push an item in a ranking, keeping only its `n` best items.
+fn push_bounded<'a, D: Serialize + Eq>(
+    mut ranking: EmbeddingRanking<'a, D>,
+    item: RankingItem<'a, D>,
+    n: usize,
+) -> EmbeddingRanking<'a, D> {
+    ranking.push(Reverse(item));
+    if ranking.len() > n {
+        ranking.pop();
+    }
+    ranking
+}
+
+impl<D: Serialize + Eq + Sync> InMemoryVectorStore<D> {
+    /// Implement vector search on [InMemoryVectorStore], scoring the documents with the
+    /// normalized score of the given metric.
+    /// To be used by implementations of [VectorStoreIndex::top_n] and [VectorStoreIndex::top_n_ids] methods.
+    ///
+    /// With the `rayon` feature, the documents are scored in parallel, each thread keeping its
+    /// own top `n` ranking before the rankings are merged.
+    fn vector_search(
+        &self,
+        prompt_embedding: &Embedding,
+        n: usize,
+        metric: DistanceMetric,
+    ) -> EmbeddingRanking<'_, D> {
+        let scorer = QueryScorer::new(metric, prompt_embedding);
+        let now = SystemTime::now();
+
+        // Sort documents by best embedding distance
+        #[cfg(not(feature = "rayon"))]
+        let docs = self
+            .embeddings
+            .iter()
+            .filter(|(id, _)| self.is_live(id, now))
+            .filter_map(|(id, (doc, embeddings))| rank_document(&scorer, id, doc, embeddings))
+            .fold(BinaryHeap::new(), |ranking, item| {
+                push_bounded(ranking, item, n)
+            });
+
+        #[cfg(feature = "rayon")]
+        let docs = {
+            use rayon::prelude::*;
+
+            self.embeddings
+                .par_iter()
+                .filter(|(id, _)| self.is_live(id, now))
+                .filter_map(|(id, (doc, embeddings))| rank_document(&scorer, id, doc, embeddings))
+                .fold(BinaryHeap::new, |ranking, item| {
+                    push_bounded(ranking, item, n)
+                })
+                .reduce(BinaryHeap::new, |ranking, other| {
+                    other.into_iter().fold(ranking, |ranking, Reverse(item)| {
+                        push_bounded(ranking, item, n)
+                    })
+                })
+        };
+
+        // Log selected tools with their distances
+        tracing::info!(target: "rig",
+            "Selected documents: {}",
+            docs.iter()
+                .map(|Reverse(RankingItem(distance, id, _, _))| format!("{} ({})", id, distance))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+
+        docs
+    }
+}
+
 impl<D: Serialize> InMemoryVectorStore<D> {
     pub fn index<M: EmbeddingModel>(self, model: M) -> InMemoryVectorIndex<M, D> {
         InMemoryVectorIndex::new(model, self)
     }
 
+    /// Same as [InMemoryVectorStore::index], but checks that the stored embeddings have the
+    /// dimensions of the embedding model.
+    pub fn try_index<M: EmbeddingModel>(
+        self,
+        model: M,
+    ) -> Result<InMemoryVectorIndex<M, D>, VectorStoreError> {
+        InMemoryVectorIndex::try_new(model, self)
+    }
+
+    /// Convert the store into a [QuantizedVectorStore], dropping the full precision embeddings.
+    pub fn quantize(self, quantization: Quantization) -> QuantizedVectorStore<D> {
+        let mut store = QuantizedVectorStore::new(quantization);
+        store.add_documents_with_ids(
+            self.embeddings
+                .into_iter()
+                .map(|(id, (doc, embeddings))| (id, doc, embeddings)),
+        );
+        store
+    }
+
+    /// The number of dimensions of the stored embeddings, if the store is not empty.
+    pub fn ndims(&self) -> Option<usize> {
+        self.embeddings
+            .values()
+            .next()
+            .and_then(|(_, embeddings)| embeddings.iter().next())
+            .map(|embedding| embedding.vec.len())
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&String, &(D, OneOrMany<Embedding>))> {
         self.embeddings.iter()
     }
@@ -193,14 +356,155 @@ impl<D: Serialize> InMemoryVectorStore<D> {
     }
 }
 
+/// Version of the format of the snapshots of [InMemoryVectorStore::save].
+#[cfg(feature = "bincode")]
+const SNAPSHOT_VERSION: u32 = 2;
+
+/// Document of a snapshot: id, document, embeddings, expiry (in milliseconds since the UNIX
+/// epoch) and tombstone.
+#[cfg(feature = "bincode")]
+type SnapshotDocument<D> = (String, D, Vec<Embedding>, Option<u64>, bool);
+
+#[cfg(feature = "bincode")]
+impl<D: Serialize> InMemoryVectorStore<D> {
+    /// Save a snapshot of the store (documents, ids, embeddings, expiries and tombstones) in
+    /// bincode format.
+    ///
+    /// The documents must be serializable by bincode, i.e.: their [Serialize] and [Deserialize]
+    /// implementations must not rely on self-describing formats (e.g.: `serde_json::Value` or
+    /// `#[serde(untagged)]` enums).
+    pub fn save(&self, writer: impl std::io::Write) -> Result<(), VectorStoreError> {
+        let mut writer = std::io::BufWriter::new(writer);
+
+        let documents = self
+            .embeddings
+            .iter()
+            .map(|(id, (doc, embeddings))| {
+                let lifecycle = self.lifecycles.get(id).copied().unwrap_or_default();
+                let expires_at = lifecycle.expires_at.map(|expires_at| {
+                    expires_at
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_millis() as u64
+                });
+
+                (
+                    id,
+                    doc,
+                    embeddings.iter().collect::<Vec<_>>(),
+                    expires_at,
+                    lifecycle.deleted,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        bincode::serialize_into(&mut writer, &SNAPSHOT_VERSION)?;
+        bincode::serialize_into(&mut writer, &documents)?;
+
+        std::io::Write::flush(&mut writer)?;
+        Ok(())
+    }
+
+    /// Save a snapshot of the store to the file at `path` (see [InMemoryVectorStore::save]).
+    /// The snapshot is written to a temporary file first, so that an existing snapshot is not
+    /// corrupted if saving fails.
+    pub fn save_to_path(&self, path: impl AsRef<std::path::Path>) -> Result<(), VectorStoreError> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        self.save(std::fs::File::create(&tmp_path)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Load a store from a snapshot saved with [InMemoryVectorStore::save].
+    pub fn load(reader: impl std::io::Read) -> Result<Self, VectorStoreError>
+    where
+        D: for<'a> Deserialize<'a>,
+    {
+        let mut reader = std::io::BufReader::new(reader);
+
+        let documents: Vec<SnapshotDocument<D>> = match bincode::deserialize_from(&mut reader)? {
+            // Snapshots without expiries and tombstones
+            1u32 => bincode::deserialize_from::<_, Vec<(String, D, Vec<Embedding>)>>(&mut reader)?
+                .into_iter()
+                .map(|(id, doc, embeddings)| (id, doc, embeddings, None, false))
+                .collect(),
+            SNAPSHOT_VERSION => bincode::deserialize_from(&mut reader)?,
+            version => {
+                return Err(VectorStoreError::DatastoreError(
+                    format!("Unsupported snapshot version: {version}").into(),
+                ))
+            }
+        };
+
+        let mut store = Self::default();
+        for (id, doc, embeddings, expires_at, deleted) in documents {
+            let embeddings = OneOrMany::many(embeddings).map_err(|_| {
+                VectorStoreError::DatastoreError(format!("Document {id} has no embeddings").into())
+            })?;
+
+            let lifecycle = Lifecycle {
+                expires_at: expires_at
+                    .map(|millis| SystemTime::UNIX_EPOCH + Duration::from_millis(millis)),
+                deleted,
+            };
+            if lifecycle != Lifecycle::default() {
+                store.lifecycles.insert(id.clone(), lifecycle);
+            }
+            store.embeddings.insert(id, (doc, embeddings));
+        }
+
+        Ok(store)
+    }
+
+    /// Load a store from the snapshot file at `path` (see [InMemoryVectorStore::save_to_path]).
+    pub fn load_from_path(path: impl AsRef<std::path::Path>) -> Result<Self, VectorStoreError>
+    where
+        D: for<'a> Deserialize<'a>,
+    {
+        Self::load(std::fs::File::open(path)?)
+    }
+}
+
 pub struct InMemoryVectorIndex<M: EmbeddingModel, D: Serialize> {
     model: M,
     pub store: InMemoryVectorStore<D>,
+    distance: DistanceMetric,
 }
 
 impl<M: EmbeddingModel, D: Serialize> InMemoryVectorIndex<M, D> {
     pub fn new(model: M, store: InMemoryVectorStore<D>) -> Self {
-        Self { model, store }
+        Self {
+            model,
+            store,
+            distance: DistanceMetric::default(),
+        }
+    }
+
+    /// Set the distance metric used to score the documents (default: [DistanceMetric::Cosine]).
+    pub fn distance(mut self, distance: DistanceMetric) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    /// Create an index, checking that the stored embeddings have the dimensions of the
+    /// embedding model (e.g.: after changing the model or its output dimensions).
+    pub fn try_new(model: M, store: InMemoryVectorStore<D>) -> Result<Self, VectorStoreError> {
+        store
+            .iter()
+            .flat_map(|(_, (_, embeddings))| embeddings.iter())
+            .try_for_each(|embedding| validate_ndims(model.ndims(), embedding))?;
+
+        Ok(Self::new(model, store))
+    }
+
+    /// Embed the query, checking that the query embedding has the dimensions of the stored embeddings.
+    async fn embed_query(&self, query: &str) -> Result<Embedding, VectorStoreError> {
+        let embedding = self.model.embed_text(query).await?;
+        validate_ndims(self.store.ndims().unwrap_or(0), &embedding)?;
+        Ok(embedding)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&String, &(D, OneOrMany<Embedding>))> {
@@ -224,9 +528,9 @@ impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> VectorStoreIndex
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let prompt_embedding = &self.model.embed_text(query).await?;
+        let prompt_embedding = &self.embed_query(query).await?;
 
-        let docs = self.store.vector_search(prompt_embedding, n);
+        let docs = self.store.vector_search(prompt_embedding, n, self.distance);
 
         // Return n best
         docs.into_iter()
@@ -248,25 +552,290 @@ impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> VectorStoreIndex
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String)>, VectorStoreError> {
-        let prompt_embedding = &self.model.embed_text(query).await?;
+        let prompt_embedding = &self.embed_query(query).await?;
 
-        let docs = self.store.vector_search(prompt_embedding, n);
+        let docs = self.store.vector_search(prompt_embedding, n, self.distance);
 
         // Return n best
         docs.into_iter()
             .map(|Reverse(RankingItem(distance, id, _, _))| Ok((distance.0, id.clone())))
             .collect::<Result<Vec<_>, _>>()
     }
+
+    async fn top_n_with_offset<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        offset: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let prompt_embedding = &self.embed_query(query).await?;
+
+        let docs = self
+            .store
+            .vector_search(prompt_embedding, offset + n, self.distance);
+
+        docs.into_sorted_vec()
+            .into_iter()
+            .skip(offset)
+            .map(|Reverse(RankingItem(distance, id, doc, _))| {
+                Ok((
+                    distance.0,
+                    id.clone(),
+                    serde_json::from_str(&serde_json::to_string(doc)?)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Embed the query and rank all the documents once, then deserialize the documents lazily.
+    fn top_n_stream<'a, T: for<'b> Deserialize<'b> + Send + 'a>(
+        &'a self,
+        query: &'a str,
+        _page_size: usize,
+    ) -> WasmBoxedStream<'a, Result<(f64, String, T), VectorStoreError>> {
+        Box::pin(
+            stream::once(async move {
+                let prompt_embedding = self.embed_query(query).await?;
+                let docs = self
+                    .store
+                    .vector_search(&prompt_embedding, self.store.len(), self.distance)
+                    .into_sorted_vec();
+
+                Ok::<_, VectorStoreError>(stream::iter(docs.into_iter().map(
+                    |Reverse(RankingItem(distance, id, doc, _))| {
+                        Ok((
+                            distance.0,
+                            id.clone(),
+                            serde_json::from_str(&serde_json::to_string(doc)?)?,
+                        ))
+                    },
+                )))
+            })
+            .try_flatten(),
+        )
+    }
+
+    /// Embed the query and rank all the documents once, then filter them.
+    async fn top_n_filtered<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &MetadataFilter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        top_n_filtered_from_stream(self, query, n, filter).await
+    }
+}
+
+impl<D: Serialize + Eq + Send + Sync> VectorStoreWriter<D> for InMemoryVectorStore<D> {
+    async fn upsert(
+        &mut self,
+        documents: Vec<(String, D, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.add_documents_with_ids(documents);
+        Ok(())
+    }
+
+    async fn delete_by_id(&mut self, id: &str) -> Result<(), VectorStoreError> {
+        self.remove_document(id);
+        Ok(())
+    }
+
+    async fn get_by_id<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        id: &str,
+    ) -> Result<Option<T>, VectorStoreError> {
+        self.get_document(id)
+    }
+}
+
+impl<D: Serialize + Eq + Send + Sync> ExpiringVectorStore<D> for InMemoryVectorStore<D> {
+    async fn upsert_with_ttl(
+        &mut self,
+        documents: Vec<(String, D, OneOrMany<Embedding>)>,
+        ttl: Duration,
+    ) -> Result<(), VectorStoreError> {
+        let expires_at = SystemTime::now() + ttl;
+
+        for (id, doc, embeddings) in documents {
+            self.insert(id.clone(), doc, embeddings);
+            self.lifecycles.insert(
+                id,
+                Lifecycle {
+                    expires_at: Some(expires_at),
+                    deleted: false,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn soft_delete(&mut self, id: &str) -> Result<(), VectorStoreError> {
+        if self.embeddings.contains_key(id) {
+            self.lifecycles.entry(id.to_string()).or_default().deleted = true;
+        }
+        Ok(())
+    }
+
+    async fn restore(&mut self, id: &str) -> Result<bool, VectorStoreError> {
+        Ok(match self.lifecycles.get_mut(id) {
+            Some(lifecycle) if lifecycle.deleted => {
+                lifecycle.deleted = false;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn purge(&mut self) -> Result<usize, VectorStoreError> {
+        let now = SystemTime::now();
+        let purged = self
+            .lifecycles
+            .iter()
+            .filter(|(_, lifecycle)| !lifecycle.is_live(now))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+
+        for id in &purged {
+            self.remove_document(id);
+        }
+
+        Ok(purged.len())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::cmp::Reverse;
+    use std::{cmp::Reverse, time::Duration};
 
-    use crate::{embeddings::embedding::Embedding, OneOrMany};
+    use futures::TryStreamExt;
+
+    use crate::{
+        embeddings::{embedding::Embedding, EmbeddingError, EmbeddingModel},
+        vector_store::{
+            DistanceMetric, ExpiringVectorStore, VectorStoreError, VectorStoreIndex,
+            VectorStoreWriter,
+        },
+        OneOrMany,
+    };
 
     use super::{InMemoryVectorStore, RankingItem};
 
+    #[derive(Clone)]
+    struct MockModel(usize);
+
+    impl EmbeddingModel for MockModel {
+        const MAX_DOCUMENTS: usize = 10;
+
+        fn ndims(&self) -> usize {
+            self.0
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|document| Embedding {
+                    document,
+                    vec: vec![1.0; self.0],
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expiry_and_soft_delete() {
+        let embedding = |document: &str| {
+            OneOrMany::one(Embedding {
+                document: document.to_string(),
+                vec: vec![1.0, 0.5],
+            })
+        };
+
+        let mut store = InMemoryVectorStore::default();
+        store
+            .upsert(vec![("a".to_string(), "a".to_string(), embedding("a"))])
+            .await
+            .unwrap();
+        store
+            .upsert_with_ttl(
+                vec![("b".to_string(), "b".to_string(), embedding("b"))],
+                Duration::ZERO,
+            )
+            .await
+            .unwrap();
+        store
+            .upsert_with_ttl(
+                vec![("c".to_string(), "c".to_string(), embedding("c"))],
+                Duration::from_secs(3600),
+            )
+            .await
+            .unwrap();
+        store.soft_delete("a").await.unwrap();
+
+        // "a" is soft-deleted and "b" is expired
+        assert_eq!(store.get_by_id::<String>("a").await.unwrap(), None);
+        assert_eq!(store.get_by_id::<String>("b").await.unwrap(), None);
+        let index = store.clone().index(MockModel(2));
+        let ids = index.top_n_ids("query", 3).await.unwrap();
+        assert_eq!(
+            ids.into_iter().map(|(_, id)| id).collect::<Vec<_>>(),
+            vec!["c"]
+        );
+
+        assert!(store.restore("a").await.unwrap());
+        assert!(!store.restore("c").await.unwrap());
+        assert_eq!(
+            store.get_by_id::<String>("a").await.unwrap(),
+            Some("a".to_string())
+        );
+
+        store.soft_delete("c").await.unwrap();
+        assert_eq!(store.purge().await.unwrap(), 2);
+        assert_eq!(store.len(), 1);
+
+        // Upserting a document again resets its expiry
+        store
+            .upsert(vec![("a".to_string(), "a2".to_string(), embedding("a"))])
+            .await
+            .unwrap();
+        assert_eq!(store.purge().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let vector_store = InMemoryVectorStore::from_documents_with_ids((0..5).map(|i| {
+            (
+                format!("doc{i}"),
+                i,
+                OneOrMany::one(Embedding {
+                    document: format!("doc{i}"),
+                    vec: vec![1.0, i as f64 / 3.0],
+                }),
+            )
+        }));
+        let index = vector_store.index(MockModel(2));
+
+        let page = index
+            .top_n_with_offset::<usize>("query", 2, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            page.into_iter().map(|(_, _, doc)| doc).collect::<Vec<_>>(),
+            vec![4, 2]
+        );
+
+        let documents = index
+            .top_n_stream::<usize>("query", 2)
+            .map_ok(|(_, id, _)| id)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(documents, vec!["doc3", "doc4", "doc2", "doc1", "doc0"]);
+    }
+
     #[test]
     fn test_auto_ids() {
         let mut vector_store = InMemoryVectorStore::from_documents(vec![
@@ -405,6 +974,7 @@ mod tests {
                 vec: vec![0.0, 0.1, 0.6],
             },
             1,
+            DistanceMetric::Cosine,
         );
 
         assert_eq!(
@@ -419,7 +989,7 @@ mod tests {
                 })
                 .collect::<Vec<(_, _, String)>>(),
             vec![(
-                0.9807965956109156,
+                0.9903982978054577,
                 "doc1".to_string(),
                 "glarb-garb".to_string()
             )]
@@ -482,6 +1052,7 @@ mod tests {
                 vec: vec![0.0, 0.1, 0.6],
             },
             1,
+            DistanceMetric::Cosine,
         );
 
         assert_eq!(
@@ -496,10 +1067,152 @@ mod tests {
                 })
                 .collect::<Vec<(_, _, String)>>(),
             vec![(
-                0.9807965956109156,
+                0.9903982978054577,
                 "doc1".to_string(),
                 "glarb-garb".to_string()
             )]
         )
     }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn test_save_and_load() {
+        let vector_store = InMemoryVectorStore::from_documents_with_ids(vec![
+            (
+                "doc1",
+                "glarb-garb".to_string(),
+                OneOrMany::one(Embedding {
+                    document: "glarb-garb".to_string(),
+                    vec: vec![0.1, 0.1, 0.5],
+                }),
+            ),
+            (
+                "doc2",
+                "marble-marble".to_string(),
+                OneOrMany::many(vec![
+                    Embedding {
+                        document: "marble".to_string(),
+                        vec: vec![0.7, -0.3, 0.0],
+                    },
+                    Embedding {
+                        document: "marble 2".to_string(),
+                        vec: vec![0.6, -0.2, 0.1],
+                    },
+                ])
+                .unwrap(),
+            ),
+        ]);
+
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("store.bin");
+        vector_store.save_to_path(&path).unwrap();
+
+        let loaded = InMemoryVectorStore::<String>::load_from_path(&path).unwrap();
+        assert_eq!(loaded.embeddings, vector_store.embeddings);
+
+        assert!(matches!(
+            InMemoryVectorStore::<String>::load(&[3, 0, 0, 0][..]),
+            Err(VectorStoreError::DatastoreError(_))
+        ));
+    }
+
+    #[test]
+    fn test_vector_search_top_n() {
+        let embedding = |i: usize| Embedding {
+            document: format!("doc{i}"),
+            vec: (0..20)
+                .map(|j| ((i * 7 + j * 3) % 11) as f64 - 5.0)
+                .collect(),
+        };
+        let vector_store = InMemoryVectorStore::from_documents(
+            (0..200).map(|i| (format!("doc{i}"), OneOrMany::one(embedding(i)))),
+        );
+        let query = embedding(1000);
+
+        for metric in [DistanceMetric::Cosine, DistanceMetric::Euclidean] {
+            let mut expected = (0..200)
+                .map(|i| metric.score(&embedding(i), &query))
+                .collect::<Vec<_>>();
+            expected.sort_by(|a, b| b.total_cmp(a));
+
+            let ranking = vector_store
+                .vector_search(&query, 10, metric)
+                .into_sorted_vec()
+                .into_iter()
+                .map(|Reverse(RankingItem(distance, _, _, _))| distance.0)
+                .collect::<Vec<_>>();
+
+            assert_eq!(ranking.len(), 10);
+            for (score, expected) in ranking.iter().zip(&expected) {
+                assert!((score - expected).abs() < 1e-9, "{metric:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dimension_validation() {
+        let vector_store = InMemoryVectorStore::from_documents_with_ids(vec![(
+            "doc1",
+            "glarb-garb",
+            OneOrMany::one(Embedding {
+                document: "glarb-garb".to_string(),
+                vec: vec![0.1, 0.1, 0.5],
+            }),
+        )]);
+        assert_eq!(vector_store.ndims(), Some(3));
+
+        assert!(matches!(
+            vector_store.clone().try_index(MockModel(2)),
+            Err(VectorStoreError::DimensionMismatch {
+                expected: 2,
+                actual: 3
+            })
+        ));
+        assert!(vector_store.clone().try_index(MockModel(3)).is_ok());
+
+        let index = vector_store.index(MockModel(2));
+        assert!(matches!(
+            index.top_n_ids("glarb", 1).await,
+            Err(VectorStoreError::DimensionMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_writer() {
+        let embedding = |document: &str| {
+            OneOrMany::one(Embedding {
+                document: document.to_string(),
+                vec: vec![0.1, 0.2],
+            })
+        };
+
+        let mut store = InMemoryVectorStore::default();
+        store
+            .upsert(vec![
+                ("a".to_string(), "glarb".to_string(), embedding("glarb")),
+                ("b".to_string(), "marble".to_string(), embedding("marble")),
+            ])
+            .await
+            .unwrap();
+        store
+            .upsert(vec![(
+                "a".to_string(),
+                "flumb".to_string(),
+                embedding("flumb"),
+            )])
+            .await
+            .unwrap();
+        store.delete_by_id("b").await.unwrap();
+        store.delete_by_id("missing").await.unwrap();
+
+        assert_eq!(store.len(), 1);
+        assert_eq!(
+            store.get_by_id::<String>("a").await.unwrap(),
+            Some("flumb".to_string())
+        );
+        assert_eq!(store.get_by_id::<String>("b").await.unwrap(), None);
+    }
 }
@@ -1,10 +1,19 @@
-use futures::future::BoxFuture;
+use futures::{stream, StreamExt, TryStreamExt};
+use std::sync::Arc;
+
 use serde::Deserialize;
 use serde_json::Value;
 
-use crate::embeddings::EmbeddingError;
+use crate::wasm_compat::{WasmBoxedFuture, WasmBoxedStream, WasmCompatSend};
+use crate::{
+    embeddings::{Embedding, EmbeddingError},
+    OneOrMany,
+};
 
+pub mod collections;
+pub use collections::DistanceMetric;
 pub mod in_memory_store;
+pub mod quantized_store;
 
 #[derive(Debug, thiserror::Error)]
 pub enum VectorStoreError {
@@ -20,6 +29,72 @@ pub enum VectorStoreError {
 
     #[error("Missing Id: {0}")]
     MissingIdError(String),
+
+    #[error("Io error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Error while saving or loading a snapshot of a store
+    #[cfg(feature = "bincode")]
+    #[error("Bincode error: {0}")]
+    BincodeError(#[from] bincode::Error),
+
+    /// The embedding model and the stored embeddings have different dimensions
+    #[error("Dimension mismatch: expected embeddings of {expected} dimensions, got {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+}
+
+/// Check that an embedding has the expected number of dimensions (e.g.: the dimensions of the
+/// embeddings of an index). An expected number of dimensions of 0 (e.g.: for models unknown
+/// to rig) matches any embedding.
+pub fn validate_ndims(expected: usize, embedding: &Embedding) -> Result<(), VectorStoreError> {
+    match embedding.vec.len() {
+        actual if expected != 0 && actual != expected => {
+            Err(VectorStoreError::DimensionMismatch { expected, actual })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Filter on the metadata of the documents of a query, keeping the documents whose field `field`
+/// equals `value` (e.g.: the documents of a tenant).
+///
+/// `field` is either the name of a top-level field of the documents, or a JSON pointer to a
+/// nested field (e.g.: `/metadata/tenant`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetadataFilter {
+    pub field: String,
+    pub value: Value,
+}
+
+impl MetadataFilter {
+    pub fn eq(field: &str, value: impl Into<Value>) -> Self {
+        Self {
+            field: field.to_string(),
+            value: value.into(),
+        }
+    }
+
+    /// Whether the document passes the filter
+    pub fn matches(&self, document: &Value) -> bool {
+        let field = if self.field.starts_with('/') {
+            document.pointer(&self.field)
+        } else {
+            document.get(&self.field)
+        };
+        field == Some(&self.value)
+    }
+
+    /// The path of the field: its name, or the (unescaped) reference tokens of its JSON pointer,
+    /// e.g.: to translate the filter to the filters of a backend.
+    pub fn path(&self) -> Vec<String> {
+        match self.field.strip_prefix('/') {
+            Some(pointer) => pointer
+                .split('/')
+                .map(|token| token.replace("~1", "/").replace("~0", "~"))
+                .collect(),
+            None => vec![self.field.clone()],
+        }
+    }
 }
 
 /// Trait for vector store indexes
@@ -30,26 +105,222 @@ pub trait VectorStoreIndex: Send + Sync {
         &self,
         query: &str,
         n: usize,
-    ) -> impl std::future::Future<Output = Result<Vec<(f64, String, T)>, VectorStoreError>> + Send;
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String, T)>, VectorStoreError>>
+           + WasmCompatSend;
 
     /// Same as `top_n` but returns the document ids only.
     fn top_n_ids(
         &self,
         query: &str,
         n: usize,
-    ) -> impl std::future::Future<Output = Result<Vec<(f64, String)>, VectorStoreError>> + Send;
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String)>, VectorStoreError>> + WasmCompatSend;
+
+    /// Same as `top_n`, but skips the `offset` best documents (i.e.: returns the documents
+    /// ranked `offset..offset + n`).
+    ///
+    /// The default implementation fetches the top `offset + n` documents and drops the first
+    /// `offset` ones. Backends supporting offsets natively should override it.
+    fn top_n_with_offset<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        offset: usize,
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String, T)>, VectorStoreError>>
+           + WasmCompatSend {
+        async move {
+            let mut results = self.top_n::<T>(query, offset + n).await?;
+            results.sort_by(|(a, _, _), (b, _, _)| b.total_cmp(a));
+            Ok(results.into_iter().skip(offset).collect())
+        }
+    }
+
+    /// Stream the documents by decreasing score, lazily fetching them by pages of `page_size`
+    /// documents (e.g.: to pull additional candidates for reranking until enough of them
+    /// pass a filter).
+    ///
+    /// The default implementation fetches the pages with [VectorStoreIndex::top_n_with_offset].
+    /// Backends should override it to run the query (and embed it) only once.
+    fn top_n_stream<'a, T: for<'b> Deserialize<'b> + Send + 'a>(
+        &'a self,
+        query: &'a str,
+        page_size: usize,
+    ) -> WasmBoxedStream<'a, Result<(f64, String, T), VectorStoreError>> {
+        let page_size = page_size.max(1);
+
+        Box::pin(
+            stream::try_unfold(Some(0), move |offset| async move {
+                let Some(offset) = offset else {
+                    return Ok::<_, VectorStoreError>(None);
+                };
+
+                let page = self
+                    .top_n_with_offset::<T>(query, page_size, offset)
+                    .await?;
+                // The last page is the first incomplete page
+                let next_offset = (page.len() == page_size).then_some(offset + page_size);
+
+                Ok(Some((stream::iter(page.into_iter().map(Ok)), next_offset)))
+            })
+            .try_flatten(),
+        )
+    }
+
+    /// Same as `top_n`, but only returns the documents passing `filter`.
+    ///
+    /// The default implementation filters the results of [VectorStoreIndex::top_n], fetching
+    /// 4 times more documents (and embedding the query again) until it gets `n` of them, possibly
+    /// scanning the whole index for selective filters. Backends supporting metadata filters or
+    /// namespaces natively should override it, or use [top_n_filtered_from_stream] if their
+    /// [VectorStoreIndex::top_n_stream] embeds the query only once.
+    fn top_n_filtered<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &MetadataFilter,
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String, T)>, VectorStoreError>>
+           + WasmCompatSend {
+        async move {
+            if n == 0 {
+                return Ok(vec![]);
+            }
+
+            let mut limit = n.max(10);
+            loop {
+                let mut results = self.top_n::<Value>(query, limit).await?;
+                let exhausted = results.len() < limit;
+                results.sort_by(|(a, _, _), (b, _, _)| b.total_cmp(a));
+                let results = results
+                    .into_iter()
+                    .filter(|(_, _, doc)| filter.matches(doc))
+                    .take(n)
+                    .collect::<Vec<_>>();
+
+                if results.len() == n || exhausted {
+                    return deserialize_results(results);
+                }
+                limit = limit.saturating_mul(4);
+            }
+        }
+    }
+}
+
+/// Get the top `n` documents of `index` passing `filter`, filtering the documents of
+/// [VectorStoreIndex::top_n_stream] until it gets `n` of them.
+///
+/// For the implementations of [VectorStoreIndex::top_n_filtered] by the backends whose stream
+/// embeds the query only once (e.g.: for the filters they do not support natively).
+pub async fn top_n_filtered_from_stream<I, T>(
+    index: &I,
+    query: &str,
+    n: usize,
+    filter: &MetadataFilter,
+) -> Result<Vec<(f64, String, T)>, VectorStoreError>
+where
+    I: VectorStoreIndex + ?Sized,
+    T: for<'a> Deserialize<'a> + Send,
+{
+    if n == 0 {
+        return Ok(vec![]);
+    }
+    let results = index
+        .top_n_stream::<Value>(query, n.max(10))
+        .try_filter(|(_, _, doc)| futures::future::ready(filter.matches(doc)))
+        .take(n)
+        .try_collect::<Vec<_>>()
+        .await?;
+    deserialize_results(results)
+}
+
+/// Trait for writing documents of type `D` to vector stores.
+///
+/// While [VectorStoreIndex] abstracts the querying of vector stores, this trait abstracts their
+/// writes, so that ingestion code (e.g.: [Ingestion](crate::ingest::Ingestion)) is portable
+/// across store backends. Documents are identified by the ids given on insertion.
+pub trait VectorStoreWriter<D>: Send + Sync {
+    /// Insert the documents with their ids and embeddings, replacing the documents (and all
+    /// their embeddings) with the same ids.
+    fn upsert(
+        &mut self,
+        documents: Vec<(String, D, OneOrMany<Embedding>)>,
+    ) -> impl std::future::Future<Output = Result<(), VectorStoreError>> + WasmCompatSend;
+
+    /// Delete the document with the given id. Deleting a missing document is not an error.
+    fn delete_by_id(
+        &mut self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<(), VectorStoreError>> + WasmCompatSend;
+
+    /// Get the document with the given id and deserialize it into the given type.
+    fn get_by_id<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<Option<T>, VectorStoreError>> + WasmCompatSend;
+}
+
+/// Extension of [VectorStoreWriter] for stores supporting the expiry (TTL) and the soft deletion
+/// (tombstones) of documents, e.g.: so that caches of ephemeral knowledge (session scratch data,
+/// ...) age out automatically.
+///
+/// Expired and soft-deleted documents are excluded from the searches of the store and from
+/// [VectorStoreWriter::get_by_id]. They are only removed from the store by
+/// [ExpiringVectorStore::purge], so soft-deleted documents can be restored until then.
+pub trait ExpiringVectorStore<D>: VectorStoreWriter<D> {
+    /// Same as [VectorStoreWriter::upsert], but the documents expire after `ttl`.
+    fn upsert_with_ttl(
+        &mut self,
+        documents: Vec<(String, D, OneOrMany<Embedding>)>,
+        ttl: std::time::Duration,
+    ) -> impl std::future::Future<Output = Result<(), VectorStoreError>> + WasmCompatSend;
+
+    /// Soft delete the document with the given id. Deleting a missing document is not an error.
+    fn soft_delete(
+        &mut self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<(), VectorStoreError>> + WasmCompatSend;
+
+    /// Restore a soft-deleted document, returning whether the document was soft-deleted.
+    /// Restoring an expired document does not extend its expiry.
+    fn restore(
+        &mut self,
+        id: &str,
+    ) -> impl std::future::Future<Output = Result<bool, VectorStoreError>> + WasmCompatSend;
+
+    /// Remove the expired and soft-deleted documents, returning the number of removed documents.
+    fn purge(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<usize, VectorStoreError>> + WasmCompatSend;
 }
 
 pub type TopNResults = Result<Vec<(f64, String, Value)>, VectorStoreError>;
 
 pub trait VectorStoreIndexDyn: Send + Sync {
-    fn top_n<'a>(&'a self, query: &'a str, n: usize) -> BoxFuture<'a, TopNResults>;
+    fn top_n<'a>(&'a self, query: &'a str, n: usize) -> WasmBoxedFuture<'a, TopNResults>;
 
     fn top_n_ids<'a>(
         &'a self,
         query: &'a str,
         n: usize,
-    ) -> BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>>;
+    ) -> WasmBoxedFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>>;
+
+    fn top_n_with_offset<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        offset: usize,
+    ) -> WasmBoxedFuture<'a, TopNResults>;
+
+    fn top_n_stream<'a>(
+        &'a self,
+        query: &'a str,
+        page_size: usize,
+    ) -> WasmBoxedStream<'a, Result<(f64, String, Value), VectorStoreError>>;
+
+    fn top_n_filtered<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        filter: &'a MetadataFilter,
+    ) -> WasmBoxedFuture<'a, TopNResults>;
 }
 
 impl<I: VectorStoreIndex> VectorStoreIndexDyn for I {
@@ -57,7 +328,7 @@ impl<I: VectorStoreIndex> VectorStoreIndexDyn for I {
         &'a self,
         query: &'a str,
         n: usize,
-    ) -> BoxFuture<'a, Result<Vec<(f64, String, Value)>, VectorStoreError>> {
+    ) -> WasmBoxedFuture<'a, Result<Vec<(f64, String, Value)>, VectorStoreError>> {
         Box::pin(async move {
             Ok(self
                 .top_n::<serde_json::Value>(query, n)
@@ -72,9 +343,125 @@ impl<I: VectorStoreIndex> VectorStoreIndexDyn for I {
         &'a self,
         query: &'a str,
         n: usize,
-    ) -> BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>> {
+    ) -> WasmBoxedFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>> {
         Box::pin(self.top_n_ids(query, n))
     }
+
+    fn top_n_with_offset<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        offset: usize,
+    ) -> WasmBoxedFuture<'a, TopNResults> {
+        Box::pin(async move {
+            Ok(self
+                .top_n_with_offset::<serde_json::Value>(query, n, offset)
+                .await?
+                .into_iter()
+                .map(|(score, id, doc)| (score, id, prune_document(doc).unwrap_or_default()))
+                .collect::<Vec<_>>())
+        })
+    }
+
+    fn top_n_stream<'a>(
+        &'a self,
+        query: &'a str,
+        page_size: usize,
+    ) -> WasmBoxedStream<'a, Result<(f64, String, Value), VectorStoreError>> {
+        Box::pin(
+            self.top_n_stream::<serde_json::Value>(query, page_size)
+                .map_ok(|(score, id, doc)| (score, id, prune_document(doc).unwrap_or_default())),
+        )
+    }
+
+    fn top_n_filtered<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        filter: &'a MetadataFilter,
+    ) -> WasmBoxedFuture<'a, TopNResults> {
+        Box::pin(async move {
+            Ok(self
+                .top_n_filtered::<serde_json::Value>(query, n, filter)
+                .await?
+                .into_iter()
+                .map(|(score, id, doc)| (score, id, prune_document(doc).unwrap_or_default()))
+                .collect::<Vec<_>>())
+        })
+    }
+}
+
+/// A type-erased [VectorStoreIndex], cheap to clone, e.g.: to store heterogeneous indexes in
+/// collections and choose them at runtime.
+///
+/// Like the dynamic context of the agents, the documents are pruned of their large arrays (e.g.:
+/// embeddings) before being deserialized.
+#[derive(Clone)]
+pub struct BoxVectorIndex(Arc<dyn VectorStoreIndexDyn>);
+
+impl BoxVectorIndex {
+    pub fn new(index: impl VectorStoreIndex + 'static) -> Self {
+        Self(Arc::new(index))
+    }
+}
+
+fn deserialize_results<T: for<'a> Deserialize<'a>>(
+    results: Vec<(f64, String, Value)>,
+) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+    results
+        .into_iter()
+        .map(|(score, id, doc)| Ok((score, id, serde_json::from_value(doc)?)))
+        .collect()
+}
+
+impl VectorStoreIndex for BoxVectorIndex {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        deserialize_results(VectorStoreIndexDyn::top_n(&*self.0, query, n).await?)
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        VectorStoreIndexDyn::top_n_ids(&*self.0, query, n).await
+    }
+
+    async fn top_n_with_offset<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        offset: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        deserialize_results(
+            VectorStoreIndexDyn::top_n_with_offset(&*self.0, query, n, offset).await?,
+        )
+    }
+
+    fn top_n_stream<'a, T: for<'b> Deserialize<'b> + Send + 'a>(
+        &'a self,
+        query: &'a str,
+        page_size: usize,
+    ) -> WasmBoxedStream<'a, Result<(f64, String, T), VectorStoreError>> {
+        Box::pin(
+            VectorStoreIndexDyn::top_n_stream(&*self.0, query, page_size).and_then(
+                |(score, id, doc)| async move { Ok((score, id, serde_json::from_value(doc)?)) },
+            ),
+        )
+    }
+
+    async fn top_n_filtered<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &MetadataFilter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        deserialize_results(VectorStoreIndexDyn::top_n_filtered(&*self.0, query, n, filter).await?)
+    }
 }
 
 fn prune_document(document: serde_json::Value) -> Option<serde_json::Value> {
@@ -99,3 +486,220 @@ fn prune_document(document: serde_json::Value) -> Option<serde_json::Value> {
         Value::Null => Some(Value::Null),
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Index of 20 documents `{"rank": i, "tenant": ...}` ranked by increasing `i`, of the tenant
+    /// `acme` for the multiples of 3 and `globex` for the others.
+    pub struct TenantIndex;
+
+    impl VectorStoreIndex for TenantIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            (0..n.min(20))
+                .map(|i| {
+                    let tenant = if i % 3 == 0 { "acme" } else { "globex" };
+                    let doc = serde_json::json!({"rank": i, "tenant": tenant});
+                    Ok((
+                        1.0 / (i + 1) as f64,
+                        i.to_string(),
+                        serde_json::from_value(doc)?,
+                    ))
+                })
+                .collect()
+        }
+
+        async fn top_n_ids(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Ok((0..n.min(20))
+                .map(|i| (1.0 / (i + 1) as f64, i.to_string()))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_top_n_filtered() {
+        let filter = MetadataFilter::eq("tenant", "acme");
+        let results = VectorStoreIndex::top_n_filtered::<Value>(&TenantIndex, "", 4, &filter)
+            .await
+            .unwrap();
+        assert_eq!(
+            results.into_iter().map(|(_, id, _)| id).collect::<Vec<_>>(),
+            ["0", "3", "6", "9"]
+        );
+
+        // All the documents of the tenant, when fewer than `n`
+        let results = VectorStoreIndexDyn::top_n_filtered(&TenantIndex, "", 100, &filter)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 7);
+
+        let filter = MetadataFilter::eq("/tenant", "initech");
+        assert!(
+            VectorStoreIndexDyn::top_n_filtered(&TenantIndex, "", 5, &filter)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    /// Index of the documents `{"i": i}` for `i` in `0..len`, ranked by increasing `i`, counting
+    /// its queries.
+    struct CountingIndex {
+        len: usize,
+        queries: std::sync::atomic::AtomicUsize,
+    }
+
+    impl VectorStoreIndex for CountingIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            self.queries
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            (0..self.len.min(n))
+                .map(|i| {
+                    let doc = serde_json::json!({ "i": i });
+                    Ok((
+                        1.0 / (i + 1) as f64,
+                        i.to_string(),
+                        serde_json::from_value(doc)?,
+                    ))
+                })
+                .collect()
+        }
+
+        async fn top_n_ids(
+            &self,
+            _query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_top_n_filtered_queries() {
+        let index = CountingIndex {
+            len: 10_000,
+            queries: Default::default(),
+        };
+
+        // The limit grows geometrically: 10, 40, ..., 10240 documents
+        let filter = MetadataFilter::eq("/i", 9_999);
+        let results = VectorStoreIndex::top_n_filtered::<Value>(&index, "", 1, &filter)
+            .await
+            .unwrap();
+        assert_eq!(results[0].1, "9999");
+        assert_eq!(index.queries.into_inner(), 6);
+
+        assert_eq!(MetadataFilter::eq("tenant", 1).path(), ["tenant"]);
+        assert_eq!(
+            MetadataFilter::eq("/metadata/a~1b", 1).path(),
+            ["metadata", "a/b"]
+        );
+    }
+
+    /// Index of the documents `0..len`, ranked by increasing value.
+    struct RangeIndex(usize);
+
+    impl VectorStoreIndex for RangeIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            (0..self.0.min(n))
+                .map(|i| {
+                    Ok((
+                        1.0 / (i + 1) as f64,
+                        i.to_string(),
+                        serde_json::from_value(i.into())?,
+                    ))
+                })
+                .collect()
+        }
+
+        async fn top_n_ids(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Ok((0..self.0.min(n))
+                .map(|i| (1.0 / (i + 1) as f64, i.to_string()))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_pagination() {
+        let index = RangeIndex(5);
+
+        let page = VectorStoreIndex::top_n_with_offset::<usize>(&index, "", 2, 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            page.into_iter().map(|(_, _, doc)| doc).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+
+        for page_size in [1, 2, 5, 10] {
+            let documents = VectorStoreIndex::top_n_stream::<usize>(&index, "", page_size)
+                .map_ok(|(_, _, doc)| doc)
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+            assert_eq!(documents, vec![0, 1, 2, 3, 4]);
+        }
+
+        // The stream is lazy: only the pages of the pulled documents are fetched
+        let documents = VectorStoreIndexDyn::top_n_stream(&index, "", 2)
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(documents.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_box_vector_index() {
+        let indexes = [
+            BoxVectorIndex::new(RangeIndex(5)),
+            BoxVectorIndex::new(RangeIndex(1)),
+        ];
+
+        let results = VectorStoreIndex::top_n::<usize>(&indexes[0], "", 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            results
+                .into_iter()
+                .map(|(_, _, doc)| doc)
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            VectorStoreIndex::top_n_ids(&indexes[1], "", 2)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let docs = VectorStoreIndex::top_n_stream::<usize>(&indexes[0], "", 2)
+            .map_ok(|(_, _, doc)| doc)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(docs, vec![0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,383 @@
+//! In-memory vector store with quantized embeddings.
+//!
+//! The [QuantizedVectorStore] stores the embeddings of the documents quantized (see
+//! [Quantization]), cutting the memory footprint of large local indexes by 8x (int8) to 64x
+//! (binary) compared to the [InMemoryVectorStore](super::in_memory_store::InMemoryVectorStore).
+//!
+//! Optionally, the full precision embeddings can be kept to re-score the top candidates of the
+//! quantized search exactly, which recovers most of the retrieval quality lost to quantization.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     embeddings::{EmbeddingsBuilder, Quantization},
+//!     providers::openai,
+//!     vector_store::quantized_store::QuantizedVectorStore,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! let embeddings = EmbeddingsBuilder::new(model.clone())
+//!     .documents(documents)?
+//!     .build()
+//!     .await?;
+//!
+//! // Binary quantized search, re-scoring the top `4 * n` candidates exactly
+//! let mut store = QuantizedVectorStore::new(Quantization::Binary).rescore(4);
+//! store.add_documents(embeddings);
+//!
+//! let index = store.index(model);
+//! ```
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use super::{DistanceMetric, VectorStoreError, VectorStoreIndex, VectorStoreWriter};
+use crate::{
+    embeddings::{Embedding, EmbeddingModel, Quantization, QuantizedVector},
+    OneOrMany,
+};
+
+struct QuantizedDocument<D> {
+    document: D,
+    vectors: Vec<QuantizedVector>,
+    /// Full precision embeddings, if kept for re-scoring
+    embeddings: Option<OneOrMany<Embedding>>,
+}
+
+/// [QuantizedVectorStore] is an in-memory vector store that stores quantized embeddings.
+pub struct QuantizedVectorStore<D: Serialize> {
+    quantization: Quantization,
+    /// Oversampling factor of the candidates re-scored with full precision embeddings
+    rescore: Option<usize>,
+    documents: HashMap<String, QuantizedDocument<D>>,
+}
+
+impl<D: Serialize> QuantizedVectorStore<D> {
+    /// Create an empty store using the given quantization scheme.
+    pub fn new(quantization: Quantization) -> Self {
+        Self {
+            quantization,
+            rescore: None,
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Keep the full precision embeddings of the documents added afterwards, and re-score
+    /// exactly the top `n * oversampling` candidates of the quantized search.
+    pub fn rescore(mut self, oversampling: usize) -> Self {
+        self.rescore = Some(oversampling.max(1));
+        self
+    }
+
+    /// Add documents and their corresponding embeddings to the store.
+    /// Ids are automatically generated have will have the form `"doc{n}"` where `n`
+    /// is the index of the document.
+    pub fn add_documents(
+        &mut self,
+        documents: impl IntoIterator<Item = (D, OneOrMany<Embedding>)>,
+    ) {
+        let current_index = self.documents.len();
+        self.add_documents_with_ids(documents.into_iter().enumerate().map(
+            |(index, (doc, embeddings))| (format!("doc{}", index + current_index), doc, embeddings),
+        ));
+    }
+
+    /// Add documents and their corresponding embeddings to the store with ids.
+    pub fn add_documents_with_ids(
+        &mut self,
+        documents: impl IntoIterator<Item = (impl ToString, D, OneOrMany<Embedding>)>,
+    ) {
+        for (id, document, embeddings) in documents {
+            let vectors = embeddings
+                .iter()
+                .map(|embedding| self.quantization.quantize(&embedding.vec))
+                .collect();
+
+            self.documents.insert(
+                id.to_string(),
+                QuantizedDocument {
+                    document,
+                    vectors,
+                    embeddings: self.rescore.map(|_| embeddings),
+                },
+            );
+        }
+    }
+
+    /// Get the document by its id and deserialize it into the given type.
+    pub fn get_document<T: for<'a> Deserialize<'a>>(
+        &self,
+        id: &str,
+    ) -> Result<Option<T>, VectorStoreError> {
+        Ok(self
+            .documents
+            .get(id)
+            .map(|doc| serde_json::from_str(&serde_json::to_string(&doc.document)?))
+            .transpose()?)
+    }
+
+    /// Remove the document with the given id, returning it.
+    pub fn remove_document(&mut self, id: &str) -> Option<D> {
+        self.documents.remove(id).map(|doc| doc.document)
+    }
+
+    /// Iterate over the ids and documents of the store.
+    pub fn documents(&self) -> impl Iterator<Item = (&String, &D)> {
+        self.documents.iter().map(|(id, doc)| (id, &doc.document))
+    }
+
+    /// Approximate memory footprint of the stored vectors (quantized and full precision), in bytes.
+    pub fn vectors_size(&self) -> usize {
+        self.documents
+            .values()
+            .map(|doc| {
+                doc.vectors.iter().map(QuantizedVector::size).sum::<usize>()
+                    + doc.embeddings.as_ref().map_or(0, |embeddings| {
+                        embeddings
+                            .iter()
+                            .map(|embedding| embedding.vec.len() * std::mem::size_of::<f64>())
+                            .sum()
+                    })
+            })
+            .sum()
+    }
+
+    pub fn index<M: EmbeddingModel>(self, model: M) -> QuantizedVectorIndex<M, D> {
+        QuantizedVectorIndex::new(model, self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Search the `n` best documents, as `(score, id, document)` tuples sorted by decreasing score.
+    ///
+    /// The documents are compared with the (approximate) cosine similarity, and the scores are
+    /// normalized between 0 and 1 (see [DistanceMetric::normalize]).
+    fn vector_search(&self, prompt_embedding: &Embedding, n: usize) -> Vec<(f64, &String, &D)> {
+        let query = self.quantization.quantize(&prompt_embedding.vec);
+        let candidates = n * self.rescore.unwrap_or(1);
+
+        // Keep the best candidates of the quantized search
+        let mut heap = BinaryHeap::new();
+        for (id, doc) in self.documents.iter() {
+            if let Some(score) = doc
+                .vectors
+                .iter()
+                .map(|vector| OrderedFloat(vector.similarity(&query)))
+                .max()
+            {
+                heap.push(Reverse((score, id)));
+            }
+
+            if heap.len() > candidates {
+                heap.pop();
+            }
+        }
+
+        let mut results = heap
+            .into_iter()
+            .map(|Reverse((score, id))| {
+                let doc = &self.documents[id];
+
+                // Re-score the candidate exactly if its full precision embeddings were kept
+                let score = match (&self.rescore, &doc.embeddings) {
+                    (Some(_), Some(embeddings)) => embeddings
+                        .iter()
+                        .map(|embedding| {
+                            OrderedFloat(DistanceMetric::Cosine.score(embedding, prompt_embedding))
+                        })
+                        .max()
+                        .unwrap_or(score),
+                    // The similarity of binary vectors is already between 0 and 1
+                    _ if matches!(self.quantization, Quantization::Binary) => score,
+                    _ => OrderedFloat(DistanceMetric::Cosine.normalize(score.0)),
+                };
+
+                (score, id, &doc.document)
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by_key(|(score, _, _)| Reverse(*score));
+        results.truncate(n);
+
+        tracing::info!(target: "rig",
+            "Selected documents: {}",
+            results.iter()
+                .map(|(score, id, _)| format!("{} ({})", id, score))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+
+        results
+            .into_iter()
+            .map(|(score, id, doc)| (score.0, id, doc))
+            .collect()
+    }
+}
+
+pub struct QuantizedVectorIndex<M: EmbeddingModel, D: Serialize> {
+    model: M,
+    pub store: QuantizedVectorStore<D>,
+}
+
+impl<M: EmbeddingModel, D: Serialize> QuantizedVectorIndex<M, D> {
+    pub fn new(model: M, store: QuantizedVectorStore<D>) -> Self {
+        Self { model, store }
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+}
+
+impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send> VectorStoreIndex
+    for QuantizedVectorIndex<M, D>
+{
+    async fn top_n<T: for<'a> Deserialize<'a>>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+
+        self.store
+            .vector_search(&prompt_embedding, n)
+            .into_iter()
+            .map(|(score, id, doc)| {
+                Ok((
+                    score,
+                    id.clone(),
+                    serde_json::from_str(&serde_json::to_string(doc)?)?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+
+        Ok(self
+            .store
+            .vector_search(&prompt_embedding, n)
+            .into_iter()
+            .map(|(score, id, _)| (score, id.clone()))
+            .collect())
+    }
+}
+
+impl<D: Serialize + Send + Sync> VectorStoreWriter<D> for QuantizedVectorStore<D> {
+    async fn upsert(
+        &mut self,
+        documents: Vec<(String, D, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.add_documents_with_ids(documents);
+        Ok(())
+    }
+
+    async fn delete_by_id(&mut self, id: &str) -> Result<(), VectorStoreError> {
+        self.remove_document(id);
+        Ok(())
+    }
+
+    async fn get_by_id<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        id: &str,
+    ) -> Result<Option<T>, VectorStoreError> {
+        self.get_document(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embedding(document: &str, vec: Vec<f64>) -> OneOrMany<Embedding> {
+        OneOrMany::one(Embedding {
+            document: document.to_string(),
+            vec,
+        })
+    }
+
+    fn documents() -> Vec<(&'static str, &'static str, OneOrMany<Embedding>)> {
+        vec![
+            (
+                "doc1",
+                "glarb",
+                embedding("glarb", vec![0.9, 0.1, 0.5, -0.2]),
+            ),
+            (
+                "doc2",
+                "marble",
+                embedding("marble", vec![0.7, -0.3, 0.0, 0.4]),
+            ),
+            (
+                "doc3",
+                "flumb",
+                embedding("flumb", vec![-0.3, 0.7, 0.1, 0.2]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_int8_search() {
+        let mut store = QuantizedVectorStore::new(Quantization::Int8);
+        store.add_documents_with_ids(documents());
+
+        let results = store.vector_search(
+            &Embedding {
+                document: "query".to_string(),
+                vec: vec![0.8, 0.0, 0.6, -0.1],
+            },
+            2,
+        );
+
+        assert_eq!(
+            results
+                .iter()
+                .map(|(_, id, _)| id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["doc1", "doc2"]
+        );
+    }
+
+    #[test]
+    fn test_binary_search_with_rescoring() {
+        let query = Embedding {
+            document: "query".to_string(),
+            vec: vec![0.8, 0.1, 0.6, -0.1],
+        };
+
+        // doc1 and the query have the same signs: without re-scoring, the score is 1
+        let mut store = QuantizedVectorStore::new(Quantization::Binary);
+        store.add_documents_with_ids(documents());
+        assert_eq!(store.vector_search(&query, 1)[0].0, 1.0);
+
+        let mut store = QuantizedVectorStore::new(Quantization::Binary).rescore(2);
+        store.add_documents_with_ids(documents());
+        let results = store.vector_search(&query, 1);
+
+        assert_eq!(results[0].1, "doc1");
+        assert!(results[0].0 < 1.0);
+        assert_eq!(store.vectors_size(), 3 * (8 + 4 * 8));
+    }
+}
@@ -0,0 +1,549 @@
+//! This module provides the preprocessing of the images of vision prompts (with the `vision`
+//! feature), so that they meet the limits of the providers before being sent:
+//! - the images are resized to the dimensions used by the provider (the larger images are
+//!   downscaled by the providers anyway, after being uploaded and billed);
+//! - the images are re-encoded, which strips their metadata (e.g.: the GPS coordinates in the
+//!   EXIF of photos), after applying their EXIF orientation;
+//! - the images are compressed (or downscaled further) to fit the size limit of the provider.
+//!
+//! The tokens billed for the images can also be estimated before sending with [VisionCost].
+//!
+//! # Example
+//! ```rust
+//! use rig::vision::{ImageLimits, ImagePreprocessor, VisionCost};
+//!
+//! let preprocessor = ImagePreprocessor::new(ImageLimits::anthropic());
+//! preprocessor.preprocess_messages(&mut history)?;
+//!
+//! let image_tokens = VisionCost::Anthropic.messages_tokens(&history);
+//! ```
+
+use std::io::Cursor;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageDecoder, ImageFormat, ImageReader,
+};
+
+use crate::message::{
+    ContentFormat, Image, ImageDetail, ImageMediaType, Message, MimeType, ToolResultContent,
+    UserContent,
+};
+
+/// The lowest quality of the JPEG images compressed to fit the size limits.
+const MIN_JPEG_QUALITY: u8 = 40;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VisionError {
+    #[error("ImageError: {0}")]
+    ImageError(#[from] image::ImageError),
+
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// The inline image is not valid base64
+    #[error("InvalidData: {0}")]
+    InvalidData(String),
+
+    /// The format of the image cannot be decoded or encoded
+    #[error("UnsupportedFormat: {0}")]
+    UnsupportedFormat(String),
+
+    /// The image cannot be compressed to the size limit
+    #[error("TooLarge: the image cannot be compressed below {0} bytes")]
+    TooLarge(usize),
+}
+
+/// Limits of the images accepted by a provider.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageLimits {
+    pub max_width: u32,
+    pub max_height: u32,
+    /// Maximum number of pixels (width × height)
+    pub max_pixels: Option<u64>,
+    /// Maximum size of the encoded image, in bytes (before the base64 encoding)
+    pub max_bytes: Option<usize>,
+    /// Media types accepted by the provider
+    pub media_types: Vec<ImageMediaType>,
+}
+
+impl ImageLimits {
+    pub fn new(max_width: u32, max_height: u32) -> Self {
+        Self {
+            max_width,
+            max_height,
+            max_pixels: None,
+            max_bytes: None,
+            media_types: vec![ImageMediaType::JPEG, ImageMediaType::PNG],
+        }
+    }
+
+    /// The limits of OpenAI: the images are scaled to fit 2048 × 2048, up to 20 MB.
+    pub fn openai() -> Self {
+        Self {
+            max_bytes: Some(20_000_000),
+            media_types: vec![
+                ImageMediaType::JPEG,
+                ImageMediaType::PNG,
+                ImageMediaType::WEBP,
+                ImageMediaType::GIF,
+            ],
+            ..Self::new(2048, 2048)
+        }
+    }
+
+    /// The limits of Anthropic: the images are scaled to a long edge of 1568 pixels and at most
+    /// 1092 × 1092 pixels, up to 5 MB.
+    pub fn anthropic() -> Self {
+        Self {
+            max_pixels: Some(1092 * 1092),
+            max_bytes: Some(5 * 1024 * 1024),
+            media_types: vec![
+                ImageMediaType::JPEG,
+                ImageMediaType::PNG,
+                ImageMediaType::WEBP,
+                ImageMediaType::GIF,
+            ],
+            ..Self::new(1568, 1568)
+        }
+    }
+
+    /// The limits of Gemini: the images are scaled to fit 3072 × 3072, up to 20 MB inline.
+    pub fn gemini() -> Self {
+        Self {
+            max_bytes: Some(20_000_000),
+            media_types: vec![
+                ImageMediaType::JPEG,
+                ImageMediaType::PNG,
+                ImageMediaType::WEBP,
+                ImageMediaType::HEIC,
+                ImageMediaType::HEIF,
+            ],
+            ..Self::new(3072, 3072)
+        }
+    }
+
+    /// Set the maximum size of the encoded images, in bytes.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+}
+
+/// An image processed by an [ImagePreprocessor].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessedImage {
+    pub data: Vec<u8>,
+    pub media_type: ImageMediaType,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Resizes and re-encodes images to meet [ImageLimits].
+#[derive(Clone, Debug)]
+pub struct ImagePreprocessor {
+    limits: ImageLimits,
+    media_type: Option<ImageMediaType>,
+    quality: u8,
+    strip_metadata: bool,
+}
+
+impl ImagePreprocessor {
+    pub fn new(limits: ImageLimits) -> Self {
+        Self {
+            limits,
+            media_type: None,
+            quality: 85,
+            strip_metadata: true,
+        }
+    }
+
+    /// Re-encode the images as `media_type` (JPEG or PNG). By default, the JPEG and PNG images
+    /// keep their format if the provider accepts it, and the others are converted to JPEG.
+    pub fn media_type(mut self, media_type: ImageMediaType) -> Self {
+        self.media_type = Some(media_type);
+        self
+    }
+
+    /// Set the quality of the JPEG images, from 1 to 100 (default: 85).
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Set whether the images are re-encoded to strip their metadata even if they already meet
+    /// the limits (default: true).
+    pub fn strip_metadata(mut self, strip_metadata: bool) -> Self {
+        self.strip_metadata = strip_metadata;
+        self
+    }
+
+    /// Process the encoded image `data`.
+    pub fn process(&self, data: &[u8]) -> Result<ProcessedImage, VisionError> {
+        let reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+        let source_type = reader
+            .format()
+            .and_then(media_type)
+            .ok_or_else(|| VisionError::UnsupportedFormat("Unknown image format".to_string()))?;
+
+        let mut decoder = reader.into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+
+        let (width, height) = fit(
+            image.width(),
+            image.height(),
+            (self.limits.max_width, self.limits.max_height),
+            self.limits.max_pixels,
+        );
+        let resized = (width, height) != (image.width(), image.height());
+        let target_type = self.target_media_type(&source_type);
+
+        if !resized && !self.strip_metadata && target_type == source_type && self.fits(data.len()) {
+            return Ok(ProcessedImage {
+                data: data.to_vec(),
+                media_type: source_type,
+                width,
+                height,
+            });
+        }
+
+        if resized {
+            image = image.resize_exact(width, height, FilterType::CatmullRom);
+        }
+        loop {
+            let mut quality = self.quality;
+            let mut data = encode(&image, &target_type, quality)?;
+            while !self.fits(data.len())
+                && target_type == ImageMediaType::JPEG
+                && quality > MIN_JPEG_QUALITY
+            {
+                quality = quality.saturating_sub(10).max(MIN_JPEG_QUALITY);
+                data = encode(&image, &target_type, quality)?;
+            }
+            if self.fits(data.len()) {
+                return Ok(ProcessedImage {
+                    data,
+                    media_type: target_type,
+                    width: image.width(),
+                    height: image.height(),
+                });
+            }
+
+            // Downscale the images which cannot be compressed further
+            if image.width().max(image.height()) <= 16 {
+                return Err(VisionError::TooLarge(
+                    self.limits.max_bytes.unwrap_or_default(),
+                ));
+            }
+            image = image.resize_exact(
+                (image.width() * 3 / 4).max(1),
+                (image.height() * 3 / 4).max(1),
+                FilterType::CatmullRom,
+            );
+        }
+    }
+
+    /// Process the inline (base64) `image` in place. The URLs and the handles of blobs are left
+    /// unchanged (see [resolve](crate::blob::resolve) to inline the blobs).
+    pub fn preprocess_image(&self, image: &mut Image) -> Result<(), VisionError> {
+        if !matches!(image.format, None | Some(ContentFormat::Base64)) {
+            return Ok(());
+        }
+
+        let data = BASE64_STANDARD
+            .decode(image.data.as_bytes())
+            .map_err(|e| VisionError::InvalidData(e.to_string()))?;
+        let processed = self.process(&data)?;
+        image.data = BASE64_STANDARD.encode(processed.data);
+        image.format = Some(ContentFormat::Base64);
+        image.media_type = Some(processed.media_type);
+        Ok(())
+    }
+
+    /// Process the inline images of `messages` (and of their tool results) in place.
+    pub fn preprocess_messages<'a>(
+        &self,
+        messages: impl IntoIterator<Item = &'a mut Message>,
+    ) -> Result<(), VisionError> {
+        for message in messages {
+            let Message::User { content } = message else {
+                continue;
+            };
+            for content in content.iter_mut() {
+                match content {
+                    UserContent::Image(image) => self.preprocess_image(image)?,
+                    UserContent::ToolResult(tool_result) => {
+                        for content in tool_result.content.iter_mut() {
+                            if let ToolResultContent::Image(image) = content {
+                                self.preprocess_image(image)?;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn target_media_type(&self, source_type: &ImageMediaType) -> ImageMediaType {
+        match &self.media_type {
+            Some(media_type) => media_type.clone(),
+            None if matches!(source_type, ImageMediaType::JPEG | ImageMediaType::PNG)
+                && self.limits.media_types.contains(source_type) =>
+            {
+                source_type.clone()
+            }
+            None if !self.limits.media_types.contains(&ImageMediaType::JPEG)
+                && self.limits.media_types.contains(&ImageMediaType::PNG) =>
+            {
+                ImageMediaType::PNG
+            }
+            None => ImageMediaType::JPEG,
+        }
+    }
+
+    fn fits(&self, size: usize) -> bool {
+        self.limits
+            .max_bytes
+            .is_none_or(|max_bytes| size <= max_bytes)
+    }
+}
+
+/// Estimation of the tokens billed by the providers for the images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisionCost {
+    /// 85 tokens per image, plus 170 tokens per tile of 512 × 512 of the image scaled to fit
+    /// 2048 × 2048 with its short side at most 768 pixels (the low detail images are 85 tokens)
+    OpenAI,
+    /// A token per 750 pixels of the image scaled to the [Anthropic limits](ImageLimits::anthropic)
+    Anthropic,
+    /// 258 tokens for the images up to 384 × 384, or per tile of 768 × 768 of the larger images
+    Gemini,
+}
+
+impl VisionCost {
+    /// The tokens of an image of `width` × `height` pixels.
+    pub fn tokens(&self, width: u32, height: u32, detail: Option<&ImageDetail>) -> usize {
+        let tiles = |width: u32, height: u32, size: u32| {
+            (width.div_ceil(size) * height.div_ceil(size)) as usize
+        };
+        match self {
+            VisionCost::OpenAI if detail == Some(&ImageDetail::Low) => 85,
+            VisionCost::OpenAI => {
+                let (width, height) = fit(width, height, (2048, 2048), None);
+                let scale = (768.0 / width.min(height) as f64).min(1.0);
+                let (width, height) = (
+                    (width as f64 * scale).round() as u32,
+                    (height as f64 * scale).round() as u32,
+                );
+                85 + 170 * tiles(width, height, 512)
+            }
+            VisionCost::Anthropic => {
+                let limits = ImageLimits::anthropic();
+                let (width, height) = fit(
+                    width,
+                    height,
+                    (limits.max_width, limits.max_height),
+                    limits.max_pixels,
+                );
+                (width as usize * height as usize).div_ceil(750)
+            }
+            VisionCost::Gemini if width <= 384 && height <= 384 => 258,
+            VisionCost::Gemini => {
+                let (width, height) = fit(width, height, (3072, 3072), None);
+                258 * tiles(width, height, 768)
+            }
+        }
+    }
+
+    /// The tokens of `image`, from the dimensions of the inline images. The images of unknown
+    /// dimensions (URLs, blobs or undecodable images) are estimated as the largest images.
+    pub fn image_tokens(&self, image: &Image) -> usize {
+        let dimensions = matches!(image.format, None | Some(ContentFormat::Base64))
+            .then(|| BASE64_STANDARD.decode(image.data.as_bytes()).ok())
+            .flatten()
+            .and_then(|data| {
+                ImageReader::new(Cursor::new(data))
+                    .with_guessed_format()
+                    .ok()?
+                    .into_dimensions()
+                    .ok()
+            });
+        let (width, height) = dimensions.unwrap_or(match self {
+            VisionCost::OpenAI => (2048, 2048),
+            VisionCost::Anthropic => (1568, 1568),
+            VisionCost::Gemini => (3072, 3072),
+        });
+        self.tokens(width, height, image.detail.as_ref())
+    }
+
+    /// The tokens of the images of `messages` (and of their tool results).
+    pub fn messages_tokens<'a>(&self, messages: impl IntoIterator<Item = &'a Message>) -> usize {
+        let mut tokens = 0;
+        for message in messages {
+            let Message::User { content } = message else {
+                continue;
+            };
+            for content in content.iter() {
+                match content {
+                    UserContent::Image(image) => tokens += self.image_tokens(image),
+                    UserContent::ToolResult(tool_result) => {
+                        for content in tool_result.content.iter() {
+                            if let ToolResultContent::Image(image) = content {
+                                tokens += self.image_tokens(image);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        tokens
+    }
+}
+
+/// The dimensions of an image of `width` × `height` scaled down (keeping its aspect ratio) to fit
+/// `max_dimensions` and `max_pixels`.
+fn fit(
+    width: u32,
+    height: u32,
+    (max_width, max_height): (u32, u32),
+    max_pixels: Option<u64>,
+) -> (u32, u32) {
+    let mut scale = (max_width as f64 / width as f64)
+        .min(max_height as f64 / height as f64)
+        .min(1.0);
+    if let Some(max_pixels) = max_pixels {
+        let pixels = width as f64 * height as f64 * scale * scale;
+        if pixels > max_pixels as f64 {
+            scale *= (max_pixels as f64 / pixels).sqrt();
+        }
+    }
+    if scale >= 1.0 {
+        return (width, height);
+    }
+    // The epsilon absorbs the rounding errors of the scale (e.g.: 1568 / 3000 * 3000 < 1568)
+    let scaled = |size: u32| ((size as f64 * scale + 1e-6).floor() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+fn media_type(format: ImageFormat) -> Option<ImageMediaType> {
+    match format {
+        ImageFormat::Jpeg => Some(ImageMediaType::JPEG),
+        ImageFormat::Png => Some(ImageMediaType::PNG),
+        ImageFormat::Gif => Some(ImageMediaType::GIF),
+        ImageFormat::WebP => Some(ImageMediaType::WEBP),
+        _ => None,
+    }
+}
+
+fn encode(
+    image: &DynamicImage,
+    media_type: &ImageMediaType,
+    quality: u8,
+) -> Result<Vec<u8>, VisionError> {
+    let mut data = vec![];
+    match media_type {
+        // JPEG has no alpha channel
+        ImageMediaType::JPEG => image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut data, quality))?,
+        ImageMediaType::PNG => image.write_with_encoder(PngEncoder::new(&mut data))?,
+        media_type => {
+            return Err(VisionError::UnsupportedFormat(format!(
+                "Cannot encode {}",
+                media_type.to_mime_type()
+            )))
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OneOrMany;
+    use image::{Rgb, RgbImage};
+
+    /// A noisy JPEG image (hard to compress), with an EXIF segment
+    fn photo(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            let noise = (x.wrapping_mul(7919) ^ y.wrapping_mul(104729)).wrapping_mul(2654435761);
+            Rgb([(noise >> 8) as u8, (noise >> 16) as u8, (noise >> 24) as u8])
+        });
+        let mut data = vec![];
+        image
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut data, 95))
+            .unwrap();
+
+        let mut exif = b"Exif\0\0GPS 48.8566N 2.3522E".to_vec();
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend(((exif.len() + 2) as u16).to_be_bytes());
+        segment.append(&mut exif);
+        data.splice(2..2, segment);
+        data
+    }
+
+    #[test]
+    fn test_preprocess_images() {
+        let data = photo(2000, 700);
+        let processed = ImagePreprocessor::new(ImageLimits::anthropic())
+            .process(&data)
+            .unwrap();
+        assert_eq!(processed.media_type, ImageMediaType::JPEG);
+        assert_eq!((processed.width, processed.height), (1568, 548));
+        assert!(!processed.data.windows(4).any(|bytes| bytes == b"Exif"));
+
+        // Compressed to the size limit
+        let processed = ImagePreprocessor::new(ImageLimits::new(1000, 1000).max_bytes(50_000))
+            .process(&data)
+            .unwrap();
+        assert!(processed.data.len() <= 50_000);
+
+        let mut message = Message::User {
+            content: OneOrMany::one(UserContent::image(
+                BASE64_STANDARD.encode(&data),
+                None,
+                Some(ImageMediaType::JPEG),
+                None,
+            )),
+        };
+        let preprocessor =
+            ImagePreprocessor::new(ImageLimits::new(300, 300)).media_type(ImageMediaType::PNG);
+        preprocessor
+            .preprocess_messages(std::iter::once(&mut message))
+            .unwrap();
+        let Message::User { content } = &message else {
+            unreachable!()
+        };
+        let UserContent::Image(image) = content.first() else {
+            unreachable!()
+        };
+        assert_eq!(image.media_type, Some(ImageMediaType::PNG));
+        assert_eq!(VisionCost::Anthropic.image_tokens(&image), 42);
+
+        assert!(matches!(
+            preprocessor.process(b"not an image"),
+            Err(VisionError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_vision_cost() {
+        assert_eq!(VisionCost::OpenAI.tokens(1024, 1024, None), 765);
+        assert_eq!(VisionCost::OpenAI.tokens(2048, 4096, None), 1105);
+        assert_eq!(
+            VisionCost::OpenAI.tokens(4096, 4096, Some(&ImageDetail::Low)),
+            85
+        );
+        assert_eq!(VisionCost::Anthropic.tokens(1092, 1092, None), 1590);
+        assert_eq!(VisionCost::Anthropic.tokens(200, 200, None), 54);
+        assert_eq!(VisionCost::Gemini.tokens(384, 384, None), 258);
+        assert_eq!(VisionCost::Gemini.tokens(1536, 800, None), 1032);
+    }
+}
@@ -0,0 +1,69 @@
+//! Compatibility of the async traits of the crate with the `wasm32-unknown-unknown` target
+//! (e.g.: agents running client-side in web apps and browser extensions).
+//!
+//! The futures of the HTTP requests are `Send` on native targets, but not on `wasm32`, where
+//! reqwest sends them with the `fetch` API of the browser. The futures returned by the traits
+//! of the crate (e.g.: by [CompletionModel](crate::completion::CompletionModel),
+//! [EmbeddingModel](crate::embeddings::EmbeddingModel) or [Tool](crate::tool::Tool), and the
+//! [WasmBoxedFuture] and [WasmBoxedStream] of the object-safe traits) are thus bounded by
+//! [WasmCompatSend] instead of `Send`: the same bound on native targets, and no bound on
+//! `wasm32`.
+//!
+//! The types implementing the model and tool traits must still be `Send` and `Sync` on
+//! `wasm32` (like the clients of the providers). The traits of the stores and handlers
+//! returning boxed futures (e.g.: [CacheBackend](crate::cache::CacheBackend) or
+//! [CheckpointStore](crate::agent::CheckpointStore)) are bounded by [WasmCompatSend] and
+//! [WasmCompatSync] instead, and the synchronous traits (e.g.:
+//! [TextSplitter](crate::chunking::TextSplitter)) by `Send` and `Sync`.
+//!
+//! On `wasm32`, the timers (timeouts, rate limits, ...) use the timers of the browser, and
+//! the clocks use `performance.now()` and `Date.now()`. The features `redis`, `rayon` and
+//! `worker` are not supported in the browser.
+
+use std::{future::Future, pin::Pin};
+
+use futures::Stream;
+
+/// `Send` on native targets, implemented by all the types on `wasm32`.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait WasmCompatSend: Send {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + ?Sized> WasmCompatSend for T {}
+
+/// `Send` on native targets, implemented by all the types on `wasm32`.
+#[cfg(target_arch = "wasm32")]
+pub trait WasmCompatSend {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> WasmCompatSend for T {}
+
+/// `Sync` on native targets, implemented by all the types on `wasm32`.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait WasmCompatSync: Sync {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Sync + ?Sized> WasmCompatSync for T {}
+
+/// `Sync` on native targets, implemented by all the types on `wasm32`.
+#[cfg(target_arch = "wasm32")]
+pub trait WasmCompatSync {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> WasmCompatSync for T {}
+
+/// A boxed future, `Send` on native targets.
+#[cfg(not(target_arch = "wasm32"))]
+pub type WasmBoxedFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A boxed future, `Send` on native targets.
+#[cfg(target_arch = "wasm32")]
+pub type WasmBoxedFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// A boxed stream, `Send` on native targets.
+#[cfg(not(target_arch = "wasm32"))]
+pub type WasmBoxedStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+
+/// A boxed stream, `Send` on native targets.
+#[cfg(target_arch = "wasm32")]
+pub type WasmBoxedStream<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
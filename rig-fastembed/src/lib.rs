@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
+#[cfg(feature = "online")]
+mod sparse;
+#[cfg(feature = "online")]
+pub use sparse::{SparseEmbeddingModel, SparseInitOptions};
+
 pub use fastembed::EmbeddingModel as FastembedModel;
-use fastembed::{
-    InitOptions, InitOptionsUserDefined, ModelInfo, TextEmbedding, UserDefinedEmbeddingModel,
-};
-use rig::{
-    embeddings::{self, EmbeddingError, EmbeddingsBuilder},
-    Embed,
-};
+pub use fastembed::InitOptions;
+use fastembed::{InitOptionsUserDefined, ModelInfo, TextEmbedding, UserDefinedEmbeddingModel};
+use rig::embeddings::{self, EmbeddingError};
+#[cfg(feature = "online")]
+use rig::{embeddings::EmbeddingsBuilder, Embed};
 
 #[derive(Clone)]
 pub struct Client;
@@ -32,17 +35,42 @@ impl Client {
     /// ```
     /// use rig_fastembed::{Client, FastembedModel};
     ///
-    /// // Initialize the OpenAI client
-    /// let fastembed_client = Client::new("your-open-ai-api-key");
+    /// // Initialize the Fastembed client
+    /// let fastembed_client = Client::new();
     ///
     /// let embedding_model = fastembed_client.embedding_model(&FastembedModel::AllMiniLML6V2Q);
     /// ```
+    #[cfg(feature = "online")]
     pub fn embedding_model(&self, model: &FastembedModel) -> EmbeddingModel {
         let ndims = fetch_model_ndims(model);
 
         EmbeddingModel::new(model, ndims)
     }
 
+    /// Create an embedding model with the given initialization options (e.g.: cache directory
+    /// of the model files, execution providers), returning an error if the model cannot be loaded.
+    ///
+    /// # Example
+    /// ```
+    /// use rig_fastembed::{Client, FastembedModel, InitOptions};
+    ///
+    /// let fastembed_client = Client::new();
+    ///
+    /// // Load the model from a local directory (e.g.: for offline use)
+    /// let embedding_model = fastembed_client.embedding_model_with_options(
+    ///     InitOptions::new(FastembedModel::BGESmallENV15).with_cache_dir("models".into()),
+    /// )?;
+    /// ```
+    #[cfg(feature = "online")]
+    pub fn embedding_model_with_options(
+        &self,
+        options: InitOptions,
+    ) -> Result<EmbeddingModel, EmbeddingError> {
+        let ndims = fetch_model_ndims(&options.model_name);
+
+        EmbeddingModel::try_new_with_options(options, ndims)
+    }
+
     /// Create an embedding builder with the given embedding model.
     ///
     /// # Example
@@ -59,12 +87,28 @@ impl Client {
     ///     .await
     ///     .expect("Failed to embed documents");
     /// ```
+    #[cfg(feature = "online")]
     pub fn embeddings<D: Embed>(
         &self,
         model: &fastembed::EmbeddingModel,
     ) -> EmbeddingsBuilder<EmbeddingModel, D> {
         EmbeddingsBuilder::new(self.embedding_model(model))
     }
+
+    /// Create a SPLADE sparse embedding model (e.g.: for hybrid retrieval).
+    ///
+    /// # Example
+    /// ```
+    /// use rig_fastembed::Client;
+    ///
+    /// let fastembed_client = Client::new();
+    ///
+    /// let sparse_model = fastembed_client.sparse_embedding_model();
+    /// ```
+    #[cfg(feature = "online")]
+    pub fn sparse_embedding_model(&self) -> SparseEmbeddingModel {
+        SparseEmbeddingModel::new()
+    }
 }
 
 #[derive(Clone)]
@@ -72,42 +116,87 @@ pub struct EmbeddingModel {
     embedder: Arc<TextEmbedding>,
     pub model: FastembedModel,
     ndims: usize,
+    batch_size: Option<usize>,
 }
 
 impl EmbeddingModel {
+    /// Create a new embedding model, downloading the model files if needed.
+    ///
+    /// Panics if the model cannot be loaded. Use [EmbeddingModel::try_new] to handle the error.
+    #[cfg(feature = "online")]
     pub fn new(model: &fastembed::EmbeddingModel, ndims: usize) -> Self {
-        let embedder = Arc::new(
-            TextEmbedding::try_new(
-                InitOptions::new(model.to_owned()).with_show_download_progress(true),
-            )
-            .unwrap(),
-        );
-
-        Self {
-            embedder,
-            model: model.to_owned(),
+        Self::try_new(model, ndims).expect("Fastembed model should load")
+    }
+
+    /// Create a new embedding model, downloading the model files if needed.
+    #[cfg(feature = "online")]
+    pub fn try_new(
+        model: &fastembed::EmbeddingModel,
+        ndims: usize,
+    ) -> Result<Self, EmbeddingError> {
+        Self::try_new_with_options(
+            InitOptions::new(model.to_owned()).with_show_download_progress(true),
+            ndims,
+        )
+    }
+
+    /// Create a new embedding model with the given initialization options.
+    #[cfg(feature = "online")]
+    pub fn try_new_with_options(
+        options: InitOptions,
+        ndims: usize,
+    ) -> Result<Self, EmbeddingError> {
+        let model = options.model_name.clone();
+        let embedder = TextEmbedding::try_new(options)
+            .map_err(|err| EmbeddingError::ProviderError(err.to_string()))?;
+
+        Ok(Self {
+            embedder: Arc::new(embedder),
+            model,
             ndims,
-        }
+            batch_size: None,
+        })
     }
 
+    /// Set the number of texts run through the ONNX model at once (default: 256).
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Create a new embedding model from user-defined model files.
+    ///
+    /// Panics if the model cannot be loaded. Use [EmbeddingModel::try_new_from_user_defined] to
+    /// handle the error.
     pub fn new_from_user_defined(
         user_defined_model: UserDefinedEmbeddingModel,
         ndims: usize,
         model_info: &ModelInfo<FastembedModel>,
     ) -> Self {
+        Self::try_new_from_user_defined(user_defined_model, ndims, model_info)
+            .expect("Fastembed model should load")
+    }
+
+    /// Create a new embedding model from user-defined model files.
+    pub fn try_new_from_user_defined(
+        user_defined_model: UserDefinedEmbeddingModel,
+        ndims: usize,
+        model_info: &ModelInfo<FastembedModel>,
+    ) -> Result<Self, EmbeddingError> {
         let fastembed_embedding_model = TextEmbedding::try_new_from_user_defined(
             user_defined_model,
             InitOptionsUserDefined::default(),
         )
-        .unwrap();
+        .map_err(|err| EmbeddingError::ProviderError(err.to_string()))?;
 
         let embedder = Arc::new(fastembed_embedding_model);
 
-        Self {
+        Ok(Self {
             embedder,
             model: model_info.model.to_owned(),
             ndims,
-        }
+            batch_size: None,
+        })
     }
 }
 
@@ -126,7 +215,7 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
 
         let documents_as_vec = self
             .embedder
-            .embed(documents_as_strings.clone(), None)
+            .embed(documents_as_strings.clone(), self.batch_size)
             .map_err(|err| EmbeddingError::ProviderError(err.to_string()))?;
 
         let docs = documents_as_strings
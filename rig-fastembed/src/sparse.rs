@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+pub use fastembed::SparseInitOptions;
+use fastembed::SparseTextEmbedding;
+use rig::embeddings::{self, EmbeddingError};
+
+/// Sparse embedding model (SPLADE) running locally with Fastembed.
+#[derive(Clone)]
+pub struct SparseEmbeddingModel {
+    embedder: Arc<SparseTextEmbedding>,
+    batch_size: Option<usize>,
+}
+
+impl SparseEmbeddingModel {
+    /// Create a new sparse embedding model using the default model (`prithivida/Splade_PP_en_v1`),
+    /// downloading the model files if needed.
+    ///
+    /// Panics if the model cannot be loaded. Use [SparseEmbeddingModel::try_new] to handle the error.
+    pub fn new() -> Self {
+        Self::try_new().expect("Fastembed sparse model should load")
+    }
+
+    /// Create a new sparse embedding model using the default model (`prithivida/Splade_PP_en_v1`),
+    /// downloading the model files if needed.
+    pub fn try_new() -> Result<Self, EmbeddingError> {
+        Self::try_new_with_options(SparseInitOptions::default())
+    }
+
+    /// Create a new sparse embedding model with the given initialization options.
+    pub fn try_new_with_options(options: SparseInitOptions) -> Result<Self, EmbeddingError> {
+        let embedder = SparseTextEmbedding::try_new(options)
+            .map_err(|err| EmbeddingError::ProviderError(err.to_string()))?;
+
+        Ok(Self {
+            embedder: Arc::new(embedder),
+            batch_size: None,
+        })
+    }
+
+    /// Set the number of texts run through the ONNX model at once (default: 256).
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+}
+
+impl Default for SparseEmbeddingModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl embeddings::SparseEmbeddingModel for SparseEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    async fn embed_sparse_texts(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<embeddings::SparseEmbedding>, EmbeddingError> {
+        let documents: Vec<String> = documents.into_iter().collect();
+
+        let sparse_embeddings = self
+            .embedder
+            .embed(documents.clone(), self.batch_size)
+            .map_err(|err| EmbeddingError::ProviderError(err.to_string()))?;
+
+        Ok(documents
+            .into_iter()
+            .zip(sparse_embeddings)
+            .map(|(document, embedding)| embeddings::SparseEmbedding {
+                document,
+                indices: embedding.indices.into_iter().map(|i| i as u32).collect(),
+                values: embedding.values.into_iter().map(|v| v as f64).collect(),
+            })
+            .collect())
+    }
+}
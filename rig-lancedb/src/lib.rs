@@ -4,7 +4,9 @@ use lancedb::{
 };
 use rig::{
     embeddings::embedding::EmbeddingModel,
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    vector_store::{
+        top_n_filtered_from_stream, MetadataFilter, VectorStoreError, VectorStoreIndex,
+    },
 };
 use serde::Deserialize;
 use serde_json::Value;
@@ -100,6 +102,70 @@ impl<M: EmbeddingModel> LanceDbVectorIndex<M> {
 
         query
     }
+
+    /// Search the top `n` records passing the SQL `condition`.
+    async fn search<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        condition: Option<String>,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+
+        let mut query = self
+            .table
+            .vector_search(prompt_embedding.vec.clone())
+            .map_err(lancedb_to_rig_error)?
+            .limit(n)
+            .select(lancedb::query::Select::Columns(
+                self.table
+                    .schema()
+                    .await
+                    .map_err(lancedb_to_rig_error)?
+                    .filter_embeddings(),
+            ));
+        if let Some(condition) = condition {
+            query = query.only_if(condition);
+        }
+
+        self.build_query(query)
+            .execute_query()
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                Ok((
+                    match value.get("_distance") {
+                        Some(Value::Number(distance)) => distance.as_f64().unwrap_or_default(),
+                        _ => 0.0,
+                    },
+                    match value.get(self.id_field.clone()) {
+                        Some(Value::String(id)) => id.to_string(),
+                        _ => format!("unknown{i}"),
+                    },
+                    serde_json::from_value(value).map_err(serde_to_rig_error)?,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Translate `filter` to an SQL condition on the columns of the table, if its value is a string,
+/// a number or a boolean.
+fn sql_condition(filter: &MetadataFilter) -> Option<String> {
+    let value = match &filter.value {
+        Value::String(value) => format!("'{}'", value.replace('\'', "''")),
+        Value::Number(value) => value.to_string(),
+        Value::Bool(value) => value.to_string(),
+        _ => return None,
+    };
+    let column = filter
+        .path()
+        .iter()
+        .map(|segment| format!("`{}`", segment.replace('`', "``")))
+        .collect::<Vec<_>>()
+        .join(".");
+    Some(format!("{column} = {value}"))
 }
 
 /// See [LanceDB vector search](https://lancedb.github.io/lancedb/search/) for more information.
@@ -199,40 +265,7 @@ impl<M: EmbeddingModel + Sync + Send> VectorStoreIndex for LanceDbVectorIndex<M>
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let prompt_embedding = self.model.embed_text(query).await?;
-
-        let query = self
-            .table
-            .vector_search(prompt_embedding.vec.clone())
-            .map_err(lancedb_to_rig_error)?
-            .limit(n)
-            .select(lancedb::query::Select::Columns(
-                self.table
-                    .schema()
-                    .await
-                    .map_err(lancedb_to_rig_error)?
-                    .filter_embeddings(),
-            ));
-
-        self.build_query(query)
-            .execute_query()
-            .await?
-            .into_iter()
-            .enumerate()
-            .map(|(i, value)| {
-                Ok((
-                    match value.get("_distance") {
-                        Some(Value::Number(distance)) => distance.as_f64().unwrap_or_default(),
-                        _ => 0.0,
-                    },
-                    match value.get(self.id_field.clone()) {
-                        Some(Value::String(id)) => id.to_string(),
-                        _ => format!("unknown{i}"),
-                    },
-                    serde_json::from_value(value).map_err(serde_to_rig_error)?,
-                ))
-            })
-            .collect()
+        self.search(query, n, None).await
     }
 
     /// Implement the `top_n_ids` method of the `VectorStoreIndex` trait for `LanceDbVectorIndex`.
@@ -285,4 +318,19 @@ impl<M: EmbeddingModel + Sync + Send> VectorStoreIndex for LanceDbVectorIndex<M>
             })
             .collect()
     }
+
+    /// Filter the records in the vector search, with an SQL condition on the column (or the
+    /// nested field) of the filter. The values which are not strings, numbers or booleans are
+    /// filtered from the results instead.
+    async fn top_n_filtered<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &MetadataFilter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        match sql_condition(filter) {
+            Some(condition) => self.search(query, n, Some(condition)).await,
+            None => top_n_filtered_from_stream(self, query, n, filter).await,
+        }
+    }
 }
@@ -3,9 +3,12 @@ use mongodb::bson::{self, doc};
 
 use rig::{
     embeddings::embedding::{Embedding, EmbeddingModel},
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    vector_store::{
+        top_n_filtered_from_stream, MetadataFilter, VectorStoreError, VectorStoreIndex,
+    },
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -104,12 +107,23 @@ pub struct MongoDbVectorIndex<M: EmbeddingModel, C: Send + Sync> {
 impl<M: EmbeddingModel, C: Send + Sync> MongoDbVectorIndex<M, C> {
     /// Vector search stage of aggregation pipeline of mongoDB collection.
     /// To be used by implementations of top_n and top_n_ids methods on VectorStoreIndex trait for MongoDbVectorIndex.
-    fn pipeline_search_stage(&self, prompt_embedding: &Embedding, n: usize) -> bson::Document {
+    /// The pre-filter `condition` is added to the filter of the search params.
+    fn pipeline_search_stage(
+        &self,
+        prompt_embedding: &Embedding,
+        n: usize,
+        condition: Option<bson::Document>,
+    ) -> bson::Document {
         let SearchParams {
             filter,
             exact,
             num_candidates,
         } = &self.search_params;
+        let filter = match condition {
+            Some(condition) if filter.is_empty() => condition,
+            Some(condition) => doc! { "$and": [filter.clone(), condition] },
+            None => filter.clone(),
+        };
 
         doc! {
           "$vectorSearch": {
@@ -133,6 +147,64 @@ impl<M: EmbeddingModel, C: Send + Sync> MongoDbVectorIndex<M, C> {
           }
         }
     }
+
+    /// Search the top `n` documents passing the pre-filter `condition`.
+    async fn search<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        condition: Option<bson::Document>,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+
+        let mut cursor = self
+            .collection
+            .aggregate([
+                self.pipeline_search_stage(&prompt_embedding, n, condition),
+                self.pipeline_score_stage(),
+                {
+                    doc! {
+                        "$project": {
+                            self.embedded_field.clone(): 0,
+                        },
+                    }
+                },
+            ])
+            .await
+            .map_err(mongodb_to_rig_error)?
+            .with_type::<serde_json::Value>();
+
+        let mut results = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(mongodb_to_rig_error)?;
+            let score = doc.get("score").expect("score").as_f64().expect("f64");
+            let id = doc.get("_id").expect("_id").to_string();
+            let doc_t: T = serde_json::from_value(doc).map_err(VectorStoreError::JsonError)?;
+            results.push((score, id, doc_t));
+        }
+
+        tracing::info!(target: "rig",
+            "Selected documents: {}",
+            results.iter()
+                .map(|(distance, id, _)| format!("{} ({})", id, distance))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+
+        Ok(results)
+    }
+}
+
+/// Translate `filter` to a pre-filter of the vector search, if its value is a string, a number
+/// or a boolean (the values supported by the pre-filters of MongoDB).
+fn pre_filter(filter: &MetadataFilter) -> Option<bson::Document> {
+    let value = match &filter.value {
+        Value::String(_) | Value::Number(_) | Value::Bool(_) => {
+            bson::to_bson(&filter.value).ok()?
+        }
+        _ => return None,
+    };
+    Some(doc! { filter.path().join("."): { "$eq": value } })
 }
 
 impl<M: EmbeddingModel, C: Send + Sync> MongoDbVectorIndex<M, C> {
@@ -229,43 +301,7 @@ impl<M: EmbeddingModel + Sync + Send, C: Sync + Send> VectorStoreIndex
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let prompt_embedding = self.model.embed_text(query).await?;
-
-        let mut cursor = self
-            .collection
-            .aggregate([
-                self.pipeline_search_stage(&prompt_embedding, n),
-                self.pipeline_score_stage(),
-                {
-                    doc! {
-                        "$project": {
-                            self.embedded_field.clone(): 0,
-                        },
-                    }
-                },
-            ])
-            .await
-            .map_err(mongodb_to_rig_error)?
-            .with_type::<serde_json::Value>();
-
-        let mut results = Vec::new();
-        while let Some(doc) = cursor.next().await {
-            let doc = doc.map_err(mongodb_to_rig_error)?;
-            let score = doc.get("score").expect("score").as_f64().expect("f64");
-            let id = doc.get("_id").expect("_id").to_string();
-            let doc_t: T = serde_json::from_value(doc).map_err(VectorStoreError::JsonError)?;
-            results.push((score, id, doc_t));
-        }
-
-        tracing::info!(target: "rig",
-            "Selected documents: {}",
-            results.iter()
-                .map(|(distance, id, _)| format!("{} ({})", id, distance))
-                .collect::<Vec<String>>()
-                .join(", ")
-        );
-
-        Ok(results)
+        self.search(query, n, None).await
     }
 
     /// Implement the `top_n_ids` method of the `VectorStoreIndex` trait for `MongoDbVectorIndex`.
@@ -279,7 +315,7 @@ impl<M: EmbeddingModel + Sync + Send, C: Sync + Send> VectorStoreIndex
         let mut cursor = self
             .collection
             .aggregate([
-                self.pipeline_search_stage(&prompt_embedding, n),
+                self.pipeline_search_stage(&prompt_embedding, n, None),
                 self.pipeline_score_stage(),
                 doc! {
                     "$project": {
@@ -310,4 +346,19 @@ impl<M: EmbeddingModel + Sync + Send, C: Sync + Send> VectorStoreIndex
 
         Ok(results)
     }
+
+    /// Pre-filter the documents in the vector search: the field of the filter must be indexed
+    /// as a `filter` field of the vector index. The values which cannot be pre-filtered (e.g.:
+    /// objects) are filtered from the results instead.
+    async fn top_n_filtered<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &MetadataFilter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        match pre_filter(filter) {
+            Some(condition) => self.search(query, n, Some(condition)).await,
+            None => top_n_filtered_from_stream(self, query, n, filter).await,
+        }
+    }
 }
@@ -0,0 +1,118 @@
+use rig::vector_store::{
+    collections::{CollectionConfig, CollectionManager, DistanceMetric},
+    VectorStoreError,
+};
+use sqlx::PgPool;
+
+/// [CollectionManager] managing document tables (with the schema expected by
+/// [PostgresVectorStore](crate::PostgresVectorStore)) in the current schema of a PostgreSQL
+/// database with the pgvector extension.
+///
+/// Each collection is a table with an HNSW index on its `embedding` column, using the operator
+/// class of the distance metric of the collection.
+pub struct PostgresCollections {
+    pg_pool: PgPool,
+}
+
+impl PostgresCollections {
+    pub fn new(pg_pool: PgPool) -> Self {
+        Self { pg_pool }
+    }
+}
+
+/// Table names are interpolated in the queries, so only plain identifiers are accepted.
+fn validate_name(name: &str) -> Result<(), VectorStoreError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(VectorStoreError::DatastoreError(
+            format!("Invalid collection name: {name}").into(),
+        ))
+    }
+}
+
+fn operator_class(distance: DistanceMetric) -> &'static str {
+    match distance {
+        DistanceMetric::Cosine => "vector_cosine_ops",
+        DistanceMetric::DotProduct => "vector_ip_ops",
+        DistanceMetric::Euclidean => "vector_l2_ops",
+    }
+}
+
+impl CollectionManager for PostgresCollections {
+    async fn create_collection(&self, config: CollectionConfig) -> Result<(), VectorStoreError> {
+        validate_name(&config.name)?;
+
+        let queries = [
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} ( \
+                  id uuid DEFAULT gen_random_uuid(), \
+                  document jsonb NOT NULL, \
+                  embedded_text text NOT NULL, \
+                  embedding vector({}) \
+                )",
+                config.name, config.ndims
+            ),
+            format!(
+                "CREATE INDEX IF NOT EXISTS {}_embeddings_idx ON {} USING hnsw(embedding {})",
+                config.name,
+                config.name,
+                operator_class(config.distance)
+            ),
+        ];
+
+        for query in queries {
+            sqlx::query(&query)
+                .execute(&self.pg_pool)
+                .await
+                .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+        }
+
+        Ok(())
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT table_name::text FROM information_schema.columns \
+            WHERE column_name = 'embedding' AND udt_name = 'vector' \
+              AND table_schema = current_schema() \
+            ORDER BY table_name",
+        )
+        .fetch_all(&self.pg_pool)
+        .await
+        .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+
+        Ok(rows.into_iter().map(|(name,)| name).collect())
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        validate_name(name)?;
+
+        sqlx::query(&format!("DROP TABLE IF EXISTS {name}"))
+            .execute(&self.pg_pool)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("documents").is_ok());
+        assert!(validate_name("_docs_2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("2docs").is_err());
+        assert!(validate_name("docs; DROP TABLE users").is_err());
+    }
+}
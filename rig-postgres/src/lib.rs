@@ -1,8 +1,12 @@
 use std::fmt::Display;
 
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
 use rig::{
     embeddings::{Embedding, EmbeddingModel},
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    vector_store::{DistanceMetric, MetadataFilter, VectorStoreError, VectorStoreIndex},
     Embed, OneOrMany,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -10,6 +14,9 @@ use serde_json::Value;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+pub mod collections;
+pub use collections::PostgresCollections;
+
 pub struct PostgresVectorStore<Model: EmbeddingModel> {
     model: Model,
     pg_pool: PgPool,
@@ -47,6 +54,36 @@ impl Display for PgVectorDistanceFunction {
     }
 }
 
+impl PgVectorDistanceFunction {
+    /// Convert a distance to a score between 0 and 1, higher being more similar, consistent
+    /// with [DistanceMetric::normalize].
+    pub fn normalize(&self, distance: f64) -> f64 {
+        match self {
+            // Cosine distance: 1 - cosine similarity
+            PgVectorDistanceFunction::Cosine => DistanceMetric::Cosine.normalize(1.0 - distance),
+            // Negative inner product
+            PgVectorDistanceFunction::InnerProduct => {
+                DistanceMetric::DotProduct.normalize(-distance)
+            }
+            PgVectorDistanceFunction::L2 => DistanceMetric::Euclidean.normalize(distance),
+            PgVectorDistanceFunction::Jaccard => (1.0 - distance).clamp(0.0, 1.0),
+            PgVectorDistanceFunction::L1 | PgVectorDistanceFunction::Hamming => {
+                1.0 / (1.0 + distance.max(0.0))
+            }
+        }
+    }
+}
+
+impl From<DistanceMetric> for PgVectorDistanceFunction {
+    fn from(distance: DistanceMetric) -> Self {
+        match distance {
+            DistanceMetric::Cosine => PgVectorDistanceFunction::Cosine,
+            DistanceMetric::DotProduct => PgVectorDistanceFunction::InnerProduct,
+            DistanceMetric::Euclidean => PgVectorDistanceFunction::L2,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, sqlx::FromRow)]
 pub struct SearchResult {
     id: Uuid,
@@ -62,10 +99,19 @@ pub struct SearchResultOnlyId {
 }
 
 impl SearchResult {
-    pub fn into_result<T: DeserializeOwned>(self) -> Result<(f64, String, T), VectorStoreError> {
+    /// Convert the row to a `(score, id, document)` result, the score being the normalized
+    /// distance (see [PgVectorDistanceFunction::normalize]).
+    pub fn into_result<T: DeserializeOwned>(
+        self,
+        distance_function: &PgVectorDistanceFunction,
+    ) -> Result<(f64, String, T), VectorStoreError> {
         let document: T =
             serde_json::from_value(self.document).map_err(VectorStoreError::JsonError)?;
-        Ok((self.distance, self.id.to_string(), document))
+        Ok((
+            distance_function.normalize(self.distance),
+            self.id.to_string(),
+            document,
+        ))
     }
 }
 
@@ -88,28 +134,75 @@ impl<Model: EmbeddingModel> PostgresVectorStore<Model> {
         Self::new(model, pg_pool, None, PgVectorDistanceFunction::Cosine)
     }
 
-    fn search_query_full(&self) -> String {
-        self.search_query(true)
+    fn search_query_full(&self, filtered: bool) -> String {
+        self.search_query(true, filtered)
     }
     fn search_query_only_ids(&self) -> String {
-        self.search_query(false)
+        self.search_query(false, false)
     }
 
-    fn search_query(&self, with_document: bool) -> String {
+    /// Query of the documents (filtered by the field at the path `$4` being equal to `$5`).
+    fn search_query(&self, with_document: bool, filtered: bool) -> String {
         let document = if with_document { ", document" } else { "" };
+        let filter = if filtered {
+            "WHERE document #> $4 = $5 "
+        } else {
+            ""
+        };
         format!(
             "
             SELECT id{}, distance FROM ( \
               SELECT DISTINCT ON (id) id{}, embedding {} $1 as distance \
               FROM {} \
-              ORDER BY id, distance \
+              {}ORDER BY id, distance \
             ) as d \
-            ORDER BY distance \
-            LIMIT $2",
-            document, document, self.distance_function, self.documents_table
+            ORDER BY distance, id \
+            LIMIT $2 OFFSET $3",
+            document, document, self.distance_function, self.documents_table, filter
         )
     }
 
+    async fn embed_query(&self, query: &str) -> Result<pgvector::Vector, VectorStoreError> {
+        Ok(self
+            .model
+            .embed_text(query)
+            .await?
+            .vec
+            .iter()
+            .map(|&x| x as f32)
+            .collect::<Vec<f32>>()
+            .into())
+    }
+
+    /// Get the `n` documents (passing `filter`) ranked after the `offset` best ones.
+    async fn search_page<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        embedded_query: &pgvector::Vector,
+        n: usize,
+        offset: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let query = self.search_query_full(filter.is_some());
+        let mut query = sqlx::query_as(query.as_str())
+            .bind(embedded_query)
+            .bind(n as i64)
+            .bind(offset as i64);
+        if let Some(filter) = filter {
+            query = query.bind(filter.path()).bind(&filter.value);
+        }
+        let rows: Vec<SearchResult> = query
+            .fetch_all(&self.pg_pool)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+
+        let rows: Vec<(f64, String, T)> = rows
+            .into_iter()
+            .flat_map(|row| row.into_result(&self.distance_function))
+            .collect();
+
+        Ok(rows)
+    }
+
     pub async fn insert_documents<Doc: Serialize + Embed + Send>(
         &self,
         documents: Vec<(Doc, OneOrMany<Embedding>)>,
@@ -145,35 +238,15 @@ impl<Model: EmbeddingModel> PostgresVectorStore<Model> {
 
 impl<Model: EmbeddingModel> VectorStoreIndex for PostgresVectorStore<Model> {
     /// Get the top n documents based on the distance to the given query.
-    /// The result is a list of tuples of the form (score, id, document)
+    /// The result is a list of tuples of the form (score, id, document), the score being the
+    /// distance normalized between 0 and 1 (see [PgVectorDistanceFunction::normalize]).
     async fn top_n<T: for<'a> Deserialize<'a> + Send>(
         &self,
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let embedded_query: pgvector::Vector = self
-            .model
-            .embed_text(query)
-            .await?
-            .vec
-            .iter()
-            .map(|&x| x as f32)
-            .collect::<Vec<f32>>()
-            .into();
-
-        let rows: Vec<SearchResult> = sqlx::query_as(self.search_query_full().as_str())
-            .bind(embedded_query)
-            .bind(n as i64)
-            .fetch_all(&self.pg_pool)
-            .await
-            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
-
-        let rows: Vec<(f64, String, T)> = rows
-            .into_iter()
-            .flat_map(SearchResult::into_result)
-            .collect();
-
-        Ok(rows)
+        let embedded_query = self.embed_query(query).await?;
+        self.search_page(&embedded_query, n, 0, None).await
     }
 
     /// Same as `top_n` but returns the document ids only.
@@ -182,28 +255,78 @@ impl<Model: EmbeddingModel> VectorStoreIndex for PostgresVectorStore<Model> {
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String)>, VectorStoreError> {
-        let embedded_query: pgvector::Vector = self
-            .model
-            .embed_text(query)
-            .await?
-            .vec
-            .iter()
-            .map(|&x| x as f32)
-            .collect::<Vec<f32>>()
-            .into();
+        let embedded_query = self.embed_query(query).await?;
 
         let rows: Vec<SearchResultOnlyId> = sqlx::query_as(self.search_query_only_ids().as_str())
             .bind(embedded_query)
             .bind(n as i64)
+            .bind(0i64)
             .fetch_all(&self.pg_pool)
             .await
             .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
 
         let rows: Vec<(f64, String)> = rows
             .into_iter()
-            .map(|row| (row.distance, row.id.to_string()))
+            .map(|row| {
+                (
+                    self.distance_function.normalize(row.distance),
+                    row.id.to_string(),
+                )
+            })
             .collect();
 
         Ok(rows)
     }
+
+    async fn top_n_with_offset<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        offset: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let embedded_query = self.embed_query(query).await?;
+        self.search_page(&embedded_query, n, offset, None).await
+    }
+
+    /// Embed the query once, then fetch the pages of documents lazily.
+    fn top_n_stream<'a, T: for<'b> Deserialize<'b> + Send + 'a>(
+        &'a self,
+        query: &'a str,
+        page_size: usize,
+    ) -> BoxStream<'a, Result<(f64, String, T), VectorStoreError>> {
+        let page_size = page_size.max(1);
+
+        stream::once(self.embed_query(query))
+            .map_ok(move |embedded_query| {
+                stream::try_unfold(Some(0), move |offset| {
+                    let embedded_query = embedded_query.clone();
+                    async move {
+                        let Some(offset) = offset else {
+                            return Ok::<_, VectorStoreError>(None);
+                        };
+
+                        let page = self
+                            .search_page::<T>(&embedded_query, page_size, offset, None)
+                            .await?;
+                        let next_offset = (page.len() == page_size).then_some(offset + page_size);
+
+                        Ok(Some((stream::iter(page.into_iter().map(Ok)), next_offset)))
+                    }
+                })
+                .try_flatten()
+            })
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Filter the documents in the query, on the field of their JSON document.
+    async fn top_n_filtered<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &MetadataFilter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let embedded_query = self.embed_query(query).await?;
+        self.search_page(&embedded_query, n, 0, Some(filter)).await
+    }
 }
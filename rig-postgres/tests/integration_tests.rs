@@ -1,4 +1,8 @@
-use rig::{embeddings::EmbeddingsBuilder, vector_store::VectorStoreIndex, Embed};
+use rig::{
+    embeddings::EmbeddingsBuilder,
+    vector_store::{MetadataFilter, VectorStoreIndex},
+    Embed,
+};
 use rig_postgres::PostgresVectorStore;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -127,6 +131,19 @@ async fn vector_search_test() {
     println!("Distance: {}, id: {}", distance, id);
 
     assert_eq!(id, full_query_id);
+
+    // search the documents passing a filter
+    let results = vector_store
+        .top_n_filtered::<Word>(
+            "What does \"glarb-glarb\" mean?",
+            2,
+            &MetadataFilter::eq("name", "flurbo"),
+        )
+        .await
+        .expect("Failed to search for filtered documents");
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].2.name, "flurbo");
 }
 
 async fn start_container() -> ContainerAsync<GenericImage> {
@@ -0,0 +1,84 @@
+use qdrant_client::{
+    qdrant::{CreateCollectionBuilder, Distance, VectorParamsBuilder},
+    Qdrant,
+};
+use rig::vector_store::{
+    collections::{CollectionConfig, CollectionManager, DistanceMetric},
+    VectorStoreError,
+};
+
+use crate::writer::datastore_error;
+
+/// [CollectionManager] managing the collections of a Qdrant server.
+pub struct QdrantCollections {
+    client: Qdrant,
+}
+
+impl QdrantCollections {
+    pub fn new(client: Qdrant) -> Self {
+        Self { client }
+    }
+
+    pub fn client(&self) -> &Qdrant {
+        &self.client
+    }
+}
+
+fn distance(distance: DistanceMetric) -> Distance {
+    match distance {
+        DistanceMetric::Cosine => Distance::Cosine,
+        DistanceMetric::DotProduct => Distance::Dot,
+        DistanceMetric::Euclidean => Distance::Euclid,
+    }
+}
+
+impl CollectionManager for QdrantCollections {
+    async fn create_collection(&self, config: CollectionConfig) -> Result<(), VectorStoreError> {
+        if self.collection_exists(&config.name).await? {
+            return Ok(());
+        }
+
+        self.client
+            .create_collection(CreateCollectionBuilder::new(&config.name).vectors_config(
+                VectorParamsBuilder::new(config.ndims as u64, distance(config.distance)),
+            ))
+            .await
+            .map_err(|err| datastore_error("creating collection", err))?;
+
+        Ok(())
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        let response = self
+            .client
+            .list_collections()
+            .await
+            .map_err(|err| datastore_error("listing collections", err))?;
+
+        Ok(response
+            .collections
+            .into_iter()
+            .map(|collection| collection.name)
+            .collect())
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        if !self.collection_exists(name).await? {
+            return Ok(());
+        }
+
+        self.client
+            .delete_collection(name)
+            .await
+            .map_err(|err| datastore_error("dropping collection", err))?;
+
+        Ok(())
+    }
+
+    async fn collection_exists(&self, name: &str) -> Result<bool, VectorStoreError> {
+        self.client
+            .collection_exists(name)
+            .await
+            .map_err(|err| datastore_error("checking collection", err))
+    }
+}
@@ -0,0 +1,244 @@
+use qdrant_client::{
+    qdrant::{
+        Fusion, NamedVectors, PointStruct, PrefetchQueryBuilder, Query, QueryPoints, ScoredPoint,
+        UpsertPointsBuilder, Vector, VectorInput,
+    },
+    Payload, Qdrant,
+};
+use rig::{
+    embeddings::{Embedding, EmbeddingModel, SparseEmbeddingModel},
+    vector_store::{VectorStoreError, VectorStoreIndex},
+    Embed, OneOrMany,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::stringify_id;
+
+/// Represents a vector store implementation using Qdrant - <https://qdrant.tech/> as the backend,
+/// combining dense and sparse embeddings (hybrid retrieval).
+///
+/// Points are stored with a named dense vector and a named sparse vector (`dense` and `sparse` by
+/// default) and queries are run against both, the results being merged with Reciprocal Rank Fusion.
+///
+/// The collection must be created with matching vector configurations, e.g.:
+/// ```rust,ignore
+/// client
+///     .create_collection(
+///         CreateCollectionBuilder::new(COLLECTION_NAME)
+///             .vectors_config(VectorsConfigBuilder::default().add_named_vector_params(
+///                 "dense",
+///                 VectorParamsBuilder::new(1536, Distance::Cosine),
+///             ))
+///             .sparse_vectors_config(SparseVectorsConfigBuilder::default().add_named_vector_params(
+///                 "sparse",
+///                 SparseVectorParamsBuilder::default(),
+///             )),
+///     )
+///     .await?;
+/// ```
+pub struct QdrantHybridVectorStore<M: EmbeddingModel, S: SparseEmbeddingModel> {
+    /// Model used to generate dense embeddings for the vector store
+    model: M,
+    /// Model used to generate sparse embeddings for the vector store
+    sparse_model: S,
+    /// Client instance for Qdrant server communication
+    client: Qdrant,
+    /// Default search parameters
+    query_params: QueryPoints,
+    dense_vector_name: String,
+    sparse_vector_name: String,
+    /// Number of candidates retrieved with each vector before fusion
+    prefetch_limit: Option<usize>,
+}
+
+impl<M: EmbeddingModel, S: SparseEmbeddingModel> QdrantHybridVectorStore<M, S> {
+    /// Creates a new instance of `QdrantHybridVectorStore`.
+    ///
+    /// # Arguments
+    /// * `client` - Qdrant client instance
+    /// * `model` - Dense embedding model instance
+    /// * `sparse_model` - Sparse embedding model instance
+    /// * `query_params` - Search parameters for vector queries (the `query` and `prefetch`
+    ///   parameters are replaced by the hybrid query)
+    ///   Reference: <https://api.qdrant.tech/v-1-12-x/api-reference/search/query-points>
+    pub fn new(client: Qdrant, model: M, sparse_model: S, query_params: QueryPoints) -> Self {
+        Self {
+            client,
+            model,
+            sparse_model,
+            query_params,
+            dense_vector_name: "dense".to_string(),
+            sparse_vector_name: "sparse".to_string(),
+            prefetch_limit: None,
+        }
+    }
+
+    /// Set the names of the dense and sparse vectors of the collection (default: `dense` and `sparse`).
+    pub fn vector_names(mut self, dense: &str, sparse: &str) -> Self {
+        self.dense_vector_name = dense.to_string();
+        self.sparse_vector_name = sparse.to_string();
+        self
+    }
+
+    /// Set the number of candidates retrieved with each vector before fusion (default: twice the
+    /// number of requested results).
+    pub fn prefetch_limit(mut self, prefetch_limit: usize) -> Self {
+        self.prefetch_limit = Some(prefetch_limit);
+        self
+    }
+
+    pub fn client(&self) -> &Qdrant {
+        &self.client
+    }
+
+    /// Build the hybrid query: nearest neighbors of both query vectors, merged with RRF.
+    async fn prepare_query_params(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<QueryPoints, VectorStoreError> {
+        let dense = self.model.embed_text(query).await?;
+        let sparse = self.sparse_model.embed_sparse_text(query).await?;
+
+        let prefetch_limit = self.prefetch_limit.unwrap_or(limit * 2) as u64;
+
+        let mut params = self.query_params.clone();
+        params.prefetch = vec![
+            PrefetchQueryBuilder::default()
+                .query(Query::new_nearest(VectorInput::new_dense(to_f32(
+                    dense.vec,
+                ))))
+                .using(&self.dense_vector_name)
+                .limit(prefetch_limit)
+                .build(),
+            PrefetchQueryBuilder::default()
+                .query(Query::new_nearest(VectorInput::new_sparse(
+                    sparse.indices,
+                    to_f32(sparse.values),
+                )))
+                .using(&self.sparse_vector_name)
+                .limit(prefetch_limit)
+                .build(),
+        ];
+        params.query = Some(Query::new_fusion(Fusion::Rrf));
+        params.using = None;
+        params.limit = Some(limit as u64);
+        Ok(params)
+    }
+
+    async fn query(&self, query: &str, n: usize) -> Result<Vec<ScoredPoint>, VectorStoreError> {
+        let params = self.prepare_query_params(query, n).await?;
+
+        Ok(self
+            .client
+            .query(params)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?
+            .result)
+    }
+
+    /// Insert documents with their dense embeddings. The sparse embeddings are generated from
+    /// the embedded text of each dense embedding.
+    pub async fn insert_documents<Doc: Serialize + Embed + Send>(
+        &self,
+        documents: Vec<(Doc, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        let collection_name = self.query_params.collection_name.clone();
+
+        for (document, embeddings) in documents {
+            let json_document = serde_json::to_value(&document)?;
+            let doc_as_payload = Payload::try_from(json_document).map_err(|err| {
+                VectorStoreError::DatastoreError(format!("Invalid payload: {err}").into())
+            })?;
+
+            let embeddings = embeddings.into_iter().collect::<Vec<_>>();
+            let sparse_embeddings = self
+                .sparse_model
+                .embed_sparse_texts(
+                    embeddings
+                        .iter()
+                        .map(|embedding| embedding.document.clone())
+                        .collect::<Vec<_>>(),
+                )
+                .await?;
+
+            let points = embeddings
+                .into_iter()
+                .zip(sparse_embeddings)
+                .map(|(embedding, sparse)| {
+                    PointStruct::new(
+                        Uuid::new_v4().to_string(),
+                        NamedVectors::default()
+                            .add_vector(&self.dense_vector_name, to_f32(embedding.vec))
+                            .add_vector(
+                                &self.sparse_vector_name,
+                                Vector::new_sparse(sparse.indices, to_f32(sparse.values)),
+                            ),
+                        doc_as_payload.clone(),
+                    )
+                })
+                .collect::<Vec<PointStruct>>();
+
+            let request = UpsertPointsBuilder::new(&collection_name, points);
+            self.client.upsert_points(request).await.map_err(|err| {
+                VectorStoreError::DatastoreError(format!("Error while upserting: {err}").into())
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+fn to_f32(values: Vec<f64>) -> Vec<f32> {
+    values.into_iter().map(|x| x as f32).collect()
+}
+
+impl<M, S> VectorStoreIndex for QdrantHybridVectorStore<M, S>
+where
+    M: EmbeddingModel + Sync + Send,
+    S: SparseEmbeddingModel + Sync + Send,
+{
+    /// Search for the top `n` documents matching the given query, by fusion of the dense and
+    /// sparse nearest neighbors. Returns a vector of tuples containing the (RRF) score, ID,
+    /// and payload of the results.
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.query(query, n)
+            .await?
+            .into_iter()
+            .map(|item| {
+                let id =
+                    stringify_id(item.id.ok_or_else(|| {
+                        VectorStoreError::DatastoreError("Missing point ID".into())
+                    })?)?;
+                let payload = serde_json::from_value(serde_json::to_value(item.payload)?)?;
+                Ok((item.score as f64, id, payload))
+            })
+            .collect()
+    }
+
+    /// Search for the top `n` documents matching the given query, by fusion of the dense and
+    /// sparse nearest neighbors. Returns a vector of tuples containing the (RRF) score and ID
+    /// of the results.
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.query(query, n)
+            .await?
+            .into_iter()
+            .map(|point| {
+                let id =
+                    stringify_id(point.id.ok_or_else(|| {
+                        VectorStoreError::DatastoreError("Missing point ID".into())
+                    })?)?;
+                Ok((point.score as f64, id))
+            })
+            .collect()
+    }
+}
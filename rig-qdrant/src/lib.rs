@@ -1,17 +1,34 @@
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
 use qdrant_client::{
     qdrant::{
-        point_id::PointIdOptions, PointId, PointStruct, Query, QueryPoints, UpsertPointsBuilder,
+        point_id::PointIdOptions, r#match::MatchValue, Condition, PointId, PointStruct, Query,
+        QueryPoints, UpsertPointsBuilder,
     },
     Payload, Qdrant,
 };
 use rig::{
     embeddings::{Embedding, EmbeddingModel},
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    vector_store::{
+        top_n_filtered_from_stream, DistanceMetric, MetadataFilter, VectorStoreError,
+        VectorStoreIndex,
+    },
     Embed, OneOrMany,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 
+pub mod hybrid;
+pub use hybrid::QdrantHybridVectorStore;
+
+pub mod collections;
+pub use collections::QdrantCollections;
+
+pub mod writer;
+
 /// Represents a vector store implementation using Qdrant - <https://qdrant.tech/> as the backend.
 pub struct QdrantVectorStore<M: EmbeddingModel> {
     /// Model used to generate embeddings for the vector store
@@ -20,6 +37,8 @@ pub struct QdrantVectorStore<M: EmbeddingModel> {
     client: Qdrant,
     /// Default search parameters
     query_params: QueryPoints,
+    /// Distance metric of the collection, used to normalize the scores
+    distance: DistanceMetric,
 }
 
 impl<M: EmbeddingModel> QdrantVectorStore<M> {
@@ -35,9 +54,17 @@ impl<M: EmbeddingModel> QdrantVectorStore<M> {
             client,
             model,
             query_params,
+            distance: DistanceMetric::default(),
         }
     }
 
+    /// Set the distance metric of the collection (default: [DistanceMetric::Cosine]), used to
+    /// normalize the scores of the points between 0 and 1.
+    pub fn distance(mut self, distance: DistanceMetric) -> Self {
+        self.distance = distance;
+        self
+    }
+
     pub fn client(&self) -> &Qdrant {
         &self.client
     }
@@ -56,6 +83,54 @@ impl<M: EmbeddingModel> QdrantVectorStore<M> {
         params
     }
 
+    /// Get the query of the search parameters, or the nearest points to the embedded query.
+    async fn query(&self, query: &str) -> Result<Option<Query>, VectorStoreError> {
+        Ok(match self.query_params.query {
+            Some(ref q) => Some(q.clone()),
+            None => Some(Query::new_nearest(self.generate_query_vector(query).await?)),
+        })
+    }
+
+    /// Query the `limit` points (passing `condition`, in addition to the filter of the search
+    /// parameters) ranked after the `offset` best ones, as `(score, id, document)`.
+    async fn query_page<T: for<'a> Deserialize<'a>>(
+        &self,
+        query: Option<Query>,
+        limit: usize,
+        offset: usize,
+        condition: Option<Condition>,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let mut params = self.prepare_query_params(query, limit);
+        params.offset = Some(offset as u64);
+        if let Some(condition) = condition {
+            params
+                .filter
+                .get_or_insert_with(Default::default)
+                .must
+                .push(condition);
+        }
+
+        let result = self
+            .client
+            .query(params)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+
+        result
+            .result
+            .into_iter()
+            .map(|item| {
+                let id =
+                    stringify_id(item.id.ok_or_else(|| {
+                        VectorStoreError::DatastoreError("Missing point ID".into())
+                    })?)?;
+                let score = self.distance.normalize(item.score as f64);
+                let payload = serde_json::from_value(serde_json::to_value(item.payload)?)?;
+                Ok((score, id, payload))
+            })
+            .collect()
+    }
+
     pub async fn insert_documents<Doc: Serialize + Embed + Send>(
         &self,
         documents: Vec<(Doc, OneOrMany<Embedding>)>,
@@ -89,6 +164,18 @@ impl<M: EmbeddingModel> QdrantVectorStore<M> {
     }
 }
 
+/// Translate `filter` to a condition on the payload of the points, if Qdrant can match its
+/// value (i.e.: a string, an integer or a boolean).
+fn payload_condition(filter: &MetadataFilter) -> Option<Condition> {
+    let value: MatchValue = match &filter.value {
+        Value::String(value) => value.clone().into(),
+        Value::Bool(value) => (*value).into(),
+        Value::Number(value) => value.as_i64()?.into(),
+        _ => return None,
+    };
+    Some(Condition::matches(filter.path().join("."), value))
+}
+
 /// Converts a `PointId` to its string representation.
 fn stringify_id(id: PointId) -> Result<String, VectorStoreError> {
     match id.point_id_options {
@@ -108,31 +195,8 @@ impl<M: EmbeddingModel + std::marker::Sync + Send> VectorStoreIndex for QdrantVe
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let query = match self.query_params.query {
-            Some(ref q) => Some(q.clone()),
-            None => Some(Query::new_nearest(self.generate_query_vector(query).await?)),
-        };
-
-        let params = self.prepare_query_params(query, n);
-        let result = self
-            .client
-            .query(params)
-            .await
-            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
-
-        result
-            .result
-            .into_iter()
-            .map(|item| {
-                let id =
-                    stringify_id(item.id.ok_or_else(|| {
-                        VectorStoreError::DatastoreError("Missing point ID".into())
-                    })?)?;
-                let score = item.score as f64;
-                let payload = serde_json::from_value(serde_json::to_value(item.payload)?)?;
-                Ok((score, id, payload))
-            })
-            .collect()
+        let query = self.query(query).await?;
+        self.query_page(query, n, 0, None).await
     }
 
     /// Search for the top `n` nearest neighbors to the given query within the Qdrant vector store.
@@ -142,10 +206,7 @@ impl<M: EmbeddingModel + std::marker::Sync + Send> VectorStoreIndex for QdrantVe
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String)>, VectorStoreError> {
-        let query = match self.query_params.query {
-            Some(ref q) => Some(q.clone()),
-            None => Some(Query::new_nearest(self.generate_query_vector(query).await?)),
-        };
+        let query = self.query(query).await?;
 
         let params = self.prepare_query_params(query, n);
         let points = self
@@ -162,8 +223,64 @@ impl<M: EmbeddingModel + std::marker::Sync + Send> VectorStoreIndex for QdrantVe
                     stringify_id(point.id.ok_or_else(|| {
                         VectorStoreError::DatastoreError("Missing point ID".into())
                     })?)?;
-                Ok((point.score as f64, id))
+                Ok((self.distance.normalize(point.score as f64), id))
             })
             .collect()
     }
+
+    async fn top_n_with_offset<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        offset: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let query = self.query(query).await?;
+        self.query_page(query, n, offset, None).await
+    }
+
+    /// Embed the query once, then fetch the pages of points lazily.
+    fn top_n_stream<'a, T: for<'b> Deserialize<'b> + Send + 'a>(
+        &'a self,
+        query: &'a str,
+        page_size: usize,
+    ) -> BoxStream<'a, Result<(f64, String, T), VectorStoreError>> {
+        let page_size = page_size.max(1);
+
+        stream::once(self.query(query))
+            .map_ok(move |query| {
+                stream::try_unfold(Some(0), move |offset| {
+                    let query = query.clone();
+                    async move {
+                        let Some(offset) = offset else {
+                            return Ok::<_, VectorStoreError>(None);
+                        };
+
+                        let page = self.query_page::<T>(query, page_size, offset, None).await?;
+                        let next_offset = (page.len() == page_size).then_some(offset + page_size);
+
+                        Ok(Some((stream::iter(page.into_iter().map(Ok)), next_offset)))
+                    }
+                })
+                .try_flatten()
+            })
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Filter the points in the query with a condition on their payload (or filter the points
+    /// of [VectorStoreIndex::top_n_stream] for the values which Qdrant cannot match).
+    async fn top_n_filtered<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &MetadataFilter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        match payload_condition(filter) {
+            Some(condition) => {
+                let query = self.query(query).await?;
+                self.query_page(query, n, 0, Some(condition)).await
+            }
+            None => top_n_filtered_from_stream(self, query, n, filter).await,
+        }
+    }
 }
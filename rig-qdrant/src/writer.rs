@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+
+use qdrant_client::{
+    qdrant::{
+        Condition, DeletePointsBuilder, Filter, PointStruct, ScrollPointsBuilder,
+        UpsertPointsBuilder,
+    },
+    Payload,
+};
+use rig::{
+    completion::Document,
+    embeddings::{Embedding, EmbeddingModel},
+    ingest::{IngestSink, StoredDocuments},
+    vector_store::{VectorStoreError, VectorStoreWriter},
+    OneOrMany,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::QdrantVectorStore;
+
+/// Payload field storing the id of the document of the points written with [VectorStoreWriter].
+///
+/// Since a document can have multiple embeddings (i.e.: multiple points), the points have random
+/// ids and are looked up by this field instead.
+pub const DOCUMENT_ID: &str = "document_id";
+
+pub(crate) fn datastore_error(context: &str, err: impl std::fmt::Display) -> VectorStoreError {
+    VectorStoreError::DatastoreError(format!("Error while {context}: {err}").into())
+}
+
+fn document_filter(id: &str) -> Filter {
+    Filter::must([Condition::matches(DOCUMENT_ID, id.to_string())])
+}
+
+/// Convert the payload of a point back to its document id and document.
+fn split_payload(
+    payload: HashMap<String, qdrant_client::qdrant::Value>,
+) -> (Option<String>, serde_json::Value) {
+    let mut document = serde_json::Value::from(Payload::from(payload));
+    let id = document
+        .as_object_mut()
+        .and_then(|object| object.remove(DOCUMENT_ID))
+        .and_then(|id| id.as_str().map(String::from));
+    (id, document)
+}
+
+impl<M: EmbeddingModel, D: Serialize + Send + Sync> VectorStoreWriter<D> for QdrantVectorStore<M> {
+    /// Insert the documents, as one point per embedding with the document as payload. The existing
+    /// points of the documents are deleted first (non atomically).
+    async fn upsert(
+        &mut self,
+        documents: Vec<(String, D, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        let collection_name = self.query_params.collection_name.clone();
+
+        for (id, document, embeddings) in documents {
+            let mut payload = match serde_json::to_value(&document)? {
+                serde_json::Value::Object(object) => object,
+                _ => {
+                    return Err(VectorStoreError::DatastoreError(
+                        "Documents must serialize to JSON objects".into(),
+                    ))
+                }
+            };
+            payload.insert(DOCUMENT_ID.to_string(), id.clone().into());
+            let payload = Payload::from(payload);
+
+            <Self as VectorStoreWriter<D>>::delete_by_id(self, &id).await?;
+
+            let points = embeddings
+                .into_iter()
+                .map(|embedding| {
+                    let vector: Vec<f32> = embedding.vec.into_iter().map(|x| x as f32).collect();
+                    PointStruct::new(Uuid::new_v4().to_string(), vector, payload.clone())
+                })
+                .collect::<Vec<_>>();
+
+            self.client
+                .upsert_points(UpsertPointsBuilder::new(&collection_name, points).wait(true))
+                .await
+                .map_err(|err| datastore_error("upserting", err))?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_by_id(&mut self, id: &str) -> Result<(), VectorStoreError> {
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(&self.query_params.collection_name)
+                    .points(document_filter(id))
+                    .wait(true),
+            )
+            .await
+            .map_err(|err| datastore_error("deleting", err))?;
+
+        Ok(())
+    }
+
+    async fn get_by_id<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        id: &str,
+    ) -> Result<Option<T>, VectorStoreError> {
+        let response = self
+            .client
+            .scroll(
+                ScrollPointsBuilder::new(&self.query_params.collection_name)
+                    .filter(document_filter(id))
+                    .limit(1)
+                    .with_payload(true),
+            )
+            .await
+            .map_err(|err| datastore_error("getting document", err))?;
+
+        response
+            .result
+            .into_iter()
+            .next()
+            .map(|point| Ok(serde_json::from_value(split_payload(point.payload).1)?))
+            .transpose()
+    }
+}
+
+impl<M: EmbeddingModel> IngestSink for QdrantVectorStore<M> {
+    async fn stored_documents(&self) -> Result<StoredDocuments, VectorStoreError> {
+        let mut documents = StoredDocuments::new();
+        let mut seen = HashSet::new();
+        let mut offset = None;
+
+        loop {
+            let mut request = ScrollPointsBuilder::new(&self.query_params.collection_name)
+                .filter(Filter::must_not([Condition::is_empty(DOCUMENT_ID)]))
+                .limit(256)
+                .with_payload(true);
+            if let Some(offset) = offset.take() {
+                request = request.offset(offset);
+            }
+
+            let response = self
+                .client
+                .scroll(request)
+                .await
+                .map_err(|err| datastore_error("listing documents", err))?;
+
+            for point in response.result {
+                // Documents with multiple embeddings are stored as multiple points
+                let (Some(id), document) = split_payload(point.payload) else {
+                    continue;
+                };
+                if !seen.insert(id.clone()) {
+                    continue;
+                }
+
+                let document: Document = serde_json::from_value(document)?;
+                documents.push((id, document.additional_props));
+            }
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => return Ok(documents),
+            }
+        }
+    }
+}
@@ -13,7 +13,10 @@ use qdrant_client::{
     Payload, Qdrant,
 };
 use rig::{
-    embeddings::EmbeddingsBuilder, providers::openai, vector_store::VectorStoreIndex, Embed,
+    embeddings::EmbeddingsBuilder,
+    providers::openai,
+    vector_store::{MetadataFilter, VectorStoreIndex},
+    Embed,
 };
 use rig_qdrant::QdrantVectorStore;
 
@@ -167,7 +170,16 @@ async fn vector_search_test() {
             "definition": "Definition of a *linglingdong*: A term used by inhabitants of the far side of the moon to describe humans.",
             "id": "f9e17d59-32e5-440c-be02-b2759a654824"
         })
-    )
+    );
+
+    let filter = MetadataFilter::eq("id", "0981d983-a5f8-49eb-89ea-f7d3b2196d2e");
+    let results = vector_store
+        .top_n_filtered::<Word>("What is a linglingdong?", 2, &filter)
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].2.id, "0981d983-a5f8-49eb-89ea-f7d3b2196d2e");
 }
 
 async fn create_points(model: openai::EmbeddingModel) -> Vec<PointStruct> {
@@ -0,0 +1,548 @@
+//! An OpenAI-compatible HTTP server for Rig agents.
+//!
+//! The server exposes its agents as models of the OpenAI chat completions API
+//! (`POST /v1/chat/completions`, with or without `"stream": true`, and `GET /v1/models`), so
+//! that existing OpenAI clients and chat front-ends (e.g.: LibreChat, Open WebUI) can talk to
+//! them directly: the `model` of a request is the name of the agent which answers it.
+//!
+//! The last user message of a request is the prompt of the agent, and the previous user and
+//! assistant messages its chat history. The system messages of the requests are ignored: the
+//! agents have their own preamble.
+//!
+//! The tool calls of the agents are not streamed: the streaming requests to agents with tools
+//! are answered with a single chunk, once the tool calls are run.
+//!
+//! # Example
+//! ```rust,no_run
+//! use rig::providers::openai;
+//! use rig_server::RigServer;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), anyhow::Error> {
+//!     let openai = openai::Client::from_env();
+//!     let comedian = openai
+//!         .agent(openai::GPT_4O)
+//!         .preamble("You are a comedian here to entertain the user using humour and jokes.")
+//!         .build();
+//!
+//!     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
+//!     RigServer::new()
+//!         .agent("comedian", comedian)
+//!         .api_key("secret")
+//!         .serve(listener)
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+
+pub mod protocol;
+
+use std::{
+    collections::HashMap,
+    future::IntoFuture,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{future::BoxFuture, stream, stream::BoxStream, StreamExt};
+use rig::{
+    agent::Agent,
+    completion::{AssistantContent, Message, Prompt, PromptError},
+    streaming::{StreamingChat, StreamingCompletionModel},
+};
+use subtle::ConstantTimeEq;
+
+use protocol::*;
+
+/// Object-safe interface of the agents served by a [RigServer], implemented by the agents of
+/// all the streaming completion models.
+pub trait ServedAgent: Send + Sync {
+    /// Answer `prompt`, running up to `max_depth` rounds of tool calls.
+    fn chat(
+        &self,
+        prompt: Message,
+        chat_history: Vec<Message>,
+        max_depth: usize,
+    ) -> BoxFuture<'_, Result<String, PromptError>>;
+
+    /// Stream the answer to `prompt`, as text chunks, running up to `max_depth` rounds of tool
+    /// calls.
+    fn stream_chat(
+        &self,
+        prompt: Message,
+        chat_history: Vec<Message>,
+        max_depth: usize,
+    ) -> BoxFuture<'_, Result<BoxStream<'static, Result<String, PromptError>>, PromptError>>;
+}
+
+impl<M> ServedAgent for Agent<M>
+where
+    M: StreamingCompletionModel + 'static,
+    M::StreamingResponse: Send,
+{
+    fn chat(
+        &self,
+        prompt: Message,
+        mut chat_history: Vec<Message>,
+        max_depth: usize,
+    ) -> BoxFuture<'_, Result<String, PromptError>> {
+        Box::pin(async move {
+            self.prompt(prompt)
+                .with_history(&mut chat_history)
+                .multi_turn(max_depth)
+                .into_future()
+                .await
+        })
+    }
+
+    fn stream_chat(
+        &self,
+        prompt: Message,
+        chat_history: Vec<Message>,
+        max_depth: usize,
+    ) -> BoxFuture<'_, Result<BoxStream<'static, Result<String, PromptError>>, PromptError>> {
+        Box::pin(async move {
+            // The streaming completions do not run the tool calls: the agents with tools answer
+            // with the response of a multi-turn prompt instead
+            if !self.static_tools.is_empty() || !self.dynamic_tools.is_empty() {
+                let content = ServedAgent::chat(self, prompt, chat_history, max_depth).await?;
+                return Ok(stream::once(async { Ok(content) }).boxed());
+            }
+
+            let stream = StreamingChat::stream_chat(self, prompt, chat_history).await?;
+            Ok(stream
+                .filter_map(|chunk| async move {
+                    match chunk {
+                        Ok(AssistantContent::Text(text)) => Some(Ok(text.text)),
+                        // The agent has no tools to call
+                        Ok(AssistantContent::ToolCall(_)) => None,
+                        Err(err) => Some(Err(err.into())),
+                    }
+                })
+                .boxed())
+        })
+    }
+}
+
+/// An OpenAI-compatible server of named agents.
+#[derive(Default)]
+pub struct RigServer {
+    agents: HashMap<String, Arc<dyn ServedAgent>>,
+    api_key: Option<String>,
+    max_depth: usize,
+}
+
+struct ServerState {
+    agents: HashMap<String, Arc<dyn ServedAgent>>,
+    api_key: Option<String>,
+    max_depth: usize,
+}
+
+impl RigServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `agent` as the model `name`.
+    pub fn agent(mut self, name: &str, agent: impl ServedAgent + 'static) -> Self {
+        self.agents.insert(name.to_string(), Arc::new(agent));
+        self
+    }
+
+    /// Require the requests to be authenticated with `Authorization: Bearer <api_key>`.
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Set the maximum number of rounds of tool calls of the agents (0 by default).
+    pub fn multi_turn(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// The router of the server, e.g.: to nest it in an existing axum application.
+    pub fn router(self) -> Router {
+        let state = Arc::new(ServerState {
+            agents: self.agents,
+            api_key: self.api_key,
+            max_depth: self.max_depth,
+        });
+
+        Router::new()
+            .route("/v1/chat/completions", post(chat_completions))
+            .route("/v1/models", get(models))
+            .with_state(state)
+    }
+
+    /// Serve the agents on `listener` until the server fails.
+    pub async fn serve(self, listener: tokio::net::TcpListener) -> std::io::Result<()> {
+        axum::serve(listener, self.router()).await
+    }
+}
+
+fn error(status: StatusCode, kind: &'static str, message: impl Into<String>) -> Response {
+    let body = ErrorResponse {
+        error: ErrorBody {
+            message: message.into(),
+            kind,
+        },
+    };
+    (status, Json(body)).into_response()
+}
+
+/// Whether the request is authenticated with the API key of the server (if any).
+fn authorized(state: &ServerState, headers: &HeaderMap) -> bool {
+    let Some(api_key) = &state.api_key else {
+        return true;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| bool::from(token.as_bytes().ct_eq(api_key.as_bytes())))
+}
+
+fn unauthorized() -> Response {
+    error(
+        StatusCode::UNAUTHORIZED,
+        "invalid_request_error",
+        "Invalid API key",
+    )
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+fn completion_id(created: u64) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "chatcmpl-{:x}{:06x}",
+        created,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+async fn models(State(state): State<Arc<ServerState>>, headers: HeaderMap) -> Response {
+    if !authorized(&state, &headers) {
+        return unauthorized();
+    }
+
+    let mut data = state
+        .agents
+        .keys()
+        .map(|name| Model {
+            id: name.clone(),
+            object: "model",
+            created: 0,
+            owned_by: "rig",
+        })
+        .collect::<Vec<_>>();
+    data.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Json(ModelList {
+        object: "list",
+        data,
+    })
+    .into_response()
+}
+
+async fn chat_completions(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Response {
+    if !authorized(&state, &headers) {
+        return unauthorized();
+    }
+
+    let Some(agent) = state.agents.get(&request.model).cloned() else {
+        return error(
+            StatusCode::NOT_FOUND,
+            "invalid_request_error",
+            format!("The model `{}` does not exist", request.model),
+        );
+    };
+    let model = request.model.clone();
+    let streaming = request.stream;
+    let Some((prompt, chat_history)) = request.into_prompt() else {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            "The last message must be a user message",
+        );
+    };
+
+    let created = now();
+    let id = completion_id(created);
+
+    if !streaming {
+        return match agent.chat(prompt, chat_history, state.max_depth).await {
+            Ok(content) => Json(ChatCompletion {
+                id,
+                object: "chat.completion",
+                created,
+                model,
+                choices: vec![Choice {
+                    index: 0,
+                    message: AssistantMessage {
+                        role: Role::Assistant,
+                        content,
+                    },
+                    finish_reason: "stop",
+                }],
+                usage: Usage::default(),
+            })
+            .into_response(),
+            Err(err) => {
+                tracing::error!("Agent `{}` failed: {}", model, err);
+                error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "server_error",
+                    err.to_string(),
+                )
+            }
+        };
+    }
+
+    let chunks = match agent
+        .stream_chat(prompt, chat_history, state.max_depth)
+        .await
+    {
+        Ok(chunks) => chunks,
+        Err(err) => {
+            tracing::error!("Agent `{}` failed: {}", model, err);
+            return error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "server_error",
+                err.to_string(),
+            );
+        }
+    };
+
+    let chunk = move |delta: Delta, finish_reason| {
+        Event::default().json_data(ChatCompletionChunk {
+            id: id.clone(),
+            object: "chat.completion.chunk",
+            created,
+            model: model.clone(),
+            choices: vec![ChunkChoice {
+                index: 0,
+                delta,
+                finish_reason,
+            }],
+        })
+    };
+
+    let role = chunk(
+        Delta {
+            role: Some(Role::Assistant),
+            content: Some(String::new()),
+        },
+        None,
+    );
+    let stop = chunk(Delta::default(), Some("stop"));
+    let content = chunks.map(move |text| match text {
+        Ok(text) => chunk(
+            Delta {
+                role: None,
+                content: Some(text),
+            },
+            None,
+        ),
+        Err(err) => Event::default().json_data(ErrorResponse {
+            error: ErrorBody {
+                message: err.to_string(),
+                kind: "server_error",
+            },
+        }),
+    });
+
+    let events = stream::once(async { role })
+        .chain(content)
+        .chain(stream::iter([stop, Ok(Event::default().data("[DONE]"))]));
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use rig::{
+        agent::AgentBuilder,
+        completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse},
+        message::UserContent,
+        streaming::{RawStreamingChoice, StreamingCompletionResponse},
+        testing::ScriptedTool,
+        OneOrMany,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Answers with the number of messages of the request, in two chunks when streaming. Calls
+    /// the tools of the request first, if any.
+    #[derive(Clone)]
+    struct CountingModel;
+
+    impl CompletionModel for CountingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let tool_result = matches!(
+                request.chat_history.iter().last(),
+                Some(Message::User { content })
+                    if matches!(content.first(), UserContent::ToolResult(_))
+            );
+            let choice = match request.tools.first() {
+                Some(tool) if !tool_result => {
+                    AssistantContent::tool_call("call_1", &tool.name, serde_json::json!({}))
+                }
+                _ => AssistantContent::text(format!("{} messages", request.chat_history.len())),
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                raw_response: (),
+                logprobs: None,
+            })
+        }
+    }
+
+    impl StreamingCompletionModel for CountingModel {
+        type StreamingResponse = ();
+
+        async fn stream(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<StreamingCompletionResponse<()>, CompletionError> {
+            let chunks = [
+                format!("{} ", request.chat_history.len()),
+                "messages".to_string(),
+            ];
+            Ok(StreamingCompletionResponse::new(Box::pin(stream::iter(
+                chunks.map(|chunk| Ok(RawStreamingChoice::Message(chunk))),
+            ))))
+        }
+    }
+
+    fn router() -> Router {
+        RigServer::new()
+            .agent("counter", AgentBuilder::new(CountingModel).build())
+            .agent(
+                "tools",
+                AgentBuilder::new(CountingModel)
+                    .scripted_tool(ScriptedTool::new("lookup").returns("found"))
+                    .build(),
+            )
+            .api_key("secret")
+            .router()
+    }
+
+    async fn send(body: serde_json::Value, api_key: &str) -> (StatusCode, String) {
+        let request = Request::post("/v1/chat/completions")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {api_key}"))
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_chat_completions() {
+        let messages = serde_json::json!([
+            {"role": "system", "content": "Ignored"},
+            {"role": "user", "content": "Hi"},
+            {"role": "assistant", "content": "Hello"},
+            {"role": "user", "content": [{"type": "text", "text": "How are you?"}]},
+        ]);
+
+        let (status, body) = send(
+            serde_json::json!({"model": "counter", "messages": messages}),
+            "secret",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let completion: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(completion["object"], "chat.completion");
+        assert_eq!(completion["choices"][0]["message"]["content"], "3 messages");
+
+        let (status, body) = send(
+            serde_json::json!({"model": "counter", "messages": messages, "stream": true}),
+            "secret",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let events = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 5);
+        let content = events[1..3]
+            .iter()
+            .map(|event| {
+                let chunk: serde_json::Value = serde_json::from_str(event).unwrap();
+                chunk["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<String>();
+        assert_eq!(content, "3 messages");
+        assert!(events[3].contains(r#""finish_reason":"stop""#));
+        assert_eq!(events[4], "[DONE]");
+
+        // The tool calls are run, and the answer streamed as a single chunk
+        let (status, body) = send(
+            serde_json::json!({"model": "tools", "messages": messages, "stream": true}),
+            "secret",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let events = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 4);
+        assert!(events[1].contains(r#""content":"5 messages""#));
+
+        let (status, _) = send(
+            serde_json::json!({"model": "other", "messages": messages}),
+            "secret",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(
+            serde_json::json!({"model": "counter", "messages": messages}),
+            "wrong",
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}